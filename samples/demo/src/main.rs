@@ -5,7 +5,7 @@ use wag::{
     handle_err,
     gui::{
         spawn_window_event_receiver, Background, BackgroundParams, Button, ButtonEvent,
-        ButtonParams, CellLimit, LayerStack, LayerStackParams, Ribbon,
+        ButtonParams, CellLimit, CornerRadius, LayerStack, LayerStackParams, Ribbon,
         RibbonOrientation, RibbonParams, SimpleButtonSkin, SimpleButtonSkinParams,
     },
     window::{
@@ -56,19 +56,19 @@ fn main() -> wag::Result<()> {
     let red_surface = BackgroundParams::builder()
         .compositor(compositor.clone())
         .color(Colors::Red()?)
-        .round_corners(true)
+        .corner_radius(CornerRadius::relative(0.05))
         .build()
         .try_into()?;
     let green_surface = BackgroundParams::builder()
         .compositor(compositor.clone())
         .color(Colors::Green()?)
-        .round_corners(true)
+        .corner_radius(CornerRadius::relative(0.05))
         .build()
         .try_into()?;
     let blue_surface = BackgroundParams::builder()
         .compositor(compositor.clone())
         .color(Colors::Blue()?)
-        .round_corners(true)
+        .corner_radius(CornerRadius::relative(0.05))
         .build()
         .try_into()?;
 
@@ -0,0 +1,361 @@
+//!
+//! Helpers for `windows::UI::Color`: parsing, HSL/HSV conversions and simple color arithmetic.
+//! The `Color` type is re-exported, so the crates using [`color!`](crate::color!) don't depend
+//! on `windows` themselves.
+//!
+pub use windows::UI::Color;
+
+///
+/// Parses color in the form "#RGB", "#RGBA", "#RRGGBB" or "#RRGGBBAA". Leading '#' is optional.
+///
+pub fn from_hex(s: &str) -> crate::Result<Color> {
+    let bad_format = || crate::Error::BadColorFormat(s.to_owned());
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    // `from_str_radix` alone would accept the sign, e.g. "#+f0000"
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(bad_format());
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).map_err(|_| bad_format());
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| bad_format());
+    let (r, g, b, a) = match hex.len() {
+        3 => (digit(0)? * 17, digit(1)? * 17, digit(2)? * 17, 255),
+        4 => (
+            digit(0)? * 17,
+            digit(1)? * 17,
+            digit(2)? * 17,
+            digit(3)? * 17,
+        ),
+        6 => (byte(0)?, byte(2)?, byte(4)?, 255),
+        8 => (byte(0)?, byte(2)?, byte(4)?, byte(6)?),
+        _ => return Err(bad_format()),
+    };
+    Ok(Color {
+        A: a,
+        R: r,
+        G: g,
+        B: b,
+    })
+}
+
+///
+/// Same as [`from_hex`] usable in constants, panics on the bad format. So the bad color in
+/// the constant or in the [`color!`](crate::color!) macro fails the compilation.
+///
+pub const fn from_hex_const(s: &str) -> Color {
+    const fn digit(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => panic!("bad hex digit in the color"),
+        }
+    }
+    const fn byte(hex: &[u8], i: usize) -> u8 {
+        digit(hex[i]) * 16 + digit(hex[i + 1])
+    }
+    let bytes = s.as_bytes();
+    let hex = match bytes {
+        [b'#', hex @ ..] => hex,
+        hex => hex,
+    };
+    match hex.len() {
+        3 => from_argb_parts(
+            255,
+            digit(hex[0]) * 17,
+            digit(hex[1]) * 17,
+            digit(hex[2]) * 17,
+        ),
+        4 => from_argb_parts(
+            digit(hex[3]) * 17,
+            digit(hex[0]) * 17,
+            digit(hex[1]) * 17,
+            digit(hex[2]) * 17,
+        ),
+        6 => from_argb_parts(255, byte(hex, 0), byte(hex, 2), byte(hex, 4)),
+        8 => from_argb_parts(byte(hex, 6), byte(hex, 0), byte(hex, 2), byte(hex, 4)),
+        _ => panic!("color must be #RGB, #RGBA, #RRGGBB or #RRGGBBAA"),
+    }
+}
+
+const fn from_argb_parts(a: u8, r: u8, g: u8, b: u8) -> Color {
+    Color {
+        A: a,
+        R: r,
+        G: g,
+        B: b,
+    }
+}
+
+///
+/// Color from the string literal "#RGB", "#RGBA", "#RRGGBB" or "#RRGGBBAA" checked at compile
+/// time. Usable in constants, e.g. the palettes of the application's themes.
+///
+/// ```ignore
+/// const ACCENT: Color = color!("#0078d4");
+/// ```
+///
+#[macro_export]
+macro_rules! color {
+    ($hex:literal) => {{
+        const COLOR: $crate::color::Color = $crate::color::from_hex_const($hex);
+        COLOR
+    }};
+}
+
+/// Color from 0xAARRGGBB value
+pub const fn from_argb(argb: u32) -> Color {
+    Color {
+        A: (argb >> 24) as u8,
+        R: (argb >> 16) as u8,
+        G: (argb >> 8) as u8,
+        B: argb as u8,
+    }
+}
+
+/// Opaque color from 0xRRGGBB value
+pub const fn from_rgb(rgb: u32) -> Color {
+    from_argb(0xff000000 | rgb)
+}
+
+/// Hue in degrees 0..360, saturation, lightness and alpha in 0..1
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Hsl {
+    pub h: f32,
+    pub s: f32,
+    pub l: f32,
+    pub a: f32,
+}
+
+/// Hue in degrees 0..360, saturation, value and alpha in 0..1
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Hsv {
+    pub h: f32,
+    pub s: f32,
+    pub v: f32,
+    pub a: f32,
+}
+
+fn to_unit(v: u8) -> f32 {
+    v as f32 / 255.
+}
+
+fn from_unit(v: f32) -> u8 {
+    (v.clamp(0., 1.) * 255.).round() as u8
+}
+
+// Hue and chroma parameters common for HSL and HSV
+fn hue_min_max(color: &Color) -> (f32, f32, f32) {
+    let (r, g, b) = (to_unit(color.R), to_unit(color.G), to_unit(color.B));
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let d = max - min;
+    let h = if d == 0. {
+        0.
+    } else if max == r {
+        60. * ((g - b) / d).rem_euclid(6.)
+    } else if max == g {
+        60. * ((b - r) / d + 2.)
+    } else {
+        60. * ((r - g) / d + 4.)
+    };
+    (h, min, max)
+}
+
+fn from_hue_chroma(h: f32, c: f32, m: f32, a: f32) -> Color {
+    let h = h.rem_euclid(360.) / 60.;
+    let x = c * (1. - (h % 2. - 1.).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.),
+        1 => (x, c, 0.),
+        2 => (0., c, x),
+        3 => (0., x, c),
+        4 => (x, 0., c),
+        _ => (c, 0., x),
+    };
+    Color {
+        A: from_unit(a),
+        R: from_unit(r + m),
+        G: from_unit(g + m),
+        B: from_unit(b + m),
+    }
+}
+
+impl From<Color> for Hsl {
+    fn from(color: Color) -> Self {
+        let (h, min, max) = hue_min_max(&color);
+        let l = (max + min) / 2.;
+        let s = if max == min {
+            0.
+        } else {
+            (max - min) / (1. - (2. * l - 1.).abs())
+        };
+        Hsl {
+            h,
+            s,
+            l,
+            a: to_unit(color.A),
+        }
+    }
+}
+
+impl From<Hsl> for Color {
+    fn from(hsl: Hsl) -> Self {
+        let c = (1. - (2. * hsl.l - 1.).abs()) * hsl.s;
+        from_hue_chroma(hsl.h, c, hsl.l - c / 2., hsl.a)
+    }
+}
+
+impl From<Color> for Hsv {
+    fn from(color: Color) -> Self {
+        let (h, min, max) = hue_min_max(&color);
+        let s = if max == 0. { 0. } else { (max - min) / max };
+        Hsv {
+            h,
+            s,
+            v: max,
+            a: to_unit(color.A),
+        }
+    }
+}
+
+impl From<Hsv> for Color {
+    fn from(hsv: Hsv) -> Self {
+        let c = hsv.v * hsv.s;
+        from_hue_chroma(hsv.h, c, hsv.v - c, hsv.a)
+    }
+}
+
+pub trait ColorExt {
+    /// 0xAARRGGBB value
+    fn to_argb(&self) -> u32;
+    /// "#RRGGBBAA" string
+    fn to_hex(&self) -> String;
+    fn with_alpha(&self, alpha: f32) -> Color;
+    /// Increases HSL lightness by `amount` (0..1)
+    fn lighten(&self, amount: f32) -> Color;
+    /// Decreases HSL lightness by `amount` (0..1)
+    fn darken(&self, amount: f32) -> Color;
+    /// Linear interpolation of all channels, `t` = 0 gives `self`, `t` = 1 gives `other`
+    fn lerp(&self, other: Color, t: f32) -> Color;
+    /// WCAG relative luminance from 0 for black to 1 for white, the alpha is ignored
+    fn relative_luminance(&self) -> f32;
+    /// WCAG contrast ratio of the colors from 1 to 21, the normal text needs at least 4.5
+    fn contrast_ratio(&self, other: Color) -> f32;
+}
+
+impl ColorExt for Color {
+    fn to_argb(&self) -> u32 {
+        (self.A as u32) << 24 | (self.R as u32) << 16 | (self.G as u32) << 8 | self.B as u32
+    }
+    fn to_hex(&self) -> String {
+        format!("#{:02X}{:02X}{:02X}{:02X}", self.R, self.G, self.B, self.A)
+    }
+    fn with_alpha(&self, alpha: f32) -> Color {
+        Color {
+            A: from_unit(alpha),
+            ..*self
+        }
+    }
+    fn lighten(&self, amount: f32) -> Color {
+        let mut hsl = Hsl::from(*self);
+        hsl.l = (hsl.l + amount).clamp(0., 1.);
+        hsl.into()
+    }
+    fn darken(&self, amount: f32) -> Color {
+        self.lighten(-amount)
+    }
+    fn lerp(&self, other: Color, t: f32) -> Color {
+        let t = t.clamp(0., 1.);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color {
+            A: mix(self.A, other.A),
+            R: mix(self.R, other.R),
+            G: mix(self.G, other.G),
+            B: mix(self.B, other.B),
+        }
+    }
+    fn relative_luminance(&self) -> f32 {
+        let linear = |v: u8| {
+            let v = to_unit(v);
+            if v <= 0.03928 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.R) + 0.7152 * linear(self.G) + 0.0722 * linear(self.B)
+    }
+    fn contrast_ratio(&self, other: Color) -> f32 {
+        let (a, b) = (self.relative_luminance(), other.relative_luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_forms() {
+        assert_eq!(from_hex("#f00").unwrap(), from_argb(0xffff0000));
+        assert_eq!(from_hex("f008").unwrap(), from_argb(0x88ff0000));
+        assert_eq!(from_hex("#102030").unwrap(), from_rgb(0x102030));
+        assert_eq!(from_hex("#10203040").unwrap(), from_argb(0x40102030));
+        assert_eq!(from_hex_const("#10203040"), from_argb(0x40102030));
+    }
+
+    #[test]
+    fn rejects_bad_hex() {
+        for s in [
+            "", "#", "#12", "#12345", "#ggg", "#+f0000", "#-f0000", "#ff 000", "#ффф",
+        ] {
+            assert!(from_hex(s).is_err(), "{} accepted", s);
+        }
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let color = from_argb(0x80123456);
+        assert_eq!(color.to_hex(), "#12345680");
+        assert_eq!(from_hex(&color.to_hex()).unwrap(), color);
+        assert_eq!(color.to_argb(), 0x80123456);
+    }
+
+    #[test]
+    fn hsl_and_hsv_round_trip() {
+        for argb in [
+            0xff000000, 0xffffffff, 0xffff0000, 0x8000ff80, 0xff123456, 0xff808080,
+        ] {
+            let color = from_argb(argb);
+            assert_eq!(Color::from(Hsl::from(color)), color);
+            assert_eq!(Color::from(Hsv::from(color)), color);
+        }
+    }
+
+    #[test]
+    fn hsl_of_primary_colors() {
+        let hsl = Hsl::from(from_rgb(0x00ff00));
+        assert_eq!((hsl.h, hsl.s, hsl.l), (120., 1., 0.5));
+        let hsv = Hsv::from(from_rgb(0x0000ff));
+        assert_eq!((hsv.h, hsv.s, hsv.v), (240., 1., 1.));
+    }
+
+    #[test]
+    fn lighten_darken_and_alpha() {
+        let gray = from_rgb(0x808080);
+        assert_eq!(gray.lighten(1.), from_rgb(0xffffff));
+        assert_eq!(gray.darken(1.), from_rgb(0x000000));
+        assert!(Hsl::from(gray.lighten(0.1)).l > Hsl::from(gray).l);
+        assert_eq!(gray.with_alpha(0.).A, 0);
+        assert_eq!(gray.with_alpha(2.).A, 255);
+    }
+
+    #[test]
+    fn lerp_ends_and_middle() {
+        let (black, white) = (from_rgb(0x000000), from_rgb(0xffffff));
+        assert_eq!(black.lerp(white, 0.), black);
+        assert_eq!(black.lerp(white, 1.), white);
+        assert_eq!(black.lerp(white, 0.5), from_rgb(0x808080));
+        assert_eq!(black.lerp(white, 5.), white);
+    }
+}
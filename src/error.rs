@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use futures::{task::SpawnError, Future};
 use thiserror::Error;
 use windows::core;
@@ -6,6 +8,22 @@ use windows::core;
 pub enum Error {
     #[error("Bad element index")]
     BadIndex,
+    #[error("Bad color format: {0}")]
+    BadColorFormat(String),
+    #[error("Bad search pattern: {0}")]
+    BadPattern(String),
+    #[error("Window thread is not running")]
+    WindowThreadStopped,
+    #[error("Window event receiver has stopped")]
+    EventReceiverStopped,
+    #[error("Event dropped: {0}")]
+    EventDropped(String),
+    #[error("Snapshot {} differs in {pixels} pixels, see {}", .path.display(), .diff.display())]
+    SnapshotMismatch {
+        path: PathBuf,
+        diff: PathBuf,
+        pixels: usize,
+    },
     #[error(transparent)]
     Spawn(SpawnError),
     #[error(transparent)]
@@ -0,0 +1,509 @@
+//!
+//! Basic geometry types used for layout and hit-testing, convertible to and from
+//! `Vector2` and Windows Foundation types. `Vector2` is re-exported for [`size!`](crate::size!).
+//!
+use std::ops::{Add, Mul, Sub};
+
+pub use windows::Foundation::Numerics::Vector2;
+use windows::{
+    Foundation::{self, Numerics::Vector3},
+    Graphics::{RectInt32, SizeInt32},
+};
+
+fn lerp_f32(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+    pub fn lerp(&self, other: Point, t: f32) -> Point {
+        Point {
+            x: lerp_f32(self.x, other.x, t),
+            y: lerp_f32(self.y, other.y, t),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Size {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Size {
+    pub const fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0. || self.height <= 0.
+    }
+    pub fn lerp(&self, other: Size, t: f32) -> Size {
+        Size {
+            width: lerp_f32(self.width, other.width, t),
+            height: lerp_f32(self.height, other.height, t),
+        }
+    }
+}
+
+///
+/// `Vector2` size from the width and height known at compile time, the negative ones fail
+/// the compilation. Usable in constants.
+///
+/// ```ignore
+/// const BUTTON_SIZE: Vector2 = size!(120, 32);
+/// ```
+///
+#[macro_export]
+macro_rules! size {
+    ($width:expr, $height:expr) => {{
+        const SIZE: $crate::geometry::Vector2 = $crate::geometry::Vector2 {
+            X: $width as f32,
+            Y: $height as f32,
+        };
+        const _: () = assert!(SIZE.X >= 0. && SIZE.Y >= 0., "size must not be negative");
+        SIZE
+    }};
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Rect {
+    pub origin: Point,
+    pub size: Size,
+}
+
+impl Rect {
+    pub const fn new(origin: Point, size: Size) -> Self {
+        Self { origin, size }
+    }
+    /// Rectangle of the given size at (0,0)
+    pub fn from_size(size: impl Into<Size>) -> Self {
+        Self {
+            origin: Point::default(),
+            size: size.into(),
+        }
+    }
+    pub fn left(&self) -> f32 {
+        self.origin.x
+    }
+    pub fn top(&self) -> f32 {
+        self.origin.y
+    }
+    pub fn right(&self) -> f32 {
+        self.origin.x + self.size.width
+    }
+    pub fn bottom(&self) -> f32 {
+        self.origin.y + self.size.height
+    }
+    pub fn center(&self) -> Point {
+        Point {
+            x: self.origin.x + self.size.width / 2.,
+            y: self.origin.y + self.size.height / 2.,
+        }
+    }
+    /// Point on the border is considered as inside
+    pub fn contains(&self, point: impl Into<Point>) -> bool {
+        let point = point.into();
+        point.x >= self.left()
+            && point.x <= self.right()
+            && point.y >= self.top()
+            && point.y <= self.bottom()
+    }
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.left() < other.right()
+            && other.left() < self.right()
+            && self.top() < other.bottom()
+            && other.top() < self.bottom()
+    }
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let left = self.left().max(other.left());
+        let top = self.top().max(other.top());
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        Some(Rect {
+            origin: Point::new(left, top),
+            size: Size::new(right - left, bottom - top),
+        })
+    }
+    /// Grows the rectangle by `dx` on left and right sides and by `dy` on top and bottom.
+    /// Negative values shrink it, the size never becomes negative.
+    pub fn inflate(&self, dx: f32, dy: f32) -> Rect {
+        Rect {
+            origin: Point::new(self.origin.x - dx, self.origin.y - dy),
+            size: Size::new(
+                (self.size.width + dx * 2.).max(0.),
+                (self.size.height + dy * 2.).max(0.),
+            ),
+        }
+    }
+    pub fn translate(&self, offset: impl Into<Point>) -> Rect {
+        let offset = offset.into();
+        Rect {
+            origin: self.origin + offset,
+            size: self.size,
+        }
+    }
+    /// Rectangle of the given size with the same center
+    pub fn centered(&self, size: impl Into<Size>) -> Rect {
+        let size = size.into();
+        let center = self.center();
+        Rect {
+            origin: Point::new(center.x - size.width / 2., center.y - size.height / 2.),
+            size,
+        }
+    }
+    /// Translates the point from the coordinate space containing the rectangle
+    /// to the rectangle's own coordinate space
+    pub fn to_local(&self, point: impl Into<Point>) -> Point {
+        point.into() - self.origin
+    }
+    pub fn lerp(&self, other: Rect, t: f32) -> Rect {
+        Rect {
+            origin: self.origin.lerp(other.origin, t),
+            size: self.size.lerp(other.size, t),
+        }
+    }
+    /// Shrinks the rectangle by the thickness on each side, the size doesn't get negative
+    pub fn deflate(&self, thickness: Thickness) -> Rect {
+        Rect {
+            origin: Point::new(
+                self.origin.x + thickness.left,
+                self.origin.y + thickness.top,
+            ),
+            size: Size::new(
+                (self.size.width - thickness.horizontal()).max(0.),
+                (self.size.height - thickness.vertical()).max(0.),
+            ),
+        }
+    }
+    ///
+    /// Rounds the edges to the device pixels for the given scale (pixels per DIP). Edges are
+    /// rounded rather than the origin and size, so the adjacent rectangles stay adjacent.
+    ///
+    pub fn snap_to_pixels(&self, scale: f32) -> Rect {
+        let round = |v: f32| (v * scale).round() / scale;
+        let (left, top) = (round(self.left()), round(self.top()));
+        let (right, bottom) = (round(self.right()), round(self.bottom()));
+        Rect {
+            origin: Point::new(left, top),
+            size: Size::new(right - left, bottom - top),
+        }
+    }
+}
+
+/// Widths of the four sides of a margin or padding
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Thickness {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Thickness {
+    pub const fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+    pub const fn uniform(v: f32) -> Self {
+        Self::new(v, v, v, v)
+    }
+    /// Sum of the left and right sides
+    pub fn horizontal(&self) -> f32 {
+        self.left + self.right
+    }
+    /// Sum of the top and bottom sides
+    pub fn vertical(&self) -> f32 {
+        self.top + self.bottom
+    }
+}
+
+impl From<f32> for Thickness {
+    fn from(v: f32) -> Self {
+        Thickness::uniform(v)
+    }
+}
+
+/// Placement of the element along one axis of the space given to it
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Alignment {
+    Start,
+    Center,
+    End,
+    /// Occupies all the space if the size isn't limited
+    #[default]
+    Stretch,
+}
+
+impl Alignment {
+    /// Start of the element of `size` in the space from `start` of `available` length
+    pub fn position(&self, start: f32, available: f32, size: f32) -> f32 {
+        match self {
+            Alignment::Start | Alignment::Stretch => start,
+            Alignment::Center => start + (available - size) / 2.,
+            Alignment::End => start + available - size,
+        }
+    }
+}
+
+///
+/// Length in layout: absolute in pixels, relative to the parent's size or automatic.
+/// The meaning of `Auto` is defined by the container, usually it's the share of the space left
+/// after placing fixed-size elements. Lengths are resolved each time the container is resized.
+///
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Length {
+    Px(f32),
+    /// Percent of the parent's size, 100. means whole parent
+    Percent(f32),
+    #[default]
+    Auto,
+}
+
+impl Length {
+    pub fn is_auto(&self) -> bool {
+        *self == Length::Auto
+    }
+    /// Resolves to pixels, `auto` is used as the value of `Length::Auto`
+    pub fn resolve(&self, parent: f32, auto: f32) -> f32 {
+        match self {
+            Length::Px(v) => *v,
+            Length::Percent(v) => parent * v / 100.,
+            Length::Auto => auto,
+        }
+    }
+    /// Resolves to pixels, `None` for `Length::Auto`
+    pub fn try_resolve(&self, parent: f32) -> Option<f32> {
+        match self {
+            Length::Auto => None,
+            _ => Some(self.resolve(parent, 0.)),
+        }
+    }
+}
+
+impl From<f32> for Length {
+    fn from(v: f32) -> Self {
+        Length::Px(v)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+    fn add(self, rhs: Point) -> Point {
+        Point::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+    fn sub(self, rhs: Point) -> Point {
+        Point::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl Mul<f32> for Size {
+    type Output = Size;
+    fn mul(self, rhs: f32) -> Size {
+        Size::new(self.width * rhs, self.height * rhs)
+    }
+}
+
+impl From<Vector2> for Point {
+    fn from(v: Vector2) -> Self {
+        Point::new(v.X, v.Y)
+    }
+}
+
+impl From<Point> for Vector2 {
+    fn from(p: Point) -> Self {
+        Vector2 { X: p.x, Y: p.y }
+    }
+}
+
+impl From<Vector3> for Point {
+    fn from(v: Vector3) -> Self {
+        Point::new(v.X, v.Y)
+    }
+}
+
+impl From<Point> for Vector3 {
+    fn from(p: Point) -> Self {
+        Vector3 {
+            X: p.x,
+            Y: p.y,
+            Z: 0.,
+        }
+    }
+}
+
+impl From<Foundation::Point> for Point {
+    fn from(p: Foundation::Point) -> Self {
+        Point::new(p.X, p.Y)
+    }
+}
+
+impl From<Point> for Foundation::Point {
+    fn from(p: Point) -> Self {
+        Foundation::Point { X: p.x, Y: p.y }
+    }
+}
+
+impl From<Vector2> for Size {
+    fn from(v: Vector2) -> Self {
+        Size::new(v.X, v.Y)
+    }
+}
+
+impl From<Size> for Vector2 {
+    fn from(s: Size) -> Self {
+        Vector2 {
+            X: s.width,
+            Y: s.height,
+        }
+    }
+}
+
+impl From<Foundation::Size> for Size {
+    fn from(s: Foundation::Size) -> Self {
+        Size::new(s.Width, s.Height)
+    }
+}
+
+impl From<Size> for Foundation::Size {
+    fn from(s: Size) -> Self {
+        Foundation::Size {
+            Width: s.width,
+            Height: s.height,
+        }
+    }
+}
+
+impl From<SizeInt32> for Size {
+    fn from(s: SizeInt32) -> Self {
+        Size::new(s.Width as f32, s.Height as f32)
+    }
+}
+
+impl From<Size> for SizeInt32 {
+    fn from(s: Size) -> Self {
+        SizeInt32 {
+            Width: s.width as i32,
+            Height: s.height as i32,
+        }
+    }
+}
+
+impl From<Foundation::Rect> for Rect {
+    fn from(r: Foundation::Rect) -> Self {
+        Rect::new(Point::new(r.X, r.Y), Size::new(r.Width, r.Height))
+    }
+}
+
+impl From<Rect> for Foundation::Rect {
+    fn from(r: Rect) -> Self {
+        Foundation::Rect {
+            X: r.origin.x,
+            Y: r.origin.y,
+            Width: r.size.width,
+            Height: r.size.height,
+        }
+    }
+}
+
+impl From<RectInt32> for Rect {
+    fn from(r: RectInt32) -> Self {
+        Rect::new(
+            Point::new(r.X as f32, r.Y as f32),
+            Size::new(r.Width as f32, r.Height as f32),
+        )
+    }
+}
+
+/// Smallest integer rectangle covering the rectangle, the pixels partially covered are included
+impl From<Rect> for RectInt32 {
+    fn from(r: Rect) -> Self {
+        let (left, top) = (r.left().floor() as i32, r.top().floor() as i32);
+        RectInt32 {
+            X: left,
+            Y: top,
+            Width: r.right().ceil() as i32 - left,
+            Height: r.bottom().ceil() as i32 - top,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn contains_includes_border() {
+        let r = rect(10., 20., 30., 40.);
+        assert!(r.contains(Point::new(10., 20.)));
+        assert!(r.contains(Point::new(40., 60.)));
+        assert!(!r.contains(Point::new(40.1, 30.)));
+        assert!(!r.contains(Point::new(9.9, 30.)));
+    }
+
+    #[test]
+    fn touching_rects_dont_intersect() {
+        let a = rect(0., 0., 10., 10.);
+        assert!(!a.intersects(&rect(10., 0., 10., 10.)));
+        assert_eq!(a.intersect(&rect(10., 0., 10., 10.)), None);
+        assert_eq!(
+            a.intersect(&rect(5., -5., 10., 10.)),
+            Some(rect(5., 0., 5., 5.))
+        );
+    }
+
+    #[test]
+    fn inflate_never_gets_negative() {
+        let r = rect(10., 10., 20., 4.);
+        assert_eq!(r.inflate(2., 1.), rect(8., 9., 24., 6.));
+        assert_eq!(r.inflate(-5., -5.).size, Size::new(10., 0.));
+    }
+
+    #[test]
+    fn centered_translate_and_local() {
+        let r = rect(0., 0., 100., 50.);
+        assert_eq!(r.centered(Size::new(20., 10.)), rect(40., 20., 20., 10.));
+        let moved = r.translate(Point::new(5., 6.));
+        assert_eq!(moved.origin, Point::new(5., 6.));
+        assert_eq!(moved.to_local(Point::new(15., 16.)), Point::new(10., 10.));
+        assert_eq!(r.lerp(moved, 0.5).origin, Point::new(2.5, 3.));
+    }
+
+    #[test]
+    fn rect_int32_covers_fractional_rect() {
+        let covered = RectInt32::from(rect(10.5, 20.25, 100.7, 0.5));
+        assert_eq!(
+            (covered.X, covered.Y, covered.Width, covered.Height),
+            (10, 20, 102, 1)
+        );
+        let negative = RectInt32::from(rect(-0.5, -1., 1., 1.));
+        assert_eq!(
+            (negative.X, negative.Y, negative.Width, negative.Height),
+            (-1, -1, 2, 1)
+        );
+        let exact = RectInt32::from(rect(1., 2., 3., 4.));
+        assert_eq!(Rect::from(exact), rect(1., 2., 3., 4.));
+    }
+}
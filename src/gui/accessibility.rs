@@ -0,0 +1,463 @@
+use std::fmt;
+
+use windows::{
+    core::{Interface, HSTRING},
+    UI::{
+        Color,
+        Composition::{CompositionGetValueStatus, ContainerVisual, Visual},
+    },
+};
+
+use crate::color::ColorExt;
+
+use super::{Panel, Theme};
+
+/// Contrast ratio the normal text needs by WCAG level AA
+pub const MIN_TEXT_CONTRAST: f32 = 4.5;
+
+const ROLE: &str = "AccessibleRole";
+const FOCUSABLE: &str = "AccessibleFocusable";
+const FOREGROUND: &str = "AccessibleForeground";
+const BACKGROUND: &str = "AccessibleBackground";
+const DECORATIVE: &str = "AccessibleDecorative";
+// Separates the name, the description and the automation id in the visual's comment
+const FIELD_SEPARATOR: &str = "\u{1f}";
+
+/// Kind of the element for the screen readers, the subset of the UI Automation control types
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AccessibleRole {
+    Button,
+    CheckBox,
+    ComboBox,
+    Edit,
+    Group,
+    Hyperlink,
+    Image,
+    List,
+    ListItem,
+    Menu,
+    MenuItem,
+    Pane,
+    ProgressBar,
+    RadioButton,
+    Tab,
+    TabItem,
+    Text,
+}
+
+const ROLES: [AccessibleRole; 17] = [
+    AccessibleRole::Button,
+    AccessibleRole::CheckBox,
+    AccessibleRole::ComboBox,
+    AccessibleRole::Edit,
+    AccessibleRole::Group,
+    AccessibleRole::Hyperlink,
+    AccessibleRole::Image,
+    AccessibleRole::List,
+    AccessibleRole::ListItem,
+    AccessibleRole::Menu,
+    AccessibleRole::MenuItem,
+    AccessibleRole::Pane,
+    AccessibleRole::ProgressBar,
+    AccessibleRole::RadioButton,
+    AccessibleRole::Tab,
+    AccessibleRole::TabItem,
+    AccessibleRole::Text,
+];
+
+impl AccessibleRole {
+    // The elements named by their content, like the UI Automation does for the buttons
+    fn named_by_content(&self) -> bool {
+        matches!(
+            self,
+            AccessibleRole::Button
+                | AccessibleRole::CheckBox
+                | AccessibleRole::Hyperlink
+                | AccessibleRole::ListItem
+                | AccessibleRole::MenuItem
+                | AccessibleRole::RadioButton
+                | AccessibleRole::TabItem
+        )
+    }
+}
+
+///
+/// Description of the visual for the assistive technologies, set by the panels with
+/// `set_accessible` on their visuals
+///
+#[derive(PartialEq, Clone, Default, Debug)]
+pub struct Accessible {
+    pub role: Option<AccessibleRole>,
+    pub name: Option<String>,
+    /// Longer text read after the name on request, e.g. the details of the chart
+    pub description: Option<String>,
+    pub focusable: bool,
+    /// Purely visual element, excluded with its children from the accessibility tree
+    pub decorative: bool,
+    ///
+    /// Identifier of the element for the UI test tools like WinAppDriver or FlaUI. Unlike the
+    /// name it doesn't depend on the content or the language. The panels set their type name
+    /// by default, `None` keeps the id the visual already has.
+    ///
+    pub automation_id: Option<String>,
+    /// Color of the text, the theme's foreground if not set
+    pub foreground: Option<Color>,
+    /// Color under the element and its children, the parent's one if not set
+    pub background: Option<Color>,
+}
+
+impl Accessible {
+    pub fn new(role: AccessibleRole) -> Self {
+        Self {
+            role: Some(role),
+            ..Default::default()
+        }
+    }
+}
+
+///
+/// Text alternative of the image-like panels: the pictures, the icons and the drawings. The
+/// described panel appears to the screen readers as the image, the decorative one is hidden
+/// from them together with its children.
+///
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum TextAlternative {
+    /// Name announced by the screen readers and the optional longer description
+    Text {
+        name: String,
+        description: Option<String>,
+    },
+    /// Purely visual image, hidden from the screen readers
+    Decorative,
+}
+
+impl TextAlternative {
+    pub fn new(name: impl Into<String>) -> Self {
+        TextAlternative::Text {
+            name: name.into(),
+            description: None,
+        }
+    }
+    pub fn with_description(self, description: impl Into<String>) -> Self {
+        match self {
+            TextAlternative::Text { name, .. } => TextAlternative::Text {
+                name,
+                description: Some(description.into()),
+            },
+            TextAlternative::Decorative => TextAlternative::Decorative,
+        }
+    }
+}
+
+// Automation id of the visual, `default_id` unless the application has set it
+pub(crate) fn automation_id_or(visual: &Visual, default_id: &str) -> crate::Result<String> {
+    Ok(comment_fields(&visual.Comment()?.to_string())
+        .2
+        .unwrap_or_else(|| default_id.to_string()))
+}
+
+// Describes the image-like visual by the text alternative
+pub(crate) fn set_text_alternative(
+    visual: &Visual,
+    default_id: &str,
+    alternative: &TextAlternative,
+) -> crate::Result<()> {
+    let mut accessible = Accessible {
+        automation_id: Some(automation_id_or(visual, default_id)?),
+        ..Accessible::new(AccessibleRole::Image)
+    };
+    match alternative {
+        TextAlternative::Text { name, description } => {
+            accessible.name = Some(name.clone());
+            accessible.description = description.clone();
+        }
+        TextAlternative::Decorative => accessible.decorative = true,
+    }
+    set_accessible(visual, &accessible)
+}
+
+// Name, description and automation id kept in the visual's comment
+fn comment_fields(comment: &str) -> (Option<String>, Option<String>, Option<String>) {
+    let mut fields = comment
+        .splitn(3, FIELD_SEPARATOR)
+        .map(|v| Some(v.to_string()).filter(|v| !v.is_empty()));
+    (
+        fields.next().flatten(),
+        fields.next().flatten(),
+        fields.next().flatten(),
+    )
+}
+
+///
+/// Attaches the description to the visual. The name, the description and the automation id
+/// go to the visual's comment, the rest to its property set, so the description lives as long
+/// as the visual.
+///
+pub fn set_accessible(visual: &Visual, accessible: &Accessible) -> crate::Result<()> {
+    let properties = visual.Properties()?;
+    let role = accessible
+        .role
+        .and_then(|role| ROLES.iter().position(|v| *v == role))
+        .map_or(0., |v| v as f32 + 1.);
+    properties.InsertScalar(&HSTRING::from(ROLE), role)?;
+    properties.InsertBoolean(&HSTRING::from(FOCUSABLE), accessible.focusable)?;
+    properties.InsertBoolean(&HSTRING::from(DECORATIVE), accessible.decorative)?;
+    // The transparent color stands for the unset one
+    let transparent = Color::default();
+    properties.InsertColor(
+        &HSTRING::from(FOREGROUND),
+        accessible.foreground.unwrap_or(transparent),
+    )?;
+    properties.InsertColor(
+        &HSTRING::from(BACKGROUND),
+        accessible.background.unwrap_or(transparent),
+    )?;
+    let automation_id = match &accessible.automation_id {
+        Some(automation_id) => Some(automation_id.clone()),
+        None => comment_fields(&visual.Comment()?.to_string()).2,
+    };
+    let comment = [&accessible.name, &accessible.description, &automation_id]
+        .map(|v| v.as_deref().unwrap_or_default())
+        .join(FIELD_SEPARATOR);
+    visual.SetComment(&HSTRING::from(comment))?;
+    Ok(())
+}
+
+/// Description of the visual, `None` if it has none
+pub fn accessible(visual: &Visual) -> crate::Result<Option<Accessible>> {
+    let properties = visual.Properties()?;
+    let mut role = 0.;
+    if properties.TryGetScalar(&HSTRING::from(ROLE), &mut role)?
+        != CompositionGetValueStatus::Succeeded
+    {
+        return Ok(None);
+    }
+    let mut focusable = false;
+    properties.TryGetBoolean(&HSTRING::from(FOCUSABLE), &mut focusable)?;
+    let mut decorative = false;
+    properties.TryGetBoolean(&HSTRING::from(DECORATIVE), &mut decorative)?;
+    let color = |key: &str| -> crate::Result<Option<Color>> {
+        let mut color = Color::default();
+        properties.TryGetColor(&HSTRING::from(key), &mut color)?;
+        Ok(if color.A == 0 { None } else { Some(color) })
+    };
+    let (name, description, automation_id) = comment_fields(&visual.Comment()?.to_string());
+    Ok(Some(Accessible {
+        role: (role as usize)
+            .checked_sub(1)
+            .and_then(|v| ROLES.get(v))
+            .copied(),
+        name,
+        description,
+        focusable,
+        decorative,
+        automation_id,
+        foreground: color(FOREGROUND)?,
+        background: color(BACKGROUND)?,
+    }))
+}
+
+///
+/// Sets the automation id the UI test tools find the panel by, e.g. to tell apart the buttons
+/// of the dialog. The panel without the description gets the one without the role.
+///
+pub fn set_automation_id<T: Panel + ?Sized>(
+    panel: &T,
+    automation_id: impl Into<String>,
+) -> crate::Result<()> {
+    let visual = panel.outer_frame();
+    let accessible = Accessible {
+        automation_id: Some(automation_id.into()),
+        ..accessible(&visual)?.unwrap_or_default()
+    };
+    set_accessible(&visual, &accessible)
+}
+
+/// Automation id of the panel, `None` if it's not described
+pub fn automation_id<T: Panel + ?Sized>(panel: &T) -> crate::Result<Option<String>> {
+    Ok(accessible(&panel.outer_frame())?.and_then(|v| v.automation_id))
+}
+
+/// Element of the accessibility tree: the visible visual with the description
+#[derive(Clone, Debug)]
+pub struct AccessibleNode {
+    pub accessible: Accessible,
+    pub children: Vec<AccessibleNode>,
+}
+
+impl AccessibleNode {
+    // Text of the descendant text elements, the name of the elements named by content
+    fn content_name(&self) -> Option<String> {
+        let mut names = Vec::new();
+        for child in &self.children {
+            match (&child.accessible.role, &child.accessible.name) {
+                (Some(AccessibleRole::Text), Some(name)) => names.push(name.clone()),
+                _ => names.extend(child.content_name()),
+            }
+        }
+        if names.is_empty() {
+            None
+        } else {
+            Some(names.join(" "))
+        }
+    }
+
+    /// The node or its descendant with the automation id, the way the UI tests find elements
+    pub fn find(&self, automation_id: &str) -> Option<&AccessibleNode> {
+        if self.accessible.automation_id.as_deref() == Some(automation_id) {
+            return Some(self);
+        }
+        self.children.iter().find_map(|v| v.find(automation_id))
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let role = match &self.accessible.role {
+            Some(role) => format!("{:?}", role),
+            None => "?".to_string(),
+        };
+        write!(f, "{:indent$}{}", "", role, indent = depth * 2)?;
+        if let Some(name) = &self.accessible.name {
+            write!(f, " {:?}", name)?;
+        }
+        if let Some(description) = &self.accessible.description {
+            write!(f, " ({:?})", description)?;
+        }
+        if let Some(automation_id) = &self.accessible.automation_id {
+            write!(f, " #{}", automation_id)?;
+        }
+        if self.accessible.focusable {
+            write!(f, " focusable")?;
+        }
+        writeln!(f)?;
+        for child in &self.children {
+            child.write(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AccessibleNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+// Described visible descendants of the visual, the visuals without description are skipped
+// with their descendants lifted to the parent, the decorative ones with their descendants
+fn collect(visual: &Visual, nodes: &mut Vec<AccessibleNode>) -> crate::Result<()> {
+    if !visual.IsVisible()? {
+        return Ok(());
+    }
+    let accessible = accessible(visual)?;
+    if accessible.as_ref().map_or(false, |v| v.decorative) {
+        return Ok(());
+    }
+    let mut children = Vec::new();
+    if let Ok(container) = visual.cast::<ContainerVisual>() {
+        for child in container.Children()? {
+            collect(&child, &mut children)?;
+        }
+    }
+    match accessible {
+        Some(accessible) => {
+            let mut node = AccessibleNode {
+                accessible,
+                children,
+            };
+            let named_by_content = node.accessible.role.map_or(false, |v| v.named_by_content());
+            if node.accessible.name.is_none() && named_by_content {
+                node.accessible.name = node.content_name();
+            }
+            nodes.push(node);
+        }
+        None => nodes.extend(children),
+    }
+    Ok(())
+}
+
+///
+/// The accessibility tree of the panel: the descriptions of its visible visuals. It's what
+/// the panel exposes to the assistive technologies, the tree may be printed to see it. Each
+/// line shows the role, the name, the description and the automation id after `#`.
+///
+pub fn accessibility_tree<T: Panel + ?Sized>(panel: &T) -> crate::Result<Vec<AccessibleNode>> {
+    let mut nodes = Vec::new();
+    collect(&panel.outer_frame(), &mut nodes)?;
+    Ok(nodes)
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum AccessibilityIssueKind {
+    /// The focusable element or the image has no name for the screen reader to announce
+    MissingName,
+    /// The focusable element has no role, so the user doesn't know how to use it
+    MissingRole,
+    /// The text is hard to read on its background
+    LowContrast { ratio: f32 },
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct AccessibilityIssue {
+    pub kind: AccessibilityIssueKind,
+    pub accessible: Accessible,
+}
+
+fn audit_node(
+    node: &AccessibleNode,
+    background: Color,
+    theme: &Theme,
+    issues: &mut Vec<AccessibilityIssue>,
+) {
+    let accessible = &node.accessible;
+    let mut report = |kind| {
+        issues.push(AccessibilityIssue {
+            kind,
+            accessible: accessible.clone(),
+        })
+    };
+    let needs_name = accessible.focusable || accessible.role == Some(AccessibleRole::Image);
+    if needs_name && accessible.name.is_none() {
+        report(AccessibilityIssueKind::MissingName);
+    }
+    if accessible.focusable && accessible.role.is_none() {
+        report(AccessibilityIssueKind::MissingRole);
+    }
+    let background = accessible.background.unwrap_or(background);
+    if accessible.role == Some(AccessibleRole::Text) && accessible.name.is_some() {
+        let foreground = accessible
+            .foreground
+            .unwrap_or_else(|| theme.palette().foreground);
+        // The translucent text is seen blended with the background
+        let foreground = background.lerp(foreground.with_alpha(1.), foreground.A as f32 / 255.);
+        let ratio = foreground.contrast_ratio(background);
+        if ratio < MIN_TEXT_CONTRAST {
+            report(AccessibilityIssueKind::LowContrast { ratio });
+        }
+    }
+    for child in &node.children {
+        audit_node(child, background, theme, issues);
+    }
+}
+
+///
+/// Checks the accessibility tree of the panel for the common issues: the focusable elements
+/// without names or roles and the text with the contrast below [`MIN_TEXT_CONTRAST`]. The
+/// colors not given by the elements are taken from the theme's palette. The apps may run it
+/// in the tests:
+///
+/// ```ignore
+/// let issues = accessibility_audit(&*dialog, &theme)?;
+/// assert!(issues.is_empty(), "{:?}", issues);
+/// ```
+///
+pub fn accessibility_audit<T: Panel + ?Sized>(
+    panel: &T,
+    theme: &Theme,
+) -> crate::Result<Vec<AccessibilityIssue>> {
+    let mut issues = Vec::new();
+    let background = theme.palette().background;
+    for node in accessibility_tree(panel)? {
+        audit_node(&node, background, theme, &mut issues);
+    }
+    Ok(issues)
+}
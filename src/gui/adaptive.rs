@@ -0,0 +1,253 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::Composition::{Compositor, ContainerVisual, Visual},
+};
+
+use super::{attach, dispatch::DispatchQueue, Panel, PanelEvent};
+
+///
+/// Set of width breakpoints. Breakpoint `i` is active when the width is not less than
+/// `breakpoints[i]` and less than `breakpoints[i + 1]`. Width below the first breakpoint
+/// activates the first one too.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Breakpoints {
+    breakpoints: Vec<f32>,
+    active: Option<usize>,
+}
+
+impl Breakpoints {
+    pub fn new(mut breakpoints: Vec<f32>) -> Self {
+        breakpoints.sort_by(|a, b| a.total_cmp(b));
+        Self {
+            breakpoints,
+            active: None,
+        }
+    }
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+    pub fn breakpoint(&self, index: usize) -> Option<f32> {
+        self.breakpoints.get(index).cloned()
+    }
+    fn index_for(&self, width: f32) -> Option<usize> {
+        if self.breakpoints.is_empty() {
+            return None;
+        }
+        Some(
+            self.breakpoints
+                .iter()
+                .rposition(|v| width >= *v)
+                .unwrap_or(0),
+        )
+    }
+    /// Updates the width, returns the new active breakpoint index if it was changed
+    pub fn update(&mut self, width: f32) -> Option<usize> {
+        let index = self.index_for(width);
+        if index != self.active {
+            self.active = index;
+            index
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum AdaptiveEvent {
+    /// Width crossed the breakpoint, layout with given index and minimal width is active now
+    BreakpointChanged { index: usize, min_width: f32 },
+}
+
+struct Layout {
+    min_width: f32,
+    panel: Arc<dyn Panel>,
+}
+
+struct Core {
+    layouts: Vec<Layout>,
+    breakpoints: Breakpoints,
+    size: Vector2,
+}
+
+impl Core {
+    fn active_panel(&self) -> Option<Arc<dyn Panel>> {
+        self.breakpoints
+            .active()
+            .map(|index| self.layouts[index].panel.clone())
+    }
+}
+
+///
+/// Container showing one of its layouts depending on its width, e.g. a wide layout with
+/// a sidebar and a narrow one with a hamburger menu. Each layout is registered with the minimal
+/// width from which it's active. Crossing the breakpoint switches the visible layout and emits
+/// `AdaptiveEvent::BreakpointChanged`, which can be also used to restyle other panels.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Adaptive {
+    container: ContainerVisual,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    adaptive_events: EventStreams<AdaptiveEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct AdaptiveParams {
+    compositor: Compositor,
+    #[builder(default)]
+    layouts: Vec<(f32, Arc<dyn Panel>)>,
+}
+
+impl AdaptiveParams {
+    pub fn add_layout(mut self, min_width: f32, panel: Arc<dyn Panel>) -> Self {
+        self.layouts.push((min_width, panel));
+        self
+    }
+}
+
+impl TryFrom<AdaptiveParams> for Adaptive {
+    type Error = crate::Error;
+
+    fn try_from(value: AdaptiveParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let mut layouts = value
+            .layouts
+            .into_iter()
+            .map(|(min_width, panel)| Layout { min_width, panel })
+            .collect::<Vec<_>>();
+        layouts.sort_by(|a, b| a.min_width.total_cmp(&b.min_width));
+        for layout in &layouts {
+            attach(&container, &*layout.panel)?;
+            layout.panel.outer_frame().SetIsVisible(false)?;
+        }
+        let breakpoints = Breakpoints::new(layouts.iter().map(|v| v.min_width).collect());
+        let core = RwLock::new(Core {
+            layouts,
+            breakpoints,
+            size: Vector2::default(),
+        });
+        Ok(Adaptive {
+            container,
+            core,
+            panel_events: EventStreams::new(),
+            adaptive_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<AdaptiveParams> for Arc<Adaptive> {
+    type Error = crate::Error;
+
+    fn try_from(value: AdaptiveParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Adaptive {
+    /// Index of the currently shown layout
+    pub async fn active(&self) -> Option<usize> {
+        self.core.read().await.breakpoints.active()
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        let (previous, changed, active) = {
+            let mut core = self.core.write().await;
+            core.size = size;
+            let previous = core.active_panel();
+            let changed = core.breakpoints.update(size.X);
+            (previous, changed, core.active_panel())
+        };
+        if let Some(index) = changed {
+            if let Some(previous) = previous {
+                previous.outer_frame().SetIsVisible(false)?;
+            }
+            if let Some(active) = &active {
+                active.outer_frame().SetIsVisible(true)?;
+            }
+            let min_width = self.core.read().await.layouts[index].min_width;
+            self.adaptive_events
+                .send_event(
+                    AdaptiveEvent::BreakpointChanged { index, min_width },
+                    source.clone(),
+                )
+                .await;
+        }
+        if let Some(active) = active {
+            active
+                .on_event_owned(PanelEvent::Resized(size), source)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            _ => {
+                let active = self.core.read().await.active_panel();
+                if let Some(active) = active {
+                    active.on_event_ref(&event, source.clone()).await?;
+                }
+            }
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<AdaptiveEvent> for Adaptive {
+    fn event_stream(&self) -> EventStream<AdaptiveEvent> {
+        self.adaptive_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for Adaptive {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Adaptive {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Adaptive {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
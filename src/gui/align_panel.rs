@@ -0,0 +1,305 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::Composition::{Compositor, ContainerVisual, Visual},
+};
+
+use crate::geometry::{Alignment, Length, Point, Rect, Size, Thickness};
+
+use super::{attach, dispatch::DispatchQueue, Panel, PanelEvent};
+
+// Size of the box along one axis: the length limited by min and max and by the available space
+fn box_length(length: Length, available: f32, min: f32, max: Option<f32>) -> f32 {
+    let v = length.resolve(available, available);
+    let v = match max {
+        Some(max) => v.min(max),
+        None => v,
+    };
+    v.min(available).max(min)
+}
+
+struct Layout {
+    margin: Thickness,
+    padding: Thickness,
+    horizontal: Alignment,
+    vertical: Alignment,
+    width: Length,
+    height: Length,
+    min_size: Size,
+    max_size: Option<Size>,
+}
+
+impl Layout {
+    // Rectangle of the child in the panel of the given size
+    fn child_rect(&self, size: Vector2) -> Rect {
+        let available = Rect::from_size(size).deflate(self.margin);
+        let width = box_length(
+            self.width,
+            available.size.width,
+            self.min_size.width,
+            self.max_size.map(|v| v.width),
+        );
+        let height = box_length(
+            self.height,
+            available.size.height,
+            self.min_size.height,
+            self.max_size.map(|v| v.height),
+        );
+        let x = self
+            .horizontal
+            .position(available.left(), available.size.width, width);
+        let y = self
+            .vertical
+            .position(available.top(), available.size.height, height);
+        Rect::new(Point::new(x, y), Size::new(width, height)).deflate(self.padding)
+    }
+}
+
+struct Core {
+    layout: Layout,
+    child_rect: Rect,
+    mouse_pos: Option<Vector2>,
+}
+
+///
+/// Wrapper applying margin, padding, alignment and size limits to its child. The child gets
+/// the space left after the margin, limited by the size constraints and placed according to
+/// the alignment, minus the padding. Since panels don't report their natural size,
+/// `Length::Auto` means all the available space, so non-stretched alignment makes sense
+/// with the fixed, relative or maximal size only.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct AlignPanel {
+    container: ContainerVisual,
+    slot: ContainerVisual,
+    child: Arc<dyn Panel>,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct AlignPanelParams {
+    compositor: Compositor,
+    child: Arc<dyn Panel>,
+    #[builder(default, setter(into))]
+    margin: Thickness,
+    #[builder(default, setter(into))]
+    padding: Thickness,
+    #[builder(default)]
+    horizontal: Alignment,
+    #[builder(default)]
+    vertical: Alignment,
+    #[builder(default, setter(into))]
+    width: Length,
+    #[builder(default, setter(into))]
+    height: Length,
+    #[builder(default)]
+    min_size: Size,
+    #[builder(default, setter(strip_option))]
+    max_size: Option<Size>,
+}
+
+impl TryFrom<AlignPanelParams> for AlignPanel {
+    type Error = crate::Error;
+
+    fn try_from(value: AlignPanelParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let slot = value.compositor.CreateContainerVisual()?;
+        container.Children()?.InsertAtTop(&slot)?;
+        attach(&slot, &*value.child)?;
+        let layout = Layout {
+            margin: value.margin,
+            padding: value.padding,
+            horizontal: value.horizontal,
+            vertical: value.vertical,
+            width: value.width,
+            height: value.height,
+            min_size: value.min_size,
+            max_size: value.max_size,
+        };
+        Ok(AlignPanel {
+            container,
+            slot,
+            child: value.child,
+            core: RwLock::new(Core {
+                layout,
+                child_rect: Rect::default(),
+                mouse_pos: None,
+            }),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<AlignPanelParams> for Arc<AlignPanel> {
+    type Error = crate::Error;
+
+    fn try_from(value: AlignPanelParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl AlignPanel {
+    pub async fn set_margin(&self, margin: impl Into<Thickness>) -> crate::Result<()> {
+        self.core.write().await.layout.margin = margin.into();
+        self.relayout().await
+    }
+
+    pub async fn set_padding(&self, padding: impl Into<Thickness>) -> crate::Result<()> {
+        self.core.write().await.layout.padding = padding.into();
+        self.relayout().await
+    }
+
+    pub async fn set_alignment(
+        &self,
+        horizontal: Alignment,
+        vertical: Alignment,
+    ) -> crate::Result<()> {
+        {
+            let mut core = self.core.write().await;
+            core.layout.horizontal = horizontal;
+            core.layout.vertical = vertical;
+        }
+        self.relayout().await
+    }
+
+    pub async fn set_size(
+        &self,
+        width: impl Into<Length>,
+        height: impl Into<Length>,
+    ) -> crate::Result<()> {
+        {
+            let mut core = self.core.write().await;
+            core.layout.width = width.into();
+            core.layout.height = height.into();
+        }
+        self.relayout().await
+    }
+
+    pub async fn set_size_limits(
+        &self,
+        min_size: Size,
+        max_size: Option<Size>,
+    ) -> crate::Result<()> {
+        {
+            let mut core = self.core.write().await;
+            core.layout.min_size = min_size;
+            core.layout.max_size = max_size;
+        }
+        self.relayout().await
+    }
+
+    async fn relayout(&self) -> crate::Result<()> {
+        self.resize(self.container.Size()?, None).await
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        let rect = {
+            let mut core = self.core.write().await;
+            core.child_rect = core.layout.child_rect(size);
+            core.child_rect
+        };
+        self.slot.SetOffset(rect.origin.into())?;
+        self.slot.SetSize(rect.size.into())?;
+        self.child
+            .on_event_owned(PanelEvent::Resized(rect.size.into()), source)
+            .await
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            PanelEvent::CursorMoved(pos) => {
+                let rect = {
+                    let mut core = self.core.write().await;
+                    core.mouse_pos = Some(*pos);
+                    core.child_rect
+                };
+                self.child
+                    .on_event_owned(
+                        PanelEvent::CursorMoved(rect.to_local(*pos).into()),
+                        source.clone(),
+                    )
+                    .await?
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                click_count,
+                seq,
+            } => {
+                let (rect, mouse_pos) = {
+                    let core = self.core.read().await;
+                    (core.child_rect, core.mouse_pos)
+                };
+                // The margin and padding don't belong to the child
+                let in_slot = *in_slot && mouse_pos.map_or(false, |v| rect.contains(v));
+                self.child
+                    .on_event_owned(
+                        PanelEvent::MouseInput {
+                            in_slot,
+                            state: *state,
+                            button: *button,
+                            click_count: *click_count,
+                            seq: *seq,
+                        },
+                        source.clone(),
+                    )
+                    .await?
+            }
+            _ => self.child.on_event_ref(&event, source.clone()).await?,
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for AlignPanel {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for AlignPanel {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for AlignPanel {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
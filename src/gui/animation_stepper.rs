@@ -0,0 +1,264 @@
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use windows::{
+    core::{self, Interface, HSTRING},
+    Foundation::TimeSpan,
+    UI::Composition::{
+        AnimationIterationBehavior, CompositionAnimation, KeyFrameAnimation, Visual,
+    },
+};
+
+use super::time_span;
+
+fn duration_of(time_span: TimeSpan) -> Duration {
+    Duration::from_nanos(time_span.Duration.max(0) as u64 * 100)
+}
+
+// Key frame animation held paused by the stepper
+struct Track {
+    visual: Visual,
+    property: HSTRING,
+    // Stepper position when the animation was started
+    start: Duration,
+    // Time of the animation already passed at the start, e.g. when the storyboard is seeked
+    skipped: Duration,
+    delay: Duration,
+    duration: Duration,
+    // None for the animations repeated forever
+    iterations: Option<u32>,
+    // Animation time the track is frozen at by the pause
+    paused_at: Option<Duration>,
+}
+
+impl Track {
+    // Animation time at the stepper position, the delay excluded
+    fn elapsed(&self, position: Duration) -> Duration {
+        self.paused_at.unwrap_or_else(|| {
+            (position.saturating_sub(self.start) + self.skipped).saturating_sub(self.delay)
+        })
+    }
+
+    // Progress of the current iteration, None when all iterations are played
+    fn progress(&self, position: Duration) -> Option<f32> {
+        let duration = self.duration.as_nanos();
+        if duration == 0 {
+            return None;
+        }
+        let elapsed = self.elapsed(position).as_nanos();
+        let iteration = elapsed / duration;
+        match self.iterations {
+            Some(count) if iteration >= count as u128 => None,
+            _ => Some(((elapsed % duration) as f64 / duration as f64) as f32),
+        }
+    }
+
+    fn is(&self, visual: &Visual, property: &HSTRING) -> bool {
+        self.visual == *visual && self.property == *property
+    }
+}
+
+#[derive(Default)]
+struct State {
+    position: Duration,
+    tracks: Vec<Track>,
+}
+
+///
+/// Test mode of the composition animations. While the stepper is set by
+/// `set_animation_stepper`, the key frame animations started by the panels and storyboards
+/// don't run on the compositor's clock: they stay paused until the test moves the stepper's
+/// time, e.g.
+///
+/// ```ignore
+/// let stepper = AnimationStepper::new();
+/// set_animation_stepper(Some(stepper.clone()));
+/// toggle_switch.set_checked(true).await?;
+/// stepper.step(Duration::from_millis(50))?;
+/// stepper.sample(&thumb, "Offset")?;
+/// assert!(thumb.Offset()?.X > 0.);
+/// ```
+///
+/// The compositor doesn't report the animated values, so the property getters return them
+/// only after `sample` stops the animation at the current point. The finished animations are
+/// stopped by the stepper as well, leaving the final values and completing their batches, so
+/// the code waiting for the batch completion proceeds.
+///
+#[derive(Clone, Default)]
+pub struct AnimationStepper {
+    state: Arc<Mutex<State>>,
+}
+
+impl AnimationStepper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time passed since the stepper was created
+    pub fn position(&self) -> Duration {
+        self.state.lock().unwrap().position
+    }
+
+    /// Number of the animations not finished yet
+    pub fn animations(&self) -> usize {
+        self.state.lock().unwrap().tracks.len()
+    }
+
+    /// Moves the time forward by `delta`
+    pub fn step(&self, delta: Duration) -> crate::Result<()> {
+        let position = self.position() + delta;
+        self.advance_to(position)
+    }
+
+    ///
+    /// Moves the time to the `position`, which allows to sample the animations at the fixed
+    /// times. Moving backward is allowed for the animations not finished yet.
+    ///
+    pub fn advance_to(&self, position: Duration) -> crate::Result<()> {
+        let (running, finished) = {
+            let mut state = self.state.lock().unwrap();
+            state.position = position;
+            let (running, finished) = state
+                .tracks
+                .drain(..)
+                .partition::<Vec<_>, _>(|v| v.progress(position).is_some());
+            let progress = running
+                .iter()
+                .map(|v| {
+                    let progress = v.progress(position).unwrap_or(1.);
+                    (v.visual.clone(), v.property.clone(), progress)
+                })
+                .collect::<Vec<_>>();
+            state.tracks = running;
+            (progress, finished)
+        };
+        for (visual, property, progress) in running {
+            if let Ok(controller) = visual.TryGetAnimationController(&property) {
+                controller.SetProgress(progress)?;
+            } else {
+                // Stopped by its owner
+                self.remove(&visual, &property);
+            }
+        }
+        for track in finished {
+            if let Ok(controller) = track.visual.TryGetAnimationController(&track.property) {
+                controller.SetProgress(1.)?;
+            }
+            track.visual.StopAnimation(&track.property)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Stops the animation of the visual's property at the current point, after that the
+    /// property's getter like `Visual::Offset` returns the animated value
+    ///
+    pub fn sample(&self, visual: &Visual, property: &str) -> crate::Result<()> {
+        let property = HSTRING::from(property);
+        self.remove(visual, &property);
+        visual.StopAnimation(&property)?;
+        Ok(())
+    }
+
+    fn remove(&self, visual: &Visual, property: &HSTRING) {
+        let mut state = self.state.lock().unwrap();
+        state.tracks.retain(|v| !v.is(visual, property));
+    }
+
+    // Starts the animation paused, `skipped` is the part of the animation already played
+    pub(crate) fn start(
+        &self,
+        visual: &Visual,
+        property: &HSTRING,
+        animation: &KeyFrameAnimation,
+        skipped: Duration,
+    ) -> core::Result<()> {
+        let delay = duration_of(animation.DelayTime()?);
+        let iterations = match animation.IterationBehavior()? {
+            AnimationIterationBehavior::Forever => None,
+            _ => Some(animation.IterationCount()?.max(1) as u32),
+        };
+        // The stepper counts the delay itself, the compositor's one would delay the progress
+        // set by the stepper. The animation is copied on start, so the template is restored.
+        animation.SetDelayTime(time_span(Duration::ZERO))?;
+        let started = visual.StartAnimation(property, animation);
+        animation.SetDelayTime(time_span(delay))?;
+        started?;
+        let controller = visual.TryGetAnimationController(property)?;
+        controller.Pause()?;
+        let track = {
+            let mut state = self.state.lock().unwrap();
+            state.tracks.retain(|v| !v.is(visual, property));
+            Track {
+                visual: visual.clone(),
+                property: property.clone(),
+                start: state.position,
+                skipped,
+                delay,
+                duration: duration_of(animation.Duration()?),
+                iterations,
+                paused_at: None,
+            }
+        };
+        let progress = track.progress(self.position());
+        controller.SetProgress(progress.unwrap_or(1.))?;
+        self.state.lock().unwrap().tracks.push(track);
+        Ok(())
+    }
+
+    // Freezes or unfreezes the animation time of the visual's property
+    pub(crate) fn set_paused(&self, visual: &Visual, property: &HSTRING, paused: bool) {
+        let mut state = self.state.lock().unwrap();
+        let position = state.position;
+        for track in state.tracks.iter_mut().filter(|v| v.is(visual, property)) {
+            match (paused, track.paused_at) {
+                (true, None) => track.paused_at = Some(track.elapsed(position)),
+                (false, Some(elapsed)) => {
+                    track.start = position;
+                    track.skipped = elapsed + track.delay;
+                    track.paused_at = None;
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+static STEPPER: RwLock<Option<AnimationStepper>> = RwLock::new(None);
+
+/// The stepper of the animations test mode, if set
+pub fn animation_stepper() -> Option<AnimationStepper> {
+    STEPPER.read().unwrap().clone()
+}
+
+///
+/// Turns on the animations test mode with the stepper or turns it off with `None`. The
+/// animations already running keep the mode they were started in.
+///
+pub fn set_animation_stepper(stepper: Option<AnimationStepper>) {
+    *STEPPER.write().unwrap() = stepper;
+}
+
+///
+/// Starts the composition animation of the visual's property. The panels start their
+/// animations with it, so in the test mode the key frame animations are driven by the
+/// [`AnimationStepper`] instead of the compositor's clock.
+///
+pub fn start_animation(
+    visual: &impl Interface,
+    property: &str,
+    animation: &impl Interface,
+) -> crate::Result<()> {
+    let visual: Visual = visual.cast()?;
+    let animation: CompositionAnimation = animation.cast()?;
+    let property = HSTRING::from(property);
+    match (animation_stepper(), animation.cast::<KeyFrameAnimation>()) {
+        (Some(stepper), Ok(animation)) => {
+            stepper.start(&visual, &property, &animation, Duration::ZERO)?
+        }
+        _ => visual.StartAnimation(&property, &animation)?,
+    }
+    Ok(())
+}
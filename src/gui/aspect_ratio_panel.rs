@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::Composition::{Compositor, ContainerVisual, Visual},
+};
+
+use crate::geometry::{Rect, Size};
+
+use super::{attach, dispatch::DispatchQueue, Panel, PanelEvent};
+
+// Largest rectangle with the ratio of width to height centered in the rectangle of the size
+fn fit(size: Vector2, ratio: f32) -> Rect {
+    let bounds = Rect::from_size(size);
+    if ratio <= 0. || size.Y <= 0. {
+        return bounds;
+    }
+    let content = if size.X / size.Y > ratio {
+        Size::new(size.Y * ratio, size.Y)
+    } else {
+        Size::new(size.X, size.X / ratio)
+    };
+    bounds.centered(content)
+}
+
+struct Core {
+    ratio: f32,
+    child_rect: Rect,
+    mouse_pos: Option<Vector2>,
+}
+
+///
+/// Container giving its child the largest centered rectangle of the fixed aspect ratio which
+/// fits into the container, for video, images and game viewports. The rest of the container
+/// is left empty and doesn't pass the clicks to the child.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct AspectRatioPanel {
+    container: ContainerVisual,
+    slot: ContainerVisual,
+    child: Arc<dyn Panel>,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct AspectRatioPanelParams {
+    compositor: Compositor,
+    child: Arc<dyn Panel>,
+    /// Width divided by height, e.g. 16. / 9.
+    ratio: f32,
+}
+
+impl TryFrom<AspectRatioPanelParams> for AspectRatioPanel {
+    type Error = crate::Error;
+
+    fn try_from(value: AspectRatioPanelParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let slot = value.compositor.CreateContainerVisual()?;
+        container.Children()?.InsertAtTop(&slot)?;
+        attach(&slot, &*value.child)?;
+        Ok(AspectRatioPanel {
+            container,
+            slot,
+            child: value.child,
+            core: RwLock::new(Core {
+                ratio: value.ratio,
+                child_rect: Rect::default(),
+                mouse_pos: None,
+            }),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<AspectRatioPanelParams> for Arc<AspectRatioPanel> {
+    type Error = crate::Error;
+
+    fn try_from(value: AspectRatioPanelParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl AspectRatioPanel {
+    pub async fn ratio(&self) -> f32 {
+        self.core.read().await.ratio
+    }
+
+    pub async fn set_ratio(&self, ratio: f32) -> crate::Result<()> {
+        self.core.write().await.ratio = ratio;
+        self.resize(self.container.Size()?, None).await
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        let rect = {
+            let mut core = self.core.write().await;
+            core.child_rect = fit(size, core.ratio);
+            core.child_rect
+        };
+        self.slot.SetOffset(rect.origin.into())?;
+        self.slot.SetSize(rect.size.into())?;
+        self.child
+            .on_event_owned(PanelEvent::Resized(rect.size.into()), source)
+            .await
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            PanelEvent::CursorMoved(pos) => {
+                let rect = {
+                    let mut core = self.core.write().await;
+                    core.mouse_pos = Some(*pos);
+                    core.child_rect
+                };
+                self.child
+                    .on_event_owned(
+                        PanelEvent::CursorMoved(rect.to_local(*pos).into()),
+                        source.clone(),
+                    )
+                    .await?
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                click_count,
+                seq,
+            } => {
+                let (rect, mouse_pos) = {
+                    let core = self.core.read().await;
+                    (core.child_rect, core.mouse_pos)
+                };
+                let in_slot = *in_slot && mouse_pos.map_or(false, |v| rect.contains(v));
+                self.child
+                    .on_event_owned(
+                        PanelEvent::MouseInput {
+                            in_slot,
+                            state: *state,
+                            button: *button,
+                            click_count: *click_count,
+                            seq: *seq,
+                        },
+                        source.clone(),
+                    )
+                    .await?
+            }
+            _ => self.child.on_event_ref(&event, source.clone()).await?,
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for AspectRatioPanel {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for AspectRatioPanel {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for AspectRatioPanel {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
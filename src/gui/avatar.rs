@@ -0,0 +1,413 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::{Matrix3x2, Vector2, Vector3},
+    Win32::Graphics::{
+        Direct2D::{Common::D2D_RECT_F, D2D1_BRUSH_PROPERTIES},
+        DirectWrite::DWRITE_TEXT_ALIGNMENT_CENTER,
+    },
+    UI::{
+        Color, Colors,
+        Composition::{
+            CompositionEllipseGeometry, Compositor, ContainerVisual, ShapeVisual, Visual,
+        },
+    },
+};
+
+use super::{
+    attach,
+    menu::{d2d_color, sized_text_format, Label},
+    theme::readable_foreground,
+    Panel, PanelEvent, Surface, SurfaceParams,
+};
+
+// Background colors of the initials, chosen by the name
+const PALETTE: [(u8, u8, u8); 8] = [
+    (0x46, 0x82, 0xB4),
+    (0x2E, 0x8B, 0x57),
+    (0xCD, 0x5C, 0x5C),
+    (0x99, 0x32, 0xCC),
+    (0xCD, 0x85, 0x3F),
+    (0x00, 0x80, 0x80),
+    (0x6A, 0x5A, 0xCD),
+    (0xB8, 0x86, 0x0B),
+];
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AvatarSize {
+    Small,
+    Medium,
+    Large,
+    ExtraLarge,
+    Custom(f32),
+}
+
+impl AvatarSize {
+    /// Diameter of the avatar
+    pub fn pixels(&self) -> f32 {
+        match self {
+            AvatarSize::Small => 24.,
+            AvatarSize::Medium => 32.,
+            AvatarSize::Large => 48.,
+            AvatarSize::ExtraLarge => 64.,
+            AvatarSize::Custom(v) => *v,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Presence {
+    Available,
+    Busy,
+    Away,
+    Offline,
+}
+
+impl Presence {
+    fn color(&self) -> Color {
+        let (r, g, b) = match self {
+            Presence::Available => (0x6B, 0xB7, 0x00),
+            Presence::Busy => (0xC5, 0x0F, 0x1F),
+            Presence::Away => (0xFF, 0xAA, 0x44),
+            Presence::Offline => (0x8A, 0x88, 0x86),
+        };
+        Color {
+            A: 0xFF,
+            R: r,
+            G: g,
+            B: b,
+        }
+    }
+}
+
+/// Uppercase first letters of the first and the last word of the name
+fn initials(name: &str) -> String {
+    let mut words = name.split_whitespace();
+    let first = words.next().and_then(|v| v.chars().next());
+    let last = words.last().and_then(|v| v.chars().next());
+    first
+        .into_iter()
+        .chain(last)
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+fn initials_color(name: &str) -> Color {
+    let hash = name.chars().fold(0usize, |hash, c| {
+        hash.wrapping_mul(31).wrapping_add(c as usize)
+    });
+    let (r, g, b) = PALETTE[hash % PALETTE.len()];
+    Color {
+        A: 0xFF,
+        R: r,
+        G: g,
+        B: b,
+    }
+}
+
+struct Core {
+    image: Option<Arc<dyn Panel>>,
+    presence: Option<Presence>,
+}
+
+///
+/// Round picture of the person: the image panel clipped to the circle or, when there is no
+/// image, the initials of the name on the background colored by the name. The optional
+/// presence badge is shown at the bottom right. The avatar is centered in its area.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Avatar {
+    compositor: Compositor,
+    container: ContainerVisual,
+    body: ContainerVisual,
+    clip_geometry: CompositionEllipseGeometry,
+    background: ShapeVisual,
+    surface: Arc<Surface>,
+    badge: ShapeVisual,
+    initials: Label,
+    initials_color: Color,
+    text_color: Color,
+    badge_border_color: Color,
+    diameter: f32,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct AvatarParams {
+    compositor: Compositor,
+    #[builder(setter(into))]
+    name: String,
+    #[builder(default, setter(strip_option))]
+    image: Option<Arc<dyn Panel>>,
+    #[builder(default = AvatarSize::Medium)]
+    size: AvatarSize,
+    #[builder(default, setter(strip_option))]
+    presence: Option<Presence>,
+    /// Preferred color of the initials, it's adjusted if it's not readable on the background
+    #[builder(default = Colors::White().unwrap())]
+    text_color: Color,
+    #[builder(default = Colors::White().unwrap())]
+    badge_border_color: Color,
+}
+
+impl TryFrom<AvatarParams> for Avatar {
+    type Error = crate::Error;
+
+    fn try_from(value: AvatarParams) -> crate::Result<Self> {
+        let compositor = value.compositor;
+        let container = compositor.CreateContainerVisual()?;
+        let body = compositor.CreateContainerVisual()?;
+        let clip_geometry = compositor.CreateEllipseGeometry()?;
+        body.SetClip(&compositor.CreateGeometricClipWithGeometry(&clip_geometry)?)?;
+        container.Children()?.InsertAtTop(&body)?;
+        let background = compositor.CreateShapeVisual()?;
+        body.Children()?.InsertAtBottom(&background)?;
+        let surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(compositor.clone())
+            .build()
+            .try_into()?;
+        attach(&body, &*surface)?;
+        if let Some(image) = &value.image {
+            attach(&body, &**image)?;
+        }
+        // Badge isn't clipped by the circle
+        let badge = compositor.CreateShapeVisual()?;
+        container.Children()?.InsertAtTop(&badge)?;
+        Ok(Avatar {
+            compositor,
+            container,
+            body,
+            clip_geometry,
+            background,
+            surface,
+            badge,
+            initials: Label::plain(&initials(&value.name)),
+            initials_color: initials_color(&value.name),
+            text_color: value.text_color,
+            badge_border_color: value.badge_border_color,
+            diameter: value.size.pixels(),
+            core: RwLock::new(Core {
+                image: value.image,
+                presence: value.presence,
+            }),
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<AvatarParams> for Arc<Avatar> {
+    type Error = crate::Error;
+
+    fn try_from(value: AvatarParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Avatar {
+    pub fn diameter(&self) -> f32 {
+        self.diameter
+    }
+
+    pub async fn presence(&self) -> Option<Presence> {
+        self.core.read().await.presence
+    }
+
+    pub async fn set_presence(&self, presence: Option<Presence>) -> crate::Result<()> {
+        self.core.write().await.presence = presence;
+        self.redraw_badge(presence)
+    }
+
+    /// Replaces the image, e.g. when it's loaded. Without image the initials are shown.
+    pub async fn set_image(&self, image: Option<Arc<dyn Panel>>) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        if let Some(old) = core.image.take() {
+            self.body.Children()?.Remove(&old.outer_frame())?;
+        }
+        if let Some(image) = &image {
+            attach(&self.body, &**image)?;
+            self.resize_image(image).await?;
+        }
+        self.surface.outer_frame().SetIsVisible(image.is_none())?;
+        core.image = image;
+        Ok(())
+    }
+
+    fn body_size(&self) -> Vector2 {
+        Vector2 {
+            X: self.diameter,
+            Y: self.diameter,
+        }
+    }
+
+    async fn resize_image(&self, image: &Arc<dyn Panel>) -> crate::Result<()> {
+        let size = self.body_size();
+        image.outer_frame().SetSize(size)?;
+        image.on_event_owned(PanelEvent::Resized(size), None).await
+    }
+
+    fn redraw_background(&self) -> crate::Result<()> {
+        let radius = self.diameter / 2.;
+        let center = Vector2 {
+            X: radius,
+            Y: radius,
+        };
+        self.clip_geometry.SetCenter(center)?;
+        self.clip_geometry.SetRadius(center)?;
+        self.background.SetSize(self.body_size())?;
+        let shapes = self.background.Shapes()?;
+        shapes.Clear()?;
+        let geometry = self.compositor.CreateEllipseGeometry()?;
+        geometry.SetCenter(center)?;
+        geometry.SetRadius(center)?;
+        let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        shape.SetFillBrush(
+            &self
+                .compositor
+                .CreateColorBrushWithColor(self.initials_color)?,
+        )?;
+        shapes.Append(&shape)?;
+        Ok(())
+    }
+
+    fn redraw_initials(&self) -> crate::Result<()> {
+        self.surface.draw(|context, size| {
+            let text_format = sized_text_format(self.diameter * 0.4)?;
+            let text_color = readable_foreground(self.initials_color, self.text_color);
+            unsafe { text_format.SetTextAlignment(DWRITE_TEXT_ALIGNMENT_CENTER)? };
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(text_color), Some(&brush_properties))
+            }?;
+            unsafe { context.Clear(Some(&d2d_color(Colors::Transparent()?))) };
+            self.initials.draw(
+                context,
+                &text_format,
+                D2D_RECT_F {
+                    left: 0.,
+                    top: 0.,
+                    right: size.X,
+                    bottom: size.Y,
+                },
+                &brush,
+            )
+        })
+    }
+
+    fn redraw_badge(&self, presence: Option<Presence>) -> crate::Result<()> {
+        let shapes = self.badge.Shapes()?;
+        shapes.Clear()?;
+        let presence = match presence {
+            Some(presence) => presence,
+            None => return Ok(()),
+        };
+        let side = (self.diameter * 0.3).max(8.);
+        let border = (side / 8.).max(1.);
+        self.badge.SetSize(Vector2 { X: side, Y: side })?;
+        let center = Vector2 {
+            X: side / 2.,
+            Y: side / 2.,
+        };
+        let geometry = self.compositor.CreateEllipseGeometry()?;
+        geometry.SetCenter(center)?;
+        geometry.SetRadius(Vector2 {
+            X: side / 2. - border / 2.,
+            Y: side / 2. - border / 2.,
+        })?;
+        let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        shape.SetFillBrush(
+            &self
+                .compositor
+                .CreateColorBrushWithColor(presence.color())?,
+        )?;
+        // The border separates the badge from the picture
+        shape.SetStrokeBrush(
+            &self
+                .compositor
+                .CreateColorBrushWithColor(self.badge_border_color)?,
+        )?;
+        shape.SetStrokeThickness(border)?;
+        shapes.Append(&shape)?;
+        Ok(())
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        let body_size = self.body_size();
+        let offset = Vector3 {
+            X: (size.X - self.diameter) / 2.,
+            Y: (size.Y - self.diameter) / 2.,
+            Z: 0.,
+        };
+        self.body.SetOffset(offset)?;
+        self.body.SetSize(body_size)?;
+        // The badge touches the circle at the bottom right, 45 degrees from the center
+        let side = (self.diameter * 0.3).max(8.);
+        let corner = self.diameter / 2. * (1. + std::f32::consts::FRAC_1_SQRT_2);
+        self.badge.SetOffset(Vector3 {
+            X: offset.X + corner - side / 2.,
+            Y: offset.Y + corner - side / 2.,
+            Z: 0.,
+        })?;
+        self.surface
+            .on_event_owned(PanelEvent::Resized(body_size), source)
+            .await?;
+        let (image, presence) = {
+            let core = self.core.read().await;
+            (core.image.clone(), core.presence)
+        };
+        self.surface.outer_frame().SetIsVisible(image.is_none())?;
+        if let Some(image) = &image {
+            self.resize_image(image).await?;
+        }
+        self.redraw_background()?;
+        self.redraw_initials()?;
+        self.redraw_badge(presence)
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Avatar {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.resize(*size, source.clone()).await?;
+        }
+        self.panel_events
+            .send_event(event.into_owned(), source)
+            .await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for Avatar {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for Avatar {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
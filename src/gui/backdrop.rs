@@ -0,0 +1,229 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::HSTRING,
+    Foundation::Numerics::Vector2,
+    Graphics::Effects::IGraphicsEffect,
+    UI::{
+        Color,
+        Composition::{
+            CompositionBrush, CompositionColorBrush, Compositor, ContainerVisual, SpriteVisual,
+            Visual,
+        },
+    },
+};
+
+use crate::color;
+
+use super::{
+    attach,
+    dispatch::DispatchQueue,
+    effect_graph::{source_parameter, D2D1Effect},
+    Panel, PanelEvent, Theme,
+};
+
+const BACKDROP: &str = "Backdrop";
+
+/// What the backdrop blurs
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum BackdropSource {
+    /// The window's content under the panel, e.g. for the popups and the sidebars over the page
+    #[default]
+    App,
+    ///
+    /// The desktop and the other windows behind the window, like the system's acrylic. The
+    /// window's content under the panel must be transparent to see it.
+    ///
+    Host,
+}
+
+struct Core {
+    tint_color: Color,
+    theme_color: Option<String>,
+}
+
+///
+/// Translucent material behind the content: the backdrop is blurred, saturated and covered
+/// with the tint color of the partial opacity, like the acrylic of the Windows sidebars and
+/// flyouts. The content panel is placed over the material and receives all the events.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Backdrop {
+    container: ContainerVisual,
+    blur: SpriteVisual,
+    tint: SpriteVisual,
+    tint_brush: CompositionColorBrush,
+    content: Arc<dyn Panel>,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct BackdropParams {
+    compositor: Compositor,
+    content: Arc<dyn Panel>,
+    #[builder(default)]
+    source: BackdropSource,
+    /// Standard deviation of the blur in pixels
+    #[builder(default = 30.)]
+    blur: f32,
+    /// Saturation of the blurred backdrop from 0 for grayscale to 1 for the original colors
+    #[builder(default = 1.)]
+    saturation: f32,
+    #[builder(default = color::from_argb(0xfff3f3f3))]
+    tint_color: Color,
+    /// Opacity of the tint layer, the lower one shows more of the backdrop
+    #[builder(default = 0.7)]
+    tint_opacity: f32,
+    /// Named color of the [`Theme`] replacing `tint_color` on `PanelEvent::ThemeChanged`
+    #[builder(default, setter(strip_option, into))]
+    theme_color: Option<String>,
+}
+
+impl TryFrom<BackdropParams> for Backdrop {
+    type Error = crate::Error;
+
+    fn try_from(value: BackdropParams) -> crate::Result<Self> {
+        let compositor = value.compositor;
+        let container = compositor.CreateContainerVisual()?;
+        let blurred = D2D1Effect::gaussian_blur("Blur", source_parameter(BACKDROP)?, value.blur)?;
+        let effect: IGraphicsEffect =
+            D2D1Effect::saturation("Saturation", blurred.into(), value.saturation)?.into();
+        let brush = compositor.CreateEffectFactory(&effect)?.CreateBrush()?;
+        let backdrop: CompositionBrush = match value.source {
+            BackdropSource::App => compositor.CreateBackdropBrush()?.into(),
+            BackdropSource::Host => compositor.CreateHostBackdropBrush()?.into(),
+        };
+        brush.SetSourceParameter(&HSTRING::from(BACKDROP), &backdrop)?;
+        let blur = compositor.CreateSpriteVisual()?;
+        blur.SetBrush(&brush)?;
+        let tint_brush = compositor.CreateColorBrushWithColor(value.tint_color)?;
+        let tint = compositor.CreateSpriteVisual()?;
+        tint.SetBrush(&tint_brush)?;
+        tint.SetOpacity(value.tint_opacity)?;
+        container.Children()?.InsertAtTop(&blur)?;
+        container.Children()?.InsertAtTop(&tint)?;
+        attach(&container, &*value.content)?;
+        Ok(Backdrop {
+            container,
+            blur,
+            tint,
+            tint_brush,
+            content: value.content,
+            core: RwLock::new(Core {
+                tint_color: value.tint_color,
+                theme_color: value.theme_color,
+            }),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<BackdropParams> for Arc<Backdrop> {
+    type Error = crate::Error;
+
+    fn try_from(value: BackdropParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Backdrop {
+    pub fn content(&self) -> Arc<dyn Panel> {
+        self.content.clone()
+    }
+
+    pub async fn tint_color(&self) -> Color {
+        self.core.read().await.tint_color
+    }
+
+    pub async fn set_tint_color(&self, color: Color) -> crate::Result<()> {
+        self.core.write().await.tint_color = color;
+        self.tint_brush.SetColor(color)?;
+        Ok(())
+    }
+
+    pub fn tint_opacity(&self) -> crate::Result<f32> {
+        Ok(self.tint.Opacity()?)
+    }
+
+    pub fn set_tint_opacity(&self, opacity: f32) -> crate::Result<()> {
+        self.tint.SetOpacity(opacity)?;
+        Ok(())
+    }
+
+    fn resize(&self, size: Vector2) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        self.blur.SetSize(size)?;
+        self.tint.SetSize(size)?;
+        Ok(())
+    }
+
+    async fn apply_theme(&self, theme: &Theme) -> crate::Result<()> {
+        let color = {
+            let core = self.core.read().await;
+            core.theme_color.as_deref().and_then(|v| theme.color(v))
+        };
+        match color {
+            Some(color) => self.set_tint_color(color).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size)?,
+            PanelEvent::ThemeChanged(theme) => self.apply_theme(theme).await?,
+            _ => (),
+        }
+        self.content.on_event_ref(&event, source.clone()).await?;
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for Backdrop {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Backdrop {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Backdrop {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
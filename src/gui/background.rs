@@ -9,59 +9,424 @@ use async_trait::async_trait;
 use float_ord::FloatOrd;
 use typed_builder::TypedBuilder;
 use windows::{
-    Foundation::Numerics::Vector2,
+    Foundation::{
+        Numerics::{Matrix3x2, Vector2},
+        Size,
+    },
+    Graphics::{
+        DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        SizeInt32,
+    },
+    Win32::Graphics::{
+        Direct2D::{
+            Common::{D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_PIXEL_FORMAT, D2D_RECT_F, D2D_SIZE_U},
+            D2D1_BITMAP_BRUSH_PROPERTIES1, D2D1_BITMAP_PROPERTIES1, D2D1_EXTEND_MODE,
+            D2D1_EXTEND_MODE_CLAMP, D2D1_EXTEND_MODE_WRAP, D2D1_INTERPOLATION_MODE_LINEAR,
+        },
+        Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM,
+    },
     UI::{
         Color,
-        Composition::{CompositionShape, Compositor, ContainerVisual, ShapeVisual, Visual},
+        Composition::{
+            CompositionBrush, CompositionDrawingSurface, CompositionGeometry,
+            CompositionGraphicsDevice, CompositionShape, CompositionStretch, CompositionStrokeCap,
+            Compositor, ContainerVisual, ShapeVisual, Visual,
+        },
     },
 };
 
-use super::{Panel, PanelEvent};
+use crate::{
+    geometry::Length,
+    window::{create_composition_graphics_device, draw, Bitmap},
+};
+
+#[cfg(feature = "accessibility")]
+use super::accessibility::{set_text_alternative, TextAlternative};
+use super::{Panel, PanelEvent, PathBuilder, Theme};
+
+/// Pattern of the stroke, the lengths of the dashes and gaps are in the stroke thicknesses
+#[derive(PartialEq, Clone, Debug, Default)]
+pub enum DashStyle {
+    #[default]
+    Solid,
+    Dash,
+    Dot,
+    DashDot,
+    /// Lengths of the dashes and gaps alternating, starting from the dash
+    Custom(Vec<f32>),
+}
+
+impl DashStyle {
+    pub(crate) fn dash_array(&self) -> &[f32] {
+        match self {
+            DashStyle::Solid => &[],
+            DashStyle::Dash => &[2., 2.],
+            // Round caps of zero-length dashes make the dots
+            DashStyle::Dot => &[0., 2.],
+            DashStyle::DashDot => &[2., 2., 0., 2.],
+            DashStyle::Custom(v) => v,
+        }
+    }
+}
+
+/// Outline of the background drawn inside its bounds, e.g. for the card borders or the focus rings
+#[derive(PartialEq, Clone, Debug)]
+pub struct Stroke {
+    pub color: Color,
+    ///
+    /// Thickness in pixels or in percent of the smaller side of the background, recalculated
+    /// on resize. `Length::Auto` is the one pixel hairline.
+    ///
+    pub thickness: Length,
+    pub dash: DashStyle,
+}
+
+impl Stroke {
+    pub fn new(color: Color, thickness: impl Into<Length>) -> Self {
+        Self {
+            color,
+            thickness: thickness.into(),
+            dash: DashStyle::Solid,
+        }
+    }
+    pub fn with_dash(self, dash: DashStyle) -> Self {
+        Self { dash, ..self }
+    }
+    fn pixels_for(&self, size: Vector2) -> f32 {
+        self.thickness.resolve(size.X.min(size.Y), 1.).max(0.)
+    }
+}
+
+/// Radius of the corner
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Radius {
+    Pixels(f32),
+    /// Fraction of the smaller side of the background, recalculated on resize
+    Relative(f32),
+}
+
+impl Default for Radius {
+    fn default() -> Self {
+        Radius::Pixels(0.)
+    }
+}
+
+impl Radius {
+    fn pixels(&self, size: Vector2) -> f32 {
+        match self {
+            Radius::Pixels(v) => *v,
+            Radius::Relative(v) => std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 * v,
+        }
+    }
+}
+
+/// Radii of the background's corners, square corners by default
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct CornerRadius {
+    pub top_left: Radius,
+    pub top_right: Radius,
+    pub bottom_right: Radius,
+    pub bottom_left: Radius,
+}
+
+impl CornerRadius {
+    pub const fn uniform(radius: Radius) -> Self {
+        Self {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+    pub const fn pixels(radius: f32) -> Self {
+        Self::uniform(Radius::Pixels(radius))
+    }
+    /// All corners rounded by the fraction of the smaller side, e.g. 0.05 for the buttons
+    pub const fn relative(fraction: f32) -> Self {
+        Self::uniform(Radius::Relative(fraction))
+    }
+    // Radii in pixels clockwise from the top left, limited by the half of the smaller side
+    fn pixels_for(&self, size: Vector2) -> [f32; 4] {
+        let max = std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 / 2.;
+        [
+            self.top_left,
+            self.top_right,
+            self.bottom_right,
+            self.bottom_left,
+        ]
+        .map(|v| v.pixels(size).clamp(0., max.max(0.)))
+    }
+}
+
+impl From<f32> for CornerRadius {
+    fn from(radius: f32) -> Self {
+        Self::pixels(radius)
+    }
+}
+
+/// How the image fills the background
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum ImageStretch {
+    /// Original size, centered
+    None,
+    /// Stretched to the background's size, the aspect ratio is not kept
+    Fill,
+    /// Scaled to fit inside the background, centered
+    Uniform,
+    /// Scaled to cover the whole background, centered and cropped
+    #[default]
+    UniformToFill,
+    /// Repeated in original size from the top left corner
+    Tile,
+}
+
+impl ImageStretch {
+    fn composition_stretch(&self) -> CompositionStretch {
+        match self {
+            ImageStretch::None | ImageStretch::Tile => CompositionStretch::None,
+            ImageStretch::Fill => CompositionStretch::Fill,
+            ImageStretch::Uniform => CompositionStretch::Uniform,
+            ImageStretch::UniformToFill => CompositionStretch::UniformToFill,
+        }
+    }
+}
+
+/// Image filling the background instead of the color, e.g. the texture or the photo
+#[derive(PartialEq, Clone, Debug)]
+pub struct BackgroundImage {
+    pub bitmap: Arc<Bitmap>,
+    pub stretch: ImageStretch,
+}
+
+impl BackgroundImage {
+    pub fn new(bitmap: impl Into<Arc<Bitmap>>, stretch: ImageStretch) -> Self {
+        Self {
+            bitmap: bitmap.into(),
+            stretch,
+        }
+    }
+    /// Reads the image file in any format supported by WIC
+    #[cfg(feature = "image")]
+    pub fn load(path: impl AsRef<std::path::Path>, stretch: ImageStretch) -> crate::Result<Self> {
+        Ok(Self::new(Bitmap::load(path)?, stretch))
+    }
+}
+
+// Fills the surface of the `size` with the image repeated or clamped at its edges
+fn draw_image(
+    surface: &CompositionDrawingSurface,
+    bitmap: &Bitmap,
+    size: Vector2,
+    extend_mode: D2D1_EXTEND_MODE,
+) -> crate::Result<()> {
+    // Direct2D takes the premultiplied colors, the bitmaps keep the straight ones
+    let mut pixels = bitmap.pixels.clone();
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        for v in &mut pixel[..3] {
+            *v = (*v as u32 * alpha / 255) as u8;
+        }
+    }
+    draw(surface, |context, offset| {
+        let properties = D2D1_BITMAP_PROPERTIES1 {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.,
+            dpiY: 96.,
+            ..Default::default()
+        };
+        let brush_properties = D2D1_BITMAP_BRUSH_PROPERTIES1 {
+            extendModeX: extend_mode,
+            extendModeY: extend_mode,
+            interpolationMode: D2D1_INTERPOLATION_MODE_LINEAR,
+        };
+        unsafe {
+            let image = context.CreateBitmap(
+                D2D_SIZE_U {
+                    width: bitmap.width,
+                    height: bitmap.height,
+                },
+                Some(pixels.as_ptr() as *const _),
+                bitmap.width * 4,
+                &properties,
+            )?;
+            let brush = context.CreateBitmapBrush(&image, Some(&brush_properties), None)?;
+            context.SetTransform(&Matrix3x2::translation(offset.x as f32, offset.y as f32));
+            context.Clear(None);
+            context.FillRectangle(
+                &D2D_RECT_F {
+                    left: 0.,
+                    top: 0.,
+                    right: size.X,
+                    bottom: size.Y,
+                },
+                &brush,
+            );
+            context.SetTransform(&Matrix3x2::identity());
+        }
+        Ok(())
+    })
+}
+
+// Rectangle of the size at (0,0) with the corners of different radii, clockwise from top left
+fn rounded_rect_path(
+    compositor: &Compositor,
+    size: Vector2,
+    radii: [f32; 4],
+) -> crate::Result<CompositionGeometry> {
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    let (right, bottom) = (size.X, size.Y);
+    let point = |x, y| Vector2 { X: x, Y: y };
+    let radius = |r| Vector2 { X: r, Y: r };
+    let path = PathBuilder::new()
+        .move_to(point(top_left, 0.))
+        .line_to(point(right - top_right, 0.))
+        .arc_to(point(right, top_right), radius(top_right), 0., false, true)
+        .line_to(point(right, bottom - bottom_right))
+        .arc_to(
+            point(right - bottom_right, bottom),
+            radius(bottom_right),
+            0.,
+            false,
+            true,
+        )
+        .line_to(point(bottom_left, bottom))
+        .arc_to(
+            point(0., bottom - bottom_left),
+            radius(bottom_left),
+            0.,
+            false,
+            true,
+        )
+        .line_to(point(0., top_left))
+        .arc_to(point(top_left, 0.), radius(top_left), 0., false, true)
+        .close();
+    Ok(path.geometry(compositor)?.into())
+}
 
 struct Core {
-    round_corners: bool,
+    corner_radius: CornerRadius,
     color: Color,
+    image: Option<BackgroundImage>,
+    stroke: Option<Stroke>,
+    theme_color: Option<String>,
     compositor: Compositor,
     container: ShapeVisual,
+    // Surface with the image, drawn once in the image's size or on each resize for the tiles
+    image_surface: Option<(CompositionGraphicsDevice, CompositionDrawingSurface)>,
 }
 
 impl Core {
     fn create_background_shape(
         compositor: &Compositor,
         size: Vector2,
-        round_corners: bool,
-        color: Color,
+        corner_radius: CornerRadius,
+        fill: &CompositionBrush,
+        stroke: Option<&Stroke>,
     ) -> crate::Result<CompositionShape> {
         let container_shape = compositor.CreateContainerShape()?;
-        let rect_geometry = compositor.CreateRoundedRectangleGeometry()?;
-        rect_geometry.SetSize(size)?;
-        if round_corners {
-            let size = rect_geometry.Size()?;
-            let radius = std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 / 20.;
+        // The stroke is centered on the geometry's edge, so the geometry is inset by
+        // the half of the stroke to keep the outline inside the bounds
+        let thickness = stroke.map_or(0., |v| v.pixels_for(size));
+        let inset = thickness / 2.;
+        let inner_size = Vector2 {
+            X: (size.X - inset * 2.).max(0.),
+            Y: (size.Y - inset * 2.).max(0.),
+        };
+        let radii = corner_radius
+            .pixels_for(size)
+            .map(|v| (v - inset).max(0.).min(inner_size.X.min(inner_size.Y) / 2.));
+        // The rounded rectangle is cheaper, the path is needed for the different corners
+        let geometry: CompositionGeometry = if radii.iter().all(|v| *v == radii[0]) {
+            let rect_geometry = compositor.CreateRoundedRectangleGeometry()?;
+            rect_geometry.SetSize(inner_size)?;
             rect_geometry.SetCornerRadius(Vector2 {
-                X: radius,
-                Y: radius,
+                X: radii[0],
+                Y: radii[0],
             })?;
+            rect_geometry.into()
         } else {
-            rect_geometry.SetCornerRadius(Vector2 { X: 0., Y: 0. })?;
+            rounded_rect_path(compositor, inner_size, radii)?
+        };
+        let rect = compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        rect.SetFillBrush(fill)?;
+        rect.SetOffset(Vector2 { X: inset, Y: inset })?;
+        if let Some(stroke) = stroke {
+            rect.SetStrokeBrush(&compositor.CreateColorBrushWithColor(stroke.color)?)?;
+            rect.SetStrokeThickness(thickness)?;
+            let dash_array = rect.StrokeDashArray()?;
+            for length in stroke.dash.dash_array() {
+                dash_array.Append(*length)?;
+            }
+            if stroke.dash != DashStyle::Solid {
+                rect.SetStrokeDashCap(CompositionStrokeCap::Round)?;
+            }
         }
-        let brush = compositor.CreateColorBrushWithColor(color)?;
-        let rect = compositor.CreateSpriteShapeWithGeometry(&rect_geometry)?;
-        rect.SetFillBrush(&brush)?;
-        rect.SetOffset(Vector2 { X: 0., Y: 0. })?;
         container_shape.Shapes()?.Append(&rect)?;
         let shape = container_shape.into();
         Ok(shape)
     }
-    fn redraw(&self) -> crate::Result<()> {
+    fn image_brush(
+        &mut self,
+        image: &BackgroundImage,
+        size: Vector2,
+    ) -> crate::Result<CompositionBrush> {
+        let (surface_size, extend_mode) = if image.stretch == ImageStretch::Tile {
+            (size, D2D1_EXTEND_MODE_WRAP)
+        } else {
+            let size = Vector2 {
+                X: image.bitmap.width as f32,
+                Y: image.bitmap.height as f32,
+            };
+            (size, D2D1_EXTEND_MODE_CLAMP)
+        };
+        let pixel_size = SizeInt32 {
+            Width: surface_size.X.ceil() as i32,
+            Height: surface_size.Y.ceil() as i32,
+        };
+        let surface = match &self.image_surface {
+            Some((_, surface)) if surface.SizeInt32()? == pixel_size => surface.clone(),
+            _ => {
+                let device = create_composition_graphics_device(&self.compositor)?;
+                let surface = device.CreateDrawingSurface(
+                    Size::default(),
+                    DirectXPixelFormat::B8G8R8A8UIntNormalized,
+                    DirectXAlphaMode::Premultiplied,
+                )?;
+                surface.Resize(pixel_size)?;
+                draw_image(&surface, &image.bitmap, surface_size, extend_mode)?;
+                self.image_surface = Some((device, surface.clone()));
+                surface
+            }
+        };
+        let brush = self.compositor.CreateSurfaceBrushWithSurface(&surface)?;
+        brush.SetStretch(image.stretch.composition_stretch())?;
+        if image.stretch == ImageStretch::Tile {
+            brush.SetHorizontalAlignmentRatio(0.)?;
+            brush.SetVerticalAlignmentRatio(0.)?;
+        }
+        Ok(brush.into())
+    }
+    fn redraw(&mut self) -> crate::Result<()> {
+        let size = self.container.Size()?;
+        let fill: CompositionBrush = match self.image.clone() {
+            Some(image) => self.image_brush(&image, size)?,
+            None => self
+                .compositor
+                .CreateColorBrushWithColor(self.color)?
+                .into(),
+        };
         self.container.Shapes()?.Clear()?;
         self.container
             .Shapes()?
             .Append(&Self::create_background_shape(
                 &self.compositor,
-                self.container.Size()?,
-                self.round_corners,
-                self.color,
+                size,
+                self.corner_radius,
+                &fill,
+                self.stroke.as_ref(),
             )?)?;
         Ok(())
     }
@@ -75,6 +440,28 @@ impl Core {
         self.redraw()?;
         Ok(())
     }
+    fn set_corner_radius(&mut self, corner_radius: CornerRadius) -> crate::Result<()> {
+        self.corner_radius = corner_radius;
+        self.redraw()?;
+        Ok(())
+    }
+    fn set_image(&mut self, image: Option<BackgroundImage>) -> crate::Result<()> {
+        self.image = image;
+        self.image_surface = None;
+        self.redraw()?;
+        Ok(())
+    }
+    fn set_stroke(&mut self, stroke: Option<Stroke>) -> crate::Result<()> {
+        self.stroke = stroke;
+        self.redraw()?;
+        Ok(())
+    }
+    fn apply_theme(&mut self, theme: &Theme) -> crate::Result<()> {
+        match self.theme_color.as_deref().and_then(|v| theme.color(v)) {
+            Some(color) if color != self.color => self.set_color(color),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(EventSink)]
@@ -88,8 +475,19 @@ pub struct Background {
 
 #[derive(TypedBuilder)]
 pub struct BackgroundParams {
-    round_corners: bool,
+    /// Radii of the corners, e.g. `CornerRadius::relative(0.05)` for the rounded buttons
+    #[builder(default, setter(into))]
+    corner_radius: CornerRadius,
     color: Color,
+    /// Image drawn instead of the color, none by default
+    #[builder(default, setter(strip_option))]
+    image: Option<BackgroundImage>,
+    /// Outline of the background, none by default
+    #[builder(default, setter(strip_option))]
+    stroke: Option<Stroke>,
+    /// Named color of the [`Theme`] replacing `color` on `PanelEvent::ThemeChanged`
+    #[builder(default, setter(strip_option, into))]
+    theme_color: Option<String>,
     compositor: Compositor,
 }
 
@@ -99,10 +497,14 @@ impl TryFrom<BackgroundParams> for Background {
     fn try_from(value: BackgroundParams) -> crate::Result<Self> {
         let container = value.compositor.CreateShapeVisual()?;
         let core = RwLock::new(Core {
-            round_corners: value.round_corners,
+            corner_radius: value.corner_radius,
             color: value.color,
+            image: value.image,
+            stroke: value.stroke,
+            theme_color: value.theme_color,
             compositor: value.compositor,
             container: container.clone(),
+            image_surface: None,
         });
         Ok(Background {
             container: container.into(),
@@ -129,6 +531,33 @@ impl Background {
         self.core.write().await.set_color(color)?;
         Ok(())
     }
+    pub async fn corner_radius(&self) -> CornerRadius {
+        self.core.read().await.corner_radius
+    }
+    pub async fn set_corner_radius(&self, corner_radius: CornerRadius) -> crate::Result<()> {
+        self.core.write().await.set_corner_radius(corner_radius)
+    }
+    pub async fn image(&self) -> Option<BackgroundImage> {
+        self.core.read().await.image.clone()
+    }
+    /// Sets the image filling the background, `None` returns to the color
+    pub async fn set_image(&self, image: Option<BackgroundImage>) -> crate::Result<()> {
+        self.core.write().await.set_image(image)
+    }
+    pub async fn stroke(&self) -> Option<Stroke> {
+        self.core.read().await.stroke.clone()
+    }
+    pub async fn set_stroke(&self, stroke: Option<Stroke>) -> crate::Result<()> {
+        self.core.write().await.set_stroke(stroke)
+    }
+    ///
+    /// Describes the background's image for the screen readers. The decorative background
+    /// hides its children from them too, so it's for the images without the content over them.
+    ///
+    #[cfg(feature = "accessibility")]
+    pub fn set_text_alternative(&self, alternative: &TextAlternative) -> crate::Result<()> {
+        set_text_alternative(&self.container.clone().into(), "Background", alternative)
+    }
 }
 
 #[async_trait]
@@ -155,8 +584,10 @@ impl EventSinkExt<PanelEvent> for Background {
         event: Cow<'a, PanelEvent>,
         source: Option<Arc<EventBox>>,
     ) -> crate::Result<()> {
-        if let PanelEvent::Resized(size) = event.as_ref() {
-            self.core.write().await.resize(*size)?;
+        match event.as_ref() {
+            PanelEvent::Resized(size) => self.core.write().await.resize(*size)?,
+            PanelEvent::ThemeChanged(theme) => self.core.write().await.apply_theme(theme)?,
+            _ => (),
         }
         self.panel_events
             .send_event(event.into_owned(), source)
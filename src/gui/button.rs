@@ -1,7 +1,8 @@
 use std::borrow::Cow;
 
-use super::{attach, Text, TextParams};
-use super::{Background, BackgroundParams, LayerStack, LayerStackParams, Panel, PanelEvent};
+#[cfg(feature = "accessibility")]
+use super::accessibility::{set_accessible, Accessible, AccessibleRole};
+use super::{attach, dispatch::DispatchQueue, Panel, PanelEvent};
 use async_event_streams::{
     EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
 };
@@ -9,24 +10,40 @@ use async_event_streams_derive::{self, EventSink};
 use async_std::sync::Arc;
 use async_std::sync::RwLock;
 use async_trait::async_trait;
-use futures::task::Spawn;
 use typed_builder::TypedBuilder;
+use windows::Foundation::Numerics::Vector2;
+use windows::UI::Colors;
 use windows::UI::Composition::Visual;
-use windows::UI::{
-    Color, Colors,
-    Composition::{Compositor, ContainerVisual},
-};
-use winit::event::{ElementState, MouseButton};
+use windows::UI::Composition::{CompositionBrush, Compositor, ContainerVisual, SpriteVisual};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+use crate::geometry::Rect;
 
+const FOCUS_RING_WIDTH: f32 = 2.;
+
+///
+/// Events of the button, sent both to the skin and to the button's event stream. Mouse
+/// and keyboard produce the same sequence: Space press/release works like mouse
+/// press/release over the button, Enter produces the complete click. Disabled button
+/// doesn't send Press, Release and hover changes. `FocusChanged` lets the skin show
+/// which button receives the keyboard input.
+///
 #[derive(PartialEq, Clone, Debug)]
 pub enum ButtonEvent {
     Press,
     Release(bool),
+    HoverChanged(bool),
+    EnabledChanged(bool),
+    FocusChanged(bool),
 }
 
 struct Core {
     skin: Arc<dyn ButtonSkin>,
     pressed: bool,
+    hover: bool,
+    focused: bool,
+    enabled: bool,
+    size: Vector2,
     button_events: Arc<EventStreams<ButtonEvent>>,
 }
 
@@ -37,6 +54,7 @@ pub struct Button {
     core: RwLock<Core>,
     panel_events: EventStreams<PanelEvent>,
     button_events: Arc<EventStreams<ButtonEvent>>,
+    dispatch: DispatchQueue<PanelEvent>,
     id: Arc<()>,
 }
 
@@ -52,12 +70,25 @@ impl TryFrom<ButtonParams> for Button {
 
     fn try_from(value: ButtonParams) -> crate::Result<Self> {
         let container = value.compositor.CreateContainerVisual()?;
+        #[cfg(feature = "accessibility")]
+        set_accessible(
+            &container.clone().into(),
+            &Accessible {
+                focusable: true,
+                automation_id: Some("Button".to_string()),
+                ..Accessible::new(AccessibleRole::Button)
+            },
+        )?;
         let skin = value.skin;
         attach(&container, &*skin)?;
         let button_events = Arc::new(EventStreams::new());
         let core = RwLock::new(Core {
             skin,
             pressed: false,
+            hover: false,
+            focused: false,
+            enabled: true,
+            size: Vector2::default(),
             button_events: button_events.clone(),
         });
         Ok(Button {
@@ -65,6 +96,7 @@ impl TryFrom<ButtonParams> for Button {
             core,
             panel_events: EventStreams::new(),
             button_events,
+            dispatch: DispatchQueue::new(),
             id: Arc::new(()),
         })
     }
@@ -79,19 +111,57 @@ impl TryFrom<ButtonParams> for Arc<Button> {
 }
 
 impl Core {
-    async fn press(&mut self, source: Option<Arc<EventBox>>) -> crate::Result<()> {
-        self.pressed = true;
-        let event = ButtonEvent::Press;
+    async fn send(&self, event: ButtonEvent, source: Option<Arc<EventBox>>) -> crate::Result<()> {
         self.skin.on_event_ref(&event, source.clone()).await?;
         self.button_events.send_event(event, source).await;
         Ok(())
     }
+    async fn set_hover(&mut self, hover: bool, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        if self.hover != hover {
+            self.hover = hover;
+            self.send(ButtonEvent::HoverChanged(hover), source).await?;
+        }
+        Ok(())
+    }
+    async fn set_focused(
+        &mut self,
+        focused: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if self.focused != focused {
+            self.focused = focused;
+            self.send(ButtonEvent::FocusChanged(focused), source)
+                .await?;
+        }
+        Ok(())
+    }
+    async fn press(&mut self, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.pressed = true;
+        self.send(ButtonEvent::Press, source).await
+    }
     async fn release(&mut self, in_slot: bool, source: Option<Arc<EventBox>>) -> crate::Result<()> {
         self.pressed = false;
-        let event = ButtonEvent::Release(in_slot);
-        self.skin.on_event_ref(&event, source.clone()).await?;
-        self.button_events.send_event(event, source).await;
-        Ok(())
+        self.send(ButtonEvent::Release(in_slot), source).await
+    }
+    async fn set_enabled(
+        &mut self,
+        enabled: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if self.enabled == enabled {
+            return Ok(());
+        }
+        if !enabled {
+            // The press in progress is cancelled as the release outside of the button
+            if self.pressed {
+                self.release(false, source.clone()).await?;
+            }
+            self.set_hover(false, source.clone()).await?;
+            self.set_focused(false, source.clone()).await?;
+        }
+        self.enabled = enabled;
+        self.send(ButtonEvent::EnabledChanged(enabled), source)
+            .await
     }
     fn is_pressed(&self) -> bool {
         self.pressed
@@ -120,29 +190,105 @@ impl EventSinkExt<PanelEvent> for Button {
         &'a self,
         event: Cow<'a, PanelEvent>,
         source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Button {
+    pub async fn is_enabled(&self) -> bool {
+        self.core.read().await.enabled
+    }
+    /// Disabled button ignores the input and its skin shows the disabled state
+    pub async fn set_enabled(&self, enabled: bool) -> crate::Result<()> {
+        self.core.write().await.set_enabled(enabled, None).await
+    }
+    pub async fn is_focused(&self) -> bool {
+        self.core.read().await.focused
+    }
+    ///
+    /// Sets the keyboard focus. The button gets focus when pressed by mouse and loses it
+    /// when the mouse is pressed outside of it. Disabled button can't get focus.
+    ///
+    pub async fn set_focused(&self, focused: bool) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        let focused = focused && core.enabled;
+        core.set_focused(focused, None).await
+    }
+
+    async fn key_input(
+        &self,
+        state: ElementState,
+        key: VirtualKeyCode,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        if !core.focused || !core.enabled {
+            return Ok(());
+        }
+        match (key, state) {
+            // Autorepeat of the pressed key doesn't produce new presses
+            (VirtualKeyCode::Space, ElementState::Pressed) if !core.is_pressed() => {
+                core.press(source).await?
+            }
+            (VirtualKeyCode::Space, ElementState::Released) if core.is_pressed() => {
+                core.release(true, source).await?
+            }
+            (VirtualKeyCode::Return, ElementState::Pressed) if !core.is_pressed() => {
+                core.press(source.clone()).await?;
+                core.release(true, source).await?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
     ) -> crate::Result<()> {
         let skin = self.core.read().await.skin_panel();
-        skin.on_event_ref(event.as_ref(), source.clone()).await?;
+        skin.on_event_ref(&event, source.clone()).await?;
         self.panel_events
-            .send_event(event.clone().into_owned(), source.clone())
+            .send_event(event.clone(), source.clone())
             .await;
-        match event.as_ref() {
+        match event {
+            PanelEvent::Resized(size) => self.core.write().await.size = size,
+            PanelEvent::CursorMoved(pos) => {
+                let mut core = self.core.write().await;
+                let hover = core.enabled && Rect::from_size(core.size).contains(pos);
+                core.set_hover(hover, source.clone()).await?;
+            }
+            PanelEvent::KeyboardInput {
+                state,
+                key: Some(key),
+                ..
+            } => self.key_input(state, key, source.clone()).await?,
             PanelEvent::MouseInput {
                 in_slot,
                 state,
                 button,
+                ..
             } => {
-                if *button == MouseButton::Left {
-                    if *state == ElementState::Pressed {
-                        if *in_slot {
-                            self.core.write().await.press(source.clone()).await?;
+                if button == MouseButton::Left {
+                    if state == ElementState::Pressed {
+                        let mut core = self.core.write().await;
+                        let focused = in_slot && core.enabled;
+                        core.set_focused(focused, source.clone()).await?;
+                        if focused {
+                            core.press(source.clone()).await?;
                         }
-                    } else if *state == ElementState::Released {
+                    } else if state == ElementState::Released {
                         if self.core.read().await.is_pressed() {
                             self.core
                                 .write()
                                 .await
-                                .release(*in_slot, source.clone())
+                                .release(in_slot, source.clone())
                                 .await?;
                         }
                     }
@@ -166,101 +312,18 @@ impl Panel for Button {
 pub trait ButtonSkin: Panel + EventSink<ButtonEvent, Error = crate::Error> {}
 impl<T: Panel + EventSink<ButtonEvent, Error = crate::Error>> ButtonSkin for T {}
 
-#[derive(EventSink)]
-#[event_sink(event=PanelEvent)]
-#[event_sink(event=ButtonEvent)]
-pub struct SimpleButtonSkin {
-    layer_stack: LayerStack,
-    text: Arc<Text>,
-    background: Arc<Background>,
-    panel_events: EventStreams<PanelEvent>,
-}
-
-#[derive(TypedBuilder)]
-pub struct SimpleButtonSkinParams<T: Spawn> {
-    compositor: Compositor,
-    text: String,
-    color: Color,
-    spawner: T,
-}
-
-impl<T: Spawn> TryFrom<SimpleButtonSkinParams<T>> for SimpleButtonSkin {
-    type Error = crate::Error;
-    fn try_from(value: SimpleButtonSkinParams<T>) -> crate::Result<Self> {
-        let background: Arc<Background> = BackgroundParams::builder()
-            .color(value.color)
-            .round_corners(true)
-            .compositor(value.compositor.clone())
-            .build()
-            .try_into()?;
-        let text: Arc<Text> = TextParams::builder()
-            .compositor(value.compositor.clone())
-            .text(value.text)
-            .spawner(value.spawner)
-            .build()
-            .try_into()?;
-        let layer_stack = LayerStackParams::builder()
-            .compositor(value.compositor.clone())
-            .build()
-            .push_panel(background.clone())
-            .push_panel(text.clone())
-            .try_into()?;
-        Ok(SimpleButtonSkin {
-            layer_stack,
-            background,
-            text,
-            panel_events: EventStreams::new(),
-        })
-    }
-}
-
-impl<T: Spawn> TryFrom<SimpleButtonSkinParams<T>> for Arc<SimpleButtonSkin> {
-    type Error = crate::Error;
-
-    fn try_from(value: SimpleButtonSkinParams<T>) -> crate::Result<Self> {
-        Ok(Arc::new(value.try_into()?))
-    }
-}
-
-#[async_trait]
-impl EventSinkExt<ButtonEvent> for SimpleButtonSkin {
-    type Error = crate::Error;
-    async fn on_event<'a>(
-        &'a self,
-        event: Cow<'a, ButtonEvent>,
-        _: Option<Arc<EventBox>>,
-    ) -> crate::Result<()> {
-        match event.as_ref() {
-            ButtonEvent::Press => self.background.set_color(Colors::DarkMagenta()?).await?,
-            ButtonEvent::Release(_) => self.background.set_color(Colors::Magenta()?).await?,
-        }
-        Ok(())
-    }
-}
-
-#[async_trait]
-impl EventSinkExt<PanelEvent> for SimpleButtonSkin {
-    type Error = crate::Error;
-    async fn on_event<'a>(
-        &'a self,
-        event: Cow<'a, PanelEvent>,
-        source: Option<Arc<EventBox>>,
-    ) -> crate::Result<()> {
-        self.layer_stack.on_event(event, source).await
-    }
-}
-
-impl EventSource<PanelEvent> for SimpleButtonSkin {
-    fn event_stream(&self) -> EventStream<PanelEvent> {
-        self.panel_events.create_event_stream()
-    }
-}
-
-impl Panel for SimpleButtonSkin {
-    fn outer_frame(&self) -> Visual {
-        self.layer_stack.outer_frame()
-    }
-    fn id(&self) -> usize {
-        Arc::as_ptr(&self.text) as usize
-    }
+/// Border over the whole skin, hidden until the button gets focus
+pub(super) fn create_focus_ring(compositor: &Compositor) -> crate::Result<SpriteVisual> {
+    let brush = compositor.CreateNineGridBrush()?;
+    let color_brush: CompositionBrush = compositor
+        .CreateColorBrushWithColor(Colors::Black()?)?
+        .into();
+    brush.SetSource(&color_brush)?;
+    brush.SetInsets(FOCUS_RING_WIDTH)?;
+    brush.SetIsCenterHollow(true)?;
+    let sprite = compositor.CreateSpriteVisual()?;
+    sprite.SetBrush(&brush)?;
+    sprite.SetRelativeSizeAdjustment(Vector2 { X: 1., Y: 1. })?;
+    sprite.SetIsVisible(false)?;
+    Ok(sprite)
 }
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::{Numerics::Vector2, Size},
+    Graphics::{
+        DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        SizeInt32,
+    },
+    UI::Composition::{
+        CompositionDrawingSurface, CompositionGraphicsDevice, CompositionStretch, Compositor,
+        ContainerVisual, SpriteVisual, Visual,
+    },
+};
+
+use crate::window::{capture_visual_to_surface, create_composition_graphics_device};
+
+use super::{attach, dispatch::DispatchQueue, Panel, PanelEvent};
+
+struct Core {
+    // The image is rendered at the current size and not invalidated since
+    valid: bool,
+}
+
+///
+/// Container showing the image of its child rendered once instead of composing the child's
+/// visuals each frame, for the complex static decorations like the skinned backgrounds. The
+/// child is rendered on the first resize and again on each `invalidate` call, which the
+/// application makes after changing the child. The child receives all the events, but its
+/// visuals are not shown directly, so the changes of them and their animations appear only
+/// after the invalidation.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct CacheVisual {
+    container: SpriteVisual,
+    // Container of the child outside of the tree
+    source: ContainerVisual,
+    _composition_graphic_device: CompositionGraphicsDevice,
+    surface: CompositionDrawingSurface,
+    child: Arc<dyn Panel>,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct CacheVisualParams {
+    compositor: Compositor,
+    child: Arc<dyn Panel>,
+}
+
+impl TryFrom<CacheVisualParams> for CacheVisual {
+    type Error = crate::Error;
+
+    fn try_from(value: CacheVisualParams) -> crate::Result<Self> {
+        let compositor = value.compositor;
+        let container = compositor.CreateSpriteVisual()?;
+        let source = compositor.CreateContainerVisual()?;
+        attach(&source, &*value.child)?;
+        let composition_graphic_device = create_composition_graphics_device(&compositor)?;
+        let surface = composition_graphic_device.CreateDrawingSurface(
+            Size::default(),
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            DirectXAlphaMode::Premultiplied,
+        )?;
+        let brush = compositor.CreateSurfaceBrushWithSurface(&surface)?;
+        // The image has the pixel size of the container
+        brush.SetStretch(CompositionStretch::None)?;
+        brush.SetHorizontalAlignmentRatio(0.)?;
+        brush.SetVerticalAlignmentRatio(0.)?;
+        container.SetBrush(&brush)?;
+        Ok(CacheVisual {
+            container,
+            source,
+            _composition_graphic_device: composition_graphic_device,
+            surface,
+            child: value.child,
+            core: RwLock::new(Core { valid: false }),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<CacheVisualParams> for Arc<CacheVisual> {
+    type Error = crate::Error;
+
+    fn try_from(value: CacheVisualParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl CacheVisual {
+    pub fn child(&self) -> Arc<dyn Panel> {
+        self.child.clone()
+    }
+
+    /// Whether the shown image is up to date
+    pub async fn is_valid(&self) -> bool {
+        self.core.read().await.valid
+    }
+
+    ///
+    /// Renders the child again. Waits for the compositor to render the child's pending
+    /// changes, so the child should finish its redraw before.
+    ///
+    pub async fn invalidate(&self) -> crate::Result<()> {
+        self.core.write().await.valid = false;
+        let size = self.surface.SizeInt32()?;
+        if size.Width <= 0 || size.Height <= 0 {
+            return Ok(());
+        }
+        capture_visual_to_surface(&self.source.clone().into(), &self.surface).await?;
+        self.core.write().await.valid = true;
+        Ok(())
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        self.source.SetSize(size)?;
+        self.child.outer_frame().SetSize(size)?;
+        self.surface.Resize(SizeInt32 {
+            Width: size.X.ceil() as i32,
+            Height: size.Y.ceil() as i32,
+        })?;
+        self.child
+            .on_event_owned(PanelEvent::Resized(size), source)
+            .await?;
+        self.invalidate().await
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            _ => self.child.on_event_ref(&event, source.clone()).await?,
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for CacheVisual {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for CacheVisual {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for CacheVisual {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
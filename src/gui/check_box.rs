@@ -0,0 +1,386 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use float_ord::FloatOrd;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color, Colors,
+        Composition::{CompositionStrokeCap, Compositor, ContainerVisual, ShapeVisual, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton};
+
+use super::{attach, dispatch::DispatchQueue, Panel, PanelEvent};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CheckState {
+    Unchecked,
+    Checked,
+    Indeterminate,
+}
+
+impl Default for CheckState {
+    fn default() -> Self {
+        CheckState::Unchecked
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum CheckBoxEvent {
+    Toggled(CheckState),
+}
+
+struct Core {
+    skin: Arc<dyn CheckBoxSkin>,
+    state: CheckState,
+    three_state: bool,
+    pressed: bool,
+    check_box_events: Arc<EventStreams<CheckBoxEvent>>,
+}
+
+impl Core {
+    fn next_state(&self) -> CheckState {
+        match self.state {
+            CheckState::Unchecked => CheckState::Checked,
+            CheckState::Checked if self.three_state => CheckState::Indeterminate,
+            CheckState::Checked | CheckState::Indeterminate => CheckState::Unchecked,
+        }
+    }
+    async fn set_state(
+        &mut self,
+        state: CheckState,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if self.state == state {
+            return Ok(());
+        }
+        self.state = state;
+        let event = CheckBoxEvent::Toggled(state);
+        self.skin.on_event_ref(&event, source.clone()).await?;
+        self.check_box_events.send_event(event, source).await;
+        Ok(())
+    }
+    fn skin_panel(&self) -> Arc<dyn CheckBoxSkin> {
+        self.skin.clone()
+    }
+}
+
+///
+/// Check box with checked, unchecked and, if `three_state` is set, indeterminate states.
+/// The state is changed by mouse click or by [`CheckBox::toggle`], which is intended
+/// to be called by keyboard handlers.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct CheckBox {
+    container: ContainerVisual,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    check_box_events: Arc<EventStreams<CheckBoxEvent>>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct CheckBoxParams {
+    compositor: Compositor,
+    #[builder(setter(transform = |skin: impl CheckBoxSkin + 'static | Arc::new(skin) as Arc<dyn CheckBoxSkin>))]
+    skin: Arc<dyn CheckBoxSkin>,
+    #[builder(default = false)]
+    three_state: bool,
+}
+
+impl TryFrom<CheckBoxParams> for CheckBox {
+    type Error = crate::Error;
+
+    fn try_from(value: CheckBoxParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let skin = value.skin;
+        attach(&container, &*skin)?;
+        let check_box_events = Arc::new(EventStreams::new());
+        let core = RwLock::new(Core {
+            skin,
+            state: CheckState::Unchecked,
+            three_state: value.three_state,
+            pressed: false,
+            check_box_events: check_box_events.clone(),
+        });
+        Ok(CheckBox {
+            container,
+            core,
+            panel_events: EventStreams::new(),
+            check_box_events,
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<CheckBoxParams> for Arc<CheckBox> {
+    type Error = crate::Error;
+
+    fn try_from(value: CheckBoxParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl CheckBox {
+    pub async fn state(&self) -> CheckState {
+        self.core.read().await.state
+    }
+    pub async fn set_state(&self, state: CheckState) -> crate::Result<()> {
+        self.core.write().await.set_state(state, None).await
+    }
+    /// Switches to the next state: unchecked -> checked (-> indeterminate) -> unchecked
+    pub async fn toggle(&self) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        let state = core.next_state();
+        core.set_state(state, None).await
+    }
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let skin = self.core.read().await.skin_panel();
+        skin.on_event_ref(&event, source.clone()).await?;
+        self.panel_events
+            .send_event(event.clone(), source.clone())
+            .await;
+        if let PanelEvent::MouseInput {
+            in_slot,
+            state,
+            button: MouseButton::Left,
+            ..
+        } = event
+        {
+            let mut core = self.core.write().await;
+            if state == ElementState::Pressed {
+                core.pressed = in_slot;
+            } else if state == ElementState::Released && core.pressed {
+                core.pressed = false;
+                if in_slot {
+                    let state = core.next_state();
+                    core.set_state(state, source).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EventSource<CheckBoxEvent> for CheckBox {
+    fn event_stream(&self) -> EventStream<CheckBoxEvent> {
+        self.check_box_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for CheckBox {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for CheckBox {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for CheckBox {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+pub trait CheckBoxSkin: Panel + EventSink<CheckBoxEvent, Error = crate::Error> {}
+impl<T: Panel + EventSink<CheckBoxEvent, Error = crate::Error>> CheckBoxSkin for T {}
+
+struct SkinCore {
+    compositor: Compositor,
+    visual: ShapeVisual,
+    color: Color,
+    mark_color: Color,
+    state: CheckState,
+}
+
+impl SkinCore {
+    fn redraw(&self) -> crate::Result<()> {
+        let compositor = &self.compositor;
+        let shapes = self.visual.Shapes()?;
+        shapes.Clear()?;
+        let size = self.visual.Size()?;
+        let side = std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0 * 0.8;
+        let offset = Vector2 {
+            X: (size.X - side) / 2.,
+            Y: (size.Y - side) / 2.,
+        };
+        let point = |x: f32, y: f32| Vector2 {
+            X: offset.X + side * x,
+            Y: offset.Y + side * y,
+        };
+
+        let box_geometry = compositor.CreateRoundedRectangleGeometry()?;
+        box_geometry.SetSize(Vector2 { X: side, Y: side })?;
+        box_geometry.SetCornerRadius(Vector2 {
+            X: side / 8.,
+            Y: side / 8.,
+        })?;
+        let box_shape = compositor.CreateSpriteShapeWithGeometry(&box_geometry)?;
+        box_shape.SetOffset(offset)?;
+        box_shape.SetStrokeBrush(&compositor.CreateColorBrushWithColor(self.color)?)?;
+        box_shape.SetStrokeThickness(side / 10.)?;
+        if self.state != CheckState::Unchecked {
+            box_shape.SetFillBrush(&compositor.CreateColorBrushWithColor(self.color)?)?;
+        }
+        shapes.Append(&box_shape)?;
+
+        let mark_brush = compositor.CreateColorBrushWithColor(self.mark_color)?;
+        let append_line = |start: Vector2, end: Vector2| -> crate::Result<()> {
+            let line = compositor.CreateLineGeometry()?;
+            line.SetStart(start)?;
+            line.SetEnd(end)?;
+            let shape = compositor.CreateSpriteShapeWithGeometry(&line)?;
+            shape.SetStrokeBrush(&mark_brush)?;
+            shape.SetStrokeThickness(side / 8.)?;
+            shape.SetStrokeStartCap(CompositionStrokeCap::Round)?;
+            shape.SetStrokeEndCap(CompositionStrokeCap::Round)?;
+            shapes.Append(&shape)?;
+            Ok(())
+        };
+        match self.state {
+            CheckState::Unchecked => {}
+            CheckState::Checked => {
+                append_line(point(0.22, 0.52), point(0.42, 0.72))?;
+                append_line(point(0.42, 0.72), point(0.78, 0.3))?;
+            }
+            CheckState::Indeterminate => {
+                append_line(point(0.25, 0.5), point(0.75, 0.5))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Default check box skin: a square box drawn with composition shapes, filled and marked
+/// with a check sign or a bar depending on the state
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+#[event_sink(event=CheckBoxEvent)]
+pub struct SimpleCheckBoxSkin {
+    visual: ShapeVisual,
+    core: RwLock<SkinCore>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct SimpleCheckBoxSkinParams {
+    compositor: Compositor,
+    color: Color,
+    #[builder(default = Colors::White().unwrap())]
+    mark_color: Color,
+}
+
+impl TryFrom<SimpleCheckBoxSkinParams> for SimpleCheckBoxSkin {
+    type Error = crate::Error;
+    fn try_from(value: SimpleCheckBoxSkinParams) -> crate::Result<Self> {
+        let visual = value.compositor.CreateShapeVisual()?;
+        let core = RwLock::new(SkinCore {
+            compositor: value.compositor,
+            visual: visual.clone(),
+            color: value.color,
+            mark_color: value.mark_color,
+            state: CheckState::Unchecked,
+        });
+        Ok(SimpleCheckBoxSkin {
+            visual,
+            core,
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<SimpleCheckBoxSkinParams> for Arc<SimpleCheckBoxSkin> {
+    type Error = crate::Error;
+
+    fn try_from(value: SimpleCheckBoxSkinParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<CheckBoxEvent> for SimpleCheckBoxSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, CheckBoxEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match event.as_ref() {
+            CheckBoxEvent::Toggled(state) => {
+                let mut core = self.core.write().await;
+                core.state = *state;
+                core.redraw()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for SimpleCheckBoxSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.visual.SetSize(*size)?;
+            self.core.read().await.redraw()?;
+        }
+        self.panel_events
+            .send_event(event.into_owned(), source)
+            .await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for SimpleCheckBoxSkin {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for SimpleCheckBoxSkin {
+    fn outer_frame(&self) -> Visual {
+        self.visual.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
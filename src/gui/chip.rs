@@ -0,0 +1,798 @@
+use std::{borrow::Cow, time::Duration};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::{
+        Numerics::{Matrix3x2, Vector2, Vector3},
+        TypedEventHandler,
+    },
+    Win32::Graphics::Direct2D::{Common::D2D_RECT_F, D2D1_BRUSH_PROPERTIES},
+    UI::{
+        Color, Colors,
+        Composition::{CompositionBatchTypes, Compositor, ContainerVisual, ShapeVisual, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton};
+
+use crate::geometry::{Point, Rect, Size};
+
+use super::{
+    animation_stepper::start_animation,
+    attach,
+    dispatch::DispatchQueue,
+    menu::{d2d_color, text_format, Label},
+    time_span, EventSeqId, Panel, PanelEvent, Surface, SurfaceParams,
+};
+
+const PADDING: f32 = 12.;
+const INSET: f32 = 4.;
+const DELETE_GLYPH: &str = "\u{2715}";
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ChipEvent {
+    Click,
+    /// The delete button of the chip is clicked
+    DeleteRequested,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Part {
+    Body,
+    Delete,
+}
+
+struct Core {
+    mouse_pos: Option<Vector2>,
+    hover: Option<Part>,
+    pressed: Option<Part>,
+}
+
+///
+/// Rounded label with optional icon on the left and delete button on the right, used for tags
+/// and filters. The chip has its own natural size `width()` x `height()` defined by the text,
+/// it's drawn at the top left corner of the area given to it.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Chip {
+    compositor: Compositor,
+    container: ContainerVisual,
+    shapes: ShapeVisual,
+    surface: Arc<Surface>,
+    icon: Option<Arc<dyn Panel>>,
+    text: String,
+    label: Label,
+    text_width: f32,
+    height: f32,
+    deletable: bool,
+    color: Color,
+    hover_color: Color,
+    text_color: Color,
+    core: RwLock<Core>,
+    chip_events: EventStreams<ChipEvent>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ChipParams {
+    compositor: Compositor,
+    #[builder(setter(into))]
+    text: String,
+    #[builder(default, setter(strip_option))]
+    icon: Option<Arc<dyn Panel>>,
+    #[builder(default = true)]
+    deletable: bool,
+    #[builder(default = 32.)]
+    height: f32,
+    #[builder(default = Colors::Gainsboro().unwrap())]
+    color: Color,
+    #[builder(default = Colors::LightGray().unwrap())]
+    hover_color: Color,
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+}
+
+impl TryFrom<ChipParams> for Chip {
+    type Error = crate::Error;
+
+    fn try_from(value: ChipParams) -> crate::Result<Self> {
+        let compositor = value.compositor;
+        let container = compositor.CreateContainerVisual()?;
+        let shapes = compositor.CreateShapeVisual()?;
+        container.Children()?.InsertAtBottom(&shapes)?;
+        if let Some(icon) = &value.icon {
+            attach(&container, &**icon)?;
+        }
+        let surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(compositor.clone())
+            .build()
+            .try_into()?;
+        attach(&container, &*surface)?;
+        let label = Label::plain(&value.text);
+        let text_width = label.width(&text_format()?)?;
+        Ok(Chip {
+            compositor,
+            container,
+            shapes,
+            surface,
+            icon: value.icon,
+            text: value.text,
+            label,
+            text_width,
+            height: value.height,
+            deletable: value.deletable,
+            color: value.color,
+            hover_color: value.hover_color,
+            text_color: value.text_color,
+            core: RwLock::new(Core {
+                mouse_pos: None,
+                hover: None,
+                pressed: None,
+            }),
+            chip_events: EventStreams::new(),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ChipParams> for Arc<Chip> {
+    type Error = crate::Error;
+
+    fn try_from(value: ChipParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Chip {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn width(&self) -> f32 {
+        self.text_left() + self.text_width + if self.deletable { self.height } else { PADDING }
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    fn size(&self) -> Vector2 {
+        Vector2 {
+            X: self.width(),
+            Y: self.height,
+        }
+    }
+
+    fn text_left(&self) -> f32 {
+        if self.icon.is_some() {
+            self.height
+        } else {
+            PADDING
+        }
+    }
+
+    fn icon_rect(&self) -> Rect {
+        Rect::new(
+            Point::new(INSET, INSET),
+            Size::new(self.height - INSET * 2., self.height - INSET * 2.),
+        )
+    }
+
+    fn delete_rect(&self) -> Rect {
+        Rect::new(
+            Point::new(self.width() - self.height + INSET, INSET),
+            Size::new(self.height - INSET * 2., self.height - INSET * 2.),
+        )
+    }
+
+    fn part_at(&self, pos: Vector2) -> Option<Part> {
+        if self.deletable && self.delete_rect().contains(pos) {
+            Some(Part::Delete)
+        } else if Rect::from_size(self.size()).contains(pos) {
+            Some(Part::Body)
+        } else {
+            None
+        }
+    }
+
+    fn redraw_shapes(&self, hover: Option<Part>) -> crate::Result<()> {
+        let shapes = self.shapes.Shapes()?;
+        shapes.Clear()?;
+        let geometry = self.compositor.CreateRoundedRectangleGeometry()?;
+        geometry.SetSize(self.size())?;
+        geometry.SetCornerRadius(Vector2 {
+            X: self.height / 2.,
+            Y: self.height / 2.,
+        })?;
+        let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        let color = if hover.is_some() {
+            self.hover_color
+        } else {
+            self.color
+        };
+        shape.SetFillBrush(&self.compositor.CreateColorBrushWithColor(color)?)?;
+        shapes.Append(&shape)?;
+        if hover == Some(Part::Delete) {
+            let rect = self.delete_rect();
+            let geometry = self.compositor.CreateEllipseGeometry()?;
+            geometry.SetCenter(rect.center().into())?;
+            geometry.SetRadius(Size::new(rect.size.width / 2., rect.size.height / 2.).into())?;
+            let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+            // Translucent text color keeps the highlight visible on any chip color
+            let color = Color {
+                A: 0x30,
+                ..self.text_color
+            };
+            shape.SetFillBrush(&self.compositor.CreateColorBrushWithColor(color)?)?;
+            shapes.Append(&shape)?;
+        }
+        Ok(())
+    }
+
+    fn redraw_text(&self) -> crate::Result<()> {
+        self.surface.draw(|context, _| {
+            let text_format = text_format()?;
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(self.text_color), Some(&brush_properties))
+            }?;
+            unsafe { context.Clear(Some(&d2d_color(Colors::Transparent()?))) };
+            let left = self.text_left();
+            self.label.draw(
+                context,
+                &text_format,
+                D2D_RECT_F {
+                    left,
+                    top: 0.,
+                    right: left + self.text_width,
+                    bottom: self.height,
+                },
+                &brush,
+            )?;
+            if self.deletable {
+                let glyph = Label::plain(DELETE_GLYPH);
+                let rect = self.delete_rect();
+                let left = rect.left() + (rect.size.width - glyph.width(&text_format)?) / 2.;
+                glyph.draw(
+                    context,
+                    &text_format,
+                    D2D_RECT_F {
+                        left,
+                        top: rect.top(),
+                        right: rect.right(),
+                        bottom: rect.bottom(),
+                    },
+                    &brush,
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn resize(&self, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        let size = self.size();
+        self.container.SetSize(size)?;
+        self.shapes.SetSize(size)?;
+        self.surface
+            .on_event_owned(PanelEvent::Resized(size), source.clone())
+            .await?;
+        if let Some(icon) = &self.icon {
+            let rect = self.icon_rect();
+            let frame = icon.outer_frame();
+            frame.SetOffset(rect.origin.into())?;
+            frame.SetSize(rect.size.into())?;
+            icon.on_event_owned(PanelEvent::Resized(rect.size.into()), source)
+                .await?;
+        }
+        let hover = self.core.read().await.hover;
+        self.redraw_shapes(hover)?;
+        self.redraw_text()
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(_) => self.resize(source.clone()).await?,
+            PanelEvent::CursorMoved(pos) => {
+                let hover = self.part_at(*pos);
+                let changed = {
+                    let mut core = self.core.write().await;
+                    core.mouse_pos = Some(*pos);
+                    let changed = core.hover != hover;
+                    core.hover = hover;
+                    changed
+                };
+                if changed {
+                    self.redraw_shapes(hover)?;
+                }
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let click = {
+                    let mut core = self.core.write().await;
+                    let part = core.mouse_pos.and_then(|pos| self.part_at(pos));
+                    match state {
+                        ElementState::Pressed if *in_slot => {
+                            core.pressed = part;
+                            None
+                        }
+                        ElementState::Released => match core.pressed.take() {
+                            Some(pressed) if *in_slot && part == Some(pressed) => Some(pressed),
+                            _ => None,
+                        },
+                        _ => None,
+                    }
+                };
+                match click {
+                    Some(Part::Body) => {
+                        self.chip_events
+                            .send_event(ChipEvent::Click, source.clone())
+                            .await
+                    }
+                    Some(Part::Delete) => {
+                        self.chip_events
+                            .send_event(ChipEvent::DeleteRequested, source.clone())
+                            .await
+                    }
+                    None => (),
+                }
+            }
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<ChipEvent> for Chip {
+    fn event_stream(&self) -> EventStream<ChipEvent> {
+        self.chip_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for Chip {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Chip {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Chip {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ChipGroupEvent {
+    /// Chip with the given id is added to the group
+    Added(usize),
+    /// Chip with the given id is removed from the group
+    Removed(usize),
+    Clicked(usize),
+    /// Delete button of the chip is clicked. The chip is already removed if the group
+    /// is created with `remove_on_delete`
+    DeleteRequested(usize),
+}
+
+struct GroupCore {
+    chips: Vec<Arc<Chip>>,
+    offsets: Vec<Vector2>,
+    size: Vector2,
+    mouse_pos: Option<Vector2>,
+    pressed: Option<(usize, Part)>,
+}
+
+impl GroupCore {
+    fn chip_at(&self, pos: Vector2) -> Option<(usize, Part)> {
+        self.chips
+            .iter()
+            .zip(&self.offsets)
+            .find_map(|(chip, offset)| {
+                let local = Vector2 {
+                    X: pos.X - offset.X,
+                    Y: pos.Y - offset.Y,
+                };
+                chip.part_at(local).map(|part| (chip.id(), part))
+            })
+    }
+}
+
+///
+/// Chips laid out in rows wrapping to the next row when the width is exhausted. Added chips
+/// fade in, removed ones fade out while the remaining chips slide to their new places.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct ChipGroup {
+    compositor: Compositor,
+    container: ContainerVisual,
+    spacing: f32,
+    animation_duration: Duration,
+    remove_on_delete: bool,
+    core: RwLock<GroupCore>,
+    chip_group_events: EventStreams<ChipGroupEvent>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ChipGroupParams {
+    compositor: Compositor,
+    #[builder(default)]
+    chips: Vec<Arc<Chip>>,
+    #[builder(default = 8.)]
+    spacing: f32,
+    /// Zero duration disables the animations
+    #[builder(default = Duration::from_millis(150))]
+    animation_duration: Duration,
+    #[builder(default = true)]
+    remove_on_delete: bool,
+}
+
+impl ChipGroupParams {
+    pub fn add_chip(mut self, chip: Arc<Chip>) -> Self {
+        self.chips.push(chip);
+        self
+    }
+}
+
+impl TryFrom<ChipGroupParams> for ChipGroup {
+    type Error = crate::Error;
+
+    fn try_from(value: ChipGroupParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        for chip in &value.chips {
+            attach(&container, &**chip)?;
+        }
+        let offsets = vec![Vector2::default(); value.chips.len()];
+        Ok(ChipGroup {
+            compositor: value.compositor,
+            container,
+            spacing: value.spacing,
+            animation_duration: value.animation_duration,
+            remove_on_delete: value.remove_on_delete,
+            core: RwLock::new(GroupCore {
+                chips: value.chips,
+                offsets,
+                size: Vector2::default(),
+                mouse_pos: None,
+                pressed: None,
+            }),
+            chip_group_events: EventStreams::new(),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ChipGroupParams> for Arc<ChipGroup> {
+    type Error = crate::Error;
+
+    fn try_from(value: ChipGroupParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ChipGroup {
+    pub async fn chips(&self) -> Vec<Arc<Chip>> {
+        self.core.read().await.chips.clone()
+    }
+
+    pub async fn add(&self, chip: Arc<Chip>) -> crate::Result<()> {
+        attach(&self.container, &*chip)?;
+        let frame = chip.outer_frame();
+        frame.SetSize(chip.size())?;
+        chip.on_event_owned(PanelEvent::Resized(chip.size()), None)
+            .await?;
+        let id = chip.id();
+        {
+            let mut core = self.core.write().await;
+            core.chips.push(chip);
+            let offsets = self.layout(&core.chips, core.size.X);
+            // The new chip appears in place, only the fade in is animated
+            let offset = offsets[offsets.len() - 1];
+            frame.SetOffset(Point::from(offset).into())?;
+            core.offsets.push(offset);
+            self.move_chips(&mut core, offsets)?;
+        }
+        if !self.animation_duration.is_zero() {
+            self.fade(&frame, 0., 1.)?;
+        }
+        self.chip_group_events
+            .send_event(ChipGroupEvent::Added(id), None)
+            .await;
+        Ok(())
+    }
+
+    /// Removes the chip with the given id, returns false if there is no such chip
+    pub async fn remove(&self, id: usize) -> crate::Result<bool> {
+        {
+            let mut core = self.core.write().await;
+            let index = match core.chips.iter().position(|v| v.id() == id) {
+                Some(index) => index,
+                None => return Ok(false),
+            };
+            let chip = core.chips.remove(index);
+            core.offsets.remove(index);
+            if core.pressed.map_or(false, |(pressed, _)| pressed == id) {
+                core.pressed = None;
+            }
+            let frame = chip.outer_frame();
+            if self.animation_duration.is_zero() {
+                self.container.Children()?.Remove(&frame)?;
+            } else {
+                let batch = self
+                    .compositor
+                    .CreateScopedBatch(CompositionBatchTypes::Animation)?;
+                self.fade(&frame, 1., 0.)?;
+                batch.End()?;
+                let container = self.container.clone();
+                batch.Completed(&TypedEventHandler::new(move |_, _| {
+                    container.Children()?.Remove(&frame)?;
+                    Ok(())
+                }))?;
+            }
+            let offsets = self.layout(&core.chips, core.size.X);
+            self.move_chips(&mut core, offsets)?;
+        }
+        self.chip_group_events
+            .send_event(ChipGroupEvent::Removed(id), None)
+            .await;
+        Ok(true)
+    }
+
+    fn layout(&self, chips: &[Arc<Chip>], width: f32) -> Vec<Vector2> {
+        let mut x = 0.;
+        let mut y = 0.;
+        let mut row_height: f32 = 0.;
+        chips
+            .iter()
+            .map(|chip| {
+                if x > 0. && x + chip.width() > width {
+                    x = 0.;
+                    y += row_height + self.spacing;
+                    row_height = 0.;
+                }
+                let offset = Vector2 { X: x, Y: y };
+                x += chip.width() + self.spacing;
+                row_height = row_height.max(chip.height());
+                offset
+            })
+            .collect()
+    }
+
+    fn move_chips(&self, core: &mut GroupCore, offsets: Vec<Vector2>) -> crate::Result<()> {
+        for ((chip, old), new) in core.chips.iter().zip(&core.offsets).zip(&offsets) {
+            if old == new {
+                continue;
+            }
+            let frame = chip.outer_frame();
+            let to: Vector3 = Point::from(*new).into();
+            frame.SetOffset(to)?;
+            if !self.animation_duration.is_zero() {
+                let animation = self.compositor.CreateVector3KeyFrameAnimation()?;
+                animation.InsertKeyFrame(0., Point::from(*old).into())?;
+                animation.InsertKeyFrame(1., to)?;
+                animation.SetDuration(time_span(self.animation_duration))?;
+                start_animation(&frame, "Offset", &animation)?;
+            }
+        }
+        core.offsets = offsets;
+        Ok(())
+    }
+
+    fn fade(&self, visual: &Visual, from: f32, to: f32) -> crate::Result<()> {
+        visual.SetOpacity(to)?;
+        let animation = self.compositor.CreateScalarKeyFrameAnimation()?;
+        animation.InsertKeyFrame(0., from)?;
+        animation.InsertKeyFrame(1., to)?;
+        animation.SetDuration(time_span(self.animation_duration))?;
+        start_animation(visual, "Opacity", &animation)?;
+        Ok(())
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        let chips = {
+            let mut core = self.core.write().await;
+            core.size = size;
+            let offsets = self.layout(&core.chips, size.X);
+            for (chip, offset) in core.chips.iter().zip(&offsets) {
+                chip.outer_frame().SetOffset(Point::from(*offset).into())?;
+            }
+            core.offsets = offsets;
+            core.chips.clone()
+        };
+        for chip in chips {
+            chip.outer_frame().SetSize(chip.size())?;
+            chip.on_event_owned(PanelEvent::Resized(chip.size()), source.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn mouse_input(
+        &self,
+        in_slot: bool,
+        state: ElementState,
+        button: MouseButton,
+        click_count: u32,
+        seq: EventSeqId,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let (chips, hit, action) = {
+            let mut core = self.core.write().await;
+            let hit = core.mouse_pos.and_then(|pos| core.chip_at(pos));
+            let action = match (state, button) {
+                (ElementState::Pressed, MouseButton::Left) if in_slot => {
+                    core.pressed = hit;
+                    None
+                }
+                (ElementState::Released, MouseButton::Left) => match core.pressed.take() {
+                    Some(pressed) if in_slot && hit == Some(pressed) => Some(pressed),
+                    _ => None,
+                },
+                _ => None,
+            };
+            (core.chips.clone(), hit.map(|(id, _)| id), action)
+        };
+        for chip in chips {
+            let event = PanelEvent::MouseInput {
+                in_slot: in_slot && hit == Some(chip.id()),
+                state,
+                button,
+                click_count,
+                seq,
+            };
+            chip.on_event_owned(event, source.clone()).await?;
+        }
+        match action {
+            Some((id, Part::Body)) => {
+                self.chip_group_events
+                    .send_event(ChipGroupEvent::Clicked(id), source)
+                    .await
+            }
+            Some((id, Part::Delete)) => {
+                if self.remove_on_delete {
+                    self.remove(id).await?;
+                }
+                self.chip_group_events
+                    .send_event(ChipGroupEvent::DeleteRequested(id), source)
+                    .await
+            }
+            None => (),
+        }
+        Ok(())
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            PanelEvent::CursorMoved(pos) => {
+                let placed = {
+                    let mut core = self.core.write().await;
+                    core.mouse_pos = Some(*pos);
+                    core.chips
+                        .iter()
+                        .cloned()
+                        .zip(core.offsets.iter().cloned())
+                        .collect::<Vec<_>>()
+                };
+                for (chip, offset) in placed {
+                    let local = Vector2 {
+                        X: pos.X - offset.X,
+                        Y: pos.Y - offset.Y,
+                    };
+                    chip.on_event_owned(PanelEvent::CursorMoved(local), source.clone())
+                        .await?;
+                }
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                click_count,
+                seq,
+            } => {
+                self.mouse_input(
+                    *in_slot,
+                    *state,
+                    *button,
+                    *click_count,
+                    *seq,
+                    source.clone(),
+                )
+                .await?
+            }
+            _ => {
+                for chip in self.chips().await {
+                    chip.on_event_ref(&event, source.clone()).await?;
+                }
+            }
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<ChipGroupEvent> for ChipGroup {
+    fn event_stream(&self) -> EventStream<ChipGroupEvent> {
+        self.chip_group_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for ChipGroup {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ChipGroup {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for ChipGroup {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use async_std::task::sleep;
+use futures::{
+    channel::oneshot,
+    future::{select, Either},
+    pin_mut,
+};
+
+struct VirtualState {
+    now: Instant,
+    sleepers: Vec<(Instant, oneshot::Sender<()>)>,
+}
+
+///
+/// Time which runs only when advanced, for the tests of the time-dependent behaviors like
+/// the auto-repeat, the double click or the idle thresholds. The sleeps end when the clock
+/// is advanced past their deadlines, the woken tasks run on their spawner after that.
+///
+#[derive(Clone)]
+pub struct VirtualClock {
+    state: Arc<Mutex<VirtualState>>,
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(VirtualState {
+                now: Instant::now(),
+                sleepers: Vec::new(),
+            })),
+        }
+    }
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn now(&self) -> Instant {
+        self.state.lock().unwrap().now
+    }
+
+    /// Moves the time forward and wakes the sleeps which are over
+    pub fn advance(&self, duration: Duration) {
+        let woken = {
+            let mut state = self.state.lock().unwrap();
+            state.now += duration;
+            let now = state.now;
+            let (woken, waiting) = state
+                .sleepers
+                .drain(..)
+                .partition::<Vec<_>, _>(|(deadline, _)| *deadline <= now);
+            state.sleepers = waiting;
+            woken
+        };
+        for (_, sender) in woken {
+            // The sleep may be cancelled already
+            let _ = sender.send(());
+        }
+    }
+
+    /// Number of the sleeps not over yet
+    pub fn sleepers(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.sleepers.retain(|(_, v)| !v.is_canceled());
+        state.sleepers.len()
+    }
+
+    async fn sleep_until(&self, deadline: Instant) {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if deadline <= state.now {
+                return;
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.sleepers.push((deadline, sender));
+            receiver
+        };
+        let _ = receiver.await;
+    }
+}
+
+///
+/// Source of the time for the framework's timers: [`Timer`], the idle monitor, the click
+/// counting, the long press and the type-ahead of the lists. The compositor animations
+/// run on the system time regardless of it.
+///
+/// [`Timer`]: super::Timer
+///
+#[derive(Clone, Default)]
+pub enum Clock {
+    #[default]
+    System,
+    Virtual(VirtualClock),
+}
+
+impl Clock {
+    pub fn now(&self) -> Instant {
+        match self {
+            Clock::System => Instant::now(),
+            Clock::Virtual(clock) => clock.now(),
+        }
+    }
+
+    pub async fn sleep(&self, duration: Duration) {
+        self.sleep_until(self.now() + duration).await
+    }
+
+    pub async fn sleep_until(&self, deadline: Instant) {
+        match self {
+            Clock::System => sleep(deadline.saturating_duration_since(Instant::now())).await,
+            Clock::Virtual(clock) => clock.sleep_until(deadline).await,
+        }
+    }
+
+    /// Output of the future if it completes before the deadline
+    pub async fn timeout_at<F: Future>(&self, deadline: Instant, future: F) -> Option<F::Output> {
+        let sleep = self.sleep_until(deadline);
+        pin_mut!(future, sleep);
+        match select(future, sleep).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+static CLOCK: RwLock<Clock> = RwLock::new(Clock::System);
+
+/// The clock used by the framework, the system one unless replaced by `set_clock`
+pub fn clock() -> Clock {
+    CLOCK.read().unwrap().clone()
+}
+
+///
+/// Replaces the clock of the framework, e.g. by the [`VirtualClock`] in the tests. The timers
+/// already sleeping keep the clock they were started with.
+///
+pub fn set_clock(clock: Clock) {
+    *CLOCK.write().unwrap() = clock;
+}
@@ -0,0 +1,466 @@
+use std::{
+    borrow::Cow,
+    time::{Duration, Instant},
+};
+
+use async_event_streams::{
+    spawn_event_pipe, EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use futures::task::Spawn;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::{Matrix3x2, Vector2},
+    Win32::Graphics::{
+        Direct2D::{
+            Common::D2D_RECT_F, D2D1_BRUSH_PROPERTIES, D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+        },
+        DirectWrite::{DWRITE_MEASURING_MODE_NATURAL, DWRITE_TEXT_ALIGNMENT_TRAILING},
+    },
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, ShapeVisual, SpriteVisual, Visual},
+    },
+};
+use winit::event::{ElementState, ModifiersState, MouseButton, VirtualKeyCode};
+
+use crate::{on_err, window::ToWide};
+
+use super::{
+    attach,
+    button::create_focus_ring,
+    clock,
+    dispatch::DispatchQueue,
+    menu::{d2d_color, key_char, text_format},
+    Menu, MenuEvent, MenuItem, MenuParams, OverlayHost, Panel, PanelEvent, PopupSide, Surface,
+    SurfaceParams,
+};
+
+const TEXT_MARGIN: f32 = 8.;
+const CORNER_RADIUS: f32 = 4.;
+// Keys typed with shorter pauses form one type-ahead prefix
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ComboBoxEvent {
+    /// Index of the selected item or None if nothing is selected
+    SelectionChanged(Option<usize>),
+}
+
+struct Core {
+    items: Vec<String>,
+    selected: Option<usize>,
+    focused: bool,
+    typed: String,
+    typed_at: Option<Instant>,
+}
+
+// Part of the combo box receiving the choice from the popup list
+#[derive(EventSink)]
+#[event_sink(event=MenuEvent)]
+struct Shared {
+    surface: Arc<Surface>,
+    text_color: Color,
+    core: RwLock<Core>,
+    combo_box_events: EventStreams<ComboBoxEvent>,
+}
+
+impl Shared {
+    async fn select(
+        &self,
+        index: Option<usize>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let index = {
+            let mut core = self.core.write().await;
+            let index = index.filter(|v| *v < core.items.len());
+            if core.selected == index {
+                return Ok(());
+            }
+            core.selected = index;
+            self.redraw(&core)?;
+            index
+        };
+        self.combo_box_events
+            .send_event(ComboBoxEvent::SelectionChanged(index), source)
+            .await;
+        Ok(())
+    }
+
+    fn redraw(&self, core: &Core) -> crate::Result<()> {
+        let text = core
+            .selected
+            .and_then(|v| core.items.get(v))
+            .cloned()
+            .unwrap_or_default();
+        self.surface.draw(|context, size| {
+            let chevron_format = text_format()?;
+            let text_format = text_format()?;
+            unsafe { chevron_format.SetTextAlignment(DWRITE_TEXT_ALIGNMENT_TRAILING)? };
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(self.text_color), Some(&brush_properties))
+            }?;
+            unsafe { context.Clear(Some(&d2d_color(Colors::Transparent()?))) };
+            let rect = D2D_RECT_F {
+                left: TEXT_MARGIN,
+                top: 0.,
+                right: size.X - TEXT_MARGIN,
+                bottom: size.Y,
+            };
+            for (text, format) in [(text.as_str(), &text_format), ("\u{25BE}", &chevron_format)] {
+                unsafe {
+                    context.DrawText(
+                        text.to_wide().0.as_slice(),
+                        format,
+                        &rect,
+                        &brush,
+                        D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+                        DWRITE_MEASURING_MODE_NATURAL,
+                    )
+                };
+            }
+            Ok(())
+        })
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<MenuEvent> for Shared {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, MenuEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let MenuEvent::Activated(index) = event.as_ref();
+        self.select(Some(*index), source).await
+    }
+}
+
+///
+/// Dropdown selector showing the selected item. Click opens the list of items in the
+/// [`OverlayHost`] below the combo box. The combo box gets keyboard focus on mouse press:
+/// Up/Down select the previous or next item without opening the list, Alt+Down, F4, Space or
+/// Enter open it, typing selects the next item starting with the typed text.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct ComboBox {
+    container: ContainerVisual,
+    shapes: ShapeVisual,
+    focus_ring: SpriteVisual,
+    compositor: Compositor,
+    host: Arc<OverlayHost>,
+    spawner: Box<dyn Spawn + Send + Sync>,
+    background: Color,
+    border_color: Color,
+    shared: Arc<Shared>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ComboBoxParams<T: Spawn + Send + Sync + 'static> {
+    compositor: Compositor,
+    host: Arc<OverlayHost>,
+    spawner: T,
+    #[builder(default)]
+    items: Vec<String>,
+    #[builder(default, setter(strip_option))]
+    selected: Option<usize>,
+    #[builder(default = Colors::White().unwrap())]
+    background: Color,
+    #[builder(default = Colors::Gray().unwrap())]
+    border_color: Color,
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+}
+
+impl<T: Spawn + Send + Sync + 'static> TryFrom<ComboBoxParams<T>> for ComboBox {
+    type Error = crate::Error;
+
+    fn try_from(value: ComboBoxParams<T>) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let shapes = value.compositor.CreateShapeVisual()?;
+        container.Children()?.InsertAtBottom(&shapes)?;
+        let surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(value.compositor.clone())
+            .build()
+            .try_into()?;
+        attach(&container, &*surface)?;
+        let focus_ring = create_focus_ring(&value.compositor)?;
+        container.Children()?.InsertAtTop(&focus_ring)?;
+        let selected = value.selected.filter(|v| *v < value.items.len());
+        let shared = Arc::new(Shared {
+            surface,
+            text_color: value.text_color,
+            core: RwLock::new(Core {
+                items: value.items,
+                selected,
+                focused: false,
+                typed: String::new(),
+                typed_at: None,
+            }),
+            combo_box_events: EventStreams::new(),
+        });
+        Ok(ComboBox {
+            container,
+            shapes,
+            focus_ring,
+            compositor: value.compositor,
+            host: value.host,
+            spawner: Box::new(value.spawner),
+            background: value.background,
+            border_color: value.border_color,
+            shared,
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl<T: Spawn + Send + Sync + 'static> TryFrom<ComboBoxParams<T>> for Arc<ComboBox> {
+    type Error = crate::Error;
+
+    fn try_from(value: ComboBoxParams<T>) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ComboBox {
+    pub async fn items(&self) -> Vec<String> {
+        self.shared.core.read().await.items.clone()
+    }
+
+    /// Replaces the items, the selection is kept if its index is still valid
+    pub async fn set_items(&self, items: Vec<String>) -> crate::Result<()> {
+        let valid = {
+            let mut core = self.shared.core.write().await;
+            core.items = items;
+            core.selected.map_or(true, |v| v < core.items.len())
+        };
+        if valid {
+            self.shared.redraw(&*self.shared.core.read().await)
+        } else {
+            self.shared.select(None, None).await
+        }
+    }
+
+    pub async fn selected(&self) -> Option<usize> {
+        self.shared.core.read().await.selected
+    }
+
+    pub async fn set_selected(&self, index: Option<usize>) -> crate::Result<()> {
+        self.shared.select(index, None).await
+    }
+
+    /// Opens the list of items below the combo box
+    pub async fn open(&self) -> crate::Result<()> {
+        let (items, selected) = {
+            let core = self.shared.core.read().await;
+            (core.items.clone(), core.selected)
+        };
+        if items.is_empty() {
+            return Ok(());
+        }
+        let items = items
+            .iter()
+            .enumerate()
+            // Item texts are shown as is, without mnemonics
+            .map(|(index, text)| {
+                MenuItem::new(index, text.replace('&', "&&")).checked(Some(index) == selected)
+            })
+            .collect();
+        let width = self.container.Size()?.X;
+        let menu: Arc<Menu> = MenuParams::builder()
+            .compositor(self.compositor.clone())
+            .items(items)
+            .width(width)
+            .build()
+            .try_into()?;
+        spawn_event_pipe(&self.spawner, &*menu, self.shared.clone(), on_err)?;
+        let anchor = self.host.bounds_of(self)?;
+        menu.show(self.host.clone(), anchor, PopupSide::Bottom)
+            .await?;
+        menu.select_first().await
+    }
+
+    async fn set_focused(&self, focused: bool) -> crate::Result<()> {
+        self.shared.core.write().await.focused = focused;
+        self.focus_ring.SetIsVisible(focused)?;
+        Ok(())
+    }
+
+    fn redraw_shapes(&self, size: Vector2) -> crate::Result<()> {
+        let shapes = self.shapes.Shapes()?;
+        shapes.Clear()?;
+        let geometry = self.compositor.CreateRoundedRectangleGeometry()?;
+        geometry.SetSize(size)?;
+        geometry.SetCornerRadius(Vector2 {
+            X: CORNER_RADIUS,
+            Y: CORNER_RADIUS,
+        })?;
+        let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        shape.SetFillBrush(&self.compositor.CreateColorBrushWithColor(self.background)?)?;
+        shape.SetStrokeBrush(
+            &self
+                .compositor
+                .CreateColorBrushWithColor(self.border_color)?,
+        )?;
+        shape.SetStrokeThickness(1.)?;
+        shapes.Append(&shape)?;
+        Ok(())
+    }
+
+    // Next item starting with the typed text, searched after the selected one for a single key
+    async fn type_ahead(&self, c: char, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        let index = {
+            let mut core = self.shared.core.write().await;
+            let now = clock().now();
+            if core
+                .typed_at
+                .map_or(true, |v| now.duration_since(v) > TYPE_AHEAD_TIMEOUT)
+            {
+                core.typed.clear();
+            }
+            core.typed.push(c);
+            core.typed_at = Some(now);
+            let count = core.items.len();
+            let start = match core.selected {
+                Some(v) if core.typed.chars().count() == 1 => v + 1,
+                Some(v) => v,
+                None => 0,
+            };
+            (0..count).map(|v| (start + v) % count).find(|v| {
+                core.items[*v]
+                    .to_lowercase()
+                    .starts_with(core.typed.as_str())
+            })
+        };
+        match index {
+            Some(index) => self.shared.select(Some(index), source).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn key_pressed(
+        &self,
+        key: VirtualKeyCode,
+        modifiers: ModifiersState,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let (focused, selected, count) = {
+            let core = self.shared.core.read().await;
+            (core.focused, core.selected, core.items.len())
+        };
+        if !focused || count == 0 {
+            return Ok(());
+        }
+        match key {
+            VirtualKeyCode::Down if modifiers.alt() => self.open().await,
+            VirtualKeyCode::F4 | VirtualKeyCode::Space | VirtualKeyCode::Return => {
+                self.open().await
+            }
+            VirtualKeyCode::Down => {
+                let index = selected.map_or(0, |v| (v + 1).min(count - 1));
+                self.shared.select(Some(index), source).await
+            }
+            VirtualKeyCode::Up => {
+                let index = selected.map_or(0, |v| v.saturating_sub(1));
+                self.shared.select(Some(index), source).await
+            }
+            VirtualKeyCode::Home => self.shared.select(Some(0), source).await,
+            VirtualKeyCode::End => self.shared.select(Some(count - 1), source).await,
+            key => match key_char(key) {
+                Some(c) if !modifiers.ctrl() && !modifiers.alt() => {
+                    self.type_ahead(c, source).await
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => {
+                self.container.SetSize(*size)?;
+                self.shapes.SetSize(*size)?;
+                self.shared
+                    .surface
+                    .on_event_ref(&event, source.clone())
+                    .await?;
+                self.redraw_shapes(*size)?;
+                self.shared.redraw(&*self.shared.core.read().await)?;
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.set_focused(*in_slot).await?;
+                if *in_slot {
+                    self.open().await?;
+                }
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(key),
+                modifiers,
+                ..
+            } => self.key_pressed(*key, *modifiers, source.clone()).await?,
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<ComboBoxEvent> for ComboBox {
+    fn event_stream(&self) -> EventStream<ComboBoxEvent> {
+        self.shared.combo_box_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for ComboBox {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ComboBox {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for ComboBox {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,269 @@
+use std::{borrow::Cow, fmt::Display};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use winit::event::{ElementState, ModifiersState, VirtualKeyCode};
+
+use super::PanelEvent;
+
+/// Key combination invoking the command
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Accelerator {
+    pub key: VirtualKeyCode,
+    pub modifiers: ModifiersState,
+}
+
+impl Accelerator {
+    pub fn new(key: VirtualKeyCode, modifiers: ModifiersState) -> Self {
+        Self { key, modifiers }
+    }
+    pub fn ctrl(key: VirtualKeyCode) -> Self {
+        Self::new(key, ModifiersState::CTRL)
+    }
+    pub fn matches(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        self.key == key && self.modifiers == modifiers
+    }
+}
+
+impl Display for Accelerator {
+    /// Text for menus, like "Ctrl+Shift+S"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in [
+            (ModifiersState::CTRL, "Ctrl+"),
+            (ModifiersState::SHIFT, "Shift+"),
+            (ModifiersState::ALT, "Alt+"),
+            (ModifiersState::LOGO, "Win+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        let key = format!("{:?}", self.key);
+        // Digit keys are named like "Key1"
+        match key.strip_prefix("Key") {
+            Some(digit) if !digit.is_empty() => f.write_str(digit),
+            _ => f.write_str(&key),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Command {
+    pub id: usize,
+    pub text: String,
+    pub accelerator: Option<Accelerator>,
+    /// Group of the command in the shortcut sheet, e.g. "Edit"
+    pub category: Option<String>,
+}
+
+impl Command {
+    pub fn new(id: usize, text: impl Into<String>) -> Self {
+        Self {
+            id,
+            text: text.into(),
+            accelerator: None,
+            category: None,
+        }
+    }
+    pub fn with_accelerator(mut self, accelerator: Accelerator) -> Self {
+        self.accelerator = Some(accelerator);
+        self
+    }
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum CommandEvent {
+    Invoked(usize),
+    EnabledChanged { id: usize, enabled: bool },
+    CheckedChanged { id: usize, checked: bool },
+}
+
+struct CommandState {
+    command: Command,
+    enabled: bool,
+    checked: bool,
+}
+
+///
+/// Set of application commands. Commands are invoked by menu items with the id equal to
+/// the command id, or by accelerators. Accelerators are handled by the `MenuBar` owning
+/// the registry; without menu bar pipe the events of the root panel into the registry.
+/// The application listens for `CommandEvent::Invoked` to execute commands.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct CommandRegistry {
+    commands: RwLock<Vec<CommandState>>,
+    command_events: EventStreams<CommandEvent>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self {
+            commands: RwLock::new(Vec::new()),
+            command_events: EventStreams::new(),
+        }
+    }
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the command, replaces the existing one with the same id
+    pub async fn add(&self, command: Command) {
+        let mut commands = self.commands.write().await;
+        commands.retain(|v| v.command.id != command.id);
+        commands.push(CommandState {
+            command,
+            enabled: true,
+            checked: false,
+        });
+    }
+
+    pub async fn remove(&self, id: usize) {
+        self.commands.write().await.retain(|v| v.command.id != id);
+    }
+
+    /// All commands in the order of registration
+    pub async fn commands(&self) -> Vec<Command> {
+        self.commands
+            .read()
+            .await
+            .iter()
+            .map(|v| v.command.clone())
+            .collect()
+    }
+
+    pub async fn command(&self, id: usize) -> Option<Command> {
+        self.commands
+            .read()
+            .await
+            .iter()
+            .find(|v| v.command.id == id)
+            .map(|v| v.command.clone())
+    }
+
+    /// Unknown commands are considered disabled
+    pub async fn is_enabled(&self, id: usize) -> bool {
+        self.commands
+            .read()
+            .await
+            .iter()
+            .any(|v| v.command.id == id && v.enabled)
+    }
+
+    pub async fn set_enabled(&self, id: usize, enabled: bool) {
+        let changed = {
+            let mut commands = self.commands.write().await;
+            match commands.iter_mut().find(|v| v.command.id == id) {
+                Some(state) if state.enabled != enabled => {
+                    state.enabled = enabled;
+                    true
+                }
+                _ => false,
+            }
+        };
+        if changed {
+            self.command_events
+                .send_event(CommandEvent::EnabledChanged { id, enabled }, None)
+                .await;
+        }
+    }
+
+    ///
+    /// Checked state of the command, e.g. the tool selected in the toolbar. Menus show
+    /// the check mark for checked commands, toggle buttons bound to the command follow it.
+    ///
+    pub async fn is_checked(&self, id: usize) -> bool {
+        self.commands
+            .read()
+            .await
+            .iter()
+            .any(|v| v.command.id == id && v.checked)
+    }
+
+    pub async fn set_checked(&self, id: usize, checked: bool) {
+        let changed = {
+            let mut commands = self.commands.write().await;
+            match commands.iter_mut().find(|v| v.command.id == id) {
+                Some(state) if state.checked != checked => {
+                    state.checked = checked;
+                    true
+                }
+                _ => false,
+            }
+        };
+        if changed {
+            self.command_events
+                .send_event(CommandEvent::CheckedChanged { id, checked }, None)
+                .await;
+        }
+    }
+
+    /// Invokes the command if it's known and enabled, returns true if invoked
+    pub async fn invoke(&self, id: usize) -> bool {
+        if !self.is_enabled(id).await {
+            return false;
+        }
+        self.command_events
+            .send_event(CommandEvent::Invoked(id), None)
+            .await;
+        true
+    }
+
+    /// Invokes the command with matching accelerator, returns true if invoked
+    pub async fn handle_key(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        let id = self
+            .commands
+            .read()
+            .await
+            .iter()
+            .find(|v| {
+                v.command
+                    .accelerator
+                    .map_or(false, |a| a.matches(key, modifiers))
+            })
+            .map(|v| v.command.id);
+        match id {
+            Some(id) => self.invoke(id).await,
+            None => false,
+        }
+    }
+}
+
+impl EventSource<CommandEvent> for CommandRegistry {
+    fn event_stream(&self) -> EventStream<CommandEvent> {
+        self.command_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for CommandRegistry {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::KeyboardInput {
+            state: ElementState::Pressed,
+            key: Some(key),
+            modifiers,
+            ..
+        } = event.as_ref()
+        {
+            self.handle_key(*key, *modifiers).await;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::UI::{
+    Color, Colors,
+    Composition::{Compositor, ContainerVisual, SpriteVisual, Visual},
+};
+
+use crate::color::ColorExt;
+
+use super::{
+    attach, button::create_focus_ring, Background, BackgroundParams, ButtonEvent, CornerRadius,
+    LayerStack, LayerStackParams, Panel, PanelEvent,
+};
+
+struct State {
+    pressed: bool,
+    hover: bool,
+    enabled: bool,
+    focused: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            pressed: false,
+            hover: false,
+            enabled: true,
+            focused: false,
+        }
+    }
+}
+
+///
+/// Button skin showing any panel, e.g. an icon, an image or a composite layout, over the
+/// rounded background changing its color with the button's state
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+#[event_sink(event=ButtonEvent)]
+pub struct ContentButtonSkin {
+    container: ContainerVisual,
+    layer_stack: LayerStack,
+    content: Arc<dyn Panel>,
+    background: Arc<Background>,
+    focus_ring: SpriteVisual,
+    color: Color,
+    hover_color: Color,
+    pressed_color: Color,
+    state: RwLock<State>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ContentButtonSkinParams {
+    compositor: Compositor,
+    content: Arc<dyn Panel>,
+    #[builder(default = Colors::Gainsboro().unwrap())]
+    color: Color,
+    #[builder(default = Colors::LightGray().unwrap())]
+    hover_color: Color,
+    #[builder(default = Colors::SkyBlue().unwrap())]
+    pressed_color: Color,
+}
+
+impl TryFrom<ContentButtonSkinParams> for ContentButtonSkin {
+    type Error = crate::Error;
+
+    fn try_from(value: ContentButtonSkinParams) -> crate::Result<Self> {
+        let background: Arc<Background> = BackgroundParams::builder()
+            .color(value.color)
+            .corner_radius(CornerRadius::relative(0.05))
+            .compositor(value.compositor.clone())
+            .build()
+            .try_into()?;
+        let layer_stack: LayerStack = LayerStackParams::builder()
+            .compositor(value.compositor.clone())
+            .build()
+            .push_panel(background.clone())
+            .push_panel(value.content.clone())
+            .try_into()?;
+        let container = value.compositor.CreateContainerVisual()?;
+        attach(&container, &layer_stack)?;
+        let focus_ring = create_focus_ring(&value.compositor)?;
+        container.Children()?.InsertAtTop(&focus_ring)?;
+        Ok(ContentButtonSkin {
+            container,
+            layer_stack,
+            content: value.content,
+            background,
+            focus_ring,
+            color: value.color,
+            hover_color: value.hover_color,
+            pressed_color: value.pressed_color,
+            state: RwLock::new(State::default()),
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ContentButtonSkinParams> for Arc<ContentButtonSkin> {
+    type Error = crate::Error;
+
+    fn try_from(value: ContentButtonSkinParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ContentButtonSkin {
+    pub fn content(&self) -> Arc<dyn Panel> {
+        self.content.clone()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<ButtonEvent> for ContentButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, ButtonEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let mut state = self.state.write().await;
+        match event.as_ref() {
+            ButtonEvent::Press => state.pressed = true,
+            ButtonEvent::Release(_) => state.pressed = false,
+            ButtonEvent::HoverChanged(hover) => state.hover = *hover,
+            ButtonEvent::EnabledChanged(enabled) => state.enabled = *enabled,
+            ButtonEvent::FocusChanged(focused) => state.focused = *focused,
+        }
+        self.focus_ring.SetIsVisible(state.focused)?;
+        let color = if !state.enabled {
+            self.color.lerp(Colors::Gray()?, 0.7)
+        } else if state.pressed {
+            self.pressed_color
+        } else if state.hover {
+            self.hover_color
+        } else {
+            self.color
+        };
+        self.background.set_color(color).await
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ContentButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.container.SetSize(*size)?;
+        }
+        self.layer_stack.on_event(event, source).await
+    }
+}
+
+impl EventSource<PanelEvent> for ContentButtonSkin {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for ContentButtonSkin {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    any::{Any, TypeId},
+    sync::Arc,
+};
+
+///
+/// Typed values shared down the panel tree: the theme, the spawner, the command registry,
+/// application services. A container passes its context to the children it creates, a child
+/// may add or override values for its own subtree with `with`, which leaves the parent's
+/// context unchanged. So deep child constructors take the one context instead of every
+/// service threaded through the params by hand.
+///
+/// The context is immutable and cheap to clone; put `RwLock` or the service's own interior
+/// mutability into the value if it has to change.
+///
+#[derive(Clone, Default)]
+pub struct Context {
+    parent: Option<Arc<Context>>,
+    value: Option<(TypeId, Arc<dyn Any + Send + Sync>)>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Context with the value added, replacing the value of the same type for its users
+    pub fn with<T: Any + Send + Sync>(&self, value: T) -> Self {
+        self.with_arc(Arc::new(value))
+    }
+
+    /// Same as `with` for the value already shared, e.g. `Arc<CommandRegistry>`
+    pub fn with_arc<T: Any + Send + Sync>(&self, value: Arc<T>) -> Self {
+        Self {
+            parent: Some(Arc::new(self.clone())),
+            value: Some((TypeId::of::<T>(), value)),
+        }
+    }
+
+    /// The nearest value of the type
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let mut current = Some(self);
+        while let Some(context) = current {
+            if let Some((type_id, value)) = &context.value {
+                if *type_id == TypeId::of::<T>() {
+                    return value.clone().downcast::<T>().ok();
+                }
+            }
+            current = context.parent.as_deref();
+        }
+        None
+    }
+
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+}
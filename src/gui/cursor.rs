@@ -0,0 +1,109 @@
+use std::sync::{Arc, Mutex, Weak};
+
+use windows::{Foundation::Numerics::Vector2, UI::Composition::Visual};
+use winit::window::CursorIcon;
+
+use crate::geometry::Rect;
+
+use super::{panel::window_offset, Panel};
+
+#[derive(Default)]
+struct State {
+    panels: Vec<(Weak<dyn Panel>, CursorIcon)>,
+    current: CursorIcon,
+    notify: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+// Number of parents of the visual: nested panels are deeper than their containers
+fn depth(visual: &Visual) -> usize {
+    let mut depth = 0;
+    let mut current = visual.Parent().ok();
+    while let Some(parent) = current {
+        depth += 1;
+        current = parent.Parent().ok();
+    }
+    depth
+}
+
+///
+/// Cursors of the panels of the window. The panel declares its cursor, e.g. the hand for
+/// buttons or I-beam for text, and the window shows it while the mouse is over the panel.
+/// When declared panels are nested, the innermost one wins; outside of them the arrow is shown.
+///
+/// Hit test uses the offsets and sizes of the panels' visuals, so it ignores other transforms.
+///
+#[derive(Clone, Default)]
+pub struct CursorSelector {
+    state: Arc<Mutex<State>>,
+}
+
+impl CursorSelector {
+    /// Sets the cursor of the panel, the panel is forgotten when dropped
+    pub fn set_cursor(&self, panel: &Arc<dyn Panel>, icon: CursorIcon) {
+        let id = panel.id();
+        let mut state = self.state.lock().unwrap();
+        state
+            .panels
+            .retain(|(v, _)| v.upgrade().map_or(false, |v| v.id() != id));
+        state.panels.push((Arc::downgrade(panel), icon));
+    }
+
+    pub fn clear_cursor(&self, id: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .panels
+            .retain(|(v, _)| v.upgrade().map_or(false, |v| v.id() != id));
+    }
+
+    /// The cursor to show in the window now
+    pub fn current(&self) -> CursorIcon {
+        self.state.lock().unwrap().current
+    }
+
+    /// Sets the function called on the cursor change to update the window's cursor
+    pub(crate) fn set_notify(&self, notify: Arc<dyn Fn() + Send + Sync>) {
+        self.state.lock().unwrap().notify = Some(notify);
+    }
+
+    /// Selects the cursor for the mouse position in window coordinates
+    pub(super) fn update(&self, pos: Vector2) -> crate::Result<()> {
+        let panels = {
+            let mut state = self.state.lock().unwrap();
+            state.panels.retain(|(v, _)| v.strong_count() > 0);
+            state.panels.clone()
+        };
+        let mut found: Option<(usize, CursorIcon)> = None;
+        for (panel, icon) in panels {
+            let panel = match panel.upgrade() {
+                Some(panel) => panel,
+                None => continue,
+            };
+            let frame = panel.outer_frame();
+            if !frame.IsVisible()? || frame.Parent().is_err() {
+                continue;
+            }
+            let rect = Rect::new(window_offset(&frame)?.into(), frame.Size()?.into());
+            if !rect.contains(pos) {
+                continue;
+            }
+            let depth = depth(&frame);
+            if found.map_or(true, |(v, _)| depth >= v) {
+                found = Some((depth, icon));
+            }
+        }
+        let icon = found.map_or(CursorIcon::Default, |(_, icon)| icon);
+        let notify = {
+            let mut state = self.state.lock().unwrap();
+            if state.current == icon {
+                return Ok(());
+            }
+            state.current = icon;
+            state.notify.clone()
+        };
+        if let Some(notify) = notify {
+            notify();
+        }
+        Ok(())
+    }
+}
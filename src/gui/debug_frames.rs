@@ -0,0 +1,118 @@
+use std::sync::{Arc, Mutex};
+
+use windows::{
+    core::Interface,
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color,
+        Composition::{CompositionBrush, Compositor, ContainerVisual, SpriteVisual, Visual},
+    },
+};
+
+use crate::color::{ColorExt, Hsl};
+
+/// Opacity of the tint over the container's area
+const TINT_ALPHA: f32 = 0.12;
+const BORDER_WIDTH: f32 = 1.;
+
+#[derive(Default)]
+struct State {
+    enabled: bool,
+    // Visuals added to the tree, removed on refresh
+    frames: Vec<Visual>,
+}
+
+// Color of the containers at the given depth, neighbouring levels get distinct hues
+fn depth_color(depth: usize) -> Color {
+    Hsl {
+        h: (depth * 137 % 360) as f32,
+        s: 0.9,
+        l: 0.5,
+        a: 1.,
+    }
+    .into()
+}
+
+///
+/// Developer mode showing the nesting of the window's visuals: each container gets a translucent
+/// tint over its area and a border, colored by the nesting depth. This makes visible the panels
+/// stretched wider than their content which receive mouse input unexpectedly.
+///
+/// The frames are added to the visual tree as it is when enabled and on each window resize.
+/// Call `refresh` after changing the tree to frame the new visuals.
+///
+#[derive(Clone)]
+pub struct DebugFrames {
+    root: ContainerVisual,
+    state: Arc<Mutex<State>>,
+}
+
+impl DebugFrames {
+    pub(super) fn new(root: ContainerVisual) -> Self {
+        Self {
+            root,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.lock().unwrap().enabled
+    }
+
+    pub fn set_enabled(&self, enabled: bool) -> crate::Result<()> {
+        self.state.lock().unwrap().enabled = enabled;
+        self.refresh()
+    }
+
+    /// Rebuilds the frames for the current visual tree, removes them if disabled
+    pub fn refresh(&self) -> crate::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for frame in state.frames.drain(..) {
+            if let Ok(parent) = frame.Parent() {
+                parent.Children()?.Remove(&frame)?;
+            }
+        }
+        if state.enabled {
+            let compositor = self.root.Compositor()?;
+            add_frames(&compositor, &self.root, 0, &mut state.frames)?;
+        }
+        Ok(())
+    }
+}
+
+fn add_frames(
+    compositor: &Compositor,
+    container: &ContainerVisual,
+    depth: usize,
+    frames: &mut Vec<Visual>,
+) -> crate::Result<()> {
+    // Children are collected before the frames of this container are inserted
+    let children = container.Children()?.into_iter().collect::<Vec<_>>();
+    for child in children {
+        if let Ok(child) = child.cast::<ContainerVisual>() {
+            add_frames(compositor, &child, depth + 1, frames)?;
+        }
+    }
+    let color = depth_color(depth);
+    let tint = frame_sprite(compositor)?;
+    tint.SetBrush(&compositor.CreateColorBrushWithColor(color.with_alpha(TINT_ALPHA))?)?;
+    container.Children()?.InsertAtBottom(&tint)?;
+    frames.push(tint.into());
+    let border_brush = compositor.CreateNineGridBrush()?;
+    let color_brush: CompositionBrush = compositor.CreateColorBrushWithColor(color)?.into();
+    border_brush.SetSource(&color_brush)?;
+    border_brush.SetInsets(BORDER_WIDTH)?;
+    border_brush.SetIsCenterHollow(true)?;
+    let border = frame_sprite(compositor)?;
+    border.SetBrush(&border_brush)?;
+    container.Children()?.InsertAtTop(&border)?;
+    frames.push(border.into());
+    Ok(())
+}
+
+// Sprite covering the whole area of its parent
+fn frame_sprite(compositor: &Compositor) -> crate::Result<SpriteVisual> {
+    let sprite = compositor.CreateSpriteVisual()?;
+    sprite.SetRelativeSizeAdjustment(Vector2 { X: 1., Y: 1. })?;
+    Ok(sprite)
+}
@@ -0,0 +1,382 @@
+use std::{
+    borrow::Cow,
+    sync::{Mutex, Weak},
+};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use futures::channel::oneshot;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color,
+        Composition::{Compositor, ContainerVisual, Visual},
+    },
+};
+use winit::event::{ElementState, VirtualKeyCode};
+
+use crate::{
+    color,
+    geometry::{Point, Rect, Size},
+};
+
+use super::{attach, dispatch::DispatchQueue, OverlayHost, Panel, PanelEvent, Scrim, ScrimParams};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DialogResult {
+    /// Dialog was cancelled, e.g. by Escape key
+    None,
+    Primary,
+    Secondary,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum DialogEvent {
+    Closed(DialogResult),
+}
+
+struct Core {
+    host: Option<Arc<OverlayHost>>,
+    content_rect: Rect,
+    mouse_pos: Option<Point>,
+    waiters: Vec<oneshot::Sender<DialogResult>>,
+}
+
+///
+/// Modal dialog: the content panel centered over the dimming scrim. The dialog is shown
+/// in the [`OverlayHost`] which blocks the input to the rest of the window while the dialog
+/// is open. Escape key closes the dialog with `DialogResult::None`, so does the click on the
+/// scrim if `dismiss_on_scrim_click` is set.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Dialog {
+    container: ContainerVisual,
+    scrim: Arc<Scrim>,
+    content: Arc<dyn Panel>,
+    content_size: Size,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    dialog_events: EventStreams<DialogEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct DialogParams {
+    compositor: Compositor,
+    content: Arc<dyn Panel>,
+    #[builder(setter(into))]
+    content_size: Size,
+    #[builder(default = color::from_argb(0x66000000))]
+    scrim_color: Color,
+    /// Standard deviation of the blur of the window content under the dialog
+    #[builder(default, setter(strip_option))]
+    scrim_blur: Option<f32>,
+    #[builder(default = false)]
+    dismiss_on_scrim_click: bool,
+}
+
+impl TryFrom<DialogParams> for Dialog {
+    type Error = crate::Error;
+
+    fn try_from(value: DialogParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let scrim = ScrimParams::builder()
+            .compositor(value.compositor.clone())
+            .color(value.scrim_color)
+            .dismiss_on_click(value.dismiss_on_scrim_click);
+        let scrim: Arc<Scrim> = match value.scrim_blur {
+            Some(blur) => scrim.blur(blur).build().try_into()?,
+            None => scrim.build().try_into()?,
+        };
+        container.Children()?.InsertAtBottom(&scrim.outer_frame())?;
+        attach(&container, &*value.content)?;
+        let core = RwLock::new(Core {
+            host: None,
+            content_rect: Rect::default(),
+            mouse_pos: None,
+            waiters: Vec::new(),
+        });
+        Ok(Dialog {
+            container,
+            scrim,
+            content: value.content,
+            content_size: value.content_size,
+            core,
+            panel_events: EventStreams::new(),
+            dialog_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<DialogParams> for Arc<Dialog> {
+    type Error = crate::Error;
+
+    fn try_from(value: DialogParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Dialog {
+    ///
+    /// Shows the dialog in the host and waits until it's closed. If the dialog is already open,
+    /// just waits for its result.
+    ///
+    pub async fn show(self: &Arc<Self>, host: Arc<OverlayHost>) -> crate::Result<DialogResult> {
+        let (tx, rx) = oneshot::channel();
+        let show = {
+            let mut core = self.core.write().await;
+            core.waiters.push(tx);
+            if core.host.is_none() {
+                core.host = Some(host.clone());
+                true
+            } else {
+                false
+            }
+        };
+        if show {
+            if let Err(e) = self.open(&host).await {
+                // The dialog stays closed, the callers joined meanwhile get no result
+                let waiters = {
+                    let mut core = self.core.write().await;
+                    core.host = None;
+                    std::mem::take(&mut core.waiters)
+                };
+                let _ = host.close_popup(self.id()).await;
+                for tx in waiters {
+                    let _ = tx.send(DialogResult::None);
+                }
+                return Err(e);
+            }
+        }
+        Ok(rx.await.unwrap_or(DialogResult::None))
+    }
+
+    async fn open(self: &Arc<Self>, host: &OverlayHost) -> crate::Result<()> {
+        host.show_modal(self.clone()).await?;
+        self.scrim.show()?;
+        Ok(())
+    }
+
+    pub async fn is_open(&self) -> bool {
+        self.core.read().await.host.is_some()
+    }
+
+    /// Closes the dialog, the `show` call returns the `result`
+    pub async fn close(&self, result: DialogResult) -> crate::Result<()> {
+        let (host, waiters) = {
+            let mut core = self.core.write().await;
+            (core.host.take(), std::mem::take(&mut core.waiters))
+        };
+        if let Some(host) = host {
+            host.close_popup(self.id()).await?;
+            for tx in waiters {
+                let _ = tx.send(result);
+            }
+            self.dialog_events
+                .send_event(DialogEvent::Closed(result), None)
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        self.scrim.outer_frame().SetSize(size)?;
+        self.scrim
+            .on_event_owned(PanelEvent::Resized(size), source.clone())
+            .await?;
+        let rect = Rect::from_size(size).centered(self.content_size);
+        self.core.write().await.content_rect = rect;
+        let visual = self.content.outer_frame();
+        visual.SetOffset(rect.origin.into())?;
+        visual.SetSize(rect.size.into())?;
+        self.content
+            .on_event_owned(PanelEvent::Resized(rect.size.into()), source)
+            .await
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            PanelEvent::CursorMoved(pos) => {
+                let rect = {
+                    let mut core = self.core.write().await;
+                    core.mouse_pos = Some((*pos).into());
+                    core.content_rect
+                };
+                self.content
+                    .on_event_owned(
+                        PanelEvent::CursorMoved(rect.to_local(*pos).into()),
+                        source.clone(),
+                    )
+                    .await?
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                click_count,
+                seq,
+            } => {
+                let in_content = {
+                    let core = self.core.read().await;
+                    core.mouse_pos
+                        .map_or(false, |pos| core.content_rect.contains(pos))
+                };
+                self.content
+                    .on_event_owned(
+                        PanelEvent::MouseInput {
+                            in_slot: *in_slot && in_content,
+                            state: *state,
+                            button: *button,
+                            click_count: *click_count,
+                            seq: *seq,
+                        },
+                        source.clone(),
+                    )
+                    .await?;
+                if self
+                    .scrim
+                    .mouse_input(*in_slot && !in_content, *state, *button)
+                    .await?
+                {
+                    self.close(DialogResult::None).await?;
+                }
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(VirtualKeyCode::Escape),
+                ..
+            } => self.close(DialogResult::None).await?,
+            _ => self.content.on_event_ref(&event, source.clone()).await?,
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<DialogEvent> for Dialog {
+    fn event_stream(&self) -> EventStream<DialogEvent> {
+        self.dialog_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for Dialog {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Dialog {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Dialog {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+struct HandleState<T> {
+    result: Option<T>,
+    dialog: Weak<Dialog>,
+}
+
+///
+/// Handle passed to the content of the dialog opened by [`show_dialog`]. The content closes
+/// the dialog with the typed result through it.
+///
+pub struct DialogHandle<T> {
+    state: Arc<Mutex<HandleState<T>>>,
+}
+
+impl<T> Clone for DialogHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> DialogHandle<T> {
+    /// Closes the dialog, `show_dialog` returns `Some(result)`
+    pub async fn close(&self, result: T) -> crate::Result<()> {
+        let dialog = {
+            let mut state = self.state.lock().unwrap();
+            state.result = Some(result);
+            state.dialog.upgrade()
+        };
+        if let Some(dialog) = dialog {
+            dialog.close(DialogResult::Primary).await?;
+        }
+        Ok(())
+    }
+    /// Closes the dialog without result, `show_dialog` returns `None`
+    pub async fn cancel(&self) -> crate::Result<()> {
+        let dialog = self.state.lock().unwrap().dialog.upgrade();
+        if let Some(dialog) = dialog {
+            dialog.close(DialogResult::None).await?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Shows the modal dialog with the content created by `content` and waits for the result.
+/// The content receives the [`DialogHandle`] to close the dialog with the result. Returns
+/// `None` if the dialog was cancelled, e.g. by Escape key.
+///
+pub async fn show_dialog<T, F>(
+    host: Arc<OverlayHost>,
+    compositor: Compositor,
+    content_size: impl Into<Size>,
+    content: F,
+) -> crate::Result<Option<T>>
+where
+    F: FnOnce(DialogHandle<T>) -> crate::Result<Arc<dyn Panel>>,
+{
+    let handle = DialogHandle {
+        state: Arc::new(Mutex::new(HandleState {
+            result: None,
+            dialog: Weak::new(),
+        })),
+    };
+    let dialog: Arc<Dialog> = DialogParams::builder()
+        .compositor(compositor)
+        .content(content(handle.clone())?)
+        .content_size(content_size.into())
+        .build()
+        .try_into()?;
+    handle.state.lock().unwrap().dialog = Arc::downgrade(&dialog);
+    dialog.show(host).await?;
+    let result = handle.state.lock().unwrap().result.take();
+    Ok(result)
+}
@@ -0,0 +1,273 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+use async_event_streams::EventBox;
+use async_std::sync::Arc;
+use futures::{channel::oneshot, Future};
+
+thread_local! {
+    // Queues whose handlers are being polled on this thread, innermost last
+    static DISPATCHING: RefCell<Vec<usize>> = RefCell::new(Vec::new());
+}
+
+// Handler's future marking its queue as dispatching while polled, so the events the handler
+// sends back to the same panel are recognized as re-posted
+struct Dispatching<FUT> {
+    queue: usize,
+    future: Pin<Box<FUT>>,
+}
+
+impl<FUT: Future> Future for Dispatching<FUT> {
+    type Output = FUT::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        DISPATCHING.with(|v| v.borrow_mut().push(self.queue));
+        let poll = self.future.as_mut().poll(cx);
+        DISPATCHING.with(|v| v.borrow_mut().pop());
+        poll
+    }
+}
+
+struct Entry<EVT> {
+    event: EVT,
+    source: Option<Arc<EventBox>>,
+    done: oneshot::Sender<crate::Result<()>>,
+}
+
+struct DispatchState<EVT> {
+    busy: bool,
+    queue: VecDeque<Entry<EVT>>,
+}
+
+///
+/// Per-panel event queue which guarantees that one event is fully processed before
+/// processing of the next one starts. If an event handler sends an event back to the same panel
+/// (directly or through children, skins, etc), this event is appended to the queue and processed
+/// after the current one instead of re-entering the handler.
+///
+/// The caller sending the event while another one is processed waits until its event is
+/// processed too, so `on_event` returning means the event is handled. Only the re-posted events
+/// are not waited for, as they can't be processed until the handler posting them returns. The
+/// handler awaiting the other task which sends the event to the same panel deadlocks.
+///
+pub(crate) struct DispatchQueue<EVT> {
+    state: Mutex<DispatchState<EVT>>,
+}
+
+impl<EVT> Default for DispatchQueue<EVT> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(DispatchState {
+                busy: false,
+                queue: VecDeque::new(),
+            }),
+        }
+    }
+}
+
+// Stops the processing if the processing caller is dropped in the middle, so the queue
+// doesn't stay busy forever
+struct ProcessingGuard<'a, EVT> {
+    queue: &'a DispatchQueue<EVT>,
+    armed: bool,
+}
+
+impl<'a, EVT> Drop for ProcessingGuard<'a, EVT> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.queue
+                .stop("processing of the previous event was cancelled".into());
+        }
+    }
+}
+
+impl<EVT> DispatchQueue<EVT> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn id(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    // Returns true if the caller have to process the queue
+    fn push(&self, entry: Entry<EVT>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.queue.push_back(entry);
+        if state.busy {
+            false
+        } else {
+            state.busy = true;
+            true
+        }
+    }
+
+    fn pop(&self) -> Option<Entry<EVT>> {
+        let mut state = self.state.lock().unwrap();
+        let next = state.queue.pop_front();
+        if next.is_none() {
+            state.busy = false;
+        }
+        next
+    }
+
+    // Ends the processing, the callers waiting for the queued events get the error
+    fn stop(&self, reason: String) {
+        let dropped = {
+            let mut state = self.state.lock().unwrap();
+            state.busy = false;
+            state.queue.drain(..).collect::<Vec<_>>()
+        };
+        for entry in dropped {
+            let _ = entry
+                .done
+                .send(Err(crate::Error::EventDropped(reason.clone())));
+        }
+    }
+
+    ///
+    /// Queues the event and, if no other event is being processed, processes the queue with `f`
+    /// until it's empty. Otherwise waits until the processing caller handles the event. On error
+    /// the rest of the queue is dropped, the callers waiting for it get
+    /// [`crate::Error::EventDropped`]. The errors of the re-posted events are returned to the
+    /// processing caller.
+    ///
+    pub async fn dispatch<F, FUT>(
+        &self,
+        event: EVT,
+        source: Option<Arc<EventBox>>,
+        mut f: F,
+    ) -> crate::Result<()>
+    where
+        F: FnMut(EVT, Option<Arc<EventBox>>) -> FUT,
+        FUT: Future<Output = crate::Result<()>>,
+    {
+        let reentrant = DISPATCHING.with(|v| v.borrow().contains(&self.id()));
+        let (done, rx) = oneshot::channel();
+        if !self.push(Entry {
+            event,
+            source,
+            done,
+        }) {
+            if reentrant {
+                return Ok(());
+            }
+            return rx
+                .await
+                .unwrap_or_else(|_| Err(crate::Error::EventDropped("queue is dropped".into())));
+        }
+        // The result of the own event is taken in the loop with the ones nobody waits for
+        drop(rx);
+        let mut guard = ProcessingGuard {
+            queue: self,
+            armed: true,
+        };
+        let mut result = Ok(());
+        while let Some(entry) = self.pop() {
+            let handled = Dispatching {
+                queue: self.id(),
+                future: Box::pin(f(entry.event, entry.source)),
+            }
+            .await;
+            let failure = handled.as_ref().err().map(|e| e.to_string());
+            if let Err(Err(e)) = entry.done.send(handled) {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+            if let Some(failure) = failure {
+                self.stop(failure);
+                break;
+            }
+        }
+        guard.armed = false;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::task::{block_on, yield_now};
+    use futures::{future::LocalBoxFuture, join, FutureExt};
+
+    use super::*;
+
+    // Panel logging the start and the end of the event processing. Event 1 sends 2 and 3 back
+    // to itself, event 10 lets the other callers in, event 20 does the same and fails.
+    #[derive(Default)]
+    struct Logger {
+        dispatch: DispatchQueue<u32>,
+        log: Mutex<Vec<String>>,
+    }
+
+    impl Logger {
+        fn on_event(&self, event: u32) -> LocalBoxFuture<'_, crate::Result<()>> {
+            self.dispatch
+                .dispatch(event, None, move |event, _| self.process_event(event))
+                .boxed_local()
+        }
+
+        async fn process_event(&self, event: u32) -> crate::Result<()> {
+            self.log.lock().unwrap().push(format!("start {}", event));
+            match event {
+                1 => {
+                    self.on_event(2).await?;
+                    self.on_event(3).await?;
+                }
+                10 => yield_now().await,
+                20 => {
+                    yield_now().await;
+                    return Err(crate::Error::BadIndex);
+                }
+                _ => (),
+            }
+            self.log.lock().unwrap().push(format!("end {}", event));
+            Ok(())
+        }
+
+        fn log(&self) -> Vec<String> {
+            self.log.lock().unwrap().clone()
+        }
+    }
+
+    #[test]
+    fn nested_events_are_processed_after_current() {
+        let logger = Logger::default();
+        block_on(logger.on_event(1)).unwrap();
+        assert_eq!(
+            logger.log(),
+            ["start 1", "end 1", "start 2", "end 2", "start 3", "end 3"]
+        );
+    }
+
+    #[test]
+    fn concurrent_events_are_processed_in_order() {
+        let logger = Logger::default();
+        let (first, second, third) =
+            block_on(async { join!(logger.on_event(10), logger.on_event(4), logger.on_event(5)) });
+        assert!(first.is_ok() && second.is_ok() && third.is_ok());
+        assert_eq!(
+            logger.log(),
+            ["start 10", "end 10", "start 4", "end 4", "start 5", "end 5"]
+        );
+        // The queue is free again
+        block_on(logger.on_event(6)).unwrap();
+        assert_eq!(logger.log().last().unwrap(), "end 6");
+    }
+
+    #[test]
+    fn failure_drops_queued_events() {
+        let logger = Logger::default();
+        let (first, second) = block_on(async { join!(logger.on_event(20), logger.on_event(4)) });
+        assert!(matches!(first, Err(crate::Error::BadIndex)));
+        assert!(matches!(second, Err(crate::Error::EventDropped(_))));
+        assert_eq!(logger.log(), ["start 20"]);
+        block_on(logger.on_event(5)).unwrap();
+        assert_eq!(logger.log(), ["start 20", "start 5", "end 5"]);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex, Weak},
+};
+
+use async_event_streams::{EventSource, EventStream, EventStreams};
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::Composition::{ContainerVisual, Visual},
+};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+use crate::geometry::{Point, Rect};
+
+use super::{panel::window_offset, Panel, PanelEvent};
+
+/// Data carried by the drag, the drop target downcasts it to the type it expects
+pub type DragPayload = Arc<dyn Any + Send + Sync>;
+
+#[derive(Clone)]
+pub enum DragDropEvent {
+    Started(DragPayload),
+    /// The cursor entered the drop target with given id accepting the payload
+    Enter(usize),
+    /// The cursor moved over the drop target, the position is in the target's coordinates
+    Over(usize, Vector2),
+    Leave(usize),
+    /// The payload is dropped on the target
+    Drop(usize, DragPayload),
+    /// The drag ended outside of accepting targets or by Escape key
+    Cancelled,
+}
+
+///
+/// Panel which can receive dropped payloads. The target is registered in [`DragDrop`] and
+/// follows the drag by its `DragDropEvent`s with the target's id.
+///
+pub trait DropTarget: Panel {
+    /// Decides if the payload can be dropped on this target
+    fn accepts(&self, payload: &DragPayload) -> bool;
+}
+
+struct Drag {
+    payload: DragPayload,
+    visual: Visual,
+    hot_spot: Vector2,
+    target: Option<usize>,
+}
+
+#[derive(Default)]
+struct State {
+    drag: Option<Drag>,
+    targets: Vec<Weak<dyn DropTarget>>,
+}
+
+///
+/// Drag-and-drop between the panels of the window. The panel starts the drag with the payload
+/// and the visual which follows the cursor over all panels. While dragging, cursor moves are
+/// consumed by the drag, the release of the left button drops the payload on the accepting
+/// target under the cursor and is then delivered to the panel tree as usual.
+///
+/// Hit test uses the offsets and sizes of the targets' visuals, so it ignores other transforms.
+///
+#[derive(Clone)]
+pub struct DragDrop {
+    root: ContainerVisual,
+    state: Arc<Mutex<State>>,
+    drag_drop_events: Arc<EventStreams<DragDropEvent>>,
+}
+
+impl DragDrop {
+    pub(super) fn new(root: ContainerVisual) -> Self {
+        Self {
+            root,
+            state: Arc::new(Mutex::new(State::default())),
+            drag_drop_events: Arc::new(EventStreams::new()),
+        }
+    }
+
+    /// Registers the drop target, the target is forgotten when dropped
+    pub fn register_target(&self, target: Arc<dyn DropTarget>) {
+        let mut state = self.state.lock().unwrap();
+        state.targets.retain(|v| v.strong_count() > 0);
+        state.targets.push(Arc::downgrade(&target));
+    }
+
+    pub fn unregister_target(&self, id: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .targets
+            .retain(|v| v.upgrade().map_or(false, |v| v.id() != id));
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.state.lock().unwrap().drag.is_some()
+    }
+
+    ///
+    /// Starts the drag. The `visual` is shown above all panels with the `hot_spot` point of it
+    /// under the cursor. The drag already in progress is cancelled.
+    ///
+    pub async fn start(
+        &self,
+        payload: DragPayload,
+        visual: Visual,
+        hot_spot: Vector2,
+    ) -> crate::Result<()> {
+        self.cancel().await?;
+        self.root.Children()?.InsertAtTop(&visual)?;
+        self.state.lock().unwrap().drag = Some(Drag {
+            payload: payload.clone(),
+            visual,
+            hot_spot,
+            target: None,
+        });
+        self.drag_drop_events
+            .send_event(DragDropEvent::Started(payload), None)
+            .await;
+        Ok(())
+    }
+
+    pub async fn cancel(&self) -> crate::Result<()> {
+        let drag = self.state.lock().unwrap().drag.take();
+        if let Some(drag) = drag {
+            self.finish(drag, false).await?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&self, drag: Drag, drop: bool) -> crate::Result<()> {
+        self.root.Children()?.Remove(&drag.visual)?;
+        let event = match drag.target {
+            Some(target) if drop => DragDropEvent::Drop(target, drag.payload),
+            Some(target) => {
+                self.drag_drop_events
+                    .send_event(DragDropEvent::Leave(target), None)
+                    .await;
+                DragDropEvent::Cancelled
+            }
+            None => DragDropEvent::Cancelled,
+        };
+        self.drag_drop_events.send_event(event, None).await;
+        Ok(())
+    }
+
+    fn hit_test(
+        &self,
+        payload: &DragPayload,
+        pos: Vector2,
+    ) -> crate::Result<Option<(usize, Vector2)>> {
+        let targets = {
+            let mut state = self.state.lock().unwrap();
+            state.targets.retain(|v| v.strong_count() > 0);
+            state.targets.clone()
+        };
+        // Targets registered later are considered to be above
+        for target in targets.iter().rev().filter_map(|v| v.upgrade()) {
+            let frame = target.outer_frame();
+            if !frame.IsVisible()? || !target.accepts(payload) {
+                continue;
+            }
+            let local: Vector2 = Rect::new(window_offset(&frame)?.into(), frame.Size()?.into())
+                .to_local(pos)
+                .into();
+            if Rect::from_size(frame.Size()?).contains(local) {
+                return Ok(Some((target.id(), local)));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn cursor_moved(&self, pos: Vector2) -> crate::Result<()> {
+        let (payload, old_target) = match &self.state.lock().unwrap().drag {
+            Some(drag) => {
+                drag.visual.SetOffset(
+                    Point::new(pos.X - drag.hot_spot.X, pos.Y - drag.hot_spot.Y).into(),
+                )?;
+                (drag.payload.clone(), drag.target)
+            }
+            None => return Ok(()),
+        };
+        let hit = self.hit_test(&payload, pos)?;
+        let target = hit.map(|(id, _)| id);
+        if let Some(drag) = &mut self.state.lock().unwrap().drag {
+            drag.target = target;
+        }
+        if target != old_target {
+            if let Some(old) = old_target {
+                self.drag_drop_events
+                    .send_event(DragDropEvent::Leave(old), None)
+                    .await;
+            }
+            if let Some(new) = target {
+                self.drag_drop_events
+                    .send_event(DragDropEvent::Enter(new), None)
+                    .await;
+            }
+        }
+        if let Some((id, local)) = hit {
+            self.drag_drop_events
+                .send_event(DragDropEvent::Over(id, local), None)
+                .await;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Handles the window event while dragging, returns true if the event is consumed and
+    /// shouldn't be delivered to the panel tree
+    ///
+    pub(super) async fn process_event(
+        &self,
+        event: &PanelEvent,
+        mouse_pos: Vector2,
+    ) -> crate::Result<bool> {
+        if !self.is_dragging() {
+            return Ok(false);
+        }
+        match event {
+            PanelEvent::CursorMoved(_) => {
+                self.cursor_moved(mouse_pos).await?;
+                Ok(true)
+            }
+            PanelEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let drag = self.state.lock().unwrap().drag.take();
+                if let Some(drag) = drag {
+                    self.finish(drag, true).await?;
+                }
+                // The source panel still receives the release to finish its press
+                Ok(false)
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(VirtualKeyCode::Escape),
+                ..
+            } => {
+                self.cancel().await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+impl EventSource<DragDropEvent> for DragDrop {
+    fn event_stream(&self) -> EventStream<DragDropEvent> {
+        self.drag_drop_events.create_event_stream()
+    }
+}
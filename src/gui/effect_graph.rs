@@ -0,0 +1,205 @@
+use windows::{
+    core::{implement, Interface, GUID, HSTRING, PCWSTR},
+    Foundation::{IPropertyValue, PropertyValue},
+    Graphics::Effects::{
+        IGraphicsEffect, IGraphicsEffectSource, IGraphicsEffectSource_Impl, IGraphicsEffect_Impl,
+    },
+    Win32::{
+        Foundation::E_INVALIDARG,
+        Graphics::Direct2D::{
+            CLSID_D2D1AlphaMask, CLSID_D2D1GaussianBlur, CLSID_D2D1Opacity, CLSID_D2D1Saturation,
+            CLSID_D2D1Tint,
+        },
+        System::WinRT::Composition::{
+            IGraphicsEffectD2D1Interop, IGraphicsEffectD2D1Interop_Impl,
+            GRAPHICS_EFFECT_PROPERTY_MAPPING, GRAPHICS_EFFECT_PROPERTY_MAPPING_COLOR_TO_VECTOR4,
+            GRAPHICS_EFFECT_PROPERTY_MAPPING_DIRECT,
+        },
+    },
+    UI::{Color, Composition::CompositionEffectSourceParameter},
+};
+
+// D2D1_GAUSSIANBLUR_PROP_BORDER_MODE value keeping the edges opaque
+const D2D1_BORDER_MODE_HARD: u32 = 1;
+
+// Property of the effect, the compositor finds the animatable ones by the name
+struct Property {
+    name: &'static str,
+    value: IPropertyValue,
+    mapping: GRAPHICS_EFFECT_PROPERTY_MAPPING,
+}
+
+///
+/// Node of the effect graph for `Compositor::CreateEffectFactory`: the Direct2D effect with
+/// its properties and sources. The sources are the other nodes or the named parameters set
+/// on the brush by `SetSourceParameter`. The property of the node named "Blur" is animated
+/// on the brush as "Blur.Deviation".
+///
+#[implement(IGraphicsEffect, IGraphicsEffectSource, IGraphicsEffectD2D1Interop)]
+pub(crate) struct D2D1Effect {
+    name: HSTRING,
+    id: GUID,
+    properties: Vec<Property>,
+    sources: Vec<IGraphicsEffectSource>,
+}
+
+impl D2D1Effect {
+    /// Gaussian blur with the standard deviation in pixels, animated as "Deviation"
+    pub fn gaussian_blur(
+        name: &str,
+        source: IGraphicsEffectSource,
+        deviation: f32,
+    ) -> windows::core::Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            id: CLSID_D2D1GaussianBlur,
+            properties: vec![
+                Property {
+                    name: "Deviation",
+                    value: PropertyValue::CreateSingle(deviation)?.cast()?,
+                    mapping: GRAPHICS_EFFECT_PROPERTY_MAPPING_DIRECT,
+                },
+                // The default balanced optimization
+                Property {
+                    name: "Optimization",
+                    value: PropertyValue::CreateUInt32(1)?.cast()?,
+                    mapping: GRAPHICS_EFFECT_PROPERTY_MAPPING_DIRECT,
+                },
+                // The backdrop is blurred without the transparent halo at the edges
+                Property {
+                    name: "BorderMode",
+                    value: PropertyValue::CreateUInt32(D2D1_BORDER_MODE_HARD)?.cast()?,
+                    mapping: GRAPHICS_EFFECT_PROPERTY_MAPPING_DIRECT,
+                },
+            ],
+            sources: vec![source],
+        })
+    }
+
+    /// Saturation from 0 for grayscale to 1 for the unchanged colors, animated as "Saturation"
+    pub fn saturation(
+        name: &str,
+        source: IGraphicsEffectSource,
+        saturation: f32,
+    ) -> windows::core::Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            id: CLSID_D2D1Saturation,
+            properties: vec![Property {
+                name: "Saturation",
+                value: PropertyValue::CreateSingle(saturation)?.cast()?,
+                mapping: GRAPHICS_EFFECT_PROPERTY_MAPPING_DIRECT,
+            }],
+            sources: vec![source],
+        })
+    }
+
+    /// Multiplies the colors by the color, animated as "Color"
+    pub fn tint(
+        name: &str,
+        source: IGraphicsEffectSource,
+        color: Color,
+    ) -> windows::core::Result<Self> {
+        let vector = [color.R, color.G, color.B, color.A].map(|v| v as f32 / 255.);
+        Ok(Self {
+            name: name.into(),
+            id: CLSID_D2D1Tint,
+            properties: vec![Property {
+                name: "Color",
+                value: PropertyValue::CreateSingleArray(&vector)?.cast()?,
+                mapping: GRAPHICS_EFFECT_PROPERTY_MAPPING_COLOR_TO_VECTOR4,
+            }],
+            sources: vec![source],
+        })
+    }
+
+    /// Multiplies the alpha by the opacity from 0 to 1, animated as "Opacity"
+    pub fn opacity(
+        name: &str,
+        source: IGraphicsEffectSource,
+        opacity: f32,
+    ) -> windows::core::Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            id: CLSID_D2D1Opacity,
+            properties: vec![Property {
+                name: "Opacity",
+                value: PropertyValue::CreateSingle(opacity)?.cast()?,
+                mapping: GRAPHICS_EFFECT_PROPERTY_MAPPING_DIRECT,
+            }],
+            sources: vec![source],
+        })
+    }
+
+    /// Multiplies the alpha of the source by the alpha of the mask
+    pub fn alpha_mask(
+        name: &str,
+        source: IGraphicsEffectSource,
+        mask: IGraphicsEffectSource,
+    ) -> windows::core::Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            id: CLSID_D2D1AlphaMask,
+            properties: Vec::new(),
+            sources: vec![source, mask],
+        })
+    }
+}
+
+/// Source of the effect graph set later on the brush by `SetSourceParameter(name, brush)`
+pub(crate) fn source_parameter(name: &str) -> windows::core::Result<IGraphicsEffectSource> {
+    CompositionEffectSourceParameter::Create(&HSTRING::from(name))?.cast()
+}
+
+impl IGraphicsEffect_Impl for D2D1Effect {
+    fn Name(&self) -> windows::core::Result<HSTRING> {
+        Ok(self.name.clone())
+    }
+    fn SetName(&self, _name: &HSTRING) -> windows::core::Result<()> {
+        Ok(())
+    }
+}
+
+impl IGraphicsEffectSource_Impl for D2D1Effect {}
+
+impl IGraphicsEffectD2D1Interop_Impl for D2D1Effect {
+    fn GetEffectId(&self) -> windows::core::Result<GUID> {
+        Ok(self.id)
+    }
+    fn GetNamedPropertyMapping(
+        &self,
+        name: &PCWSTR,
+        index: *mut u32,
+        mapping: *mut GRAPHICS_EFFECT_PROPERTY_MAPPING,
+    ) -> windows::core::Result<()> {
+        let name = unsafe { name.to_string() }.map_err(|_| E_INVALIDARG)?;
+        let position = self
+            .properties
+            .iter()
+            .position(|v| v.name.eq_ignore_ascii_case(&name))
+            .ok_or(E_INVALIDARG)?;
+        unsafe {
+            *index = position as u32;
+            *mapping = self.properties[position].mapping;
+        }
+        Ok(())
+    }
+    fn GetPropertyCount(&self) -> windows::core::Result<u32> {
+        Ok(self.properties.len() as u32)
+    }
+    fn GetProperty(&self, index: u32) -> windows::core::Result<IPropertyValue> {
+        self.properties
+            .get(index as usize)
+            .map(|v| v.value.clone())
+            .ok_or_else(|| E_INVALIDARG.into())
+    }
+    fn GetSource(&self, index: u32) -> windows::core::Result<IGraphicsEffectSource> {
+        self.sources
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| E_INVALIDARG.into())
+    }
+    fn GetSourceCount(&self) -> windows::core::Result<u32> {
+        Ok(self.sources.len() as u32)
+    }
+}
@@ -0,0 +1,320 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::{Interface, HSTRING},
+    Foundation::{Collections::IIterable, Numerics::Vector2},
+    Graphics::Effects::{IGraphicsEffect, IGraphicsEffectSource},
+    Win32::Foundation::E_INVALIDARG,
+    UI::{
+        Color,
+        Composition::{
+            CompositionAnimation, CompositionBrush, CompositionEffectBrush,
+            CompositionSurfaceBrush, CompositionVisualSurface, Compositor, ContainerVisual,
+            SpriteVisual, Visual,
+        },
+    },
+};
+
+use super::{
+    attach,
+    dispatch::DispatchQueue,
+    effect_graph::{source_parameter, D2D1Effect},
+    Panel, PanelEvent,
+};
+
+const SOURCE: &str = "Source";
+
+/// Effect applied by the [`Effects`] panel
+#[derive(PartialEq, Clone, Debug)]
+pub enum Effect {
+    /// Gaussian blur with the standard deviation in pixels
+    GaussianBlur(f32),
+    /// Saturation from 0 for grayscale to 1 for the original colors
+    Saturation(f32),
+    /// Multiplies the colors by the color
+    Tint(Color),
+    /// Multiplies the alpha by the opacity from 0 to 1
+    Opacity(f32),
+    /// Multiplies the alpha by the alpha of the brush, e.g. the gradient fading the edge
+    OpacityMask(CompositionBrush),
+}
+
+impl Effect {
+    // Name of the effect's animatable property
+    fn property(&self) -> Option<&'static str> {
+        match self {
+            Effect::GaussianBlur(_) => Some("Deviation"),
+            Effect::Saturation(_) => Some("Saturation"),
+            Effect::Tint(_) => Some("Color"),
+            Effect::Opacity(_) => Some("Opacity"),
+            Effect::OpacityMask(_) => None,
+        }
+    }
+}
+
+// Name of the effect's node in the graph and of its mask parameter
+fn node_name(index: usize) -> String {
+    format!("Effect{}", index)
+}
+
+fn mask_name(index: usize) -> String {
+    format!("Mask{}", index)
+}
+
+// Brush drawing the source through the effects in order, the source itself without effects
+fn create_brush(
+    compositor: &Compositor,
+    effects: &[Effect],
+    source: &CompositionSurfaceBrush,
+) -> crate::Result<CompositionBrush> {
+    let mut graph: IGraphicsEffectSource = source_parameter(SOURCE)?;
+    let mut properties = Vec::new();
+    for (index, effect) in effects.iter().enumerate() {
+        let name = node_name(index);
+        let node = match effect {
+            Effect::GaussianBlur(deviation) => D2D1Effect::gaussian_blur(&name, graph, *deviation),
+            Effect::Saturation(saturation) => D2D1Effect::saturation(&name, graph, *saturation),
+            Effect::Tint(color) => D2D1Effect::tint(&name, graph, *color),
+            Effect::Opacity(opacity) => D2D1Effect::opacity(&name, graph, *opacity),
+            Effect::OpacityMask(_) => {
+                D2D1Effect::alpha_mask(&name, graph, source_parameter(&mask_name(index))?)
+            }
+        }?;
+        graph = node.into();
+        if let Some(property) = effect.property() {
+            properties.push(HSTRING::from(format!("{}.{}", name, property)));
+        }
+    }
+    if effects.is_empty() {
+        return Ok(source.clone().into());
+    }
+    let effect: IGraphicsEffect = graph.cast()?;
+    let properties = IIterable::<HSTRING>::try_from(properties)?;
+    let brush = compositor
+        .CreateEffectFactoryWithProperties(&effect, &properties)?
+        .CreateBrush()?;
+    brush.SetSourceParameter(&HSTRING::from(SOURCE), source)?;
+    for (index, effect) in effects.iter().enumerate() {
+        if let Effect::OpacityMask(mask) = effect {
+            brush.SetSourceParameter(&HSTRING::from(mask_name(index)), mask)?;
+        }
+    }
+    Ok(brush.into())
+}
+
+struct Core {
+    effects: Vec<Effect>,
+    brush: CompositionBrush,
+}
+
+///
+/// Container applying the chain of effects to its child: blur, saturation, tint, opacity and
+/// opacity mask. The child is rendered to the surface which is drawn through the effects, it
+/// still receives all the events as usual. The effects' parameters are set and animated by
+/// the effect's index:
+///
+/// ```ignore
+/// let animation = compositor.CreateScalarKeyFrameAnimation()?;
+/// animation.InsertKeyFrame(1., 20.)?;
+/// effects.start_animation(0, &animation).await?;
+/// ```
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Effects {
+    compositor: Compositor,
+    container: ContainerVisual,
+    sprite: SpriteVisual,
+    // Container of the child outside of the tree, rendered by the visual surface
+    source: ContainerVisual,
+    surface: CompositionVisualSurface,
+    surface_brush: CompositionSurfaceBrush,
+    child: Arc<dyn Panel>,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct EffectsParams {
+    compositor: Compositor,
+    child: Arc<dyn Panel>,
+    /// Effects applied in order, each to the result of the previous one
+    #[builder(default)]
+    effects: Vec<Effect>,
+}
+
+impl TryFrom<EffectsParams> for Effects {
+    type Error = crate::Error;
+
+    fn try_from(value: EffectsParams) -> crate::Result<Self> {
+        let compositor = value.compositor;
+        let container = compositor.CreateContainerVisual()?;
+        let sprite = compositor.CreateSpriteVisual()?;
+        container.Children()?.InsertAtTop(&sprite)?;
+        let source = compositor.CreateContainerVisual()?;
+        attach(&source, &*value.child)?;
+        let surface = compositor.CreateVisualSurface()?;
+        surface.SetSourceVisual(&source)?;
+        let surface_brush = compositor.CreateSurfaceBrushWithSurface(&surface)?;
+        let brush = create_brush(&compositor, &value.effects, &surface_brush)?;
+        sprite.SetBrush(&brush)?;
+        Ok(Effects {
+            compositor,
+            container,
+            sprite,
+            source,
+            surface,
+            surface_brush,
+            child: value.child,
+            core: RwLock::new(Core {
+                effects: value.effects,
+                brush,
+            }),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<EffectsParams> for Arc<Effects> {
+    type Error = crate::Error;
+
+    fn try_from(value: EffectsParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Effects {
+    pub fn child(&self) -> Arc<dyn Panel> {
+        self.child.clone()
+    }
+
+    pub async fn effects(&self) -> Vec<Effect> {
+        self.core.read().await.effects.clone()
+    }
+
+    /// Replaces the effects, the running animations of the old ones stop
+    pub async fn set_effects(&self, effects: Vec<Effect>) -> crate::Result<()> {
+        let brush = create_brush(&self.compositor, &effects, &self.surface_brush)?;
+        self.sprite.SetBrush(&brush)?;
+        let mut core = self.core.write().await;
+        core.effects = effects;
+        core.brush = brush;
+        Ok(())
+    }
+
+    ///
+    /// Name of the animatable parameter of the effect on the brush, e.g. "Effect0.Deviation",
+    /// `None` if the effect has no parameter
+    ///
+    pub async fn property_name(&self, index: usize) -> Option<String> {
+        let core = self.core.read().await;
+        let property = core.effects.get(index)?.property()?;
+        Some(format!("{}.{}", node_name(index), property))
+    }
+
+    // The effect brush and the name of the effect's parameter on it
+    async fn property(&self, index: usize) -> crate::Result<(CompositionEffectBrush, HSTRING)> {
+        let name = self
+            .property_name(index)
+            .await
+            .ok_or_else(|| windows::core::Error::from(E_INVALIDARG))?;
+        let brush = self.core.read().await.brush.cast()?;
+        Ok((brush, name.into()))
+    }
+
+    /// Sets the scalar parameter: the blur deviation, the saturation or the opacity
+    pub async fn set_scalar(&self, index: usize, value: f32) -> crate::Result<()> {
+        let (brush, name) = self.property(index).await?;
+        brush.Properties()?.InsertScalar(&name, value)?;
+        Ok(())
+    }
+
+    /// Sets the color of the tint
+    pub async fn set_color(&self, index: usize, color: Color) -> crate::Result<()> {
+        let (brush, name) = self.property(index).await?;
+        brush.Properties()?.InsertColor(&name, color)?;
+        Ok(())
+    }
+
+    /// Animates the parameter of the effect, e.g. by the scalar animation for the blur
+    pub async fn start_animation(
+        &self,
+        index: usize,
+        animation: &impl Interface,
+    ) -> crate::Result<()> {
+        let (brush, name) = self.property(index).await?;
+        let animation: CompositionAnimation = animation.cast()?;
+        brush.StartAnimation(&name, &animation)?;
+        Ok(())
+    }
+
+    pub async fn stop_animation(&self, index: usize) -> crate::Result<()> {
+        let (brush, name) = self.property(index).await?;
+        brush.StopAnimation(&name)?;
+        Ok(())
+    }
+
+    fn resize(&self, size: Vector2) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        self.sprite.SetSize(size)?;
+        self.source.SetSize(size)?;
+        self.child.outer_frame().SetSize(size)?;
+        self.surface.SetSourceSize(size)?;
+        Ok(())
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = &event {
+            self.resize(*size)?;
+        }
+        self.child.on_event_ref(&event, source.clone()).await?;
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for Effects {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Effects {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Effects {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use super::PanelEvent;
+
+///
+/// Middleware receiving the window's events before the panel tree. The filter returns the
+/// event to pass it further, possibly changed, or `None` to swallow it.
+///
+#[async_trait]
+pub trait EventFilter: Send + Sync {
+    async fn filter(&self, event: PanelEvent) -> crate::Result<Option<PanelEvent>>;
+}
+
+#[async_trait]
+impl<F: Fn(PanelEvent) -> Option<PanelEvent> + Send + Sync> EventFilter for F {
+    async fn filter(&self, event: PanelEvent) -> crate::Result<Option<PanelEvent>> {
+        Ok(self(event))
+    }
+}
+
+/// Registration of the filter, used to remove it
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub struct EventFilterId(u64);
+
+#[derive(Default)]
+struct State {
+    // Sorted by order, filters with the same order keep the order of registration
+    filters: Vec<(EventFilterId, i32, Arc<dyn EventFilter>)>,
+    next_id: u64,
+}
+
+///
+/// Ordered chain of the event filters of the window, e.g. global shortcuts, input recording
+/// or kiosk lockdown. Each event passes the filters with lower order first, and reaches
+/// the pointer capture, drag-and-drop and the root panel only if none of them swallowed it.
+///
+#[derive(Clone, Default)]
+pub struct EventFilters {
+    state: Arc<Mutex<State>>,
+}
+
+impl EventFilters {
+    pub fn add(&self, order: i32, filter: Arc<dyn EventFilter>) -> EventFilterId {
+        let mut state = self.state.lock().unwrap();
+        let id = EventFilterId(state.next_id);
+        state.next_id += 1;
+        let index = state.filters.partition_point(|(_, v, _)| *v <= order);
+        state.filters.insert(index, (id, order, filter));
+        id
+    }
+
+    /// Returns false if the filter was already removed
+    pub fn remove(&self, id: EventFilterId) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let len = state.filters.len();
+        state.filters.retain(|(v, _, _)| *v != id);
+        state.filters.len() != len
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().unwrap().filters.clear();
+    }
+
+    /// Passes the event through the chain, `None` if it's swallowed
+    pub(super) async fn apply(&self, event: PanelEvent) -> crate::Result<Option<PanelEvent>> {
+        let filters = self
+            .state
+            .lock()
+            .unwrap()
+            .filters
+            .iter()
+            .map(|(_, _, filter)| filter.clone())
+            .collect::<Vec<_>>();
+        let mut event = event;
+        for filter in filters {
+            match filter.filter(event).await? {
+                Some(v) => event = v,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(event))
+    }
+}
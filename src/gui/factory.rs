@@ -0,0 +1,76 @@
+use async_std::sync::Arc;
+use futures::task::Spawn;
+use windows::UI::Composition::Compositor;
+
+use super::Context;
+
+///
+/// Compositor and spawner of the parent handed to the closures creating its children, so
+/// the child's params are filled without the `compositor(value.compositor.clone())` chains:
+///
+/// ```ignore
+/// let text: Arc<Text> = factory.create(|compositor, spawner| {
+///     TextParams::builder()
+///         .compositor(compositor)
+///         .spawner(spawner)
+///         .text("Hello".into())
+///         .build()
+/// })?;
+/// ```
+///
+/// The factory is cheap to clone and may be shared down the tree in the [`Context`]. It also
+/// carries the context of the parent, which the closures pass to the children's params.
+///
+#[derive(Clone)]
+pub struct PanelFactory<S: Spawn + Clone> {
+    compositor: Compositor,
+    spawner: S,
+    context: Context,
+}
+
+impl<S: Spawn + Clone> PanelFactory<S> {
+    pub fn new(compositor: Compositor, spawner: S) -> Self {
+        Self {
+            compositor,
+            spawner,
+            context: Context::new(),
+        }
+    }
+
+    /// The factory handing `context` to the children
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn compositor(&self) -> Compositor {
+        self.compositor.clone()
+    }
+
+    pub fn spawner(&self) -> S {
+        self.spawner.clone()
+    }
+
+    pub fn context(&self) -> Context {
+        self.context.clone()
+    }
+
+    /// Creates the panel from the params built by `f` with this factory's compositor and spawner
+    pub fn create<P, X>(&self, f: impl FnOnce(Compositor, S) -> X) -> crate::Result<Arc<P>>
+    where
+        X: TryInto<Arc<P>, Error = crate::Error>,
+    {
+        f(self.compositor(), self.spawner()).try_into()
+    }
+
+    /// Same as `create` for the params without the spawner
+    pub fn create_with_compositor<P, X>(
+        &self,
+        f: impl FnOnce(Compositor) -> X,
+    ) -> crate::Result<Arc<P>>
+    where
+        X: TryInto<Arc<P>, Error = crate::Error>,
+    {
+        f(self.compositor()).try_into()
+    }
+}
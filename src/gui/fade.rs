@@ -0,0 +1,80 @@
+use std::{sync::Mutex, time::Duration};
+
+use futures::channel::oneshot;
+use windows::{
+    core::HSTRING,
+    Foundation::TypedEventHandler,
+    UI::Composition::{CompositionBatchTypes, Visual},
+};
+
+use super::{animation_stepper::start_animation, set_visible, time_span, Panel};
+
+pub fn opacity<T: Panel + ?Sized>(panel: &T) -> crate::Result<f32> {
+    Ok(panel.outer_frame().Opacity()?)
+}
+
+/// Sets the opacity of the panel immediately, stopping the fade if any
+pub fn set_opacity<T: Panel + ?Sized>(panel: &T, opacity: f32) -> crate::Result<()> {
+    let visual = panel.outer_frame();
+    visual.StopAnimation(&HSTRING::from("Opacity"))?;
+    visual.SetOpacity(opacity)?;
+    Ok(())
+}
+
+///
+/// Shows the panel and animates its opacity to 1. Completes when the animation ends,
+/// including the case when it's interrupted by another fade.
+///
+pub async fn fade_in<T: Panel + ?Sized>(panel: &T, duration: Duration) -> crate::Result<()> {
+    let visual = panel.outer_frame();
+    let from = if visual.IsVisible()? {
+        visual.Opacity()?
+    } else {
+        0.
+    };
+    set_visible(panel, true).await?;
+    fade(&visual, from, 1., duration).await
+}
+
+///
+/// Animates the opacity of the panel to 0 and hides it. The panel stays visible if it's
+/// faded in again before the fade out ends.
+///
+pub async fn fade_out<T: Panel + ?Sized>(panel: &T, duration: Duration) -> crate::Result<()> {
+    let visual = panel.outer_frame();
+    if !visual.IsVisible()? {
+        return Ok(());
+    }
+    fade(&visual, visual.Opacity()?, 0., duration).await?;
+    if visual.Opacity()? == 0. {
+        set_visible(panel, false).await?;
+    }
+    Ok(())
+}
+
+async fn fade(visual: &Visual, from: f32, to: f32, duration: Duration) -> crate::Result<()> {
+    visual.SetOpacity(to)?;
+    if duration.is_zero() {
+        visual.StopAnimation(&HSTRING::from("Opacity"))?;
+        return Ok(());
+    }
+    let compositor = visual.Compositor()?;
+    let batch = compositor.CreateScopedBatch(CompositionBatchTypes::Animation)?;
+    let animation = compositor.CreateScalarKeyFrameAnimation()?;
+    animation.InsertKeyFrame(0., from)?;
+    animation.InsertKeyFrame(1., to)?;
+    animation.SetDuration(time_span(duration))?;
+    start_animation(visual, "Opacity", &animation)?;
+    batch.End()?;
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    batch.Completed(&TypedEventHandler::new(move |_, _| {
+        if let Some(tx) = tx.lock().unwrap().take() {
+            let _ = tx.send(());
+        }
+        Ok(())
+    }))?;
+    // The sender is dropped without sending only if the batch is destroyed, nothing to wait then
+    let _ = rx.await;
+    Ok(())
+}
@@ -0,0 +1,807 @@
+use std::{borrow::Cow, ops::Range};
+
+use async_event_streams::{
+    spawn_event_pipe, EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use futures::task::Spawn;
+use regex::{Captures, Regex, RegexBuilder};
+use typed_builder::TypedBuilder;
+use windows::UI::{
+    Colors,
+    Composition::{Compositor, ContainerVisual, Visual},
+};
+use winit::event::{ElementState, VirtualKeyCode};
+
+use crate::{geometry::Alignment, on_err};
+
+use super::{
+    attach, dispatch::DispatchQueue, Button, ButtonEvent, ButtonParams, CellLimit, Context, Panel,
+    PanelEvent, Ribbon, RibbonOrientation, RibbonParams, SimpleButtonSkin, SimpleButtonSkinParams,
+    SimpleToggleButtonSkin, SimpleToggleButtonSkinParams, Text, TextDocumentEvent, TextEdit,
+    TextEditor, TextEditorParams, TextLayout, TextParams, Theme, ToggleButton, ToggleButtonEvent,
+    ToggleButtonParams,
+};
+
+const OPTION_WIDTH: f32 = 32.;
+const ARROW_WIDTH: f32 = 32.;
+const STATUS_WIDTH: f32 = 96.;
+const REPLACE_WIDTH: f32 = 96.;
+
+///
+/// Text widget the [`FindBar`] searches in. The ranges are in bytes of the text returned
+/// by `search_text`.
+///
+#[async_trait]
+pub trait Searchable: Send + Sync {
+    async fn search_text(&self) -> String;
+    /// The search starts from the selection, the next match is the one after it
+    async fn search_selection(&self) -> Range<usize>;
+    /// Selects the match and scrolls it into view
+    async fn show_match(&self, range: Range<usize>) -> crate::Result<()>;
+    /// Applies the edits as one undoable change, returns false if the widget is read-only
+    async fn replace(&self, edits: Vec<TextEdit>) -> crate::Result<bool>;
+}
+
+/// What to search for
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct SearchQuery {
+    pub pattern: String,
+    pub match_case: bool,
+    /// The match can't start or end inside the word
+    pub whole_word: bool,
+    /// The pattern is the regular expression, the replacement may refer to its groups as `$1`
+    /// or `${name}`
+    pub regex: bool,
+}
+
+impl SearchQuery {
+    fn compile(&self) -> crate::Result<Regex> {
+        let pattern = if self.regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        let pattern = if self.whole_word {
+            format!(r"\b(?:{})\b", pattern)
+        } else {
+            pattern
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.match_case)
+            .multi_line(true)
+            .build()
+            .map_err(|e| crate::Error::BadPattern(e.to_string()))
+    }
+
+    fn substitute(&self, captures: &Captures, replacement: &str) -> String {
+        if self.regex {
+            let mut text = String::new();
+            captures.expand(replacement, &mut text);
+            text
+        } else {
+            replacement.to_string()
+        }
+    }
+
+    /// Ranges of the matches in the text, the empty matches are skipped
+    pub fn find_all(&self, text: &str) -> crate::Result<Vec<Range<usize>>> {
+        if self.pattern.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .compile()?
+            .find_iter(text)
+            .map(|m| m.range())
+            .filter(|r| !r.is_empty())
+            .collect())
+    }
+
+    ///
+    /// Replacement of the match at the range with the regex groups expanded, `None` if the
+    /// range is not the match, e.g. the text was edited after the search
+    ///
+    pub fn expand(
+        &self,
+        text: &str,
+        range: Range<usize>,
+        replacement: &str,
+    ) -> crate::Result<Option<String>> {
+        if self.pattern.is_empty() || range.is_empty() || range.end > text.len() {
+            return Ok(None);
+        }
+        Ok(self
+            .compile()?
+            .captures_at(text, range.start)
+            .filter(|c| c.get(0).map(|m| m.range()) == Some(range.clone()))
+            .map(|c| self.substitute(&c, replacement)))
+    }
+
+    ///
+    /// Edits replacing all matches. They go from the end of the text, so each one applies
+    /// to the text not shifted by the previous ones.
+    ///
+    pub fn replace_all_edits(&self, text: &str, replacement: &str) -> crate::Result<Vec<TextEdit>> {
+        if self.pattern.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut edits: Vec<_> = self
+            .compile()?
+            .captures_iter(text)
+            .filter_map(|c| {
+                let range = c.get(0)?.range();
+                (!range.is_empty()).then(|| TextEdit::new(range, self.substitute(&c, replacement)))
+            })
+            .collect();
+        edits.reverse();
+        Ok(edits)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Step {
+    // The match at the selection or after it, keeps the match while the pattern is typed
+    Here,
+    Next,
+    Previous,
+}
+
+// Index of the match to show, the search wraps around the ends of the text
+fn pick_match(matches: &[Range<usize>], selection: &Range<usize>, step: Step) -> Option<usize> {
+    if matches.is_empty() {
+        return None;
+    }
+    let index = match step {
+        Step::Here => matches.iter().position(|m| m.start >= selection.start),
+        Step::Next => matches.iter().position(|m| {
+            m.start > selection.start || (selection.is_empty() && m.start == selection.start)
+        }),
+        Step::Previous => matches.iter().rposition(|m| m.start < selection.start),
+    };
+    Some(match (index, step) {
+        (Some(index), _) => index,
+        (None, Step::Previous) => matches.len() - 1,
+        (None, _) => 0,
+    })
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum FindBarEvent {
+    /// Index of the shown match and the number of matches
+    MatchesChanged {
+        current: Option<usize>,
+        count: usize,
+    },
+    /// Escape was pressed in the bar, the application usually hides it
+    Closed,
+}
+
+#[derive(Copy, Clone)]
+enum Action {
+    Previous,
+    Next,
+    Replace,
+    ReplaceAll,
+}
+
+struct Core {
+    target: Option<Arc<dyn Searchable>>,
+    current: Option<usize>,
+    count: usize,
+}
+
+// Part of the bar searching again on the changes of the pattern
+#[derive(EventSink)]
+#[event_sink(event=TextDocumentEvent)]
+struct Shared {
+    find_editor: Arc<TextEditor>,
+    replace_editor: Arc<TextEditor>,
+    match_case: Arc<ToggleButton>,
+    whole_word: Arc<ToggleButton>,
+    regex: Arc<ToggleButton>,
+    status: Arc<Text>,
+    core: RwLock<Core>,
+    find_bar_events: EventStreams<FindBarEvent>,
+}
+
+impl Shared {
+    async fn query(&self) -> SearchQuery {
+        SearchQuery {
+            pattern: self.find_editor.document().text().await,
+            match_case: self.match_case.is_checked().await,
+            whole_word: self.whole_word.is_checked().await,
+            regex: self.regex.is_checked().await,
+        }
+    }
+
+    async fn find(&self, step: Step) -> crate::Result<()> {
+        let target = match self.core.read().await.target.clone() {
+            Some(target) => target,
+            None => return self.set_matches(None, 0, "").await,
+        };
+        let query = self.query().await;
+        let matches = match query.find_all(&target.search_text().await) {
+            Ok(matches) => matches,
+            Err(crate::Error::BadPattern(_)) => {
+                return self.set_matches(None, 0, "Bad pattern").await
+            }
+            Err(e) => return Err(e),
+        };
+        let current = pick_match(&matches, &target.search_selection().await, step);
+        if let Some(index) = current {
+            target.show_match(matches[index].clone()).await?;
+        }
+        let status = match current {
+            Some(index) => format!("{} of {}", index + 1, matches.len()),
+            None if query.pattern.is_empty() => String::new(),
+            None => "No results".to_string(),
+        };
+        self.set_matches(current, matches.len(), &status).await
+    }
+
+    async fn set_matches(
+        &self,
+        current: Option<usize>,
+        count: usize,
+        status: &str,
+    ) -> crate::Result<()> {
+        self.status.set_text(status).await?;
+        {
+            let mut core = self.core.write().await;
+            if core.current == current && core.count == count {
+                return Ok(());
+            }
+            core.current = current;
+            core.count = count;
+        }
+        self.find_bar_events
+            .send_event(FindBarEvent::MatchesChanged { current, count }, None)
+            .await;
+        Ok(())
+    }
+
+    // Replaces the selected match and shows the next one
+    async fn replace(&self) -> crate::Result<()> {
+        let target = match self.core.read().await.target.clone() {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        let query = self.query().await;
+        let replacement = self.replace_editor.document().text().await;
+        let selection = target.search_selection().await;
+        let text = target.search_text().await;
+        let step = match query.expand(&text, selection.clone(), &replacement) {
+            Ok(Some(text)) => {
+                let end = selection.start + text.len();
+                if target.replace(vec![TextEdit::new(selection, text)]).await? {
+                    // The search goes on after the replaced text, it may match again
+                    target.show_match(end..end).await?;
+                    Step::Here
+                } else {
+                    Step::Next
+                }
+            }
+            Ok(None) => Step::Here,
+            Err(crate::Error::BadPattern(_)) => Step::Here,
+            Err(e) => return Err(e),
+        };
+        self.find(step).await
+    }
+
+    async fn replace_all(&self) -> crate::Result<usize> {
+        let target = match self.core.read().await.target.clone() {
+            Some(target) => target,
+            None => return Ok(0),
+        };
+        let query = self.query().await;
+        let replacement = self.replace_editor.document().text().await;
+        let edits = match query.replace_all_edits(&target.search_text().await, &replacement) {
+            Ok(edits) => edits,
+            Err(crate::Error::BadPattern(_)) => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let count = edits.len();
+        let replaced = count > 0 && target.replace(edits).await?;
+        self.find(Step::Here).await?;
+        if replaced {
+            self.status.set_text(format!("Replaced {}", count)).await?;
+            Ok(count)
+        } else {
+            Ok(0)
+        }
+    }
+
+    async fn run(&self, action: Action) -> crate::Result<()> {
+        match action {
+            Action::Previous => self.find(Step::Previous).await,
+            Action::Next => self.find(Step::Next).await,
+            Action::Replace => self.replace().await,
+            Action::ReplaceAll => self.replace_all().await.map(|_| ()),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<TextDocumentEvent> for Shared {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        _: Cow<'a, TextDocumentEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.find(Step::Here).await
+    }
+}
+
+// Searches again when the option is toggled. The toggle button sends the panel events too,
+// so its pipe needs the sink of the single event type.
+#[derive(EventSink)]
+#[event_sink(event=ToggleButtonEvent)]
+struct OptionSink(Arc<Shared>);
+
+#[async_trait]
+impl EventSinkExt<ToggleButtonEvent> for OptionSink {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        _: Cow<'a, ToggleButtonEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.0.find(Step::Here).await
+    }
+}
+
+// Runs the action on the button's click
+#[derive(EventSink)]
+#[event_sink(event=ButtonEvent)]
+struct ActionSink {
+    shared: Arc<Shared>,
+    action: Action,
+}
+
+#[async_trait]
+impl EventSinkExt<ButtonEvent> for ActionSink {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, ButtonEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if *event == ButtonEvent::Release(true) {
+            self.shared.run(self.action).await?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Find and replace bar for the [`Searchable`] widget. The search is incremental: the match
+/// at the target's selection or after it is selected as the pattern is typed or the options
+/// are changed, the bar shows the number of the matches. In the bar's fields Enter and F3
+/// go to the next match, Shift+Enter and Shift+F3 to the previous one, Enter in the
+/// replacement field replaces the match and Escape sends `FindBarEvent::Closed`. Replace
+/// all is one undoable change of the target.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct FindBar {
+    container: ContainerVisual,
+    ribbon: Arc<Ribbon>,
+    shared: Arc<Shared>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct FindBarParams<T: Spawn + Clone + Send + Sync + 'static> {
+    compositor: Compositor,
+    spawner: T,
+    /// Widget to search in, may be set later by `set_target`
+    #[builder(default, setter(strip_option))]
+    target: Option<Arc<dyn Searchable>>,
+    /// Context of the parent, the buttons follow the [`Theme`] found in it
+    #[builder(default)]
+    context: Context,
+}
+
+fn label_layout() -> TextLayout {
+    TextLayout {
+        wrap: false,
+        horizontal_alignment: Alignment::Center,
+        vertical_alignment: Alignment::Center,
+        ..Default::default()
+    }
+}
+
+fn text_editor<T: Spawn + Clone + Send + Sync + 'static>(
+    compositor: &Compositor,
+    spawner: &T,
+) -> crate::Result<Arc<TextEditor>> {
+    TextEditorParams::builder()
+        .compositor(compositor.clone())
+        .spawner(spawner.clone())
+        .build()
+        .try_into()
+}
+
+fn button<T: Spawn>(
+    compositor: &Compositor,
+    spawner: &T,
+    context: &Context,
+    text: &str,
+) -> crate::Result<Arc<Button>> {
+    let skin: SimpleButtonSkin = SimpleButtonSkinParams::builder()
+        .compositor(compositor.clone())
+        .text(text.to_string())
+        .color(Colors::Gainsboro()?)
+        .themed(context.contains::<Theme>())
+        .context(context.clone())
+        .spawner(spawner)
+        .build()
+        .try_into()?;
+    ButtonParams::builder()
+        .compositor(compositor.clone())
+        .skin(skin)
+        .build()
+        .try_into()
+}
+
+fn option_button<T: Spawn>(
+    compositor: &Compositor,
+    spawner: &T,
+    text: &str,
+) -> crate::Result<Arc<ToggleButton>> {
+    let label: Arc<Text> = TextParams::builder()
+        .compositor(compositor.clone())
+        .spawner(spawner)
+        .text(text.to_string())
+        .layout(label_layout())
+        .build()
+        .try_into()?;
+    let skin: SimpleToggleButtonSkin = SimpleToggleButtonSkinParams::builder()
+        .compositor(compositor.clone())
+        .content(label)
+        .build()
+        .try_into()?;
+    ToggleButtonParams::builder()
+        .compositor(compositor.clone())
+        .skin(skin)
+        .build()
+        .try_into()
+}
+
+impl<T: Spawn + Clone + Send + Sync + 'static> TryFrom<FindBarParams<T>> for FindBar {
+    type Error = crate::Error;
+
+    fn try_from(value: FindBarParams<T>) -> crate::Result<Self> {
+        let compositor = &value.compositor;
+        let spawner = &value.spawner;
+        let find_editor = text_editor(compositor, spawner)?;
+        let replace_editor = text_editor(compositor, spawner)?;
+        let match_case = option_button(compositor, spawner, "Aa")?;
+        let whole_word = option_button(compositor, spawner, "ab|")?;
+        let regex = option_button(compositor, spawner, ".*")?;
+        let status: Arc<Text> = TextParams::builder()
+            .compositor(compositor.clone())
+            .spawner(spawner)
+            .text(String::new())
+            .layout(label_layout())
+            .build()
+            .try_into()?;
+        let context = &value.context;
+        let previous = button(compositor, spawner, context, "\u{2191}")?;
+        let next = button(compositor, spawner, context, "\u{2193}")?;
+        let replace = button(compositor, spawner, context, "Replace")?;
+        let replace_all = button(compositor, spawner, context, "Replace all")?;
+        let find_row: Arc<Ribbon> = RibbonParams::builder()
+            .compositor(compositor.clone())
+            .orientation(RibbonOrientation::Horizontal)
+            .build()
+            .add_panel(find_editor.clone(), CellLimit::default())?
+            .add_panel(status.clone(), CellLimit::with_length(STATUS_WIDTH))?
+            .add_panel(match_case.clone(), CellLimit::with_length(OPTION_WIDTH))?
+            .add_panel(whole_word.clone(), CellLimit::with_length(OPTION_WIDTH))?
+            .add_panel(regex.clone(), CellLimit::with_length(OPTION_WIDTH))?
+            .add_panel(previous.clone(), CellLimit::with_length(ARROW_WIDTH))?
+            .add_panel(next.clone(), CellLimit::with_length(ARROW_WIDTH))?
+            .try_into()?;
+        let replace_row: Arc<Ribbon> = RibbonParams::builder()
+            .compositor(compositor.clone())
+            .orientation(RibbonOrientation::Horizontal)
+            .build()
+            .add_panel(replace_editor.clone(), CellLimit::default())?
+            .add_panel(replace.clone(), CellLimit::with_length(REPLACE_WIDTH))?
+            .add_panel(replace_all.clone(), CellLimit::with_length(REPLACE_WIDTH))?
+            .try_into()?;
+        let ribbon: Arc<Ribbon> = RibbonParams::builder()
+            .compositor(compositor.clone())
+            .orientation(RibbonOrientation::Vertical)
+            .build()
+            .add_panel(find_row, CellLimit::default())?
+            .add_panel(replace_row, CellLimit::default())?
+            .try_into()?;
+        let container = compositor.CreateContainerVisual()?;
+        attach(&container, &*ribbon)?;
+        let shared = Arc::new(Shared {
+            find_editor: find_editor.clone(),
+            replace_editor,
+            match_case: match_case.clone(),
+            whole_word: whole_word.clone(),
+            regex: regex.clone(),
+            status,
+            core: RwLock::new(Core {
+                target: value.target,
+                current: None,
+                count: 0,
+            }),
+            find_bar_events: EventStreams::new(),
+        });
+        spawn_event_pipe(spawner, &*find_editor.document(), shared.clone(), on_err)?;
+        for option in [&match_case, &whole_word, &regex] {
+            let sink = Arc::new(OptionSink(shared.clone()));
+            spawn_event_pipe(spawner, &**option, sink, on_err)?;
+        }
+        for (button, action) in [
+            (previous, Action::Previous),
+            (next, Action::Next),
+            (replace, Action::Replace),
+            (replace_all, Action::ReplaceAll),
+        ] {
+            let sink = Arc::new(ActionSink {
+                shared: shared.clone(),
+                action,
+            });
+            spawn_event_pipe(spawner, &*button, sink, on_err)?;
+        }
+        Ok(FindBar {
+            container,
+            ribbon,
+            shared,
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl<T: Spawn + Clone + Send + Sync + 'static> TryFrom<FindBarParams<T>> for Arc<FindBar> {
+    type Error = crate::Error;
+
+    fn try_from(value: FindBarParams<T>) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl FindBar {
+    /// Binds the bar to the other widget and searches in it
+    pub async fn set_target(&self, target: Option<Arc<dyn Searchable>>) -> crate::Result<()> {
+        self.shared.core.write().await.target = target;
+        self.shared.find(Step::Here).await
+    }
+
+    pub async fn query(&self) -> SearchQuery {
+        self.shared.query().await
+    }
+
+    /// Sets the pattern and the options, e.g. to search for the target's selected text
+    pub async fn set_query(&self, query: SearchQuery) -> crate::Result<()> {
+        self.shared.match_case.set_checked(query.match_case).await?;
+        self.shared.whole_word.set_checked(query.whole_word).await?;
+        self.shared.regex.set_checked(query.regex).await?;
+        let document = self.shared.find_editor.document();
+        let len = document.len().await;
+        document.replace(0..len, &query.pattern).await
+    }
+
+    pub async fn replacement(&self) -> String {
+        self.shared.replace_editor.document().text().await
+    }
+
+    pub async fn set_replacement(&self, replacement: &str) -> crate::Result<()> {
+        let document = self.shared.replace_editor.document();
+        let len = document.len().await;
+        document.replace(0..len, replacement).await
+    }
+
+    /// Number of the matches found by the last search
+    pub async fn match_count(&self) -> usize {
+        self.shared.core.read().await.count
+    }
+
+    pub async fn find_next(&self) -> crate::Result<()> {
+        self.shared.find(Step::Next).await
+    }
+
+    pub async fn find_previous(&self) -> crate::Result<()> {
+        self.shared.find(Step::Previous).await
+    }
+
+    /// Replaces the selected match or selects the next one if the selection is not a match
+    pub async fn replace(&self) -> crate::Result<()> {
+        self.shared.replace().await
+    }
+
+    /// Returns the number of the replaced matches
+    pub async fn replace_all(&self) -> crate::Result<usize> {
+        self.shared.replace_all().await
+    }
+
+    /// Focuses the pattern field and selects its text to type the new one
+    pub async fn focus(&self) -> crate::Result<()> {
+        self.shared.replace_editor.set_focused(false).await?;
+        self.shared.find_editor.set_focused(true).await?;
+        self.shared.find_editor.select(0..usize::MAX).await
+    }
+
+    // Returns true if the key is handled by the bar and is not passed to the fields
+    async fn key(&self, key: VirtualKeyCode, shift: bool) -> crate::Result<bool> {
+        let in_find = self.shared.find_editor.is_focused().await;
+        let in_replace = self.shared.replace_editor.is_focused().await;
+        if !in_find && !in_replace {
+            return Ok(false);
+        }
+        let step = if shift { Step::Previous } else { Step::Next };
+        match key {
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter if in_replace && !shift => {
+                self.shared.replace().await?
+            }
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter | VirtualKeyCode::F3 => {
+                self.shared.find(step).await?
+            }
+            VirtualKeyCode::Escape => {
+                self.shared
+                    .find_bar_events
+                    .send_event(FindBarEvent::Closed, None)
+                    .await
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let handled = match &event {
+            PanelEvent::Resized(size) => {
+                self.container.SetSize(*size)?;
+                false
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(key),
+                modifiers,
+                ..
+            } => self.key(*key, modifiers.shift()).await?,
+            _ => false,
+        };
+        if !handled {
+            self.ribbon.on_event_ref(&event, source.clone()).await?;
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<FindBarEvent> for FindBar {
+    fn event_stream(&self) -> EventStream<FindBarEvent> {
+        self.shared.find_bar_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for FindBar {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for FindBar {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for FindBar {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(pattern: &str) -> SearchQuery {
+        SearchQuery {
+            pattern: pattern.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn literal_search_options() {
+        let text = "Cat cat.concat (cat)";
+        assert_eq!(
+            literal("cat").find_all(text).unwrap(),
+            [0..3, 4..7, 11..14, 16..19]
+        );
+        let case = SearchQuery {
+            match_case: true,
+            ..literal("cat")
+        };
+        assert_eq!(case.find_all(text).unwrap(), [4..7, 11..14, 16..19]);
+        let word = SearchQuery {
+            whole_word: true,
+            ..literal("cat")
+        };
+        assert_eq!(word.find_all(text).unwrap(), [0..3, 4..7, 16..19]);
+        assert_eq!(
+            literal("(cat)").find_all("(cat) (cat)").unwrap(),
+            [0..5, 6..11]
+        );
+        assert!(literal("").find_all(text).unwrap().is_empty());
+    }
+
+    #[test]
+    fn regex_search_and_replace() {
+        let query = SearchQuery {
+            regex: true,
+            ..literal(r"(\w+)@(\w+)")
+        };
+        let text = "a@b, cd@ef";
+        assert_eq!(query.find_all(text).unwrap(), [0..3, 5..10]);
+        assert_eq!(
+            query.expand(text, 5..10, "$2@$1").unwrap(),
+            Some("ef@cd".to_string())
+        );
+        assert_eq!(query.expand(text, 5..8, "$2@$1").unwrap(), None);
+        assert_eq!(
+            query.replace_all_edits(text, "<$1>").unwrap(),
+            [TextEdit::new(5..10, "<cd>"), TextEdit::new(0..3, "<a>")]
+        );
+        assert!(matches!(
+            SearchQuery {
+                regex: true,
+                ..literal("(")
+            }
+            .find_all(text),
+            Err(crate::Error::BadPattern(_))
+        ));
+        // Literal replacement keeps the dollar signs
+        assert_eq!(
+            literal("a").expand("a", 0..1, "$1").unwrap(),
+            Some("$1".to_string())
+        );
+    }
+
+    #[test]
+    fn match_picking_wraps() {
+        let matches = [2..4, 6..8, 10..12];
+        assert_eq!(pick_match(&matches, &(6..8), Step::Here), Some(1));
+        assert_eq!(pick_match(&matches, &(6..8), Step::Next), Some(2));
+        assert_eq!(pick_match(&matches, &(6..6), Step::Next), Some(1));
+        assert_eq!(pick_match(&matches, &(10..12), Step::Next), Some(0));
+        assert_eq!(pick_match(&matches, &(6..8), Step::Previous), Some(0));
+        assert_eq!(pick_match(&matches, &(2..4), Step::Previous), Some(2));
+        assert_eq!(pick_match(&matches, &(13..13), Step::Here), Some(0));
+        assert_eq!(pick_match(&[], &(0..0), Step::Next), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::{Duration, Instant};
+
+use windows::Foundation::Numerics::Vector2;
+use winit::event::TouchPhase;
+
+use crate::window::double_click_limits;
+
+/// Distance the touch may move before the tap or long press turns into the pan
+const SLOP: f32 = 10.;
+const LONG_PRESS: Duration = Duration::from_millis(500);
+
+///
+/// Gestures recognized from the touches of the window. The gesture is delivered to the panel
+/// tree as `PanelEvent::Gesture` right after `PanelEvent::CursorMoved` to its focal point, so
+/// panels hit test it by the cursor position as the mouse input.
+///
+#[derive(PartialEq, Clone, Debug)]
+pub enum GestureEvent {
+    Tap,
+    /// Second tap within the system double-click time and distance
+    DoubleTap,
+    /// The touch is held without moving
+    LongPress,
+    /// Single touch moved, the delta is since the previous pan event
+    Pan {
+        phase: TouchPhase,
+        delta: Vector2,
+    },
+    /// Two touches, the scale is the distance between them relative to the pinch start
+    Pinch {
+        phase: TouchPhase,
+        scale: f32,
+    },
+}
+
+fn distance(a: Vector2, b: Vector2) -> f32 {
+    ((a.X - b.X).powi(2) + (a.Y - b.Y).powi(2)).sqrt()
+}
+
+fn middle(a: Vector2, b: Vector2) -> Vector2 {
+    Vector2 {
+        X: (a.X + b.X) / 2.,
+        Y: (a.Y + b.Y) / 2.,
+    }
+}
+
+enum Mode {
+    Idle,
+    // Single touch not moved yet: tap or long press
+    Pending { time: Instant, pos: Vector2 },
+    Pan { last: Vector2 },
+    Pinch { start_distance: f32, scale: f32 },
+    // Gesture is over, waiting for all touches to be released
+    Done,
+}
+
+/// Turns the touches of the window into gestures, the positions are in window coordinates
+pub(super) struct GestureRecognizer {
+    touches: Vec<(u64, Vector2)>,
+    mode: Mode,
+    last_tap: Option<(Instant, Vector2)>,
+    double_tap_time: Duration,
+    double_tap_distance: Vector2,
+}
+
+impl GestureRecognizer {
+    pub(super) fn new() -> Self {
+        let (double_tap_time, double_tap_distance) = double_click_limits();
+        Self {
+            touches: Vec::new(),
+            mode: Mode::Idle,
+            last_tap: None,
+            double_tap_time,
+            double_tap_distance,
+        }
+    }
+
+    /// Time when the current touch becomes the long press if it isn't moved or released
+    pub(super) fn long_press_deadline(&self) -> Option<Instant> {
+        match self.mode {
+            Mode::Pending { time, .. } => Some(time + LONG_PRESS),
+            _ => None,
+        }
+    }
+
+    /// Recognizes the long press if its deadline is passed
+    pub(super) fn long_press(&mut self, now: Instant) -> Option<(Vector2, GestureEvent)> {
+        match self.mode {
+            Mode::Pending { time, pos } if now >= time + LONG_PRESS => {
+                self.mode = Mode::Done;
+                Some((pos, GestureEvent::LongPress))
+            }
+            _ => None,
+        }
+    }
+
+    /// Handles the touch, returns recognized gestures with their focal points
+    pub(super) fn touch(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        pos: Vector2,
+        now: Instant,
+    ) -> Vec<(Vector2, GestureEvent)> {
+        let mut gestures = Vec::new();
+        match phase {
+            TouchPhase::Started => {
+                self.touches.push((id, pos));
+                let single = matches!(self.mode, Mode::Pending { .. } | Mode::Pan { .. });
+                match self.touches.len() {
+                    1 => self.mode = Mode::Pending { time: now, pos },
+                    2 if single => {
+                        if let Mode::Pan { .. } = self.mode {
+                            gestures.push((
+                                pos,
+                                GestureEvent::Pan {
+                                    phase: TouchPhase::Ended,
+                                    delta: Vector2::default(),
+                                },
+                            ));
+                        }
+                        let (a, b) = (self.touches[0].1, self.touches[1].1);
+                        self.mode = Mode::Pinch {
+                            start_distance: distance(a, b).max(1.),
+                            scale: 1.,
+                        };
+                        gestures.push((
+                            middle(a, b),
+                            GestureEvent::Pinch {
+                                phase: TouchPhase::Started,
+                                scale: 1.,
+                            },
+                        ));
+                    }
+                    _ => (),
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some(touch) = self.touches.iter_mut().find(|(v, _)| *v == id) {
+                    touch.1 = pos;
+                }
+                match &mut self.mode {
+                    Mode::Pending { pos: start, .. } if distance(*start, pos) > SLOP => {
+                        let delta = Vector2 {
+                            X: pos.X - start.X,
+                            Y: pos.Y - start.Y,
+                        };
+                        self.mode = Mode::Pan { last: pos };
+                        gestures.push((
+                            pos,
+                            GestureEvent::Pan {
+                                phase: TouchPhase::Started,
+                                delta,
+                            },
+                        ));
+                    }
+                    Mode::Pan { last } => {
+                        let delta = Vector2 {
+                            X: pos.X - last.X,
+                            Y: pos.Y - last.Y,
+                        };
+                        *last = pos;
+                        gestures.push((
+                            pos,
+                            GestureEvent::Pan {
+                                phase: TouchPhase::Moved,
+                                delta,
+                            },
+                        ));
+                    }
+                    Mode::Pinch {
+                        start_distance,
+                        scale,
+                    } if self.touches.len() >= 2 => {
+                        let (a, b) = (self.touches[0].1, self.touches[1].1);
+                        *scale = distance(a, b) / *start_distance;
+                        gestures.push((
+                            middle(a, b),
+                            GestureEvent::Pinch {
+                                phase: TouchPhase::Moved,
+                                scale: *scale,
+                            },
+                        ));
+                    }
+                    _ => (),
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.retain(|(v, _)| *v != id);
+                match self.mode {
+                    Mode::Pending { .. } if phase == TouchPhase::Ended => {
+                        gestures.push((pos, self.tap(pos, now)));
+                    }
+                    Mode::Pan { .. } => gestures.push((
+                        pos,
+                        GestureEvent::Pan {
+                            phase,
+                            delta: Vector2::default(),
+                        },
+                    )),
+                    Mode::Pinch { scale, .. } => {
+                        gestures.push((pos, GestureEvent::Pinch { phase, scale }))
+                    }
+                    _ => (),
+                }
+                self.mode = if self.touches.is_empty() {
+                    Mode::Idle
+                } else {
+                    Mode::Done
+                };
+            }
+        }
+        gestures
+    }
+
+    fn tap(&mut self, pos: Vector2, now: Instant) -> GestureEvent {
+        let double = match self.last_tap {
+            Some((time, last)) => {
+                now - time <= self.double_tap_time
+                    && (pos.X - last.X).abs() <= self.double_tap_distance.X / 2.
+                    && (pos.Y - last.Y).abs() <= self.double_tap_distance.Y / 2.
+            }
+            None => false,
+        };
+        if double {
+            self.last_tap = None;
+            GestureEvent::DoubleTap
+        } else {
+            self.last_tap = Some((now, pos));
+            GestureEvent::Tap
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
+
+use async_event_streams::{EventBox, EventSink, EventSinkExt, EventSource, EventStream};
+use async_event_streams_derive::{self, EventSink};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::{self, Interface},
+    Win32::Foundation::E_INVALIDARG,
+    UI::Composition::{ContainerVisual, Visual},
+};
+
+use crate::geometry::{Point, Rect, Size};
+
+use super::{Panel, PanelEvent};
+
+/// Alpha channel stretched over the panel, e.g. of the image the panel shows
+#[derive(Clone, Debug)]
+pub struct AlphaMask {
+    width: u32,
+    height: u32,
+    alpha: Arc<[u8]>,
+    threshold: u8,
+}
+
+impl AlphaMask {
+    ///
+    /// Mask of `width` x `height` alpha values, rows from top to bottom. The points where
+    /// the alpha is below the `threshold` are transparent for the mouse.
+    ///
+    pub fn new(width: u32, height: u32, alpha: Vec<u8>, threshold: u8) -> crate::Result<Self> {
+        if alpha.len() != width as usize * height as usize {
+            return Err(core::Error::from(E_INVALIDARG).into());
+        }
+        Ok(Self {
+            width,
+            height,
+            alpha: alpha.into(),
+            threshold,
+        })
+    }
+
+    /// Mask from the alpha channel of 8-bit BGRA pixels
+    pub fn from_bgra(width: u32, height: u32, pixels: &[u8], threshold: u8) -> crate::Result<Self> {
+        let alpha = pixels.chunks_exact(4).map(|v| v[3]).collect();
+        Self::new(width, height, alpha, threshold)
+    }
+
+    fn contains(&self, pos: Point, size: Size) -> bool {
+        if size.is_empty() || self.width == 0 || self.height == 0 {
+            return false;
+        }
+        let x = (pos.x / size.width * self.width as f32) as u32;
+        let y = (pos.y / size.height * self.height as f32) as u32;
+        let (x, y) = (x.min(self.width - 1), y.min(self.height - 1));
+        self.alpha[(y * self.width + x) as usize] >= self.threshold
+    }
+}
+
+/// Area of the panel which is hit by the mouse, in the panel's coordinates
+#[derive(Clone)]
+pub enum HitShape {
+    Rects(Vec<Rect>),
+    /// Ellipse inscribed into the panel, e.g. for round buttons
+    Ellipse,
+    Alpha(AlphaMask),
+    /// Function of the point and the panel's size
+    Custom(Arc<dyn Fn(Point, Size) -> bool + Send + Sync>),
+}
+
+impl HitShape {
+    pub fn contains(&self, pos: Point, size: Size) -> bool {
+        match self {
+            HitShape::Rects(rects) => rects.iter().any(|v| v.contains(pos)),
+            HitShape::Ellipse => {
+                if size.is_empty() {
+                    return false;
+                }
+                let center = Rect::from_size(size).center();
+                let dx = (pos.x - center.x) / (size.width / 2.);
+                let dy = (pos.y - center.y) / (size.height / 2.);
+                dx * dx + dy * dy <= 1.
+            }
+            HitShape::Alpha(mask) => mask.contains(pos, size),
+            HitShape::Custom(f) => f(pos, size),
+        }
+    }
+}
+
+///
+/// How the panel takes the mouse clicks in the containers stacking panels one over another,
+/// like [`LayerStack`] and [`OverlayHost`]. The click goes to the topmost panel hit at
+/// the cursor, the panels below receive it with `in_slot` cleared.
+///
+/// [`LayerStack`]: super::LayerStack
+/// [`OverlayHost`]: super::OverlayHost
+///
+#[derive(Clone, Default)]
+pub enum HitTestMode {
+    /// The whole panel's rectangle is hit and blocks the panels below
+    #[default]
+    Opaque,
+    /// The panel is never hit, the clicks pass to the panels below, e.g. for decorations
+    Transparent,
+    /// Only the points over the panel's child visuals are hit, the rest passes through
+    ChildrenOnly,
+    /// Only the points inside the shape are hit
+    Shaped(HitShape),
+}
+
+// The point is over one of the visible children of the visual. Like the cursor selector,
+// the test uses the offsets and sizes of the visuals ignoring other transforms.
+fn children_contain(visual: &Visual, pos: Point) -> crate::Result<bool> {
+    let container = match visual.cast::<ContainerVisual>() {
+        Ok(container) => container,
+        Err(_) => return Ok(false),
+    };
+    for child in container.Children()? {
+        if !child.IsVisible()? {
+            continue;
+        }
+        let rect = Rect::new(child.Offset()?.into(), child.Size()?.into());
+        if rect.contains(pos) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+///
+/// Checks if the point in the panel's coordinates hits the panel according to its
+/// [`HitTestMode`]. Hidden panels are never hit.
+///
+pub fn hit_test<T: Panel + ?Sized>(panel: &T, pos: impl Into<Point>) -> crate::Result<bool> {
+    let visual = panel.outer_frame();
+    if !visual.IsVisible()? {
+        return Ok(false);
+    }
+    let pos = pos.into();
+    let size: Size = visual.Size()?.into();
+    if !Rect::from_size(size).contains(pos) {
+        return Ok(false);
+    }
+    Ok(match panel.hit_test_mode() {
+        HitTestMode::Opaque => true,
+        HitTestMode::Transparent => false,
+        HitTestMode::ChildrenOnly => children_contain(&visual, pos)?,
+        HitTestMode::Shaped(shape) => shape.contains(pos, size),
+    })
+}
+
+///
+/// Gives the panel the hit test mode. The wrapper shares the content's visual and events,
+/// only the mode is added:
+///
+/// ```ignore
+/// let badge: Arc<HitTestPanel> = HitTestPanelParams::builder()
+///     .content(badge)
+///     .mode(HitTestMode::Transparent)
+///     .build()
+///     .try_into()?;
+/// ```
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct HitTestPanel {
+    content: Arc<dyn Panel>,
+    mode: Mutex<HitTestMode>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct HitTestPanelParams {
+    content: Arc<dyn Panel>,
+    #[builder(default)]
+    mode: HitTestMode,
+}
+
+impl TryFrom<HitTestPanelParams> for HitTestPanel {
+    type Error = crate::Error;
+
+    fn try_from(value: HitTestPanelParams) -> crate::Result<Self> {
+        Ok(HitTestPanel {
+            content: value.content,
+            mode: Mutex::new(value.mode),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<HitTestPanelParams> for Arc<HitTestPanel> {
+    type Error = crate::Error;
+
+    fn try_from(value: HitTestPanelParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl HitTestPanel {
+    pub fn content(&self) -> Arc<dyn Panel> {
+        self.content.clone()
+    }
+
+    pub fn set_mode(&self, mode: HitTestMode) {
+        *self.mode.lock().unwrap() = mode;
+    }
+}
+
+impl Panel for HitTestPanel {
+    fn outer_frame(&self) -> Visual {
+        self.content.outer_frame()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+    fn hit_test_mode(&self) -> HitTestMode {
+        self.mode.lock().unwrap().clone()
+    }
+}
+
+impl EventSource<PanelEvent> for HitTestPanel {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.content.event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for HitTestPanel {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match event {
+            Cow::Borrowed(event) => self.content.on_event_ref(event, source).await,
+            Cow::Owned(event) => self.content.on_event_owned(event, source).await,
+        }
+    }
+}
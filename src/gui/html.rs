@@ -0,0 +1,1045 @@
+//!
+//! Renderer of the simple HTML, e.g. of the mails or the release notes. Only the paragraphs,
+//! headings, bold and italic text, links, lists and images are shown. The markup is sanitized
+//! on parsing: the scripts, styles and embedded documents are dropped with their content, the
+//! links are kept only to the web pages and mail addresses, the other elements are unwrapped
+//! to their text.
+//!
+use std::{borrow::Cow, collections::HashMap};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton};
+
+use crate::{
+    geometry::{Point, Rect, Size},
+    window::Bitmap,
+};
+
+#[cfg(feature = "accessibility")]
+use super::TextAlternative;
+use super::{
+    attach, default_text_rendering, dispatch::DispatchQueue, Background, BackgroundImage,
+    BackgroundParams, Font, ImageStretch, Panel, PanelEvent, RichText, RichTextParams,
+    TextRendering, TextRun,
+};
+
+// Vertical space between the blocks
+const BLOCK_SPACING: f32 = 8.;
+// Indentation of each level of the nested lists
+const LIST_INDENT: f32 = 24.;
+
+// Elements dropped with their content: scripts, styles and embedded documents
+const DROPPED: &[&str] = &[
+    "script", "style", "head", "title", "template", "iframe", "object", "embed", "svg", "math",
+    "canvas", "textarea", "select",
+];
+// Elements with the raw text content, its '<' don't start the tags
+const RAW_TEXT: &[&str] = &["script", "style", "title", "textarea"];
+// Elements without content and closing tag
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+// Elements ending the paragraph
+const BLOCKS: &[&str] = &[
+    "p",
+    "div",
+    "blockquote",
+    "pre",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "table",
+    "tr",
+    "td",
+    "th",
+    "hr",
+];
+// Schemes of the links which are kept, the relative links have none
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Fragment of the block's text with its own formatting
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct HtmlSpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    /// Target of the link, already sanitized
+    pub link: Option<String>,
+}
+
+/// Block of the parsed HTML
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum HtmlBlock {
+    /// Paragraph, or heading of the level from 1 to 6 if `heading` is not 0
+    Paragraph {
+        heading: u8,
+        spans: Vec<HtmlSpan>,
+    },
+    /// Item of the list nested `depth` levels deep, with its number in the ordered list
+    ListItem {
+        depth: usize,
+        number: Option<usize>,
+        spans: Vec<HtmlSpan>,
+    },
+    Image {
+        src: String,
+        alt: String,
+    },
+}
+
+struct Tag {
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    attributes: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(v, _)| v == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number
+            .strip_prefix('x')
+            .or_else(|| number.strip_prefix('X'))
+        {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        // NUL and the invalid code points are replaced as browsers do
+        return Some(
+            char::from_u32(code)
+                .filter(|v| *v != '\0')
+                .unwrap_or('\u{FFFD}'),
+        );
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{A0}',
+        "copy" => '\u{A9}',
+        "reg" => '\u{AE}',
+        "trade" => '\u{2122}',
+        "hellip" => '\u{2026}',
+        "ndash" => '\u{2013}',
+        "mdash" => '\u{2014}',
+        "lsquo" => '\u{2018}',
+        "rsquo" => '\u{2019}',
+        "ldquo" => '\u{201C}',
+        "rdquo" => '\u{201D}',
+        "laquo" => '\u{AB}',
+        "raquo" => '\u{BB}',
+        "bull" => '\u{2022}',
+        "middot" => '\u{B7}',
+        "euro" => '\u{20AC}',
+        _ => return None,
+    })
+}
+
+// Replaces the character references, the unknown ones are left as is
+fn decode_entities(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('&') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        let decoded = rest
+            .find(';')
+            .filter(|v| *v <= 10)
+            .and_then(|end| entity(&rest[..end]).map(|c| (c, end + 1)));
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => result.push('&'),
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// Parses the tag at the start of the input, returns it with its length in bytes
+fn parse_tag(input: &str) -> Option<(Tag, usize)> {
+    let bytes = input.as_bytes();
+    let skip_whitespace = |mut pos: usize| {
+        while bytes.get(pos).map_or(false, u8::is_ascii_whitespace) {
+            pos += 1;
+        }
+        pos
+    };
+    let mut pos = 1;
+    let closing = bytes.get(pos) == Some(&b'/');
+    if closing {
+        pos += 1;
+    }
+    let start = pos;
+    while bytes.get(pos).map_or(false, u8::is_ascii_alphanumeric) {
+        pos += 1;
+    }
+    if pos == start || !bytes[start].is_ascii_alphabetic() {
+        return None;
+    }
+    let mut tag = Tag {
+        name: input[start..pos].to_ascii_lowercase(),
+        closing,
+        self_closing: false,
+        attributes: Vec::new(),
+    };
+    loop {
+        pos = skip_whitespace(pos);
+        match bytes.get(pos)? {
+            b'>' => return Some((tag, pos + 1)),
+            b'/' => {
+                tag.self_closing = true;
+                pos += 1;
+                continue;
+            }
+            _ => tag.self_closing = false,
+        }
+        let start = pos;
+        while bytes
+            .get(pos)
+            .map_or(false, |v| !v.is_ascii_whitespace() && !b">/=".contains(v))
+        {
+            pos += 1;
+        }
+        let name = input[start..pos].to_ascii_lowercase();
+        pos = skip_whitespace(pos);
+        let mut value = String::new();
+        if bytes.get(pos) == Some(&b'=') {
+            pos = skip_whitespace(pos + 1);
+            match *bytes.get(pos)? {
+                quote @ (b'"' | b'\'') => {
+                    let end = pos + 1 + input[pos + 1..].find(quote as char)?;
+                    value = decode_entities(&input[pos + 1..end]);
+                    pos = end + 1;
+                }
+                _ => {
+                    let start = pos;
+                    while bytes
+                        .get(pos)
+                        .map_or(false, |v| !v.is_ascii_whitespace() && *v != b'>')
+                    {
+                        pos += 1;
+                    }
+                    value = decode_entities(&input[start..pos]);
+                }
+            }
+        }
+        if !name.is_empty() {
+            tag.attributes.push((name, value));
+        }
+    }
+}
+
+// The rest of the input after the closing tag of the raw text element
+fn skip_raw_text<'a>(input: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{}", name);
+    // The lowercase copy has the same byte positions
+    match input.to_ascii_lowercase().find(&closing) {
+        Some(pos) => input[pos..].find('>').map_or("", |v| &input[pos + v + 1..]),
+        None => "",
+    }
+}
+
+///
+/// Keeps the links to the web pages, mail addresses and relative ones. The links with other
+/// schemes, e.g. `javascript:` or `data:`, are dropped.
+///
+fn sanitize_link(href: &str) -> Option<String> {
+    // Browsers ignore the whitespace and control characters, also inside the scheme
+    let href: String = href
+        .chars()
+        .filter(|v| !v.is_ascii_control() && !v.is_ascii_whitespace())
+        .collect();
+    if href.is_empty() {
+        return None;
+    }
+    match href.find(|v| matches!(v, ':' | '/' | '?' | '#')) {
+        Some(pos) if href[pos..].starts_with(':') => LINK_SCHEMES
+            .iter()
+            .any(|v| href[..pos].eq_ignore_ascii_case(v))
+            .then_some(href),
+        _ => Some(href),
+    }
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    match name {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+// Collects the blocks from the text and tags
+#[derive(Default)]
+struct Builder {
+    blocks: Vec<HtmlBlock>,
+    spans: Vec<HtmlSpan>,
+    heading: u8,
+    // Depth and number of the list item being built, the paragraph if not set
+    item: Option<(usize, Option<usize>)>,
+    // Numbers of the next items of the open lists, `None` for the unordered ones
+    lists: Vec<Option<usize>>,
+    bold: usize,
+    italic: usize,
+    links: Vec<Option<String>>,
+    // Name and nesting depth of the element dropped with its content
+    dropped: Option<(String, usize)>,
+}
+
+impl Builder {
+    // Adds the text with the whitespace collapsed to single spaces, as browsers show it
+    fn text(&mut self, text: &str) {
+        if self.dropped.is_some() {
+            return;
+        }
+        let mut space = self
+            .spans
+            .last()
+            .map_or(true, |v| v.text.ends_with(|c| c == ' ' || c == '\n'));
+        let mut collapsed = String::new();
+        for c in text.chars() {
+            if !c.is_ascii_whitespace() {
+                collapsed.push(c);
+                space = false;
+            } else if !space {
+                collapsed.push(' ');
+                space = true;
+            }
+        }
+        self.push(collapsed);
+    }
+
+    fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        let span = HtmlSpan {
+            text,
+            bold: self.bold > 0,
+            italic: self.italic > 0,
+            link: self.links.last().cloned().flatten(),
+        };
+        match self.spans.last_mut() {
+            Some(last)
+                if last.bold == span.bold
+                    && last.italic == span.italic
+                    && last.link == span.link =>
+            {
+                last.text.push_str(&span.text)
+            }
+            _ => self.spans.push(span),
+        }
+    }
+
+    // Ends the block being built, the blocks of the whitespace only are dropped
+    fn flush(&mut self) {
+        while let Some(last) = self.spans.last_mut() {
+            let len = last.text.trim_end_matches(|c| c == ' ' || c == '\n').len();
+            last.text.truncate(len);
+            if !last.text.is_empty() {
+                break;
+            }
+            self.spans.pop();
+        }
+        if self.spans.is_empty() {
+            return;
+        }
+        let spans = std::mem::take(&mut self.spans);
+        self.blocks.push(match self.item.take() {
+            Some((depth, number)) => HtmlBlock::ListItem {
+                depth,
+                number,
+                spans,
+            },
+            None => HtmlBlock::Paragraph {
+                heading: self.heading,
+                spans,
+            },
+        });
+    }
+
+    fn tag(&mut self, tag: &Tag) {
+        if let Some((name, depth)) = &mut self.dropped {
+            if tag.name == *name && tag.closing {
+                *depth -= 1;
+                if *depth == 0 {
+                    self.dropped = None;
+                }
+            } else if tag.name == *name && !tag.self_closing {
+                *depth += 1;
+            }
+            return;
+        }
+        if tag.closing {
+            self.close(&tag.name);
+        } else {
+            self.open(tag);
+            if tag.self_closing && !VOID.contains(&tag.name.as_str()) {
+                self.close(&tag.name);
+            }
+        }
+    }
+
+    fn open(&mut self, tag: &Tag) {
+        let name = tag.name.as_str();
+        if DROPPED.contains(&name) {
+            if !VOID.contains(&name) && !tag.self_closing {
+                self.dropped = Some((tag.name.clone(), 1));
+            }
+            return;
+        }
+        if BLOCKS.contains(&name) {
+            return self.flush();
+        }
+        if let Some(level) = heading_level(name) {
+            self.flush();
+            self.heading = level;
+            return;
+        }
+        match name {
+            "ul" => {
+                self.flush();
+                self.lists.push(None);
+            }
+            "ol" => {
+                self.flush();
+                let start = tag.attribute("start").and_then(|v| v.trim().parse().ok());
+                self.lists.push(Some(start.unwrap_or(1)));
+            }
+            "li" => {
+                self.flush();
+                let number = match self.lists.last_mut() {
+                    Some(Some(next)) => {
+                        *next += 1;
+                        Some(*next - 1)
+                    }
+                    _ => None,
+                };
+                self.item = Some((self.lists.len().max(1), number));
+            }
+            "b" | "strong" => self.bold += 1,
+            "i" | "em" => self.italic += 1,
+            "a" => self
+                .links
+                .push(tag.attribute("href").and_then(sanitize_link)),
+            "br" => self.push("\n".to_string()),
+            "img" => {
+                self.flush();
+                if let Some(src) = tag.attribute("src").filter(|v| !v.is_empty()) {
+                    self.blocks.push(HtmlBlock::Image {
+                        src: src.to_string(),
+                        alt: tag.attribute("alt").unwrap_or_default().trim().to_string(),
+                    });
+                }
+            }
+            // Other elements are unwrapped, their content is shown as the plain text
+            _ => (),
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        if BLOCKS.contains(&name) {
+            return self.flush();
+        }
+        if heading_level(name).is_some() {
+            self.flush();
+            self.heading = 0;
+            return;
+        }
+        match name {
+            "li" => self.flush(),
+            "ul" | "ol" => {
+                self.flush();
+                self.lists.pop();
+            }
+            "b" | "strong" => self.bold = self.bold.saturating_sub(1),
+            "i" | "em" => self.italic = self.italic.saturating_sub(1),
+            "a" => {
+                self.links.pop();
+            }
+            _ => (),
+        }
+    }
+}
+
+///
+/// Parses the HTML into the blocks to show. The parser never fails: the unknown elements are
+/// unwrapped, the broken tags are shown as text, the unclosed elements end with the input.
+///
+pub fn parse_html(html: &str) -> Vec<HtmlBlock> {
+    let mut builder = Builder::default();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let pos = rest.find('<').unwrap_or(rest.len());
+        builder.text(&decode_entities(&rest[..pos]));
+        rest = &rest[pos..];
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |v| &comment[v + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |v| &rest[v + 1..]);
+        } else if let Some((tag, len)) = parse_tag(rest) {
+            rest = &rest[len..];
+            // All the raw text elements are dropped, so their content is skipped at once
+            if !tag.closing && !tag.self_closing && RAW_TEXT.contains(&tag.name.as_str()) {
+                rest = skip_raw_text(rest, &tag.name);
+            } else {
+                builder.tag(&tag);
+            }
+        } else {
+            builder.text("<");
+            rest = &rest[1..];
+        }
+    }
+    builder.flush();
+    builder.blocks
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum HtmlViewEvent {
+    /// The link with the URL is clicked, the view doesn't open it itself
+    LinkClicked(String),
+}
+
+fn default_font() -> Font {
+    Font {
+        family: "Segoe UI".to_string(),
+        size: 14.,
+        weight: 400,
+        italic: false,
+    }
+}
+
+fn heading_font(font: &Font, heading: u8) -> Font {
+    let scale = match heading {
+        1 => 2.,
+        2 => 1.5,
+        3 => 1.17,
+        4 => 1.,
+        5 => 0.83,
+        6 => 0.67,
+        _ => return font.clone(),
+    };
+    Font {
+        size: font.size * scale,
+        weight: 700,
+        ..font.clone()
+    }
+}
+
+struct Style {
+    font: Font,
+    text_color: Color,
+    link_color: Color,
+    rendering: TextRendering,
+}
+
+enum Item {
+    Text {
+        rich_text: Arc<RichText>,
+        // Link of each run of the text
+        links: Vec<Option<String>>,
+        indent: f32,
+    },
+    Image {
+        background: Arc<Background>,
+        size: Vector2,
+    },
+}
+
+impl Item {
+    fn text(
+        compositor: &Compositor,
+        style: &Style,
+        font: &Font,
+        marker: Option<String>,
+        spans: Vec<HtmlSpan>,
+        indent: f32,
+    ) -> crate::Result<Self> {
+        let mut runs = Vec::with_capacity(spans.len() + 1);
+        let mut links = Vec::with_capacity(spans.len() + 1);
+        if let Some(marker) = marker {
+            runs.push(
+                TextRun::new(marker)
+                    .with_font(font.clone())
+                    .with_color(style.text_color),
+            );
+            links.push(None);
+        }
+        for span in spans {
+            let font = Font {
+                weight: if span.bold { 700 } else { font.weight },
+                italic: font.italic || span.italic,
+                ..font.clone()
+            };
+            let color = if span.link.is_some() {
+                style.link_color
+            } else {
+                style.text_color
+            };
+            runs.push(
+                TextRun::new(span.text)
+                    .with_font(font)
+                    .with_color(color)
+                    .with_underline(span.link.is_some()),
+            );
+            links.push(span.link);
+        }
+        let rich_text: Arc<RichText> = RichTextParams::builder()
+            .compositor(compositor.clone())
+            .runs(runs)
+            .rendering(style.rendering)
+            .build()
+            .try_into()?;
+        Ok(Item::Text {
+            rich_text,
+            links,
+            indent,
+        })
+    }
+
+    fn image(compositor: &Compositor, bitmap: Arc<Bitmap>, alt: &str) -> crate::Result<Self> {
+        let size = Vector2 {
+            X: bitmap.width as f32,
+            Y: bitmap.height as f32,
+        };
+        let background: Arc<Background> = BackgroundParams::builder()
+            .compositor(compositor.clone())
+            .color(Colors::Transparent()?)
+            .image(BackgroundImage::new(bitmap, ImageStretch::Uniform))
+            .build()
+            .try_into()?;
+        #[cfg(feature = "accessibility")]
+        background.set_text_alternative(&if alt.is_empty() {
+            TextAlternative::Decorative
+        } else {
+            TextAlternative::new(alt)
+        })?;
+        #[cfg(not(feature = "accessibility"))]
+        let _ = alt;
+        Ok(Item::Image { background, size })
+    }
+
+    fn panel(&self) -> Arc<dyn Panel> {
+        match self {
+            Item::Text { rich_text, .. } => rich_text.clone(),
+            Item::Image { background, .. } => background.clone(),
+        }
+    }
+}
+
+struct Core {
+    // Rectangles of the items, placed from top to bottom
+    rects: Vec<Rect>,
+    mouse_pos: Option<Vector2>,
+    pressed: Option<String>,
+}
+
+///
+/// Simple HTML shown by the [`RichText`] panels for the text blocks and the [`Background`]
+/// panels for the images, stacked from top to bottom in the panel's width. The headings are
+/// bold and larger, the list items are indented and marked by the bullets or numbers. The
+/// images are taken from the given map by their `src`, they are never loaded by the view
+/// itself; the missing image is replaced by its `alt` text. The blocks below the panel's
+/// height are not clipped, so the view is usually placed into the scrolling container sized
+/// by [`HtmlView::content_height`].
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct HtmlView {
+    container: ContainerVisual,
+    items: Vec<Item>,
+    core: RwLock<Core>,
+    html_view_events: EventStreams<HtmlViewEvent>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct HtmlViewParams {
+    compositor: Compositor,
+    #[builder(setter(into))]
+    html: String,
+    /// Images by the `src` of the `img` elements, e.g. the mail's attachments by their "cid:" URLs
+    #[builder(default)]
+    images: HashMap<String, Arc<Bitmap>>,
+    /// Font of the paragraphs, the headings are scaled from it
+    #[builder(default = default_font())]
+    font: Font,
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+    #[builder(default = Colors::Blue().unwrap())]
+    link_color: Color,
+    /// Rendering options, the global default if not set
+    #[builder(default, setter(strip_option))]
+    rendering: Option<TextRendering>,
+}
+
+impl TryFrom<HtmlViewParams> for HtmlView {
+    type Error = crate::Error;
+
+    fn try_from(value: HtmlViewParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let style = Style {
+            font: value.font,
+            text_color: value.text_color,
+            link_color: value.link_color,
+            rendering: value.rendering.unwrap_or_else(default_text_rendering),
+        };
+        let mut items = Vec::new();
+        for block in parse_html(&value.html) {
+            let item = match block {
+                HtmlBlock::Paragraph { heading, spans } => Item::text(
+                    &value.compositor,
+                    &style,
+                    &heading_font(&style.font, heading),
+                    None,
+                    spans,
+                    0.,
+                )?,
+                HtmlBlock::ListItem {
+                    depth,
+                    number,
+                    spans,
+                } => {
+                    let marker = match number {
+                        Some(number) => format!("{}. ", number),
+                        None => "\u{2022} ".to_string(),
+                    };
+                    Item::text(
+                        &value.compositor,
+                        &style,
+                        &style.font,
+                        Some(marker),
+                        spans,
+                        depth as f32 * LIST_INDENT,
+                    )?
+                }
+                HtmlBlock::Image { src, alt } => match value.images.get(&src) {
+                    Some(bitmap) => Item::image(&value.compositor, bitmap.clone(), &alt)?,
+                    None if alt.is_empty() => continue,
+                    None => Item::text(
+                        &value.compositor,
+                        &style,
+                        &style.font,
+                        None,
+                        vec![HtmlSpan {
+                            text: alt,
+                            italic: true,
+                            ..Default::default()
+                        }],
+                        0.,
+                    )?,
+                },
+            };
+            attach(&container, &*item.panel())?;
+            items.push(item);
+        }
+        Ok(HtmlView {
+            container,
+            items,
+            core: RwLock::new(Core {
+                rects: Vec::new(),
+                mouse_pos: None,
+                pressed: None,
+            }),
+            html_view_events: EventStreams::new(),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<HtmlViewParams> for Arc<HtmlView> {
+    type Error = crate::Error;
+
+    fn try_from(value: HtmlViewParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl HtmlView {
+    /// Height of the blocks laid out in the panel's width
+    pub async fn content_height(&self) -> f32 {
+        self.core
+            .read()
+            .await
+            .rects
+            .iter()
+            .fold(0., |height, v| height.max(v.bottom()))
+    }
+
+    // Places the blocks one below another, the large images are scaled down to the width
+    async fn layout(&self, width: f32, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        let mut rects = Vec::with_capacity(self.items.len());
+        let mut y = 0.;
+        for item in &self.items {
+            let rect = match item {
+                Item::Text {
+                    rich_text, indent, ..
+                } => {
+                    let width = (width - indent).max(0.);
+                    let height = rich_text.text_height(width).await?;
+                    Rect::new(Point::new(*indent, y), Size::new(width, height))
+                }
+                Item::Image { size, .. } => {
+                    let scale = if size.X > width { width / size.X } else { 1. };
+                    Rect::new(Point::new(0., y), Size::new(size.X * scale, size.Y * scale))
+                }
+            };
+            let panel = item.panel();
+            panel.outer_frame().SetOffset(rect.origin.into())?;
+            panel
+                .on_event_owned(PanelEvent::Resized(rect.size.into()), source.clone())
+                .await?;
+            y = rect.bottom() + BLOCK_SPACING;
+            rects.push(rect);
+        }
+        self.core.write().await.rects = rects;
+        Ok(())
+    }
+
+    // Link under the point in the panel's coordinates
+    async fn link_at(&self, pos: Vector2) -> crate::Result<Option<String>> {
+        let rects = self.core.read().await.rects.clone();
+        let index = match rects.iter().position(|v| v.contains(pos)) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        if let Item::Text {
+            rich_text, links, ..
+        } = &self.items[index]
+        {
+            let local = rects[index].to_local(pos);
+            if let Some(run) = rich_text.run_at(local.into()).await? {
+                return Ok(links[run].clone());
+            }
+        }
+        Ok(None)
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => {
+                self.container.SetSize(*size)?;
+                self.layout(size.X, source.clone()).await?;
+            }
+            PanelEvent::CursorMoved(pos) => self.core.write().await.mouse_pos = Some(*pos),
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let mouse_pos = self.core.read().await.mouse_pos;
+                let link = match mouse_pos {
+                    Some(pos) if *in_slot => self.link_at(pos).await?,
+                    _ => None,
+                };
+                let clicked = {
+                    let mut core = self.core.write().await;
+                    match state {
+                        ElementState::Pressed => {
+                            core.pressed = link;
+                            None
+                        }
+                        ElementState::Released => {
+                            core.pressed.take().filter(|v| link.as_ref() == Some(v))
+                        }
+                    }
+                };
+                if let Some(url) = clicked {
+                    self.html_view_events
+                        .send_event(HtmlViewEvent::LinkClicked(url), source.clone())
+                        .await;
+                }
+            }
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<HtmlViewEvent> for HtmlView {
+    fn event_stream(&self) -> EventStream<HtmlViewEvent> {
+        self.html_view_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for HtmlView {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for HtmlView {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for HtmlView {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str) -> HtmlSpan {
+        HtmlSpan {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn paragraph(spans: Vec<HtmlSpan>) -> HtmlBlock {
+        HtmlBlock::Paragraph { heading: 0, spans }
+    }
+
+    #[test]
+    fn formatting_is_split_into_spans() {
+        let blocks = parse_html("<h2>Title</h2><p>Plain <b>bold</b> and\n  <i>italic</i></p>");
+        assert_eq!(
+            blocks,
+            vec![
+                HtmlBlock::Paragraph {
+                    heading: 2,
+                    spans: vec![span("Title")],
+                },
+                paragraph(vec![
+                    span("Plain "),
+                    HtmlSpan {
+                        bold: true,
+                        ..span("bold")
+                    },
+                    span(" and "),
+                    HtmlSpan {
+                        italic: true,
+                        ..span("italic")
+                    },
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn scripts_and_unsafe_links_are_dropped() {
+        let blocks = parse_html(
+            "<script>if (a <b) alert('<p>x</p>')</script>\
+             <p onclick=\"steal()\">Hi <a href=\"java\tscript:steal()\">there</a> \
+             <a href=' https://example.com/?a=1&amp;b=2'>link</a></p>",
+        );
+        assert_eq!(
+            blocks,
+            vec![paragraph(vec![
+                span("Hi there "),
+                HtmlSpan {
+                    link: Some("https://example.com/?a=1&b=2".to_string()),
+                    ..span("link")
+                },
+            ])]
+        );
+    }
+
+    #[test]
+    fn lists_are_numbered_and_nested() {
+        let blocks = parse_html("<ol start=3><li>a</li><li>b<ul><li>c</ul></li></ol>");
+        assert_eq!(
+            blocks,
+            vec![
+                HtmlBlock::ListItem {
+                    depth: 1,
+                    number: Some(3),
+                    spans: vec![span("a")],
+                },
+                HtmlBlock::ListItem {
+                    depth: 1,
+                    number: Some(4),
+                    spans: vec![span("b")],
+                },
+                HtmlBlock::ListItem {
+                    depth: 2,
+                    number: None,
+                    spans: vec![span("c")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_markup_falls_back_to_text() {
+        let blocks = parse_html(
+            "<!-- note --><custom>  a&nbsp;&lt;b&gt;\n c </custom>&#x41;&bogus; 1 < 2\
+             <p>before<img src=\"cid:logo\" alt=\" Logo \">after",
+        );
+        assert_eq!(
+            blocks,
+            vec![
+                paragraph(vec![span("a\u{A0}<b> c A&bogus; 1 < 2")]),
+                paragraph(vec![span("before")]),
+                HtmlBlock::Image {
+                    src: "cid:logo".to_string(),
+                    alt: "Logo".to_string(),
+                },
+                paragraph(vec![span("after")]),
+            ]
+        );
+    }
+}
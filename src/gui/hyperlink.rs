@@ -0,0 +1,314 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::{Matrix3x2, Vector2},
+    Win32::Graphics::{
+        Direct2D::{
+            Common::D2D_POINT_2F, D2D1_BRUSH_PROPERTIES, D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+        },
+        DirectWrite::DWRITE_TEXT_RANGE,
+    },
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, SpriteVisual, Visual},
+    },
+};
+use winit::{
+    event::{ElementState, MouseButton, VirtualKeyCode},
+    window::CursorIcon,
+};
+
+use crate::{
+    geometry::Rect,
+    window::{dwrite_factory, open_url},
+};
+
+use super::{
+    attach,
+    button::create_focus_ring,
+    dispatch::DispatchQueue,
+    menu::{d2d_color, text_format, Label},
+    CursorSelector, Panel, PanelEvent, Surface, SurfaceParams,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum HyperlinkEvent {
+    /// The link is clicked or Enter is pressed while it has focus. The URL, if any, is
+    /// already opened.
+    Activated,
+}
+
+#[derive(Default)]
+struct Core {
+    size: Vector2,
+    mouse_pos: Option<Vector2>,
+    hover: bool,
+    pressed: bool,
+    focused: bool,
+}
+
+///
+/// Underlined text activated by click or by Enter when focused. If the URL is given, the
+/// activation opens it in the default browser. The link has its natural width `width()`
+/// defined by the text, it's drawn at the left side of the area given to it.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Hyperlink {
+    container: ContainerVisual,
+    surface: Arc<Surface>,
+    focus_ring: SpriteVisual,
+    text: String,
+    label: Label,
+    text_width: f32,
+    url: Option<String>,
+    color: Color,
+    hover_color: Color,
+    core: RwLock<Core>,
+    hyperlink_events: EventStreams<HyperlinkEvent>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct HyperlinkParams {
+    compositor: Compositor,
+    #[builder(setter(into))]
+    text: String,
+    #[builder(default, setter(strip_option, into))]
+    url: Option<String>,
+    #[builder(default = Colors::RoyalBlue().unwrap())]
+    color: Color,
+    #[builder(default = Colors::DarkBlue().unwrap())]
+    hover_color: Color,
+}
+
+impl TryFrom<HyperlinkParams> for Hyperlink {
+    type Error = crate::Error;
+
+    fn try_from(value: HyperlinkParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(value.compositor.clone())
+            .build()
+            .try_into()?;
+        attach(&container, &*surface)?;
+        let focus_ring = create_focus_ring(&value.compositor)?;
+        container.Children()?.InsertAtTop(&focus_ring)?;
+        let label = Label::plain(&value.text);
+        let text_width = label.width(&text_format()?)?;
+        Ok(Hyperlink {
+            container,
+            surface,
+            focus_ring,
+            text: value.text,
+            label,
+            text_width,
+            url: value.url,
+            color: value.color,
+            hover_color: value.hover_color,
+            core: RwLock::new(Core::default()),
+            hyperlink_events: EventStreams::new(),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<HyperlinkParams> for Arc<Hyperlink> {
+    type Error = crate::Error;
+
+    fn try_from(value: HyperlinkParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Hyperlink {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    pub fn width(&self) -> f32 {
+        self.text_width
+    }
+
+    /// Shows the hand cursor over the link, pass the window's `cursors()`
+    pub fn set_hand_cursor(self: &Arc<Self>, cursors: &CursorSelector) {
+        let panel: Arc<dyn Panel> = self.clone();
+        cursors.set_cursor(&panel, CursorIcon::Hand);
+    }
+
+    pub async fn activate(&self) -> crate::Result<()> {
+        if let Some(url) = &self.url {
+            open_url(url)?;
+        }
+        self.hyperlink_events
+            .send_event(HyperlinkEvent::Activated, None)
+            .await;
+        Ok(())
+    }
+
+    fn hit(&self, size: Vector2, pos: Vector2) -> bool {
+        let size = Vector2 {
+            X: self.text_width.min(size.X),
+            Y: size.Y,
+        };
+        Rect::from_size(size).contains(pos)
+    }
+
+    fn redraw(&self, hover: bool) -> crate::Result<()> {
+        self.surface.draw(|context, size| {
+            let text_format = text_format()?;
+            let layout = unsafe {
+                dwrite_factory()?.CreateTextLayout(&self.label.text, &text_format, size.X, size.Y)
+            }?;
+            unsafe {
+                layout.SetUnderline(
+                    true,
+                    DWRITE_TEXT_RANGE {
+                        startPosition: 0,
+                        length: self.label.text.len() as u32,
+                    },
+                )
+            }?;
+            let color = if hover { self.hover_color } else { self.color };
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(color), Some(&brush_properties))
+            }?;
+            unsafe {
+                context.Clear(Some(&d2d_color(Colors::Transparent()?)));
+                context.DrawTextLayout(
+                    D2D_POINT_2F { x: 0., y: 0. },
+                    &layout,
+                    &brush,
+                    D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+                );
+            }
+            Ok(())
+        })
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => {
+                self.container.SetSize(*size)?;
+                self.surface.on_event_ref(&event, source.clone()).await?;
+                let hover = {
+                    let mut core = self.core.write().await;
+                    core.size = *size;
+                    core.hover
+                };
+                self.redraw(hover)?;
+            }
+            PanelEvent::CursorMoved(pos) => {
+                let hover = {
+                    let mut core = self.core.write().await;
+                    core.mouse_pos = Some(*pos);
+                    let hover = self.hit(core.size, *pos);
+                    if core.hover == hover {
+                        None
+                    } else {
+                        core.hover = hover;
+                        Some(hover)
+                    }
+                };
+                if let Some(hover) = hover {
+                    self.redraw(hover)?;
+                }
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let click = {
+                    let mut core = self.core.write().await;
+                    let hit = *in_slot && core.mouse_pos.map_or(false, |v| self.hit(core.size, v));
+                    match state {
+                        ElementState::Pressed => {
+                            core.pressed = hit;
+                            core.focused = hit;
+                            self.focus_ring.SetIsVisible(hit)?;
+                            false
+                        }
+                        ElementState::Released => std::mem::take(&mut core.pressed) && hit,
+                    }
+                };
+                if click {
+                    self.activate().await?;
+                }
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(VirtualKeyCode::Return),
+                ..
+            } => {
+                if self.core.read().await.focused {
+                    self.activate().await?;
+                }
+            }
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<HyperlinkEvent> for Hyperlink {
+    fn event_stream(&self) -> EventStream<HyperlinkEvent> {
+        self.hyperlink_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for Hyperlink {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Hyperlink {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Hyperlink {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
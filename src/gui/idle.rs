@@ -0,0 +1,165 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_event_streams::{EventSource, EventStream, EventStreams};
+use async_std::sync::Arc;
+use futures::task::{Spawn, SpawnExt};
+
+use crate::window::system_idle_time;
+
+use super::clock;
+
+/// How often the system-wide idle time is checked for the input in other applications
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum IdleEvent {
+    /// There was no input for the given threshold
+    Idle(Duration),
+    /// The input came after some of thresholds was reached
+    Active,
+}
+
+struct State {
+    last_input: Instant,
+    // Sorted ascending
+    thresholds: Vec<Duration>,
+    // Number of thresholds reached since the last input
+    reached: usize,
+    system_wide: bool,
+}
+
+///
+/// Idle detection for kiosk and security applications which dim, lock or reset the UI when
+/// the user is away. The monitor counts the time since the last input in the window, or in
+/// the whole system if `system_wide` is set, and sends `IdleEvent::Idle` when each of the
+/// thresholds is reached and `IdleEvent::Active` on the input after that.
+///
+#[derive(Clone)]
+pub struct IdleMonitor {
+    state: Arc<Mutex<State>>,
+    generation: Arc<AtomicUsize>,
+    idle_events: Arc<EventStreams<IdleEvent>>,
+}
+
+impl Default for IdleMonitor {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                last_input: clock().now(),
+                thresholds: Vec::new(),
+                reached: 0,
+                system_wide: false,
+            })),
+            generation: Arc::new(AtomicUsize::new(0)),
+            idle_events: Arc::new(EventStreams::new()),
+        }
+    }
+}
+
+impl IdleMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_thresholds(&self, thresholds: impl IntoIterator<Item = Duration>) {
+        let mut state = self.state.lock().unwrap();
+        state.thresholds = thresholds.into_iter().collect();
+        state.thresholds.sort();
+        state.reached = 0;
+    }
+
+    pub fn set_system_wide(&self, system_wide: bool) {
+        self.state.lock().unwrap().system_wide = system_wide;
+    }
+
+    /// Time since the last input
+    pub fn idle_time(&self) -> Duration {
+        Self::state_idle_time(&self.state.lock().unwrap())
+    }
+
+    fn state_idle_time(state: &State) -> Duration {
+        let idle = clock().now().saturating_duration_since(state.last_input);
+        if state.system_wide {
+            idle.min(system_idle_time())
+        } else {
+            idle
+        }
+    }
+
+    /// Starts tracking the thresholds on the spawner until stopped
+    pub fn start<S: Spawn + ?Sized>(&self, spawner: &S) -> crate::Result<()> {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let monitor = self.clone();
+        let clock = clock();
+        spawner.spawn(async move {
+            while monitor.generation.load(Ordering::SeqCst) == generation {
+                let delay = monitor.check().await;
+                clock.sleep(delay).await;
+            }
+        })?;
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Registers the input in the window
+    pub(super) fn input(&self) {
+        let active = {
+            let mut state = self.state.lock().unwrap();
+            state.last_input = clock().now();
+            std::mem::take(&mut state.reached) > 0
+        };
+        if active {
+            self.idle_events.post_event(IdleEvent::Active, None);
+        }
+    }
+
+    // Sends events for the reached thresholds, returns the delay until the next check
+    async fn check(&self) -> Duration {
+        let (events, delay) = {
+            let mut state = self.state.lock().unwrap();
+            let idle = Self::state_idle_time(&state);
+            let mut events = Vec::new();
+            // The idle time decreases only when there was the input in other application
+            if state.reached > 0 && idle < state.thresholds[state.reached - 1] {
+                state.reached = 0;
+                events.push(IdleEvent::Active);
+            }
+            while let Some(threshold) = state.thresholds.get(state.reached).cloned() {
+                if idle < threshold {
+                    break;
+                }
+                state.reached += 1;
+                events.push(IdleEvent::Idle(threshold));
+            }
+            let delay = match state.thresholds.get(state.reached) {
+                Some(threshold) => *threshold - idle,
+                None => POLL_INTERVAL,
+            };
+            let delay = if state.system_wide {
+                delay.min(POLL_INTERVAL)
+            } else {
+                delay
+            };
+            (events, delay)
+        };
+        for event in events {
+            self.idle_events.send_event(event, None).await;
+        }
+        delay
+    }
+}
+
+impl EventSource<IdleEvent> for IdleMonitor {
+    fn event_stream(&self) -> EventStream<IdleEvent> {
+        self.idle_events.create_event_stream()
+    }
+}
@@ -3,25 +3,53 @@ use std::borrow::Cow;
 use async_event_streams_derive::EventSink;
 use async_std::sync::{Arc, RwLock};
 
-use super::{attach, detach, Panel, PanelEvent};
+use super::{
+    attach, detach, dispatch::DispatchQueue, hit_test, is_visible, EventSeqId, Panel, PanelEvent,
+    PanelFactory,
+};
 use async_event_streams::{
     EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
 };
 use async_trait::async_trait;
+use futures::task::Spawn;
 
 use typed_builder::TypedBuilder;
-use windows::UI::Composition::{Compositor, ContainerVisual, Visual};
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::Composition::{Compositor, ContainerVisual, Visual},
+};
+use winit::event::{ElementState, MouseButton};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum LayerStackEvent {
+    /// Layers were added, removed or reordered
+    LayersChanged,
+}
 
 struct Core {
+    // From bottom to top
     layers: Vec<Arc<dyn Panel>>,
+    mouse_pos: Option<Vector2>,
 }
 
+///
+/// Panels placed one over another, all of them receive the events. Layers are ordered from
+/// bottom (index 0) to top, the order of the container visual's children follows it.
+///
+/// Mouse clicks are taken by the topmost layer hit at the cursor according to its
+/// [`HitTestMode`], the other layers receive them with `in_slot` cleared. So the decoration
+/// layer made transparent doesn't steal the clicks from the content beneath.
+///
+/// [`HitTestMode`]: super::HitTestMode
+///
 #[derive(EventSink)]
 #[event_sink(event=PanelEvent)]
 pub struct LayerStack {
     container: ContainerVisual,
     core: RwLock<Core>,
     panel_events: EventStreams<PanelEvent>,
+    layer_stack_events: EventStreams<LayerStackEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
     id: Arc<()>,
 }
 
@@ -30,20 +58,112 @@ impl LayerStack {
         self.core.read().await.layers.clone()
     }
 
-    pub async fn push_panel(&mut self, panel: Arc<dyn Panel>) -> crate::Result<()> {
+    /// Adds the panel on top of the others
+    pub async fn push_panel(&self, panel: Arc<dyn Panel>) -> crate::Result<()> {
         attach(&self.container, &*panel)?;
         self.core.write().await.layers.push(panel);
+        self.layers_changed().await;
+        Ok(())
+    }
+
+    /// Adds the panel at the index, the index past the end puts it on top
+    pub async fn insert_panel(&self, index: usize, panel: Arc<dyn Panel>) -> crate::Result<()> {
+        {
+            let mut core = self.core.write().await;
+            let index = index.min(core.layers.len());
+            attach(&self.container, &*panel)?;
+            core.layers.insert(index, panel);
+            self.restack(&core.layers)?;
+        }
+        self.layers_changed().await;
+        Ok(())
+    }
+
+    pub async fn remove_panel(&self, panel: impl Panel) -> crate::Result<()> {
+        let removed = {
+            let mut core = self.core.write().await;
+            match core.layers.iter().position(|v| v.id() == panel.id()) {
+                Some(index) => {
+                    detach(&panel)?;
+                    core.layers.remove(index);
+                    true
+                }
+                None => false,
+            }
+        };
+        if removed {
+            self.layers_changed().await;
+        }
         Ok(())
     }
 
-    pub async fn remove_panel(&mut self, panel: impl Panel) -> crate::Result<()> {
-        let mut core = self.core.write().await;
-        if let Some(index) = core.layers.iter().position(|v| v.id() == panel.id()) {
-            detach(&panel)?;
-            core.layers.remove(index);
+    /// Raises the layer with the given panel id above all others
+    pub async fn move_to_top(&self, id: usize) -> crate::Result<bool> {
+        self.reorder(|layers| {
+            let index = layers.iter().position(|v| v.id() == id)?;
+            let layer = layers.remove(index);
+            layers.push(layer);
+            Some(())
+        })
+        .await
+    }
+
+    /// Lowers the layer with the given panel id below all others
+    pub async fn move_to_bottom(&self, id: usize) -> crate::Result<bool> {
+        self.reorder(|layers| {
+            let index = layers.iter().position(|v| v.id() == id)?;
+            let layer = layers.remove(index);
+            layers.insert(0, layer);
+            Some(())
+        })
+        .await
+    }
+
+    /// Exchanges positions of two layers with the given panel ids
+    pub async fn swap(&self, a: usize, b: usize) -> crate::Result<bool> {
+        self.reorder(|layers| {
+            let a = layers.iter().position(|v| v.id() == a)?;
+            let b = layers.iter().position(|v| v.id() == b)?;
+            layers.swap(a, b);
+            Some(())
+        })
+        .await
+    }
+
+    // Applies the change to the order of the layers, returns false if the change was
+    // rejected because some of the panels isn't in the stack
+    async fn reorder(
+        &self,
+        change: impl FnOnce(&mut Vec<Arc<dyn Panel>>) -> Option<()>,
+    ) -> crate::Result<bool> {
+        {
+            let mut core = self.core.write().await;
+            if change(&mut core.layers).is_none() {
+                return Ok(false);
+            }
+            self.restack(&core.layers)?;
+        }
+        self.layers_changed().await;
+        Ok(true)
+    }
+
+    // Puts the children of the container visual in the order of the layers
+    fn restack(&self, layers: &[Arc<dyn Panel>]) -> crate::Result<()> {
+        let children = self.container.Children()?;
+        for layer in layers {
+            let visual = layer.outer_frame();
+            children.Remove(&visual)?;
+            children.InsertAtTop(&visual)?;
         }
         Ok(())
     }
+
+    async fn layers_changed(&self) {
+        self.layer_stack_events
+            .send_event(LayerStackEvent::LayersChanged, None)
+            .await;
+    }
+
     async fn translate_event_to_all_layers(
         &self,
         event: &PanelEvent,
@@ -51,17 +171,55 @@ impl LayerStack {
     ) -> crate::Result<()> {
         // TODO: run simultaneously
         for item in self.layers().await {
+            if event.is_input() && !is_visible(&*item)? {
+                continue;
+            }
             item.on_event_ref(event, source.clone()).await?;
         }
         Ok(())
     }
-    async fn translate_event_to_top_layer(
+    async fn translate_mouse_input(
         &self,
-        event: &PanelEvent,
+        in_slot: bool,
+        state: ElementState,
+        button: MouseButton,
+        click_count: u32,
+        seq: EventSeqId,
         source: Option<Arc<EventBox>>,
     ) -> crate::Result<()> {
-        if let Some(item) = self.layers().await.first_mut() {
-            item.on_event_ref(event, source).await?;
+        let (layers, mouse_pos) = {
+            let core = self.core.read().await;
+            (core.layers.clone(), core.mouse_pos)
+        };
+        let hit = match mouse_pos {
+            Some(pos) => {
+                let mut hit = None;
+                for (index, layer) in layers.iter().enumerate().rev() {
+                    if hit_test(&**layer, pos)? {
+                        hit = Some(index);
+                        break;
+                    }
+                }
+                hit
+            }
+            None => None,
+        };
+        for (index, layer) in layers.iter().enumerate() {
+            if !is_visible(&**layer)? {
+                continue;
+            }
+            layer
+                .on_event_owned(
+                    PanelEvent::MouseInput {
+                        in_slot: in_slot && hit == Some(index),
+                        state,
+                        button,
+                        click_count,
+                        seq,
+                    },
+                    source.clone(),
+                )
+                .await?;
         }
         Ok(())
     }
@@ -75,7 +233,20 @@ impl LayerStack {
                 self.container.SetSize(*size)?;
                 self.translate_event_to_all_layers(event, source).await
             }
-            PanelEvent::MouseInput { .. } => self.translate_event_to_top_layer(event, source).await,
+            PanelEvent::CursorMoved(pos) => {
+                self.core.write().await.mouse_pos = Some(*pos);
+                self.translate_event_to_all_layers(event, source).await
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                click_count,
+                seq,
+            } => {
+                self.translate_mouse_input(*in_slot, *state, *button, *click_count, *seq, source)
+                    .await
+            }
             _ => self.translate_event_to_all_layers(event, source).await,
         }
     }
@@ -108,6 +279,19 @@ impl LayerStackParams {
         self.layers.push(panel);
         self
     }
+    /// Adds the layer built by `f` from the factory's compositor and spawner
+    pub fn push_child<S, P, X>(
+        self,
+        factory: &PanelFactory<S>,
+        f: impl FnOnce(Compositor, S) -> X,
+    ) -> crate::Result<Self>
+    where
+        S: Spawn + Clone,
+        P: Panel + 'static,
+        X: TryInto<Arc<P>, Error = crate::Error>,
+    {
+        Ok(self.push_panel(factory.create::<P, X>(f)?))
+    }
 }
 
 impl TryFrom<LayerStackParams> for LayerStack {
@@ -119,12 +303,17 @@ impl TryFrom<LayerStackParams> for LayerStack {
         for layer in &mut layers {
             attach(&container, &**layer)?;
         }
-        let core = RwLock::new(Core { layers });
+        let core = RwLock::new(Core {
+            layers,
+            mouse_pos: None,
+        });
         // container.SetComment(HSTRING::from("LAYER_STACK"))?;
         Ok(LayerStack {
             container,
             core,
             panel_events: EventStreams::new(),
+            layer_stack_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
             id: Arc::new(()),
         })
     }
@@ -153,6 +342,12 @@ impl EventSource<PanelEvent> for LayerStack {
     }
 }
 
+impl EventSource<LayerStackEvent> for LayerStack {
+    fn event_stream(&self) -> EventStream<LayerStackEvent> {
+        self.layer_stack_events.create_event_stream()
+    }
+}
+
 #[async_trait]
 impl EventSinkExt<PanelEvent> for LayerStack {
     type Error = crate::Error;
@@ -161,10 +356,12 @@ impl EventSinkExt<PanelEvent> for LayerStack {
         event: Cow<'a, PanelEvent>,
         source: Option<Arc<EventBox>>,
     ) -> crate::Result<()> {
-        self.translate_event(event.as_ref(), source.clone()).await?;
-        self.panel_events
-            .send_event(event.into_owned(), source)
-            .await;
-        Ok(())
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| async move {
+                self.translate_event(&event, source.clone()).await?;
+                self.panel_events.send_event(event, source).await;
+                Ok(())
+            })
+            .await
     }
 }
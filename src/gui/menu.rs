@@ -0,0 +1,882 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::InParam,
+    w,
+    Foundation::Numerics::{Matrix3x2, Vector2},
+    Win32::Graphics::{
+        Direct2D::{
+            Common::{D2D1_COLOR_F, D2D_POINT_2F, D2D_RECT_F},
+            ID2D1DeviceContext, ID2D1SolidColorBrush, D2D1_BRUSH_PROPERTIES,
+            D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+        },
+        DirectWrite::{
+            IDWriteTextFormat, DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_NORMAL,
+            DWRITE_FONT_WEIGHT_NORMAL, DWRITE_MEASURING_MODE_NATURAL,
+            DWRITE_PARAGRAPH_ALIGNMENT_CENTER, DWRITE_TEXT_ALIGNMENT_TRAILING, DWRITE_TEXT_METRICS,
+            DWRITE_TEXT_RANGE, DWRITE_WORD_WRAPPING_NO_WRAP,
+        },
+    },
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, ShapeVisual, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+use crate::{
+    geometry::{Point, Rect, Size},
+    window::{dwrite_factory, ToWide},
+};
+
+use super::{
+    attach, dispatch::DispatchQueue, Command, CommandRegistry, OverlayHost, Panel, PanelEvent,
+    Placement, PopupSide, Surface, SurfaceParams,
+};
+
+const ITEM_HEIGHT: f32 = 32.;
+const SEPARATOR_HEIGHT: f32 = 9.;
+const PADDING: f32 = 4.;
+const TEXT_MARGIN: f32 = 12.;
+const CHECK_WIDTH: f32 = 20.;
+
+///
+/// Item of the menu. Item with nested items opens the submenu, other items emit
+/// `MenuEvent::Activated` with their id when chosen. The character after `&` in the text
+/// is the mnemonic: it's underlined and pressing it chooses the item (`&&` is the `&` itself).
+///
+#[derive(Clone, Debug)]
+pub struct MenuItem {
+    pub id: usize,
+    pub text: String,
+    pub shortcut: Option<String>,
+    pub enabled: bool,
+    pub checked: bool,
+    pub separator: bool,
+    pub items: Vec<MenuItem>,
+}
+
+impl MenuItem {
+    pub fn new(id: usize, text: impl Into<String>) -> Self {
+        Self {
+            id,
+            text: text.into(),
+            shortcut: None,
+            enabled: true,
+            checked: false,
+            separator: false,
+            items: Vec::new(),
+        }
+    }
+    /// Item invoking the command, with the command's accelerator shown as the shortcut
+    pub fn command(command: &Command) -> Self {
+        Self {
+            shortcut: command.accelerator.map(|v| v.to_string()),
+            ..Self::new(command.id, command.text.clone())
+        }
+    }
+    pub fn separator() -> Self {
+        Self {
+            id: 0,
+            text: String::new(),
+            shortcut: None,
+            enabled: false,
+            checked: false,
+            separator: true,
+            items: Vec::new(),
+        }
+    }
+    pub fn submenu(id: usize, text: impl Into<String>, items: Vec<MenuItem>) -> Self {
+        Self {
+            items,
+            ..Self::new(id, text)
+        }
+    }
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+    /// Shows the check mark, items of checked commands have it without this flag
+    pub fn checked(mut self, checked: bool) -> Self {
+        self.checked = checked;
+        self
+    }
+    /// Text shown at the right side of the item, e.g. "Ctrl+S"
+    pub fn with_shortcut(mut self, shortcut: impl Into<String>) -> Self {
+        self.shortcut = Some(shortcut.into());
+        self
+    }
+    pub fn has_submenu(&self) -> bool {
+        !self.items.is_empty()
+    }
+    fn is_selectable(&self) -> bool {
+        self.enabled && !self.separator
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum MenuEvent {
+    Activated(usize),
+}
+
+#[derive(Clone)]
+pub(super) struct MenuStyle {
+    pub width: f32,
+    pub background: Color,
+    pub highlight: Color,
+    pub text_color: Color,
+    pub disabled_text_color: Color,
+}
+
+// Chain of open menus: ids of the root menu and its open submenus, one per level
+#[derive(Default)]
+struct Chain {
+    host: Option<Arc<OverlayHost>>,
+    menus: Vec<usize>,
+}
+
+// State shared by the menu and all its submenus
+struct Shared {
+    compositor: Compositor,
+    style: MenuStyle,
+    chain: RwLock<Chain>,
+    commands: Option<Arc<CommandRegistry>>,
+    menu_events: Arc<EventStreams<MenuEvent>>,
+}
+
+impl Shared {
+    async fn host(&self) -> Option<Arc<OverlayHost>> {
+        self.chain.read().await.host.clone()
+    }
+    /// Closes menus starting from given level
+    async fn close_from(&self, level: usize) -> crate::Result<()> {
+        let (host, closed) = {
+            let mut chain = self.chain.write().await;
+            let level = level.min(chain.menus.len());
+            (chain.host.clone(), chain.menus.split_off(level))
+        };
+        if let Some(host) = host {
+            for id in closed.into_iter().rev() {
+                host.close_popup(id).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn item_rects(items: &[MenuItem], width: f32) -> Vec<Rect> {
+    let mut y = PADDING;
+    items
+        .iter()
+        .map(|item| {
+            let height = if item.separator {
+                SEPARATOR_HEIGHT
+            } else {
+                ITEM_HEIGHT
+            };
+            let rect = Rect::new(Point::new(0., y), Size::new(width, height));
+            y += height;
+            rect
+        })
+        .collect()
+}
+
+// Default text format of menu items
+pub(super) fn text_format() -> crate::Result<IDWriteTextFormat> {
+    sized_text_format(14.)
+}
+
+// Text format of the default font with given size, vertically centered
+pub(super) fn sized_text_format(size: f32) -> crate::Result<IDWriteTextFormat> {
+    let text_format = unsafe {
+        dwrite_factory()?.CreateTextFormat(
+            w!("Segoe UI"),
+            InParam::null(),
+            DWRITE_FONT_WEIGHT_NORMAL,
+            DWRITE_FONT_STYLE_NORMAL,
+            DWRITE_FONT_STRETCH_NORMAL,
+            size,
+            w!("en-US"),
+        )
+    }?;
+    unsafe { text_format.SetParagraphAlignment(DWRITE_PARAGRAPH_ALIGNMENT_CENTER)? };
+    Ok(text_format)
+}
+
+pub(super) fn d2d_color(color: Color) -> D2D1_COLOR_F {
+    D2D1_COLOR_F {
+        r: color.R as f32 / 255.,
+        g: color.G as f32 / 255.,
+        b: color.B as f32 / 255.,
+        a: color.A as f32 / 255.,
+    }
+}
+
+// Item text without mnemonic markers and the mnemonic with its position in UTF-16 units
+pub(super) struct Label {
+    pub text: Vec<u16>,
+    pub mnemonic: Option<(u32, char)>,
+}
+
+impl Label {
+    /// Label shown as is, without mnemonic
+    pub fn plain(text: &str) -> Self {
+        Self {
+            text: text.encode_utf16().collect(),
+            mnemonic: None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut result = String::new();
+        let mut mnemonic = None;
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '&' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('&') => result.push('&'),
+                Some(next) => {
+                    if mnemonic.is_none() {
+                        let pos = result.encode_utf16().count() as u32;
+                        mnemonic = Some((pos, next.to_ascii_lowercase()));
+                    }
+                    result.push(next);
+                }
+                None => (),
+            }
+        }
+        Self {
+            text: result.encode_utf16().collect(),
+            mnemonic,
+        }
+    }
+
+    pub fn matches(&self, key: VirtualKeyCode) -> bool {
+        match (self.mnemonic, key_char(key)) {
+            (Some((_, mnemonic)), Some(c)) => mnemonic == c,
+            _ => false,
+        }
+    }
+
+    ///
+    /// Width of the text rounded up to whole DIPs, so that the text fits into the rectangle
+    /// of this width when drawn and the layouts built on it don't get fractional offsets
+    ///
+    pub fn width(&self, format: &IDWriteTextFormat) -> crate::Result<f32> {
+        let layout =
+            unsafe { dwrite_factory()?.CreateTextLayout(&self.text, format, f32::MAX, f32::MAX)? };
+        let mut metrics = DWRITE_TEXT_METRICS::default();
+        unsafe { layout.GetMetrics(&mut metrics)? };
+        Ok(metrics.widthIncludingTrailingWhitespace.ceil())
+    }
+
+    pub fn draw(
+        &self,
+        context: &ID2D1DeviceContext,
+        format: &IDWriteTextFormat,
+        rect: D2D_RECT_F,
+        brush: &ID2D1SolidColorBrush,
+    ) -> crate::Result<()> {
+        let layout = unsafe {
+            dwrite_factory()?.CreateTextLayout(
+                &self.text,
+                format,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+            )?
+        };
+        // The measured width may still differ from the rectangle's one in the last bits,
+        // this must not move the last word to the next line
+        unsafe { layout.SetWordWrapping(DWRITE_WORD_WRAPPING_NO_WRAP)? };
+        if let Some((pos, _)) = self.mnemonic {
+            unsafe {
+                layout.SetUnderline(
+                    true,
+                    DWRITE_TEXT_RANGE {
+                        startPosition: pos,
+                        length: 1,
+                    },
+                )?
+            };
+        }
+        unsafe {
+            context.DrawTextLayout(
+                D2D_POINT_2F {
+                    x: rect.left,
+                    y: rect.top,
+                },
+                &layout,
+                brush,
+                D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+            )
+        };
+        Ok(())
+    }
+}
+
+// Lowercase character of the letter or digit key
+pub(super) fn key_char(key: VirtualKeyCode) -> Option<char> {
+    let name = format!("{:?}", key);
+    // Digit keys are named like "Key1"
+    let name = name.strip_prefix("Key").unwrap_or(&name);
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+struct Core {
+    highlighted: Option<usize>,
+    // Index of the item and id of its open submenu
+    submenu: Option<(usize, usize)>,
+}
+
+///
+/// Popup menu with nested submenus, shown in the [`OverlayHost`] at arbitrary position
+/// (e.g. on right click) or anchored to a panel. Supports mouse and keyboard navigation:
+/// Up/Down moves the highlight, Right/Enter opens the submenu, Left closes it, Enter
+/// activates the item, the item's mnemonic key chooses it. Click outside or Escape closes
+/// the menu. Items with ids of the commands in the [`CommandRegistry`] invoke these commands
+/// and are ignored while the command is disabled.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Menu {
+    container: ContainerVisual,
+    shapes: ShapeVisual,
+    surface: Arc<Surface>,
+    items: Vec<MenuItem>,
+    labels: Vec<Label>,
+    rects: Vec<Rect>,
+    level: usize,
+    shared: Arc<Shared>,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct MenuParams {
+    compositor: Compositor,
+    #[builder(default)]
+    items: Vec<MenuItem>,
+    #[builder(default = 200.)]
+    width: f32,
+    #[builder(default = Colors::WhiteSmoke().unwrap())]
+    background: Color,
+    #[builder(default = Colors::LightGray().unwrap())]
+    highlight: Color,
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+    #[builder(default = Colors::Gray().unwrap())]
+    disabled_text_color: Color,
+    #[builder(default, setter(strip_option))]
+    commands: Option<Arc<CommandRegistry>>,
+}
+
+impl MenuParams {
+    pub fn add_item(mut self, item: MenuItem) -> Self {
+        self.items.push(item);
+        self
+    }
+}
+
+impl TryFrom<MenuParams> for Menu {
+    type Error = crate::Error;
+
+    fn try_from(value: MenuParams) -> crate::Result<Self> {
+        let style = MenuStyle {
+            width: value.width,
+            background: value.background,
+            highlight: value.highlight,
+            text_color: value.text_color,
+            disabled_text_color: value.disabled_text_color,
+        };
+        Menu::create(
+            value.compositor,
+            value.items,
+            style,
+            value.commands,
+            Arc::new(EventStreams::new()),
+        )
+    }
+}
+
+impl TryFrom<MenuParams> for Arc<Menu> {
+    type Error = crate::Error;
+
+    fn try_from(value: MenuParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Menu {
+    /// Creates the root menu sending `MenuEvent`s to the given streams
+    pub(super) fn create(
+        compositor: Compositor,
+        items: Vec<MenuItem>,
+        style: MenuStyle,
+        commands: Option<Arc<CommandRegistry>>,
+        menu_events: Arc<EventStreams<MenuEvent>>,
+    ) -> crate::Result<Self> {
+        let shared = Arc::new(Shared {
+            compositor,
+            style,
+            chain: RwLock::new(Chain::default()),
+            commands,
+            menu_events,
+        });
+        Menu::new(shared, items, 0)
+    }
+
+    fn new(shared: Arc<Shared>, items: Vec<MenuItem>, level: usize) -> crate::Result<Self> {
+        let container = shared.compositor.CreateContainerVisual()?;
+        let shapes = shared.compositor.CreateShapeVisual()?;
+        container.Children()?.InsertAtBottom(&shapes)?;
+        let surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(shared.compositor.clone())
+            .build()
+            .try_into()?;
+        attach(&container, &*surface)?;
+        let rects = item_rects(&items, shared.style.width);
+        let labels = items.iter().map(|v| Label::parse(&v.text)).collect();
+        Ok(Menu {
+            container,
+            shapes,
+            surface,
+            items,
+            labels,
+            rects,
+            level,
+            shared,
+            core: RwLock::new(Core {
+                highlighted: None,
+                submenu: None,
+            }),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+
+    /// Size of the menu popup
+    pub fn size(&self) -> Size {
+        let height = self.rects.last().map_or(0., |v| v.bottom()) + PADDING;
+        Size::new(self.shared.style.width, height)
+    }
+
+    /// Opens the menu at the side of the anchor rectangle in the host's coordinates
+    pub async fn show(
+        self: &Arc<Self>,
+        host: Arc<OverlayHost>,
+        anchor: Rect,
+        side: PopupSide,
+    ) -> crate::Result<()> {
+        self.shared.close_from(0).await?;
+        {
+            let mut chain = self.shared.chain.write().await;
+            chain.host = Some(host.clone());
+            chain.menus = vec![self.id()];
+        }
+        *self.core.write().await = Core {
+            highlighted: None,
+            submenu: None,
+        };
+        host.show_popup(
+            self.clone(),
+            Placement::anchored(anchor, side, self.size()),
+            true,
+        )
+        .await
+    }
+
+    /// Opens the menu at the point, e.g. at the mouse position on right click
+    pub async fn show_at(
+        self: &Arc<Self>,
+        host: Arc<OverlayHost>,
+        point: Point,
+    ) -> crate::Result<()> {
+        self.show(host, Rect::new(point, Size::default()), PopupSide::Bottom)
+            .await
+    }
+
+    /// Closes the menu with all submenus
+    pub async fn close(&self) -> crate::Result<()> {
+        self.shared.close_from(0).await
+    }
+
+    /// Highlights the first selectable item, e.g. when the menu is opened from keyboard
+    pub(super) async fn select_first(&self) -> crate::Result<()> {
+        self.set_highlighted(self.next_selectable(None, true)).await
+    }
+
+    fn redraw_shapes(&self, highlighted: Option<usize>) -> crate::Result<()> {
+        let compositor = &self.shared.compositor;
+        let style = &self.shared.style;
+        let shapes = self.shapes.Shapes()?;
+        shapes.Clear()?;
+        let append_rect = |rect: Rect, radius: f32, color: Color| -> crate::Result<()> {
+            let geometry = compositor.CreateRoundedRectangleGeometry()?;
+            geometry.SetOffset(rect.origin.into())?;
+            geometry.SetSize(rect.size.into())?;
+            geometry.SetCornerRadius(Vector2 {
+                X: radius,
+                Y: radius,
+            })?;
+            let shape = compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+            shape.SetFillBrush(&compositor.CreateColorBrushWithColor(color)?)?;
+            shapes.Append(&shape)?;
+            Ok(())
+        };
+        append_rect(Rect::from_size(self.size()), 4., style.background)?;
+        if let Some(rect) = highlighted.and_then(|index| self.rects.get(index)) {
+            append_rect(rect.inflate(-PADDING, 0.), 4., style.highlight)?;
+        }
+        for (item, rect) in self.items.iter().zip(self.rects.iter()) {
+            if item.separator {
+                let line = Rect::new(
+                    Point::new(TEXT_MARGIN, rect.center().y),
+                    Size::new(rect.size.width - TEXT_MARGIN * 2., 1.),
+                );
+                append_rect(line, 0., style.disabled_text_color)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Check marks of the items, both set in the items and taken from the checked commands
+    async fn checked_items(&self) -> Vec<bool> {
+        let mut checked = Vec::with_capacity(self.items.len());
+        for item in &self.items {
+            let command_checked = match &self.shared.commands {
+                Some(commands) => commands.is_checked(item.id).await,
+                None => false,
+            };
+            checked.push(item.checked || command_checked);
+        }
+        checked
+    }
+
+    fn redraw_text(&self, checked: &[bool]) -> crate::Result<()> {
+        let style = &self.shared.style;
+        self.surface.draw(|context, _| {
+            let trailing_format = text_format()?;
+            let text_format = text_format()?;
+            unsafe { trailing_format.SetTextAlignment(DWRITE_TEXT_ALIGNMENT_TRAILING)? };
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let text_brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(style.text_color), Some(&brush_properties))
+            }?;
+            let disabled_brush = unsafe {
+                context.CreateSolidColorBrush(
+                    &d2d_color(style.disabled_text_color),
+                    Some(&brush_properties),
+                )
+            }?;
+            unsafe { context.Clear(Some(&d2d_color(Colors::Transparent()?))) };
+            for (((item, label), rect), checked) in self
+                .items
+                .iter()
+                .zip(self.labels.iter())
+                .zip(self.rects.iter())
+                .zip(checked.iter())
+            {
+                if item.separator {
+                    continue;
+                }
+                let brush = if item.enabled {
+                    &text_brush
+                } else {
+                    &disabled_brush
+                };
+                let layout_rect = D2D_RECT_F {
+                    left: rect.left() + TEXT_MARGIN + CHECK_WIDTH,
+                    top: rect.top(),
+                    right: rect.right() - TEXT_MARGIN,
+                    bottom: rect.bottom(),
+                };
+                if *checked {
+                    let check_rect = D2D_RECT_F {
+                        left: rect.left() + TEXT_MARGIN,
+                        right: layout_rect.left,
+                        ..layout_rect
+                    };
+                    unsafe {
+                        context.DrawText(
+                            "\u{2713}".to_wide().0.as_slice(),
+                            &text_format,
+                            &check_rect,
+                            brush,
+                            D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+                            DWRITE_MEASURING_MODE_NATURAL,
+                        )
+                    };
+                }
+                label.draw(context, &text_format, layout_rect, brush)?;
+                let trailing = if item.has_submenu() {
+                    Some("\u{203A}")
+                } else {
+                    item.shortcut.as_deref()
+                };
+                if let Some(trailing) = trailing {
+                    unsafe {
+                        context.DrawText(
+                            trailing.to_wide().0.as_slice(),
+                            &trailing_format,
+                            &layout_rect,
+                            brush,
+                            D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+                            DWRITE_MEASURING_MODE_NATURAL,
+                        )
+                    };
+                }
+            }
+            Ok(())
+        })
+    }
+
+    async fn set_highlighted(&self, highlighted: Option<usize>) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        if core.highlighted != highlighted {
+            core.highlighted = highlighted;
+            self.redraw_shapes(highlighted)?;
+        }
+        Ok(())
+    }
+
+    // Next selectable item in the direction, wrapping around
+    fn next_selectable(&self, from: Option<usize>, forward: bool) -> Option<usize> {
+        let count = self.items.len();
+        (1..=count)
+            .map(|step| match (from, forward) {
+                (None, true) => step - 1,
+                (None, false) => count - step,
+                (Some(from), true) => (from + step) % count,
+                (Some(from), false) => (from + count - step) % count,
+            })
+            .find(|index| self.items[*index].is_selectable())
+    }
+
+    async fn close_submenu(&self) -> crate::Result<()> {
+        if self.core.write().await.submenu.take().is_some() {
+            self.shared.close_from(self.level + 1).await?;
+        }
+        Ok(())
+    }
+
+    async fn open_submenu(&self, index: usize, select_first: bool) -> crate::Result<()> {
+        let host = match self.shared.host().await {
+            Some(host) => host,
+            None => return Ok(()),
+        };
+        let current = self.core.read().await.submenu;
+        if let Some((current_index, id)) = current {
+            if current_index == index && host.is_open(id).await {
+                return Ok(());
+            }
+        }
+        self.shared.close_from(self.level + 1).await?;
+        let origin = match host.popup_rect(self.id()).await {
+            Some(rect) => rect.origin,
+            None => return Ok(()),
+        };
+        let submenu = Arc::new(Menu::new(
+            self.shared.clone(),
+            self.items[index].items.clone(),
+            self.level + 1,
+        )?);
+        self.shared.chain.write().await.menus.push(submenu.id());
+        self.core.write().await.submenu = Some((index, submenu.id()));
+        let anchor = self.rects[index].translate(origin);
+        host.show_popup(
+            submenu.clone(),
+            Placement::anchored(anchor, PopupSide::Right, submenu.size()),
+            true,
+        )
+        .await?;
+        if select_first {
+            submenu
+                .set_highlighted(submenu.next_selectable(None, true))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn activate(&self, index: usize) -> crate::Result<()> {
+        let item = &self.items[index];
+        if !item.is_selectable() {
+            return Ok(());
+        }
+        if item.has_submenu() {
+            self.open_submenu(index, true).await
+        } else {
+            if let Some(commands) = &self.shared.commands {
+                if commands.command(item.id).await.is_some() && !commands.is_enabled(item.id).await
+                {
+                    return Ok(());
+                }
+            }
+            self.shared.close_from(0).await?;
+            if let Some(commands) = &self.shared.commands {
+                commands.invoke(item.id).await;
+            }
+            self.shared
+                .menu_events
+                .send_event(MenuEvent::Activated(item.id), None)
+                .await;
+            Ok(())
+        }
+    }
+
+    async fn cursor_moved(&self, pos: Vector2) -> crate::Result<()> {
+        let inside = Rect::from_size(self.size()).contains(pos);
+        let index = if inside {
+            self.rects
+                .iter()
+                .position(|v| v.contains(pos))
+                .filter(|v| self.items[*v].is_selectable())
+        } else {
+            None
+        };
+        match index {
+            Some(index) => {
+                self.set_highlighted(Some(index)).await?;
+                if self.items[index].has_submenu() {
+                    self.open_submenu(index, false).await?;
+                } else {
+                    self.close_submenu().await?;
+                }
+            }
+            // Keep the highlight of the item with open submenu when the cursor leaves the menu
+            None if self.core.read().await.submenu.is_none() => self.set_highlighted(None).await?,
+            None => (),
+        }
+        Ok(())
+    }
+
+    async fn key_pressed(&self, key: VirtualKeyCode) -> crate::Result<()> {
+        let highlighted = self.core.read().await.highlighted;
+        match key {
+            VirtualKeyCode::Down => {
+                self.set_highlighted(self.next_selectable(highlighted, true))
+                    .await?
+            }
+            VirtualKeyCode::Up => {
+                self.set_highlighted(self.next_selectable(highlighted, false))
+                    .await?
+            }
+            VirtualKeyCode::Right => {
+                if let Some(index) = highlighted.filter(|v| self.items[*v].has_submenu()) {
+                    self.open_submenu(index, true).await?;
+                }
+            }
+            VirtualKeyCode::Left if self.level > 0 => self.shared.close_from(self.level).await?,
+            VirtualKeyCode::Return | VirtualKeyCode::Space => {
+                if let Some(index) = highlighted {
+                    self.activate(index).await?;
+                }
+            }
+            key => {
+                let index = self
+                    .labels
+                    .iter()
+                    .zip(self.items.iter())
+                    .position(|(label, item)| item.is_selectable() && label.matches(key));
+                if let Some(index) = index {
+                    self.set_highlighted(Some(index)).await?;
+                    self.activate(index).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => {
+                self.container.SetSize(*size)?;
+                self.shapes.SetSize(*size)?;
+                self.surface.on_event_ref(&event, source.clone()).await?;
+                self.redraw_text(&self.checked_items().await)?;
+                let highlighted = self.core.read().await.highlighted;
+                self.redraw_shapes(highlighted)?;
+            }
+            PanelEvent::CursorMoved(pos) => self.cursor_moved(*pos).await?,
+            PanelEvent::MouseInput {
+                in_slot: true,
+                state: ElementState::Released,
+                button: MouseButton::Left | MouseButton::Right,
+                ..
+            } => {
+                let highlighted = self.core.read().await.highlighted;
+                if let Some(index) = highlighted {
+                    self.activate(index).await?;
+                }
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(key),
+                ..
+            } => self.key_pressed(*key).await?,
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<MenuEvent> for Menu {
+    fn event_stream(&self) -> EventStream<MenuEvent> {
+        self.shared.menu_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for Menu {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Menu {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Menu {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,466 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::{Matrix3x2, Vector2},
+    Win32::Graphics::Direct2D::{Common::D2D_RECT_F, D2D1_BRUSH_PROPERTIES},
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, ShapeVisual, Visual},
+    },
+};
+use winit::event::{ElementState, ModifiersState, MouseButton, VirtualKeyCode};
+
+use crate::geometry::{Point, Rect, Size};
+
+use super::{
+    attach,
+    dispatch::DispatchQueue,
+    menu::{d2d_color, text_format, Label, MenuStyle},
+    CommandRegistry, Context, Menu, MenuEvent, MenuItem, OverlayHost, Panel, PanelEvent, PopupSide,
+    Surface, SurfaceParams,
+};
+
+const ITEM_PADDING: f32 = 10.;
+
+struct Core {
+    size: Vector2,
+    host: Option<Arc<OverlayHost>>,
+    mouse_pos: Option<Vector2>,
+    highlighted: Option<usize>,
+    // Index of the top-level item which drop-down menu was opened last
+    open: Option<usize>,
+}
+
+///
+/// Horizontal bar of top-level menu items opening drop-down [`Menu`]s. The drop-downs are shown
+/// in the [`OverlayHost`] set with `set_host`. While a drop-down is open, moving the mouse over
+/// other items switches to their drop-downs. Alt with the item's mnemonic key opens the drop-down
+/// from the keyboard. When the bar has the [`CommandRegistry`], the menu items invoke its
+/// commands and the bar handles the commands' accelerators.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct MenuBar {
+    container: ContainerVisual,
+    shapes: ShapeVisual,
+    surface: Arc<Surface>,
+    items: Vec<MenuItem>,
+    labels: Vec<Label>,
+    widths: Vec<f32>,
+    // Drop-down menus of the items with submenus
+    menus: Vec<Option<Arc<Menu>>>,
+    style: MenuStyle,
+    commands: Option<Arc<CommandRegistry>>,
+    core: RwLock<Core>,
+    menu_events: Arc<EventStreams<MenuEvent>>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct MenuBarParams {
+    compositor: Compositor,
+    #[builder(default)]
+    items: Vec<MenuItem>,
+    #[builder(default = 200.)]
+    menu_width: f32,
+    #[builder(default = Colors::WhiteSmoke().unwrap())]
+    background: Color,
+    #[builder(default = Colors::LightGray().unwrap())]
+    highlight: Color,
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+    #[builder(default = Colors::Gray().unwrap())]
+    disabled_text_color: Color,
+    /// Registry of the commands, the one from the `context` if not set
+    #[builder(default, setter(strip_option))]
+    commands: Option<Arc<CommandRegistry>>,
+    #[builder(default)]
+    context: Context,
+}
+
+impl MenuBarParams {
+    pub fn add_item(mut self, item: MenuItem) -> Self {
+        self.items.push(item);
+        self
+    }
+}
+
+impl TryFrom<MenuBarParams> for MenuBar {
+    type Error = crate::Error;
+
+    fn try_from(mut value: MenuBarParams) -> crate::Result<Self> {
+        if value.commands.is_none() {
+            value.commands = value.context.get::<CommandRegistry>();
+        }
+        let container = value.compositor.CreateContainerVisual()?;
+        let shapes = value.compositor.CreateShapeVisual()?;
+        container.Children()?.InsertAtBottom(&shapes)?;
+        let surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(value.compositor.clone())
+            .build()
+            .try_into()?;
+        attach(&container, &*surface)?;
+        let style = MenuStyle {
+            width: value.menu_width,
+            background: value.background,
+            highlight: value.highlight,
+            text_color: value.text_color,
+            disabled_text_color: value.disabled_text_color,
+        };
+        let menu_events = Arc::new(EventStreams::new());
+        let format = text_format()?;
+        let labels = value
+            .items
+            .iter()
+            .map(|v| Label::parse(&v.text))
+            .collect::<Vec<_>>();
+        let widths = labels
+            .iter()
+            .map(|v| Ok(v.width(&format)? + ITEM_PADDING * 2.))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let menus = value
+            .items
+            .iter()
+            .map(|item| {
+                if item.has_submenu() {
+                    let menu = Menu::create(
+                        value.compositor.clone(),
+                        item.items.clone(),
+                        style.clone(),
+                        value.commands.clone(),
+                        menu_events.clone(),
+                    )?;
+                    Ok(Some(Arc::new(menu)))
+                } else {
+                    Ok(None)
+                }
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(MenuBar {
+            container,
+            shapes,
+            surface,
+            items: value.items,
+            labels,
+            widths,
+            menus,
+            style,
+            commands: value.commands,
+            core: RwLock::new(Core {
+                size: Vector2::default(),
+                host: None,
+                mouse_pos: None,
+                highlighted: None,
+                open: None,
+            }),
+            menu_events,
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<MenuBarParams> for Arc<MenuBar> {
+    type Error = crate::Error;
+
+    fn try_from(value: MenuBarParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl MenuBar {
+    /// Sets the overlay host showing the drop-down menus
+    pub async fn set_host(&self, host: Arc<OverlayHost>) {
+        self.core.write().await.host = Some(host);
+    }
+
+    /// Closes the open drop-down menu
+    pub async fn close(&self) -> crate::Result<()> {
+        let open = self.core.write().await.open.take();
+        if let Some(menu) = open.and_then(|index| self.menus[index].as_ref()) {
+            menu.close().await?;
+        }
+        Ok(())
+    }
+
+    fn item_rects(&self, height: f32) -> Vec<Rect> {
+        let mut x = 0.;
+        self.widths
+            .iter()
+            .map(|width| {
+                let rect = Rect::new(Point::new(x, 0.), Size::new(*width, height));
+                x += width;
+                rect
+            })
+            .collect()
+    }
+
+    fn item_at(&self, pos: Vector2, height: f32) -> Option<usize> {
+        self.item_rects(height)
+            .iter()
+            .position(|v| v.contains(pos))
+            .filter(|v| self.items[*v].enabled)
+    }
+
+    // Index of the item with the drop-down menu currently shown
+    async fn open_index(&self) -> Option<usize> {
+        let (open, host) = {
+            let core = self.core.read().await;
+            (core.open, core.host.clone())
+        };
+        let (index, host) = (open?, host?);
+        let menu = self.menus[index].as_ref()?;
+        if host.is_open(menu.id()).await {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    async fn open_menu(&self, index: usize, select_first: bool) -> crate::Result<()> {
+        self.close().await?;
+        let (host, height) = {
+            let core = self.core.read().await;
+            (core.host.clone(), core.size.Y)
+        };
+        let menu = match &self.menus[index] {
+            Some(menu) => menu,
+            None => return self.activate(index).await,
+        };
+        let host = match host {
+            Some(host) => host,
+            None => return Ok(()),
+        };
+        let origin = host.bounds_of(self)?.origin;
+        let anchor = self.item_rects(height)[index].translate(origin);
+        menu.show(host, anchor, PopupSide::Bottom).await?;
+        if select_first {
+            menu.select_first().await?;
+        }
+        self.core.write().await.open = Some(index);
+        self.set_highlighted(Some(index)).await
+    }
+
+    // Activates the top-level item without submenu
+    async fn activate(&self, index: usize) -> crate::Result<()> {
+        let id = self.items[index].id;
+        if let Some(commands) = &self.commands {
+            if commands.command(id).await.is_some() && !commands.invoke(id).await {
+                return Ok(());
+            }
+        }
+        self.menu_events
+            .send_event(MenuEvent::Activated(id), None)
+            .await;
+        Ok(())
+    }
+
+    async fn set_highlighted(&self, highlighted: Option<usize>) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        if core.highlighted != highlighted {
+            core.highlighted = highlighted;
+            self.redraw_shapes(core.size, highlighted)?;
+        }
+        Ok(())
+    }
+
+    fn redraw_shapes(&self, size: Vector2, highlighted: Option<usize>) -> crate::Result<()> {
+        let compositor = self.shapes.Compositor()?;
+        let shapes = self.shapes.Shapes()?;
+        shapes.Clear()?;
+        let append_rect = |rect: Rect, color: Color| -> crate::Result<()> {
+            let geometry = compositor.CreateRoundedRectangleGeometry()?;
+            geometry.SetOffset(rect.origin.into())?;
+            geometry.SetSize(rect.size.into())?;
+            geometry.SetCornerRadius(Vector2 { X: 4., Y: 4. })?;
+            let shape = compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+            shape.SetFillBrush(&compositor.CreateColorBrushWithColor(color)?)?;
+            shapes.Append(&shape)?;
+            Ok(())
+        };
+        append_rect(Rect::from_size(size), self.style.background)?;
+        if let Some(rect) =
+            highlighted.and_then(|index| self.item_rects(size.Y).get(index).cloned())
+        {
+            append_rect(rect.inflate(0., -2.), self.style.highlight)?;
+        }
+        Ok(())
+    }
+
+    fn redraw_text(&self, height: f32) -> crate::Result<()> {
+        let style = &self.style;
+        self.surface.draw(|context, _| {
+            let text_format = text_format()?;
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let text_brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(style.text_color), Some(&brush_properties))
+            }?;
+            let disabled_brush = unsafe {
+                context.CreateSolidColorBrush(
+                    &d2d_color(style.disabled_text_color),
+                    Some(&brush_properties),
+                )
+            }?;
+            unsafe { context.Clear(Some(&d2d_color(Colors::Transparent()?))) };
+            for ((item, label), rect) in self
+                .items
+                .iter()
+                .zip(self.labels.iter())
+                .zip(self.item_rects(height))
+            {
+                let brush = if item.enabled {
+                    &text_brush
+                } else {
+                    &disabled_brush
+                };
+                let layout_rect = D2D_RECT_F {
+                    left: rect.left() + ITEM_PADDING,
+                    top: rect.top(),
+                    right: rect.right() - ITEM_PADDING,
+                    bottom: rect.bottom(),
+                };
+                label.draw(context, &text_format, layout_rect, brush)?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn cursor_moved(&self, pos: Vector2) -> crate::Result<()> {
+        let height = {
+            let mut core = self.core.write().await;
+            core.mouse_pos = Some(pos);
+            core.size.Y
+        };
+        let index = self.item_at(pos, height);
+        let open = self.open_index().await;
+        match (index, open) {
+            (Some(index), Some(open)) if index != open && self.menus[index].is_some() => {
+                self.open_menu(index, false).await
+            }
+            _ => self.set_highlighted(index.or(open)).await,
+        }
+    }
+
+    async fn mouse_pressed(&self) -> crate::Result<()> {
+        let (pos, height) = {
+            let core = self.core.read().await;
+            (core.mouse_pos, core.size.Y)
+        };
+        if let Some(index) = pos.and_then(|pos| self.item_at(pos, height)) {
+            if self.open_index().await == Some(index) {
+                self.close().await?;
+            } else {
+                self.open_menu(index, false).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn key_pressed(
+        &self,
+        key: VirtualKeyCode,
+        modifiers: ModifiersState,
+    ) -> crate::Result<()> {
+        if modifiers == ModifiersState::ALT {
+            let index = self
+                .labels
+                .iter()
+                .zip(self.items.iter())
+                .position(|(label, item)| item.enabled && label.matches(key));
+            if let Some(index) = index {
+                return self.open_menu(index, true).await;
+            }
+        }
+        if let Some(commands) = &self.commands {
+            commands.handle_key(key, modifiers).await;
+        }
+        Ok(())
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => {
+                self.container.SetSize(*size)?;
+                self.shapes.SetSize(*size)?;
+                self.surface.on_event_ref(&event, source.clone()).await?;
+                let highlighted = {
+                    let mut core = self.core.write().await;
+                    core.size = *size;
+                    core.highlighted
+                };
+                self.redraw_text(size.Y)?;
+                self.redraw_shapes(*size, highlighted)?;
+            }
+            PanelEvent::CursorMoved(pos) => self.cursor_moved(*pos).await?,
+            PanelEvent::MouseInput {
+                in_slot: true,
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => self.mouse_pressed().await?,
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(key),
+                modifiers,
+                ..
+            } => self.key_pressed(*key, *modifiers).await?,
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<MenuEvent> for MenuBar {
+    fn event_stream(&self) -> EventStream<MenuEvent> {
+        self.menu_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for MenuBar {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for MenuBar {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for MenuBar {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
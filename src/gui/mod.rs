@@ -1,33 +1,235 @@
+#[cfg(feature = "accessibility")]
+mod accessibility;
+mod adaptive;
+mod align_panel;
+mod animation_stepper;
+mod aspect_ratio_panel;
+#[cfg(feature = "text")]
+mod avatar;
+mod backdrop;
 mod background;
+#[cfg(feature = "core-panels")]
 mod button;
+#[cfg(feature = "capture")]
+mod cache_visual;
+#[cfg(feature = "core-panels")]
+mod check_box;
+#[cfg(feature = "text")]
+mod chip;
+mod clock;
+#[cfg(feature = "text")]
+mod combo_box;
+mod command;
+#[cfg(feature = "core-panels")]
+mod content_button_skin;
+mod context;
+mod cursor;
+mod debug_frames;
+#[cfg(feature = "dialogs")]
+mod dialog;
+mod dispatch;
+mod drag_drop;
+mod effect_graph;
+mod effects;
+mod event_filter;
+mod factory;
+mod fade;
+#[cfg(feature = "text")]
+mod find_bar;
+mod gesture;
+mod hit_test;
+#[cfg(feature = "text")]
+mod html;
+#[cfg(feature = "text")]
+mod hyperlink;
+mod idle;
 mod layer_stack;
+#[cfg(feature = "text")]
+mod menu;
+#[cfg(feature = "text")]
+mod menu_bar;
+mod navigator;
+mod overlay_host;
 mod panel;
+mod path_builder;
+#[cfg(feature = "core-panels")]
+mod progress;
+mod redraw_scheduler;
+#[cfg(feature = "core-panels")]
+mod radio_button;
+#[cfg(feature = "core-panels")]
+mod repeat_button;
 mod ribbon;
+#[cfg(feature = "text")]
+mod rich_text;
+mod scrim;
+mod sequence;
+mod shapes;
+#[cfg(feature = "text")]
+mod shortcut_sheet;
+#[cfg(feature = "text")]
+mod simple_button_skin;
+#[cfg(feature = "capture")]
+mod snapshot;
+mod storyboard;
 mod surface;
+mod suspendable;
+#[cfg(feature = "text")]
+mod tab_control;
+#[cfg(feature = "text")]
 mod text;
+mod text_document;
+#[cfg(feature = "text")]
+mod text_editor;
+#[cfg(feature = "text")]
+mod text_lines;
+mod theme;
+mod timer;
+#[cfg(feature = "core-panels")]
+mod toggle_button;
+#[cfg(feature = "core-panels")]
+mod toggle_switch;
+#[cfg(feature = "core-panels")]
+mod virtual_surface;
+mod window_services;
+mod zoom_panel;
 
-pub use background::{Background, BackgroundParams};
-pub use button::{
-    Button, ButtonEvent, ButtonParams, ButtonSkin, SimpleButtonSkin, SimpleButtonSkinParams,
+#[cfg(feature = "accessibility")]
+pub use accessibility::{
+    accessibility_audit, accessibility_tree, accessible, automation_id, set_accessible,
+    set_automation_id, AccessibilityIssue, AccessibilityIssueKind, Accessible, AccessibleNode,
+    AccessibleRole, TextAlternative, MIN_TEXT_CONTRAST,
 };
-pub use layer_stack::{LayerStack, LayerStackParams};
-pub use panel::{attach, detach, spawn_window_event_receiver, Panel, PanelEvent};
+pub use adaptive::{Adaptive, AdaptiveEvent, AdaptiveParams, Breakpoints};
+pub use align_panel::{AlignPanel, AlignPanelParams};
+pub use animation_stepper::{
+    animation_stepper, set_animation_stepper, start_animation, AnimationStepper,
+};
+pub use aspect_ratio_panel::{AspectRatioPanel, AspectRatioPanelParams};
+#[cfg(feature = "text")]
+pub use avatar::{Avatar, AvatarParams, AvatarSize, Presence};
+pub use backdrop::{Backdrop, BackdropParams, BackdropSource};
+pub use background::{
+    Background, BackgroundImage, BackgroundParams, CornerRadius, DashStyle, ImageStretch, Radius,
+    Stroke,
+};
+#[cfg(feature = "core-panels")]
+pub use button::{Button, ButtonEvent, ButtonParams, ButtonSkin};
+#[cfg(feature = "capture")]
+pub use cache_visual::{CacheVisual, CacheVisualParams};
+#[cfg(feature = "core-panels")]
+pub use check_box::{
+    CheckBox, CheckBoxEvent, CheckBoxParams, CheckBoxSkin, CheckState, SimpleCheckBoxSkin,
+    SimpleCheckBoxSkinParams,
+};
+#[cfg(feature = "text")]
+pub use chip::{Chip, ChipEvent, ChipGroup, ChipGroupEvent, ChipGroupParams, ChipParams};
+#[cfg(feature = "text")]
+pub use combo_box::{ComboBox, ComboBoxEvent, ComboBoxParams};
+pub use clock::{clock, set_clock, Clock, VirtualClock};
+pub use command::{Accelerator, Command, CommandEvent, CommandRegistry};
+#[cfg(feature = "core-panels")]
+pub use content_button_skin::{ContentButtonSkin, ContentButtonSkinParams};
+pub use context::Context;
+pub use cursor::CursorSelector;
+pub use debug_frames::DebugFrames;
+#[cfg(feature = "dialogs")]
+pub use dialog::{show_dialog, Dialog, DialogEvent, DialogHandle, DialogParams, DialogResult};
+pub use drag_drop::{DragDrop, DragDropEvent, DragPayload, DropTarget};
+pub use effects::{Effect, Effects, EffectsParams};
+pub use event_filter::{EventFilter, EventFilterId, EventFilters};
+pub use factory::PanelFactory;
+pub use fade::{fade_in, fade_out, opacity, set_opacity};
+#[cfg(feature = "text")]
+pub use find_bar::{FindBar, FindBarEvent, FindBarParams, SearchQuery, Searchable};
+pub use gesture::GestureEvent;
+pub use hit_test::{hit_test, AlphaMask, HitShape, HitTestMode, HitTestPanel, HitTestPanelParams};
+#[cfg(feature = "text")]
+pub use html::{parse_html, HtmlBlock, HtmlSpan, HtmlView, HtmlViewEvent, HtmlViewParams};
+#[cfg(feature = "text")]
+pub use hyperlink::{Hyperlink, HyperlinkEvent, HyperlinkParams};
+pub use idle::{IdleEvent, IdleMonitor};
+pub use layer_stack::{LayerStack, LayerStackEvent, LayerStackParams};
+#[cfg(feature = "text")]
+pub use menu::{Menu, MenuEvent, MenuItem, MenuParams};
+#[cfg(feature = "text")]
+pub use menu_bar::{MenuBar, MenuBarParams};
+pub use navigator::{Navigator, NavigatorEvent, NavigatorParams, SharedElement};
+pub use overlay_host::{OverlayEvent, OverlayHost, OverlayHostParams, Placement, PopupSide};
+pub use panel::{
+    attach, detach, is_visible, set_pixel_snapping, set_visible, spawn_window_event_receiver,
+    Panel, PanelEvent, PointerCapture,
+};
+pub use path_builder::PathBuilder;
+#[cfg(feature = "core-panels")]
+pub use progress::{ProgressBar, ProgressBarParams, ProgressRing, ProgressRingParams};
+pub use redraw_scheduler::{redraw_scheduler, set_redraw_scheduler, RedrawScheduler};
+#[cfg(feature = "core-panels")]
+pub use radio_button::{
+    RadioButton, RadioButtonEvent, RadioButtonParams, RadioButtonSkin, RadioGroup,
+    RadioGroupEvent, SimpleRadioButtonSkin, SimpleRadioButtonSkinParams,
+};
+#[cfg(feature = "core-panels")]
+pub use repeat_button::{RepeatButton, RepeatButtonEvent, RepeatButtonParams};
 pub use ribbon::{CellLimit, Ribbon, RibbonOrientation, RibbonParams};
-pub use surface::{Surface, SurfaceParams};
-pub use text::{Text, TextParams};
+#[cfg(feature = "text")]
+pub use rich_text::{RichText, RichTextEvent, RichTextParams, TextRun};
+pub use scrim::{Scrim, ScrimEvent, ScrimParams};
+pub use sequence::{EventSeqId, EventSequencer, WindowEventSender};
+pub use shapes::{Shape, ShapeBrush, Shapes, ShapesParams};
+#[cfg(feature = "text")]
+pub use shortcut_sheet::{ShortcutSheet, ShortcutSheetParams};
+#[cfg(feature = "text")]
+pub use simple_button_skin::{SimpleButtonSkin, SimpleButtonSkinParams};
+#[cfg(feature = "capture")]
+pub use snapshot::{compare, Comparison, Snapshot, Tolerance, UPDATE_SNAPSHOTS_VAR};
+pub use storyboard::{Repeat, Storyboard, StoryboardEvent, StoryboardParams};
+pub use surface::{Surface, SurfaceEvent, SurfaceParams};
+pub use suspendable::{Suspendable, SuspendableParams};
+#[cfg(feature = "text")]
+pub use tab_control::{TabControl, TabControlEvent, TabControlParams};
+#[cfg(feature = "text")]
+pub use text::{
+    default_text_rendering, measure_text, set_default_text_rendering, Font, Text, TextAntialias,
+    TextEvent, TextLayout, TextOverflow, TextParams, TextRendering,
+};
+pub use text_document::{TextDocument, TextDocumentEvent, TextEdit};
+#[cfg(feature = "text")]
+pub use text_editor::{TextEditor, TextEditorParams};
+#[cfg(feature = "text")]
+pub use text_lines::{layout_rows, line_operation, wrapped_layout, LineOperation, Replacement};
+pub use theme::{
+    contrast_foreground, readable_foreground, Metrics, Palette, Theme, ThemeEvent,
+};
+pub use timer::{
+    accelerating_delays, capped_frame_interval, frame_interval, refresh_rate, set_refresh_rate,
+    Timer, TimerEvent, DEFAULT_REFRESH_RATE,
+};
+#[cfg(feature = "core-panels")]
+pub use toggle_button::{
+    SimpleToggleButtonSkin, SimpleToggleButtonSkinParams, ToggleButton, ToggleButtonEvent,
+    ToggleButtonParams, ToggleButtonSkin, ToggleGroup, ToggleGroupEvent,
+};
+#[cfg(feature = "core-panels")]
+pub use toggle_switch::{
+    SimpleToggleSwitchSkin, SimpleToggleSwitchSkinParams, ToggleSwitch, ToggleSwitchEvent,
+    ToggleSwitchParams, ToggleSwitchSkin,
+};
+#[cfg(feature = "core-panels")]
+pub use virtual_surface::{VirtualSurface, VirtualSurfaceEvent, VirtualSurfaceParams};
+pub use window_services::WindowServices;
+pub use zoom_panel::{ZoomPanel, ZoomPanelEvent, ZoomPanelParams};
 
-use windows::Foundation::Numerics::Vector2;
-use winit::dpi::{PhysicalPosition, PhysicalSize};
+use std::time::Duration;
 
-fn is_translated_point_in_box(point: Vector2, size: Vector2) -> bool {
-    is_point_in_box(point, Vector2 { X: 0., Y: 0. }, size)
-}
+use windows::Foundation::{Numerics::Vector2, TimeSpan};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 
-fn is_point_in_box(point: Vector2, offset: Vector2, size: Vector2) -> bool {
-    point.X >= offset.X
-        && point.X <= offset.X + size.X
-        && point.Y >= offset.X
-        && point.Y <= offset.Y + size.Y
+// Composition animations take durations in 100ns units
+fn time_span(duration: Duration) -> TimeSpan {
+    TimeSpan {
+        Duration: (duration.as_nanos() / 100) as i64,
+    }
 }
 
 trait IntoVector2 {
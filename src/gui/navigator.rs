@@ -0,0 +1,354 @@
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::{Numerics::Vector2, TypedEventHandler},
+    UI::Composition::{
+        CompositionBatchTypes, CompositionStretch, Compositor, ContainerVisual, Visual,
+    },
+};
+
+use crate::geometry::Point;
+
+use super::{
+    animation_stepper::start_animation, attach, dispatch::DispatchQueue, panel::window_offset,
+    time_span, Panel, PanelEvent,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum NavigatorEvent {
+    /// The page with given id became current
+    Navigated(usize),
+}
+
+///
+/// Pair of panels representing the same element on the old and the new page. During the
+/// transition the snapshot of `from` moves and stretches from its place on the old page to
+/// the place of `to` on the new page.
+///
+#[derive(Clone)]
+pub struct SharedElement {
+    from: Arc<dyn Panel>,
+    to: Arc<dyn Panel>,
+}
+
+impl SharedElement {
+    pub fn new(from: Arc<dyn Panel>, to: Arc<dyn Panel>) -> Self {
+        Self { from, to }
+    }
+}
+
+// Visuals to remove when the transition ends
+#[derive(Default)]
+struct Cleanup {
+    // Distinguishes the transition from the following ones
+    generation: usize,
+    visuals: Vec<Visual>,
+}
+
+impl Cleanup {
+    fn run(self, container: &ContainerVisual) -> crate::Result<()> {
+        for visual in self.visuals {
+            container.Children()?.Remove(&visual)?;
+        }
+        Ok(())
+    }
+}
+
+struct Core {
+    // Current page is the last one
+    pages: Vec<Arc<dyn Panel>>,
+}
+
+///
+/// Container showing one page at a time with the history of visited pages. Pages cross-fade
+/// on navigation, the shared elements fly from the old page to the new one on the overlay
+/// above both pages.
+///
+/// The position of the shared element is calculated from the offsets of its visual and its
+/// parents, other transforms are not taken into account.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Navigator {
+    compositor: Compositor,
+    container: ContainerVisual,
+    transition_duration: Duration,
+    core: RwLock<Core>,
+    pending: Arc<Mutex<Option<Cleanup>>>,
+    generation: AtomicUsize,
+    navigator_events: EventStreams<NavigatorEvent>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct NavigatorParams {
+    compositor: Compositor,
+    #[builder(default, setter(strip_option))]
+    page: Option<Arc<dyn Panel>>,
+    /// Zero duration disables the animations
+    #[builder(default = Duration::from_millis(300))]
+    transition_duration: Duration,
+}
+
+impl TryFrom<NavigatorParams> for Navigator {
+    type Error = crate::Error;
+
+    fn try_from(value: NavigatorParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let mut pages = Vec::new();
+        if let Some(page) = value.page {
+            attach(&container, &*page)?;
+            pages.push(page);
+        }
+        Ok(Navigator {
+            compositor: value.compositor,
+            container,
+            transition_duration: value.transition_duration,
+            core: RwLock::new(Core { pages }),
+            pending: Arc::new(Mutex::new(None)),
+            generation: AtomicUsize::new(0),
+            navigator_events: EventStreams::new(),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<NavigatorParams> for Arc<Navigator> {
+    type Error = crate::Error;
+
+    fn try_from(value: NavigatorParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Navigator {
+    pub async fn current(&self) -> Option<Arc<dyn Panel>> {
+        self.core.read().await.pages.last().cloned()
+    }
+
+    pub async fn can_go_back(&self) -> bool {
+        self.core.read().await.pages.len() > 1
+    }
+
+    /// Shows the page keeping the current one in the history
+    pub async fn navigate(
+        &self,
+        page: Arc<dyn Panel>,
+        shared: Vec<SharedElement>,
+    ) -> crate::Result<()> {
+        let old = {
+            let mut core = self.core.write().await;
+            let old = core.pages.last().cloned();
+            core.pages.push(page.clone());
+            old
+        };
+        self.transition(old, page, shared).await
+    }
+
+    /// Returns to the previous page, returns false if there is no one
+    pub async fn go_back(&self, shared: Vec<SharedElement>) -> crate::Result<bool> {
+        let (old, page) = {
+            let mut core = self.core.write().await;
+            if core.pages.len() < 2 {
+                return Ok(false);
+            }
+            let old = core.pages.pop();
+            (old, core.pages.last().unwrap().clone())
+        };
+        self.transition(old, page, shared).await?;
+        Ok(true)
+    }
+
+    async fn transition(
+        &self,
+        old: Option<Arc<dyn Panel>>,
+        page: Arc<dyn Panel>,
+        shared: Vec<SharedElement>,
+    ) -> crate::Result<()> {
+        // Finish the previous transition immediately
+        if let Some(cleanup) = self.pending.lock().unwrap().take() {
+            cleanup.run(&self.container)?;
+        }
+        // The new page is laid out before the transition to know where the shared elements go
+        let size = self.container.Size()?;
+        let frame = page.outer_frame();
+        frame.SetSize(size)?;
+        attach(&self.container, &*page)?;
+        page.on_event_owned(PanelEvent::Resized(size), None).await?;
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst);
+        let mut cleanup = Cleanup {
+            generation,
+            ..Default::default()
+        };
+        if let Some(old) = &old {
+            cleanup.visuals.push(old.outer_frame());
+        }
+        if self.transition_duration.is_zero() {
+            cleanup.run(&self.container)?;
+        } else {
+            let batch = self
+                .compositor
+                .CreateScopedBatch(CompositionBatchTypes::Animation)?;
+            if let Some(old) = &old {
+                self.fade(&old.outer_frame(), 1., 0.)?;
+            }
+            self.fade(&frame, 0., 1.)?;
+            for element in &shared {
+                cleanup.visuals.push(self.fly(element)?);
+            }
+            batch.End()?;
+            *self.pending.lock().unwrap() = Some(cleanup);
+            let pending = self.pending.clone();
+            let container = self.container.clone();
+            batch.Completed(&TypedEventHandler::new(move |_, _| {
+                let cleanup = {
+                    let mut pending = pending.lock().unwrap();
+                    match &*pending {
+                        Some(v) if v.generation == generation => pending.take(),
+                        _ => None,
+                    }
+                };
+                if let Some(cleanup) = cleanup {
+                    cleanup.run(&container)?;
+                }
+                Ok(())
+            }))?;
+        }
+        self.navigator_events
+            .send_event(NavigatorEvent::Navigated(page.id()), None)
+            .await;
+        Ok(())
+    }
+
+    // Position of the visual in the navigator's coordinates
+    fn local_offset(&self, visual: &Visual) -> crate::Result<Vector2> {
+        let offset = window_offset(visual)?;
+        let origin = window_offset(&self.container.clone().into())?;
+        Ok(Vector2 {
+            X: offset.X - origin.X,
+            Y: offset.Y - origin.Y,
+        })
+    }
+
+    // Starts the flight of the shared element's snapshot, returns the sprite to remove later
+    fn fly(&self, element: &SharedElement) -> crate::Result<Visual> {
+        let from = element.from.outer_frame();
+        let to = element.to.outer_frame();
+        let (from_offset, from_size) = (self.local_offset(&from)?, from.Size()?);
+        let (to_offset, to_size) = (self.local_offset(&to)?, to.Size()?);
+
+        // The snapshot shows the source visual without its parents, so it stays visible
+        // while the old page fades out
+        let surface = self.compositor.CreateVisualSurface()?;
+        surface.SetSourceVisual(&from)?;
+        surface.SetSourceSize(from_size)?;
+        let brush = self.compositor.CreateSurfaceBrushWithSurface(&surface)?;
+        brush.SetStretch(CompositionStretch::Fill)?;
+        let sprite = self.compositor.CreateSpriteVisual()?;
+        sprite.SetBrush(&brush)?;
+        sprite.SetOffset(Point::from(to_offset).into())?;
+        sprite.SetSize(to_size)?;
+        self.container.Children()?.InsertAtTop(&sprite)?;
+
+        let duration = time_span(self.transition_duration);
+        let offset = self.compositor.CreateVector3KeyFrameAnimation()?;
+        offset.InsertKeyFrame(0., Point::from(from_offset).into())?;
+        offset.InsertKeyFrame(1., Point::from(to_offset).into())?;
+        offset.SetDuration(duration)?;
+        start_animation(&sprite, "Offset", &offset)?;
+        let size = self.compositor.CreateVector2KeyFrameAnimation()?;
+        size.InsertKeyFrame(0., from_size)?;
+        size.InsertKeyFrame(1., to_size)?;
+        size.SetDuration(duration)?;
+        start_animation(&sprite, "Size", &size)?;
+        // The snapshot dissolves into the real element which fades in with the new page
+        let sprite: Visual = sprite.into();
+        self.fade(&sprite, 1., 0.)?;
+        Ok(sprite)
+    }
+
+    fn fade(&self, visual: &Visual, from: f32, to: f32) -> crate::Result<()> {
+        visual.SetOpacity(to)?;
+        let animation = self.compositor.CreateScalarKeyFrameAnimation()?;
+        animation.InsertKeyFrame(0., from)?;
+        animation.InsertKeyFrame(1., to)?;
+        animation.SetDuration(time_span(self.transition_duration))?;
+        start_animation(visual, "Opacity", &animation)?;
+        Ok(())
+    }
+
+    async fn translate_event(
+        &self,
+        event: &PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event {
+            self.container.SetSize(*size)?;
+            if let Some(page) = self.current().await {
+                page.outer_frame().SetSize(*size)?;
+            }
+        }
+        if let Some(page) = self.current().await {
+            page.on_event_ref(event, source).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Panel for Navigator {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+impl EventSource<PanelEvent> for Navigator {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl EventSource<NavigatorEvent> for Navigator {
+    fn event_stream(&self) -> EventStream<NavigatorEvent> {
+        self.navigator_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Navigator {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| async move {
+                self.translate_event(&event, source.clone()).await?;
+                self.panel_events.send_event(event, source).await;
+                Ok(())
+            })
+            .await
+    }
+}
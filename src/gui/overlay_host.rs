@@ -0,0 +1,674 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::Composition::{Compositor, ContainerVisual, Visual},
+};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+use crate::{
+    geometry::{Point, Rect, Size},
+    window::native::{PopupWindowHandle, PopupWindowHost},
+};
+
+use super::{attach, dispatch::DispatchQueue, hit_test, EventSeqId, Panel, PanelEvent};
+
+/// Side of the anchor where the popup is preferably placed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PopupSide {
+    Bottom,
+    Top,
+    Right,
+    Left,
+}
+
+impl PopupSide {
+    fn opposite(&self) -> PopupSide {
+        match self {
+            PopupSide::Bottom => PopupSide::Top,
+            PopupSide::Top => PopupSide::Bottom,
+            PopupSide::Right => PopupSide::Left,
+            PopupSide::Left => PopupSide::Right,
+        }
+    }
+}
+
+///
+/// Position of the popup relative to the anchor rectangle. The popup is placed on the preferred
+/// side of the anchor, aligned to its left (or top) edge. If the popup doesn't fit into
+/// the bounds, it's flipped to the opposite side and aligned to the opposite edge and then
+/// shifted inside the bounds if it still doesn't fit.
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Placement {
+    pub anchor: Rect,
+    pub side: PopupSide,
+    pub size: Size,
+}
+
+fn fits(bounds: &Rect, rect: &Rect) -> bool {
+    rect.left() >= bounds.left()
+        && rect.top() >= bounds.top()
+        && rect.right() <= bounds.right()
+        && rect.bottom() <= bounds.bottom()
+}
+
+impl Placement {
+    pub fn anchored(anchor: Rect, side: PopupSide, size: Size) -> Self {
+        Self { anchor, side, size }
+    }
+    /// Placement at the point, e.g. for context menu at the mouse cursor
+    pub fn at(point: Point, size: Size) -> Self {
+        Self {
+            anchor: Rect::new(point, Size::default()),
+            side: PopupSide::Bottom,
+            size,
+        }
+    }
+    fn on_side(&self, side: PopupSide) -> Rect {
+        let a = &self.anchor;
+        let s = self.size;
+        let origin = match side {
+            PopupSide::Bottom => Point::new(a.left(), a.bottom()),
+            PopupSide::Top => Point::new(a.left(), a.top() - s.height),
+            PopupSide::Right => Point::new(a.right(), a.top()),
+            PopupSide::Left => Point::new(a.left() - s.width, a.top()),
+        };
+        Rect::new(origin, s)
+    }
+    /// Rectangle of the popup inside the `bounds`
+    pub fn place(&self, bounds: Rect) -> Rect {
+        let mut rect = self.on_side(self.side);
+        if !fits(&bounds, &rect) {
+            let flipped = self.on_side(self.side.opposite());
+            if fits(&bounds, &flipped) {
+                rect = flipped;
+            }
+        }
+        match self.side {
+            PopupSide::Bottom | PopupSide::Top if rect.right() > bounds.right() => {
+                rect.origin.x = self.anchor.right() - rect.size.width;
+            }
+            PopupSide::Right | PopupSide::Left if rect.bottom() > bounds.bottom() => {
+                rect.origin.y = self.anchor.bottom() - rect.size.height;
+            }
+            _ => (),
+        }
+        rect.origin.x = rect
+            .origin
+            .x
+            .min(bounds.right() - rect.size.width)
+            .max(bounds.left());
+        rect.origin.y = rect
+            .origin
+            .y
+            .min(bounds.bottom() - rect.size.height)
+            .max(bounds.top());
+        rect
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum OverlayEvent {
+    Opened(usize),
+    /// Popup was closed, `dismissed` is true if it's closed by click outside or Escape key
+    Closed {
+        id: usize,
+        dismissed: bool,
+    },
+}
+
+#[derive(Clone)]
+struct Popup {
+    panel: Arc<dyn Panel>,
+    placement: Placement,
+    rect: Rect,
+    light_dismiss: bool,
+    // Modal popup covers the whole host and blocks input to everything below it
+    modal: bool,
+    // Separate window for the popup which doesn't fit into the host
+    window: Option<Arc<PopupWindowHandle>>,
+}
+
+impl Popup {
+    fn update_rect(&mut self, bounds: Rect) -> crate::Result<()> {
+        if self.window.is_some() {
+            return Ok(());
+        }
+        self.rect = if self.modal {
+            bounds
+        } else {
+            self.placement.place(bounds)
+        };
+        let visual = self.panel.outer_frame();
+        visual.SetOffset(self.rect.origin.into())?;
+        visual.SetSize(self.rect.size.into())?;
+        Ok(())
+    }
+}
+
+struct Core {
+    popups: Vec<Popup>,
+    size: Vector2,
+    mouse_pos: Option<Point>,
+    // Release of the mouse button which dismissed popups shouldn't reach the content
+    swallow_release: bool,
+    popup_windows: Option<PopupWindowHost>,
+}
+
+impl Core {
+    fn bounds(&self) -> Rect {
+        Rect::from_size(self.size)
+    }
+    // Topmost popup hit at the cursor according to its hit test mode
+    fn hit_test(&self) -> crate::Result<Option<usize>> {
+        let mouse_pos = match self.mouse_pos {
+            Some(pos) => pos,
+            None => return Ok(None),
+        };
+        for (index, popup) in self.popups.iter().enumerate().rev() {
+            if hit_test(&*popup.panel, popup.rect.to_local(mouse_pos))? {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+}
+
+// Index of the topmost modal popup: popups below it and the content don't receive input
+fn modal_index(popups: &[Popup]) -> Option<usize> {
+    popups.iter().rposition(|v| v.modal)
+}
+
+///
+/// Window-level layer for popups, menus, tooltips and dialogs, shown above the content panel.
+/// Popups are placed relative to anchor rectangles (see [`Placement`]). Light-dismiss popups
+/// are closed when the mouse is pressed outside all popups or Escape is pressed, such
+/// mouse press is not delivered to the content. If popup windows are enabled, popups which
+/// don't fit into the host are shown in separate windows extending beyond the host's bounds.
+///
+/// Modal popups (e.g. dialogs) cover the whole host. While modal popup is open, the input
+/// is delivered only to it and to the popups above it.
+///
+/// Popups take the clicks according to their [`HitTestMode`], so the click on the transparent
+/// part of the popup goes to the popups below or to the content and may dismiss the popup.
+///
+/// [`HitTestMode`]: super::HitTestMode
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct OverlayHost {
+    container: ContainerVisual,
+    content: Arc<dyn Panel>,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    overlay_events: EventStreams<OverlayEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct OverlayHostParams {
+    compositor: Compositor,
+    content: Arc<dyn Panel>,
+}
+
+impl TryFrom<OverlayHostParams> for OverlayHost {
+    type Error = crate::Error;
+
+    fn try_from(value: OverlayHostParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        attach(&container, &*value.content)?;
+        let core = RwLock::new(Core {
+            popups: Vec::new(),
+            size: Vector2::default(),
+            mouse_pos: None,
+            swallow_release: false,
+            popup_windows: None,
+        });
+        Ok(OverlayHost {
+            container,
+            content: value.content,
+            core,
+            panel_events: EventStreams::new(),
+            overlay_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<OverlayHostParams> for Arc<OverlayHost> {
+    type Error = crate::Error;
+
+    fn try_from(value: OverlayHostParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+// Offset of the visual relative to the root of its visual tree
+fn absolute_offset(visual: &Visual) -> crate::Result<Point> {
+    let offset = visual.Offset()?;
+    let mut result = Point::new(offset.X, offset.Y);
+    let mut parent = visual.Parent();
+    while let Ok(container) = parent {
+        let offset = container.Offset()?;
+        result = result + Point::new(offset.X, offset.Y);
+        parent = container.Parent();
+    }
+    Ok(result)
+}
+
+impl OverlayHost {
+    ///
+    /// Rectangle occupied by the panel in the host's coordinates, to be used as popup anchor.
+    /// The panel have to be inside the host's content.
+    ///
+    pub fn bounds_of<T: Panel + ?Sized>(&self, panel: &T) -> crate::Result<Rect> {
+        let visual = panel.outer_frame();
+        let origin = absolute_offset(&visual)? - absolute_offset(&self.container.clone().into())?;
+        Ok(Rect::new(origin, visual.Size()?.into()))
+    }
+
+    ///
+    /// Enables showing popups in separate windows when they don't fit into the host. The host
+    /// have to be the root panel of the window providing `popup_windows`.
+    ///
+    pub async fn set_popup_windows(&self, popup_windows: Option<PopupWindowHost>) {
+        self.core.write().await.popup_windows = popup_windows;
+    }
+
+    pub async fn show_popup(
+        &self,
+        panel: Arc<dyn Panel>,
+        placement: Placement,
+        light_dismiss: bool,
+    ) -> crate::Result<()> {
+        self.open_popup(panel, placement, light_dismiss, false)
+            .await
+    }
+
+    /// Shows the panel over the whole host blocking input to the content and other popups
+    pub async fn show_modal(&self, panel: Arc<dyn Panel>) -> crate::Result<()> {
+        let bounds = self.core.read().await.bounds();
+        let placement = Placement::anchored(bounds, PopupSide::Bottom, bounds.size);
+        self.open_popup(panel, placement, false, true).await
+    }
+
+    async fn open_popup(
+        &self,
+        panel: Arc<dyn Panel>,
+        placement: Placement,
+        light_dismiss: bool,
+        modal: bool,
+    ) -> crate::Result<()> {
+        let id = panel.id();
+        let (bounds, popup_windows) = {
+            let core = self.core.read().await;
+            if core.popups.iter().any(|v| v.panel.id() == id) {
+                return Ok(());
+            }
+            (core.bounds(), core.popup_windows.clone())
+        };
+        let mut popup = Popup {
+            panel: panel.clone(),
+            placement,
+            rect: Rect::default(),
+            light_dismiss,
+            modal,
+            window: None,
+        };
+        if let (Some(popup_windows), false) = (popup_windows, modal) {
+            let rect = placement.place(popup_windows.screen_bounds()?);
+            if !fits(&bounds, &rect) {
+                popup.rect = rect;
+                popup.window = Some(Arc::new(popup_windows.open(rect).await?));
+            }
+        }
+        match &popup.window {
+            Some(window) => {
+                attach(window.root_visual(), &*panel)?;
+                let visual = panel.outer_frame();
+                visual.SetOffset(Vector3::default())?;
+                visual.SetSize(popup.rect.size.into())?;
+            }
+            None => {
+                attach(&self.container, &*panel)?;
+                popup.update_rect(bounds)?;
+            }
+        }
+        let rect = popup.rect;
+        self.core.write().await.popups.push(popup);
+        panel
+            .on_event_owned(PanelEvent::Resized(rect.size.into()), None)
+            .await?;
+        self.overlay_events
+            .send_event(OverlayEvent::Opened(id), None)
+            .await;
+        Ok(())
+    }
+
+    /// Closes the popup, returns false if it's not opened
+    pub async fn close_popup(&self, id: usize) -> crate::Result<bool> {
+        let closed = self.remove_popups(|v| v.panel.id() == id, false).await?;
+        Ok(closed > 0)
+    }
+
+    pub async fn close_all(&self) -> crate::Result<()> {
+        self.remove_popups(|_| true, false).await?;
+        Ok(())
+    }
+
+    pub async fn is_open(&self, id: usize) -> bool {
+        self.core
+            .read()
+            .await
+            .popups
+            .iter()
+            .any(|v| v.panel.id() == id)
+    }
+
+    /// Rectangle of the open popup in the host's coordinates
+    pub async fn popup_rect(&self, id: usize) -> Option<Rect> {
+        self.core
+            .read()
+            .await
+            .popups
+            .iter()
+            .find(|v| v.panel.id() == id)
+            .map(|v| v.rect)
+    }
+
+    async fn remove_popups(
+        &self,
+        f: impl Fn(&Popup) -> bool,
+        dismissed: bool,
+    ) -> crate::Result<usize> {
+        let removed = {
+            let mut core = self.core.write().await;
+            let (removed, kept) = core.popups.drain(..).partition::<Vec<_>, _>(|v| f(v));
+            core.popups = kept;
+            removed
+        };
+        for popup in &removed {
+            let visual = popup.panel.outer_frame();
+            match &popup.window {
+                Some(window) => {
+                    window.root_visual().Children()?.Remove(&visual)?;
+                    window.close().await?;
+                }
+                None => self.container.Children()?.Remove(&visual)?,
+            }
+        }
+        for popup in &removed {
+            self.overlay_events
+                .send_event(
+                    OverlayEvent::Closed {
+                        id: popup.panel.id(),
+                        dismissed,
+                    },
+                    None,
+                )
+                .await;
+        }
+        Ok(removed.len())
+    }
+
+    async fn popups(&self) -> Vec<Popup> {
+        self.core.read().await.popups.clone()
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        let popups = {
+            let mut core = self.core.write().await;
+            core.size = size;
+            let bounds = core.bounds();
+            for popup in &mut core.popups {
+                popup.update_rect(bounds)?;
+            }
+            core.popups.clone()
+        };
+        self.content
+            .on_event_owned(PanelEvent::Resized(size), source.clone())
+            .await?;
+        for popup in popups {
+            popup
+                .panel
+                .on_event_owned(PanelEvent::Resized(popup.rect.size.into()), source.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn cursor_moved(&self, pos: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.core.write().await.mouse_pos = Some(pos.into());
+        let popups = self.popups().await;
+        let modal = modal_index(&popups);
+        if modal.is_none() {
+            self.content
+                .on_event_owned(PanelEvent::CursorMoved(pos), source.clone())
+                .await?;
+        }
+        for popup in &popups[modal.unwrap_or(0)..] {
+            let pos = popup.rect.to_local(pos);
+            popup
+                .panel
+                .on_event_owned(PanelEvent::CursorMoved(pos.into()), source.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn mouse_input(
+        &self,
+        in_slot: bool,
+        state: ElementState,
+        button: MouseButton,
+        click_count: u32,
+        seq: EventSeqId,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let (popups, hit, swallow_release) = {
+            let mut core = self.core.write().await;
+            let swallow_release = core.swallow_release;
+            core.swallow_release = false;
+            (core.popups.clone(), core.hit_test()?, swallow_release)
+        };
+        // Light-dismiss popups above the clicked one (or all if clicked outside) are closed
+        let outside = hit.map(|v| v + 1).unwrap_or(0);
+        if state == ElementState::Pressed && popups[outside..].iter().any(|v| v.light_dismiss) {
+            let dismissed = popups[outside..]
+                .iter()
+                .filter(|v| v.light_dismiss)
+                .map(|v| v.panel.id())
+                .collect::<Vec<_>>();
+            self.remove_popups(|v| dismissed.contains(&v.panel.id()), true)
+                .await?;
+            self.core.write().await.swallow_release = true;
+            return Ok(());
+        }
+        if swallow_release && state == ElementState::Released {
+            return Ok(());
+        }
+        let modal = modal_index(&popups);
+        for (index, popup) in popups.iter().enumerate().skip(modal.unwrap_or(0)) {
+            popup
+                .panel
+                .on_event_owned(
+                    PanelEvent::MouseInput {
+                        in_slot: hit == Some(index),
+                        state,
+                        button,
+                        click_count,
+                        seq,
+                    },
+                    source.clone(),
+                )
+                .await?;
+        }
+        if modal.is_some() {
+            return Ok(());
+        }
+        self.content
+            .on_event_owned(
+                PanelEvent::MouseInput {
+                    in_slot: in_slot && hit.is_none(),
+                    state,
+                    button,
+                    click_count,
+                    seq,
+                },
+                source,
+            )
+            .await
+    }
+
+    async fn keyboard_input(
+        &self,
+        event: &PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let popups = self.popups().await;
+        if let PanelEvent::KeyboardInput {
+            state: ElementState::Pressed,
+            key: Some(VirtualKeyCode::Escape),
+            ..
+        } = event
+        {
+            let modal = modal_index(&popups).unwrap_or(0);
+            if let Some(popup) = popups[modal..].iter().rev().find(|v| v.light_dismiss) {
+                let id = popup.panel.id();
+                self.remove_popups(|v| v.panel.id() == id, true).await?;
+                return Ok(());
+            }
+        }
+        // Keyboard input goes to the topmost popup only
+        match popups.last() {
+            Some(popup) => popup.panel.on_event_ref(event, source).await,
+            None => self.content.on_event_ref(event, source).await,
+        }
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            PanelEvent::CursorMoved(pos) => self.cursor_moved(*pos, source.clone()).await?,
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                click_count,
+                seq,
+            } => {
+                self.mouse_input(
+                    *in_slot,
+                    *state,
+                    *button,
+                    *click_count,
+                    *seq,
+                    source.clone(),
+                )
+                .await?
+            }
+            PanelEvent::KeyboardInput { .. } | PanelEvent::ReceivedCharacter(_) => {
+                self.keyboard_input(&event, source.clone()).await?
+            }
+            _ => {
+                self.content.on_event_ref(&event, source.clone()).await?;
+                for popup in self.popups().await {
+                    popup.panel.on_event_ref(&event, source.clone()).await?;
+                }
+            }
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<OverlayEvent> for OverlayHost {
+    fn event_stream(&self) -> EventStream<OverlayEvent> {
+        self.overlay_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for OverlayHost {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for OverlayHost {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for OverlayHost {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    fn bounds() -> Rect {
+        rect(0., 0., 200., 100.)
+    }
+
+    #[test]
+    fn placed_on_preferred_side() {
+        let anchor = rect(10., 10., 50., 20.);
+        let size = Size::new(40., 30.);
+        let placement = Placement::anchored(anchor, PopupSide::Bottom, size);
+        assert_eq!(placement.place(bounds()), rect(10., 30., 40., 30.));
+        let placement = Placement::anchored(anchor, PopupSide::Right, size);
+        assert_eq!(placement.place(bounds()), rect(60., 10., 40., 30.));
+    }
+
+    #[test]
+    fn flipped_when_not_fitting() {
+        let anchor = rect(10., 60., 50., 20.);
+        let placement = Placement::anchored(anchor, PopupSide::Bottom, Size::new(40., 30.));
+        assert_eq!(placement.place(bounds()), rect(10., 30., 40., 30.));
+    }
+
+    #[test]
+    fn aligned_to_opposite_edge_and_shifted_inside() {
+        let anchor = rect(150., 10., 40., 20.);
+        let placement = Placement::anchored(anchor, PopupSide::Bottom, Size::new(60., 30.));
+        assert_eq!(placement.place(bounds()), rect(130., 30., 60., 30.));
+        let placement = Placement::at(Point::new(190., 90.), Size::new(50., 50.));
+        assert_eq!(placement.place(bounds()), rect(140., 50., 50., 50.));
+    }
+}
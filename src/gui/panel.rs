@@ -1,8 +1,12 @@
-use std::sync::Arc;
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use async_event_streams::{EventSink, EventSource};
 use futures::{
-    channel::mpsc::{channel, Sender},
+    channel::mpsc::channel,
     task::{Spawn, SpawnExt},
     StreamExt,
 };
@@ -10,11 +14,18 @@ use windows::{
     Foundation::Numerics::Vector2,
     UI::Composition::{ContainerVisual, Visual},
 };
-use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::event::{
+    ElementState, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase, VirtualKeyCode,
+    WindowEvent,
+};
 
-use crate::error::handle_err;
+use crate::{error::handle_err, geometry::Rect, window::double_click_limits};
 
-use super::IntoVector2;
+use super::{
+    clock, gesture::GestureRecognizer, sequence::CloseOnDrop, window_services::WindowHandles,
+    Clock, CursorSelector, DebugFrames, DragDrop, EventFilters, EventSeqId, EventSequencer,
+    GestureEvent, HitTestMode, IdleMonitor, IntoVector2, Theme, WindowEventSender, WindowServices,
+};
 
 #[derive(Clone, Debug)]
 pub enum PanelEvent {
@@ -24,10 +35,96 @@ pub enum PanelEvent {
         in_slot: bool,
         state: ElementState,
         button: MouseButton,
+        /// Number of successive presses of the button within the system double-click time
+        /// and distance: 1 for a single click, 2 for a double click and so on. The release
+        /// carries the count of its press.
+        click_count: u32,
+        /// Sequence id of the posted event, see [`EventSequencer`]. 0 for the events made
+        /// by the panels themselves.
+        seq: EventSeqId,
+    },
+    KeyboardInput {
+        state: ElementState,
+        key: Option<VirtualKeyCode>,
+        modifiers: ModifiersState,
+        /// Sequence id of the posted event, see [`EventSequencer`]. 0 for the events made
+        /// by the panels themselves.
+        seq: EventSeqId,
     },
+    /// Wheel rotation at the cursor position, positive vertical delta scrolls up
+    MouseWheel {
+        delta: MouseScrollDelta,
+        modifiers: ModifiersState,
+        /// Sequence id of the posted event, see [`EventSequencer`]. 0 for the events made
+        /// by the panels themselves.
+        seq: EventSeqId,
+    },
+    /// Files dropped from other applications. The drop point is sent before as `CursorMoved`,
+    /// so the panel under the cursor accepts the files.
+    FileDrop(Vec<PathBuf>),
+    /// Touch of the screen. The touch point is sent before as `CursorMoved`, the mouse
+    /// events promoted by the system from the primary touch follow as usual.
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        /// Sequence id of the posted event, see [`EventSequencer`]. 0 for the events made
+        /// by the panels themselves.
+        seq: EventSeqId,
+    },
+    Gesture(GestureEvent),
+    /// Relative movement of the mouse from the raw input, not limited by the screen edges.
+    /// Sent only after `Window::set_raw_mouse_input`.
+    MouseMotion(Vector2),
+    /// Character typed by the user, after the keyboard layout and the dead keys are applied.
+    /// Control characters like backspace come as well, the text editors skip them and
+    /// handle the keys by `KeyboardInput`.
+    ReceivedCharacter(char),
+    /// The panel was shown or hidden by [`set_visible`]
+    VisibilityChanged(bool),
+    /// The theme has changed, sent to the whole tree by `Window::broadcast_theme`. The skins
+    /// following the theme take its colors and sizes.
+    ThemeChanged(Theme),
     Empty,
 }
 
+impl PanelEvent {
+    /// Events from the user which the containers don't route to the hidden panels
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            PanelEvent::CursorMoved(_)
+                | PanelEvent::MouseInput { .. }
+                | PanelEvent::KeyboardInput { .. }
+                | PanelEvent::ReceivedCharacter(_)
+                | PanelEvent::MouseWheel { .. }
+                | PanelEvent::FileDrop(_)
+                | PanelEvent::Touch { .. }
+                | PanelEvent::Gesture(_)
+                | PanelEvent::MouseMotion(_)
+        )
+    }
+    /// Sequence id of the input event posted by the window, `None` for the other events
+    pub fn seq(&self) -> Option<EventSeqId> {
+        match self {
+            PanelEvent::MouseInput { seq, .. }
+            | PanelEvent::KeyboardInput { seq, .. }
+            | PanelEvent::MouseWheel { seq, .. }
+            | PanelEvent::Touch { seq, .. } => Some(*seq),
+            _ => None,
+        }
+    }
+    // Stamps the input event with the sequence id assigned when it's posted
+    pub(crate) fn set_seq(&mut self, id: EventSeqId) {
+        match self {
+            PanelEvent::MouseInput { seq, .. }
+            | PanelEvent::KeyboardInput { seq, .. }
+            | PanelEvent::MouseWheel { seq, .. }
+            | PanelEvent::Touch { seq, .. } => *seq = id,
+            _ => (),
+        }
+    }
+}
+
 impl From<WindowEvent<'static>> for PanelEvent {
     fn from(source: WindowEvent<'static>) -> Self {
         match source {
@@ -39,6 +136,28 @@ impl From<WindowEvent<'static>> for PanelEvent {
                 in_slot: true,
                 state: state,
                 button: button,
+                click_count: 1,
+                seq: 0,
+            },
+            #[allow(deprecated)]
+            WindowEvent::KeyboardInput { input, .. } => PanelEvent::KeyboardInput {
+                state: input.state,
+                key: input.virtual_keycode,
+                modifiers: input.modifiers,
+                seq: 0,
+            },
+            WindowEvent::ReceivedCharacter(c) => PanelEvent::ReceivedCharacter(c),
+            WindowEvent::MouseWheel {
+                delta, modifiers, ..
+            } => PanelEvent::MouseWheel {
+                delta,
+                modifiers,
+                seq: 0,
+            },
+            WindowEvent::Touch(touch) => PanelEvent::Touch {
+                id: touch.id,
+                phase: touch.phase,
+                seq: 0,
             },
             _ => PanelEvent::Empty,
         }
@@ -56,6 +175,10 @@ pub trait Panel:
     ///
     fn outer_frame(&self) -> Visual;
     fn id(&self) -> usize;
+    /// How the panel takes the clicks when it's stacked over other panels, see [`HitTestMode`]
+    fn hit_test_mode(&self) -> HitTestMode {
+        HitTestMode::Opaque
+    }
 }
 
 impl<T: Panel> Panel for Arc<T> {
@@ -65,6 +188,9 @@ impl<T: Panel> Panel for Arc<T> {
     fn id(&self) -> usize {
         (**self).id()
     }
+    fn hit_test_mode(&self) -> HitTestMode {
+        (**self).hit_test_mode()
+    }
 }
 
 pub fn attach<T: Panel + ?Sized>(container: &ContainerVisual, panel: &T) -> crate::Result<()> {
@@ -80,25 +206,365 @@ pub fn detach(panel: &impl Panel) -> crate::Result<()> {
     Ok(())
 }
 
+///
+/// Shows or hides the panel and notifies it with `PanelEvent::VisibilityChanged`. Hidden panel
+/// keeps its place in the parent, but the containers stop routing input to it.
+///
+pub async fn set_visible<T: Panel + ?Sized>(panel: &T, visible: bool) -> crate::Result<()> {
+    let visual = panel.outer_frame();
+    if visual.IsVisible()? == visible {
+        return Ok(());
+    }
+    visual.SetIsVisible(visible)?;
+    panel
+        .on_event_owned(PanelEvent::VisibilityChanged(visible), None)
+        .await
+}
+
+pub fn is_visible<T: Panel + ?Sized>(panel: &T) -> crate::Result<bool> {
+    Ok(panel.outer_frame().IsVisible()?)
+}
+
+///
+/// Makes the compositor align the panel's visual to whole device pixels at the current scale,
+/// so that the content laid out at fractional offsets isn't blurred
+///
+pub fn set_pixel_snapping<T: Panel + ?Sized>(panel: &T, enabled: bool) -> crate::Result<()> {
+    panel.outer_frame().SetIsPixelSnappingEnabled(enabled)?;
+    Ok(())
+}
+
+///
+/// Spawns the task delivering window events to the root panel. Events are delivered one by one
+/// in the order they were posted, see [`EventSequencer`] for ordering guarantees.
+///
 pub fn spawn_window_event_receiver(
     pool: impl Spawn,
     panel: impl Panel + 'static,
     container: ContainerVisual,
-) -> crate::Result<Sender<WindowEvent<'static>>> {
-    let (tx_event_channel, mut rx_event_channel) = channel::<WindowEvent<'static>>(1024 * 64);
+) -> crate::Result<WindowEventSender> {
+    let (tx_event_channel, mut rx_event_channel) = channel::<(EventSeqId, PanelEvent)>(1024 * 64);
+    let sequencer = EventSequencer::new();
+    let pointer_capture = PointerCapture::default();
+    let drag_drop = DragDrop::new(container.clone());
+    let idle_monitor = IdleMonitor::new();
+    let cursors = CursorSelector::default();
+    let debug_frames = DebugFrames::new(container.clone());
+    let filters = EventFilters::default();
     let panel = panel;
     attach(&container, &panel)?;
-    pool.spawn(handle_err(async move {
-        while let Some(event) = rx_event_channel.next().await {
-            let panel_event = event.into();
-            match &panel_event {
-                // TODO: handle quit here
-                PanelEvent::Resized(size) => container.SetSize(*size)?,
-                _ => (),
-            };
-            panel.on_event_owned(panel_event, None).await?;
+    pool.spawn(handle_err({
+        let sequencer = sequencer.clone();
+        let mut router = Router {
+            panel,
+            container,
+            drag_drop: drag_drop.clone(),
+            pointer_capture: pointer_capture.clone(),
+            idle_monitor: idle_monitor.clone(),
+            cursors: cursors.clone(),
+            debug_frames: debug_frames.clone(),
+            filters: filters.clone(),
+            mouse_pos: Vector2::default(),
+        };
+        let clock = clock();
+        async move {
+            let _close = CloseOnDrop(sequencer.clone());
+            let mut click_counter = ClickCounter::new(clock.clone());
+            let mut gestures = GestureRecognizer::new();
+            loop {
+                // Wake up without events to recognize the long press
+                let next = match gestures.long_press_deadline() {
+                    Some(deadline) => clock.timeout_at(deadline, rx_event_channel.next()).await,
+                    None => Some(rx_event_channel.next().await),
+                };
+                let (id, event) = match next {
+                    Some(Some((id, event))) => (Some(id), Some(click_counter.count(event))),
+                    Some(None) => break,
+                    None => (None, None),
+                };
+                let recognized = match &event {
+                    Some(PanelEvent::Touch { id, phase, .. }) => {
+                        gestures.touch(*id, *phase, router.mouse_pos, clock.now())
+                    }
+                    Some(_) => Vec::new(),
+                    None => gestures.long_press(clock.now()).into_iter().collect(),
+                };
+                if let Some(event) = event {
+                    router.deliver(event).await?;
+                }
+                for (pos, gesture) in recognized {
+                    if pos != router.mouse_pos {
+                        router.deliver(PanelEvent::CursorMoved(pos)).await?;
+                    }
+                    router.deliver(PanelEvent::Gesture(gesture)).await?;
+                }
+                if let Some(id) = id {
+                    sequencer.set_processed(id);
+                }
+            }
+            Ok(())
         }
-        Ok(())
     }))?;
-    Ok(tx_event_channel)
+    let services = WindowServices::new(WindowHandles {
+        pointer_capture,
+        drag_drop,
+        idle_monitor,
+        cursors,
+        debug_frames,
+        filters,
+    });
+    Ok(WindowEventSender::new(
+        tx_event_channel,
+        sequencer,
+        services,
+    ))
+}
+
+// Delivers the window events to the drag, the panel holding the pointer capture or the root panel
+struct Router<P> {
+    panel: P,
+    container: ContainerVisual,
+    drag_drop: DragDrop,
+    pointer_capture: PointerCapture,
+    idle_monitor: IdleMonitor,
+    cursors: CursorSelector,
+    debug_frames: DebugFrames,
+    filters: EventFilters,
+    mouse_pos: Vector2,
+}
+
+impl<P: Panel> Router<P> {
+    async fn deliver(&mut self, event: PanelEvent) -> crate::Result<()> {
+        let event = match self.filters.apply(event).await? {
+            Some(event) => event,
+            None => return Ok(()),
+        };
+        match &event {
+            // TODO: handle quit here
+            PanelEvent::Resized(size) => self.container.SetSize(*size)?,
+            PanelEvent::CursorMoved(pos) => {
+                self.mouse_pos = *pos;
+                self.idle_monitor.input();
+            }
+            PanelEvent::MouseInput { .. }
+            | PanelEvent::KeyboardInput { .. }
+            | PanelEvent::ReceivedCharacter(_)
+            | PanelEvent::MouseWheel { .. }
+            | PanelEvent::MouseMotion(_)
+            | PanelEvent::Touch { .. } => self.idle_monitor.input(),
+            _ => (),
+        };
+        if self.drag_drop.process_event(&event, self.mouse_pos).await? {
+            return Ok(());
+        }
+        let captured = match &event {
+            PanelEvent::CursorMoved(_) | PanelEvent::MouseInput { .. } => {
+                self.pointer_capture.panel()
+            }
+            _ => None,
+        };
+        let moved = matches!(event, PanelEvent::CursorMoved(_));
+        let resized = matches!(event, PanelEvent::Resized(_));
+        match captured {
+            Some(captured) => deliver_captured(&*captured, event, self.mouse_pos).await?,
+            None => self.panel.on_event_owned(event, None).await?,
+        }
+        if moved {
+            self.cursors.update(self.mouse_pos)?;
+        }
+        if resized && self.debug_frames.is_enabled() {
+            self.debug_frames.refresh()?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Pointer capture of the window. While some panel holds the capture, mouse events of the window
+/// go to this panel only, bypassing the panel tree: the cursor position is translated to the
+/// panel's coordinates and `in_slot` tells if the cursor is inside the panel. This allows to keep
+/// tracking the mouse dragging the slider thumb or scrollbar outside of the panel.
+///
+/// The position is calculated from the offsets of the panel's visual and its parents, other
+/// transforms are not taken into account.
+///
+#[derive(Clone, Default)]
+pub struct PointerCapture {
+    panel: Arc<Mutex<Option<Arc<dyn Panel>>>>,
+}
+
+impl PointerCapture {
+    pub fn capture_pointer(&self, panel: Arc<dyn Panel>) {
+        *self.panel.lock().unwrap() = Some(panel);
+    }
+    pub fn release_pointer(&self) {
+        *self.panel.lock().unwrap() = None;
+    }
+    /// Id of the panel holding the capture
+    pub fn captured(&self) -> Option<usize> {
+        self.panel.lock().unwrap().as_ref().map(|v| v.id())
+    }
+    fn panel(&self) -> Option<Arc<dyn Panel>> {
+        self.panel.lock().unwrap().clone()
+    }
+}
+
+// Offset of the visual from the root visual of the window
+pub(super) fn window_offset(visual: &Visual) -> crate::Result<Vector2> {
+    let mut offset = Vector2::default();
+    let mut current = Some(visual.clone());
+    while let Some(visual) = current {
+        let v = visual.Offset()?;
+        offset.X += v.X;
+        offset.Y += v.Y;
+        current = visual.Parent().ok().map(Into::into);
+    }
+    Ok(offset)
+}
+
+async fn deliver_captured(
+    panel: &dyn Panel,
+    event: PanelEvent,
+    mouse_pos: Vector2,
+) -> crate::Result<()> {
+    let frame = panel.outer_frame();
+    let origin = window_offset(&frame)?;
+    let pos = Vector2 {
+        X: mouse_pos.X - origin.X,
+        Y: mouse_pos.Y - origin.Y,
+    };
+    let event = match event {
+        PanelEvent::CursorMoved(_) => PanelEvent::CursorMoved(pos),
+        PanelEvent::MouseInput {
+            state,
+            button,
+            click_count,
+            seq,
+            ..
+        } => PanelEvent::MouseInput {
+            in_slot: Rect::from_size(frame.Size()?).contains(pos),
+            state,
+            button,
+            click_count,
+            seq,
+        },
+        event => event,
+    };
+    panel.on_event_owned(event, None).await
+}
+
+// Sets `click_count` of the mouse input events coming from the window
+struct ClickCounter {
+    clock: Clock,
+    time: Duration,
+    distance: Vector2,
+    mouse_pos: Vector2,
+    last_press: Option<(MouseButton, Instant, Vector2)>,
+    count: u32,
+}
+
+impl ClickCounter {
+    fn new(clock: Clock) -> Self {
+        let (time, distance) = double_click_limits();
+        Self {
+            clock,
+            time,
+            distance,
+            mouse_pos: Vector2::default(),
+            last_press: None,
+            count: 0,
+        }
+    }
+
+    fn count(&mut self, event: PanelEvent) -> PanelEvent {
+        match event {
+            PanelEvent::CursorMoved(pos) => {
+                self.mouse_pos = pos;
+                event
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                seq,
+                ..
+            } => {
+                let same_button = matches!(self.last_press, Some((last, ..)) if last == button);
+                let click_count = match state {
+                    ElementState::Pressed => {
+                        let now = self.clock.now();
+                        let repeated = match self.last_press {
+                            Some((_, time, pos)) => {
+                                same_button
+                                    && now - time <= self.time
+                                    && (pos.X - self.mouse_pos.X).abs() <= self.distance.X / 2.
+                                    && (pos.Y - self.mouse_pos.Y).abs() <= self.distance.Y / 2.
+                            }
+                            None => false,
+                        };
+                        self.count = if repeated { self.count + 1 } else { 1 };
+                        self.last_press = Some((button, now, self.mouse_pos));
+                        self.count
+                    }
+                    ElementState::Released if same_button => self.count,
+                    ElementState::Released => 1,
+                };
+                PanelEvent::MouseInput {
+                    in_slot,
+                    state,
+                    button,
+                    click_count,
+                    seq,
+                }
+            }
+            event => event,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::VirtualClock;
+
+    fn press(counter: &mut ClickCounter, state: ElementState, button: MouseButton) -> u32 {
+        match counter.count(PanelEvent::MouseInput {
+            in_slot: true,
+            state,
+            button,
+            click_count: 0,
+            seq: 0,
+        }) {
+            PanelEvent::MouseInput { click_count, .. } => click_count,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn click_counter_counts_successive_clicks() {
+        let virtual_clock = VirtualClock::new();
+        let mut counter = ClickCounter {
+            clock: Clock::Virtual(virtual_clock.clone()),
+            time: Duration::from_millis(500),
+            distance: Vector2 { X: 4., Y: 4. },
+            mouse_pos: Vector2::default(),
+            last_press: None,
+            count: 0,
+        };
+        let (pressed, released) = (ElementState::Pressed, ElementState::Released);
+        assert_eq!(press(&mut counter, pressed, MouseButton::Left), 1);
+        assert_eq!(press(&mut counter, released, MouseButton::Left), 1);
+        virtual_clock.advance(Duration::from_millis(300));
+        assert_eq!(press(&mut counter, pressed, MouseButton::Left), 2);
+        assert_eq!(press(&mut counter, released, MouseButton::Left), 2);
+        // Other button, too late and too far presses start the new count
+        assert_eq!(press(&mut counter, pressed, MouseButton::Right), 1);
+        assert_eq!(press(&mut counter, released, MouseButton::Left), 1);
+        virtual_clock.advance(Duration::from_millis(600));
+        assert_eq!(press(&mut counter, pressed, MouseButton::Right), 1);
+        counter.count(PanelEvent::CursorMoved(Vector2 { X: 3., Y: 0. }));
+        assert_eq!(press(&mut counter, pressed, MouseButton::Right), 1);
+        counter.count(PanelEvent::CursorMoved(Vector2 { X: 4., Y: 1. }));
+        assert_eq!(press(&mut counter, pressed, MouseButton::Right), 2);
+    }
 }
@@ -0,0 +1,259 @@
+use windows::{
+    core::implement,
+    Foundation::Numerics::Vector2,
+    Graphics::{IGeometrySource2D, IGeometrySource2D_Impl},
+    Win32::{
+        Foundation::{E_NOTIMPL, E_POINTER},
+        Graphics::Direct2D::{
+            Common::{
+                D2D1_BEZIER_SEGMENT, D2D1_FIGURE_BEGIN_FILLED, D2D1_FIGURE_END_CLOSED,
+                D2D1_FIGURE_END_OPEN, D2D_POINT_2F, D2D_SIZE_F,
+            },
+            ID2D1Factory, ID2D1Geometry, D2D1_ARC_SEGMENT, D2D1_ARC_SIZE_LARGE,
+            D2D1_ARC_SIZE_SMALL, D2D1_QUADRATIC_BEZIER_SEGMENT, D2D1_SWEEP_DIRECTION_CLOCKWISE,
+            D2D1_SWEEP_DIRECTION_COUNTER_CLOCKWISE,
+        },
+        System::WinRT::Graphics::Direct2D::{
+            IGeometrySource2DInterop, IGeometrySource2DInterop_Impl,
+        },
+    },
+    UI::Composition::{CompositionPath, CompositionPathGeometry, Compositor},
+};
+
+use crate::window::d2d1_device;
+
+// Direct2D geometry handed to the compositor as the path
+#[implement(IGeometrySource2D, IGeometrySource2DInterop)]
+struct GeometrySource {
+    geometry: ID2D1Geometry,
+}
+
+impl IGeometrySource2D_Impl for GeometrySource {}
+
+impl IGeometrySource2DInterop_Impl for GeometrySource {
+    fn GetGeometry(&self) -> windows::core::Result<ID2D1Geometry> {
+        Ok(self.geometry.clone())
+    }
+    fn TryGetGeometryUsingFactory(
+        &self,
+        _factory: &Option<ID2D1Factory>,
+    ) -> windows::core::Result<ID2D1Geometry> {
+        Err(E_NOTIMPL.into())
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+enum Segment {
+    Line(Vector2),
+    Quadratic(Vector2, Vector2),
+    Cubic(Vector2, Vector2, Vector2),
+    Arc {
+        to: Vector2,
+        radius: Vector2,
+        rotation: f32,
+        large: bool,
+        clockwise: bool,
+    },
+}
+
+#[derive(PartialEq, Clone, Debug)]
+struct Figure {
+    start: Vector2,
+    segments: Vec<Segment>,
+    closed: bool,
+}
+
+///
+/// Outline of the lines, Bézier curves and elliptical arcs for the composition shapes, like
+/// the path of SVG. The path consists of the figures, each started by `move_to` and
+/// optionally closed by `close`:
+///
+/// ```ignore
+/// let triangle = PathBuilder::new()
+///     .move_to(point(0., 10.))
+///     .line_to(point(10., 10.))
+///     .line_to(point(5., 0.))
+///     .close();
+/// ```
+///
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct PathBuilder {
+    figures: Vec<Figure>,
+}
+
+impl PathBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the new figure at the point
+    pub fn move_to(mut self, point: Vector2) -> Self {
+        self.figures.push(Figure {
+            start: point,
+            segments: Vec::new(),
+            closed: false,
+        });
+        self
+    }
+
+    // Adds the segment to the current figure, the path without figures starts at (0,0)
+    fn segment(mut self, segment: Segment) -> Self {
+        if self.figures.last().map_or(true, |v| v.closed) {
+            let start = self.current_point();
+            self = self.move_to(start);
+        }
+        if let Some(figure) = self.figures.last_mut() {
+            figure.segments.push(segment);
+        }
+        self
+    }
+
+    /// End of the last segment, the start of the next one
+    pub fn current_point(&self) -> Vector2 {
+        let figure = match self.figures.last() {
+            Some(v) => v,
+            None => return Vector2::default(),
+        };
+        if figure.closed {
+            return figure.start;
+        }
+        match figure.segments.last() {
+            Some(Segment::Line(to))
+            | Some(Segment::Quadratic(_, to))
+            | Some(Segment::Cubic(_, _, to))
+            | Some(Segment::Arc { to, .. }) => *to,
+            None => figure.start,
+        }
+    }
+
+    pub fn line_to(self, point: Vector2) -> Self {
+        self.segment(Segment::Line(point))
+    }
+
+    /// Quadratic Bézier curve with the control point
+    pub fn quadratic_to(self, control: Vector2, point: Vector2) -> Self {
+        self.segment(Segment::Quadratic(control, point))
+    }
+
+    /// Cubic Bézier curve with two control points
+    pub fn cubic_to(self, control1: Vector2, control2: Vector2, point: Vector2) -> Self {
+        self.segment(Segment::Cubic(control1, control2, point))
+    }
+
+    ///
+    /// Elliptical arc to the point with the radii rotated by `rotation` degrees. Of the arcs
+    /// connecting the points, the one larger than 180 degrees is taken if `large` is set.
+    ///
+    pub fn arc_to(
+        self,
+        point: Vector2,
+        radius: Vector2,
+        rotation: f32,
+        large: bool,
+        clockwise: bool,
+    ) -> Self {
+        self.segment(Segment::Arc {
+            to: point,
+            radius,
+            rotation,
+            large,
+            clockwise,
+        })
+    }
+
+    /// Closes the current figure by the line to its start
+    pub fn close(mut self) -> Self {
+        if let Some(figure) = self.figures.last_mut() {
+            figure.closed = true;
+        }
+        self
+    }
+
+    /// Figure of the lines connecting the points
+    pub fn polyline(points: &[Vector2]) -> Self {
+        let mut points = points.iter();
+        let mut path = match points.next() {
+            Some(start) => Self::new().move_to(*start),
+            None => return Self::new(),
+        };
+        for point in points {
+            path = path.line_to(*point);
+        }
+        path
+    }
+
+    /// The Direct2D geometry of the path
+    pub fn d2d1_geometry(&self) -> crate::Result<ID2D1Geometry> {
+        let mut factory: Option<ID2D1Factory> = None;
+        unsafe { d2d1_device()?.GetFactory(&mut factory) };
+        let factory = factory.ok_or_else(|| windows::core::Error::from(E_POINTER))?;
+        let path = unsafe { factory.CreatePathGeometry() }?;
+        let sink = unsafe { path.Open() }?;
+        let point = |v: &Vector2| D2D_POINT_2F { x: v.X, y: v.Y };
+        for figure in &self.figures {
+            unsafe { sink.BeginFigure(point(&figure.start), D2D1_FIGURE_BEGIN_FILLED) };
+            for segment in &figure.segments {
+                match segment {
+                    Segment::Line(to) => unsafe { sink.AddLine(point(to)) },
+                    Segment::Quadratic(control, to) => unsafe {
+                        sink.AddQuadraticBezier(&D2D1_QUADRATIC_BEZIER_SEGMENT {
+                            point1: point(control),
+                            point2: point(to),
+                        })
+                    },
+                    Segment::Cubic(control1, control2, to) => unsafe {
+                        sink.AddBezier(&D2D1_BEZIER_SEGMENT {
+                            point1: point(control1),
+                            point2: point(control2),
+                            point3: point(to),
+                        })
+                    },
+                    Segment::Arc {
+                        to,
+                        radius,
+                        rotation,
+                        large,
+                        clockwise,
+                    } => unsafe {
+                        sink.AddArc(&D2D1_ARC_SEGMENT {
+                            point: point(to),
+                            size: D2D_SIZE_F {
+                                width: radius.X,
+                                height: radius.Y,
+                            },
+                            rotationAngle: *rotation,
+                            sweepDirection: if *clockwise {
+                                D2D1_SWEEP_DIRECTION_CLOCKWISE
+                            } else {
+                                D2D1_SWEEP_DIRECTION_COUNTER_CLOCKWISE
+                            },
+                            arcSize: if *large {
+                                D2D1_ARC_SIZE_LARGE
+                            } else {
+                                D2D1_ARC_SIZE_SMALL
+                            },
+                        })
+                    },
+                }
+            }
+            let end = if figure.closed {
+                D2D1_FIGURE_END_CLOSED
+            } else {
+                D2D1_FIGURE_END_OPEN
+            };
+            unsafe { sink.EndFigure(end) };
+        }
+        unsafe { sink.Close() }?;
+        Ok(path.into())
+    }
+
+    /// The composition geometry of the path for the sprite shapes
+    pub fn geometry(&self, compositor: &Compositor) -> crate::Result<CompositionPathGeometry> {
+        let source: IGeometrySource2D = GeometrySource {
+            geometry: self.d2d1_geometry()?,
+        }
+        .into();
+        let path = CompositionPath::Create(&source)?;
+        Ok(compositor.CreatePathGeometryWithPath(&path)?)
+    }
+}
@@ -0,0 +1,317 @@
+use std::{borrow::Cow, time::Duration};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use float_ord::FloatOrd;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::HSTRING,
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::{
+        Color, Colors,
+        Composition::{
+            AnimationIterationBehavior, CompositionStrokeCap, Compositor, ShapeVisual, Visual,
+        },
+    },
+};
+
+use super::{animation_stepper::start_animation, time_span, Panel, PanelEvent};
+
+struct BarCore {
+    compositor: Compositor,
+    visual: ShapeVisual,
+    value: f32,
+    color: Color,
+    track_color: Color,
+}
+
+impl BarCore {
+    fn redraw(&self) -> crate::Result<()> {
+        let size = self.visual.Size()?;
+        let shapes = self.visual.Shapes()?;
+        shapes.Clear()?;
+        let radius = size.Y / 2.;
+        let append_rect = |width: f32, color: Color| -> crate::Result<()> {
+            let geometry = self.compositor.CreateRoundedRectangleGeometry()?;
+            geometry.SetSize(Vector2 {
+                X: width,
+                Y: size.Y,
+            })?;
+            geometry.SetCornerRadius(Vector2 {
+                X: radius,
+                Y: radius,
+            })?;
+            let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+            shape.SetFillBrush(&self.compositor.CreateColorBrushWithColor(color)?)?;
+            shapes.Append(&shape)?;
+            Ok(())
+        };
+        append_rect(size.X, self.track_color)?;
+        if self.value > 0. {
+            append_rect(size.X * self.value, self.color)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Determinate progress indicator: a track filled proportionally to the value in 0..1 range
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct ProgressBar {
+    visual: ShapeVisual,
+    core: RwLock<BarCore>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ProgressBarParams {
+    compositor: Compositor,
+    color: Color,
+    #[builder(default = Colors::LightGray().unwrap())]
+    track_color: Color,
+    #[builder(default = 0.)]
+    value: f32,
+}
+
+impl TryFrom<ProgressBarParams> for ProgressBar {
+    type Error = crate::Error;
+
+    fn try_from(value: ProgressBarParams) -> crate::Result<Self> {
+        let visual = value.compositor.CreateShapeVisual()?;
+        let core = RwLock::new(BarCore {
+            compositor: value.compositor,
+            visual: visual.clone(),
+            value: value.value.clamp(0., 1.),
+            color: value.color,
+            track_color: value.track_color,
+        });
+        Ok(ProgressBar {
+            visual,
+            core,
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ProgressBarParams> for Arc<ProgressBar> {
+    type Error = crate::Error;
+
+    fn try_from(value: ProgressBarParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ProgressBar {
+    pub async fn value(&self) -> f32 {
+        self.core.read().await.value
+    }
+    pub async fn set_value(&self, value: f32) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        core.value = value.clamp(0., 1.);
+        core.redraw()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ProgressBar {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.visual.SetSize(*size)?;
+            self.core.read().await.redraw()?;
+        }
+        self.panel_events
+            .send_event(event.into_owned(), source)
+            .await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for ProgressBar {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for ProgressBar {
+    fn outer_frame(&self) -> Visual {
+        self.visual.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+struct RingCore {
+    compositor: Compositor,
+    visual: ShapeVisual,
+    color: Color,
+    period: Duration,
+    active: bool,
+}
+
+impl RingCore {
+    fn redraw(&self) -> crate::Result<()> {
+        let size = self.visual.Size()?;
+        let side = std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0;
+        let thickness = side / 10.;
+        let center = Vector2 {
+            X: size.X / 2.,
+            Y: size.Y / 2.,
+        };
+        self.visual.SetCenterPoint(Vector3 {
+            X: center.X,
+            Y: center.Y,
+            Z: 0.,
+        })?;
+        let shapes = self.visual.Shapes()?;
+        shapes.Clear()?;
+        let geometry = self.compositor.CreateEllipseGeometry()?;
+        geometry.SetCenter(center)?;
+        let radius = (side - thickness) / 2.;
+        geometry.SetRadius(Vector2 {
+            X: radius,
+            Y: radius,
+        })?;
+        geometry.SetTrimEnd(0.25)?;
+        let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        shape.SetStrokeBrush(&self.compositor.CreateColorBrushWithColor(self.color)?)?;
+        shape.SetStrokeThickness(thickness)?;
+        shape.SetStrokeStartCap(CompositionStrokeCap::Round)?;
+        shape.SetStrokeEndCap(CompositionStrokeCap::Round)?;
+        shapes.Append(&shape)?;
+        Ok(())
+    }
+    fn start(&self) -> crate::Result<()> {
+        let animation = self.compositor.CreateScalarKeyFrameAnimation()?;
+        let easing = self.compositor.CreateLinearEasingFunction()?;
+        animation.InsertKeyFrame(0., 0.)?;
+        animation.InsertKeyFrameWithEasingFunction(1., 360., &easing)?;
+        animation.SetDuration(time_span(self.period))?;
+        animation.SetIterationBehavior(AnimationIterationBehavior::Forever)?;
+        start_animation(&self.visual, "RotationAngleInDegrees", &animation)?;
+        Ok(())
+    }
+    fn set_active(&mut self, active: bool) -> crate::Result<()> {
+        if self.active == active {
+            return Ok(());
+        }
+        self.active = active;
+        self.visual.SetIsVisible(active)?;
+        if active {
+            self.start()
+        } else {
+            self.visual
+                .StopAnimation(&HSTRING::from("RotationAngleInDegrees"))?;
+            Ok(())
+        }
+    }
+}
+
+///
+/// Indeterminate progress indicator: an arc continuously rotating with composition animation,
+/// running without any involvement of the application
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct ProgressRing {
+    visual: ShapeVisual,
+    core: RwLock<RingCore>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ProgressRingParams {
+    compositor: Compositor,
+    color: Color,
+    /// Time of one revolution
+    #[builder(default = Duration::from_secs(1))]
+    period: Duration,
+}
+
+impl TryFrom<ProgressRingParams> for ProgressRing {
+    type Error = crate::Error;
+
+    fn try_from(value: ProgressRingParams) -> crate::Result<Self> {
+        let visual = value.compositor.CreateShapeVisual()?;
+        let core = RingCore {
+            compositor: value.compositor,
+            visual: visual.clone(),
+            color: value.color,
+            period: value.period,
+            active: true,
+        };
+        core.start()?;
+        Ok(ProgressRing {
+            visual,
+            core: RwLock::new(core),
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ProgressRingParams> for Arc<ProgressRing> {
+    type Error = crate::Error;
+
+    fn try_from(value: ProgressRingParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ProgressRing {
+    pub async fn is_active(&self) -> bool {
+        self.core.read().await.active
+    }
+    /// Inactive ring is hidden and its animation is stopped
+    pub async fn set_active(&self, active: bool) -> crate::Result<()> {
+        self.core.write().await.set_active(active)
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ProgressRing {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.visual.SetSize(*size)?;
+            self.core.read().await.redraw()?;
+        }
+        self.panel_events
+            .send_event(event.into_owned(), source)
+            .await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for ProgressRing {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for ProgressRing {
+    fn outer_frame(&self) -> Visual {
+        self.visual.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    borrow::Cow,
+    sync::{Mutex, Weak},
+};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use float_ord::FloatOrd;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, ShapeVisual, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+#[cfg(feature = "accessibility")]
+use super::accessibility::{set_accessible, Accessible, AccessibleRole};
+use super::{attach, dispatch::DispatchQueue, Panel, PanelEvent};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum RadioButtonEvent {
+    CheckedChanged(bool),
+    /// The button got or lost the keyboard focus
+    FocusChanged(bool),
+}
+
+struct Core {
+    skin: Arc<dyn RadioButtonSkin>,
+    checked: bool,
+    pressed: bool,
+    focused: bool,
+    radio_button_events: Arc<EventStreams<RadioButtonEvent>>,
+}
+
+impl Core {
+    async fn send(
+        &self,
+        event: RadioButtonEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.skin.on_event_ref(&event, source.clone()).await?;
+        self.radio_button_events.send_event(event, source).await;
+        Ok(())
+    }
+    async fn set_checked(
+        &mut self,
+        checked: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if self.checked != checked {
+            self.checked = checked;
+            self.send(RadioButtonEvent::CheckedChanged(checked), source)
+                .await?;
+        }
+        Ok(())
+    }
+    async fn set_focused(
+        &mut self,
+        focused: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if self.focused != focused {
+            self.focused = focused;
+            self.send(RadioButtonEvent::FocusChanged(focused), source)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Radio button: one of the mutually exclusive options of its [`RadioGroup`]. Click or Space
+/// checks the button and unchecks the other buttons of the group, the checked button can't be
+/// unchecked by the user. The button gets the keyboard focus on mouse press, arrow keys move
+/// the focus and the check to the previous or next button of the group.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct RadioButton {
+    container: ContainerVisual,
+    core: Arc<RwLock<Core>>,
+    group: Arc<RadioGroup>,
+    panel_events: EventStreams<PanelEvent>,
+    radio_button_events: Arc<EventStreams<RadioButtonEvent>>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct RadioButtonParams {
+    compositor: Compositor,
+    #[builder(setter(transform = |skin: impl RadioButtonSkin + 'static | Arc::new(skin) as Arc<dyn RadioButtonSkin>))]
+    skin: Arc<dyn RadioButtonSkin>,
+    group: Arc<RadioGroup>,
+    /// Initially selected button of the group, only one button of the group should be checked
+    #[builder(default)]
+    checked: bool,
+}
+
+impl TryFrom<RadioButtonParams> for RadioButton {
+    type Error = crate::Error;
+
+    fn try_from(value: RadioButtonParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        #[cfg(feature = "accessibility")]
+        set_accessible(
+            &container.clone().into(),
+            &Accessible {
+                focusable: true,
+                automation_id: Some("RadioButton".to_string()),
+                ..Accessible::new(AccessibleRole::RadioButton)
+            },
+        )?;
+        let skin = value.skin;
+        attach(&container, &*skin)?;
+        let radio_button_events = Arc::new(EventStreams::new());
+        let core = Arc::new(RwLock::new(Core {
+            skin,
+            checked: value.checked,
+            pressed: false,
+            focused: false,
+            radio_button_events: radio_button_events.clone(),
+        }));
+        let id = Arc::new(());
+        value
+            .group
+            .add_member(Arc::as_ptr(&id) as usize, &core, value.checked);
+        Ok(RadioButton {
+            container,
+            core,
+            group: value.group,
+            panel_events: EventStreams::new(),
+            radio_button_events,
+            dispatch: DispatchQueue::new(),
+            id,
+        })
+    }
+}
+
+impl TryFrom<RadioButtonParams> for Arc<RadioButton> {
+    type Error = crate::Error;
+
+    fn try_from(value: RadioButtonParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl RadioButton {
+    pub async fn is_checked(&self) -> bool {
+        self.core.read().await.checked
+    }
+
+    /// Checks the button, unchecking the other buttons of the group
+    pub async fn select(&self) -> crate::Result<()> {
+        self.group.select_from(self.id(), None).await
+    }
+
+    async fn key_pressed(
+        &self,
+        key: VirtualKeyCode,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if !self.core.read().await.focused {
+            return Ok(());
+        }
+        match key {
+            VirtualKeyCode::Space => self.group.select_from(self.id(), source).await,
+            VirtualKeyCode::Left | VirtualKeyCode::Up => {
+                self.group.step(self.id(), false, source).await
+            }
+            VirtualKeyCode::Right | VirtualKeyCode::Down => {
+                self.group.step(self.id(), true, source).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let skin = self.core.read().await.skin.clone();
+        skin.on_event_ref(&event, source.clone()).await?;
+        self.panel_events
+            .send_event(event.clone(), source.clone())
+            .await;
+        match event {
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let click = {
+                    let mut core = self.core.write().await;
+                    match state {
+                        ElementState::Pressed => {
+                            core.set_focused(in_slot, source.clone()).await?;
+                            core.pressed = in_slot;
+                            false
+                        }
+                        ElementState::Released if core.pressed => {
+                            core.pressed = false;
+                            in_slot
+                        }
+                        ElementState::Released => false,
+                    }
+                };
+                if click {
+                    self.group.select_from(self.id(), source).await?;
+                }
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(key),
+                ..
+            } => self.key_pressed(key, source).await?,
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+impl EventSource<RadioButtonEvent> for RadioButton {
+    fn event_stream(&self) -> EventStream<RadioButtonEvent> {
+        self.radio_button_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for RadioButton {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for RadioButton {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for RadioButton {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum RadioGroupEvent {
+    /// Id of the checked [`RadioButton`], as returned by its `Panel::id`
+    SelectionChanged(usize),
+}
+
+///
+/// Coordinator of the [`RadioButton`]s keeping at most one of them checked. The button joins
+/// the group by `RadioButtonParams::group`, arrow keys move between the buttons in the order
+/// they joined. Nothing is selected until a button is created checked or selected.
+///
+#[derive(Default)]
+pub struct RadioGroup {
+    members: Mutex<Vec<(usize, Weak<RwLock<Core>>)>>,
+    selected: Mutex<Option<usize>>,
+    radio_group_events: EventStreams<RadioGroupEvent>,
+}
+
+impl RadioGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Id of the checked button
+    pub fn selected(&self) -> Option<usize> {
+        *self.selected.lock().unwrap()
+    }
+
+    /// Checks the button with the id, returns false if there is no such button in the group
+    pub async fn select(&self, id: usize) -> crate::Result<bool> {
+        if !self.members().iter().any(|(member, _)| *member == id) {
+            return Ok(false);
+        }
+        self.select_from(id, None).await?;
+        Ok(true)
+    }
+
+    fn add_member(&self, id: usize, core: &Arc<RwLock<Core>>, checked: bool) {
+        let mut members = self.members.lock().unwrap();
+        members.retain(|(_, v)| v.strong_count() > 0);
+        members.push((id, Arc::downgrade(core)));
+        if checked {
+            *self.selected.lock().unwrap() = Some(id);
+        }
+    }
+
+    // Members still alive, in the order they joined
+    fn members(&self) -> Vec<(usize, Arc<RwLock<Core>>)> {
+        let mut members = self.members.lock().unwrap();
+        members.retain(|(_, v)| v.strong_count() > 0);
+        members
+            .iter()
+            .filter_map(|(id, core)| Some((*id, core.upgrade()?)))
+            .collect()
+    }
+
+    async fn select_from(&self, id: usize, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        {
+            let mut selected = self.selected.lock().unwrap();
+            if *selected == Some(id) {
+                return Ok(());
+            }
+            *selected = Some(id);
+        }
+        for (member, core) in self.members() {
+            core.write()
+                .await
+                .set_checked(member == id, source.clone())
+                .await?;
+        }
+        self.radio_group_events
+            .send_event(RadioGroupEvent::SelectionChanged(id), source)
+            .await;
+        Ok(())
+    }
+
+    // Moves the focus and the check from the button to its neighbour, wrapping around
+    async fn step(
+        &self,
+        id: usize,
+        forward: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let members = self.members();
+        let count = members.len();
+        let index = match members.iter().position(|(member, _)| *member == id) {
+            Some(index) if count > 1 => index,
+            _ => return Ok(()),
+        };
+        let next = if forward {
+            (index + 1) % count
+        } else {
+            (index + count - 1) % count
+        };
+        members[index]
+            .1
+            .write()
+            .await
+            .set_focused(false, source.clone())
+            .await?;
+        let (next_id, next_core) = &members[next];
+        next_core
+            .write()
+            .await
+            .set_focused(true, source.clone())
+            .await?;
+        self.select_from(*next_id, source).await
+    }
+}
+
+impl EventSource<RadioGroupEvent> for RadioGroup {
+    fn event_stream(&self) -> EventStream<RadioGroupEvent> {
+        self.radio_group_events.create_event_stream()
+    }
+}
+
+pub trait RadioButtonSkin: Panel + EventSink<RadioButtonEvent, Error = crate::Error> {}
+impl<T: Panel + EventSink<RadioButtonEvent, Error = crate::Error>> RadioButtonSkin for T {}
+
+struct SkinCore {
+    compositor: Compositor,
+    visual: ShapeVisual,
+    color: Color,
+    dot_color: Color,
+    checked: bool,
+    focused: bool,
+}
+
+impl SkinCore {
+    fn redraw(&self) -> crate::Result<()> {
+        let compositor = &self.compositor;
+        let shapes = self.visual.Shapes()?;
+        shapes.Clear()?;
+        let size = self.visual.Size()?;
+        let side = std::cmp::min(FloatOrd(size.X), FloatOrd(size.Y)).0;
+        let center = Vector2 {
+            X: size.X / 2.,
+            Y: size.Y / 2.,
+        };
+        let append_circle =
+            |radius: f32, fill: Option<Color>, stroke: Option<(Color, f32)>| -> crate::Result<()> {
+                let geometry = compositor.CreateEllipseGeometry()?;
+                geometry.SetCenter(center)?;
+                geometry.SetRadius(Vector2 {
+                    X: radius,
+                    Y: radius,
+                })?;
+                let shape = compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+                if let Some(fill) = fill {
+                    shape.SetFillBrush(&compositor.CreateColorBrushWithColor(fill)?)?;
+                }
+                if let Some((color, thickness)) = stroke {
+                    shape.SetStrokeBrush(&compositor.CreateColorBrushWithColor(color)?)?;
+                    shape.SetStrokeThickness(thickness)?;
+                }
+                shapes.Append(&shape)?;
+                Ok(())
+            };
+        if self.focused {
+            append_circle(side * 0.48, None, Some((self.color, side / 25.)))?;
+        }
+        if self.checked {
+            append_circle(side * 0.35, Some(self.color), None)?;
+            append_circle(side * 0.14, Some(self.dot_color), None)?;
+        } else {
+            append_circle(side * 0.32, None, Some((self.color, side / 12.)))?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Default radio button skin: a circle drawn with composition shapes, filled and marked with
+/// a dot when checked and surrounded by a ring while focused
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+#[event_sink(event=RadioButtonEvent)]
+pub struct SimpleRadioButtonSkin {
+    visual: ShapeVisual,
+    core: RwLock<SkinCore>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct SimpleRadioButtonSkinParams {
+    compositor: Compositor,
+    color: Color,
+    #[builder(default = Colors::White().unwrap())]
+    dot_color: Color,
+}
+
+impl TryFrom<SimpleRadioButtonSkinParams> for SimpleRadioButtonSkin {
+    type Error = crate::Error;
+    fn try_from(value: SimpleRadioButtonSkinParams) -> crate::Result<Self> {
+        let visual = value.compositor.CreateShapeVisual()?;
+        let core = RwLock::new(SkinCore {
+            compositor: value.compositor,
+            visual: visual.clone(),
+            color: value.color,
+            dot_color: value.dot_color,
+            checked: false,
+            focused: false,
+        });
+        Ok(SimpleRadioButtonSkin {
+            visual,
+            core,
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<SimpleRadioButtonSkinParams> for Arc<SimpleRadioButtonSkin> {
+    type Error = crate::Error;
+
+    fn try_from(value: SimpleRadioButtonSkinParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<RadioButtonEvent> for SimpleRadioButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, RadioButtonEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        match event.as_ref() {
+            RadioButtonEvent::CheckedChanged(checked) => core.checked = *checked,
+            RadioButtonEvent::FocusChanged(focused) => core.focused = *focused,
+        }
+        core.redraw()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for SimpleRadioButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.visual.SetSize(*size)?;
+            self.core.read().await.redraw()?;
+        }
+        self.panel_events
+            .send_event(event.into_owned(), source)
+            .await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for SimpleRadioButtonSkin {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for SimpleRadioButtonSkin {
+    fn outer_frame(&self) -> Visual {
+        self.visual.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
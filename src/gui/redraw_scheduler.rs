@@ -0,0 +1,180 @@
+use std::{
+    sync::{Mutex, RwLock, Weak},
+    time::Duration,
+};
+
+use async_event_streams::EventStreams;
+use async_std::sync::Arc;
+use futures::{
+    channel::mpsc::{unbounded, UnboundedSender},
+    task::{Spawn, SpawnExt},
+    StreamExt,
+};
+use windows::{Foundation::Numerics::Vector2, UI::Composition::Visual};
+
+use super::{clock, frame_interval, SurfaceEvent};
+
+// Redraw of the surface waiting for its turn
+struct Request {
+    id: usize,
+    visual: Visual,
+    priority: i32,
+    size: Vector2,
+    surface_events: Weak<EventStreams<SurfaceEvent>>,
+}
+
+// Whether the visual and its parents are visible and it overlaps the root's bounds. The
+// scale and the rotation of the visuals are not taken into account.
+fn is_on_screen(visual: &Visual) -> bool {
+    let check = || -> crate::Result<bool> {
+        let size = visual.Size()?;
+        let (mut left, mut top) = (0., 0.);
+        let mut current = visual.clone();
+        loop {
+            if !current.IsVisible()? {
+                return Ok(false);
+            }
+            match current.Parent() {
+                Ok(parent) => {
+                    let offset = current.Offset()?;
+                    left += offset.X;
+                    top += offset.Y;
+                    current = parent.into();
+                }
+                Err(_) => break,
+            }
+        }
+        let root = current.Size()?;
+        // The root without size, e.g. not attached to the window yet, is not clipped
+        if root.X <= 0. || root.Y <= 0. {
+            return Ok(true);
+        }
+        Ok(left < root.X && top < root.Y && left + size.X > 0. && top + size.Y > 0.)
+    };
+    check().unwrap_or(true)
+}
+
+struct State {
+    requests: Vec<Request>,
+    notify: UnboundedSender<()>,
+}
+
+///
+/// Orders the redraws of the [`Surface`] panels when many of them need redrawing at once, e.g.
+/// the dashboard of charts on resize. While the scheduler is set by `set_redraw_scheduler`,
+/// the surfaces don't send `SurfaceEvent::Redraw` immediately but queue it. Each frame the
+/// scheduler sends the queued redraws of the visible surfaces first, the higher priority
+/// first, until the redraw handlers take the `budget` of time. The rest waits for the next
+/// frame, the hidden and off-screen surfaces are redrawn after all the visible ones.
+///
+/// [`Surface`]: super::Surface
+///
+#[derive(Clone)]
+pub struct RedrawScheduler {
+    state: Arc<Mutex<State>>,
+    budget: Duration,
+}
+
+impl RedrawScheduler {
+    /// Starts the scheduler's task on the spawner, the task ends when the scheduler is dropped
+    pub fn new<S: Spawn + ?Sized>(spawner: &S, budget: Duration) -> crate::Result<Self> {
+        let (notify, mut notifications) = unbounded();
+        let state = Arc::new(Mutex::new(State {
+            requests: Vec::new(),
+            notify,
+        }));
+        let scheduler = Self { state, budget };
+        let weak = Arc::downgrade(&scheduler.state);
+        spawner.spawn(async move {
+            while notifications.next().await.is_some() {
+                let scheduler = match weak.upgrade() {
+                    Some(state) => RedrawScheduler { state, budget },
+                    None => return,
+                };
+                scheduler.run().await;
+            }
+        })?;
+        Ok(scheduler)
+    }
+
+    /// The time per frame given to the redraw handlers
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Number of the redraws waiting
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().requests.len()
+    }
+
+    // Queues the redraw replacing the one of the same surface, only the latest size matters
+    pub(crate) fn request(
+        &self,
+        id: usize,
+        visual: Visual,
+        priority: i32,
+        size: Vector2,
+        surface_events: Weak<EventStreams<SurfaceEvent>>,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.requests.retain(|v| v.id != id);
+        state.requests.push(Request {
+            id,
+            visual,
+            priority,
+            size,
+            surface_events,
+        });
+        let _ = state.notify.unbounded_send(());
+    }
+
+    // Most urgent request: visible before off-screen, then by priority, then by the age
+    fn next(&self) -> Option<Request> {
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .requests
+            .iter()
+            .enumerate()
+            .max_by_key(|(index, v)| (is_on_screen(&v.visual), v.priority, usize::MAX - index))
+            .map(|(index, _)| index)?;
+        Some(state.requests.remove(index))
+    }
+
+    // Sends the redraws frame by frame until the queue is empty
+    async fn run(&self) {
+        let clock = clock();
+        loop {
+            let frame_start = clock.now();
+            // At least one redraw per frame even if it takes longer than the budget
+            while let Some(request) = self.next() {
+                if let Some(surface_events) = request.surface_events.upgrade() {
+                    surface_events
+                        .send_event(SurfaceEvent::Redraw(request.size), None)
+                        .await;
+                }
+                if clock.now() - frame_start >= self.budget {
+                    break;
+                }
+            }
+            if self.pending() == 0 {
+                return;
+            }
+            clock.sleep_until(frame_start + frame_interval()).await;
+        }
+    }
+}
+
+static SCHEDULER: RwLock<Option<RedrawScheduler>> = RwLock::new(None);
+
+/// The scheduler of the surfaces' redraws, if set
+pub fn redraw_scheduler() -> Option<RedrawScheduler> {
+    SCHEDULER.read().unwrap().clone()
+}
+
+///
+/// Makes the surfaces queue their redraws to the scheduler or, with `None`, send them
+/// immediately. The redraws already queued are still sent by the old scheduler.
+///
+pub fn set_redraw_scheduler(scheduler: Option<RedrawScheduler>) {
+    *SCHEDULER.write().unwrap() = scheduler;
+}
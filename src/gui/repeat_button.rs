@@ -0,0 +1,261 @@
+use std::{
+    borrow::Cow,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use async_event_streams::{
+    spawn_event_pipe, EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use futures::task::Spawn;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::Composition::{Compositor, ContainerVisual, Visual},
+};
+use winit::event::{ElementState, MouseButton};
+
+use crate::{geometry::Rect, on_err};
+
+use super::{
+    accelerating_delays, attach, dispatch::DispatchQueue, ButtonEvent, ButtonSkin, Panel,
+    PanelEvent, Timer, TimerEvent,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum RepeatButtonEvent {
+    /// Sent on press and then repeatedly while the button is held
+    Pressed,
+}
+
+// Receives the timer ticks and turns them into presses
+#[derive(EventSink)]
+#[event_sink(event=TimerEvent)]
+struct Repeater {
+    hover: AtomicBool,
+    repeat_button_events: EventStreams<RepeatButtonEvent>,
+}
+
+#[async_trait]
+impl EventSinkExt<TimerEvent> for Repeater {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        _: Cow<'a, TimerEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        // Like the scrollbar arrow, the held button doesn't fire while the cursor is away
+        if self.hover.load(Ordering::SeqCst) {
+            self.repeat_button_events
+                .send_event(RepeatButtonEvent::Pressed, source)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+struct Core {
+    skin: Arc<dyn ButtonSkin>,
+    pressed: bool,
+    size: Vector2,
+}
+
+///
+/// Button firing repeatedly while held: `RepeatButtonEvent::Pressed` is sent on press, then
+/// after `initial_delay` and then with the `interval` decreasing by `acceleration` factor down
+/// to `min_interval`, the `acceleration` of 1 keeps the interval constant. It's the button of
+/// the scroll bar arrows and the numeric spinners. The skin receives the same `ButtonEvent`s as
+/// the skin of [`Button`].
+///
+/// [`Button`]: super::Button
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct RepeatButton {
+    container: ContainerVisual,
+    core: RwLock<Core>,
+    repeater: Arc<Repeater>,
+    timer: Timer,
+    spawner: Box<dyn Spawn + Send + Sync>,
+    initial_delay: Duration,
+    interval: Duration,
+    min_interval: Duration,
+    acceleration: f32,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct RepeatButtonParams<T: Spawn + Send + Sync + 'static> {
+    compositor: Compositor,
+    #[builder(setter(transform = |skin: impl ButtonSkin + 'static | Arc::new(skin) as Arc<dyn ButtonSkin>))]
+    skin: Arc<dyn ButtonSkin>,
+    spawner: T,
+    #[builder(default = Duration::from_millis(400))]
+    initial_delay: Duration,
+    #[builder(default = Duration::from_millis(100))]
+    interval: Duration,
+    #[builder(default = Duration::from_millis(25))]
+    min_interval: Duration,
+    #[builder(default = 0.9)]
+    acceleration: f32,
+}
+
+impl<T: Spawn + Send + Sync + 'static> TryFrom<RepeatButtonParams<T>> for RepeatButton {
+    type Error = crate::Error;
+
+    fn try_from(value: RepeatButtonParams<T>) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        attach(&container, &*value.skin)?;
+        let repeater = Arc::new(Repeater {
+            hover: AtomicBool::new(false),
+            repeat_button_events: EventStreams::new(),
+        });
+        let timer = Timer::new();
+        spawn_event_pipe(&value.spawner, &timer, repeater.clone(), on_err)?;
+        Ok(RepeatButton {
+            container,
+            core: RwLock::new(Core {
+                skin: value.skin,
+                pressed: false,
+                size: Vector2::default(),
+            }),
+            repeater,
+            timer,
+            spawner: Box::new(value.spawner),
+            initial_delay: value.initial_delay,
+            interval: value.interval,
+            min_interval: value.min_interval,
+            acceleration: value.acceleration,
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl<T: Spawn + Send + Sync + 'static> TryFrom<RepeatButtonParams<T>> for Arc<RepeatButton> {
+    type Error = crate::Error;
+
+    fn try_from(value: RepeatButtonParams<T>) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl RepeatButton {
+    async fn press(&self, core: &mut Core, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        core.pressed = true;
+        core.skin
+            .on_event_owned(ButtonEvent::Press, source.clone())
+            .await?;
+        self.repeater
+            .repeat_button_events
+            .send_event(RepeatButtonEvent::Pressed, source)
+            .await;
+        self.timer.start(
+            &self.spawner,
+            accelerating_delays(
+                self.initial_delay,
+                self.interval,
+                self.min_interval,
+                self.acceleration,
+            ),
+        )
+    }
+
+    async fn release(
+        &self,
+        core: &mut Core,
+        in_slot: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        core.pressed = false;
+        self.timer.stop();
+        core.skin
+            .on_event_owned(ButtonEvent::Release(in_slot), source)
+            .await
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        core.skin.on_event_ref(&event, source.clone()).await?;
+        match &event {
+            PanelEvent::Resized(size) => core.size = *size,
+            PanelEvent::CursorMoved(pos) => {
+                let hover = Rect::from_size(core.size).contains(*pos);
+                if self.repeater.hover.swap(hover, Ordering::SeqCst) != hover {
+                    core.skin
+                        .on_event_owned(ButtonEvent::HoverChanged(hover), source.clone())
+                        .await?;
+                }
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed if *in_slot => self.press(&mut core, source.clone()).await?,
+                ElementState::Released if core.pressed => {
+                    self.release(&mut core, *in_slot, source.clone()).await?
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+        drop(core);
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<RepeatButtonEvent> for RepeatButton {
+    fn event_stream(&self) -> EventStream<RepeatButtonEvent> {
+        self.repeater.repeat_button_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for RepeatButton {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for RepeatButton {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for RepeatButton {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+impl Drop for RepeatButton {
+    fn drop(&mut self) {
+        self.timer.stop();
+    }
+}
@@ -1,6 +1,8 @@
 use std::borrow::Cow;
 
-use super::{attach, is_translated_point_in_box, Panel, PanelEvent};
+use super::{
+    attach, dispatch::DispatchQueue, is_visible, set_visible, EventSeqId, Panel, PanelEvent,
+};
 use async_event_streams::{
     EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
 };
@@ -9,11 +11,13 @@ use async_std::sync::{Arc, RwLock};
 use async_trait::async_trait;
 use typed_builder::TypedBuilder;
 use windows::{
-    Foundation::Numerics::{Vector2, Vector3},
+    Foundation::Numerics::Vector2,
     UI::Composition::{Compositor, ContainerVisual, Visual},
 };
 use winit::event::{ElementState, MouseButton};
 
+use crate::geometry::{Length, Point, Rect, Size};
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum RibbonOrientation {
     Stack,
@@ -27,6 +31,9 @@ pub struct CellLimit {
     pub min_size: f32,
     pub max_size: Option<f32>,
     pub content_ratio: Vector2,
+    /// Size of the cell along the ribbon. `Length::Auto` cells share the space left
+    /// from the fixed and relative ones according to `ratio`
+    pub length: Length,
 }
 
 impl CellLimit {
@@ -42,6 +49,14 @@ impl CellLimit {
             min_size,
             max_size,
             content_ratio,
+            length: Length::Auto,
+        }
+    }
+
+    pub fn with_length(length: impl Into<Length>) -> Self {
+        Self {
+            length: length.into(),
+            ..Default::default()
         }
     }
 
@@ -49,6 +64,19 @@ impl CellLimit {
         self.min_size = size;
         self.max_size = Some(size);
     }
+
+    // Fixes min and max sizes for non-auto length, keeping them in original min..max range
+    fn resolve(&self, target: f32) -> CellLimit {
+        let mut limit = *self;
+        if let Some(size) = self.length.try_resolve(target) {
+            let size = match self.max_size {
+                Some(max_size) => size.min(max_size),
+                None => size,
+            };
+            limit.set_size(size.max(self.min_size));
+        }
+        limit
+    }
 }
 
 impl Default for CellLimit {
@@ -58,6 +86,7 @@ impl Default for CellLimit {
             min_size: 0.,
             max_size: None,
             content_ratio: Vector2::new(1., 1.),
+            length: Length::Auto,
         }
     }
 }
@@ -84,23 +113,24 @@ impl Cell {
             limit,
         })
     }
-    fn translate_point(&self, mut point: Vector2) -> crate::Result<Vector2> {
-        let offset = self.container.Offset()?;
-        point.X -= offset.X;
-        point.Y -= offset.Y;
-        Ok(point)
+    fn rect(&self) -> crate::Result<Rect> {
+        Ok(Rect::new(
+            self.container.Offset()?.into(),
+            self.container.Size()?.into(),
+        ))
+    }
+    fn translate_point(&self, point: Vector2) -> crate::Result<Vector2> {
+        Ok(self.rect()?.to_local(point).into())
     }
     fn is_translated_point_in_cell(&self, point: Vector2) -> crate::Result<bool> {
-        let size = self.container.Size()?;
-        Ok(is_translated_point_in_box(point, size))
-    }
-    fn resize(&mut self, offset: Vector2, size: Vector2) -> crate::Result<()> {
-        self.container.SetOffset(Vector3 {
-            X: offset.X,
-            Y: offset.Y,
-            Z: 0.,
-        })?;
-        self.container.SetSize(size)?;
+        Ok(Rect::from_size(self.container.Size()?).contains(point))
+    }
+    fn is_visible(&self) -> crate::Result<bool> {
+        is_visible(&*self.panel)
+    }
+    fn resize(&mut self, rect: Rect) -> crate::Result<()> {
+        self.container.SetOffset(rect.origin.into())?;
+        self.container.SetSize(rect.size.into())?;
         Ok(())
     }
 }
@@ -115,6 +145,7 @@ struct Core {
     orientation: RibbonOrientation,
     cells: Vec<Cell>,
     mouse_pos: Option<Vector2>,
+    collapse_hidden: bool,
 }
 
 impl Core {
@@ -139,6 +170,7 @@ pub struct Ribbon {
     ribbon_container: ContainerVisual,
     core: RwLock<Core>,
     panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
     id: Arc<()>,
 }
 
@@ -148,6 +180,9 @@ pub struct RibbonParams {
     orientation: RibbonOrientation,
     #[builder(default)]
     cells: Vec<Cell>,
+    /// Hidden panels take no space, the others share it
+    #[builder(default = false)]
+    collapse_hidden: bool,
 }
 
 impl RibbonParams {
@@ -171,12 +206,14 @@ impl TryFrom<RibbonParams> for Ribbon {
             orientation: value.orientation,
             cells: value.cells,
             mouse_pos: None,
+            collapse_hidden: value.collapse_hidden,
         });
         Ok(Ribbon {
             compositor: value.compositor,
             ribbon_container,
             core,
             panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
             id: Arc::new(()),
         })
     }
@@ -200,47 +237,61 @@ impl Ribbon {
         self.resize_cells(self.ribbon_container.Size()?).await?;
         Ok(())
     }
+    ///
+    /// Shows or hides the panel with the given id, see [`set_visible`]. Returns false if there
+    /// is no such panel in the ribbon.
+    ///
+    pub async fn set_panel_visible(&self, id: usize, visible: bool) -> crate::Result<bool> {
+        let (cell, collapse_hidden) = {
+            let core = self.core.read().await;
+            let cell = core.cells.iter().find(|c| c.panel.id() == id).cloned();
+            (cell, core.collapse_hidden)
+        };
+        let cell = match cell {
+            Some(cell) => cell,
+            None => return Ok(false),
+        };
+        set_visible(&*cell.panel, visible).await?;
+        if collapse_hidden {
+            self.translate_panel_event_resized(self.ribbon_container.Size()?, None)
+                .await?;
+        }
+        Ok(true)
+    }
     async fn resize_cells(&self, size: Vector2) -> crate::Result<()> {
         self.ribbon_container.SetSize(size)?;
-        let (orientation, mut cells) = {
+        let (orientation, mut cells, collapse_hidden) = {
             let v = self.core.read().await;
-            (v.orientation(), v.cells())
+            (v.orientation(), v.cells(), v.collapse_hidden)
         };
+        let bounds = Rect::from_size(size);
         if orientation == RibbonOrientation::Stack {
             for cell in &mut cells {
-                let content_size = size.clone() * cell.limit.content_ratio.clone();
-                let content_offset = Vector2 {
-                    X: (size.X - content_size.X) / 2.,
-                    Y: (size.Y - content_size.Y) / 2.,
-                };
-                cell.resize(content_offset, content_size)?;
+                let ratio = cell.limit.content_ratio;
+                let content_size = Size::new(size.X * ratio.X, size.Y * ratio.Y);
+                cell.resize(bounds.centered(content_size))?;
             }
         } else {
-            let limits = cells.iter().map(|c| c.limit).collect::<Vec<_>>();
             let hor = orientation == RibbonOrientation::Horizontal;
             let target = if hor { size.X } else { size.Y };
+            let mut limits = Vec::with_capacity(cells.len());
+            for cell in &cells {
+                let mut limit = cell.limit.resolve(target);
+                if collapse_hidden && !cell.is_visible()? {
+                    limit.set_size(0.);
+                }
+                limits.push(limit);
+            }
             let sizes = adjust_cells(limits, target);
             let mut pos: f32 = 0.;
-            for i in 0..cells.len() {
-                let size = if hor {
-                    Vector2 {
-                        X: sizes[i],
-                        Y: size.Y,
-                    }
-                } else {
-                    Vector2 {
-                        X: size.X,
-                        Y: sizes[i],
-                    }
-                };
-                let cell = &mut cells[i];
-                let offset = if hor {
-                    Vector2 { X: pos, Y: 0. }
+            for (cell, cell_size) in cells.iter_mut().zip(sizes) {
+                let rect = if hor {
+                    Rect::new(Point::new(pos, 0.), Size::new(cell_size, size.Y))
                 } else {
-                    Vector2 { X: 0., Y: pos }
+                    Rect::new(Point::new(0., pos), Size::new(size.X, cell_size))
                 };
-                cell.resize(offset, size)?;
-                pos += sizes[i];
+                cell.resize(rect)?;
+                pos += cell_size;
             }
         }
         Ok(())
@@ -270,32 +321,54 @@ impl EventSinkExt<PanelEvent> for Ribbon {
         event: Cow<'a, PanelEvent>,
         source: Option<Arc<EventBox>>,
     ) -> crate::Result<()> {
-        match event.as_ref() {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Ribbon {
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
             PanelEvent::Resized(size) => {
                 self.translate_panel_event_resized(*size, source.clone())
                     .await
             }
-            PanelEvent::MouseInput { state, button, .. } => {
-                self.translate_slot_event_mouse_input(*state, *button, source.clone())
-                    .await
+            PanelEvent::MouseInput {
+                state,
+                button,
+                click_count,
+                seq,
+                ..
+            } => {
+                self.translate_slot_event_mouse_input(
+                    *state,
+                    *button,
+                    *click_count,
+                    *seq,
+                    source.clone(),
+                )
+                .await
             }
             PanelEvent::CursorMoved(mouse_pos) => {
                 self.translate_slot_event_cursor_moved(*mouse_pos, source.clone())
                     .await
             }
             _ => {
-                self.translate_panel_event_default(event.as_ref(), source.clone())
+                self.translate_panel_event_default(&event, source.clone())
                     .await
             }
         }?;
-        self.panel_events
-            .send_event(event.into_owned(), source)
-            .await;
+        self.panel_events.send_event(event, source).await;
         Ok(())
     }
-}
 
-impl Ribbon {
     async fn translate_panel_event_default(
         &self,
         event: &PanelEvent,
@@ -304,6 +377,9 @@ impl Ribbon {
         // TODO: run simultaneosuly
         let cells = self.core.read().await.cells();
         for cell in cells {
+            if event.is_input() && !cell.is_visible()? {
+                continue;
+            }
             cell.panel.on_event_ref(event, source.clone()).await?;
         }
         Ok(())
@@ -335,6 +411,9 @@ impl Ribbon {
         // TODO: run simultaneosuly
         let cells = self.core.read().await.cells();
         for cell in cells {
+            if !cell.is_visible()? {
+                continue;
+            }
             let mouse_pos = cell.translate_point(mouse_pos)?;
             cell.panel
                 .on_event_owned(PanelEvent::CursorMoved(mouse_pos), source.clone())
@@ -347,12 +426,17 @@ impl Ribbon {
         &self,
         state: ElementState,
         button: MouseButton,
+        click_count: u32,
+        seq: EventSeqId,
         source: Option<Arc<EventBox>>,
     ) -> crate::Result<()> {
         if let Some(mouse_pos) = self.core.read().await.get_mouse_pos() {
             // TODO: run simultaneosuly
             let cells = self.core.read().await.cells();
             for cell in cells {
+                if !cell.is_visible()? {
+                    continue;
+                }
                 let mouse_pos = cell.translate_point(mouse_pos)?;
                 let in_slot = cell.is_translated_point_in_cell(mouse_pos)?;
                 cell.panel
@@ -361,6 +445,8 @@ impl Ribbon {
                             in_slot,
                             state,
                             button,
+                            click_count,
+                            seq,
                         },
                         source.clone(),
                     )
@@ -0,0 +1,455 @@
+use std::{borrow::Cow, ops::Range};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::{IUnknown, Interface},
+    Foundation::Numerics::{Matrix3x2, Vector2},
+    Win32::{
+        Foundation::BOOL,
+        Graphics::{
+            Direct2D::{
+                Common::{D2D_POINT_2F, D2D_RECT_F},
+                D2D1_BRUSH_PROPERTIES,
+            },
+            DirectWrite::{
+                IDWriteTextLayout, DWRITE_FONT_STYLE_ITALIC, DWRITE_FONT_STYLE_NORMAL,
+                DWRITE_FONT_WEIGHT, DWRITE_HIT_TEST_METRICS, DWRITE_TEXT_METRICS,
+                DWRITE_TEXT_RANGE,
+            },
+        },
+    },
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton};
+
+use crate::window::{dwrite_factory, font_collection, ToWide};
+
+use super::{
+    default_text_rendering, dispatch::DispatchQueue, menu::d2d_color, Font, Panel, PanelEvent,
+    Surface, SurfaceParams, TextLayout, TextRendering,
+};
+
+/// Fragment of the rich text with its own formatting
+#[derive(PartialEq, Clone, Debug)]
+pub struct TextRun {
+    pub text: String,
+    pub font: Font,
+    pub color: Color,
+    pub underline: bool,
+    /// Background color behind the run's glyphs
+    pub highlight: Option<Color>,
+}
+
+impl TextRun {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            font: Font::default(),
+            color: Colors::Black().unwrap(),
+            underline: false,
+            highlight: None,
+        }
+    }
+
+    pub fn with_font(mut self, font: Font) -> Self {
+        self.font = font;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_underline(mut self, underline: bool) -> Self {
+        self.underline = underline;
+        self
+    }
+
+    pub fn with_highlight(mut self, highlight: Color) -> Self {
+        self.highlight = Some(highlight);
+        self
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum RichTextEvent {
+    /// The run with the given index is clicked
+    RunClicked(usize),
+}
+
+struct Core {
+    runs: Vec<TextRun>,
+    layout: TextLayout,
+    size: Vector2,
+    mouse_pos: Option<Vector2>,
+    pressed: Option<usize>,
+}
+
+impl Core {
+    // Positions of the runs in the concatenated text in UTF-16 units
+    fn ranges(&self) -> Vec<Range<u32>> {
+        let mut start = 0;
+        self.runs
+            .iter()
+            .map(|run| {
+                let end = start + run.text.encode_utf16().count() as u32;
+                let range = start..end;
+                start = end;
+                range
+            })
+            .collect()
+    }
+
+    // Layout of all the runs in the panel, without colors which need the device context
+    fn text_layout(&self) -> crate::Result<IDWriteTextLayout> {
+        self.sized_text_layout(self.size)
+    }
+
+    fn sized_text_layout(&self, size: Vector2) -> crate::Result<IDWriteTextLayout> {
+        let base_font = self
+            .runs
+            .first()
+            .map(|v| v.font.clone())
+            .unwrap_or_default();
+        let text_format = base_font.text_format()?;
+        self.layout.apply(&text_format)?;
+        let text: Vec<u16> = self
+            .runs
+            .iter()
+            .flat_map(|v| v.text.encode_utf16())
+            .collect();
+        let text_layout =
+            unsafe { dwrite_factory()?.CreateTextLayout(&text, &text_format, size.X, size.Y) }?;
+        for (run, range) in self.runs.iter().zip(self.ranges()) {
+            let range = DWRITE_TEXT_RANGE {
+                startPosition: range.start,
+                length: range.end - range.start,
+            };
+            let style = if run.font.italic {
+                DWRITE_FONT_STYLE_ITALIC
+            } else {
+                DWRITE_FONT_STYLE_NORMAL
+            };
+            unsafe {
+                text_layout
+                    .SetFontFamilyName(run.font.family.as_str().to_wide().as_pcwstr(), range)?;
+                if let Some(collection) = font_collection(&run.font.family)? {
+                    text_layout.SetFontCollection(&collection, range)?;
+                }
+                text_layout.SetFontSize(run.font.size, range)?;
+                text_layout.SetFontWeight(
+                    DWRITE_FONT_WEIGHT(run.font.weight.clamp(1, 999) as i32),
+                    range,
+                )?;
+                text_layout.SetFontStyle(style, range)?;
+                text_layout.SetUnderline(run.underline, range)?;
+            }
+        }
+        Ok(text_layout)
+    }
+
+    fn run_at(&self, pos: Vector2) -> crate::Result<Option<usize>> {
+        let text_layout = self.text_layout()?;
+        let mut is_trailing = BOOL::default();
+        let mut is_inside = BOOL::default();
+        let mut metrics = DWRITE_HIT_TEST_METRICS::default();
+        unsafe {
+            text_layout.HitTestPoint(pos.X, pos.Y, &mut is_trailing, &mut is_inside, &mut metrics)
+        }?;
+        if !is_inside.as_bool() {
+            return Ok(None);
+        }
+        let position = metrics.textPosition;
+        Ok(self.ranges().iter().position(|v| v.contains(&position)))
+    }
+}
+
+// Rectangles covered by the text range, one per line
+pub(super) fn range_rects(
+    text_layout: &IDWriteTextLayout,
+    range: &Range<u32>,
+) -> crate::Result<Vec<D2D_RECT_F>> {
+    let mut metrics = vec![DWRITE_HIT_TEST_METRICS::default(); 4];
+    loop {
+        let mut count = 0;
+        let result = unsafe {
+            text_layout.HitTestTextRange(
+                range.start,
+                range.end - range.start,
+                0.,
+                0.,
+                Some(&mut metrics),
+                &mut count,
+            )
+        };
+        match result {
+            Ok(()) => {
+                metrics.truncate(count as usize);
+                break;
+            }
+            // The buffer is too small for the wrapped run, the count is the required size
+            Err(_) if count as usize > metrics.len() => {
+                metrics.resize(count as usize, DWRITE_HIT_TEST_METRICS::default())
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(metrics
+        .iter()
+        .map(|v| D2D_RECT_F {
+            left: v.left,
+            top: v.top,
+            right: v.left + v.width,
+            bottom: v.top + v.height,
+        })
+        .collect())
+}
+
+///
+/// Block of text flowed from the runs with different fonts, colors, underline and highlight.
+/// Clicking the text reports the index of the run under the cursor, e.g. to follow the link
+/// made of the underlined run.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct RichText {
+    surface: Arc<Surface>,
+    rendering: TextRendering,
+    core: RwLock<Core>,
+    rich_text_events: EventStreams<RichTextEvent>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct RichTextParams {
+    compositor: Compositor,
+    #[builder(default)]
+    runs: Vec<TextRun>,
+    #[builder(default)]
+    layout: TextLayout,
+    /// Rendering options, the global default if not set
+    #[builder(default, setter(strip_option))]
+    rendering: Option<TextRendering>,
+}
+
+impl RichTextParams {
+    pub fn push_run(mut self, run: TextRun) -> Self {
+        self.runs.push(run);
+        self
+    }
+}
+
+impl TryFrom<RichTextParams> for RichText {
+    type Error = crate::Error;
+
+    fn try_from(value: RichTextParams) -> crate::Result<Self> {
+        let surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(value.compositor)
+            .build()
+            .try_into()?;
+        Ok(RichText {
+            surface,
+            rendering: value.rendering.unwrap_or_else(default_text_rendering),
+            core: RwLock::new(Core {
+                runs: value.runs,
+                layout: value.layout,
+                size: Vector2::default(),
+                mouse_pos: None,
+                pressed: None,
+            }),
+            rich_text_events: EventStreams::new(),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<RichTextParams> for Arc<RichText> {
+    type Error = crate::Error;
+
+    fn try_from(value: RichTextParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl RichText {
+    pub async fn runs(&self) -> Vec<TextRun> {
+        self.core.read().await.runs.clone()
+    }
+
+    pub async fn set_runs(&self, runs: Vec<TextRun>) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        core.runs = runs;
+        core.pressed = None;
+        self.redraw(&core)
+    }
+
+    pub async fn layout(&self) -> TextLayout {
+        self.core.read().await.layout
+    }
+
+    pub async fn set_layout(&self, layout: TextLayout) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        if core.layout != layout {
+            core.layout = layout;
+            self.redraw(&core)?;
+        }
+        Ok(())
+    }
+
+    /// Height of the text laid out in the given width, e.g. to size the panel by its content
+    pub async fn text_height(&self, width: f32) -> crate::Result<f32> {
+        let text_layout = self.core.read().await.sized_text_layout(Vector2 {
+            X: width,
+            Y: f32::MAX,
+        })?;
+        let mut metrics = DWRITE_TEXT_METRICS::default();
+        unsafe { text_layout.GetMetrics(&mut metrics) }?;
+        Ok(metrics.height)
+    }
+
+    /// Index of the run under the point in the panel's coordinates
+    pub async fn run_at(&self, pos: Vector2) -> crate::Result<Option<usize>> {
+        self.core.read().await.run_at(pos)
+    }
+
+    fn redraw(&self, core: &Core) -> crate::Result<()> {
+        self.surface.draw(|context, _| {
+            unsafe { context.Clear(Some(&d2d_color(Colors::Transparent()?))) };
+            let text_layout = core.text_layout()?;
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            for (run, range) in core.runs.iter().zip(core.ranges()) {
+                let brush = unsafe {
+                    context.CreateSolidColorBrush(&d2d_color(run.color), Some(&brush_properties))
+                }?;
+                // The text layout draws the run with the brush set as its drawing effect
+                let effect: IUnknown = brush.cast()?;
+                let text_range = DWRITE_TEXT_RANGE {
+                    startPosition: range.start,
+                    length: range.end - range.start,
+                };
+                unsafe { text_layout.SetDrawingEffect(&effect, text_range) }?;
+                if let Some(highlight) = run.highlight {
+                    let brush = unsafe {
+                        context
+                            .CreateSolidColorBrush(&d2d_color(highlight), Some(&brush_properties))
+                    }?;
+                    for rect in range_rects(&text_layout, &range)? {
+                        unsafe { context.FillRectangle(&rect, &brush) };
+                    }
+                }
+            }
+            unsafe { context.SetTextAntialiasMode(self.rendering.antialias.into()) };
+            let default_brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(Colors::Black()?), Some(&brush_properties))
+            }?;
+            unsafe {
+                context.DrawTextLayout(
+                    D2D_POINT_2F { x: 0., y: 0. },
+                    &text_layout,
+                    &default_brush,
+                    self.rendering.draw_text_options(),
+                )
+            };
+            Ok(())
+        })
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => {
+                self.surface.on_event_ref(&event, source.clone()).await?;
+                let mut core = self.core.write().await;
+                core.size = *size;
+                self.redraw(&core)?;
+            }
+            PanelEvent::CursorMoved(pos) => self.core.write().await.mouse_pos = Some(*pos),
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let click = {
+                    let mut core = self.core.write().await;
+                    let run = match core.mouse_pos {
+                        Some(pos) if *in_slot => core.run_at(pos)?,
+                        _ => None,
+                    };
+                    match state {
+                        ElementState::Pressed => {
+                            core.pressed = run;
+                            None
+                        }
+                        ElementState::Released => core.pressed.take().filter(|v| run == Some(*v)),
+                    }
+                };
+                if let Some(index) = click {
+                    self.rich_text_events
+                        .send_event(RichTextEvent::RunClicked(index), source.clone())
+                        .await;
+                }
+            }
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<RichTextEvent> for RichText {
+    fn event_stream(&self) -> EventStream<RichTextEvent> {
+        self.rich_text_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for RichText {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for RichText {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for RichText {
+    fn outer_frame(&self) -> Visual {
+        self.surface.outer_frame()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,263 @@
+use std::{borrow::Cow, time::Duration};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::HSTRING,
+    Foundation::{Numerics::Vector2, TypedEventHandler},
+    Graphics::Effects::IGraphicsEffect,
+    UI::{
+        Color,
+        Composition::{CompositionBatchTypes, Compositor, ContainerVisual, SpriteVisual, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton};
+
+use crate::color;
+
+use super::{
+    animation_stepper::start_animation,
+    dispatch::DispatchQueue,
+    effect_graph::{source_parameter, D2D1Effect},
+    time_span, Panel, PanelEvent,
+};
+
+const BACKDROP: &str = "Backdrop";
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ScrimEvent {
+    /// The scrim is clicked, the modal surface over it should be closed
+    Dismissed,
+}
+
+struct Core {
+    pressed: bool,
+}
+
+///
+/// Dimming layer placed under modal surfaces. The content beneath is covered with the
+/// translucent color and optionally blurred. The scrim fades in and out, the click on it
+/// sends `ScrimEvent::Dismissed` if `dismiss_on_click` is set.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Scrim {
+    compositor: Compositor,
+    container: ContainerVisual,
+    layers: Vec<SpriteVisual>,
+    fade_duration: Duration,
+    dismiss_on_click: bool,
+    core: RwLock<Core>,
+    scrim_events: EventStreams<ScrimEvent>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ScrimParams {
+    compositor: Compositor,
+    #[builder(default = color::from_argb(0x66000000))]
+    color: Color,
+    /// Standard deviation of the backdrop blur in pixels, no blur if not set
+    #[builder(default, setter(strip_option))]
+    blur: Option<f32>,
+    /// Zero duration disables the animations
+    #[builder(default = Duration::from_millis(150))]
+    fade_duration: Duration,
+    #[builder(default = false)]
+    dismiss_on_click: bool,
+}
+
+impl TryFrom<ScrimParams> for Scrim {
+    type Error = crate::Error;
+
+    fn try_from(value: ScrimParams) -> crate::Result<Self> {
+        let compositor = value.compositor;
+        let container = compositor.CreateContainerVisual()?;
+        let mut layers = Vec::new();
+        if let Some(deviation) = value.blur {
+            let effect: IGraphicsEffect =
+                D2D1Effect::gaussian_blur("Blur", source_parameter(BACKDROP)?, deviation)?.into();
+            let brush = compositor.CreateEffectFactory(&effect)?.CreateBrush()?;
+            brush
+                .SetSourceParameter(&HSTRING::from(BACKDROP), &compositor.CreateBackdropBrush()?)?;
+            let blur = compositor.CreateSpriteVisual()?;
+            blur.SetBrush(&brush)?;
+            layers.push(blur);
+        }
+        let dim = compositor.CreateSpriteVisual()?;
+        dim.SetBrush(&compositor.CreateColorBrushWithColor(value.color)?)?;
+        layers.push(dim);
+        for layer in &layers {
+            container.Children()?.InsertAtTop(layer)?;
+        }
+        Ok(Scrim {
+            compositor,
+            container,
+            layers,
+            fade_duration: value.fade_duration,
+            dismiss_on_click: value.dismiss_on_click,
+            core: RwLock::new(Core { pressed: false }),
+            scrim_events: EventStreams::new(),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ScrimParams> for Arc<Scrim> {
+    type Error = crate::Error;
+
+    fn try_from(value: ScrimParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Scrim {
+    /// Fades the scrim in
+    pub fn show(&self) -> crate::Result<()> {
+        self.container.SetIsVisible(true)?;
+        self.fade(0., 1.)?;
+        Ok(())
+    }
+
+    /// Fades the scrim out and hides it
+    pub fn hide(&self) -> crate::Result<()> {
+        if self.fade_duration.is_zero() {
+            self.container.SetIsVisible(false)?;
+            return Ok(());
+        }
+        let batch = self
+            .compositor
+            .CreateScopedBatch(CompositionBatchTypes::Animation)?;
+        self.fade(1., 0.)?;
+        batch.End()?;
+        let container = self.container.clone();
+        batch.Completed(&TypedEventHandler::new(move |_, _| {
+            // The scrim may be shown again while fading out
+            if container.Opacity()? == 0. {
+                container.SetIsVisible(false)?;
+            }
+            Ok(())
+        }))?;
+        Ok(())
+    }
+
+    fn fade(&self, from: f32, to: f32) -> crate::Result<()> {
+        self.container.SetOpacity(to)?;
+        if !self.fade_duration.is_zero() {
+            let animation = self.compositor.CreateScalarKeyFrameAnimation()?;
+            animation.InsertKeyFrame(0., from)?;
+            animation.InsertKeyFrame(1., to)?;
+            animation.SetDuration(time_span(self.fade_duration))?;
+            start_animation(&self.container, "Opacity", &animation)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Tracks the click on the scrim, returns true and sends `ScrimEvent::Dismissed` when
+    /// the click dismisses the modal surface. Modal surfaces call it with `in_slot` cleared
+    /// when the cursor is over their content.
+    ///
+    pub async fn mouse_input(
+        &self,
+        in_slot: bool,
+        state: ElementState,
+        button: MouseButton,
+    ) -> crate::Result<bool> {
+        if button != MouseButton::Left {
+            return Ok(false);
+        }
+        let dismissed = {
+            let mut core = self.core.write().await;
+            match state {
+                ElementState::Pressed => {
+                    core.pressed = in_slot;
+                    false
+                }
+                ElementState::Released => std::mem::take(&mut core.pressed) && in_slot,
+            }
+        };
+        let dismissed = dismissed && self.dismiss_on_click;
+        if dismissed {
+            self.scrim_events
+                .send_event(ScrimEvent::Dismissed, None)
+                .await;
+        }
+        Ok(dismissed)
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size)?,
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                ..
+            } => {
+                self.mouse_input(*in_slot, *state, *button).await?;
+            }
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+
+    fn resize(&self, size: Vector2) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        for layer in &self.layers {
+            layer.SetSize(size)?;
+        }
+        Ok(())
+    }
+}
+
+impl EventSource<ScrimEvent> for Scrim {
+    fn event_stream(&self) -> EventStream<ScrimEvent> {
+        self.scrim_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for Scrim {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Scrim {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Scrim {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,168 @@
+//!
+//! Ordering of events entering the panel tree.
+//!
+//! Every event posted by a window gets a monotonic sequence id, returned by
+//! [`WindowEventSender::try_send`]. The input events carry it in their `seq` field, see
+//! [`PanelEvent::seq`], so the handlers can correlate them with the ids passed to
+//! [`EventSequencer::flush`]. The events made by the panels themselves have 0. The ids
+//! are assigned and the events are queued under one lock, so the ids grow in the queue order
+//! even when several threads post events. The window event receiver
+//! delivers events to the root panel strictly in the order of their ids, and the next event
+//! is not delivered until `on_event` of the root panel returns for the previous one. Containers
+//! forward events to their children inside their own `on_event`, so when the root panel returns,
+//! the whole tree has processed the event synchronously.
+//!
+//! Subscribers of panels' event streams receive events in the same order, but asynchronously:
+//! the barrier below only covers processing done inside `on_event` calls.
+//!
+use std::sync::{Arc, Mutex};
+
+use futures::channel::{
+    mpsc::{Sender, TrySendError},
+    oneshot,
+};
+use winit::event::WindowEvent;
+
+use super::{PanelEvent, WindowServices};
+
+/// Sequence id of the event posted to the panel tree. First event gets id 1.
+pub type EventSeqId = u64;
+
+#[derive(Default)]
+struct SequencerState {
+    posted: EventSeqId,
+    processed: EventSeqId,
+    // The receiver has stopped, the events not processed yet never will be
+    closed: bool,
+    waiters: Vec<(EventSeqId, oneshot::Sender<()>)>,
+}
+
+#[derive(Clone, Default)]
+pub struct EventSequencer {
+    state: Arc<Mutex<SequencerState>>,
+}
+
+impl EventSequencer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Stamps the event with the next id and queues it. The id is taken only if the event is
+    // queued, so the waiters for it are not left without the event.
+    fn post(
+        &self,
+        tx: &mut Sender<(EventSeqId, PanelEvent)>,
+        mut event: PanelEvent,
+    ) -> Result<EventSeqId, TrySendError<(EventSeqId, PanelEvent)>> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.posted + 1;
+        event.set_seq(id);
+        tx.try_send((id, event))?;
+        state.posted = id;
+        Ok(id)
+    }
+    /// Id of the last event posted to the tree
+    pub fn last_posted(&self) -> EventSeqId {
+        self.state.lock().unwrap().posted
+    }
+    /// Id of the last event completely processed by the tree
+    pub fn last_processed(&self) -> EventSeqId {
+        self.state.lock().unwrap().processed
+    }
+    pub(crate) fn set_processed(&self, id: EventSeqId) {
+        let ready = {
+            let mut state = self.state.lock().unwrap();
+            state.processed = state.processed.max(id);
+            let processed = state.processed;
+            let (ready, pending) = state.waiters.drain(..).partition(|(v, _)| *v <= processed);
+            state.waiters = pending;
+            ready
+        };
+        for (_, tx) in ready {
+            let _ = tx.send(());
+        }
+    }
+    // Called when the receiver stops, the waiting `flush` calls fail
+    pub(crate) fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        // Dropping the senders cancels the receivers
+        state.waiters.clear();
+    }
+    ///
+    /// Wait until all events up to `id` inclusive are processed by the tree. Fails with
+    /// [`crate::Error::EventReceiverStopped`] if the receiver stops before, e.g. when the window
+    /// is closed or the root panel returns the error.
+    ///
+    pub async fn flush(&self, id: EventSeqId) -> crate::Result<()> {
+        let rx = {
+            let mut state = self.state.lock().unwrap();
+            if state.processed >= id {
+                return Ok(());
+            }
+            if state.closed {
+                return Err(crate::Error::EventReceiverStopped);
+            }
+            let (tx, rx) = oneshot::channel();
+            state.waiters.push((id, tx));
+            rx
+        };
+        rx.await.map_err(|_| crate::Error::EventReceiverStopped)
+    }
+    /// Wait until all events posted so far are processed by the tree
+    pub async fn settle(&self) -> crate::Result<()> {
+        self.flush(self.last_posted()).await
+    }
+}
+
+// Closes the sequencer when the receiver task ends by any way, including the error
+pub(crate) struct CloseOnDrop(pub(crate) EventSequencer);
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        self.0.close()
+    }
+}
+
+///
+/// Sending side of the window event channel. Assigns sequence ids to posted events. The
+/// window's pointer capture, cursors and the other singletons are in its [`WindowServices`].
+///
+#[derive(Clone)]
+pub struct WindowEventSender {
+    tx: Sender<(EventSeqId, PanelEvent)>,
+    sequencer: EventSequencer,
+    services: WindowServices,
+}
+
+impl WindowEventSender {
+    pub(crate) fn new(
+        tx: Sender<(EventSeqId, PanelEvent)>,
+        sequencer: EventSequencer,
+        services: WindowServices,
+    ) -> Self {
+        Self {
+            tx,
+            sequencer,
+            services,
+        }
+    }
+    pub fn try_send(
+        &mut self,
+        event: WindowEvent<'static>,
+    ) -> Result<EventSeqId, TrySendError<(EventSeqId, PanelEvent)>> {
+        self.try_send_panel_event(event.into())
+    }
+    /// Posts the event which has no winit counterpart, like files dropped from the shell
+    pub fn try_send_panel_event(
+        &mut self,
+        event: PanelEvent,
+    ) -> Result<EventSeqId, TrySendError<(EventSeqId, PanelEvent)>> {
+        self.sequencer.post(&mut self.tx, event)
+    }
+    pub fn sequencer(&self) -> &EventSequencer {
+        &self.sequencer
+    }
+    pub fn services(&self) -> &WindowServices {
+        &self.services
+    }
+}
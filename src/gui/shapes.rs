@@ -0,0 +1,333 @@
+use std::{borrow::Cow, f32::consts::PI};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color,
+        Composition::{
+            CompositionBrush, CompositionGeometry, CompositionSpriteShape, CompositionStrokeCap,
+            Compositor, ShapeVisual, Visual,
+        },
+    },
+};
+
+#[cfg(feature = "accessibility")]
+use super::accessibility::{set_text_alternative, TextAlternative};
+use super::{DashStyle, Panel, PanelEvent, PathBuilder};
+
+/// Fill or stroke of the [`Shape`]
+#[derive(PartialEq, Clone, Debug)]
+pub enum ShapeBrush {
+    Color(Color),
+    /// Any composition brush, e.g. the gradient
+    Brush(CompositionBrush),
+}
+
+impl From<Color> for ShapeBrush {
+    fn from(value: Color) -> Self {
+        ShapeBrush::Color(value)
+    }
+}
+
+impl From<CompositionBrush> for ShapeBrush {
+    fn from(value: CompositionBrush) -> Self {
+        ShapeBrush::Brush(value)
+    }
+}
+
+impl ShapeBrush {
+    fn create_brush(&self, compositor: &Compositor) -> crate::Result<CompositionBrush> {
+        match self {
+            ShapeBrush::Color(color) => Ok(compositor.CreateColorBrushWithColor(*color)?.into()),
+            ShapeBrush::Brush(brush) => Ok(brush.clone()),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+enum Geometry {
+    Line(Vector2, Vector2),
+    Ellipse(Vector2, Vector2),
+    Path(PathBuilder),
+}
+
+impl Geometry {
+    fn create_geometry(&self, compositor: &Compositor) -> crate::Result<CompositionGeometry> {
+        match self {
+            Geometry::Line(from, to) => {
+                let line = compositor.CreateLineGeometry()?;
+                line.SetStart(*from)?;
+                line.SetEnd(*to)?;
+                Ok(line.into())
+            }
+            Geometry::Ellipse(center, radius) => {
+                let ellipse = compositor.CreateEllipseGeometry()?;
+                ellipse.SetCenter(*center)?;
+                ellipse.SetRadius(*radius)?;
+                Ok(ellipse.into())
+            }
+            Geometry::Path(path) => Ok(path.geometry(compositor)?.into()),
+        }
+    }
+}
+
+///
+/// Figure drawn by the [`Shapes`] panel, in pixels from the panel's top left corner. The shape
+/// without fill and stroke is invisible, the open figures like lines are usually stroked:
+///
+/// ```ignore
+/// let diagonal = Shape::line(point(0., 0.), point(100., 100.)).with_stroke(color, 2.);
+/// ```
+///
+#[derive(PartialEq, Clone, Debug)]
+pub struct Shape {
+    geometry: Geometry,
+    fill: Option<ShapeBrush>,
+    stroke: Option<ShapeBrush>,
+    stroke_thickness: f32,
+    dash: DashStyle,
+}
+
+impl Shape {
+    fn new(geometry: Geometry) -> Self {
+        Self {
+            geometry,
+            fill: None,
+            stroke: None,
+            stroke_thickness: 1.,
+            dash: DashStyle::Solid,
+        }
+    }
+
+    pub fn line(from: Vector2, to: Vector2) -> Self {
+        Self::new(Geometry::Line(from, to))
+    }
+
+    /// Lines connecting the points
+    pub fn polyline(points: &[Vector2]) -> Self {
+        Self::path(PathBuilder::polyline(points))
+    }
+
+    /// Polyline closed by the line from the last point to the first
+    pub fn polygon(points: &[Vector2]) -> Self {
+        Self::path(PathBuilder::polyline(points).close())
+    }
+
+    /// Cubic Bézier curve
+    pub fn bezier(from: Vector2, control1: Vector2, control2: Vector2, to: Vector2) -> Self {
+        Self::path(
+            PathBuilder::new()
+                .move_to(from)
+                .cubic_to(control1, control2, to),
+        )
+    }
+
+    pub fn ellipse(center: Vector2, radius: Vector2) -> Self {
+        Self::new(Geometry::Ellipse(center, radius))
+    }
+
+    ///
+    /// Part of the ellipse's outline from `start` degrees, clockwise for the positive `sweep`.
+    /// The angles are counted from the positive X axis, as the angles of the rotation.
+    ///
+    pub fn arc(center: Vector2, radius: Vector2, start: f32, sweep: f32) -> Self {
+        if sweep.abs() >= 360. {
+            return Self::ellipse(center, radius);
+        }
+        let point = |angle: f32| {
+            let angle = angle * PI / 180.;
+            Vector2 {
+                X: center.X + radius.X * angle.cos(),
+                Y: center.Y + radius.Y * angle.sin(),
+            }
+        };
+        Self::path(PathBuilder::new().move_to(point(start)).arc_to(
+            point(start + sweep),
+            radius,
+            0.,
+            sweep.abs() > 180.,
+            sweep > 0.,
+        ))
+    }
+
+    /// Arbitrary outline of lines, Béziers and arcs
+    pub fn path(path: PathBuilder) -> Self {
+        Self::new(Geometry::Path(path))
+    }
+
+    pub fn with_fill(self, fill: impl Into<ShapeBrush>) -> Self {
+        Self {
+            fill: Some(fill.into()),
+            ..self
+        }
+    }
+
+    /// Outline centered on the shape's edge
+    pub fn with_stroke(self, stroke: impl Into<ShapeBrush>, thickness: f32) -> Self {
+        Self {
+            stroke: Some(stroke.into()),
+            stroke_thickness: thickness,
+            ..self
+        }
+    }
+
+    pub fn with_dash(self, dash: DashStyle) -> Self {
+        Self { dash, ..self }
+    }
+
+    fn create_shape(&self, compositor: &Compositor) -> crate::Result<CompositionSpriteShape> {
+        let geometry = self.geometry.create_geometry(compositor)?;
+        let shape = compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        if let Some(fill) = &self.fill {
+            shape.SetFillBrush(&fill.create_brush(compositor)?)?;
+        }
+        if let Some(stroke) = &self.stroke {
+            shape.SetStrokeBrush(&stroke.create_brush(compositor)?)?;
+            shape.SetStrokeThickness(self.stroke_thickness)?;
+            let dash_array = shape.StrokeDashArray()?;
+            for length in self.dash.dash_array() {
+                dash_array.Append(*length)?;
+            }
+            if self.dash != DashStyle::Solid {
+                shape.SetStrokeDashCap(CompositionStrokeCap::Round)?;
+            }
+        }
+        Ok(shape)
+    }
+}
+
+struct Core {
+    compositor: Compositor,
+    container: ShapeVisual,
+    shapes: Vec<Shape>,
+}
+
+impl Core {
+    // Recreates the composition shapes, later shapes are drawn over the earlier ones
+    fn redraw(&self) -> crate::Result<()> {
+        let shapes = self.container.Shapes()?;
+        shapes.Clear()?;
+        for shape in &self.shapes {
+            shapes.Append(&shape.create_shape(&self.compositor)?)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Retained vector canvas drawing the lines, Béziers, ellipses, arcs and arbitrary paths by
+/// the compositor, without the Direct2D drawing code and the surface to redraw on resize. The
+/// shapes keep their pixel coordinates when the panel is resized, the parts outside of the
+/// panel are cut off.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Shapes {
+    container: ShapeVisual,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ShapesParams {
+    compositor: Compositor,
+    /// Shapes in the drawing order, none by default
+    #[builder(default)]
+    shapes: Vec<Shape>,
+}
+
+impl TryFrom<ShapesParams> for Shapes {
+    type Error = crate::Error;
+
+    fn try_from(value: ShapesParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateShapeVisual()?;
+        let core = Core {
+            compositor: value.compositor,
+            container: container.clone(),
+            shapes: value.shapes,
+        };
+        core.redraw()?;
+        Ok(Shapes {
+            container,
+            core: RwLock::new(core),
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ShapesParams> for Arc<Shapes> {
+    type Error = crate::Error;
+
+    fn try_from(value: ShapesParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Shapes {
+    pub async fn shapes(&self) -> Vec<Shape> {
+        self.core.read().await.shapes.clone()
+    }
+    pub async fn set_shapes(&self, shapes: Vec<Shape>) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        core.shapes = shapes;
+        core.redraw()
+    }
+    /// Adds the shape over the others
+    pub async fn push(&self, shape: Shape) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        let composition_shape = shape.create_shape(&core.compositor)?;
+        core.container.Shapes()?.Append(&composition_shape)?;
+        core.shapes.push(shape);
+        Ok(())
+    }
+    pub async fn clear(&self) -> crate::Result<()> {
+        self.set_shapes(Vec::new()).await
+    }
+    /// Describes the drawing for the screen readers, e.g. the icon or the chart
+    #[cfg(feature = "accessibility")]
+    pub fn set_text_alternative(&self, alternative: &TextAlternative) -> crate::Result<()> {
+        set_text_alternative(&self.container.clone().into(), "Shapes", alternative)
+    }
+}
+
+impl Panel for Shapes {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+impl EventSource<PanelEvent> for Shapes {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Shapes {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.container.SetSize(*size)?;
+        }
+        self.panel_events
+            .send_event(event.into_owned(), source)
+            .await;
+        Ok(())
+    }
+}
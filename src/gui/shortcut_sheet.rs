@@ -0,0 +1,358 @@
+use std::{borrow::Cow, sync::Weak};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::{Matrix3x2, Vector2},
+    Win32::Graphics::{
+        Direct2D::{
+            Common::D2D_RECT_F, ID2D1SolidColorBrush, D2D1_BRUSH_PROPERTIES,
+            D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+        },
+        DirectWrite::{
+            IDWriteTextFormat, DWRITE_MEASURING_MODE_NATURAL, DWRITE_TEXT_ALIGNMENT_TRAILING,
+        },
+    },
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, ShapeVisual, Visual},
+    },
+};
+use winit::event::{ElementState, VirtualKeyCode};
+
+use crate::{
+    geometry::{Point, Rect, Size},
+    window::ToWide,
+};
+
+use super::{
+    attach,
+    dispatch::DispatchQueue,
+    menu::{d2d_color, sized_text_format, text_format, Label},
+    Accelerator, Command, CommandRegistry, EventFilter, OverlayHost, Panel, PanelEvent, Placement,
+    Surface, SurfaceParams,
+};
+
+const ROW_HEIGHT: f32 = 28.;
+const HEADER_HEIGHT: f32 = 36.;
+const PADDING: f32 = 16.;
+// Group of the commands registered without category
+const DEFAULT_CATEGORY: &str = "General";
+
+enum Row {
+    Header(String),
+    Command { text: String, shortcut: String },
+}
+
+impl Row {
+    fn height(&self) -> f32 {
+        match self {
+            Row::Header(_) => HEADER_HEIGHT,
+            Row::Command { .. } => ROW_HEIGHT,
+        }
+    }
+}
+
+// Rows of the sheet: commands grouped by category in the order the categories first appear
+fn build_rows(commands: Vec<Command>) -> Vec<Row> {
+    let mut groups: Vec<(String, Vec<Command>)> = Vec::new();
+    for command in commands {
+        let category = command
+            .category
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CATEGORY.to_string());
+        match groups.iter_mut().find(|(v, _)| *v == category) {
+            Some((_, group)) => group.push(command),
+            None => groups.push((category, vec![command])),
+        }
+    }
+    let mut rows = Vec::new();
+    for (category, group) in groups {
+        rows.push(Row::Header(category));
+        rows.extend(group.into_iter().map(|command| {
+            Row::Command {
+                // Mnemonic markers are for menus only
+                text: String::from_utf16_lossy(&Label::parse(&command.text).text),
+                shortcut: command
+                    .accelerator
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+            }
+        }));
+    }
+    rows
+}
+
+///
+/// Overlay listing all commands of the [`CommandRegistry`] with their current shortcuts,
+/// grouped by category. The list is rebuilt from the registry each time the sheet is shown.
+/// The sheet is toggled by the chord (Ctrl+/ by default) when the filter returned by
+/// [`ShortcutSheet::toggle_filter`] is added to the window's event filters, and closed by
+/// Escape or click outside.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct ShortcutSheet {
+    container: ContainerVisual,
+    shapes: ShapeVisual,
+    surface: Arc<Surface>,
+    compositor: Compositor,
+    commands: Arc<CommandRegistry>,
+    host: Arc<OverlayHost>,
+    chord: Accelerator,
+    width: f32,
+    background: Color,
+    text_color: Color,
+    shortcut_color: Color,
+    rows: RwLock<Vec<Row>>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ShortcutSheetParams {
+    compositor: Compositor,
+    commands: Arc<CommandRegistry>,
+    host: Arc<OverlayHost>,
+    #[builder(default = Accelerator::ctrl(VirtualKeyCode::Slash))]
+    chord: Accelerator,
+    #[builder(default = 420.)]
+    width: f32,
+    #[builder(default = Colors::WhiteSmoke().unwrap())]
+    background: Color,
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+    #[builder(default = Colors::DimGray().unwrap())]
+    shortcut_color: Color,
+}
+
+impl TryFrom<ShortcutSheetParams> for ShortcutSheet {
+    type Error = crate::Error;
+
+    fn try_from(value: ShortcutSheetParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let shapes = value.compositor.CreateShapeVisual()?;
+        container.Children()?.InsertAtBottom(&shapes)?;
+        let surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(value.compositor.clone())
+            .build()
+            .try_into()?;
+        attach(&container, &*surface)?;
+        Ok(ShortcutSheet {
+            container,
+            shapes,
+            surface,
+            compositor: value.compositor,
+            commands: value.commands,
+            host: value.host,
+            chord: value.chord,
+            width: value.width,
+            background: value.background,
+            text_color: value.text_color,
+            shortcut_color: value.shortcut_color,
+            rows: RwLock::new(Vec::new()),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ShortcutSheetParams> for Arc<ShortcutSheet> {
+    type Error = crate::Error;
+
+    fn try_from(value: ShortcutSheetParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ShortcutSheet {
+    pub fn chord(&self) -> Accelerator {
+        self.chord
+    }
+
+    pub async fn is_open(&self) -> bool {
+        self.host.is_open(self.id()).await
+    }
+
+    /// Rebuilds the list from the command registry and shows it centered in the host
+    pub async fn show(self: &Arc<Self>) -> crate::Result<()> {
+        if self.is_open().await {
+            return Ok(());
+        }
+        let rows = build_rows(self.commands.commands().await);
+        let height = rows.iter().map(|v| v.height()).sum::<f32>() + PADDING * 2.;
+        *self.rows.write().await = rows;
+        let size = Size::new(self.width, height);
+        let bounds = self.host.bounds_of(&*self.host)?;
+        let placement = Placement::at(bounds.centered(size).origin, size);
+        self.host.show_popup(self.clone(), placement, true).await
+    }
+
+    pub async fn close(&self) -> crate::Result<()> {
+        self.host.close_popup(self.id()).await?;
+        Ok(())
+    }
+
+    pub async fn toggle(self: &Arc<Self>) -> crate::Result<()> {
+        if self.is_open().await {
+            self.close().await
+        } else {
+            self.show().await
+        }
+    }
+
+    /// Event filter toggling the sheet on the chord, to be added to the window's filters
+    pub fn toggle_filter(self: &Arc<Self>) -> Arc<dyn EventFilter> {
+        Arc::new(ToggleFilter {
+            sheet: Arc::downgrade(self),
+            chord: self.chord,
+        })
+    }
+
+    fn redraw_shapes(&self, size: Vector2) -> crate::Result<()> {
+        let shapes = self.shapes.Shapes()?;
+        shapes.Clear()?;
+        let geometry = self.compositor.CreateRoundedRectangleGeometry()?;
+        geometry.SetSize(size)?;
+        geometry.SetCornerRadius(Vector2 { X: 8., Y: 8. })?;
+        let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        shape.SetFillBrush(&self.compositor.CreateColorBrushWithColor(self.background)?)?;
+        shapes.Append(&shape)?;
+        Ok(())
+    }
+
+    fn redraw_text(&self, rows: &[Row]) -> crate::Result<()> {
+        self.surface.draw(|context, size| {
+            let header_format = sized_text_format(16.)?;
+            let shortcut_format = text_format()?;
+            let text_format = text_format()?;
+            unsafe { shortcut_format.SetTextAlignment(DWRITE_TEXT_ALIGNMENT_TRAILING)? };
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let text_brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(self.text_color), Some(&brush_properties))
+            }?;
+            let shortcut_brush = unsafe {
+                context
+                    .CreateSolidColorBrush(&d2d_color(self.shortcut_color), Some(&brush_properties))
+            }?;
+            unsafe { context.Clear(Some(&d2d_color(Colors::Transparent()?))) };
+            let mut y = PADDING;
+            for row in rows {
+                let rect = Rect::new(
+                    Point::new(PADDING, y),
+                    Size::new(size.X - PADDING * 2., row.height()),
+                );
+                let layout_rect = D2D_RECT_F {
+                    left: rect.left(),
+                    top: rect.top(),
+                    right: rect.right(),
+                    bottom: rect.bottom(),
+                };
+                let draw =
+                    |text: &str, format: &IDWriteTextFormat, brush: &ID2D1SolidColorBrush| unsafe {
+                        context.DrawText(
+                            text.to_wide().0.as_slice(),
+                            format,
+                            &layout_rect,
+                            brush,
+                            D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT,
+                            DWRITE_MEASURING_MODE_NATURAL,
+                        )
+                    };
+                match row {
+                    Row::Header(text) => draw(text, &header_format, &text_brush),
+                    Row::Command { text, shortcut } => {
+                        draw(text, &text_format, &text_brush);
+                        draw(shortcut, &shortcut_format, &shortcut_brush);
+                    }
+                }
+                y += row.height();
+            }
+            Ok(())
+        })
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = &event {
+            self.container.SetSize(*size)?;
+            self.shapes.SetSize(*size)?;
+            self.surface.on_event_ref(&event, source.clone()).await?;
+            self.redraw_shapes(*size)?;
+            self.redraw_text(&self.rows.read().await)?;
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+// Holds the sheet weakly: the sheet owns nothing of the window, the filter shouldn't keep it
+struct ToggleFilter {
+    sheet: Weak<ShortcutSheet>,
+    chord: Accelerator,
+}
+
+#[async_trait]
+impl EventFilter for ToggleFilter {
+    async fn filter(&self, event: PanelEvent) -> crate::Result<Option<PanelEvent>> {
+        if let PanelEvent::KeyboardInput {
+            state: ElementState::Pressed,
+            key: Some(key),
+            modifiers,
+            ..
+        } = &event
+        {
+            if self.chord.matches(*key, *modifiers) {
+                if let Some(sheet) = self.sheet.upgrade() {
+                    sheet.toggle().await?;
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(event))
+    }
+}
+
+impl EventSource<PanelEvent> for ShortcutSheet {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ShortcutSheet {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for ShortcutSheet {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,278 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use futures::task::Spawn;
+use typed_builder::TypedBuilder;
+use windows::UI::{
+    Color, Colors,
+    Composition::{Compositor, ContainerVisual, SpriteVisual, Visual},
+};
+
+use crate::color::ColorExt;
+
+use super::{
+    attach, button::create_focus_ring, theme::readable_foreground, Background, BackgroundParams,
+    ButtonEvent, Context, CornerRadius, Font, LayerStack, LayerStackParams, Palette, Panel,
+    PanelEvent, PanelFactory, Text, TextParams, Theme,
+};
+
+#[derive(Clone, Copy)]
+struct SkinColors {
+    color: Color,
+    pressed_color: Color,
+    hover_color: Color,
+    disabled_color: Color,
+    // Preferred text color, adjusted to be readable on the background
+    text_color: Color,
+}
+
+impl SkinColors {
+    fn from_palette(palette: &Palette) -> crate::Result<Self> {
+        Ok(Self {
+            color: palette.accent,
+            pressed_color: palette.accent_dark,
+            hover_color: palette.accent_light,
+            disabled_color: palette.accent.lerp(Colors::Gray()?, 0.7),
+            text_color: palette.on_accent,
+        })
+    }
+}
+
+struct State {
+    pressed: bool,
+    hover: bool,
+    enabled: bool,
+    focused: bool,
+    colors: SkinColors,
+}
+
+impl State {
+    fn new(colors: SkinColors) -> Self {
+        Self {
+            pressed: false,
+            hover: false,
+            enabled: true,
+            focused: false,
+            colors,
+        }
+    }
+
+    fn background_color(&self) -> Color {
+        if !self.enabled {
+            self.colors.disabled_color
+        } else if self.pressed {
+            self.colors.pressed_color
+        } else if self.hover {
+            self.colors.hover_color
+        } else {
+            self.colors.color
+        }
+    }
+
+    fn text_color(&self) -> Color {
+        readable_foreground(self.background_color(), self.colors.text_color)
+    }
+}
+
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+#[event_sink(event=ButtonEvent)]
+pub struct SimpleButtonSkin {
+    container: ContainerVisual,
+    layer_stack: LayerStack,
+    // Border over the button shown while it has the keyboard focus
+    focus_ring: SpriteVisual,
+    text: Arc<Text>,
+    background: Arc<Background>,
+    themed: bool,
+    state: RwLock<State>,
+    panel_events: EventStreams<PanelEvent>,
+}
+
+#[derive(TypedBuilder)]
+pub struct SimpleButtonSkinParams<T: Spawn> {
+    compositor: Compositor,
+    text: String,
+    color: Color,
+    #[builder(default = Colors::DarkMagenta().unwrap())]
+    pressed_color: Color,
+    #[builder(default = Colors::Orchid().unwrap())]
+    hover_color: Color,
+    /// Color of the disabled button, `color` grayed out if not set
+    #[builder(default, setter(strip_option))]
+    disabled_color: Option<Color>,
+    /// Preferred color of the text, it's adjusted if it's not readable on the background
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+    /// Radii of the background's corners, the default is proportional to the button size
+    #[builder(default = CornerRadius::relative(0.05), setter(into))]
+    corner_radius: CornerRadius,
+    ///
+    /// Takes the accent colors, the corner radius and the body font size from the [`Theme`]
+    /// on `PanelEvent::ThemeChanged` instead of the colors and the radius given here
+    ///
+    #[builder(default)]
+    themed: bool,
+    /// Context of the parent, the themed skin starts with the [`Theme`] found in it
+    #[builder(default)]
+    context: Context,
+    spawner: T,
+}
+
+impl<T: Spawn + Clone> TryFrom<SimpleButtonSkinParams<T>> for SimpleButtonSkin {
+    type Error = crate::Error;
+    fn try_from(value: SimpleButtonSkinParams<T>) -> crate::Result<Self> {
+        let theme = value.context.get::<Theme>().filter(|_| value.themed);
+        let (colors, corner_radius, font) = match theme {
+            Some(theme) => {
+                let metrics = theme.metrics();
+                let font = Font {
+                    size: metrics.body_font_size,
+                    ..Default::default()
+                };
+                (
+                    SkinColors::from_palette(&theme.palette())?,
+                    CornerRadius::pixels(metrics.corner_radius),
+                    font,
+                )
+            }
+            None => {
+                let disabled_color = match value.disabled_color {
+                    Some(v) => v,
+                    None => value.color.lerp(Colors::Gray()?, 0.7),
+                };
+                let colors = SkinColors {
+                    color: value.color,
+                    pressed_color: value.pressed_color,
+                    hover_color: value.hover_color,
+                    disabled_color,
+                    text_color: value.text_color,
+                };
+                (colors, value.corner_radius, Font::default())
+            }
+        };
+        let factory =
+            PanelFactory::new(value.compositor.clone(), value.spawner).with_context(value.context);
+        let background: Arc<Background> = factory.create_with_compositor(|compositor| {
+            BackgroundParams::builder()
+                .color(colors.color)
+                .corner_radius(corner_radius)
+                .compositor(compositor)
+                .build()
+        })?;
+        let text: Arc<Text> = factory.create(|compositor, spawner| {
+            TextParams::builder()
+                .compositor(compositor)
+                .text(value.text)
+                .font(font)
+                .color(readable_foreground(colors.color, colors.text_color))
+                .spawner(spawner)
+                .build()
+        })?;
+        let layer_stack = LayerStackParams::builder()
+            .compositor(factory.compositor())
+            .build()
+            .push_panel(background.clone())
+            .push_panel(text.clone())
+            .try_into()?;
+        let container = value.compositor.CreateContainerVisual()?;
+        attach(&container, &layer_stack)?;
+        let focus_ring = create_focus_ring(&value.compositor)?;
+        container.Children()?.InsertAtTop(&focus_ring)?;
+        Ok(SimpleButtonSkin {
+            container,
+            layer_stack,
+            focus_ring,
+            background,
+            text,
+            themed: value.themed,
+            state: RwLock::new(State::new(colors)),
+            panel_events: EventStreams::new(),
+        })
+    }
+}
+
+impl<T: Spawn + Clone> TryFrom<SimpleButtonSkinParams<T>> for Arc<SimpleButtonSkin> {
+    type Error = crate::Error;
+
+    fn try_from(value: SimpleButtonSkinParams<T>) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<ButtonEvent> for SimpleButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, ButtonEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let mut state = self.state.write().await;
+        match event.as_ref() {
+            ButtonEvent::Press => state.pressed = true,
+            ButtonEvent::Release(_) => state.pressed = false,
+            ButtonEvent::HoverChanged(hover) => state.hover = *hover,
+            ButtonEvent::EnabledChanged(enabled) => state.enabled = *enabled,
+            ButtonEvent::FocusChanged(focused) => state.focused = *focused,
+        }
+        self.focus_ring.SetIsVisible(state.focused)?;
+        self.background.set_color(state.background_color()).await?;
+        self.text.set_color(state.text_color()).await
+    }
+}
+
+impl SimpleButtonSkin {
+    async fn apply_theme(&self, theme: &Theme) -> crate::Result<()> {
+        let palette = theme.palette();
+        let metrics = theme.metrics();
+        let (color, text_color) = {
+            let mut state = self.state.write().await;
+            state.colors = SkinColors::from_palette(&palette)?;
+            (state.background_color(), state.text_color())
+        };
+        self.background.set_color(color).await?;
+        self.background
+            .set_corner_radius(CornerRadius::pixels(metrics.corner_radius))
+            .await?;
+        self.text.set_color(text_color).await?;
+        self.text.set_font_size(metrics.body_font_size).await
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for SimpleButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match event.as_ref() {
+            PanelEvent::Resized(size) => self.container.SetSize(*size)?,
+            PanelEvent::ThemeChanged(theme) if self.themed => self.apply_theme(theme).await?,
+            _ => (),
+        }
+        self.layer_stack.on_event(event, source).await
+    }
+}
+
+impl EventSource<PanelEvent> for SimpleButtonSkin {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for SimpleButtonSkin {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.text) as usize
+    }
+}
@@ -0,0 +1,256 @@
+use std::path::{Path, PathBuf};
+
+use typed_builder::TypedBuilder;
+use windows::Foundation::Numerics::{Vector2, Vector3};
+
+use crate::window::{capture_visual, Bitmap};
+
+use super::{Panel, PanelEvent, Theme};
+
+/// Environment variable which makes `Snapshot::check` overwrite the stored images
+pub const UPDATE_SNAPSHOTS_VAR: &str = "WAG_UPDATE_SNAPSHOTS";
+
+// Largest possible YIQ distance between two colors
+const MAX_YIQ_DELTA: f32 = 35215.;
+
+/// How much the rendered image may differ from the stored one
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tolerance {
+    /// Perceived color difference of the pixels considered equal, from 0 to 1. The default
+    /// 0.1 ignores the rendering noise like the slightly different antialiasing.
+    pub threshold: f32,
+    /// Number of the different pixels allowed
+    pub max_different_pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            threshold: 0.1,
+            max_different_pixels: 0,
+        }
+    }
+}
+
+/// Result of `compare`
+pub struct Comparison {
+    pub different_pixels: usize,
+    /// The expected image faded to gray with the different pixels painted red
+    pub diff: Bitmap,
+}
+
+// The color blended over the white background by its alpha, as the images are viewed
+fn blend_to_white(pixel: [u8; 4]) -> [f32; 3] {
+    let alpha = pixel[3] as f32 / 255.;
+    let blend = |v: u8| 255. + (v as f32 - 255.) * alpha;
+    [blend(pixel[2]), blend(pixel[1]), blend(pixel[0])]
+}
+
+// Perceived difference of the colors by the YIQ color space distance
+fn yiq_delta(a: [u8; 4], b: [u8; 4]) -> f32 {
+    let yiq = |[r, g, b]: [f32; 3]| {
+        (
+            r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+            r * 0.59597799 - g * 0.27417610 - b * 0.32180189,
+            r * 0.21147017 - g * 0.52261711 + b * 0.31114694,
+        )
+    };
+    let (ya, ia, qa) = yiq(blend_to_white(a));
+    let (yb, ib, qb) = yiq(blend_to_white(b));
+    let (y, i, q) = (ya - yb, ia - ib, qa - qb);
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+///
+/// Compares the images pixel by pixel. The pixels differ if the perceived difference of their
+/// colors exceeds the `threshold` from 0 to 1. If the sizes differ, the pixels present in
+/// only one of the images are different.
+///
+pub fn compare(expected: &Bitmap, actual: &Bitmap, threshold: f32) -> Comparison {
+    let width = expected.width.max(actual.width);
+    let height = expected.height.max(actual.height);
+    let max_delta = MAX_YIQ_DELTA * threshold * threshold;
+    let mut diff = Bitmap::empty(width, height);
+    let mut different_pixels = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let same = match (expected.pixel(x, y), actual.pixel(x, y)) {
+                (Some(e), Some(a)) => yiq_delta(e, a) <= max_delta,
+                _ => false,
+            };
+            let pixel = if same {
+                let [r, g, b] = blend_to_white(expected.pixel(x, y).unwrap_or_default());
+                let gray = (r * 0.299 + g * 0.587 + b * 0.114) as u8;
+                // Faded so the red pixels stand out
+                let faded = 255 - (255 - gray) / 10;
+                [faded, faded, faded, 255]
+            } else {
+                different_pixels += 1;
+                [0, 0, 255, 255]
+            };
+            diff.set_pixel(x, y, pixel);
+        }
+    }
+    Comparison {
+        different_pixels,
+        diff,
+    }
+}
+
+// Path of the file next to the snapshot with the suffix added to the name, e.g. "button.diff.png"
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+///
+/// Golden image test of the panel: renders it without a window at the size, DPI and theme
+/// given and compares the image with the one stored in the PNG file.
+///
+/// ```ignore
+/// Snapshot::builder()
+///     .path("tests/snapshots/button_dark.png")
+///     .size(size!(120, 32))
+///     .dpi(144.)
+///     .theme(Theme::new(Palette::new(accent, ColorScheme::Dark)))
+///     .build()
+///     .check(&*button)
+///     .await?;
+/// ```
+///
+/// The stored image is created on the first run and replaced on the runs with the
+/// `WAG_UPDATE_SNAPSHOTS` environment variable set. On the mismatch the rendered image and
+/// the diff are written next to the stored one as "name.actual.png" and "name.diff.png".
+///
+/// The panel must not be attached to the tree. The rendering needs the thread with the
+/// dispatcher queue and COM initialized, like the window thread, and the panels processing
+/// the events by the spawner should finish it before the check.
+///
+#[derive(TypedBuilder)]
+pub struct Snapshot {
+    #[builder(setter(into))]
+    path: PathBuf,
+    /// Size of the panel in device independent pixels
+    size: Vector2,
+    #[builder(default = 96.)]
+    dpi: f32,
+    #[builder(default, setter(strip_option))]
+    theme: Option<Theme>,
+    #[builder(default)]
+    tolerance: Tolerance,
+}
+
+impl Snapshot {
+    /// Renders the panel, the image size is the panel's size scaled by the DPI
+    pub async fn render(&self, panel: &dyn Panel) -> crate::Result<Bitmap> {
+        let visual = panel.outer_frame();
+        let compositor = visual.Compositor()?;
+        let scale = self.dpi / 96.;
+        let width = (self.size.X * scale).ceil().max(1.) as u32;
+        let height = (self.size.Y * scale).ceil().max(1.) as u32;
+        // The captured visual is not scaled itself, the scale goes to its child
+        let root = compositor.CreateContainerVisual()?;
+        root.SetSize(Vector2 {
+            X: width as f32,
+            Y: height as f32,
+        })?;
+        let scaled = compositor.CreateContainerVisual()?;
+        scaled.SetSize(self.size)?;
+        scaled.SetScale(Vector3 {
+            X: scale,
+            Y: scale,
+            Z: 1.,
+        })?;
+        root.Children()?.InsertAtTop(&scaled)?;
+        scaled.Children()?.InsertAtTop(&visual)?;
+        if let Some(theme) = &self.theme {
+            panel
+                .on_event_owned(PanelEvent::ThemeChanged(theme.clone()), None)
+                .await?;
+        }
+        panel
+            .on_event_owned(PanelEvent::Resized(self.size), None)
+            .await?;
+        let bitmap = capture_visual(&root.clone().into(), width, height).await;
+        scaled.Children()?.Remove(&visual)?;
+        bitmap
+    }
+
+    /// Renders the panel and compares the image with the stored one
+    pub async fn check(&self, panel: &dyn Panel) -> crate::Result<()> {
+        let actual = self.render(panel).await?;
+        if std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_some() || !self.path.exists() {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            return actual.save_png(&self.path);
+        }
+        let expected = Bitmap::load(&self.path)?;
+        let comparison = compare(&expected, &actual, self.tolerance.threshold);
+        let actual_path = sibling_path(&self.path, "actual");
+        let diff_path = sibling_path(&self.path, "diff");
+        if comparison.different_pixels <= self.tolerance.max_different_pixels {
+            // The files left by the previous failure are stale now
+            let _ = std::fs::remove_file(actual_path);
+            let _ = std::fs::remove_file(diff_path);
+            return Ok(());
+        }
+        actual.save_png(&actual_path)?;
+        comparison.diff.save_png(&diff_path)?;
+        Err(crate::Error::SnapshotMismatch {
+            path: self.path.clone(),
+            diff: diff_path,
+            pixels: comparison.different_pixels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [0, 0, 255, 255];
+
+    fn filled(width: u32, height: u32, pixel: [u8; 4]) -> Bitmap {
+        let mut bitmap = Bitmap::empty(width, height);
+        bitmap
+            .pixels
+            .chunks_exact_mut(4)
+            .for_each(|v| v.copy_from_slice(&pixel));
+        bitmap
+    }
+
+    #[test]
+    fn same_images_dont_differ() {
+        let image = filled(3, 2, [10, 20, 30, 255]);
+        let comparison = compare(&image, &image.clone(), 0.);
+        assert_eq!(comparison.different_pixels, 0);
+        assert!(comparison.diff.pixels.chunks_exact(4).all(|v| v != RED));
+    }
+
+    #[test]
+    fn threshold_and_alpha() {
+        let black = filled(2, 2, [0, 0, 0, 255]);
+        let gray = filled(2, 2, [8, 8, 8, 255]);
+        let white = filled(2, 2, [255, 255, 255, 255]);
+        assert_eq!(compare(&black, &gray, 0.1).different_pixels, 0);
+        assert_eq!(compare(&black, &white, 0.1).different_pixels, 4);
+        assert_eq!(compare(&black, &white, 1.).different_pixels, 0);
+        // Transparent pixels are seen over the white background
+        let transparent = filled(2, 2, [0, 0, 0, 0]);
+        assert_eq!(compare(&transparent, &white, 0.).different_pixels, 0);
+    }
+
+    #[test]
+    fn size_mismatch_marks_missing_pixels() {
+        let expected = filled(2, 2, [0, 0, 0, 255]);
+        let mut actual = filled(3, 1, [0, 0, 0, 255]);
+        actual.set_pixel(0, 0, [255, 255, 255, 255]);
+        let comparison = compare(&expected, &actual, 0.1);
+        assert_eq!((comparison.diff.width, comparison.diff.height), (3, 2));
+        // The changed pixel, the column missing in expected and the row missing in actual
+        assert_eq!(comparison.different_pixels, 5);
+        assert_eq!(comparison.diff.pixel(0, 0), Some(RED));
+        assert_eq!(comparison.diff.pixel(1, 0).map(|v| v == RED), Some(false));
+    }
+}
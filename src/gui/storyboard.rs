@@ -0,0 +1,312 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_event_streams::{EventSource, EventStream, EventStreams};
+use typed_builder::TypedBuilder;
+use windows::{
+    core::{self, HSTRING},
+    Foundation::TypedEventHandler,
+    UI::Composition::{
+        AnimationController, CompositionBatchTypes, Compositor, KeyFrameAnimation, Visual,
+    },
+};
+
+use super::{animation_stepper, time_span};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum StoryboardEvent {
+    /// All iterations of the storyboard are played
+    Completed,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Repeat {
+    Count(u32),
+    Forever,
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Repeat::Count(1)
+    }
+}
+
+// Animation of the visual's property starting at `begin` from the storyboard start
+struct Entry {
+    visual: Visual,
+    property: HSTRING,
+    animation: KeyFrameAnimation,
+    begin: Duration,
+}
+
+impl Entry {
+    fn duration(&self) -> core::Result<Duration> {
+        Ok(Duration::from_nanos(
+            self.animation.Duration()?.Duration as u64 * 100,
+        ))
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // Incremented on each start and stop to ignore completion of outdated batches
+    generation: usize,
+    iteration: u32,
+    playing: bool,
+    paused: bool,
+}
+
+struct Inner {
+    compositor: Compositor,
+    entries: Vec<Entry>,
+    repeat: Repeat,
+    state: Mutex<State>,
+    storyboard_events: EventStreams<StoryboardEvent>,
+}
+
+impl Inner {
+    fn start(self: &Arc<Self>, position: Duration) -> core::Result<()> {
+        let (generation, paused) = {
+            let mut state = self.state.lock().unwrap();
+            state.generation += 1;
+            state.playing = true;
+            (state.generation, state.paused)
+        };
+        let batch = self
+            .compositor
+            .CreateScopedBatch(CompositionBatchTypes::Animation)?;
+        let stepper = animation_stepper();
+        for entry in &self.entries {
+            // Animations are templates, so the same animation object may be started with
+            // different delays for different entries
+            entry
+                .animation
+                .SetDelayTime(time_span(entry.begin.saturating_sub(position)))?;
+            if let Some(stepper) = &stepper {
+                let skipped = position.saturating_sub(entry.begin);
+                stepper.start(&entry.visual, &entry.property, &entry.animation, skipped)?;
+                continue;
+            }
+            entry
+                .visual
+                .StartAnimation(&entry.property, &entry.animation)?;
+            let duration = entry.duration()?;
+            if position > entry.begin && !duration.is_zero() {
+                let progress = (position - entry.begin).as_secs_f32() / duration.as_secs_f32();
+                if let Ok(controller) = entry.visual.TryGetAnimationController(&entry.property) {
+                    controller.SetProgress(progress.min(1.))?;
+                }
+            }
+        }
+        batch.End()?;
+        let inner = self.clone();
+        batch.Completed(&TypedEventHandler::new(move |_, _| {
+            inner.completed(generation)
+        }))?;
+        if paused {
+            self.set_paused(true)?;
+        }
+        Ok(())
+    }
+
+    fn set_paused(&self, paused: bool) -> core::Result<()> {
+        // In the test mode the animations are held paused by the stepper, which only stops
+        // counting their time
+        if let Some(stepper) = animation_stepper() {
+            for entry in &self.entries {
+                stepper.set_paused(&entry.visual, &entry.property, paused);
+            }
+            return Ok(());
+        }
+        self.for_each_controller(|v| if paused { v.Pause() } else { v.Resume() })
+    }
+
+    fn completed(self: &Arc<Self>, generation: usize) -> core::Result<()> {
+        let restart = {
+            let mut state = self.state.lock().unwrap();
+            if state.generation != generation {
+                return Ok(());
+            }
+            state.iteration += 1;
+            let restart = match self.repeat {
+                Repeat::Count(count) => state.iteration < count,
+                Repeat::Forever => true,
+            };
+            state.playing = restart;
+            restart
+        };
+        if restart {
+            self.start(Duration::ZERO)
+        } else {
+            self.storyboard_events
+                .post_event(StoryboardEvent::Completed, None);
+            Ok(())
+        }
+    }
+
+    fn for_each_controller(
+        &self,
+        f: impl Fn(&AnimationController) -> core::Result<()>,
+    ) -> core::Result<()> {
+        for entry in &self.entries {
+            // Controller is absent if the animation is already finished
+            if let Ok(controller) = entry.visual.TryGetAnimationController(&entry.property) {
+                f(&controller)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Group of composition animations of properties of different visuals played as a whole.
+/// Each animation starts at its own time from the storyboard start, which allows to play
+/// them in parallel, one after another or staggered. The storyboard can be paused, resumed and
+/// positioned at any time point; `StoryboardEvent::Completed` is sent when it's played through
+/// the given number of iterations.
+///
+pub struct Storyboard {
+    inner: Arc<Inner>,
+}
+
+#[derive(TypedBuilder)]
+pub struct StoryboardParams {
+    compositor: Compositor,
+    #[builder(default)]
+    repeat: Repeat,
+    #[builder(setter(skip), default)]
+    entries: Vec<Entry>,
+}
+
+impl StoryboardParams {
+    /// Adds the animation of the visual's property starting at `begin` from the storyboard start
+    pub fn add(
+        mut self,
+        visual: Visual,
+        property: &str,
+        animation: impl Into<KeyFrameAnimation>,
+        begin: Duration,
+    ) -> Self {
+        self.entries.push(Entry {
+            visual,
+            property: HSTRING::from(property),
+            animation: animation.into(),
+            begin,
+        });
+        self
+    }
+
+    /// Adds the same animation for several visuals, each one starts `step` later than previous
+    pub fn add_staggered(
+        mut self,
+        visuals: impl IntoIterator<Item = Visual>,
+        property: &str,
+        animation: impl Into<KeyFrameAnimation>,
+        begin: Duration,
+        step: Duration,
+    ) -> Self {
+        let animation = animation.into();
+        let mut begin = begin;
+        for visual in visuals {
+            self = self.add(visual, property, animation.clone(), begin);
+            begin += step;
+        }
+        self
+    }
+}
+
+impl TryFrom<StoryboardParams> for Storyboard {
+    type Error = crate::Error;
+
+    fn try_from(value: StoryboardParams) -> crate::Result<Self> {
+        Ok(Storyboard {
+            inner: Arc::new(Inner {
+                compositor: value.compositor,
+                entries: value.entries,
+                repeat: value.repeat,
+                state: Mutex::new(State::default()),
+                storyboard_events: EventStreams::new(),
+            }),
+        })
+    }
+}
+
+impl TryFrom<StoryboardParams> for Arc<Storyboard> {
+    type Error = crate::Error;
+
+    fn try_from(value: StoryboardParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Storyboard {
+    /// Length of one iteration: the end of the latest animation
+    pub fn duration(&self) -> crate::Result<Duration> {
+        let mut result = Duration::ZERO;
+        for entry in &self.inner.entries {
+            result = result.max(entry.begin + entry.duration()?);
+        }
+        Ok(result)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.inner.state.lock().unwrap().playing
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.state.lock().unwrap().paused
+    }
+
+    /// Plays the storyboard from the start or resumes it if paused
+    pub fn play(&self) -> crate::Result<()> {
+        let resume = {
+            let mut state = self.inner.state.lock().unwrap();
+            let resume = state.playing && state.paused;
+            state.paused = false;
+            if !resume {
+                state.iteration = 0;
+            }
+            resume
+        };
+        if resume {
+            self.inner.set_paused(false)?;
+        } else {
+            self.inner.start(Duration::ZERO)?;
+        }
+        Ok(())
+    }
+
+    pub fn pause(&self) -> crate::Result<()> {
+        self.inner.state.lock().unwrap().paused = true;
+        self.inner.set_paused(true)?;
+        Ok(())
+    }
+
+    /// Stops the animations leaving the properties with their current values
+    pub fn stop(&self) -> crate::Result<()> {
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            state.generation += 1;
+            state.playing = false;
+            state.paused = false;
+        }
+        for entry in &self.inner.entries {
+            entry.visual.StopAnimation(&entry.property)?;
+        }
+        Ok(())
+    }
+
+    /// Moves to the given time point of the current iteration, keeping the paused state
+    pub fn seek(&self, position: Duration) -> crate::Result<()> {
+        self.inner.start(position)?;
+        Ok(())
+    }
+}
+
+impl EventSource<StoryboardEvent> for Storyboard {
+    fn event_stream(&self) -> EventStream<StoryboardEvent> {
+        self.inner.storyboard_events.create_event_stream()
+    }
+}
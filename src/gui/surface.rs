@@ -1,4 +1,10 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+};
 
 use async_event_streams::{
     EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
@@ -7,17 +13,23 @@ use async_event_streams_derive::EventSink;
 use async_trait::async_trait;
 use typed_builder::TypedBuilder;
 use windows::{
-    Foundation::Numerics::Vector2,
-    Graphics::DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+    Foundation::Numerics::{Matrix3x2, Vector2},
+    Graphics::{
+        DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        SizeInt32,
+    },
+    Win32::Graphics::Direct2D::{
+        Common::D2D_RECT_F, ID2D1DeviceContext, D2D1_ANTIALIAS_MODE_ALIASED,
+    },
     UI::Composition::{
         CompositionDrawingSurface, CompositionGraphicsDevice, CompositionStretch,
         CompositionSurfaceBrush, Compositor, SpriteVisual, Visual,
     },
 };
 
-use crate::window::create_composition_graphics_device;
+use crate::window::{create_composition_graphics_device, draw};
 
-use super::{Panel, PanelEvent};
+use super::{redraw_scheduler, Panel, PanelEvent};
 
 #[derive(PartialEq, Clone)]
 pub enum SurfaceEvent {
@@ -32,12 +44,14 @@ pub struct Surface {
     surface: CompositionDrawingSurface,
     _surface_brush: CompositionSurfaceBrush,
     panel_events: EventStreams<PanelEvent>,
-    surface_events: EventStreams<SurfaceEvent>,
+    // Shared with the redraw scheduler
+    surface_events: Arc<EventStreams<SurfaceEvent>>,
+    priority: AtomicI32,
     id: Arc<()>,
 }
 
 impl Surface {
-    fn new(compositor: Compositor) -> crate::Result<Self> {
+    fn new(compositor: Compositor, priority: i32) -> crate::Result<Self> {
         let sprite_visual = compositor.CreateSpriteVisual()?;
         let composition_graphic_device = create_composition_graphics_device(&compositor)?;
         let surface_brush = compositor.CreateSurfaceBrush()?;
@@ -55,13 +69,78 @@ impl Surface {
             surface,
             _surface_brush: surface_brush,
             panel_events: EventStreams::new(),
-            surface_events: EventStreams::new(),
+            surface_events: Arc::new(EventStreams::new()),
+            priority: AtomicI32::new(priority),
             id: Arc::new(()),
         })
     }
     pub fn surface(&self) -> &CompositionDrawingSurface {
         &self.surface
     }
+    pub fn priority(&self) -> i32 {
+        self.priority.load(Ordering::SeqCst)
+    }
+    /// Priority of the redraws queued to the [`RedrawScheduler`](super::RedrawScheduler)
+    pub fn set_priority(&self, priority: i32) {
+        self.priority.store(priority, Ordering::SeqCst);
+    }
+    ///
+    /// Sends `SurfaceEvent::Redraw` with the surface's size, through the redraw scheduler
+    /// if it's set
+    ///
+    pub fn request_redraw(&self) -> crate::Result<()> {
+        let size = self.sprite_visual.Size()?;
+        match redraw_scheduler() {
+            Some(scheduler) => scheduler.request(
+                Arc::as_ptr(&self.id) as usize,
+                self.sprite_visual.clone().into(),
+                self.priority(),
+                size,
+                Arc::downgrade(&self.surface_events),
+            ),
+            None => {
+                self.surface_events.clear(); // No need to keep unhandled redraw events - only latest one makes sense
+                self.surface_events
+                    .post_event(SurfaceEvent::Redraw(size), None);
+            }
+        }
+        Ok(())
+    }
+    ///
+    /// Draws on the surface with Direct2D. The context passed to `f` is translated and clipped
+    /// so that the surface occupies the rectangle from (0,0) to the size passed as second parameter.
+    /// The drawing is finished even if `f` returns error.
+    ///
+    pub fn draw<F: FnOnce(&ID2D1DeviceContext, Vector2) -> crate::Result<()>>(
+        &self,
+        f: F,
+    ) -> crate::Result<()> {
+        let size = self.surface.Size()?;
+        let size = Vector2 {
+            X: size.Width,
+            Y: size.Height,
+        };
+        draw(&self.surface, |context, offset| {
+            unsafe {
+                context.SetTransform(&Matrix3x2::translation(offset.x as f32, offset.y as f32));
+                context.PushAxisAlignedClip(
+                    &D2D_RECT_F {
+                        left: 0.,
+                        top: 0.,
+                        right: size.X,
+                        bottom: size.Y,
+                    },
+                    D2D1_ANTIALIAS_MODE_ALIASED,
+                );
+            }
+            let result = f(&context, size);
+            unsafe {
+                context.PopAxisAlignedClip();
+                context.SetTransform(&Matrix3x2::identity());
+            }
+            result
+        })
+    }
 }
 
 #[async_trait]
@@ -74,9 +153,11 @@ impl EventSinkExt<PanelEvent> for Surface {
     ) -> crate::Result<()> {
         if let PanelEvent::Resized(size) = event.as_ref() {
             self.sprite_visual.SetSize(*size)?;
-            self.surface_events.clear(); // No need to keep unhandled redraw events - only latest one makes sense
-            self.surface_events
-                .post_event(SurfaceEvent::Redraw(*size), None);
+            self.surface.Resize(SizeInt32 {
+                Width: size.X as i32,
+                Height: size.Y as i32,
+            })?;
+            self.request_redraw()?;
         }
         self.panel_events
             .send_event(event.into_owned(), source)
@@ -109,13 +190,17 @@ impl Panel for Surface {
 #[derive(TypedBuilder)]
 pub struct SurfaceParams {
     compositor: Compositor,
+    /// Priority of the redraws queued to the [`RedrawScheduler`](super::RedrawScheduler),
+    /// the higher is redrawn first
+    #[builder(default)]
+    priority: i32,
 }
 
 impl TryFrom<SurfaceParams> for Surface {
     type Error = crate::Error;
 
     fn try_from(value: SurfaceParams) -> crate::Result<Self> {
-        Ok(Surface::new(value.compositor)?)
+        Ok(Surface::new(value.compositor, value.priority)?)
     }
 }
 
@@ -0,0 +1,191 @@
+use std::borrow::Cow;
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::Composition::{Compositor, ContainerVisual, Visual},
+};
+
+use super::{attach, dispatch::DispatchQueue, Panel, PanelEvent, Theme};
+
+struct Core {
+    suspended: bool,
+    size: Vector2,
+    // Size and theme changed while suspended, delivered on resume
+    size_changed: bool,
+    theme: Option<Theme>,
+}
+
+///
+/// Container which can freeze its child, e.g. the inactive page of the tab control or the
+/// document in the background. The suspended child receives no events, so it doesn't handle
+/// input and doesn't redraw its surfaces on resize. The child is notified by
+/// `PanelEvent::VisibilityChanged(false)` on suspend, so the panels running their own timers
+/// may stop them. On resume the child gets the latest theme and size it missed and
+/// `PanelEvent::VisibilityChanged(true)`.
+///
+/// The suspension doesn't hide the child, the container hides it to stop the composition.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct Suspendable {
+    container: ContainerVisual,
+    child: Arc<dyn Panel>,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct SuspendableParams {
+    compositor: Compositor,
+    child: Arc<dyn Panel>,
+    /// Creates the container with the child suspended
+    #[builder(default = false)]
+    suspended: bool,
+}
+
+impl TryFrom<SuspendableParams> for Suspendable {
+    type Error = crate::Error;
+
+    fn try_from(value: SuspendableParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        attach(&container, &*value.child)?;
+        Ok(Suspendable {
+            container,
+            child: value.child,
+            core: RwLock::new(Core {
+                suspended: value.suspended,
+                size: Vector2::default(),
+                size_changed: false,
+                theme: None,
+            }),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<SuspendableParams> for Arc<Suspendable> {
+    type Error = crate::Error;
+
+    fn try_from(value: SuspendableParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl Suspendable {
+    pub fn child(&self) -> Arc<dyn Panel> {
+        self.child.clone()
+    }
+
+    pub async fn is_suspended(&self) -> bool {
+        self.core.read().await.suspended
+    }
+
+    /// Stops the event delivery to the child
+    pub async fn suspend(&self) -> crate::Result<()> {
+        if std::mem::replace(&mut self.core.write().await.suspended, true) {
+            return Ok(());
+        }
+        self.child
+            .on_event_owned(PanelEvent::VisibilityChanged(false), None)
+            .await
+    }
+
+    /// Resumes the event delivery, the child catches up with the theme and size changed meanwhile
+    pub async fn resume(&self) -> crate::Result<()> {
+        let (theme, size) = {
+            let mut core = self.core.write().await;
+            if !core.suspended {
+                return Ok(());
+            }
+            core.suspended = false;
+            let size = std::mem::take(&mut core.size_changed).then_some(core.size);
+            (core.theme.take(), size)
+        };
+        if let Some(theme) = theme {
+            self.child
+                .on_event_owned(PanelEvent::ThemeChanged(theme), None)
+                .await?;
+        }
+        if let Some(size) = size {
+            self.child
+                .on_event_owned(PanelEvent::Resized(size), None)
+                .await?;
+        }
+        self.child
+            .on_event_owned(PanelEvent::VisibilityChanged(true), None)
+            .await
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let suspended = {
+            let mut core = self.core.write().await;
+            match &event {
+                PanelEvent::Resized(size) => {
+                    core.size = *size;
+                    core.size_changed = core.suspended;
+                }
+                PanelEvent::ThemeChanged(theme) if core.suspended => {
+                    core.theme = Some(theme.clone())
+                }
+                _ => (),
+            }
+            core.suspended
+        };
+        if let PanelEvent::Resized(size) = &event {
+            // The visuals are kept in size, so the child appears in place when shown
+            self.container.SetSize(*size)?;
+            self.child.outer_frame().SetSize(*size)?;
+        }
+        if !suspended {
+            self.child.on_event_ref(&event, source.clone()).await?;
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for Suspendable {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for Suspendable {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for Suspendable {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,557 @@
+use std::{
+    borrow::Cow,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::{
+        Numerics::{Matrix3x2, Vector2, Vector3},
+        TypedEventHandler,
+    },
+    Win32::Graphics::Direct2D::{Common::D2D_RECT_F, D2D1_BRUSH_PROPERTIES},
+    UI::{
+        Color, Colors,
+        Composition::{CompositionBatchTypes, Compositor, ContainerVisual, ShapeVisual, Visual},
+    },
+};
+use winit::event::{ElementState, ModifiersState, MouseButton, VirtualKeyCode};
+
+use crate::geometry::{Point, Rect, Size};
+
+use super::{
+    animation_stepper::start_animation,
+    attach,
+    dispatch::DispatchQueue,
+    menu::{d2d_color, text_format, Label},
+    time_span, EventSeqId, Panel, PanelEvent, Surface, SurfaceParams, Suspendable,
+    SuspendableParams,
+};
+
+const HEADER_PADDING: f32 = 16.;
+const INDICATOR_HEIGHT: f32 = 3.;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum TabControlEvent {
+    SelectionChanged(usize),
+}
+
+struct Core {
+    size: Vector2,
+    mouse_pos: Option<Vector2>,
+    hover: Option<usize>,
+}
+
+///
+/// Strip of tab headers over the content area showing the content panel of the selected tab.
+/// Clicking the header or Ctrl+Tab / Ctrl+Shift+Tab selects the tab. With `slide_duration`
+/// set, the new content slides in from the side of its header while the old one slides out.
+/// With `suspend_inactive` set, the contents of the tabs not selected are [`Suspendable`]
+/// suspended and don't process the events until selected.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct TabControl {
+    compositor: Compositor,
+    container: ContainerVisual,
+    header_shapes: ShapeVisual,
+    header_surface: Arc<Surface>,
+    content_host: ContainerVisual,
+    labels: Vec<Label>,
+    widths: Vec<f32>,
+    contents: Vec<Arc<dyn Panel>>,
+    // Wrappers of the contents when the inactive tabs are suspended
+    suspendables: Vec<Arc<Suspendable>>,
+    header_height: f32,
+    slide_duration: Option<Duration>,
+    header_background: Color,
+    hover_color: Color,
+    indicator_color: Color,
+    text_color: Color,
+    // Shared with the completion handlers of the slide animations
+    selected: Arc<AtomicUsize>,
+    core: RwLock<Core>,
+    tab_control_events: EventStreams<TabControlEvent>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct TabControlParams {
+    compositor: Compositor,
+    #[builder(default)]
+    tabs: Vec<(String, Arc<dyn Panel>)>,
+    #[builder(default)]
+    selected: usize,
+    #[builder(default = 40.)]
+    header_height: f32,
+    #[builder(default, setter(strip_option))]
+    slide_duration: Option<Duration>,
+    /// Suspends the contents of the inactive tabs, for the many heavy documents
+    #[builder(default = false)]
+    suspend_inactive: bool,
+    #[builder(default = Colors::WhiteSmoke().unwrap())]
+    header_background: Color,
+    #[builder(default = Colors::Gainsboro().unwrap())]
+    hover_color: Color,
+    #[builder(default = Colors::DodgerBlue().unwrap())]
+    indicator_color: Color,
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+}
+
+impl TabControlParams {
+    pub fn add_tab(mut self, header: impl Into<String>, content: Arc<dyn Panel>) -> Self {
+        self.tabs.push((header.into(), content));
+        self
+    }
+}
+
+impl TryFrom<TabControlParams> for TabControl {
+    type Error = crate::Error;
+
+    fn try_from(value: TabControlParams) -> crate::Result<Self> {
+        let compositor = value.compositor;
+        let container = compositor.CreateContainerVisual()?;
+        let header_shapes = compositor.CreateShapeVisual()?;
+        container.Children()?.InsertAtBottom(&header_shapes)?;
+        let header_surface: Arc<Surface> = SurfaceParams::builder()
+            .compositor(compositor.clone())
+            .build()
+            .try_into()?;
+        attach(&container, &*header_surface)?;
+        let content_host = compositor.CreateContainerVisual()?;
+        content_host.SetOffset(Vector3 {
+            X: 0.,
+            Y: value.header_height,
+            Z: 0.,
+        })?;
+        // Sliding contents are clipped by the content area
+        content_host.SetClip(&compositor.CreateInsetClip()?)?;
+        container.Children()?.InsertAtTop(&content_host)?;
+        let selected = value.selected.min(value.tabs.len().saturating_sub(1));
+        let format = text_format()?;
+        let mut labels = Vec::new();
+        let mut widths = Vec::new();
+        let mut contents = Vec::new();
+        let mut suspendables = Vec::new();
+        for (index, (header, content)) in value.tabs.into_iter().enumerate() {
+            let label = Label::parse(&header);
+            widths.push(label.width(&format)? + HEADER_PADDING * 2.);
+            labels.push(label);
+            let content = if value.suspend_inactive {
+                let suspendable: Arc<Suspendable> = SuspendableParams::builder()
+                    .compositor(compositor.clone())
+                    .child(content)
+                    .suspended(index != selected)
+                    .build()
+                    .try_into()?;
+                suspendables.push(suspendable.clone());
+                suspendable
+            } else {
+                content
+            };
+            attach(&content_host, &*content)?;
+            content.outer_frame().SetIsVisible(index == selected)?;
+            contents.push(content);
+        }
+        Ok(TabControl {
+            compositor,
+            container,
+            header_shapes,
+            header_surface,
+            content_host,
+            labels,
+            widths,
+            contents,
+            suspendables,
+            header_height: value.header_height,
+            slide_duration: value.slide_duration,
+            header_background: value.header_background,
+            hover_color: value.hover_color,
+            indicator_color: value.indicator_color,
+            text_color: value.text_color,
+            selected: Arc::new(AtomicUsize::new(selected)),
+            core: RwLock::new(Core {
+                size: Vector2::default(),
+                mouse_pos: None,
+                hover: None,
+            }),
+            tab_control_events: EventStreams::new(),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<TabControlParams> for Arc<TabControl> {
+    type Error = crate::Error;
+
+    fn try_from(value: TabControlParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl TabControl {
+    pub fn selected(&self) -> usize {
+        self.selected.load(Ordering::SeqCst)
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub async fn select(&self, index: usize) -> crate::Result<()> {
+        if index >= self.contents.len() {
+            return Ok(());
+        }
+        let old = self.selected.swap(index, Ordering::SeqCst);
+        if old == index {
+            return Ok(());
+        }
+        let (size, hover) = {
+            let core = self.core.read().await;
+            (core.size, core.hover)
+        };
+        if let Some(suspendable) = self.suspendables.get(index) {
+            suspendable.resume().await?;
+        }
+        // The old content stays visible while sliding out, but doesn't take the events
+        if let Some(suspendable) = self.suspendables.get(old) {
+            suspendable.suspend().await?;
+        }
+        let new_visual = self.contents[index].outer_frame();
+        let old_visual = self.contents[old].outer_frame();
+        new_visual.SetIsVisible(true)?;
+        match self.slide_duration {
+            Some(duration) if size.X > 0. => {
+                self.slide(&old_visual, &new_visual, old, index, size.X, duration)?
+            }
+            _ => old_visual.SetIsVisible(false)?,
+        }
+        self.redraw_shapes(size, index, hover)?;
+        self.tab_control_events
+            .send_event(TabControlEvent::SelectionChanged(index), None)
+            .await;
+        Ok(())
+    }
+
+    fn slide(
+        &self,
+        old_visual: &Visual,
+        new_visual: &Visual,
+        old: usize,
+        new: usize,
+        width: f32,
+        duration: Duration,
+    ) -> crate::Result<()> {
+        // The tab to the right comes from the right side
+        let shift = if new > old { width } else { -width };
+        let animate = |visual: &Visual, from: f32, to: f32| -> crate::Result<()> {
+            let animation = self.compositor.CreateVector3KeyFrameAnimation()?;
+            animation.InsertKeyFrame(
+                0.,
+                Vector3 {
+                    X: from,
+                    Y: 0.,
+                    Z: 0.,
+                },
+            )?;
+            animation.InsertKeyFrame(
+                1.,
+                Vector3 {
+                    X: to,
+                    Y: 0.,
+                    Z: 0.,
+                },
+            )?;
+            animation.SetDuration(time_span(duration))?;
+            start_animation(visual, "Offset", &animation)?;
+            Ok(())
+        };
+        let batch = self
+            .compositor
+            .CreateScopedBatch(CompositionBatchTypes::Animation)?;
+        animate(new_visual, shift, 0.)?;
+        animate(old_visual, 0., -shift)?;
+        batch.End()?;
+        let selected = self.selected.clone();
+        let old_visual = old_visual.clone();
+        batch.Completed(&TypedEventHandler::new(move |_, _| {
+            // The old tab may have been selected again while sliding out
+            if selected.load(Ordering::SeqCst) != old {
+                old_visual.SetIsVisible(false)?;
+            }
+            old_visual.SetOffset(Vector3::default())?;
+            Ok(())
+        }))?;
+        Ok(())
+    }
+
+    fn header_rects(&self) -> Vec<Rect> {
+        let mut x = 0.;
+        self.widths
+            .iter()
+            .map(|width| {
+                let rect = Rect::new(Point::new(x, 0.), Size::new(*width, self.header_height));
+                x += width;
+                rect
+            })
+            .collect()
+    }
+
+    fn header_at(&self, pos: Vector2) -> Option<usize> {
+        self.header_rects().iter().position(|v| v.contains(pos))
+    }
+
+    fn content_rect(&self, size: Vector2) -> Rect {
+        Rect::new(
+            Point::new(0., self.header_height),
+            Size::new(size.X, (size.Y - self.header_height).max(0.)),
+        )
+    }
+
+    fn redraw_shapes(
+        &self,
+        size: Vector2,
+        selected: usize,
+        hover: Option<usize>,
+    ) -> crate::Result<()> {
+        let shapes = self.header_shapes.Shapes()?;
+        shapes.Clear()?;
+        let append_rect = |rect: Rect, color: Color| -> crate::Result<()> {
+            let geometry = self.compositor.CreateRectangleGeometry()?;
+            geometry.SetOffset(rect.origin.into())?;
+            geometry.SetSize(rect.size.into())?;
+            let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+            shape.SetFillBrush(&self.compositor.CreateColorBrushWithColor(color)?)?;
+            shapes.Append(&shape)?;
+            Ok(())
+        };
+        append_rect(
+            Rect::new(Point::default(), Size::new(size.X, self.header_height)),
+            self.header_background,
+        )?;
+        let rects = self.header_rects();
+        if let Some(rect) = hover.and_then(|index| rects.get(index)) {
+            append_rect(*rect, self.hover_color)?;
+        }
+        if let Some(rect) = rects.get(selected) {
+            append_rect(
+                Rect::new(
+                    Point::new(rect.left(), rect.bottom() - INDICATOR_HEIGHT),
+                    Size::new(rect.size.width, INDICATOR_HEIGHT),
+                ),
+                self.indicator_color,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn redraw_text(&self) -> crate::Result<()> {
+        self.header_surface.draw(|context, _| {
+            let text_format = text_format()?;
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(self.text_color), Some(&brush_properties))
+            }?;
+            unsafe { context.Clear(Some(&d2d_color(Colors::Transparent()?))) };
+            for (label, rect) in self.labels.iter().zip(self.header_rects()) {
+                let layout_rect = D2D_RECT_F {
+                    left: rect.left() + HEADER_PADDING,
+                    top: rect.top(),
+                    right: rect.right() - HEADER_PADDING,
+                    bottom: rect.bottom(),
+                };
+                label.draw(context, &text_format, layout_rect, &brush)?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        let header_size = Vector2 {
+            X: size.X,
+            Y: self.header_height,
+        };
+        self.header_shapes.SetSize(header_size)?;
+        self.header_surface
+            .on_event_owned(PanelEvent::Resized(header_size), source.clone())
+            .await?;
+        let content_size: Vector2 = self.content_rect(size).size.into();
+        self.content_host.SetSize(content_size)?;
+        let hover = {
+            let mut core = self.core.write().await;
+            core.size = size;
+            core.hover
+        };
+        self.redraw_text()?;
+        self.redraw_shapes(size, self.selected(), hover)?;
+        // All contents are kept resized to be ready for sliding in
+        for content in &self.contents {
+            content.outer_frame().SetSize(content_size)?;
+            content
+                .on_event_owned(PanelEvent::Resized(content_size), source.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn cursor_moved(&self, pos: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        let hover = self.header_at(pos);
+        let (size, changed) = {
+            let mut core = self.core.write().await;
+            core.mouse_pos = Some(pos);
+            let changed = core.hover != hover;
+            core.hover = hover;
+            (core.size, changed)
+        };
+        if changed {
+            self.redraw_shapes(size, self.selected(), hover)?;
+        }
+        if let Some(content) = self.contents.get(self.selected()) {
+            let pos = self.content_rect(size).to_local(pos);
+            content
+                .on_event_owned(PanelEvent::CursorMoved(pos.into()), source)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn mouse_input(
+        &self,
+        in_slot: bool,
+        state: ElementState,
+        button: MouseButton,
+        click_count: u32,
+        seq: EventSeqId,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let (size, pos) = {
+            let core = self.core.read().await;
+            (core.size, core.mouse_pos)
+        };
+        let header = pos.and_then(|pos| self.header_at(pos));
+        if let (true, ElementState::Pressed, MouseButton::Left, Some(index)) =
+            (in_slot, state, button, header)
+        {
+            self.select(index).await?;
+        }
+        let in_content = pos.map_or(false, |pos| self.content_rect(size).contains(pos));
+        if let Some(content) = self.contents.get(self.selected()) {
+            content
+                .on_event_owned(
+                    PanelEvent::MouseInput {
+                        in_slot: in_slot && in_content,
+                        state,
+                        button,
+                        click_count,
+                        seq,
+                    },
+                    source,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            PanelEvent::CursorMoved(pos) => self.cursor_moved(*pos, source.clone()).await?,
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button,
+                click_count,
+                seq,
+            } => {
+                self.mouse_input(
+                    *in_slot,
+                    *state,
+                    *button,
+                    *click_count,
+                    *seq,
+                    source.clone(),
+                )
+                .await?
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(VirtualKeyCode::Tab),
+                modifiers,
+                ..
+            } if modifiers.ctrl() && !self.contents.is_empty() => {
+                let count = self.contents.len();
+                let selected = self.selected();
+                let next = if modifiers.contains(ModifiersState::SHIFT) {
+                    (selected + count - 1) % count
+                } else {
+                    (selected + 1) % count
+                };
+                self.select(next).await?
+            }
+            _ => {
+                if let Some(content) = self.contents.get(self.selected()) {
+                    content.on_event_ref(&event, source.clone()).await?;
+                }
+            }
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<TabControlEvent> for TabControl {
+    fn event_stream(&self) -> EventStream<TabControlEvent> {
+        self.tab_control_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for TabControl {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for TabControl {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for TabControl {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
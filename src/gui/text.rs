@@ -1,4 +1,7 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
 
 use async_event_streams::{
     spawn_event_pipe, EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
@@ -9,62 +12,335 @@ use async_trait::async_trait;
 use futures::task::Spawn;
 use typed_builder::TypedBuilder;
 use windows::{
-    core::InParam,
     w,
     Foundation::Numerics::{Matrix3x2, Vector2},
-    Graphics::SizeInt32,
     Win32::Graphics::{
         Direct2D::{
             Common::{D2D1_COLOR_F, D2D_RECT_F},
-            D2D1_BRUSH_PROPERTIES, D2D1_DRAW_TEXT_OPTIONS_NONE,
+            D2D1_BRUSH_PROPERTIES, D2D1_DRAW_TEXT_OPTIONS,
+            D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT, D2D1_DRAW_TEXT_OPTIONS_NONE,
+            D2D1_DRAW_TEXT_OPTIONS_NO_SNAP, D2D1_TEXT_ANTIALIAS_MODE,
+            D2D1_TEXT_ANTIALIAS_MODE_ALIASED, D2D1_TEXT_ANTIALIAS_MODE_CLEARTYPE,
+            D2D1_TEXT_ANTIALIAS_MODE_DEFAULT, D2D1_TEXT_ANTIALIAS_MODE_GRAYSCALE,
         },
         DirectWrite::{
-            DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_ITALIC, DWRITE_FONT_WEIGHT_BOLD,
-            DWRITE_MEASURING_MODE_NATURAL,
+            IDWriteTextFormat, DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_ITALIC,
+            DWRITE_FONT_STYLE_NORMAL, DWRITE_FONT_WEIGHT, DWRITE_MEASURING_MODE_NATURAL,
+            DWRITE_PARAGRAPH_ALIGNMENT_CENTER, DWRITE_PARAGRAPH_ALIGNMENT_FAR,
+            DWRITE_PARAGRAPH_ALIGNMENT_NEAR, DWRITE_TEXT_ALIGNMENT_CENTER,
+            DWRITE_TEXT_ALIGNMENT_JUSTIFIED, DWRITE_TEXT_ALIGNMENT_LEADING,
+            DWRITE_TEXT_ALIGNMENT_TRAILING, DWRITE_TEXT_METRICS, DWRITE_TRIMMING,
+            DWRITE_TRIMMING_GRANULARITY_CHARACTER, DWRITE_WORD_WRAPPING_NO_WRAP,
+            DWRITE_WORD_WRAPPING_WRAP,
         },
     },
-    UI::Composition::{CompositionDrawingSurface, Compositor, Visual},
+    UI::Composition::{Compositor, Visual},
+    UI::{Color, Colors},
 };
 
 use crate::{
+    geometry::Alignment,
     on_err,
-    window::{draw, dwrite_factory, ToWide},
+    window::{dwrite_factory, font_collection, ToWide},
 };
 
+#[cfg(feature = "accessibility")]
+use super::accessibility::{automation_id_or, set_accessible, Accessible, AccessibleRole};
 use super::{surface::SurfaceEvent, Panel, PanelEvent, Surface, SurfaceParams};
 
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum TextAntialias {
+    /// System setting
+    #[default]
+    Default,
+    /// Subpixel antialiasing, for text over the opaque background only
+    ClearType,
+    /// For text over transparent or acrylic backgrounds where ClearType gives color fringes
+    Grayscale,
+    Aliased,
+}
+
+impl From<TextAntialias> for D2D1_TEXT_ANTIALIAS_MODE {
+    fn from(value: TextAntialias) -> Self {
+        match value {
+            TextAntialias::Default => D2D1_TEXT_ANTIALIAS_MODE_DEFAULT,
+            TextAntialias::ClearType => D2D1_TEXT_ANTIALIAS_MODE_CLEARTYPE,
+            TextAntialias::Grayscale => D2D1_TEXT_ANTIALIAS_MODE_GRAYSCALE,
+            TextAntialias::Aliased => D2D1_TEXT_ANTIALIAS_MODE_ALIASED,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct TextRendering {
+    pub antialias: TextAntialias,
+    /// Snap glyphs to pixels: sharper static text, but jittery when the text is animated
+    /// or scaled
+    pub pixel_snapping: bool,
+    /// Draw the glyphs of color fonts, e.g. emoji, in color instead of monochrome outlines
+    pub color_fonts: bool,
+}
+
+// Rendering of the text panels until the default is changed by the application
+const SYSTEM_TEXT_RENDERING: TextRendering = TextRendering {
+    antialias: TextAntialias::Default,
+    pixel_snapping: true,
+    color_fonts: true,
+};
+
+impl Default for TextRendering {
+    fn default() -> Self {
+        SYSTEM_TEXT_RENDERING
+    }
+}
+
+impl TextRendering {
+    pub(super) fn draw_text_options(&self) -> D2D1_DRAW_TEXT_OPTIONS {
+        let snap = if self.pixel_snapping {
+            D2D1_DRAW_TEXT_OPTIONS_NONE
+        } else {
+            D2D1_DRAW_TEXT_OPTIONS_NO_SNAP
+        };
+        if self.color_fonts {
+            snap | D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT
+        } else {
+            snap
+        }
+    }
+}
+
+static DEFAULT_TEXT_RENDERING: Mutex<TextRendering> = Mutex::new(SYSTEM_TEXT_RENDERING);
+
+/// Rendering options for the text panels created without their own ones
+pub fn default_text_rendering() -> TextRendering {
+    *DEFAULT_TEXT_RENDERING.lock().unwrap()
+}
+
+/// Sets the rendering options for the text panels created after this call
+pub fn set_default_text_rendering(rendering: TextRendering) {
+    *DEFAULT_TEXT_RENDERING.lock().unwrap() = rendering;
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum TextEvent {
+    /// New content of the text set by [`Text::set_text`]
+    Changed(String),
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Font {
+    /// Installed font family or the one registered by `register_font_file` or
+    /// `register_font_data`
+    pub family: String,
+    /// Size in DIPs
+    pub size: f32,
+    /// Weight from 100 (thin) to 900 (black), 400 is normal, 700 is bold
+    pub weight: u16,
+    pub italic: bool,
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self {
+            family: "Segoe UI".to_string(),
+            size: 30.,
+            weight: 700,
+            italic: true,
+        }
+    }
+}
+
+impl Font {
+    pub(super) fn text_format(&self) -> crate::Result<IDWriteTextFormat> {
+        let style = if self.italic {
+            DWRITE_FONT_STYLE_ITALIC
+        } else {
+            DWRITE_FONT_STYLE_NORMAL
+        };
+        let collection = font_collection(&self.family)?;
+        let text_format = unsafe {
+            dwrite_factory()?.CreateTextFormat(
+                self.family.as_str().to_wide().as_pcwstr(),
+                collection.as_ref(),
+                DWRITE_FONT_WEIGHT(self.weight.clamp(1, 999) as i32),
+                style,
+                DWRITE_FONT_STRETCH_NORMAL,
+                self.size,
+                w!("en-US"),
+            )
+        }?;
+        Ok(text_format)
+    }
+}
+
+///
+/// Size of the text rendered with the font. Without `max_width` the lines are broken only
+/// at the line breaks in the text, otherwise the text is wrapped at word boundaries to fit
+/// the width. Trailing spaces are included, so the size of "a " is wider than of "a".
+///
+pub fn measure_text(text: &str, font: &Font, max_width: Option<f32>) -> crate::Result<Vector2> {
+    let text_format = font.text_format()?;
+    let layout = TextLayout {
+        wrap: max_width.is_some(),
+        ..Default::default()
+    };
+    layout.apply(&text_format)?;
+    let text: Vec<u16> = text.encode_utf16().collect();
+    let text_layout = unsafe {
+        dwrite_factory()?.CreateTextLayout(
+            &text,
+            &text_format,
+            max_width.unwrap_or(f32::MAX),
+            f32::MAX,
+        )
+    }?;
+    let mut metrics = DWRITE_TEXT_METRICS::default();
+    unsafe { text_layout.GetMetrics(&mut metrics) }?;
+    Ok(Vector2 {
+        X: metrics.widthIncludingTrailingWhitespace,
+        Y: metrics.height,
+    })
+}
+
+/// What to do with the text which doesn't fit the panel
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum TextOverflow {
+    #[default]
+    Clip,
+    /// Cut the text at the character boundary and put "…" at the end
+    Ellipsis,
+}
+
+///
+/// Placement of the text inside the panel. The layout is recalculated each time the panel
+/// is resized, so the wrapped text follows the width of its slot.
+///
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct TextLayout {
+    /// Break the lines at word boundaries to fit the width
+    pub wrap: bool,
+    /// `Stretch` justifies the wrapped lines
+    pub horizontal_alignment: Alignment,
+    /// `Stretch` is the same as `Start`
+    pub vertical_alignment: Alignment,
+    pub overflow: TextOverflow,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            wrap: true,
+            horizontal_alignment: Alignment::Start,
+            vertical_alignment: Alignment::Start,
+            overflow: TextOverflow::Clip,
+        }
+    }
+}
+
+impl TextLayout {
+    pub(super) fn apply(&self, format: &IDWriteTextFormat) -> crate::Result<()> {
+        let wrapping = if self.wrap {
+            DWRITE_WORD_WRAPPING_WRAP
+        } else {
+            DWRITE_WORD_WRAPPING_NO_WRAP
+        };
+        let text_alignment = match self.horizontal_alignment {
+            Alignment::Start => DWRITE_TEXT_ALIGNMENT_LEADING,
+            Alignment::Center => DWRITE_TEXT_ALIGNMENT_CENTER,
+            Alignment::End => DWRITE_TEXT_ALIGNMENT_TRAILING,
+            Alignment::Stretch => DWRITE_TEXT_ALIGNMENT_JUSTIFIED,
+        };
+        let paragraph_alignment = match self.vertical_alignment {
+            Alignment::Start | Alignment::Stretch => DWRITE_PARAGRAPH_ALIGNMENT_NEAR,
+            Alignment::Center => DWRITE_PARAGRAPH_ALIGNMENT_CENTER,
+            Alignment::End => DWRITE_PARAGRAPH_ALIGNMENT_FAR,
+        };
+        unsafe {
+            format.SetWordWrapping(wrapping)?;
+            format.SetTextAlignment(text_alignment)?;
+            format.SetParagraphAlignment(paragraph_alignment)?;
+        }
+        if self.overflow == TextOverflow::Ellipsis {
+            let trimming = DWRITE_TRIMMING {
+                granularity: DWRITE_TRIMMING_GRANULARITY_CHARACTER,
+                delimiter: 0,
+                delimiterCount: 0,
+            };
+            let sign = unsafe { dwrite_factory()?.CreateEllipsisTrimmingSign(format) }?;
+            unsafe { format.SetTrimming(&trimming, &sign) }?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(EventSink)]
 #[event_sink(event=SurfaceEvent)]
 struct Core {
     surface: Arc<Surface>,
     text: String,
+    font: Font,
+    layout: TextLayout,
+    color: Color,
+    rendering: TextRendering,
 }
 
 impl Core {
-    fn new(surface: Arc<Surface>, text: String) -> crate::Result<Self> {
-        Ok(Self { surface, text })
+    fn new(
+        surface: Arc<Surface>,
+        text: String,
+        font: Font,
+        layout: TextLayout,
+        color: Color,
+        rendering: TextRendering,
+    ) -> crate::Result<Self> {
+        let core = Self {
+            surface,
+            text,
+            font,
+            layout,
+            color,
+            rendering,
+        };
+        #[cfg(feature = "accessibility")]
+        core.update_accessible()?;
+        Ok(core)
+    }
+    #[cfg(feature = "accessibility")]
+    fn update_accessible(&self) -> crate::Result<()> {
+        let visual = self.surface.outer_frame();
+        let accessible = Accessible {
+            name: Some(self.text.clone()).filter(|v| !v.is_empty()),
+            foreground: Some(self.color),
+            automation_id: Some(automation_id_or(&visual, "Text")?),
+            ..Accessible::new(AccessibleRole::Text)
+        };
+        set_accessible(&visual, &accessible)
+    }
+    fn redraw(&self) -> crate::Result<()> {
+        #[cfg(feature = "accessibility")]
+        self.update_accessible()?;
+        redraw(
+            &self.surface,
+            self.text.as_str(),
+            &self.font,
+            self.layout,
+            self.color,
+            self.rendering,
+        )
     }
 }
 
-fn redraw(size: Vector2, surface: &CompositionDrawingSurface, text: &str) -> crate::Result<()> {
-    let new_surface_size = SizeInt32 {
-        Width: size.X as i32,
-        Height: size.Y as i32,
-    };
-    surface.Resize(new_surface_size)?;
-    draw(surface, |context, point| {
-        let fontsize = 30.;
-        let dwrite_text_format = unsafe {
-            dwrite_factory()?.CreateTextFormat(
-                w!("Segoe UI"),
-                InParam::null(),
-                DWRITE_FONT_WEIGHT_BOLD,
-                DWRITE_FONT_STYLE_ITALIC,
-                DWRITE_FONT_STRETCH_NORMAL,
-                fontsize,
-                w!("en-US"),
-            )
-        }?;
+fn redraw(
+    surface: &Surface,
+    text: &str,
+    font: &Font,
+    layout: TextLayout,
+    color: Color,
+    rendering: TextRendering,
+) -> crate::Result<()> {
+    surface.draw(|context, size| {
+        let dwrite_text_format = font.text_format()?;
+        layout.apply(&dwrite_text_format)?;
 
         let clearcolor = D2D1_COLOR_F {
             r: 0.,
@@ -73,16 +349,17 @@ fn redraw(size: Vector2, surface: &CompositionDrawingSurface, text: &str) -> cra
             a: 0.,
         };
         let text_color = D2D1_COLOR_F {
-            r: 0.,
-            g: 0.,
-            b: 0.,
-            a: 255.,
+            r: color.R as f32 / 255.,
+            g: color.G as f32 / 255.,
+            b: color.B as f32 / 255.,
+            a: color.A as f32 / 255.,
         };
         let text_brush_properties = D2D1_BRUSH_PROPERTIES {
             opacity: 1.,
             transform: Matrix3x2::identity(),
         };
         unsafe { context.Clear(Some(&clearcolor)) };
+        unsafe { context.SetTextAntialiasMode(rendering.antialias.into()) };
         let text_brush =
             unsafe { context.CreateSolidColorBrush(&text_color, Some(&text_brush_properties)) }?;
         unsafe {
@@ -90,26 +367,15 @@ fn redraw(size: Vector2, surface: &CompositionDrawingSurface, text: &str) -> cra
                 text.to_wide().0.as_slice(),
                 &dwrite_text_format,
                 &D2D_RECT_F {
-                    left: point.x as f32,
-                    top: point.y as f32,
-                    right: point.x as f32 + size.X,
-                    bottom: point.y as f32 + size.Y,
+                    left: 0.,
+                    top: 0.,
+                    right: size.X,
+                    bottom: size.Y,
                 },
                 &text_brush,
-                D2D1_DRAW_TEXT_OPTIONS_NONE,
+                rendering.draw_text_options(),
                 DWRITE_MEASURING_MODE_NATURAL,
             );
-            /*
-            context.DrawTextLayout(
-                D2D_POINT_2F {
-                    x: 0.,
-                    y: size.Y / 2.,
-                },
-                &text_layout,
-                &text_brush,
-                D2D1_DRAW_TEXT_OPTIONS_NONE,
-            )
-            */
         };
 
         Ok(())
@@ -126,9 +392,7 @@ impl EventSinkExt<SurfaceEvent> for Core {
         _: Option<Arc<EventBox>>,
     ) -> crate::Result<()> {
         match event.as_ref() {
-            SurfaceEvent::Redraw(size) => {
-                redraw(*size, self.surface.surface(), self.text.as_str())?
-            }
+            SurfaceEvent::Redraw(_) => self.redraw()?,
         }
         Ok(())
     }
@@ -138,11 +402,119 @@ impl EventSinkExt<SurfaceEvent> for Core {
 #[event_sink(event=PanelEvent)]
 pub struct Text {
     surface: Arc<Surface>,
-    _core: Arc<RwLock<Core>>,
+    core: Arc<RwLock<Core>>,
     panel_events: EventStreams<PanelEvent>,
+    text_events: EventStreams<TextEvent>,
     id: Arc<()>,
 }
 
+impl Text {
+    pub async fn text(&self) -> String {
+        self.core.read().await.text.clone()
+    }
+
+    pub async fn set_text(&self, text: impl Into<String>) -> crate::Result<()> {
+        let text = text.into();
+        {
+            let mut core = self.core.write().await;
+            if core.text == text {
+                return Ok(());
+            }
+            core.text = text.clone();
+            core.redraw()?;
+        }
+        self.text_events
+            .send_event(TextEvent::Changed(text), None)
+            .await;
+        Ok(())
+    }
+
+    pub async fn rendering(&self) -> TextRendering {
+        self.core.read().await.rendering
+    }
+
+    pub async fn set_rendering(&self, rendering: TextRendering) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        if core.rendering != rendering {
+            core.rendering = rendering;
+            core.redraw()?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Size the panel needs to show the whole text on the lines given by the line breaks in
+    /// it, for the containers and skins sizing themselves to the label
+    ///
+    pub async fn desired_size(&self) -> crate::Result<Vector2> {
+        let core = self.core.read().await;
+        measure_text(&core.text, &core.font, None)
+    }
+
+    pub async fn font(&self) -> Font {
+        self.core.read().await.font.clone()
+    }
+
+    pub async fn set_font(&self, font: Font) -> crate::Result<()> {
+        self.update_font(|v| *v = font).await
+    }
+
+    pub async fn set_font_family(&self, family: impl Into<String>) -> crate::Result<()> {
+        let family = family.into();
+        self.update_font(|v| v.family = family).await
+    }
+
+    pub async fn set_font_size(&self, size: f32) -> crate::Result<()> {
+        self.update_font(|v| v.size = size).await
+    }
+
+    pub async fn set_font_weight(&self, weight: u16) -> crate::Result<()> {
+        self.update_font(|v| v.weight = weight).await
+    }
+
+    pub async fn set_italic(&self, italic: bool) -> crate::Result<()> {
+        self.update_font(|v| v.italic = italic).await
+    }
+
+    // Redraws the text only if the font has actually changed
+    async fn update_font(&self, f: impl FnOnce(&mut Font)) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        let mut font = core.font.clone();
+        f(&mut font);
+        if core.font != font {
+            core.font = font;
+            core.redraw()?;
+        }
+        Ok(())
+    }
+
+    pub async fn layout(&self) -> TextLayout {
+        self.core.read().await.layout
+    }
+
+    pub async fn set_layout(&self, layout: TextLayout) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        if core.layout != layout {
+            core.layout = layout;
+            core.redraw()?;
+        }
+        Ok(())
+    }
+
+    pub async fn color(&self) -> Color {
+        self.core.read().await.color
+    }
+
+    pub async fn set_color(&self, color: Color) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        if core.color != color {
+            core.color = color;
+            core.redraw()?;
+        }
+        Ok(())
+    }
+}
+
 /*
 impl Text {
     fn resize(&mut self, size: Vector2) -> crate::Result<()> {
@@ -264,6 +636,12 @@ impl EventSource<PanelEvent> for Text {
     }
 }
 
+impl EventSource<TextEvent> for Text {
+    fn event_stream(&self) -> EventStream<TextEvent> {
+        self.text_events.create_event_stream()
+    }
+}
+
 #[async_trait]
 impl Panel for Text {
     fn outer_frame(&self) -> Visual {
@@ -278,6 +656,15 @@ impl Panel for Text {
 pub struct TextParams<T: Spawn> {
     compositor: Compositor,
     text: String,
+    #[builder(default)]
+    font: Font,
+    #[builder(default)]
+    layout: TextLayout,
+    #[builder(default = Colors::Black().unwrap())]
+    color: Color,
+    /// Rendering options, the global default if not set
+    #[builder(default, setter(strip_option))]
+    rendering: Option<TextRendering>,
     spawner: T,
 }
 
@@ -289,12 +676,21 @@ impl<T: Spawn> TryFrom<TextParams<T>> for Text {
             .compositor(value.compositor)
             .build()
             .try_into()?;
-        let core = Arc::new(RwLock::new(Core::new(surface.clone(), value.text)?));
+        let rendering = value.rendering.unwrap_or_else(default_text_rendering);
+        let core = Arc::new(RwLock::new(Core::new(
+            surface.clone(),
+            value.text,
+            value.font,
+            value.layout,
+            value.color,
+            rendering,
+        )?));
         spawn_event_pipe(&value.spawner, &surface, core.clone(), on_err)?;
         Ok(Text {
             surface,
-            _core: core,
+            core,
             panel_events: EventStreams::new(),
+            text_events: EventStreams::new(),
             id: Arc::new(()),
         })
     }
@@ -0,0 +1,674 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use async_event_streams::{EventSource, EventStream, EventStreams};
+use async_std::sync::{Mutex, RwLock};
+
+// Chunks are split to about this size in bytes, so an edit copies only a few of them. The
+// smaller chunks are merged with their neighbours, so there are few of them to scan.
+const CHUNK_SIZE: usize = 1024;
+
+// Text stored in chunks: edits in the middle of the large text don't move the whole text
+#[derive(Default)]
+struct Rope {
+    chunks: Vec<String>,
+    len: usize,
+}
+
+impl Rope {
+    fn new(text: &str) -> Self {
+        Rope {
+            chunks: split_chunks(text.to_string()),
+            len: text.len(),
+        }
+    }
+
+    fn is_char_boundary(&self, pos: usize) -> bool {
+        if pos > self.len {
+            return false;
+        }
+        let (index, offset) = self.locate(pos);
+        self.chunks
+            .get(index)
+            .map_or(true, |chunk| chunk.is_char_boundary(offset))
+    }
+
+    // Chunk containing the position and the offset in it, the end is after the last chunk
+    fn locate(&self, pos: usize) -> (usize, usize) {
+        let mut start = 0;
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            if pos < start + chunk.len() {
+                return (index, pos - start);
+            }
+            start += chunk.len();
+        }
+        (self.chunks.len(), 0)
+    }
+
+    fn slice(&self, range: Range<usize>) -> String {
+        let mut result = String::with_capacity(range.len());
+        let mut start = 0;
+        for chunk in &self.chunks {
+            let end = start + chunk.len();
+            if end > range.start && start < range.end {
+                let from = range.start.max(start) - start;
+                let to = range.end.min(end) - start;
+                result.push_str(&chunk[from..to]);
+            }
+            start = end;
+        }
+        result
+    }
+
+    // Replaces the range with the text, returns the replaced text
+    fn replace(&mut self, range: Range<usize>, text: &str) -> String {
+        let (mut first, offset) = self.locate(range.start);
+        let (last, _) = self.locate(range.end);
+        let mut last = (last + 1).min(self.chunks.len());
+        let mut start = range.start - offset;
+        let size: usize = self.chunks[first..last].iter().map(String::len).sum();
+        // The edited chunks left small are joined with their neighbours
+        if size + text.len() < CHUNK_SIZE / 2 + range.len() {
+            if first > 0 {
+                first -= 1;
+                start -= self.chunks[first].len();
+            }
+            last = (last + 1).min(self.chunks.len());
+        }
+        let mut joined: String = self.chunks[first..last].concat();
+        let removed = joined[range.start - start..range.end - start].to_string();
+        joined.replace_range(range.start - start..range.end - start, text);
+        self.chunks.splice(first..last, split_chunks(joined));
+        self.len = self.len - range.len() + text.len();
+        removed
+    }
+
+    fn text(&self) -> String {
+        self.chunks.concat()
+    }
+}
+
+// Splits the text into the chunks of about the same size not above `CHUNK_SIZE`, so none of
+// them is much smaller than a half of it
+fn split_chunks(mut text: String) -> Vec<String> {
+    let count = (text.len() + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    let mut chunks = Vec::with_capacity(count);
+    for left in (2..=count).rev() {
+        let mut pos = text.len() / left;
+        while !text.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        let rest = text.split_off(pos);
+        chunks.push(text);
+        text = rest;
+    }
+    if !text.is_empty() {
+        chunks.push(text);
+    }
+    chunks
+}
+
+/// Replacement of the byte range of the document with the text
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    pub fn insert(pos: usize, text: impl Into<String>) -> Self {
+        Self::new(pos..pos, text)
+    }
+
+    pub fn delete(range: Range<usize>) -> Self {
+        Self::new(range, String::new())
+    }
+
+    // Edit restoring the text replaced by this edit
+    fn inverse(&self, removed: String) -> TextEdit {
+        TextEdit::new(
+            self.range.start..self.range.start + self.text.len(),
+            removed,
+        )
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum TextDocumentEvent {
+    ///
+    /// Edits applied to the document as one transaction, by the edit itself, by undo or by
+    /// redo. Each edit's range refers to the text after the previous edits of the list, so
+    /// the views replay them in order to update their layout. `revision` is the document's
+    /// revision after the transaction, the events are sent in the order of the revisions.
+    /// `author` is the id of the view which made the edits, 0 for the other changes.
+    ///
+    Changed {
+        revision: u64,
+        author: u64,
+        edits: Vec<TextEdit>,
+    },
+}
+
+#[derive(Default)]
+struct Core {
+    rope: Rope,
+    // Inverse edits of the transactions, in the order to apply them
+    undo: Vec<Vec<TextEdit>>,
+    redo: Vec<Vec<TextEdit>>,
+    // Number of the transactions applied
+    revision: u64,
+}
+
+impl Core {
+    // Applies all the edits or none, returns the inverse edits to undo them
+    fn apply(&mut self, edits: &[TextEdit]) -> crate::Result<Vec<TextEdit>> {
+        let mut inverse: Vec<TextEdit> = Vec::with_capacity(edits.len());
+        for edit in edits {
+            let valid = edit.range.start <= edit.range.end
+                && self.rope.is_char_boundary(edit.range.start)
+                && self.rope.is_char_boundary(edit.range.end);
+            if !valid {
+                for undo in inverse.iter().rev() {
+                    self.rope.replace(undo.range.clone(), &undo.text);
+                }
+                return Err(crate::Error::BadIndex);
+            }
+            let removed = self.rope.replace(edit.range.clone(), &edit.text);
+            inverse.push(edit.inverse(removed));
+        }
+        inverse.reverse();
+        Ok(inverse)
+    }
+}
+
+///
+/// Text shared by the views editing or showing it. Each change is a transaction of edits
+/// reported by `TextDocumentEvent::Changed`, so several views over one document stay in sync,
+/// and undone or redone as a whole. Positions are byte offsets in UTF-8 and must fall on
+/// character boundaries. Each transaction increments the document's revision, so the views
+/// loading the text may skip the events of the changes already in it.
+///
+pub struct TextDocument {
+    core: RwLock<Core>,
+    // Held from the change until its event is sent, so the events of the concurrent changes
+    // are not reordered
+    sending: Mutex<()>,
+    text_document_events: EventStreams<TextDocumentEvent>,
+}
+
+impl Default for TextDocument {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl TextDocument {
+    pub fn new(text: &str) -> Self {
+        Self {
+            core: RwLock::new(Core {
+                rope: Rope::new(text),
+                ..Default::default()
+            }),
+            sending: Mutex::new(()),
+            text_document_events: EventStreams::new(),
+        }
+    }
+
+    pub async fn text(&self) -> String {
+        self.core.read().await.rope.text()
+    }
+
+    /// Revision of the text, the number of the transactions applied to it
+    pub async fn revision(&self) -> u64 {
+        self.core.read().await.revision
+    }
+
+    /// The text with its revision, taken together
+    pub async fn text_with_revision(&self) -> (String, u64) {
+        let core = self.core.read().await;
+        (core.rope.text(), core.revision)
+    }
+
+    /// Length in bytes
+    pub async fn len(&self) -> usize {
+        self.core.read().await.rope.len
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    pub async fn slice(&self, range: Range<usize>) -> crate::Result<String> {
+        let core = self.core.read().await;
+        if range.start > range.end
+            || !core.rope.is_char_boundary(range.start)
+            || !core.rope.is_char_boundary(range.end)
+        {
+            return Err(crate::Error::BadIndex);
+        }
+        Ok(core.rope.slice(range))
+    }
+
+    ///
+    /// Applies the edits as one undoable transaction. The edits are applied in order, each
+    /// one to the text left by the previous ones. If any edit has the bad range, nothing
+    /// is changed and `Error::BadIndex` is returned.
+    ///
+    pub async fn edit(&self, edits: Vec<TextEdit>) -> crate::Result<()> {
+        if edits.is_empty() {
+            return Ok(());
+        }
+        self.commit(0, |core| {
+            let inverse = core.apply(&edits)?;
+            core.undo.push(inverse);
+            core.redo.clear();
+            Ok(Some(edits))
+        })
+        .await?;
+        Ok(())
+    }
+
+    ///
+    /// Applies the edits made by the view `author` to the text of the revision `base`, see
+    /// [`DocumentSync`]. Returns false and changes nothing if the document has changed since.
+    ///
+    pub(crate) async fn edit_as(
+        &self,
+        author: u64,
+        base: u64,
+        edits: Vec<TextEdit>,
+    ) -> crate::Result<bool> {
+        self.commit(author, |core| {
+            if core.revision != base {
+                return Ok(None);
+            }
+            let inverse = core.apply(&edits)?;
+            core.undo.push(inverse);
+            core.redo.clear();
+            Ok(Some(edits))
+        })
+        .await
+    }
+
+    ///
+    /// Applies the transaction made by `f` and sends its event, unless `f` returns `None`.
+    /// The events are sent in the order of the changes. Returns false if nothing was changed.
+    ///
+    async fn commit(
+        &self,
+        author: u64,
+        f: impl FnOnce(&mut Core) -> crate::Result<Option<Vec<TextEdit>>>,
+    ) -> crate::Result<bool> {
+        let _sending = self.sending.lock().await;
+        let (revision, edits) = {
+            let mut core = self.core.write().await;
+            let edits = match f(&mut core)? {
+                Some(edits) => edits,
+                None => return Ok(false),
+            };
+            core.revision += 1;
+            (core.revision, edits)
+        };
+        self.text_document_events
+            .send_event(
+                TextDocumentEvent::Changed {
+                    revision,
+                    author,
+                    edits,
+                },
+                None,
+            )
+            .await;
+        Ok(true)
+    }
+
+    pub async fn replace(&self, range: Range<usize>, text: &str) -> crate::Result<()> {
+        self.edit(vec![TextEdit::new(range, text)]).await
+    }
+
+    pub async fn insert(&self, pos: usize, text: &str) -> crate::Result<()> {
+        self.edit(vec![TextEdit::insert(pos, text)]).await
+    }
+
+    pub async fn delete(&self, range: Range<usize>) -> crate::Result<()> {
+        self.edit(vec![TextEdit::delete(range)]).await
+    }
+
+    pub async fn can_undo(&self) -> bool {
+        !self.core.read().await.undo.is_empty()
+    }
+
+    pub async fn can_redo(&self) -> bool {
+        !self.core.read().await.redo.is_empty()
+    }
+
+    /// Reverts the last transaction, returns false if there is nothing to undo
+    pub async fn undo(&self) -> crate::Result<bool> {
+        self.commit(0, |core| {
+            let edits = match core.undo.pop() {
+                Some(edits) => edits,
+                None => return Ok(None),
+            };
+            let inverse = core.apply(&edits)?;
+            core.redo.push(inverse);
+            Ok(Some(edits))
+        })
+        .await
+    }
+
+    /// Applies the last undone transaction again, returns false if there is nothing to redo
+    pub async fn redo(&self) -> crate::Result<bool> {
+        self.commit(0, |core| {
+            let edits = match core.redo.pop() {
+                Some(edits) => edits,
+                None => return Ok(None),
+            };
+            let inverse = core.apply(&edits)?;
+            core.undo.push(inverse);
+            Ok(Some(edits))
+        })
+        .await
+    }
+
+    /// Forgets the undo and redo history, e.g. after the document is saved and reloaded
+    pub async fn clear_history(&self) {
+        let mut core = self.core.write().await;
+        core.undo.clear();
+        core.redo.clear();
+    }
+}
+
+impl EventSource<TextDocumentEvent> for TextDocument {
+    fn event_stream(&self) -> EventStream<TextDocumentEvent> {
+        self.text_document_events.create_event_stream()
+    }
+}
+
+// Source of the views' author ids, 0 is for the changes made not by a view
+static NEXT_AUTHOR: AtomicU64 = AtomicU64::new(1);
+
+/// What the view does with the document's change, see [`DocumentSync::changed`]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum SyncAction {
+    /// The change is shown already
+    Skip,
+    /// The change's edits are applied to the view
+    Apply,
+    /// The view is out of sync and loads the text again
+    Reload,
+}
+
+///
+/// Keeps the view which applies its own edits at once in sync with the document: the view
+/// records each own edit with [`DocumentSync::push`] and sends it with
+/// [`TextDocument::edit_as`] on the base revision returned, so the edit made on the stale
+/// text is rejected instead of applied on the shifted offsets. The events of the own edits
+/// are skipped by their author and revision, the others are applied while no own edit is
+/// pending.
+///
+pub(crate) struct DocumentSync {
+    author: u64,
+    // Revision shown, `None` until the text is loaded
+    revision: Option<u64>,
+    // Own edits shown ahead of the document with the revisions they are to make
+    pending: VecDeque<(u64, Vec<TextEdit>)>,
+}
+
+impl DocumentSync {
+    pub(crate) fn new() -> Self {
+        Self {
+            author: NEXT_AUTHOR.fetch_add(1, Ordering::Relaxed),
+            revision: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn author(&self) -> u64 {
+        self.author
+    }
+
+    // The text of the revision is loaded, the own edits not in it are dropped
+    pub(crate) fn reset(&mut self, revision: u64) {
+        self.revision = Some(revision);
+        self.pending.clear();
+    }
+
+    ///
+    /// Records the own edits applied to the view, returns the revision they are made on, or
+    /// `None` if the text is not loaded yet
+    ///
+    pub(crate) fn push(&mut self, edits: Vec<TextEdit>) -> Option<u64> {
+        let base = self.revision? + self.pending.len() as u64;
+        self.pending.push_back((base + 1, edits));
+        Some(base)
+    }
+
+    pub(crate) fn changed(&mut self, revision: u64, author: u64, edits: &[TextEdit]) -> SyncAction {
+        let current = match self.revision {
+            Some(current) => current,
+            None => return SyncAction::Reload,
+        };
+        if revision <= current {
+            return SyncAction::Skip;
+        }
+        if revision != current + 1 {
+            return SyncAction::Reload;
+        }
+        self.revision = Some(revision);
+        match self.pending.front() {
+            None => SyncAction::Apply,
+            Some((expected, own)) if author == self.author && *expected == revision => {
+                if own.as_slice() == edits {
+                    self.pending.pop_front();
+                    SyncAction::Skip
+                } else {
+                    SyncAction::Reload
+                }
+            }
+            // The own edits pending would shift the others' edits
+            Some(_) => SyncAction::Reload,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_std::task::block_on;
+
+    use super::*;
+
+    #[test]
+    fn edits_across_chunks() {
+        block_on(async {
+            let text = "ab€".repeat(CHUNK_SIZE);
+            let doc = TextDocument::new(&text);
+            let mut expected = text.clone();
+            let range = 1000..3502;
+            assert_eq!(
+                doc.slice(range.clone()).await.unwrap(),
+                &text[range.clone()]
+            );
+            doc.replace(range.clone(), "xyz").await.unwrap();
+            expected.replace_range(range, "xyz");
+            doc.insert(expected.len(), "end").await.unwrap();
+            expected.push_str("end");
+            doc.delete(0..2).await.unwrap();
+            expected.replace_range(0..2, "");
+            assert_eq!(doc.text().await, expected);
+            assert_eq!(doc.len().await, expected.len());
+        })
+    }
+
+    #[test]
+    fn bad_edit_changes_nothing() {
+        block_on(async {
+            let doc = TextDocument::new("a€b");
+            // The second edit splits the euro sign
+            let edits = vec![TextEdit::insert(0, "x"), TextEdit::delete(2..3)];
+            assert!(matches!(doc.edit(edits).await, Err(crate::Error::BadIndex)));
+            assert!(matches!(
+                doc.slice(0..10).await,
+                Err(crate::Error::BadIndex)
+            ));
+            assert_eq!(doc.text().await, "a€b");
+            assert!(!doc.can_undo().await);
+            assert_eq!(doc.revision().await, 0);
+        })
+    }
+
+    #[test]
+    fn small_chunks_are_merged() {
+        let mut rope = Rope::new(&"a".repeat(CHUNK_SIZE * 4));
+        let mut expected = rope.text();
+        for i in 0..CHUNK_SIZE * 3 {
+            let pos = i * 7 % (expected.len() - 1);
+            rope.replace(pos..pos + 1, "");
+            expected.replace_range(pos..pos + 1, "");
+        }
+        assert_eq!(rope.text(), expected);
+        assert!(rope.chunks.iter().all(|v| v.len() >= CHUNK_SIZE / 2));
+        assert!(rope.chunks.len() <= 2);
+    }
+
+    #[test]
+    fn undo_and_redo_transactions() {
+        block_on(async {
+            let doc = TextDocument::new("hello world");
+            doc.edit(vec![TextEdit::delete(0..6), TextEdit::insert(5, "!")])
+                .await
+                .unwrap();
+            doc.insert(0, "big ").await.unwrap();
+            assert_eq!(doc.text().await, "big world!");
+            assert!(doc.undo().await.unwrap());
+            assert_eq!(doc.text().await, "world!");
+            assert!(doc.undo().await.unwrap());
+            assert_eq!(doc.text().await, "hello world");
+            assert!(!doc.undo().await.unwrap());
+            // Undo is the transaction too, the failed undo changes nothing
+            assert_eq!(doc.revision().await, 4);
+            assert!(doc.redo().await.unwrap());
+            assert_eq!(doc.text().await, "world!");
+            doc.insert(0, "new ").await.unwrap();
+            assert!(!doc.can_redo().await);
+            assert_eq!(doc.text().await, "new world!");
+        })
+    }
+
+    // The view over the document for the tests: the text shown and its sync state
+    struct View {
+        text: String,
+        sync: DocumentSync,
+    }
+
+    impl View {
+        async fn load(doc: &TextDocument) -> Self {
+            let mut view = View {
+                text: String::new(),
+                sync: DocumentSync::new(),
+            };
+            view.reload(doc).await;
+            view
+        }
+
+        async fn reload(&mut self, doc: &TextDocument) {
+            let (text, revision) = doc.text_with_revision().await;
+            self.text = text;
+            self.sync.reset(revision);
+        }
+
+        // Applies the own edit at once, returns what to send to the document
+        fn edit(&mut self, edit: TextEdit) -> (u64, Vec<TextEdit>) {
+            self.text.replace_range(edit.range.clone(), &edit.text);
+            let base = self.sync.push(vec![edit.clone()]).unwrap();
+            (base, vec![edit])
+        }
+
+        async fn send(&mut self, doc: &TextDocument, (base, edits): (u64, Vec<TextEdit>)) {
+            if !doc.edit_as(self.sync.author(), base, edits).await.unwrap() {
+                self.reload(doc).await;
+            }
+        }
+
+        async fn changed(&mut self, doc: &TextDocument, event: &TextDocumentEvent) {
+            let TextDocumentEvent::Changed {
+                revision,
+                author,
+                edits,
+            } = event;
+            match self.sync.changed(*revision, *author, edits) {
+                SyncAction::Skip => (),
+                SyncAction::Apply => {
+                    for edit in edits {
+                        self.text.replace_range(edit.range.clone(), &edit.text);
+                    }
+                }
+                SyncAction::Reload => self.reload(doc).await,
+            }
+        }
+    }
+
+    fn changed(revision: u64, author: u64, edits: &[TextEdit]) -> TextDocumentEvent {
+        TextDocumentEvent::Changed {
+            revision,
+            author,
+            edits: edits.to_vec(),
+        }
+    }
+
+    #[test]
+    fn two_views_stay_in_sync() {
+        block_on(async {
+            let doc = TextDocument::new("hello");
+            let mut a = View::load(&doc).await;
+            let mut b = View::load(&doc).await;
+
+            // Both views edit before the document reports either edit, b's edit goes first
+            let a_edit = a.edit(TextEdit::insert(5, " world"));
+            let b_edit = b.edit(TextEdit::insert(0, "oh, "));
+            let b_event = changed(1, b.sync.author(), &b_edit.1);
+            b.send(&doc, b_edit).await;
+            // b's event reaches a with a's own edit pending, so a reloads
+            a.changed(&doc, &b_event).await;
+            assert_eq!(a.text, "oh, hello");
+            // a's edit was made on the stale text, it's rejected
+            a.send(&doc, a_edit).await;
+            b.changed(&doc, &b_event).await;
+            assert_eq!(doc.text().await, "oh, hello");
+            assert_eq!(a.text, "oh, hello");
+            assert_eq!(b.text, "oh, hello");
+
+            // Own edits are skipped, the other view's ones applied after them
+            let first = a.edit(TextEdit::insert(9, "!"));
+            let second = a.edit(TextEdit::delete(0..4));
+            let a_events = [
+                changed(2, a.sync.author(), &first.1),
+                changed(3, a.sync.author(), &second.1),
+            ];
+            a.send(&doc, first).await;
+            a.send(&doc, second).await;
+            for event in &a_events {
+                b.changed(&doc, event).await;
+            }
+            let b_edit = b.edit(TextEdit::insert(0, "well, "));
+            let b_event = changed(4, b.sync.author(), &b_edit.1);
+            b.send(&doc, b_edit).await;
+            for event in a_events.iter().chain([&b_event]) {
+                a.changed(&doc, event).await;
+            }
+            b.changed(&doc, &b_event).await;
+            assert_eq!(doc.text().await, "well, hello!");
+            assert_eq!(a.text, "well, hello!");
+            assert_eq!(b.text, "well, hello!");
+        })
+    }
+}
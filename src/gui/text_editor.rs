@@ -0,0 +1,2141 @@
+use std::{borrow::Cow, collections::HashSet, ops::Range};
+
+use async_event_streams::{
+    spawn_event_pipe, EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use futures::task::{Spawn, SpawnExt};
+use typed_builder::TypedBuilder;
+use windows::{
+    core::{InParam, HSTRING},
+    Foundation::Numerics::{Matrix3x2, Vector2, Vector3},
+    Graphics::{RectInt32, SizeInt32},
+    Win32::{
+        Foundation::{BOOL, E_INVALIDARG},
+        Graphics::{
+            Direct2D::{
+                Common::{D2D_POINT_2F, D2D_RECT_F},
+                ID2D1DeviceContext, ID2D1SolidColorBrush, D2D1_BRUSH_PROPERTIES,
+            },
+            DirectWrite::{
+                IDWriteTextFormat, IDWriteTextLayout, DWRITE_HIT_TEST_METRICS, DWRITE_TEXT_METRICS,
+                DWRITE_WORD_WRAPPING_NO_WRAP,
+            },
+        },
+    },
+    UI::{
+        Color, Colors,
+        Composition::{
+            AnimationIterationBehavior, Compositor, ContainerVisual, SpriteVisual, Visual,
+        },
+    },
+};
+use winit::event::{ElementState, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode};
+
+use crate::{
+    geometry::{Alignment, Point, Rect, Size},
+    handle_err, on_err,
+    window::{
+        caret_blink_time, caret_width, clipboard, dwrite_factory, Misspelling, SpellChecker,
+        SpellingAction,
+    },
+};
+
+#[cfg(feature = "accessibility")]
+use super::accessibility::{set_accessible, Accessible, AccessibleRole};
+use super::{
+    animation_stepper::start_animation,
+    attach,
+    dispatch::DispatchQueue,
+    menu::d2d_color,
+    rich_text::range_rects,
+    text::{default_text_rendering, Font, Text, TextLayout, TextParams, TextRendering},
+    text_document::{DocumentSync, SyncAction},
+    text_lines::{layout_rows, line_operation, wrapped_layout, LineOperation},
+    time_span, Menu, MenuEvent, MenuItem, MenuParams, OverlayHost, Panel, PanelEvent, Searchable,
+    TextDocument, TextDocumentEvent, TextEdit, VirtualSurface, VirtualSurfaceEvent,
+    VirtualSurfaceParams,
+};
+
+// Space between the panel's left edge and the text
+const PADDING: f32 = 4.;
+// Lines scrolled by one wheel notch
+const WHEEL_LINES: f32 = 3.;
+// Character drawn in place of each character of the password
+const PASSWORD_CHAR: char = '\u{25CF}';
+// Eye glyph of the Segoe MDL2 Assets font
+const REVEAL_GLYPH: &str = "\u{E7B3}";
+const REVEAL_BUTTON_WIDTH: f32 = 32.;
+// Width of the scroll bar's thumb drawn over the right edge of the text
+const SCROLLBAR_WIDTH: f32 = 6.;
+const MIN_THUMB_HEIGHT: f32 = 16.;
+// Height of the zigzag under the misspelled words, also the length of its segments
+const SQUIGGLE_STEP: f32 = 2.;
+// Ids of the spelling menu items, the suggestions have their indices as ids
+const IGNORE_ITEM: usize = usize::MAX - 1;
+const ADD_TO_DICTIONARY_ITEM: usize = usize::MAX;
+
+fn default_font() -> Font {
+    Font {
+        family: "Consolas".to_string(),
+        size: 14.,
+        weight: 400,
+        italic: false,
+    }
+}
+
+fn text_format(font: &Font) -> crate::Result<IDWriteTextFormat> {
+    let format = font.text_format()?;
+    unsafe { format.SetWordWrapping(DWRITE_WORD_WRAPPING_NO_WRAP) }?;
+    Ok(format)
+}
+
+// DirectWrite positions are in UTF-16 code units, the document's are in UTF-8 bytes. Each
+// character of the masked text is drawn by one bullet.
+fn utf16_index(text: &str, offset: usize, masked: bool) -> u32 {
+    if masked {
+        text[..offset].chars().count() as u32
+    } else {
+        text[..offset].encode_utf16().count() as u32
+    }
+}
+
+fn byte_offset(text: &str, index: u32, masked: bool) -> usize {
+    let mut count = 0;
+    for (offset, c) in text.char_indices() {
+        if count >= index {
+            return offset;
+        }
+        count += if masked { 1 } else { c.len_utf16() as u32 };
+    }
+    text.len()
+}
+
+// Words are runs of the characters of the same class
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+// Zigzag line under the misspelled word, its lower points are on the bottom
+fn draw_squiggle(
+    context: &ID2D1DeviceContext,
+    brush: &ID2D1SolidColorBrush,
+    left: f32,
+    right: f32,
+    bottom: f32,
+) {
+    let mut x = left;
+    let mut up = true;
+    while x < right {
+        let next = (x + SQUIGGLE_STEP).min(right);
+        let rise = next - x;
+        let (from, to) = if up {
+            (bottom, bottom - rise)
+        } else {
+            (bottom - SQUIGGLE_STEP, bottom - SQUIGGLE_STEP + rise)
+        };
+        unsafe {
+            context.DrawLine(
+                D2D_POINT_2F { x, y: from },
+                D2D_POINT_2F { x: next, y: to },
+                brush,
+                1.,
+                InParam::null(),
+            )
+        };
+        x = next;
+        up = !up;
+    }
+}
+
+// Misspelled word under the context menu and what the menu items replace it with
+struct SpellingMenu {
+    range: Range<usize>,
+    word: String,
+    replacements: Vec<String>,
+}
+
+fn spelling_menu_items(action: &SpellingAction, replacements: &[String]) -> Vec<MenuItem> {
+    let mut items: Vec<_> = match action {
+        SpellingAction::Delete => vec![MenuItem::new(0, "&Delete repeated word")],
+        _ => replacements
+            .iter()
+            .enumerate()
+            .map(|(index, text)| MenuItem::new(index, text.replace('&', "&&")))
+            .collect(),
+    };
+    if items.is_empty() {
+        items.push(MenuItem::new(0, "No suggestions").enabled(false));
+    }
+    items.push(MenuItem::separator());
+    items.push(MenuItem::new(IGNORE_ITEM, "&Ignore"));
+    items.push(MenuItem::new(ADD_TO_DICTIONARY_ITEM, "&Add to dictionary"));
+    items
+}
+
+struct Line {
+    text: String,
+    width: f32,
+    // Number of the rows the wrapped line takes, 1 if the line is not wrapped
+    rows: usize,
+    // Laid out for the old wrapping width or masking, the width and the rows are kept until
+    // the line comes into view
+    stale: bool,
+}
+
+impl Line {
+    fn new(
+        format: &IDWriteTextFormat,
+        text: &str,
+        masked: bool,
+        wrap_width: Option<f32>,
+    ) -> crate::Result<Self> {
+        let layout = wrapped_layout(format, &shown_text(display_text(text), masked), wrap_width)?;
+        let (width, rows) = layout_rows(&layout)?;
+        Ok(Line {
+            text: text.to_string(),
+            width,
+            rows,
+            stale: false,
+        })
+    }
+}
+
+// The line without the carriage return of the CRLF line break
+fn display_text(text: &str) -> &str {
+    text.strip_suffix('\r').unwrap_or(text)
+}
+
+// The text as drawn, the password is drawn by bullets
+fn shown_text(text: &str, masked: bool) -> Cow<str> {
+    if masked {
+        Cow::Owned(text.chars().map(|_| PASSWORD_CHAR).collect())
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+struct Core {
+    font: Font,
+    line_height: f32,
+    lines: Vec<Line>,
+    // Byte offset of each line in the document
+    line_starts: Vec<usize>,
+    // Index of the first row of each line, the wrapped lines take several rows
+    row_starts: Vec<usize>,
+    word_wrap: bool,
+    caret: usize,
+    // The other end of the selection, equal to the caret if nothing is selected
+    anchor: usize,
+    // Horizontal position kept by the vertical moves through the shorter lines
+    preferred_x: Option<f32>,
+    scroll: Vector2,
+    size: Vector2,
+    mouse_pos: Option<Vector2>,
+    focused: bool,
+    dragging: bool,
+    // Mouse position and scroll when the scroll bar's thumb was pressed
+    thumb_drag: Option<(f32, f32)>,
+    password: bool,
+    revealed: bool,
+    // The password is revealed while the reveal button is held
+    revealing: bool,
+    // Revision of the document shown and the own edits applied to the lines ahead of it
+    sync: DocumentSync,
+    // Checker of the spell checking language, created once for the editor, no spell checking
+    // if not set
+    spell_checker: Option<SpellChecker>,
+    // Words ignored by the spell checking of this editor
+    ignored: HashSet<String>,
+    spelling_menu: Option<SpellingMenu>,
+}
+
+impl Core {
+    fn new(text: &str, font: Font, password: bool) -> crate::Result<Self> {
+        let format = text_format(&font)?;
+        let mut metrics = DWRITE_TEXT_METRICS::default();
+        unsafe { wrapped_layout(&format, "", None)?.GetMetrics(&mut metrics) }?;
+        let mut core = Core {
+            font,
+            line_height: metrics.height,
+            lines: Vec::new(),
+            line_starts: Vec::new(),
+            row_starts: Vec::new(),
+            word_wrap: false,
+            caret: 0,
+            anchor: 0,
+            preferred_x: None,
+            scroll: Vector2::default(),
+            size: Vector2::default(),
+            mouse_pos: None,
+            focused: false,
+            dragging: false,
+            thumb_drag: None,
+            password,
+            revealed: false,
+            revealing: false,
+            sync: DocumentSync::new(),
+            spell_checker: None,
+            ignored: HashSet::new(),
+            spelling_menu: None,
+        };
+        core.load(text)?;
+        Ok(core)
+    }
+
+    fn masked(&self) -> bool {
+        self.password && !self.revealed
+    }
+
+    // Width the lines are wrapped to, none if they are not wrapped or the size is not known yet
+    fn wrap_width(&self) -> Option<f32> {
+        (self.word_wrap && !self.password && self.size.X > 0.)
+            .then(|| (self.size.X - PADDING * 2. - caret_width() as f32 - SCROLLBAR_WIDTH).max(1.))
+    }
+
+    fn layout(&self, format: &IDWriteTextFormat, line: usize) -> crate::Result<IDWriteTextLayout> {
+        let text = shown_text(display_text(&self.lines[line].text), self.masked());
+        wrapped_layout(format, &text, self.wrap_width())
+    }
+
+    // The password is never spell checked, even when revealed
+    fn spell_checker(&self) -> Option<&SpellChecker> {
+        self.spell_checker.as_ref().filter(|_| !self.password)
+    }
+
+    // Misspelled words of the line, in UTF-16 code units of its text, except the ignored ones
+    fn misspellings(&self, checker: &SpellChecker, line: usize) -> crate::Result<Vec<Misspelling>> {
+        let text = display_text(&self.lines[line].text);
+        let wide: Vec<u16> = text.encode_utf16().collect();
+        Ok(checker
+            .check(text)?
+            .into_iter()
+            .filter(|v| {
+                wide.get(v.range.clone()).map_or(false, |word| {
+                    !self.ignored.contains(&String::from_utf16_lossy(word))
+                })
+            })
+            .collect())
+    }
+
+    // Byte range and corrective action of the misspelled word at the position
+    fn misspelling_at(
+        &self,
+        checker: &SpellChecker,
+        pos: usize,
+    ) -> crate::Result<Option<(Range<usize>, SpellingAction)>> {
+        let line = self.line_of(pos);
+        let start = self.line_starts[line];
+        let text = display_text(&self.lines[line].text);
+        let index = utf16_index(text, (pos - start).min(text.len()), false) as usize;
+        Ok(self
+            .misspellings(checker, line)?
+            .into_iter()
+            .find(|v| v.range.start <= index && index <= v.range.end)
+            .map(|v| {
+                let range = start + byte_offset(text, v.range.start as u32, false)
+                    ..start + byte_offset(text, v.range.end as u32, false);
+                (range, v.action)
+            }))
+    }
+
+    // Text of the range inside one line, `None` if the range is not there
+    fn text_in(&self, range: &Range<usize>) -> Option<&str> {
+        if range.start > range.end || range.end > self.len() {
+            return None;
+        }
+        let line = self.line_of(range.start);
+        let start = self.line_starts[line];
+        self.lines[line]
+            .text
+            .get(range.start - start..range.end - start)
+    }
+
+    fn load(&mut self, text: &str) -> crate::Result<()> {
+        let format = text_format(&self.font)?;
+        let masked = self.masked();
+        let wrap_width = self.wrap_width();
+        self.lines = text
+            .split('\n')
+            .map(|v| Line::new(&format, v, masked, wrap_width))
+            .collect::<crate::Result<_>>()?;
+        self.update_line_starts();
+        self.caret = self.caret.min(self.len());
+        self.anchor = self.caret;
+        Ok(())
+    }
+
+    // Shows or masks the password, the widths of the lines change
+    fn set_revealed(&mut self, revealed: bool) -> crate::Result<()> {
+        self.revealed = revealed;
+        self.relayout()
+    }
+
+    ///
+    /// Lays out the lines again after the change of their shown text or of the wrapping width.
+    /// Only the lines in view are laid out at once, the others when they come into view, so
+    /// resizing the long text doesn't lay out all of it.
+    ///
+    fn relayout(&mut self) -> crate::Result<()> {
+        for line in &mut self.lines {
+            line.stale = true;
+        }
+        self.layout_shown()?;
+        self.clamp_scroll();
+        Ok(())
+    }
+
+    // Lays out the stale lines in view and the caret's one, returns the first of them
+    fn layout_shown(&mut self) -> crate::Result<Option<usize>> {
+        let format = text_format(&self.font)?;
+        let masked = self.masked();
+        let wrap_width = self.wrap_width();
+        let mut first: Option<usize> = None;
+        loop {
+            let caret_line = self.line_of(self.caret);
+            let shown = self.lines_in(self.scroll.Y, self.scroll.Y + self.size.Y);
+            let caret_shown = shown.contains(&caret_line);
+            let stale: Vec<_> = shown
+                .chain((!caret_shown).then_some(caret_line))
+                .filter(|v| self.lines[*v].stale)
+                .collect();
+            if stale.is_empty() {
+                return Ok(first);
+            }
+            // The rows of the laid out lines change, other lines may come into view
+            for line in stale {
+                let laid_out = Line::new(&format, &self.lines[line].text, masked, wrap_width)?;
+                self.lines[line] = laid_out;
+                first = Some(first.map_or(line, |v| v.min(line)));
+            }
+            self.update_line_starts();
+            self.clamp_scroll();
+        }
+    }
+
+    fn update_line_starts(&mut self) {
+        let (mut start, mut row) = (0, 0);
+        (self.line_starts, self.row_starts) = self
+            .lines
+            .iter()
+            .map(|line| {
+                let starts = (start, row);
+                start += line.text.len() + 1;
+                row += line.rows;
+                starts
+            })
+            .unzip();
+    }
+
+    fn rows(&self) -> usize {
+        self.row_starts.last().unwrap_or(&0) + self.lines.last().map_or(0, |v| v.rows)
+    }
+
+    // First row of the line, the number of rows for the lines past the end
+    fn row_of_line(&self, line: usize) -> usize {
+        self.row_starts
+            .get(line)
+            .copied()
+            .unwrap_or_else(|| self.rows())
+    }
+
+    fn line_of_row(&self, row: usize) -> usize {
+        self.row_starts.partition_point(|v| *v <= row).max(1) - 1
+    }
+
+    fn line_top(&self, line: usize) -> f32 {
+        self.row_of_line(line) as f32 * self.line_height
+    }
+
+    fn len(&self) -> usize {
+        self.line_starts.last().unwrap_or(&0) + self.lines.last().map_or(0, |v| v.text.len())
+    }
+
+    fn line_of(&self, pos: usize) -> usize {
+        self.line_starts.partition_point(|v| *v <= pos).max(1) - 1
+    }
+
+    fn is_char_boundary(&self, pos: usize) -> bool {
+        let line = self.line_of(pos);
+        let text = &self.lines[line].text;
+        pos <= self.len() && text.is_char_boundary(pos - self.line_starts[line])
+    }
+
+    fn line_end(&self, line: usize) -> usize {
+        self.line_starts[line] + display_text(&self.lines[line].text).len()
+    }
+
+    fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    fn selected_lines(&self) -> Range<usize> {
+        let selection = self.selection();
+        self.line_of(selection.start)..self.line_of(selection.end) + 1
+    }
+
+    // The wrapped lines fit the width, so only the rows are scrolled
+    fn content_size(&self) -> SizeInt32 {
+        let width = match self.wrap_width() {
+            Some(_) => self.size.X,
+            None => {
+                let width = self.lines.iter().fold(0., |w: f32, v| w.max(v.width));
+                width + PADDING * 2. + caret_width() as f32
+            }
+        };
+        SizeInt32 {
+            Width: width.ceil() as i32,
+            Height: (self.rows() as f32 * self.line_height).ceil().max(1.) as i32,
+        }
+    }
+
+    fn char_before(&self, pos: usize) -> Option<char> {
+        let line = self.line_of(pos);
+        let col = pos - self.line_starts[line];
+        if col > 0 {
+            self.lines[line].text[..col].chars().next_back()
+        } else {
+            (line > 0).then_some('\n')
+        }
+    }
+
+    fn char_after(&self, pos: usize) -> Option<char> {
+        let line = self.line_of(pos);
+        let col = pos - self.line_starts[line];
+        match self.lines[line].text[col..].chars().next() {
+            Some(c) => Some(c),
+            None => (line + 1 < self.lines.len()).then_some('\n'),
+        }
+    }
+
+    fn prev_char(&self, pos: usize) -> usize {
+        pos - self.char_before(pos).map_or(0, char::len_utf8)
+    }
+
+    fn next_char(&self, pos: usize) -> usize {
+        pos + self.char_after(pos).map_or(0, char::len_utf8)
+    }
+
+    // The password is one word, its words shouldn't be revealed by the caret jumps
+    fn word_start(&self, mut pos: usize) -> usize {
+        if self.masked() {
+            return 0;
+        }
+        while let Some(c) = self.char_before(pos).filter(|v| v.is_whitespace()) {
+            pos -= c.len_utf8();
+        }
+        if let Some(class) = self.char_before(pos).map(char_class) {
+            while let Some(c) = self.char_before(pos).filter(|v| char_class(*v) == class) {
+                pos -= c.len_utf8();
+            }
+        }
+        pos
+    }
+
+    fn word_end(&self, mut pos: usize) -> usize {
+        if self.masked() {
+            return self.len();
+        }
+        if let Some(class) = self.char_after(pos).map(char_class) {
+            while let Some(c) = self.char_after(pos).filter(|v| char_class(*v) == class) {
+                pos += c.len_utf8();
+            }
+        }
+        while let Some(c) = self.char_after(pos).filter(|v| v.is_whitespace()) {
+            pos += c.len_utf8();
+        }
+        pos
+    }
+
+    // Word or run of spaces or punctuation around the position, for the double click
+    fn word_at(&self, pos: usize) -> Range<usize> {
+        if self.masked() {
+            return 0..self.len();
+        }
+        let class = match self.char_after(pos).filter(|v| *v != '\n') {
+            Some(c) => char_class(c),
+            None => return pos..pos,
+        };
+        let (mut start, mut end) = (pos, pos);
+        while let Some(c) = self.char_before(start).filter(|v| char_class(*v) == class) {
+            start -= c.len_utf8();
+        }
+        while let Some(c) = self.char_after(end).filter(|v| char_class(*v) == class) {
+            end += c.len_utf8();
+        }
+        start..end
+    }
+
+    // Top left corner of the caret placed at the position, in content coordinates
+    fn caret_point(&self, format: &IDWriteTextFormat, pos: usize) -> crate::Result<Vector2> {
+        let line = self.line_of(pos);
+        let text = display_text(&self.lines[line].text);
+        let col = (pos - self.line_starts[line]).min(text.len());
+        let (mut x, mut y) = (0., 0.);
+        let mut metrics = DWRITE_HIT_TEST_METRICS::default();
+        unsafe {
+            self.layout(format, line)?.HitTestTextPosition(
+                utf16_index(text, col, self.masked()),
+                false,
+                &mut x,
+                &mut y,
+                &mut metrics,
+            )
+        }?;
+        Ok(Vector2 {
+            X: PADDING + x,
+            Y: self.line_top(line) + metrics.top,
+        })
+    }
+
+    // Position in the row of the line nearest to the horizontal position in content coordinates
+    fn pos_in_row(
+        &self,
+        format: &IDWriteTextFormat,
+        line: usize,
+        row: usize,
+        x: f32,
+    ) -> crate::Result<usize> {
+        let text = display_text(&self.lines[line].text);
+        let masked = self.masked();
+        let mut is_trailing = BOOL::default();
+        let mut is_inside = BOOL::default();
+        let mut metrics = DWRITE_HIT_TEST_METRICS::default();
+        unsafe {
+            self.layout(format, line)?.HitTestPoint(
+                x - PADDING,
+                (row as f32 + 0.5) * self.line_height,
+                &mut is_trailing,
+                &mut is_inside,
+                &mut metrics,
+            )
+        }?;
+        let mut index = metrics.textPosition;
+        if is_trailing.as_bool() {
+            index += metrics.length;
+        }
+        Ok(self.line_starts[line] + byte_offset(text, index, masked))
+    }
+
+    // Position at the horizontal position in the row counted over all lines
+    fn pos_in_text_row(
+        &self,
+        format: &IDWriteTextFormat,
+        row: usize,
+        x: f32,
+    ) -> crate::Result<usize> {
+        let line = self.line_of_row(row);
+        self.pos_in_row(format, line, row - self.row_starts[line], x)
+    }
+
+    // Position under the point in panel coordinates
+    fn pos_at(&self, format: &IDWriteTextFormat, point: Vector2) -> crate::Result<usize> {
+        let y = point.Y + self.scroll.Y;
+        let row = ((y / self.line_height).max(0.) as usize).min(self.rows() - 1);
+        self.pos_in_text_row(format, row, point.X + self.scroll.X)
+    }
+
+    // Position on the row `rows` above or below the caret, at the preferred column
+    fn vertical_move(&mut self, format: &IDWriteTextFormat, rows: isize) -> crate::Result<usize> {
+        let point = self.caret_point(format, self.caret)?;
+        let target = (point.Y / self.line_height).round() as isize + rows;
+        if target < 0 {
+            return Ok(0);
+        }
+        if target as usize >= self.rows() {
+            return Ok(self.len());
+        }
+        let x = *self.preferred_x.get_or_insert(point.X);
+        self.pos_in_text_row(format, target as usize, x)
+    }
+
+    ///
+    /// Start and end of the caret's row. The end of the row broken by the wrapping is before
+    /// its last character, the position after it is the start of the next row.
+    ///
+    fn caret_row_bounds(&self, format: &IDWriteTextFormat) -> crate::Result<Range<usize>> {
+        let line = self.line_of(self.caret);
+        let point = self.caret_point(format, self.caret)?;
+        let row = ((point.Y - self.line_top(line)) / self.line_height).round() as usize;
+        let start = self.pos_in_row(format, line, row, f32::MIN)?;
+        let end = self.pos_in_row(format, line, row, f32::MAX)?;
+        Ok(if start < end && end < self.line_end(line) {
+            start..self.prev_char(end)
+        } else {
+            start..end
+        })
+    }
+
+    ///
+    /// Moves the caret, keeping the anchor if the selection is extended. Returns the lines
+    /// to redraw if the selection has changed.
+    ///
+    fn move_to(&mut self, pos: usize, extend: bool) -> Option<Range<usize>> {
+        let old = self.selection();
+        let old_lines = self.selected_lines();
+        self.caret = pos.min(self.len());
+        if !extend {
+            self.anchor = self.caret;
+        }
+        if old.is_empty() && self.selection().is_empty() {
+            return None;
+        }
+        let new_lines = self.selected_lines();
+        Some(old_lines.start.min(new_lines.start)..old_lines.end.max(new_lines.end))
+    }
+
+    fn select(&mut self, range: Range<usize>) -> Option<Range<usize>> {
+        self.move_to(range.start, false);
+        self.move_to(range.end, true)
+    }
+
+    // Replaces the text in the lines, returns the first changed line
+    fn replace_lines(
+        &mut self,
+        format: &IDWriteTextFormat,
+        edit: &TextEdit,
+    ) -> crate::Result<usize> {
+        if edit.range.start > edit.range.end || edit.range.end > self.len() {
+            return Err(crate::Error::BadIndex);
+        }
+        let first = self.line_of(edit.range.start);
+        let last = self.line_of(edit.range.end);
+        let wrap_width = self.wrap_width();
+        let start = edit.range.start - self.line_starts[first];
+        let end = edit.range.end - self.line_starts[last];
+        if !self.lines[first].text.is_char_boundary(start)
+            || !self.lines[last].text.is_char_boundary(end)
+        {
+            return Err(crate::Error::BadIndex);
+        }
+        let joined = [
+            &self.lines[first].text[..start],
+            &edit.text,
+            &self.lines[last].text[end..],
+        ]
+        .concat();
+        let masked = self.masked();
+        let lines = joined
+            .split('\n')
+            .map(|v| Line::new(format, v, masked, wrap_width))
+            .collect::<crate::Result<Vec<_>>>()?;
+        self.lines.splice(first..last + 1, lines);
+        self.update_line_starts();
+        Ok(first)
+    }
+
+    ///
+    /// Applies the edits made by the document's other users, moving the caret and the
+    /// anchor with the text around them. Returns the first changed line.
+    ///
+    fn apply(&mut self, edits: &[TextEdit]) -> crate::Result<usize> {
+        let format = text_format(&self.font)?;
+        let mut first = self.lines.len();
+        for edit in edits {
+            first = first.min(self.replace_lines(&format, edit)?);
+            for pos in [&mut self.caret, &mut self.anchor] {
+                if *pos >= edit.range.end {
+                    *pos = *pos - edit.range.len() + edit.text.len();
+                } else if *pos > edit.range.start {
+                    *pos = edit.range.start + edit.text.len();
+                }
+            }
+        }
+        self.preferred_x = None;
+        Ok(first)
+    }
+
+    ///
+    /// Replaces the selection with the text, returns the edit for the document and the
+    /// first changed line
+    ///
+    fn replace_selection(&mut self, text: &str) -> crate::Result<(TextEdit, usize)> {
+        let edit = TextEdit::new(self.selection(), text);
+        let first = self.replace_lines(&text_format(&self.font)?, &edit)?;
+        self.caret = edit.range.start + text.len();
+        self.anchor = self.caret;
+        self.preferred_x = None;
+        Ok((edit, first))
+    }
+
+    fn selected_text(&self) -> String {
+        let selection = self.selection();
+        let first = self.line_of(selection.start);
+        let last = self.line_of(selection.end);
+        let start = selection.start - self.line_starts[first];
+        let end = selection.end - self.line_starts[last];
+        if first == last {
+            return self.lines[first].text[start..end].to_string();
+        }
+        let mut text = self.lines[first].text[start..].to_string();
+        for line in &self.lines[first + 1..last] {
+            text.push('\n');
+            text.push_str(&line.text);
+        }
+        text.push('\n');
+        text.push_str(&self.lines[last].text[..end]);
+        text
+    }
+
+    // Scrolls the least distance to show the caret, keeps the scroll inside the content
+    fn scroll_to_caret(&mut self, format: &IDWriteTextFormat) -> crate::Result<()> {
+        let Vector2 { X: x, Y: y } = self.caret_point(format, self.caret)?;
+        let right = x + caret_width() as f32 + PADDING;
+        if x - PADDING < self.scroll.X {
+            self.scroll.X = x - PADDING;
+        } else if right > self.scroll.X + self.size.X {
+            self.scroll.X = right - self.size.X;
+        }
+        if y < self.scroll.Y {
+            self.scroll.Y = y;
+        } else if y + self.line_height > self.scroll.Y + self.size.Y {
+            self.scroll.Y = y + self.line_height - self.size.Y;
+        }
+        self.clamp_scroll();
+        Ok(())
+    }
+
+    fn clamp_scroll(&mut self) {
+        let content = self.content_size();
+        self.scroll.X = self
+            .scroll
+            .X
+            .min(content.Width as f32 - self.size.X)
+            .max(0.);
+        self.scroll.Y = self
+            .scroll
+            .Y
+            .min(content.Height as f32 - self.size.Y)
+            .max(0.);
+    }
+
+    // Lines having the rows between the vertical positions
+    fn lines_in(&self, top: f32, bottom: f32) -> Range<usize> {
+        let rows = self.rows();
+        let first = ((top / self.line_height).max(0.) as usize).min(rows);
+        let last = ((bottom / self.line_height).ceil().max(0.) as usize).min(rows);
+        if last <= first {
+            return 0..0;
+        }
+        self.line_of_row(first)..self.line_of_row(last - 1) + 1
+    }
+
+    fn lines_rect(&self, lines: Range<usize>) -> RectInt32 {
+        let content = self.content_size();
+        let top = self.line_top(lines.start).floor() as i32;
+        let bottom = self.line_top(lines.end).ceil() as i32;
+        RectInt32 {
+            X: 0,
+            Y: top,
+            Width: content.Width,
+            Height: bottom.min(content.Height) - top,
+        }
+    }
+
+    ///
+    /// Rectangles of the selection on the line's rows in content coordinates, with the line
+    /// break shown as a narrow space after the line
+    ///
+    fn selection_rects(
+        &self,
+        layout: &IDWriteTextLayout,
+        line: usize,
+        selection: &Range<usize>,
+    ) -> crate::Result<Vec<D2D_RECT_F>> {
+        let start = self.line_starts[line];
+        let text = display_text(&self.lines[line].text);
+        let end = start + self.lines[line].text.len();
+        if selection.start > end || selection.end <= start || selection.is_empty() {
+            return Ok(Vec::new());
+        }
+        let index = |pos: usize| {
+            let col = (pos.clamp(start, end) - start).min(text.len());
+            utf16_index(text, col, self.masked())
+        };
+        let range = index(selection.start)..index(selection.end);
+        let mut rects = if range.is_empty() {
+            Vec::new()
+        } else {
+            range_rects(layout, &range)?
+        };
+        if selection.end > end {
+            let (mut x, mut y) = (0., 0.);
+            let mut metrics = DWRITE_HIT_TEST_METRICS::default();
+            unsafe { layout.HitTestTextPosition(range.end, false, &mut x, &mut y, &mut metrics) }?;
+            rects.push(D2D_RECT_F {
+                left: x,
+                top: metrics.top,
+                right: x + self.line_height / 3.,
+                bottom: metrics.top + self.line_height,
+            });
+        }
+        let top = self.line_top(line);
+        Ok(rects
+            .into_iter()
+            .map(|v| D2D_RECT_F {
+                left: v.left + PADDING,
+                top: v.top + top,
+                right: v.right + PADDING,
+                bottom: v.bottom + top,
+            })
+            .collect())
+    }
+
+    // Thumb of the vertical scroll bar in panel coordinates, none if all rows are visible
+    fn thumb(&self) -> Option<Rect> {
+        let content = self.content_size().Height as f32;
+        let view = self.size.Y;
+        if content <= view || view <= 0. {
+            return None;
+        }
+        let height = (view * view / content).max(MIN_THUMB_HEIGHT).min(view);
+        let top = self.scroll.Y / (content - view) * (view - height);
+        Some(Rect::new(
+            Point::new(self.size.X - SCROLLBAR_WIDTH, top),
+            Size::new(SCROLLBAR_WIDTH, height),
+        ))
+    }
+
+    ///
+    /// Starts dragging the thumb pressed at the point or scrolls by the page toward the point
+    /// on the scroll bar. Returns false if the point is not on the scroll bar.
+    ///
+    fn press_scrollbar(&mut self, point: Vector2) -> bool {
+        let thumb = match self.thumb() {
+            Some(thumb) => thumb,
+            None => return false,
+        };
+        if point.X < thumb.left() || point.X > self.size.X || point.Y > self.size.Y {
+            return false;
+        }
+        if point.Y < thumb.top() {
+            self.scroll.Y -= self.size.Y;
+        } else if point.Y > thumb.bottom() {
+            self.scroll.Y += self.size.Y;
+        } else {
+            self.thumb_drag = Some((point.Y, self.scroll.Y));
+        }
+        self.clamp_scroll();
+        true
+    }
+
+    // Scrolls following the dragged thumb, returns false if the thumb is not dragged
+    fn drag_thumb(&mut self, y: f32) -> bool {
+        let ((start_y, start_scroll), thumb) = match (self.thumb_drag, self.thumb()) {
+            (Some(drag), Some(thumb)) => (drag, thumb),
+            _ => return false,
+        };
+        let track = self.size.Y - thumb.size.height;
+        if track > 0. {
+            let content = self.content_size().Height as f32;
+            self.scroll.Y = start_scroll + (y - start_y) * (content - self.size.Y) / track;
+            self.clamp_scroll();
+        }
+        true
+    }
+}
+
+// Part of the editor drawing the tiles and following the document
+#[derive(EventSink)]
+#[event_sink(event=TextDocumentEvent)]
+struct Shared {
+    virtual_surface: Arc<VirtualSurface>,
+    caret: SpriteVisual,
+    scrollbar: SpriteVisual,
+    document: Arc<TextDocument>,
+    text_color: Color,
+    background_color: Color,
+    selection_color: Color,
+    misspelling_color: Color,
+    rendering: TextRendering,
+    core: RwLock<Core>,
+}
+
+impl Shared {
+    fn draw(&self, core: &Core, rect: RectInt32) -> crate::Result<()> {
+        self.virtual_surface.draw_tile(rect, |context, rect| {
+            let brush_properties = D2D1_BRUSH_PROPERTIES {
+                opacity: 1.,
+                transform: Matrix3x2::identity(),
+            };
+            let text_brush = unsafe {
+                context.CreateSolidColorBrush(&d2d_color(self.text_color), Some(&brush_properties))
+            }?;
+            let selection_brush = unsafe {
+                context.CreateSolidColorBrush(
+                    &d2d_color(self.selection_color),
+                    Some(&brush_properties),
+                )
+            }?;
+            let misspelling_brush = unsafe {
+                context.CreateSolidColorBrush(
+                    &d2d_color(self.misspelling_color),
+                    Some(&brush_properties),
+                )
+            }?;
+            let checker = core.spell_checker();
+            unsafe {
+                context.Clear(Some(&d2d_color(self.background_color)));
+                context.SetTextAntialiasMode(self.rendering.antialias.into());
+            }
+            let format = text_format(&core.font)?;
+            let selection = core.selection();
+            for line in core.lines_in(rect.Y as f32, (rect.Y + rect.Height) as f32) {
+                let y = core.line_top(line);
+                let layout = core.layout(&format, line)?;
+                for rect in core.selection_rects(&layout, line, &selection)? {
+                    unsafe { context.FillRectangle(&rect, &selection_brush) };
+                }
+                unsafe {
+                    context.DrawTextLayout(
+                        D2D_POINT_2F { x: PADDING, y },
+                        &layout,
+                        &text_brush,
+                        self.rendering.draw_text_options(),
+                    )
+                };
+                if let Some(checker) = checker {
+                    for misspelling in core.misspellings(checker, line)? {
+                        let range = misspelling.range.start as u32..misspelling.range.end as u32;
+                        // The word broken by the wrapping is underlined on each row
+                        for rect in range_rects(&layout, &range)? {
+                            draw_squiggle(
+                                context,
+                                &misspelling_brush,
+                                PADDING + rect.left,
+                                PADDING + rect.right,
+                                y + rect.bottom - 1.,
+                            );
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn update_caret(&self, core: &Core) -> crate::Result<()> {
+        self.caret.SetIsVisible(core.focused)?;
+        if !core.focused {
+            return Ok(());
+        }
+        let Vector2 { X: x, Y: y } = core.caret_point(&text_format(&core.font)?, core.caret)?;
+        self.caret.SetSize(Vector2 {
+            X: caret_width() as f32,
+            Y: core.line_height,
+        })?;
+        self.caret.SetOffset(Vector3 {
+            X: x - core.scroll.X,
+            Y: y - core.scroll.Y,
+            Z: 0.,
+        })?;
+        // The caret stays solid while it moves and starts blinking from the visible state
+        self.caret.StopAnimation(&HSTRING::from("Opacity"))?;
+        self.caret.SetOpacity(1.)?;
+        if let Some(period) = caret_blink_time() {
+            let compositor = self.caret.Compositor()?;
+            let step = compositor.CreateStepEasingFunction()?;
+            let animation = compositor.CreateScalarKeyFrameAnimation()?;
+            animation.InsertKeyFrame(0., 1.)?;
+            animation.InsertKeyFrameWithEasingFunction(0.5, 0., &step)?;
+            animation.InsertKeyFrameWithEasingFunction(1., 1., &step)?;
+            animation.SetDuration(time_span(period * 2))?;
+            animation.SetIterationBehavior(AnimationIterationBehavior::Forever)?;
+            start_animation(&self.caret, "Opacity", &animation)?;
+        }
+        Ok(())
+    }
+
+    fn update_scrollbar(&self, core: &Core) -> crate::Result<()> {
+        let thumb = core.thumb();
+        self.scrollbar.SetIsVisible(thumb.is_some())?;
+        if let Some(thumb) = thumb {
+            self.scrollbar.SetOffset(thumb.origin.into())?;
+            self.scrollbar.SetSize(thumb.size.into())?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Brings the surface, the caret and the scroll bar up to date with the core: resizes
+    /// the content, scrolls it and redraws the changed lines. The core lock is not held
+    /// while the surface requests the tiles from this object.
+    ///
+    async fn refresh(&self, dirty: Option<Range<usize>>) -> crate::Result<()> {
+        let (content_size, scroll, rect) = {
+            let mut core = self.core.write().await;
+            // The lines scrolled into view may be laid out for the old width yet
+            let dirty = match core.layout_shown()? {
+                Some(line) => Some(dirty.map_or(line, |v| v.start.min(line))..usize::MAX),
+                None => dirty,
+            };
+            self.update_caret(&core)?;
+            self.update_scrollbar(&core)?;
+            let rect = dirty.map(|v| core.lines_rect(v));
+            (core.content_size(), core.scroll, rect)
+        };
+        if self.virtual_surface.content_size().await != content_size {
+            self.virtual_surface.set_content_size(content_size).await?;
+        } else if let Some(rect) = rect.filter(|v| v.Height > 0) {
+            self.virtual_surface.invalidate(rect).await?;
+        }
+        if self.virtual_surface.viewport_offset().await != scroll {
+            self.virtual_surface.set_viewport_offset(scroll).await?;
+        }
+        Ok(())
+    }
+
+    // Reloads the text if the lines went out of sync with the document
+    async fn reload(&self) -> crate::Result<()> {
+        {
+            // The text is taken under the lock, so no change is applied between taking and
+            // loading it
+            let mut core = self.core.write().await;
+            let (text, revision) = self.document.text_with_revision().await;
+            core.load(&text)?;
+            core.sync.reset(revision);
+        }
+        self.virtual_surface.invalidate_all().await?;
+        self.refresh(None).await
+    }
+
+    async fn set_revealed(&self, revealed: bool) -> crate::Result<()> {
+        {
+            let mut core = self.core.write().await;
+            if core.revealed == revealed {
+                return Ok(());
+            }
+            core.set_revealed(revealed)?;
+        }
+        self.virtual_surface.invalidate_all().await?;
+        self.refresh(None).await
+    }
+
+    async fn replace_selection(&self, text: &str) -> crate::Result<()> {
+        let (edits, first, author, base) = {
+            let mut core = self.core.write().await;
+            let (edit, first) = core.replace_selection(text)?;
+            let base = core.sync.push(vec![edit.clone()]);
+            let format = text_format(&core.font)?;
+            core.scroll_to_caret(&format)?;
+            (vec![edit], first, core.sync.author(), base)
+        };
+        self.send(author, base, edits, first).await
+    }
+
+    ///
+    /// Sends the own edits applied to the lines already to the document. The edits made on
+    /// the stale text are rejected by the document, the text is reloaded then.
+    ///
+    async fn send(
+        &self,
+        author: u64,
+        base: Option<u64>,
+        edits: Vec<TextEdit>,
+        first: usize,
+    ) -> crate::Result<()> {
+        let applied = match base {
+            Some(base) => matches!(self.document.edit_as(author, base, edits).await, Ok(true)),
+            None => false,
+        };
+        if !applied {
+            return self.reload().await;
+        }
+        self.refresh(Some(first..usize::MAX)).await
+    }
+
+    ///
+    /// Applies the own edits made from the core's state to the lines and to the document as
+    /// one undoable change, then selects the range given with them
+    ///
+    async fn edit(
+        &self,
+        f: impl FnOnce(&Core) -> Option<(Vec<TextEdit>, Range<usize>)>,
+    ) -> crate::Result<()> {
+        let (edits, first, author, base) = {
+            let mut core = self.core.write().await;
+            let (edits, selection) = match f(&core) {
+                Some(result) => result,
+                None => return Ok(()),
+            };
+            let format = text_format(&core.font)?;
+            let mut first = core.selected_lines().start;
+            for edit in &edits {
+                first = first.min(core.replace_lines(&format, edit)?);
+            }
+            core.select(selection);
+            core.preferred_x = None;
+            let base = core.sync.push(edits.clone());
+            core.scroll_to_caret(&format)?;
+            (edits, first, core.sync.author(), base)
+        };
+        self.send(author, base, edits, first).await
+    }
+
+    // Redraws the underlines after the spell checking settings change
+    async fn respell(&self) -> crate::Result<()> {
+        self.core.write().await.spelling_menu = None;
+        self.virtual_surface.invalidate_all().await
+    }
+
+    async fn on_spelling_menu(&self, id: usize) -> crate::Result<()> {
+        let menu = match self.core.write().await.spelling_menu.take() {
+            Some(menu) => menu,
+            None => return Ok(()),
+        };
+        match id {
+            IGNORE_ITEM => {
+                self.core.write().await.ignored.insert(menu.word);
+                self.respell().await
+            }
+            ADD_TO_DICTIONARY_ITEM => {
+                if let Some(checker) = self.core.read().await.spell_checker() {
+                    checker.add(&menu.word)?;
+                }
+                self.respell().await
+            }
+            index => {
+                let replacement = match menu.replacements.get(index) {
+                    Some(replacement) => replacement,
+                    None => return Ok(()),
+                };
+                let dirty = {
+                    let mut core = self.core.write().await;
+                    // The document could be changed by the other editor while the menu was open
+                    if core.text_in(&menu.range) != Some(menu.word.as_str()) {
+                        return Ok(());
+                    }
+                    core.preferred_x = None;
+                    core.select(menu.range)
+                };
+                self.refresh(dirty).await?;
+                self.replace_selection(replacement).await
+            }
+        }
+    }
+
+    async fn on_document_changed(
+        &self,
+        revision: u64,
+        author: u64,
+        edits: &[TextEdit],
+    ) -> crate::Result<()> {
+        let first = {
+            let mut core = self.core.write().await;
+            match core.sync.changed(revision, author, edits) {
+                SyncAction::Skip => return Ok(()),
+                SyncAction::Apply => (),
+                SyncAction::Reload => {
+                    drop(core);
+                    return self.reload().await;
+                }
+            }
+            let first = core.apply(edits)?;
+            core.clamp_scroll();
+            first
+        };
+        self.refresh(Some(first..usize::MAX)).await
+    }
+}
+
+// Draws the tiles requested by the virtual surface. The surface sends the panel events too,
+// so its pipe needs the sink of the single event type, not the `Shared` itself.
+#[derive(EventSink)]
+#[event_sink(event=VirtualSurfaceEvent)]
+struct TileSink(Arc<Shared>);
+
+#[async_trait]
+impl EventSinkExt<VirtualSurfaceEvent> for TileSink {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, VirtualSurfaceEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let VirtualSurfaceEvent::Redraw(tiles) = event.as_ref();
+        let core = self.0.core.read().await;
+        for tile in tiles {
+            self.0.draw(&core, *tile)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<TextDocumentEvent> for Shared {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, TextDocumentEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let TextDocumentEvent::Changed {
+            revision,
+            author,
+            edits,
+        } = event.as_ref();
+        if self
+            .on_document_changed(*revision, *author, edits)
+            .await
+            .is_err()
+        {
+            self.reload().await?;
+        }
+        Ok(())
+    }
+}
+
+// Receives the choice from the spelling menu, the menu sends the panel events too
+#[derive(EventSink)]
+#[event_sink(event=MenuEvent)]
+struct SpellingMenuSink(Arc<Shared>);
+
+#[async_trait]
+impl EventSinkExt<MenuEvent> for SpellingMenuSink {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, MenuEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let MenuEvent::Activated(id) = event.as_ref();
+        self.0.on_spelling_menu(*id).await
+    }
+}
+
+///
+/// Multi-line plain text editor over the [`TextDocument`]. Only the visible lines are drawn,
+/// by the tiles of the [`VirtualSurface`], so the document may be long. The editor gets
+/// keyboard focus on mouse press and supports the usual keys: arrows with Ctrl to jump by
+/// Home/End, PageUp/PageDown, Ctrl+A, Ctrl+X/C/V with the system
+/// clipboard, Ctrl+Z and Ctrl+Y or Ctrl+Shift+Z. Several editors sharing one document show
+/// the edits of each other. The edit typed while the other editor's edit is on the way is
+/// dropped, so the editors never apply the edits on the shifted offsets.
+///
+/// The [`LineOperation`]s edit the whole lines touched by the selection: Alt+Up/Down move
+/// them, Ctrl+D duplicates, Ctrl+Shift+K deletes, Tab with the selection over several lines
+/// indents them and Shift+Tab outdents. With `word_wrap` the long lines are broken into rows
+/// fitting the width, the arrows and Home/End move the caret by rows. Only the lines in view
+/// are laid out again on resize. The scroll bar's thumb shown over the right edge when the
+/// text is taller than the editor can be dragged, the click beside the thumb scrolls by the
+/// page.
+///
+/// In the password mode the editor is a single-line masked input: the characters are drawn
+/// as bullets, the text can't be copied or cut and the caret jumps over it as a whole word.
+/// The optional reveal button shows the password while it's held.
+///
+/// With the spell checking language set the misspelled words are underlined by the zigzag
+/// line. Right click on such word opens the menu in the overlay host with the suggested
+/// replacements and the items to ignore the word or to add it to the user's dictionary.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct TextEditor {
+    container: ContainerVisual,
+    shared: Arc<Shared>,
+    reveal_button: Option<Arc<Text>>,
+    host: Option<Arc<OverlayHost>>,
+    spawner: Box<dyn Spawn + Send + Sync>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct TextEditorParams<T: Spawn + Send + Sync + 'static> {
+    compositor: Compositor,
+    spawner: T,
+    /// Overlay host showing the spelling suggestions menu, no menu if not set
+    #[builder(default, setter(strip_option))]
+    host: Option<Arc<OverlayHost>>,
+    /// Document to edit, the new empty one if not set
+    #[builder(default, setter(strip_option))]
+    document: Option<Arc<TextDocument>>,
+    #[builder(default = default_font())]
+    font: Font,
+    #[builder(default = Colors::Black().unwrap())]
+    text_color: Color,
+    #[builder(default = Colors::White().unwrap())]
+    background_color: Color,
+    #[builder(default = Colors::LightSkyBlue().unwrap())]
+    selection_color: Color,
+    /// Color of the line under the misspelled words
+    #[builder(default = Colors::Red().unwrap())]
+    misspelling_color: Color,
+    /// BCP47 tag of the spell checking language, e.g. "en-US", no spell checking if not set
+    #[builder(default, setter(strip_option, into))]
+    spell_check_language: Option<String>,
+    /// Rendering options, the global default if not set
+    #[builder(default, setter(strip_option))]
+    rendering: Option<TextRendering>,
+    #[builder(default)]
+    password: bool,
+    /// Shows the button revealing the password while it's held, in the password mode only
+    #[builder(default)]
+    reveal_button: bool,
+    /// Breaks the lines longer than the width into several rows, the password is never wrapped
+    #[builder(default)]
+    word_wrap: bool,
+}
+
+impl<T: Spawn + Send + Sync + 'static> TryFrom<TextEditorParams<T>> for TextEditor {
+    type Error = crate::Error;
+
+    fn try_from(value: TextEditorParams<T>) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        container.SetClip(&value.compositor.CreateInsetClip()?)?;
+        #[cfg(feature = "accessibility")]
+        set_accessible(
+            &container.clone().into(),
+            &Accessible {
+                focusable: true,
+                automation_id: Some("TextEditor".to_string()),
+                ..Accessible::new(AccessibleRole::Edit)
+            },
+        )?;
+        let document = value.document.unwrap_or_default();
+        // The text is loaded by the spawned task, the edits made before are in it already
+        let mut core = Core::new("", value.font, value.password)?;
+        core.spell_checker = value
+            .spell_check_language
+            .as_deref()
+            .map(spell_checker)
+            .transpose()?;
+        core.word_wrap = value.word_wrap;
+        let virtual_surface: Arc<VirtualSurface> = VirtualSurfaceParams::builder()
+            .compositor(value.compositor.clone())
+            .content_size(core.content_size())
+            .build()
+            .try_into()?;
+        attach(&container, &*virtual_surface)?;
+        let reveal_button = if value.password && value.reveal_button {
+            let button: Arc<Text> = TextParams::builder()
+                .compositor(value.compositor.clone())
+                .spawner(&value.spawner)
+                .text(REVEAL_GLYPH.to_string())
+                .font(Font {
+                    family: "Segoe MDL2 Assets".to_string(),
+                    size: core.font.size,
+                    weight: 400,
+                    italic: false,
+                })
+                .layout(TextLayout {
+                    wrap: false,
+                    horizontal_alignment: Alignment::Center,
+                    vertical_alignment: Alignment::Center,
+                    ..Default::default()
+                })
+                .color(value.text_color)
+                .build()
+                .try_into()?;
+            attach(&container, &*button)?;
+            Some(button)
+        } else {
+            None
+        };
+        let caret = value.compositor.CreateSpriteVisual()?;
+        caret.SetBrush(
+            &value
+                .compositor
+                .CreateColorBrushWithColor(value.text_color)?,
+        )?;
+        caret.SetIsVisible(false)?;
+        container.Children()?.InsertAtTop(&caret)?;
+        let scrollbar = value.compositor.CreateSpriteVisual()?;
+        scrollbar.SetBrush(&value.compositor.CreateColorBrushWithColor(Color {
+            A: 0x80,
+            ..value.text_color
+        })?)?;
+        scrollbar.SetIsVisible(false)?;
+        container.Children()?.InsertAtTop(&scrollbar)?;
+        let shared = Arc::new(Shared {
+            virtual_surface: virtual_surface.clone(),
+            caret,
+            scrollbar,
+            document,
+            text_color: value.text_color,
+            background_color: value.background_color,
+            selection_color: value.selection_color,
+            misspelling_color: value.misspelling_color,
+            rendering: value.rendering.unwrap_or_else(default_text_rendering),
+            core: RwLock::new(core),
+        });
+        spawn_event_pipe(
+            &value.spawner,
+            &*virtual_surface,
+            Arc::new(TileSink(shared.clone())),
+            on_err,
+        )?;
+        spawn_event_pipe(&value.spawner, &*shared.document, shared.clone(), on_err)?;
+        value.spawner.spawn(handle_err({
+            let shared = shared.clone();
+            async move { shared.reload().await }
+        }))?;
+        Ok(TextEditor {
+            container,
+            shared,
+            reveal_button,
+            host: value.host,
+            spawner: Box::new(value.spawner),
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl<T: Spawn + Send + Sync + 'static> TryFrom<TextEditorParams<T>> for Arc<TextEditor> {
+    type Error = crate::Error;
+
+    fn try_from(value: TextEditorParams<T>) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+// Checker of the language, fails if the language has no spell checker installed
+fn spell_checker(language: &str) -> crate::Result<SpellChecker> {
+    if SpellChecker::is_supported(language)? {
+        SpellChecker::new(language)
+    } else {
+        Err(windows::core::Error::from(E_INVALIDARG).into())
+    }
+}
+
+impl TextEditor {
+    pub fn document(&self) -> Arc<TextDocument> {
+        self.shared.document.clone()
+    }
+
+    /// Byte range of the selected text, empty at the caret if nothing is selected
+    pub async fn selection(&self) -> Range<usize> {
+        self.shared.core.read().await.selection()
+    }
+
+    pub async fn selected_text(&self) -> String {
+        self.shared.core.read().await.selected_text()
+    }
+
+    ///
+    /// Selects the range, the caret is placed at its end and scrolled into view. The offsets
+    /// past the end are moved to the end, the ones inside the characters fail with
+    /// `Error::BadIndex`.
+    ///
+    pub async fn select(&self, range: Range<usize>) -> crate::Result<()> {
+        let dirty = {
+            let mut core = self.shared.core.write().await;
+            let len = core.len();
+            let range = range.start.min(len)..range.end.min(len);
+            if range.start > range.end
+                || !core.is_char_boundary(range.start)
+                || !core.is_char_boundary(range.end)
+            {
+                return Err(crate::Error::BadIndex);
+            }
+            let dirty = core.select(range);
+            core.preferred_x = None;
+            let format = text_format(&core.font)?;
+            core.scroll_to_caret(&format)?;
+            dirty
+        };
+        self.shared.refresh(dirty).await
+    }
+
+    pub async fn is_focused(&self) -> bool {
+        self.shared.core.read().await.focused
+    }
+
+    /// Gives the keyboard input to the editor, the editor takes it itself on mouse press
+    pub async fn set_focused(&self, focused: bool) -> crate::Result<()> {
+        self.shared.core.write().await.focused = focused;
+        self.shared.refresh(None).await
+    }
+
+    pub async fn is_revealed(&self) -> bool {
+        self.shared.core.read().await.revealed
+    }
+
+    /// Shows the password as plain text, e.g. by the application's own toggle
+    pub async fn set_revealed(&self, revealed: bool) -> crate::Result<()> {
+        self.shared.set_revealed(revealed).await
+    }
+
+    /// Does nothing in the password mode
+    pub async fn cut(&self) -> crate::Result<()> {
+        if self.shared.core.read().await.password {
+            return Ok(());
+        }
+        self.copy().await?;
+        if !self.selection().await.is_empty() {
+            self.shared.replace_selection("").await?;
+        }
+        Ok(())
+    }
+
+    /// Does nothing in the password mode
+    pub async fn copy(&self) -> crate::Result<()> {
+        if self.shared.core.read().await.password {
+            return Ok(());
+        }
+        let text = self.selected_text().await;
+        if !text.is_empty() {
+            clipboard::set_text(&text).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn paste(&self) -> crate::Result<()> {
+        if let Some(text) = clipboard::get_text().await? {
+            let text = if self.shared.core.read().await.password {
+                text.replace(|c: char| c == '\r' || c == '\n', "")
+            } else {
+                text.replace("\r\n", "\n")
+            };
+            self.shared.replace_selection(&text).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn spell_check_language(&self) -> Option<String> {
+        let core = self.shared.core.read().await;
+        core.spell_checker
+            .as_ref()
+            .map(|checker| checker.language().to_string())
+    }
+
+    ///
+    /// Sets the spell checking language, `None` turns the spell checking off. Fails if the
+    /// language has no spell checker installed, see [`crate::window::spell_checker_languages`].
+    ///
+    pub async fn set_spell_check_language(&self, language: Option<String>) -> crate::Result<()> {
+        let checker = language.as_deref().map(spell_checker).transpose()?;
+        self.shared.core.write().await.spell_checker = checker;
+        self.shared.respell().await
+    }
+
+    /// Stops underlining the word in this editor
+    pub async fn ignore_word(&self, word: &str) -> crate::Result<()> {
+        self.shared
+            .core
+            .write()
+            .await
+            .ignored
+            .insert(word.to_string());
+        self.shared.respell().await
+    }
+
+    /// Adds the word to the user's dictionary of the spell checking language
+    pub async fn add_to_dictionary(&self, word: &str) -> crate::Result<()> {
+        if let Some(checker) = self.shared.core.read().await.spell_checker() {
+            checker.add(word)?;
+        }
+        self.shared.respell().await
+    }
+
+    // Opens the spelling menu for the misspelled word under the mouse
+    async fn show_spelling_menu(&self) -> crate::Result<()> {
+        let host = match &self.host {
+            Some(host) => host.clone(),
+            None => return Ok(()),
+        };
+        let (items, point) = {
+            let mut core = self.shared.core.write().await;
+            let point = match core
+                .mouse_pos
+                .filter(|v| Rect::from_size(core.size).contains(*v))
+            {
+                Some(point) => point,
+                None => return Ok(()),
+            };
+            let checker = match core.spell_checker() {
+                Some(checker) => checker,
+                None => return Ok(()),
+            };
+            let pos = core.pos_at(&text_format(&core.font)?, point)?;
+            let (range, action) = match core.misspelling_at(checker, pos)? {
+                Some(misspelling) => misspelling,
+                None => return Ok(()),
+            };
+            let word = core.text_in(&range).unwrap_or_default().to_string();
+            let replacements = match &action {
+                SpellingAction::Suggest => checker.suggest(&word)?,
+                SpellingAction::Replace(text) => vec![text.clone()],
+                SpellingAction::Delete => vec![String::new()],
+            };
+            let items = spelling_menu_items(&action, &replacements);
+            core.spelling_menu = Some(SpellingMenu {
+                range,
+                word,
+                replacements,
+            });
+            (items, point)
+        };
+        let menu: Arc<Menu> = MenuParams::builder()
+            .compositor(self.container.Compositor()?)
+            .items(items)
+            .build()
+            .try_into()?;
+        let sink = Arc::new(SpellingMenuSink(self.shared.clone()));
+        spawn_event_pipe(&self.spawner, &*menu, sink, on_err)?;
+        let origin = host.bounds_of(self)?.origin;
+        menu.show_at(host, origin + Point::from(point)).await
+    }
+
+    pub async fn word_wrap(&self) -> bool {
+        self.shared.core.read().await.word_wrap
+    }
+
+    pub async fn set_word_wrap(&self, word_wrap: bool) -> crate::Result<()> {
+        {
+            let mut core = self.shared.core.write().await;
+            if core.word_wrap == word_wrap {
+                return Ok(());
+            }
+            core.word_wrap = word_wrap;
+            core.relayout()?;
+        }
+        self.shared.virtual_surface.invalidate_all().await?;
+        self.shared.refresh(None).await
+    }
+
+    /// Edits the lines touched by the selection as one undoable change, not in the password mode
+    pub async fn edit_lines(&self, operation: LineOperation) -> crate::Result<()> {
+        self.shared
+            .edit(|core| {
+                if core.password {
+                    return None;
+                }
+                let lines: Vec<_> = core.lines.iter().map(|v| v.text.as_str()).collect();
+                let (replacements, selection) =
+                    line_operation(operation, &lines, &core.line_starts, core.selection())?;
+                let edits = replacements
+                    .into_iter()
+                    .map(|(range, text)| TextEdit::new(range, text))
+                    .collect();
+                Some((edits, selection))
+            })
+            .await
+    }
+
+    pub async fn undo(&self) -> crate::Result<bool> {
+        self.shared.document.undo().await
+    }
+
+    pub async fn redo(&self) -> crate::Result<bool> {
+        self.shared.document.redo().await
+    }
+
+    ///
+    /// Moves the caret to the position given by the core, extending the selection with Shift.
+    /// The vertical moves keep the column of the caret.
+    ///
+    async fn move_caret(
+        &self,
+        extend: bool,
+        vertical: bool,
+        f: impl FnOnce(&mut Core, &IDWriteTextFormat) -> crate::Result<usize>,
+    ) -> crate::Result<()> {
+        let dirty = {
+            let mut core = self.shared.core.write().await;
+            let format = text_format(&core.font)?;
+            if !vertical {
+                core.preferred_x = None;
+            }
+            let pos = f(&mut core, &format)?;
+            let dirty = core.move_to(pos, extend);
+            core.scroll_to_caret(&format)?;
+            dirty
+        };
+        self.shared.refresh(dirty).await
+    }
+
+    async fn key(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> crate::Result<()> {
+        let ctrl = modifiers.ctrl();
+        let shift = modifiers.shift();
+        let alt = modifiers.alt();
+        let password = self.shared.core.read().await.password;
+        match key {
+            VirtualKeyCode::Up if alt => self.edit_lines(LineOperation::MoveUp).await,
+            VirtualKeyCode::Down if alt => self.edit_lines(LineOperation::MoveDown).await,
+            VirtualKeyCode::D if ctrl => self.edit_lines(LineOperation::Duplicate).await,
+            VirtualKeyCode::K if ctrl && shift => self.edit_lines(LineOperation::Delete).await,
+            VirtualKeyCode::Left => {
+                self.move_caret(shift, false, |core, _| {
+                    let selection = core.selection();
+                    Ok(if ctrl {
+                        core.word_start(core.caret)
+                    } else if !shift && !selection.is_empty() {
+                        selection.start
+                    } else {
+                        core.prev_char(core.caret)
+                    })
+                })
+                .await
+            }
+            VirtualKeyCode::Right => {
+                self.move_caret(shift, false, |core, _| {
+                    let selection = core.selection();
+                    Ok(if ctrl {
+                        core.word_end(core.caret)
+                    } else if !shift && !selection.is_empty() {
+                        selection.end
+                    } else {
+                        core.next_char(core.caret)
+                    })
+                })
+                .await
+            }
+            VirtualKeyCode::Up => {
+                self.move_caret(shift, true, |core, format| core.vertical_move(format, -1))
+                    .await
+            }
+            VirtualKeyCode::Down => {
+                self.move_caret(shift, true, |core, format| core.vertical_move(format, 1))
+                    .await
+            }
+            VirtualKeyCode::PageUp | VirtualKeyCode::PageDown => {
+                self.move_caret(shift, true, |core, format| {
+                    let page = ((core.size.Y / core.line_height) as isize - 1).max(1);
+                    let lines = if key == VirtualKeyCode::PageUp {
+                        -page
+                    } else {
+                        page
+                    };
+                    core.scroll.Y += lines as f32 * core.line_height;
+                    core.clamp_scroll();
+                    core.vertical_move(format, lines)
+                })
+                .await
+            }
+            VirtualKeyCode::Home => {
+                self.move_caret(shift, false, |core, format| {
+                    Ok(if ctrl {
+                        0
+                    } else {
+                        core.caret_row_bounds(format)?.start
+                    })
+                })
+                .await
+            }
+            VirtualKeyCode::End => {
+                self.move_caret(shift, false, |core, format| {
+                    Ok(if ctrl {
+                        core.len()
+                    } else {
+                        core.caret_row_bounds(format)?.end
+                    })
+                })
+                .await
+            }
+            VirtualKeyCode::A if ctrl => {
+                let dirty = self.shared.core.write().await.select(0..usize::MAX);
+                self.shared.refresh(dirty).await
+            }
+            VirtualKeyCode::C | VirtualKeyCode::Insert if ctrl => self.copy().await,
+            VirtualKeyCode::X if ctrl => self.cut().await,
+            VirtualKeyCode::V if ctrl => self.paste().await,
+            VirtualKeyCode::Z if ctrl && shift => self.redo().await.map(|_| ()),
+            VirtualKeyCode::Z if ctrl => self.undo().await.map(|_| ()),
+            VirtualKeyCode::Y if ctrl => self.redo().await.map(|_| ()),
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter if !password => {
+                self.shared.replace_selection("\n").await
+            }
+            VirtualKeyCode::Tab if shift && !ctrl => self.edit_lines(LineOperation::Outdent).await,
+            VirtualKeyCode::Tab if !ctrl && !password => {
+                // Tab indents the selected lines instead of replacing them
+                let multiline = {
+                    let core = self.shared.core.read().await;
+                    let selection = core.selection();
+                    core.line_of(selection.start) != core.line_of(selection.end)
+                };
+                if multiline {
+                    self.edit_lines(LineOperation::Indent).await
+                } else {
+                    self.shared.replace_selection("\t").await
+                }
+            }
+            VirtualKeyCode::Back | VirtualKeyCode::Delete => {
+                {
+                    let mut core = self.shared.core.write().await;
+                    if core.selection().is_empty() {
+                        let caret = core.caret;
+                        core.anchor = match (key, ctrl) {
+                            (VirtualKeyCode::Back, false) => core.prev_char(caret),
+                            (VirtualKeyCode::Back, true) => core.word_start(caret),
+                            (_, false) => core.next_char(caret),
+                            (_, true) => core.word_end(caret),
+                        };
+                    }
+                    if core.selection().is_empty() {
+                        return Ok(());
+                    }
+                }
+                self.shared.replace_selection("").await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn mouse_press(&self, click_count: u32) -> crate::Result<()> {
+        let dirty = {
+            let mut core = self.shared.core.write().await;
+            let pos = match core.mouse_pos {
+                Some(pos) => pos,
+                None => return Ok(()),
+            };
+            let format = text_format(&core.font)?;
+            let pos = core.pos_at(&format, pos)?;
+            core.preferred_x = None;
+            if click_count == 2 {
+                let word = core.word_at(pos);
+                core.select(word)
+            } else if click_count > 2 {
+                let line = core.line_of(pos);
+                let start = core.line_starts[line];
+                let end = core
+                    .line_starts
+                    .get(line + 1)
+                    .copied()
+                    .unwrap_or(core.len());
+                core.select(start..end)
+            } else {
+                core.dragging = true;
+                core.move_to(pos, false)
+            }
+        };
+        self.shared.refresh(dirty).await
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => {
+                self.container.SetSize(*size)?;
+                // The reveal button takes the right side of the editor
+                let text_size = match &self.reveal_button {
+                    Some(button) => {
+                        let width = REVEAL_BUTTON_WIDTH.min(size.X);
+                        button.outer_frame().SetOffset(Vector3 {
+                            X: size.X - width,
+                            Y: 0.,
+                            Z: 0.,
+                        })?;
+                        button
+                            .on_event_owned(
+                                PanelEvent::Resized(Vector2 {
+                                    X: width,
+                                    Y: size.Y,
+                                }),
+                                source.clone(),
+                            )
+                            .await?;
+                        Vector2 {
+                            X: size.X - width,
+                            Y: size.Y,
+                        }
+                    }
+                    None => *size,
+                };
+                self.shared
+                    .virtual_surface
+                    .on_event_owned(PanelEvent::Resized(text_size), source.clone())
+                    .await?;
+                let rewrapped = {
+                    let mut core = self.shared.core.write().await;
+                    let wrap_width = core.wrap_width();
+                    core.size = text_size;
+                    let rewrapped = core.wrap_width() != wrap_width;
+                    if rewrapped {
+                        core.relayout()?;
+                    }
+                    core.clamp_scroll();
+                    rewrapped
+                };
+                if rewrapped {
+                    self.shared.virtual_surface.invalidate_all().await?;
+                }
+                self.shared.refresh(None).await?;
+            }
+            PanelEvent::CursorMoved(pos) => {
+                let dirty = {
+                    let mut core = self.shared.core.write().await;
+                    core.mouse_pos = Some(*pos);
+                    if core.drag_thumb(pos.Y) {
+                        Some(None)
+                    } else if core.dragging {
+                        let format = text_format(&core.font)?;
+                        let pos = core.pos_at(&format, *pos)?;
+                        let dirty = core.move_to(pos, true);
+                        core.scroll_to_caret(&format)?;
+                        Some(dirty)
+                    } else {
+                        None
+                    }
+                };
+                if let Some(dirty) = dirty {
+                    self.shared.refresh(dirty).await?;
+                }
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button: MouseButton::Left,
+                click_count,
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    let bounds = Rect::from_size(self.container.Size()?);
+                    let (hit, reveal) = {
+                        let mut core = self.shared.core.write().await;
+                        let (hit, in_text) = core.mouse_pos.map_or((false, false), |v| {
+                            (bounds.contains(v), Rect::from_size(core.size).contains(v))
+                        });
+                        let hit = *in_slot && hit;
+                        let reveal = hit && !in_text && self.reveal_button.is_some();
+                        core.focused = hit;
+                        core.revealing = reveal;
+                        (hit, reveal)
+                    };
+                    let on_scrollbar = hit && {
+                        let mut core = self.shared.core.write().await;
+                        match core.mouse_pos {
+                            Some(pos) => core.press_scrollbar(pos),
+                            None => false,
+                        }
+                    };
+                    if reveal {
+                        self.shared.set_revealed(true).await?;
+                    } else if on_scrollbar {
+                        self.shared.refresh(None).await?;
+                    } else if hit {
+                        self.mouse_press(*click_count).await?;
+                    } else {
+                        self.shared.refresh(None).await?;
+                    }
+                }
+                ElementState::Released => {
+                    let revealing = {
+                        let mut core = self.shared.core.write().await;
+                        core.dragging = false;
+                        core.thumb_drag = None;
+                        std::mem::take(&mut core.revealing)
+                    };
+                    if revealing {
+                        self.shared.set_revealed(false).await?;
+                    }
+                }
+            },
+            PanelEvent::MouseInput {
+                in_slot: true,
+                state: ElementState::Released,
+                button: MouseButton::Right,
+                ..
+            } => self.show_spelling_menu().await?,
+            PanelEvent::MouseWheel { delta, .. } => {
+                let scrolled = {
+                    let mut core = self.shared.core.write().await;
+                    let over = core
+                        .mouse_pos
+                        .map_or(false, |v| Rect::from_size(core.size).contains(v));
+                    if over {
+                        let (x, y) = match delta {
+                            MouseScrollDelta::LineDelta(x, y) => (
+                                x * WHEEL_LINES * core.line_height,
+                                y * WHEEL_LINES * core.line_height,
+                            ),
+                            MouseScrollDelta::PixelDelta(v) => (v.x as f32, v.y as f32),
+                        };
+                        core.scroll.X -= x;
+                        core.scroll.Y -= y;
+                        core.clamp_scroll();
+                    }
+                    over
+                };
+                if scrolled {
+                    self.shared.refresh(None).await?;
+                }
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(key),
+                modifiers,
+                ..
+            } => {
+                if self.shared.core.read().await.focused {
+                    self.key(*key, *modifiers).await?;
+                }
+            }
+            PanelEvent::ReceivedCharacter(c) => {
+                if !c.is_control() && self.shared.core.read().await.focused {
+                    self.shared.replace_selection(&c.to_string()).await?;
+                }
+            }
+            _ => (),
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for TextEditor {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for TextEditor {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl Searchable for TextEditor {
+    // The password can't be found by guessing its parts
+    async fn search_text(&self) -> String {
+        if self.shared.core.read().await.password {
+            String::new()
+        } else {
+            self.shared.document.text().await
+        }
+    }
+    async fn search_selection(&self) -> Range<usize> {
+        self.selection().await
+    }
+    async fn show_match(&self, range: Range<usize>) -> crate::Result<()> {
+        self.select(range).await
+    }
+    async fn replace(&self, edits: Vec<TextEdit>) -> crate::Result<bool> {
+        self.shared.document.edit(edits).await?;
+        Ok(true)
+    }
+}
+
+impl Panel for TextEditor {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,276 @@
+//!
+//! Lines of the multi-line text: the layout of the line soft-wrapped to the width and the
+//! operations on the whole lines, like moving, duplicating and indenting them. The lines are
+//! split by `\n` and addressed by the byte offsets of their starts, the `\r` of the CRLF line
+//! break stays at the end of the line.
+//!
+use std::ops::Range;
+
+use windows::Win32::Graphics::DirectWrite::{
+    IDWriteTextFormat, IDWriteTextLayout, DWRITE_TEXT_METRICS, DWRITE_WORD_WRAPPING_EMERGENCY_BREAK,
+};
+
+use crate::window::dwrite_factory;
+
+// Spaces removed by the outdent of the line indented by spaces
+const TAB_SPACES: usize = 4;
+
+///
+/// Layout of the line broken into the rows not wider than `wrap_width`, the line is one row
+/// if it's not set. The words longer than the row are broken too.
+///
+pub fn wrapped_layout(
+    format: &IDWriteTextFormat,
+    text: &str,
+    wrap_width: Option<f32>,
+) -> crate::Result<IDWriteTextLayout> {
+    let text: Vec<u16> = text.encode_utf16().collect();
+    let layout = unsafe {
+        dwrite_factory()?.CreateTextLayout(&text, format, wrap_width.unwrap_or(f32::MAX), f32::MAX)
+    }?;
+    if wrap_width.is_some() {
+        unsafe { layout.SetWordWrapping(DWRITE_WORD_WRAPPING_EMERGENCY_BREAK) }?;
+    }
+    Ok(layout)
+}
+
+/// Width of the widest row of the layout and the number of its rows
+pub fn layout_rows(layout: &IDWriteTextLayout) -> crate::Result<(f32, usize)> {
+    let mut metrics = DWRITE_TEXT_METRICS::default();
+    unsafe { layout.GetMetrics(&mut metrics) }?;
+    Ok((
+        metrics.widthIncludingTrailingWhitespace,
+        (metrics.lineCount as usize).max(1),
+    ))
+}
+
+/// Replacement of the byte range of the text by the string
+pub type Replacement = (Range<usize>, String);
+
+/// Operations on the whole lines touched by the selection
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum LineOperation {
+    /// Inserts the copy of the lines below them and selects it
+    Duplicate,
+    Delete,
+    /// Swaps the lines with the line above them
+    MoveUp,
+    /// Swaps the lines with the line below them
+    MoveDown,
+    /// Inserts the tab at the start of each line
+    Indent,
+    /// Removes the tab or up to 4 spaces at the start of each line
+    Outdent,
+}
+
+// Lines touched by the selection, without the last one if the selection ends at its start
+fn line_block(line_starts: &[usize], selection: &Range<usize>) -> Range<usize> {
+    let line_of = |pos| line_starts.partition_point(|v| *v <= pos).max(1) - 1;
+    let first = line_of(selection.start);
+    let last = line_of(selection.end);
+    if last > first && line_starts[last] == selection.end {
+        first..last
+    } else {
+        first..last + 1
+    }
+}
+
+// Length of the indent removed by the outdent of the line
+fn indent_len(line: &str) -> usize {
+    if line.starts_with('\t') {
+        1
+    } else {
+        (line.len() - line.trim_start_matches(' ').len()).min(TAB_SPACES)
+    }
+}
+
+///
+/// Replacements of the byte ranges made by the line operation on the `lines` starting at the
+/// `line_starts` and the selection after them, `None` if the operation changes nothing. The
+/// replacements go from the end of the text, so each one applies to the offsets not shifted
+/// by the others.
+///
+pub fn line_operation(
+    operation: LineOperation,
+    lines: &[&str],
+    line_starts: &[usize],
+    selection: Range<usize>,
+) -> Option<(Vec<Replacement>, Range<usize>)> {
+    let block = line_block(line_starts, &selection);
+    let start = line_starts[block.start];
+    let end = line_starts[block.end - 1] + lines[block.end - 1].len();
+    let text = lines[block.clone()].join("\n");
+    let shift = |offset: isize| {
+        (selection.start as isize + offset) as usize..(selection.end as isize + offset) as usize
+    };
+    match operation {
+        LineOperation::Duplicate => {
+            let offset = text.len() as isize + 1;
+            Some((vec![(end..end, format!("\n{}", text))], shift(offset)))
+        }
+        LineOperation::Delete => {
+            // The line break after the lines is removed, or before them for the last lines
+            let (range, pos) = if block.end < lines.len() {
+                (start..line_starts[block.end], start)
+            } else if block.start > 0 {
+                let crlf = lines[block.start - 1].ends_with('\r');
+                (start - 1 - crlf as usize..end, line_starts[block.start - 1])
+            } else {
+                (start..end, start)
+            };
+            Some((vec![(range, String::new())], pos..pos))
+        }
+        LineOperation::MoveUp if block.start > 0 => {
+            let above = lines[block.start - 1];
+            let range = line_starts[block.start - 1]..end;
+            let offset = -(above.len() as isize + 1);
+            Some((vec![(range, format!("{}\n{}", text, above))], shift(offset)))
+        }
+        LineOperation::MoveDown if block.end < lines.len() => {
+            let below = lines[block.end];
+            let range = start..line_starts[block.end] + below.len();
+            let offset = below.len() as isize + 1;
+            Some((vec![(range, format!("{}\n{}", below, text))], shift(offset)))
+        }
+        LineOperation::MoveUp | LineOperation::MoveDown => None,
+        LineOperation::Indent => {
+            let edits: Vec<_> = block
+                .rev()
+                .map(|line| (line_starts[line]..line_starts[line], "\t".to_string()))
+                .collect();
+            let end = end + edits.len();
+            Some((edits, start..end))
+        }
+        LineOperation::Outdent => {
+            let edits: Vec<_> = block
+                .rev()
+                .filter_map(|line| {
+                    let len = indent_len(lines[line]);
+                    let line_start = line_starts[line];
+                    (len > 0).then(|| (line_start..line_start + len, String::new()))
+                })
+                .collect();
+            let removed: usize = edits.iter().map(|(range, _)| range.len()).sum();
+            (!edits.is_empty()).then(|| (edits, start..end - removed))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Applies the line operation to the text, returns the new text and the selection
+    fn run(
+        operation: LineOperation,
+        text: &str,
+        selection: Range<usize>,
+    ) -> (String, Range<usize>) {
+        let lines: Vec<_> = text.split('\n').collect();
+        let mut start = 0;
+        let line_starts: Vec<_> = lines
+            .iter()
+            .map(|v| {
+                let line_start = start;
+                start += v.len() + 1;
+                line_start
+            })
+            .collect();
+        let (edits, selection) =
+            match line_operation(operation, &lines, &line_starts, selection.clone()) {
+                Some(result) => result,
+                None => return (text.to_string(), selection),
+            };
+        let mut text = text.to_string();
+        for (range, replacement) in edits {
+            text.replace_range(range, &replacement);
+        }
+        (text, selection)
+    }
+
+    #[test]
+    fn block_excludes_line_at_selection_end() {
+        let line_starts = [0, 4, 8];
+        assert_eq!(line_block(&line_starts, &(1..1)), 0..1);
+        assert_eq!(line_block(&line_starts, &(1..4)), 0..1);
+        assert_eq!(line_block(&line_starts, &(1..5)), 0..2);
+        assert_eq!(line_block(&line_starts, &(4..4)), 1..2);
+        assert_eq!(line_block(&line_starts, &(9..9)), 2..3);
+    }
+
+    #[test]
+    fn duplicate_and_delete_lines() {
+        let text = "one\ntwo\nthree";
+        assert_eq!(
+            run(LineOperation::Duplicate, text, 5..6),
+            ("one\ntwo\ntwo\nthree".to_string(), 9..10)
+        );
+        assert_eq!(
+            run(LineOperation::Duplicate, text, 1..6),
+            ("one\ntwo\none\ntwo\nthree".to_string(), 9..14)
+        );
+        assert_eq!(
+            run(LineOperation::Delete, text, 5..5),
+            ("one\nthree".to_string(), 4..4)
+        );
+        assert_eq!(
+            run(LineOperation::Delete, text, 10..10),
+            ("one\ntwo".to_string(), 4..4)
+        );
+        assert_eq!(
+            run(LineOperation::Delete, "one", 1..1),
+            (String::new(), 0..0)
+        );
+    }
+
+    #[test]
+    fn delete_last_crlf_line() {
+        assert_eq!(
+            run(LineOperation::Delete, "one\r\ntwo", 6..6),
+            ("one".to_string(), 0..0)
+        );
+        assert_eq!(
+            run(LineOperation::Delete, "one\r\ntwo\r\n", 10..10),
+            ("one\r\ntwo".to_string(), 5..5)
+        );
+    }
+
+    #[test]
+    fn move_lines() {
+        let text = "one\ntwo\nthree";
+        assert_eq!(
+            run(LineOperation::MoveUp, text, 5..6),
+            ("two\none\nthree".to_string(), 1..2)
+        );
+        assert_eq!(
+            run(LineOperation::MoveDown, text, 0..8),
+            ("three\none\ntwo".to_string(), 6..14)
+        );
+        assert_eq!(
+            run(LineOperation::MoveUp, text, 1..1),
+            (text.to_string(), 1..1)
+        );
+        assert_eq!(
+            run(LineOperation::MoveDown, text, 9..9),
+            (text.to_string(), 9..9)
+        );
+    }
+
+    #[test]
+    fn indent_and_outdent_lines() {
+        let text = "one\n  two\n\tthree";
+        assert_eq!(
+            run(LineOperation::Indent, text, 1..5),
+            ("\tone\n\t  two\n\tthree".to_string(), 0..11)
+        );
+        assert_eq!(
+            run(LineOperation::Outdent, text, 0..text.len()),
+            ("one\ntwo\nthree".to_string(), 0..13)
+        );
+        assert_eq!(
+            run(LineOperation::Outdent, "one", 1..1),
+            ("one".to_string(), 1..1)
+        );
+        assert_eq!(indent_len("      six"), TAB_SPACES);
+    }
+}
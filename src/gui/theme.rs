@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use async_event_streams::{EventSource, EventStream, EventStreams};
+use async_std::{stream::StreamExt, sync::Arc};
+use futures::task::{Spawn, SpawnExt};
+use windows::UI::Color;
+
+use crate::{
+    color,
+    color::{from_rgb, ColorExt},
+    window::native::{ColorScheme, SystemEvent, SystemEvents},
+};
+
+use super::{PanelEvent, WindowEventSender, MIN_TEXT_CONTRAST};
+
+///
+/// Colors for the skins derived from the accent color and the color scheme the same way
+/// as the system does for native applications. The fixed palettes of the application's own
+/// themes may be declared as constants with [`color!`](crate::color!), like
+/// [`Palette::LIGHT`] and [`Palette::DARK`].
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Palette {
+    pub color_scheme: ColorScheme,
+    pub accent: Color,
+    /// Accent for hovered controls
+    pub accent_light: Color,
+    /// Accent for pressed controls
+    pub accent_dark: Color,
+    /// Text and glyphs over the accent color
+    pub on_accent: Color,
+    pub background: Color,
+    pub foreground: Color,
+    /// Text caret of the editable text
+    pub caret: Color,
+    /// Background of the selected text
+    pub selection: Color,
+}
+
+impl Palette {
+    pub fn new(accent: Color, color_scheme: ColorScheme) -> Self {
+        // Dark scheme needs lighter accent for contrast with the background
+        let (accent, background, foreground) = match color_scheme {
+            ColorScheme::Light => (accent, from_rgb(0xf3f3f3), from_rgb(0x1b1b1b)),
+            ColorScheme::Dark => (accent.lighten(0.1), from_rgb(0x202020), from_rgb(0xffffff)),
+        };
+        let on_accent = contrast_foreground(accent);
+        Self {
+            color_scheme,
+            accent,
+            accent_light: accent.lighten(0.1),
+            accent_dark: accent.darken(0.1),
+            on_accent,
+            background,
+            foreground,
+            caret: foreground,
+            selection: accent.with_alpha(0.4),
+        }
+    }
+}
+
+/// Black or white, whichever is more readable on the opaque background
+pub fn contrast_foreground(background: Color) -> Color {
+    let (black, white) = (from_rgb(0x000000), from_rgb(0xffffff));
+    if black.contrast_ratio(background) > white.contrast_ratio(background) {
+        black
+    } else {
+        white
+    }
+}
+
+///
+/// Foreground for the text on the opaque background: the `preferred` color if its contrast
+/// reaches [`MIN_TEXT_CONTRAST`], otherwise the preferred one lightened or darkened until
+/// it does, so the user's colors keep their hue where possible
+///
+pub fn readable_foreground(background: Color, preferred: Color) -> Color {
+    let preferred = preferred.with_alpha(1.);
+    if preferred.contrast_ratio(background) >= MIN_TEXT_CONTRAST {
+        return preferred;
+    }
+    let lighten = contrast_foreground(background) != from_rgb(0x000000);
+    for step in 1..=20 {
+        let amount = step as f32 * 0.05;
+        let color = if lighten {
+            preferred.lighten(amount)
+        } else {
+            preferred.darken(amount)
+        };
+        if color.contrast_ratio(background) >= MIN_TEXT_CONTRAST {
+            return color;
+        }
+    }
+    contrast_foreground(background)
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::LIGHT
+    }
+}
+
+impl Palette {
+    /// Light palette of the default blue accent, used when the system one is unknown
+    pub const LIGHT: Palette = Palette {
+        color_scheme: ColorScheme::Light,
+        accent: color!("#0078d4"),
+        accent_light: color!("#0894ff"),
+        accent_dark: color!("#005ba1"),
+        on_accent: color!("#000000"),
+        background: color!("#f3f3f3"),
+        foreground: color!("#1b1b1b"),
+        caret: color!("#1b1b1b"),
+        selection: color!("#0078d466"),
+    };
+
+    /// Dark palette of the default blue accent
+    pub const DARK: Palette = Palette {
+        color_scheme: ColorScheme::Dark,
+        accent: color!("#0894ff"),
+        accent_light: color!("#3baaff"),
+        accent_dark: color!("#0078d4"),
+        on_accent: color!("#000000"),
+        background: color!("#202020"),
+        foreground: color!("#ffffff"),
+        caret: color!("#ffffff"),
+        selection: color!("#0894ff66"),
+    };
+
+    /// Color by the name of the field, e.g. "accent_light", for the colors chosen by settings
+    pub fn color(&self, name: &str) -> Option<Color> {
+        match name {
+            "accent" => Some(self.accent),
+            "accent_light" => Some(self.accent_light),
+            "accent_dark" => Some(self.accent_dark),
+            "on_accent" => Some(self.on_accent),
+            "background" => Some(self.background),
+            "foreground" => Some(self.foreground),
+            "caret" => Some(self.caret),
+            "selection" => Some(self.selection),
+            _ => None,
+        }
+    }
+}
+
+/// Sizes for the skins, the defaults are the ones of the system controls
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Metrics {
+    /// Radius of the corners of the buttons, the edit boxes and other controls
+    pub corner_radius: f32,
+    /// Radius of the corners of the popups, the menus and the dialogs
+    pub overlay_corner_radius: f32,
+    pub caption_font_size: f32,
+    pub body_font_size: f32,
+    pub subtitle_font_size: f32,
+    pub title_font_size: f32,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            corner_radius: 4.,
+            overlay_corner_radius: 8.,
+            caption_font_size: 12.,
+            body_font_size: 14.,
+            subtitle_font_size: 20.,
+            title_font_size: 28.,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ThemeEvent {
+    PaletteChanged(Palette),
+    MetricsChanged(Metrics),
+    /// The application's own named color was set
+    ColorChanged(String),
+}
+
+///
+/// Palette, metrics and named colors shared by the skins of the application. The theme
+/// created by `from_system` takes the user's accent color and color scheme, and after `follow`
+/// it updates the palette and sends `ThemeEvent::PaletteChanged` when the user changes them.
+///
+/// The window started by `Window::broadcast_theme` sends `PanelEvent::ThemeChanged` through
+/// its panel tree on each change, so the skins restyle themselves at runtime.
+/// `Window::follow_system_theme` does both: the skins switch with the Windows dark mode.
+///
+#[derive(Clone)]
+pub struct Theme {
+    palette: Arc<Mutex<Palette>>,
+    metrics: Arc<Mutex<Metrics>>,
+    colors: Arc<Mutex<HashMap<String, Color>>>,
+    generation: Arc<AtomicUsize>,
+    theme_events: Arc<EventStreams<ThemeEvent>>,
+}
+
+impl fmt::Debug for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Theme")
+            .field("palette", &self.palette())
+            .field("metrics", &self.metrics())
+            .finish()
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(Palette::default())
+    }
+}
+
+impl Theme {
+    pub fn new(palette: Palette) -> Self {
+        Self {
+            palette: Arc::new(Mutex::new(palette)),
+            metrics: Arc::new(Mutex::new(Metrics::default())),
+            colors: Arc::new(Mutex::new(HashMap::new())),
+            generation: Arc::new(AtomicUsize::new(0)),
+            theme_events: Arc::new(EventStreams::new()),
+        }
+    }
+
+    pub fn from_system(system_events: &SystemEvents) -> Self {
+        Self::new(Palette::new(
+            system_events.accent_color(),
+            system_events.color_scheme(),
+        ))
+    }
+
+    pub fn palette(&self) -> Palette {
+        *self.palette.lock().unwrap()
+    }
+
+    pub async fn set_palette(&self, palette: Palette) {
+        let changed = {
+            let mut current = self.palette.lock().unwrap();
+            std::mem::replace(&mut *current, palette) != palette
+        };
+        if changed {
+            self.theme_events
+                .send_event(ThemeEvent::PaletteChanged(palette), None)
+                .await;
+        }
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        *self.metrics.lock().unwrap()
+    }
+
+    pub async fn set_metrics(&self, metrics: Metrics) {
+        let changed = {
+            let mut current = self.metrics.lock().unwrap();
+            std::mem::replace(&mut *current, metrics) != metrics
+        };
+        if changed {
+            self.theme_events
+                .send_event(ThemeEvent::MetricsChanged(metrics), None)
+                .await;
+        }
+    }
+
+    ///
+    /// Color by the name: the application's own one set by `set_color` or the palette's
+    /// field of that name
+    ///
+    pub fn color(&self, name: &str) -> Option<Color> {
+        let color = self.colors.lock().unwrap().get(name).copied();
+        color.or_else(|| self.palette().color(name))
+    }
+
+    /// Sets the application's named color, it overrides the palette's field of the same name
+    pub async fn set_color(&self, name: impl Into<String>, color: Color) {
+        let name = name.into();
+        let previous = self.colors.lock().unwrap().insert(name.clone(), color);
+        if previous != Some(color) {
+            self.theme_events
+                .send_event(ThemeEvent::ColorChanged(name), None)
+                .await;
+        }
+    }
+
+    /// Updates the palette on the system accent color and color scheme changes until stopped
+    pub fn follow<S: Spawn + ?Sized>(
+        &self,
+        system_events: &SystemEvents,
+        spawner: &S,
+    ) -> crate::Result<()> {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let theme = self.clone();
+        let mut stream = system_events.event_stream();
+        let system_events = system_events.clone();
+        spawner.spawn(async move {
+            while let Some(event) = stream.next().await {
+                if theme.generation.load(Ordering::SeqCst) != generation {
+                    break;
+                }
+                match &*event {
+                    SystemEvent::AccentColorChanged(_) | SystemEvent::ColorSchemeChanged(_) => (),
+                    _ => continue,
+                }
+                // The palette adjusts the accent to the scheme, so it's built from the
+                // system values rather than the current palette
+                let palette =
+                    Palette::new(system_events.accent_color(), system_events.color_scheme());
+                theme.set_palette(palette).await;
+            }
+        })?;
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    ///
+    /// Posts `PanelEvent::ThemeChanged` to the window's panel tree now and on each change of
+    /// the theme until the window is closed
+    ///
+    pub(crate) fn broadcast<S: Spawn + ?Sized>(
+        &self,
+        mut sender: WindowEventSender,
+        spawner: &S,
+    ) -> crate::Result<()> {
+        let theme = self.clone();
+        let mut stream = self.event_stream();
+        let _ = sender.try_send_panel_event(PanelEvent::ThemeChanged(theme.clone()));
+        spawner.spawn(async move {
+            while stream.next().await.is_some() {
+                if let Err(error) =
+                    sender.try_send_panel_event(PanelEvent::ThemeChanged(theme.clone()))
+                {
+                    if error.is_disconnected() {
+                        break;
+                    }
+                }
+            }
+        })?;
+        Ok(())
+    }
+}
+
+impl EventSource<ThemeEvent> for Theme {
+    fn event_stream(&self) -> EventStream<ThemeEvent> {
+        self.theme_events.create_event_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_palettes_match_derived_ones() {
+        let accent = color!("#0078d4");
+        assert_eq!(Palette::LIGHT, Palette::new(accent, ColorScheme::Light));
+        assert_eq!(Palette::DARK, Palette::new(accent, ColorScheme::Dark));
+    }
+}
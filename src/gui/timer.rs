@@ -0,0 +1,198 @@
+use std::{
+    iter,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+    time::Duration,
+};
+
+use async_event_streams::{EventSource, EventStream, EventStreams};
+use async_std::sync::Arc;
+use futures::task::{Spawn, SpawnExt};
+
+use super::clock;
+
+/// Refresh rate assumed until the window reports the one of its display
+pub const DEFAULT_REFRESH_RATE: f32 = 60.;
+
+static REFRESH_RATE: RwLock<f32> = RwLock::new(DEFAULT_REFRESH_RATE);
+
+/// Refresh rate in Hz of the display showing the active window
+pub fn refresh_rate() -> f32 {
+    *REFRESH_RATE.read().unwrap()
+}
+
+///
+/// Sets the refresh rate the frame timers follow. The window calls it when it's activated
+/// or moved to the display with the different rate, so the applications need it only for
+/// their own windows.
+///
+pub fn set_refresh_rate(rate: f32) {
+    if rate > 0. {
+        *REFRESH_RATE.write().unwrap() = rate;
+    }
+}
+
+/// Duration of the display's frame
+pub fn frame_interval() -> Duration {
+    Duration::from_secs_f32(1. / refresh_rate())
+}
+
+///
+/// Interval of the frames limited by `max_fps`. The limited interval is the whole number of
+/// the display's frames, so the frames are shown for the equal time, e.g. 30 FPS on 60 Hz
+/// display takes each second frame and on 144 Hz each fifth one.
+///
+pub fn capped_frame_interval(max_fps: Option<f32>) -> Duration {
+    let rate = refresh_rate();
+    let frames = match max_fps {
+        Some(fps) if fps > 0. && fps < rate => (rate / fps).ceil(),
+        _ => 1.,
+    };
+    Duration::from_secs_f32(frames / rate)
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum TimerEvent {
+    /// Number of the tick since the timer start, counting from 0
+    Tick(usize),
+}
+
+///
+/// Timer sending `TimerEvent::Tick` after each delay of the given sequence. The ticks are sent
+/// from the task on the spawner passed to `start`. Restarting, stopping or dropping the timer
+/// cancels the ticks not yet sent. The delays are counted by the framework's [`clock`] from the start,
+/// so the ticks don't drift when the task is late.
+///
+pub struct Timer {
+    generation: Arc<AtomicUsize>,
+    timer_events: Arc<EventStreams<TimerEvent>>,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self {
+            generation: Arc::new(AtomicUsize::new(0)),
+            timer_events: Arc::new(EventStreams::new()),
+        }
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the timer ticking after each of the delays, the timer stops when they are over
+    pub fn start<S: Spawn + ?Sized>(
+        &self,
+        spawner: &S,
+        delays: impl Iterator<Item = Duration> + Send + 'static,
+    ) -> crate::Result<()> {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = self.generation.clone();
+        let timer_events = self.timer_events.clone();
+        let clock = clock();
+        let mut deadline = clock.now();
+        spawner.spawn(async move {
+            for (index, delay) in delays.enumerate() {
+                deadline += delay;
+                clock.sleep_until(deadline).await;
+                if current.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                timer_events.send_event(TimerEvent::Tick(index), None).await;
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Starts the timer ticking with the constant interval until stopped
+    pub fn start_periodic<S: Spawn + ?Sized>(
+        &self,
+        spawner: &S,
+        interval: Duration,
+    ) -> crate::Result<()> {
+        self.start(spawner, iter::repeat(interval))
+    }
+
+    ///
+    /// Starts the timer ticking once per frame of the display, e.g. for the animations redrawn
+    /// by the panels on each tick. The interval follows the refresh rate changes when the window
+    /// moves to the other display, `max_fps` caps the rate by the [`capped_frame_interval`].
+    ///
+    pub fn start_frames<S: Spawn + ?Sized>(
+        &self,
+        spawner: &S,
+        max_fps: Option<f32>,
+    ) -> crate::Result<()> {
+        self.start(
+            spawner,
+            iter::repeat_with(move || capped_frame_interval(max_fps)),
+        )
+    }
+
+    pub fn stop(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// The task of the dropped timer ends on its next tick instead of ticking forever
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.stop()
+    }
+}
+
+impl EventSource<TimerEvent> for Timer {
+    fn event_stream(&self) -> EventStream<TimerEvent> {
+        self.timer_events.create_event_stream()
+    }
+}
+
+///
+/// Delays for auto-repeat: the `initial_delay` followed by the `interval` multiplied by
+/// `acceleration` on each step until it reaches `min_interval`
+///
+pub fn accelerating_delays(
+    initial_delay: Duration,
+    interval: Duration,
+    min_interval: Duration,
+    acceleration: f32,
+) -> impl Iterator<Item = Duration> + Send + 'static {
+    iter::once(initial_delay).chain(iter::successors(Some(interval), move |v| {
+        Some(v.mul_f32(acceleration).max(min_interval))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accelerating_delays_reach_min_interval() {
+        // Powers of two to keep the f32 multiplication exact
+        let delays: Vec<_> = accelerating_delays(
+            Duration::from_secs(8),
+            Duration::from_secs(4),
+            Duration::from_secs(1),
+            0.5,
+        )
+        .take(6)
+        .collect();
+        assert_eq!(delays, [8, 4, 2, 1, 1, 1].map(Duration::from_secs).to_vec());
+    }
+
+    #[test]
+    fn capped_interval_is_whole_frames() {
+        let frame = Duration::from_secs_f32(1. / DEFAULT_REFRESH_RATE);
+        assert_eq!(capped_frame_interval(None), frame);
+        assert_eq!(capped_frame_interval(Some(0.)), frame);
+        assert_eq!(capped_frame_interval(Some(120.)), frame);
+        assert_eq!(
+            capped_frame_interval(Some(25.)),
+            Duration::from_secs_f32(3. / DEFAULT_REFRESH_RATE)
+        );
+    }
+}
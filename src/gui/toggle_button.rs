@@ -0,0 +1,638 @@
+use std::{
+    borrow::Cow,
+    sync::{Mutex, Weak},
+};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::Vector2,
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+use crate::geometry::Rect;
+
+use super::{
+    attach, dispatch::DispatchQueue, Background, BackgroundParams, ButtonEvent, CommandEvent,
+    CommandRegistry, CornerRadius, LayerStack, LayerStackParams, Panel, PanelEvent,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ToggleButtonEvent {
+    CheckedChanged(bool),
+}
+
+struct Core {
+    skin: Arc<dyn ToggleButtonSkin>,
+    checked: bool,
+    pressed: bool,
+    hover: bool,
+    focused: bool,
+    size: Vector2,
+    command: Option<(Arc<CommandRegistry>, usize)>,
+    toggle_button_events: Arc<EventStreams<ToggleButtonEvent>>,
+}
+
+impl Core {
+    async fn set_checked(
+        &mut self,
+        checked: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<bool> {
+        if self.checked == checked {
+            return Ok(false);
+        }
+        self.checked = checked;
+        let event = ToggleButtonEvent::CheckedChanged(checked);
+        self.skin.on_event_ref(&event, source.clone()).await?;
+        self.toggle_button_events.send_event(event, source).await;
+        if let Some((commands, id)) = &self.command {
+            commands.set_checked(*id, checked).await;
+        }
+        Ok(true)
+    }
+    async fn send(&self, event: ButtonEvent, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.skin.on_event_owned(event, source).await
+    }
+    async fn set_focused(
+        &mut self,
+        focused: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if self.focused != focused {
+            self.focused = focused;
+            self.send(ButtonEvent::FocusChanged(focused), source)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Button keeping the checked state, toggled by click. Buttons in the same [`ToggleGroup`]
+/// are mutually exclusive like radio buttons. The button bound to the command of
+/// the [`CommandRegistry`] invokes the command on click and mirrors the checked state into it.
+/// The button gets keyboard focus on mouse press: Space or Enter toggles it, arrow keys move
+/// the focus and the check to the previous or next button of the group, like radio buttons.
+/// To follow the changes of the command state made elsewhere, pipe the registry's
+/// `CommandEvent`s into the button.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+#[event_sink(event=CommandEvent)]
+pub struct ToggleButton {
+    container: ContainerVisual,
+    core: Arc<RwLock<Core>>,
+    group: Option<Arc<ToggleGroup>>,
+    panel_events: EventStreams<PanelEvent>,
+    toggle_button_events: Arc<EventStreams<ToggleButtonEvent>>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ToggleButtonParams {
+    compositor: Compositor,
+    #[builder(setter(transform = |skin: impl ToggleButtonSkin + 'static | Arc::new(skin) as Arc<dyn ToggleButtonSkin>))]
+    skin: Arc<dyn ToggleButtonSkin>,
+    #[builder(default)]
+    checked: bool,
+    #[builder(default, setter(strip_option))]
+    group: Option<Arc<ToggleGroup>>,
+    #[builder(default, setter(strip_option))]
+    command: Option<(Arc<CommandRegistry>, usize)>,
+}
+
+impl TryFrom<ToggleButtonParams> for ToggleButton {
+    type Error = crate::Error;
+
+    fn try_from(value: ToggleButtonParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let skin = value.skin;
+        attach(&container, &*skin)?;
+        let toggle_button_events = Arc::new(EventStreams::new());
+        let core = Arc::new(RwLock::new(Core {
+            skin,
+            checked: value.checked,
+            pressed: false,
+            hover: false,
+            focused: false,
+            size: Vector2::default(),
+            command: value.command,
+            toggle_button_events: toggle_button_events.clone(),
+        }));
+        let id = Arc::new(());
+        if let Some(group) = &value.group {
+            group.add_member(Arc::as_ptr(&id) as usize, &core, value.checked);
+        }
+        Ok(ToggleButton {
+            container,
+            core,
+            group: value.group,
+            panel_events: EventStreams::new(),
+            toggle_button_events,
+            dispatch: DispatchQueue::new(),
+            id,
+        })
+    }
+}
+
+impl TryFrom<ToggleButtonParams> for Arc<ToggleButton> {
+    type Error = crate::Error;
+
+    fn try_from(value: ToggleButtonParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ToggleButton {
+    pub async fn is_checked(&self) -> bool {
+        self.core.read().await.checked
+    }
+
+    pub async fn set_checked(&self, checked: bool) -> crate::Result<()> {
+        self.set_checked_from(checked, None).await
+    }
+
+    async fn set_checked_from(
+        &self,
+        checked: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let changed = self
+            .core
+            .write()
+            .await
+            .set_checked(checked, source.clone())
+            .await?;
+        if let (true, Some(group)) = (changed, &self.group) {
+            group.member_changed(self.id(), checked, source).await?;
+        }
+        Ok(())
+    }
+
+    async fn click(&self, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        let (checked, command) = {
+            let core = self.core.read().await;
+            (core.checked, core.command.clone())
+        };
+        // Checked member of the exclusive group can be unchecked only by checking another one
+        if checked && self.group.as_ref().map_or(false, |v| !v.allow_none) {
+            return Ok(());
+        }
+        if let Some((commands, id)) = command {
+            if commands.command(id).await.is_some() && !commands.is_enabled(id).await {
+                return Ok(());
+            }
+            self.set_checked_from(!checked, source).await?;
+            commands.invoke(id).await;
+            Ok(())
+        } else {
+            self.set_checked_from(!checked, source).await
+        }
+    }
+
+    async fn key_pressed(
+        &self,
+        key: VirtualKeyCode,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if !self.core.read().await.focused {
+            return Ok(());
+        }
+        match (key, &self.group) {
+            (VirtualKeyCode::Space | VirtualKeyCode::Return, _) => self.click(source).await,
+            (VirtualKeyCode::Left | VirtualKeyCode::Up, Some(group)) => {
+                group.step(self.id(), false, source).await
+            }
+            (VirtualKeyCode::Right | VirtualKeyCode::Down, Some(group)) => {
+                group.step(self.id(), true, source).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let skin = self.core.read().await.skin.clone();
+        skin.on_event_ref(&event, source.clone()).await?;
+        self.panel_events
+            .send_event(event.clone(), source.clone())
+            .await;
+        match event {
+            PanelEvent::Resized(size) => self.core.write().await.size = size,
+            PanelEvent::CursorMoved(pos) => {
+                let mut core = self.core.write().await;
+                let hover = Rect::from_size(core.size).contains(pos);
+                if core.hover != hover {
+                    core.hover = hover;
+                    core.send(ButtonEvent::HoverChanged(hover), source).await?;
+                }
+            }
+            PanelEvent::MouseInput {
+                in_slot,
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let click = {
+                    let mut core = self.core.write().await;
+                    match state {
+                        ElementState::Pressed => {
+                            core.set_focused(in_slot, source.clone()).await?;
+                            if !in_slot {
+                                return Ok(());
+                            }
+                            core.pressed = true;
+                            core.send(ButtonEvent::Press, source.clone()).await?;
+                            false
+                        }
+                        ElementState::Released if core.pressed => {
+                            core.pressed = false;
+                            core.send(ButtonEvent::Release(in_slot), source.clone())
+                                .await?;
+                            in_slot
+                        }
+                        _ => false,
+                    }
+                };
+                if click {
+                    self.click(source).await?;
+                }
+            }
+            PanelEvent::KeyboardInput {
+                state: ElementState::Pressed,
+                key: Some(key),
+                ..
+            } => self.key_pressed(key, source).await?,
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+impl EventSource<ToggleButtonEvent> for ToggleButton {
+    fn event_stream(&self) -> EventStream<ToggleButtonEvent> {
+        self.toggle_button_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for ToggleButton {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ToggleButton {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<CommandEvent> for ToggleButton {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, CommandEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let CommandEvent::CheckedChanged { id, checked } = event.as_ref() {
+            let bound = self.core.read().await.command.as_ref().map(|v| v.1);
+            if bound == Some(*id) {
+                self.set_checked_from(*checked, source).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Panel for ToggleButton {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ToggleGroupEvent {
+    /// Id of the checked button or None if no button is checked
+    SelectionChanged(Option<usize>),
+}
+
+///
+/// Set of mutually exclusive [`ToggleButton`]s: checking one button unchecks the others.
+/// The button joins the group by `ToggleButtonParams::group`. Arrow keys move the check
+/// between the buttons in the order they joined the group.
+///
+pub struct ToggleGroup {
+    allow_none: bool,
+    members: Mutex<Vec<(usize, Weak<RwLock<Core>>)>>,
+    selected: Mutex<Option<usize>>,
+    toggle_group_events: EventStreams<ToggleGroupEvent>,
+}
+
+impl ToggleGroup {
+    /// With `allow_none` the checked button can be unchecked by click leaving no selection
+    pub fn new(allow_none: bool) -> Self {
+        Self {
+            allow_none,
+            members: Mutex::new(Vec::new()),
+            selected: Mutex::new(None),
+            toggle_group_events: EventStreams::new(),
+        }
+    }
+
+    /// Id of the checked button
+    pub fn selected(&self) -> Option<usize> {
+        *self.selected.lock().unwrap()
+    }
+
+    fn add_member(&self, id: usize, core: &Arc<RwLock<Core>>, checked: bool) {
+        let mut members = self.members.lock().unwrap();
+        members.retain(|(_, v)| v.strong_count() > 0);
+        members.push((id, Arc::downgrade(core)));
+        if checked {
+            *self.selected.lock().unwrap() = Some(id);
+        }
+    }
+
+    // Moves the focus and the check from the button to its neighbour, wrapping around
+    async fn step(
+        &self,
+        id: usize,
+        forward: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let (current, next) = {
+            let mut members = self.members.lock().unwrap();
+            members.retain(|(_, v)| v.strong_count() > 0);
+            let count = members.len();
+            let index = match members.iter().position(|(member, _)| *member == id) {
+                Some(index) if count > 1 => index,
+                _ => return Ok(()),
+            };
+            let next = if forward {
+                (index + 1) % count
+            } else {
+                (index + count - 1) % count
+            };
+            (members[index].1.upgrade(), members[next].clone())
+        };
+        let (next_id, next_core) = match next.1.upgrade() {
+            Some(core) => (next.0, core),
+            None => return Ok(()),
+        };
+        if let Some(core) = current {
+            core.write()
+                .await
+                .set_focused(false, source.clone())
+                .await?;
+        }
+        let changed = {
+            let mut core = next_core.write().await;
+            core.set_focused(true, source.clone()).await?;
+            core.set_checked(true, source.clone()).await?
+        };
+        if changed {
+            self.member_changed(next_id, true, source).await?;
+        }
+        Ok(())
+    }
+
+    async fn member_changed(
+        &self,
+        id: usize,
+        checked: bool,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let selected = {
+            let mut selected = self.selected.lock().unwrap();
+            match (checked, *selected) {
+                (true, _) => *selected = Some(id),
+                (false, Some(current)) if current == id => *selected = None,
+                _ => return Ok(()),
+            }
+            *selected
+        };
+        if checked {
+            let others = self
+                .members
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(member, _)| *member != id)
+                .filter_map(|(_, core)| core.upgrade())
+                .collect::<Vec<_>>();
+            for core in others {
+                core.write()
+                    .await
+                    .set_checked(false, source.clone())
+                    .await?;
+            }
+        }
+        self.toggle_group_events
+            .send_event(ToggleGroupEvent::SelectionChanged(selected), source)
+            .await;
+        Ok(())
+    }
+}
+
+impl EventSource<ToggleGroupEvent> for ToggleGroup {
+    fn event_stream(&self) -> EventStream<ToggleGroupEvent> {
+        self.toggle_group_events.create_event_stream()
+    }
+}
+
+pub trait ToggleButtonSkin:
+    Panel
+    + EventSink<ButtonEvent, Error = crate::Error>
+    + EventSink<ToggleButtonEvent, Error = crate::Error>
+{
+}
+impl<
+        T: Panel
+            + EventSink<ButtonEvent, Error = crate::Error>
+            + EventSink<ToggleButtonEvent, Error = crate::Error>,
+    > ToggleButtonSkin for T
+{
+}
+
+#[derive(Default)]
+struct SkinState {
+    checked: bool,
+    pressed: bool,
+    hover: bool,
+}
+
+///
+/// Default toggle button skin: rounded background changing the color with the state
+/// under the optional content, e.g. the tool icon
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+#[event_sink(event=ButtonEvent)]
+#[event_sink(event=ToggleButtonEvent)]
+pub struct SimpleToggleButtonSkin {
+    layer_stack: LayerStack,
+    background: Arc<Background>,
+    color: Color,
+    hover_color: Color,
+    checked_color: Color,
+    pressed_color: Color,
+    state: RwLock<SkinState>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct SimpleToggleButtonSkinParams {
+    compositor: Compositor,
+    #[builder(default, setter(strip_option))]
+    content: Option<Arc<dyn Panel>>,
+    #[builder(default = Colors::Gainsboro().unwrap())]
+    color: Color,
+    #[builder(default = Colors::LightGray().unwrap())]
+    hover_color: Color,
+    #[builder(default = Colors::LightSkyBlue().unwrap())]
+    checked_color: Color,
+    #[builder(default = Colors::SkyBlue().unwrap())]
+    pressed_color: Color,
+}
+
+impl TryFrom<SimpleToggleButtonSkinParams> for SimpleToggleButtonSkin {
+    type Error = crate::Error;
+
+    fn try_from(value: SimpleToggleButtonSkinParams) -> crate::Result<Self> {
+        let background: Arc<Background> = BackgroundParams::builder()
+            .color(value.color)
+            .corner_radius(CornerRadius::relative(0.05))
+            .compositor(value.compositor.clone())
+            .build()
+            .try_into()?;
+        let mut layer_stack = LayerStackParams::builder()
+            .compositor(value.compositor)
+            .build()
+            .push_panel(background.clone());
+        if let Some(content) = value.content {
+            layer_stack = layer_stack.push_panel(content);
+        }
+        Ok(SimpleToggleButtonSkin {
+            layer_stack: layer_stack.try_into()?,
+            background,
+            color: value.color,
+            hover_color: value.hover_color,
+            checked_color: value.checked_color,
+            pressed_color: value.pressed_color,
+            state: RwLock::new(SkinState::default()),
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<SimpleToggleButtonSkinParams> for Arc<SimpleToggleButtonSkin> {
+    type Error = crate::Error;
+
+    fn try_from(value: SimpleToggleButtonSkinParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl SimpleToggleButtonSkin {
+    async fn update(&self, f: impl FnOnce(&mut SkinState)) -> crate::Result<()> {
+        let mut state = self.state.write().await;
+        f(&mut state);
+        let color = if state.pressed {
+            self.pressed_color
+        } else if state.checked {
+            self.checked_color
+        } else if state.hover {
+            self.hover_color
+        } else {
+            self.color
+        };
+        self.background.set_color(color).await
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<ButtonEvent> for SimpleToggleButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, ButtonEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match event.as_ref() {
+            ButtonEvent::Press => self.update(|v| v.pressed = true).await,
+            ButtonEvent::Release(_) => self.update(|v| v.pressed = false).await,
+            ButtonEvent::HoverChanged(hover) => self.update(|v| v.hover = *hover).await,
+            ButtonEvent::EnabledChanged(_) | ButtonEvent::FocusChanged(_) => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<ToggleButtonEvent> for SimpleToggleButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, ToggleButtonEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let ToggleButtonEvent::CheckedChanged(checked) = event.as_ref();
+        self.update(|v| v.checked = *checked).await
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for SimpleToggleButtonSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.layer_stack.on_event(event, source).await
+    }
+}
+
+impl EventSource<PanelEvent> for SimpleToggleButtonSkin {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for SimpleToggleButtonSkin {
+    fn outer_frame(&self) -> Visual {
+        self.layer_stack.outer_frame()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -0,0 +1,398 @@
+use std::{borrow::Cow, time::Duration};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::{
+        Color, Colors,
+        Composition::{Compositor, ContainerVisual, ShapeVisual, Visual},
+    },
+};
+use winit::event::{ElementState, MouseButton};
+
+use super::{
+    animation_stepper::start_animation, attach, dispatch::DispatchQueue, time_span, Panel,
+    PanelEvent,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ToggleSwitchEvent {
+    Toggled(bool),
+}
+
+struct Core {
+    skin: Arc<dyn ToggleSwitchSkin>,
+    on: bool,
+    pressed: bool,
+    toggle_switch_events: Arc<EventStreams<ToggleSwitchEvent>>,
+}
+
+impl Core {
+    async fn set_on(&mut self, on: bool, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        if self.on == on {
+            return Ok(());
+        }
+        self.on = on;
+        let event = ToggleSwitchEvent::Toggled(on);
+        self.skin.on_event_ref(&event, source.clone()).await?;
+        self.toggle_switch_events.send_event(event, source).await;
+        Ok(())
+    }
+    fn skin_panel(&self) -> Arc<dyn ToggleSwitchSkin> {
+        self.skin.clone()
+    }
+}
+
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct ToggleSwitch {
+    container: ContainerVisual,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    toggle_switch_events: Arc<EventStreams<ToggleSwitchEvent>>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ToggleSwitchParams {
+    compositor: Compositor,
+    #[builder(setter(transform = |skin: impl ToggleSwitchSkin + 'static | Arc::new(skin) as Arc<dyn ToggleSwitchSkin>))]
+    skin: Arc<dyn ToggleSwitchSkin>,
+}
+
+impl TryFrom<ToggleSwitchParams> for ToggleSwitch {
+    type Error = crate::Error;
+
+    fn try_from(value: ToggleSwitchParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let skin = value.skin;
+        attach(&container, &*skin)?;
+        let toggle_switch_events = Arc::new(EventStreams::new());
+        let core = RwLock::new(Core {
+            skin,
+            on: false,
+            pressed: false,
+            toggle_switch_events: toggle_switch_events.clone(),
+        });
+        Ok(ToggleSwitch {
+            container,
+            core,
+            panel_events: EventStreams::new(),
+            toggle_switch_events,
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ToggleSwitchParams> for Arc<ToggleSwitch> {
+    type Error = crate::Error;
+
+    fn try_from(value: ToggleSwitchParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ToggleSwitch {
+    pub async fn is_on(&self) -> bool {
+        self.core.read().await.on
+    }
+    pub async fn set_on(&self, on: bool) -> crate::Result<()> {
+        self.core.write().await.set_on(on, None).await
+    }
+    pub async fn toggle(&self) -> crate::Result<()> {
+        let mut core = self.core.write().await;
+        let on = !core.on;
+        core.set_on(on, None).await
+    }
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        let skin = self.core.read().await.skin_panel();
+        skin.on_event_ref(&event, source.clone()).await?;
+        self.panel_events
+            .send_event(event.clone(), source.clone())
+            .await;
+        if let PanelEvent::MouseInput {
+            in_slot,
+            state,
+            button: MouseButton::Left,
+            ..
+        } = event
+        {
+            let mut core = self.core.write().await;
+            if state == ElementState::Pressed {
+                core.pressed = in_slot;
+            } else if state == ElementState::Released && core.pressed {
+                core.pressed = false;
+                if in_slot {
+                    let on = !core.on;
+                    core.set_on(on, source).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EventSource<ToggleSwitchEvent> for ToggleSwitch {
+    fn event_stream(&self) -> EventStream<ToggleSwitchEvent> {
+        self.toggle_switch_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for ToggleSwitch {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ToggleSwitch {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for ToggleSwitch {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+pub trait ToggleSwitchSkin: Panel + EventSink<ToggleSwitchEvent, Error = crate::Error> {}
+impl<T: Panel + EventSink<ToggleSwitchEvent, Error = crate::Error>> ToggleSwitchSkin for T {}
+
+struct SkinCore {
+    compositor: Compositor,
+    track: ShapeVisual,
+    thumb: ShapeVisual,
+    on_color: Color,
+    off_color: Color,
+    thumb_color: Color,
+    duration: Duration,
+    on: bool,
+}
+
+impl SkinCore {
+    // Track is a pill of 2:1 proportion centered in the panel, thumb is a circle inside it
+    fn track_rect(&self) -> crate::Result<(Vector2, Vector2)> {
+        let size = self.track.Size()?;
+        let height = size.Y.min(size.X / 2.);
+        let track_size = Vector2 {
+            X: height * 2.,
+            Y: height,
+        };
+        let offset = Vector2 {
+            X: (size.X - track_size.X) / 2.,
+            Y: (size.Y - track_size.Y) / 2.,
+        };
+        Ok((offset, track_size))
+    }
+    fn thumb_offset(&self, on: bool) -> crate::Result<Vector3> {
+        let (offset, track_size) = self.track_rect()?;
+        let x = if on {
+            offset.X + track_size.X - track_size.Y
+        } else {
+            offset.X
+        };
+        Ok(Vector3 {
+            X: x,
+            Y: offset.Y,
+            Z: 0.,
+        })
+    }
+    fn redraw_track(&self) -> crate::Result<()> {
+        let (offset, track_size) = self.track_rect()?;
+        let shapes = self.track.Shapes()?;
+        shapes.Clear()?;
+        let geometry = self.compositor.CreateRoundedRectangleGeometry()?;
+        geometry.SetSize(track_size)?;
+        geometry.SetCornerRadius(Vector2 {
+            X: track_size.Y / 2.,
+            Y: track_size.Y / 2.,
+        })?;
+        let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        let color = if self.on {
+            self.on_color
+        } else {
+            self.off_color
+        };
+        shape.SetFillBrush(&self.compositor.CreateColorBrushWithColor(color)?)?;
+        shape.SetOffset(offset)?;
+        shapes.Append(&shape)?;
+        Ok(())
+    }
+    fn redraw_thumb(&self) -> crate::Result<()> {
+        let (_, track_size) = self.track_rect()?;
+        let side = track_size.Y;
+        self.thumb.SetSize(Vector2 { X: side, Y: side })?;
+        let shapes = self.thumb.Shapes()?;
+        shapes.Clear()?;
+        let geometry = self.compositor.CreateEllipseGeometry()?;
+        let radius = side * 0.4;
+        geometry.SetCenter(Vector2 {
+            X: side / 2.,
+            Y: side / 2.,
+        })?;
+        geometry.SetRadius(Vector2 {
+            X: radius,
+            Y: radius,
+        })?;
+        let shape = self.compositor.CreateSpriteShapeWithGeometry(&geometry)?;
+        shape.SetFillBrush(
+            &self
+                .compositor
+                .CreateColorBrushWithColor(self.thumb_color)?,
+        )?;
+        shapes.Append(&shape)?;
+        self.thumb.SetOffset(self.thumb_offset(self.on)?)?;
+        Ok(())
+    }
+    fn resize(&self, size: Vector2) -> crate::Result<()> {
+        self.track.SetSize(size)?;
+        self.redraw_track()?;
+        self.redraw_thumb()?;
+        Ok(())
+    }
+    fn switch(&mut self, on: bool) -> crate::Result<()> {
+        self.on = on;
+        self.redraw_track()?;
+        let animation = self.compositor.CreateVector3KeyFrameAnimation()?;
+        animation.InsertKeyFrame(1., self.thumb_offset(on)?)?;
+        animation.SetDuration(time_span(self.duration))?;
+        start_animation(&self.thumb, "Offset", &animation)?;
+        Ok(())
+    }
+}
+
+///
+/// Default toggle switch skin: a pill-shaped track with round thumb sliding between
+/// the left (off) and right (on) positions
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+#[event_sink(event=ToggleSwitchEvent)]
+pub struct SimpleToggleSwitchSkin {
+    container: ContainerVisual,
+    core: RwLock<SkinCore>,
+    panel_events: EventStreams<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct SimpleToggleSwitchSkinParams {
+    compositor: Compositor,
+    on_color: Color,
+    #[builder(default = Colors::Gray().unwrap())]
+    off_color: Color,
+    #[builder(default = Colors::White().unwrap())]
+    thumb_color: Color,
+    #[builder(default = Duration::from_millis(150))]
+    duration: Duration,
+}
+
+impl TryFrom<SimpleToggleSwitchSkinParams> for SimpleToggleSwitchSkin {
+    type Error = crate::Error;
+    fn try_from(value: SimpleToggleSwitchSkinParams) -> crate::Result<Self> {
+        let container = value.compositor.CreateContainerVisual()?;
+        let track = value.compositor.CreateShapeVisual()?;
+        let thumb = value.compositor.CreateShapeVisual()?;
+        container.Children()?.InsertAtTop(&track)?;
+        container.Children()?.InsertAtTop(&thumb)?;
+        let core = RwLock::new(SkinCore {
+            compositor: value.compositor,
+            track,
+            thumb,
+            on_color: value.on_color,
+            off_color: value.off_color,
+            thumb_color: value.thumb_color,
+            duration: value.duration,
+            on: false,
+        });
+        Ok(SimpleToggleSwitchSkin {
+            container,
+            core,
+            panel_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<SimpleToggleSwitchSkinParams> for Arc<SimpleToggleSwitchSkin> {
+    type Error = crate::Error;
+
+    fn try_from(value: SimpleToggleSwitchSkinParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<ToggleSwitchEvent> for SimpleToggleSwitchSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, ToggleSwitchEvent>,
+        _: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match event.as_ref() {
+            ToggleSwitchEvent::Toggled(on) => self.core.write().await.switch(*on)?,
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for SimpleToggleSwitchSkin {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.container.SetSize(*size)?;
+            self.core.read().await.resize(*size)?;
+        }
+        self.panel_events
+            .send_event(event.into_owned(), source)
+            .await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for SimpleToggleSwitchSkin {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl Panel for SimpleToggleSwitchSkin {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
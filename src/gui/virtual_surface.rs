@@ -0,0 +1,357 @@
+use std::{borrow::Cow, collections::HashSet, sync::Arc};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::EventSink;
+use async_std::sync::RwLock;
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::Interface,
+    Foundation::Numerics::{Matrix3x2, Vector2},
+    Graphics::{
+        DirectX::{DirectXAlphaMode, DirectXPixelFormat},
+        RectInt32, SizeInt32,
+    },
+    Win32::{
+        Foundation::{E_INVALIDARG, RECT},
+        Graphics::Direct2D::{Common::D2D_RECT_F, ID2D1DeviceContext, D2D1_ANTIALIAS_MODE_ALIASED},
+    },
+    UI::Composition::{
+        CompositionGraphicsDevice, CompositionStretch, CompositionSurfaceBrush,
+        CompositionVirtualDrawingSurface, Compositor, SpriteVisual, Visual,
+    },
+};
+
+use crate::{
+    geometry::{Point, Rect, Size},
+    window::{create_composition_graphics_device, draw_rect},
+};
+
+use super::{Panel, PanelEvent};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum VirtualSurfaceEvent {
+    /// Tiles (in content coordinates) which became visible and are not drawn yet
+    Redraw(Vec<RectInt32>),
+}
+
+struct Core {
+    content_size: SizeInt32,
+    tile_size: i32,
+    viewport_offset: Vector2,
+    viewport_size: Vector2,
+    requested_tiles: HashSet<(i32, i32)>,
+}
+
+impl Core {
+    fn tile_rect(&self, tx: i32, ty: i32) -> RectInt32 {
+        let x = tx * self.tile_size;
+        let y = ty * self.tile_size;
+        RectInt32 {
+            X: x,
+            Y: y,
+            Width: std::cmp::min(self.tile_size, self.content_size.Width - x),
+            Height: std::cmp::min(self.tile_size, self.content_size.Height - y),
+        }
+    }
+    fn tile_range(&self, from: f32, len: f32, limit: i32) -> std::ops::Range<i32> {
+        let start = (from.max(0.) as i32).min(limit);
+        let end = ((from + len).ceil() as i32).min(limit);
+        if end <= start {
+            return 0..0;
+        }
+        (start / self.tile_size)..((end - 1) / self.tile_size + 1)
+    }
+    fn visible_tiles(&self) -> Vec<(i32, i32)> {
+        let columns = self.tile_range(
+            self.viewport_offset.X,
+            self.viewport_size.X,
+            self.content_size.Width,
+        );
+        let rows = self.tile_range(
+            self.viewport_offset.Y,
+            self.viewport_size.Y,
+            self.content_size.Height,
+        );
+        rows.flat_map(|ty| columns.clone().map(move |tx| (tx, ty)))
+            .collect()
+    }
+    // Returns visible tiles which were not requested yet and marks them as requested
+    fn take_missing_tiles(&mut self) -> Vec<RectInt32> {
+        let missing = self
+            .visible_tiles()
+            .into_iter()
+            .filter(|tile| !self.requested_tiles.contains(tile))
+            .collect::<Vec<_>>();
+        self.requested_tiles.extend(missing.iter().cloned());
+        missing
+            .into_iter()
+            .map(|(tx, ty)| self.tile_rect(tx, ty))
+            .collect()
+    }
+    fn tile_bounds(&self, tx: i32, ty: i32) -> Rect {
+        let ts = self.tile_size as f32;
+        Rect::new(
+            Point::new(tx as f32 * ts, ty as f32 * ts),
+            Size::new(ts, ts),
+        )
+    }
+    fn forget_tiles(&mut self, rect: Rect) {
+        let tiles = self.requested_tiles.clone();
+        for (tx, ty) in tiles {
+            if self.tile_bounds(tx, ty).intersects(&rect) {
+                self.requested_tiles.remove(&(tx, ty));
+            }
+        }
+    }
+    fn keep_tiles(&mut self, rects: &[Rect]) {
+        let tiles = self.requested_tiles.clone();
+        for (tx, ty) in tiles {
+            let bounds = self.tile_bounds(tx, ty);
+            if !rects.iter().any(|r| bounds.intersects(r)) {
+                self.requested_tiles.remove(&(tx, ty));
+            }
+        }
+    }
+}
+
+///
+/// Panel showing content much larger than the panel itself, backed by
+/// `CompositionVirtualDrawingSurface`. The content is drawn by tiles on demand: when tiles become
+/// visible due to resize or scrolling, `VirtualSurfaceEvent::Redraw` is emitted and the application
+/// is expected to draw them with [`VirtualSurface::draw_tile`].
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct VirtualSurface {
+    sprite_visual: SpriteVisual,
+    _composition_graphic_device: CompositionGraphicsDevice,
+    surface: CompositionVirtualDrawingSurface,
+    surface_brush: CompositionSurfaceBrush,
+    core: RwLock<Core>,
+    panel_events: EventStreams<PanelEvent>,
+    virtual_surface_events: EventStreams<VirtualSurfaceEvent>,
+    id: Arc<()>,
+}
+
+impl VirtualSurface {
+    fn new(compositor: Compositor, content_size: SizeInt32, tile_size: i32) -> crate::Result<Self> {
+        let sprite_visual = compositor.CreateSpriteVisual()?;
+        let composition_graphic_device = create_composition_graphics_device(&compositor)?;
+        let surface = composition_graphic_device.CreateVirtualDrawingSurface(
+            content_size,
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            DirectXAlphaMode::Premultiplied,
+        )?;
+        let surface_brush = compositor.CreateSurfaceBrush()?;
+        surface_brush.SetStretch(CompositionStretch::None)?;
+        surface_brush.SetHorizontalAlignmentRatio(0.)?;
+        surface_brush.SetVerticalAlignmentRatio(0.)?;
+        surface_brush.SetSurface(&surface)?;
+        sprite_visual.SetBrush(&surface_brush)?;
+        let core = RwLock::new(Core {
+            content_size,
+            tile_size,
+            viewport_offset: Vector2::default(),
+            viewport_size: Vector2::default(),
+            requested_tiles: HashSet::new(),
+        });
+        Ok(Self {
+            sprite_visual,
+            _composition_graphic_device: composition_graphic_device,
+            surface,
+            surface_brush,
+            core,
+            panel_events: EventStreams::new(),
+            virtual_surface_events: EventStreams::new(),
+            id: Arc::new(()),
+        })
+    }
+
+    pub fn surface(&self) -> &CompositionVirtualDrawingSurface {
+        &self.surface
+    }
+
+    pub async fn content_size(&self) -> SizeInt32 {
+        self.core.read().await.content_size
+    }
+
+    pub async fn set_content_size(&self, content_size: SizeInt32) -> crate::Result<()> {
+        self.surface.Resize(content_size)?;
+        {
+            let mut core = self.core.write().await;
+            core.content_size = content_size;
+            core.requested_tiles.clear();
+        }
+        self.request_missing_tiles().await
+    }
+
+    pub async fn viewport_offset(&self) -> Vector2 {
+        self.core.read().await.viewport_offset
+    }
+
+    /// Scrolls the content so that the point `offset` of the content is at the panel's top left corner
+    pub async fn set_viewport_offset(&self, offset: Vector2) -> crate::Result<()> {
+        self.surface_brush.SetOffset(Vector2 {
+            X: -offset.X,
+            Y: -offset.Y,
+        })?;
+        self.core.write().await.viewport_offset = offset;
+        self.request_missing_tiles().await
+    }
+
+    /// Marks the area as outdated. Visible tiles in this area are requested for redraw immediately,
+    /// the invisible ones - when they become visible.
+    pub async fn invalidate(&self, rect: RectInt32) -> crate::Result<()> {
+        self.core.write().await.forget_tiles(rect.into());
+        self.request_missing_tiles().await
+    }
+
+    pub async fn invalidate_all(&self) -> crate::Result<()> {
+        self.core.write().await.requested_tiles.clear();
+        self.request_missing_tiles().await
+    }
+
+    /// Releases the memory of the content outside of `rects`. The trimmed tiles are requested
+    /// again when they become visible.
+    pub async fn trim(&self, rects: &[RectInt32]) -> crate::Result<()> {
+        self.surface.Trim(rects)?;
+        let rects = rects.iter().map(|r| Rect::from(*r)).collect::<Vec<_>>();
+        self.core.write().await.keep_tiles(&rects);
+        Ok(())
+    }
+
+    /// Releases the memory of everything except the visible area
+    pub async fn trim_to_viewport(&self) -> crate::Result<()> {
+        let viewport = {
+            let core = self.core.read().await;
+            Rect::new(core.viewport_offset.into(), core.viewport_size.into())
+        };
+        self.trim(&[viewport.into()]).await
+    }
+
+    ///
+    /// Draws the `rect` area of the content. The context passed to `f` is translated to content
+    /// coordinates and clipped by `rect`.
+    ///
+    pub fn draw_tile<F: FnOnce(&ID2D1DeviceContext, RectInt32) -> crate::Result<()>>(
+        &self,
+        rect: RectInt32,
+        f: F,
+    ) -> crate::Result<()> {
+        let update_rect = RECT {
+            left: rect.X,
+            top: rect.Y,
+            right: rect.X + rect.Width,
+            bottom: rect.Y + rect.Height,
+        };
+        draw_rect(
+            &self.surface.cast()?,
+            Some(update_rect),
+            |context, offset| {
+                unsafe {
+                    context.SetTransform(&Matrix3x2::translation(
+                        (offset.x - rect.X) as f32,
+                        (offset.y - rect.Y) as f32,
+                    ));
+                    context.PushAxisAlignedClip(
+                        &D2D_RECT_F {
+                            left: rect.X as f32,
+                            top: rect.Y as f32,
+                            right: (rect.X + rect.Width) as f32,
+                            bottom: (rect.Y + rect.Height) as f32,
+                        },
+                        D2D1_ANTIALIAS_MODE_ALIASED,
+                    );
+                }
+                let result = f(&context, rect);
+                unsafe {
+                    context.PopAxisAlignedClip();
+                    context.SetTransform(&Matrix3x2::identity());
+                }
+                result
+            },
+        )
+    }
+
+    async fn request_missing_tiles(&self) -> crate::Result<()> {
+        let tiles = self.core.write().await.take_missing_tiles();
+        if !tiles.is_empty() {
+            self.virtual_surface_events
+                .send_event(VirtualSurfaceEvent::Redraw(tiles), None)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for VirtualSurface {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        if let PanelEvent::Resized(size) = event.as_ref() {
+            self.sprite_visual.SetSize(*size)?;
+            self.core.write().await.viewport_size = *size;
+            self.request_missing_tiles().await?;
+        }
+        self.panel_events
+            .send_event(event.into_owned(), source)
+            .await;
+        Ok(())
+    }
+}
+
+impl EventSource<PanelEvent> for VirtualSurface {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+impl EventSource<VirtualSurfaceEvent> for VirtualSurface {
+    fn event_stream(&self) -> EventStream<VirtualSurfaceEvent> {
+        self.virtual_surface_events.create_event_stream()
+    }
+}
+
+impl Panel for VirtualSurface {
+    fn outer_frame(&self) -> Visual {
+        self.sprite_visual.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
+
+#[derive(TypedBuilder)]
+pub struct VirtualSurfaceParams {
+    compositor: Compositor,
+    content_size: SizeInt32,
+    /// Side of the square tile in pixels, must be positive
+    #[builder(default = 256)]
+    tile_size: i32,
+}
+
+impl TryFrom<VirtualSurfaceParams> for VirtualSurface {
+    type Error = crate::Error;
+
+    fn try_from(value: VirtualSurfaceParams) -> crate::Result<Self> {
+        if value.tile_size <= 0 {
+            return Err(windows::core::Error::from(E_INVALIDARG).into());
+        }
+        VirtualSurface::new(value.compositor, value.content_size, value.tile_size)
+    }
+}
+
+impl TryFrom<VirtualSurfaceParams> for Arc<VirtualSurface> {
+    type Error = crate::Error;
+
+    fn try_from(value: VirtualSurfaceParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use super::{CursorSelector, DebugFrames, DragDrop, EventFilters, IdleMonitor, PointerCapture};
+
+type Services = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+///
+/// Singletons of one window found by their type: the command registry, the timers, the
+/// application's own services. The window's own cursor selector, drag-and-drop, pointer
+/// capture, idle monitor, event filters and debug frames are here from the start and have
+/// their accessors, the application adds the rest. Pass the services to the panels, e.g. in the [`Context`], so a panel deep in the
+/// tree reaches them without the window.
+///
+/// The services added by the application are released when the window is destroyed, even if
+/// the panels still hold the registry.
+///
+/// [`Context`]: super::Context
+///
+#[derive(Clone)]
+pub struct WindowServices {
+    services: Arc<Mutex<Services>>,
+    handles: WindowHandles,
+}
+
+// The window's own services, created with the window
+#[derive(Clone)]
+pub(crate) struct WindowHandles {
+    pub(crate) pointer_capture: PointerCapture,
+    pub(crate) drag_drop: DragDrop,
+    pub(crate) idle_monitor: IdleMonitor,
+    pub(crate) cursors: CursorSelector,
+    pub(crate) debug_frames: DebugFrames,
+    pub(crate) filters: EventFilters,
+}
+
+impl WindowServices {
+    pub(crate) fn new(handles: WindowHandles) -> Self {
+        let services = Self {
+            services: Arc::new(Mutex::new(Services::new())),
+            handles,
+        };
+        let handles = &services.handles;
+        services.register(handles.cursors.clone());
+        services.register(handles.drag_drop.clone());
+        services.register(handles.pointer_capture.clone());
+        services.register(handles.idle_monitor.clone());
+        services.register(handles.filters.clone());
+        services.register(handles.debug_frames.clone());
+        services
+    }
+
+    /// Pointer capture routing the mouse events of the window to one panel
+    pub fn pointer_capture(&self) -> &PointerCapture {
+        &self.handles.pointer_capture
+    }
+
+    /// Drag-and-drop between the panels of the window
+    pub fn drag_drop(&self) -> &DragDrop {
+        &self.handles.drag_drop
+    }
+
+    /// Idle detection by the input in the window
+    pub fn idle_monitor(&self) -> &IdleMonitor {
+        &self.handles.idle_monitor
+    }
+
+    /// Cursors declared by the panels of the window
+    pub fn cursors(&self) -> &CursorSelector {
+        &self.handles.cursors
+    }
+
+    /// Developer mode framing the visuals of the window
+    pub fn debug_frames(&self) -> &DebugFrames {
+        &self.handles.debug_frames
+    }
+
+    /// Filters receiving the events of the window before the panel tree
+    pub fn filters(&self) -> &EventFilters {
+        &self.handles.filters
+    }
+
+    /// Registers the service, returns the replaced one of the same type
+    pub fn register<T: Any + Send + Sync>(&self, service: T) -> Option<Arc<T>> {
+        self.register_arc(Arc::new(service))
+    }
+
+    /// Same as `register` for the service already shared
+    pub fn register_arc<T: Any + Send + Sync>(&self, service: Arc<T>) -> Option<Arc<T>> {
+        self.services
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<T>(), service)
+            .and_then(|v| v.downcast::<T>().ok())
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.services
+            .lock()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .and_then(|v| v.clone().downcast::<T>().ok())
+    }
+
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.services
+            .lock()
+            .unwrap()
+            .contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn remove<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.services
+            .lock()
+            .unwrap()
+            .remove(&TypeId::of::<T>())
+            .and_then(|v| v.downcast::<T>().ok())
+    }
+
+    /// Releases all the services, done by the window when it's destroyed
+    pub fn clear(&self) {
+        // The services are dropped out of the lock, their drop may use the registry
+        let services = std::mem::take(&mut *self.services.lock().unwrap());
+        drop(services);
+    }
+}
@@ -0,0 +1,403 @@
+use std::{borrow::Cow, sync::Mutex};
+
+use async_event_streams::{
+    EventBox, EventSink, EventSinkExt, EventSource, EventStream, EventStreams,
+};
+use async_event_streams_derive::{self, EventSink};
+use async_std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use typed_builder::TypedBuilder;
+use windows::{
+    core::{implement, HSTRING},
+    Foundation::Numerics::{Vector2, Vector3},
+    UI::{
+        Composition::{
+            Compositor, ContainerVisual,
+            Interactions::{
+                IInteractionTrackerOwner, IInteractionTrackerOwner_Impl, InteractionSourceMode,
+                InteractionTracker, InteractionTrackerCustomAnimationStateEnteredArgs,
+                InteractionTrackerIdleStateEnteredArgs, InteractionTrackerInertiaStateEnteredArgs,
+                InteractionTrackerInteractingStateEnteredArgs,
+                InteractionTrackerRequestIgnoredArgs, InteractionTrackerValuesChangedArgs,
+                VisualInteractionSource,
+            },
+            Visual,
+        },
+        Input::PointerPoint,
+    },
+};
+use winit::event::{MouseScrollDelta, TouchPhase};
+
+use crate::geometry::{Point, Rect, Size};
+
+use super::{dispatch::DispatchQueue, Panel, PanelEvent};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ZoomPanelEvent {
+    /// Part of the child visible in the panel, in the child's coordinates
+    ViewportChanged(Rect),
+}
+
+#[derive(Default, Clone, Copy)]
+struct Extent {
+    viewport: Vector2,
+    content: Vector2,
+}
+
+// Viewport of the content visible in the panel when the content is moved by `position` and
+// scaled by `scale`
+fn viewport(extent: Extent, position: Vector3, scale: f32) -> Rect {
+    Rect::new(
+        Point::new(position.X / scale, position.Y / scale),
+        Size::new(extent.viewport.X / scale, extent.viewport.Y / scale),
+    )
+}
+
+// Keeps the tracker's position range matching the scaled content and reports the viewport
+#[implement(IInteractionTrackerOwner)]
+struct TrackerOwner {
+    extent: Arc<Mutex<Extent>>,
+    zoom_panel_events: Arc<EventStreams<ZoomPanelEvent>>,
+}
+
+impl IInteractionTrackerOwner_Impl for TrackerOwner {
+    fn CustomAnimationStateEntered(
+        &self,
+        _sender: &Option<InteractionTracker>,
+        _args: &Option<InteractionTrackerCustomAnimationStateEnteredArgs>,
+    ) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn IdleStateEntered(
+        &self,
+        _sender: &Option<InteractionTracker>,
+        _args: &Option<InteractionTrackerIdleStateEnteredArgs>,
+    ) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn InertiaStateEntered(
+        &self,
+        _sender: &Option<InteractionTracker>,
+        _args: &Option<InteractionTrackerInertiaStateEnteredArgs>,
+    ) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn InteractingStateEntered(
+        &self,
+        _sender: &Option<InteractionTracker>,
+        _args: &Option<InteractionTrackerInteractingStateEnteredArgs>,
+    ) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn RequestIgnored(
+        &self,
+        _sender: &Option<InteractionTracker>,
+        _args: &Option<InteractionTrackerRequestIgnoredArgs>,
+    ) -> windows::core::Result<()> {
+        Ok(())
+    }
+    fn ValuesChanged(
+        &self,
+        sender: &Option<InteractionTracker>,
+        args: &Option<InteractionTrackerValuesChangedArgs>,
+    ) -> windows::core::Result<()> {
+        if let (Some(tracker), Some(args)) = (sender, args) {
+            let extent = *self.extent.lock().unwrap();
+            let scale = args.Scale()?;
+            set_max_position(tracker, extent, scale)?;
+            self.zoom_panel_events.post_event(
+                ZoomPanelEvent::ViewportChanged(viewport(extent, args.Position()?, scale)),
+                None,
+            );
+        }
+        Ok(())
+    }
+}
+
+fn set_max_position(
+    tracker: &InteractionTracker,
+    extent: Extent,
+    scale: f32,
+) -> windows::core::Result<()> {
+    tracker.SetMaxPosition(Vector3 {
+        X: (extent.content.X * scale - extent.viewport.X).max(0.),
+        Y: (extent.content.Y * scale - extent.viewport.Y).max(0.),
+        Z: 0.,
+    })
+}
+
+struct Core {
+    mouse_pos: Option<Vector2>,
+}
+
+///
+/// Panel showing the child of fixed size which the user pans and zooms. Touch manipulations
+/// are handled by the composition `InteractionTracker` with inertia, the wheel pans the child
+/// and Ctrl+wheel zooms it around the cursor. Precision touchpads send their pinch as Ctrl+wheel,
+/// so it zooms too. The child receives the cursor position in its own coordinates.
+///
+#[derive(EventSink)]
+#[event_sink(event=PanelEvent)]
+pub struct ZoomPanel {
+    container: ContainerVisual,
+    child: Arc<dyn Panel>,
+    content_size: Vector2,
+    wheel_step: f32,
+    zoom_step: f32,
+    tracker: InteractionTracker,
+    interaction_source: VisualInteractionSource,
+    extent: Arc<Mutex<Extent>>,
+    core: RwLock<Core>,
+    zoom_panel_events: Arc<EventStreams<ZoomPanelEvent>>,
+    panel_events: EventStreams<PanelEvent>,
+    dispatch: DispatchQueue<PanelEvent>,
+    id: Arc<()>,
+}
+
+#[derive(TypedBuilder)]
+pub struct ZoomPanelParams {
+    compositor: Compositor,
+    child: Arc<dyn Panel>,
+    #[builder(setter(into))]
+    content_size: Size,
+    #[builder(default = 0.25)]
+    min_scale: f32,
+    #[builder(default = 4.)]
+    max_scale: f32,
+    /// Pixels to pan by one line of the wheel
+    #[builder(default = 48.)]
+    wheel_step: f32,
+    /// Scale factor applied by one line of Ctrl+wheel
+    #[builder(default = 1.1)]
+    zoom_step: f32,
+}
+
+impl TryFrom<ZoomPanelParams> for ZoomPanel {
+    type Error = crate::Error;
+
+    fn try_from(value: ZoomPanelParams) -> crate::Result<Self> {
+        let compositor = value.compositor;
+        let container = compositor.CreateContainerVisual()?;
+        container.SetClip(&compositor.CreateInsetClip()?)?;
+        let frame = value.child.outer_frame();
+        let content_size: Vector2 = value.content_size.into();
+        frame.SetSize(content_size)?;
+        container.Children()?.InsertAtTop(&frame)?;
+
+        let extent = Arc::new(Mutex::new(Extent {
+            viewport: Vector2::default(),
+            content: content_size,
+        }));
+        let zoom_panel_events = Arc::new(EventStreams::new());
+        let owner: IInteractionTrackerOwner = TrackerOwner {
+            extent: extent.clone(),
+            zoom_panel_events: zoom_panel_events.clone(),
+        }
+        .into();
+        let tracker = InteractionTracker::CreateWithOwner(&compositor, &owner)?;
+        tracker.SetMinScale(value.min_scale)?;
+        tracker.SetMaxScale(value.max_scale)?;
+        let interaction_source = VisualInteractionSource::Create(&container)?;
+        interaction_source.SetPositionXSourceMode(InteractionSourceMode::EnabledWithInertia)?;
+        interaction_source.SetPositionYSourceMode(InteractionSourceMode::EnabledWithInertia)?;
+        interaction_source.SetScaleSourceMode(InteractionSourceMode::EnabledWithInertia)?;
+        tracker.InteractionSources()?.Add(&interaction_source)?;
+
+        // The child follows the tracker on the compositor thread
+        let offset = compositor
+            .CreateExpressionAnimationWithExpression(&HSTRING::from("-tracker.Position"))?;
+        offset.SetReferenceParameter(&HSTRING::from("tracker"), &tracker)?;
+        frame.StartAnimation(&HSTRING::from("Offset"), &offset)?;
+        let scale = compositor.CreateExpressionAnimationWithExpression(&HSTRING::from(
+            "Vector3(tracker.Scale, tracker.Scale, 1)",
+        ))?;
+        scale.SetReferenceParameter(&HSTRING::from("tracker"), &tracker)?;
+        frame.StartAnimation(&HSTRING::from("Scale"), &scale)?;
+
+        Ok(ZoomPanel {
+            container,
+            child: value.child,
+            content_size,
+            wheel_step: value.wheel_step,
+            zoom_step: value.zoom_step,
+            tracker,
+            interaction_source,
+            extent,
+            core: RwLock::new(Core { mouse_pos: None }),
+            zoom_panel_events,
+            panel_events: EventStreams::new(),
+            dispatch: DispatchQueue::new(),
+            id: Arc::new(()),
+        })
+    }
+}
+
+impl TryFrom<ZoomPanelParams> for Arc<ZoomPanel> {
+    type Error = crate::Error;
+
+    fn try_from(value: ZoomPanelParams) -> crate::Result<Self> {
+        Ok(Arc::new(value.try_into()?))
+    }
+}
+
+impl ZoomPanel {
+    /// Part of the child currently visible, in the child's coordinates
+    pub fn viewport(&self) -> crate::Result<Rect> {
+        let extent = *self.extent.lock().unwrap();
+        Ok(viewport(
+            extent,
+            self.tracker.Position()?,
+            self.tracker.Scale()?,
+        ))
+    }
+
+    /// Zooms to the scale keeping the point of the panel at `center` in place
+    pub fn zoom_to(&self, scale: f32, center: Vector2) -> crate::Result<()> {
+        self.tracker.TryUpdateScale(
+            scale,
+            Vector3 {
+                X: center.X,
+                Y: center.Y,
+                Z: 0.,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Scrolls the child so its `point` is at the top left corner of the panel
+    pub fn scroll_to(&self, point: Vector2) -> crate::Result<()> {
+        let scale = self.tracker.Scale()?;
+        self.tracker.TryUpdatePosition(Vector3 {
+            X: point.X * scale,
+            Y: point.Y * scale,
+            Z: 0.,
+        })?;
+        Ok(())
+    }
+
+    // Translates the panel point to the child's coordinates
+    fn to_child(&self, pos: Vector2) -> crate::Result<Vector2> {
+        let position = self.tracker.Position()?;
+        let scale = self.tracker.Scale()?;
+        Ok(Vector2 {
+            X: (pos.X + position.X) / scale,
+            Y: (pos.Y + position.Y) / scale,
+        })
+    }
+
+    async fn resize(&self, size: Vector2, source: Option<Arc<EventBox>>) -> crate::Result<()> {
+        self.container.SetSize(size)?;
+        let extent = {
+            let mut extent = self.extent.lock().unwrap();
+            extent.viewport = size;
+            *extent
+        };
+        set_max_position(&self.tracker, extent, self.tracker.Scale()?)?;
+        self.child
+            .on_event_owned(PanelEvent::Resized(self.content_size), source)
+            .await
+    }
+
+    fn wheel(&self, delta: MouseScrollDelta, ctrl: bool, pos: Vector2) -> crate::Result<()> {
+        let (x, y) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (x, y),
+            MouseScrollDelta::PixelDelta(v) => {
+                (v.x as f32 / self.wheel_step, v.y as f32 / self.wheel_step)
+            }
+        };
+        if ctrl {
+            let scale = self.tracker.Scale()? * self.zoom_step.powf(y);
+            self.zoom_to(scale, pos)?;
+        } else {
+            self.tracker.TryUpdatePositionBy(Vector3 {
+                X: -x * self.wheel_step,
+                Y: -y * self.wheel_step,
+                Z: 0.,
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn process_event(
+        &self,
+        event: PanelEvent,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        match &event {
+            PanelEvent::Resized(size) => self.resize(*size, source.clone()).await?,
+            PanelEvent::CursorMoved(pos) => {
+                self.core.write().await.mouse_pos = Some(*pos);
+                self.child
+                    .on_event_owned(
+                        PanelEvent::CursorMoved(self.to_child(*pos)?),
+                        source.clone(),
+                    )
+                    .await?;
+            }
+            PanelEvent::MouseWheel {
+                delta, modifiers, ..
+            } => {
+                let pos = self.core.read().await.mouse_pos;
+                let size = self.container.Size()?;
+                // Wheel goes to all panels, only the one under the cursor reacts
+                if let Some(pos) = pos.filter(|v| Rect::from_size(size).contains(*v)) {
+                    self.wheel(*delta, modifiers.ctrl(), pos)?;
+                }
+            }
+            PanelEvent::Touch {
+                id,
+                phase: TouchPhase::Started,
+                ..
+            } => {
+                let pos = self.core.read().await.mouse_pos;
+                let size = self.container.Size()?;
+                if pos.map_or(false, |v| Rect::from_size(size).contains(v)) {
+                    // The system takes over the touch and feeds it to the tracker
+                    let point = PointerPoint::GetCurrentPoint(*id as u32)?;
+                    self.interaction_source.TryRedirectForManipulation(&point)?;
+                }
+                self.child.on_event_ref(&event, source.clone()).await?;
+            }
+            _ => self.child.on_event_ref(&event, source.clone()).await?,
+        }
+        self.panel_events.send_event(event, source).await;
+        Ok(())
+    }
+}
+
+impl EventSource<ZoomPanelEvent> for ZoomPanel {
+    fn event_stream(&self) -> EventStream<ZoomPanelEvent> {
+        self.zoom_panel_events.create_event_stream()
+    }
+}
+
+impl EventSource<PanelEvent> for ZoomPanel {
+    fn event_stream(&self) -> EventStream<PanelEvent> {
+        self.panel_events.create_event_stream()
+    }
+}
+
+#[async_trait]
+impl EventSinkExt<PanelEvent> for ZoomPanel {
+    type Error = crate::Error;
+    async fn on_event<'a>(
+        &'a self,
+        event: Cow<'a, PanelEvent>,
+        source: Option<Arc<EventBox>>,
+    ) -> crate::Result<()> {
+        self.dispatch
+            .dispatch(event.into_owned(), source, |event, source| {
+                self.process_event(event, source)
+            })
+            .await
+    }
+}
+
+impl Panel for ZoomPanel {
+    fn outer_frame(&self) -> Visual {
+        self.container.clone().into()
+    }
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.id) as usize
+    }
+}
@@ -1,5 +1,20 @@
 //! # WAG - Windows Asynchronous GUI
+//!
+//! Optional parts of the library are enabled by cargo features:
+//! - `core-panels` - controls: buttons, check boxes, toggle switches, progress indicators,
+//!   virtual surface
+//! - `text` - text rendering with DirectWrite, the controls depending on it, the clipboard
+//!   and spell checking
+//! - `image` - reading and writing image files with WIC
+//! - `capture` - capturing visuals to bitmaps: `CacheVisual` and golden-image `Snapshot`
+//! - `dialogs` - modal `Dialog` and drag and drop with other applications
+//! - `accessibility` - accessibility metadata on visuals, the accessibility tree and audit
+//! - `media`, `webview` - reserved for the media and web content panels, enable nothing yet
+//!
+//! All but `media` and `webview` are enabled by default.
+pub mod color;
 mod error;
+pub mod geometry;
 pub mod gui;
 pub mod window;
 
@@ -0,0 +1,125 @@
+#[cfg(feature = "image")]
+use std::path::Path;
+
+use windows::{core, Win32::Foundation::E_INVALIDARG};
+#[cfg(feature = "image")]
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Graphics::Imaging::{
+            CLSID_WICImagingFactory, GUID_ContainerFormatPng, GUID_WICPixelFormat32bppBGRA,
+            IWICBitmapFrameEncode, IWICImagingFactory, WICBitmapDitherTypeNone,
+            WICBitmapEncoderNoCache, WICBitmapPaletteTypeCustom, WICDecodeMetadataCacheOnDemand,
+        },
+        System::Com::{CoCreateInstance, StructuredStorage::IPropertyBag2, CLSCTX_INPROC_SERVER},
+    },
+};
+
+// Access right of the WIC file stream
+#[cfg(feature = "image")]
+const GENERIC_WRITE: u32 = 0x4000_0000;
+
+#[cfg(feature = "image")]
+fn imaging_factory() -> crate::Result<IWICImagingFactory> {
+    Ok(unsafe { CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER) }?)
+}
+
+/// Image with 8-bit blue, green, red and alpha channels, rows from top to bottom
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> crate::Result<Self> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return Err(core::Error::from(E_INVALIDARG).into());
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Transparent image of the size
+    pub fn empty(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Pixel at the position, `None` outside of the image
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let pos = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[pos..pos + 4].try_into().ok()
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
+        if x < self.width && y < self.height {
+            let pos = (y as usize * self.width as usize + x as usize) * 4;
+            self.pixels[pos..pos + 4].copy_from_slice(&pixel);
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl Bitmap {
+    /// Reads the image file in any format supported by WIC, PNG included
+    pub fn load(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let mut bytes = std::fs::read(path)?;
+        let factory = imaging_factory()?;
+        unsafe {
+            let stream = factory.CreateStream()?;
+            stream.InitializeFromMemory(&mut bytes)?;
+            let decoder =
+                factory.CreateDecoderFromStream(&stream, None, WICDecodeMetadataCacheOnDemand)?;
+            let frame = decoder.GetFrame(0)?;
+            let converter = factory.CreateFormatConverter()?;
+            converter.Initialize(
+                &frame,
+                &GUID_WICPixelFormat32bppBGRA,
+                WICBitmapDitherTypeNone,
+                None,
+                0.,
+                WICBitmapPaletteTypeCustom,
+            )?;
+            let (mut width, mut height) = (0, 0);
+            converter.GetSize(&mut width, &mut height)?;
+            let mut pixels = vec![0; width as usize * height as usize * 4];
+            converter.CopyPixels(std::ptr::null(), width * 4, &mut pixels)?;
+            Self::new(width, height, pixels)
+        }
+    }
+
+    /// Writes the image to the PNG file, replacing it
+    pub fn save_png(&self, path: impl AsRef<Path>) -> crate::Result<()> {
+        let path = HSTRING::from(path.as_ref().as_os_str());
+        let factory = imaging_factory()?;
+        unsafe {
+            let stream = factory.CreateStream()?;
+            stream.InitializeFromFilename(&path, GENERIC_WRITE)?;
+            let encoder = factory.CreateEncoder(&GUID_ContainerFormatPng, None)?;
+            encoder.Initialize(&stream, WICBitmapEncoderNoCache)?;
+            let mut frame: Option<IWICBitmapFrameEncode> = None;
+            let mut options: Option<IPropertyBag2> = None;
+            encoder.CreateNewFrame(&mut frame, &mut options)?;
+            let frame = frame.ok_or_else(|| core::Error::from(E_INVALIDARG))?;
+            frame.Initialize(options.as_ref())?;
+            frame.SetSize(self.width, self.height)?;
+            let mut format = GUID_WICPixelFormat32bppBGRA;
+            frame.SetPixelFormat(&mut format)?;
+            frame.WritePixels(self.height, self.width * 4, &self.pixels)?;
+            frame.Commit()?;
+            encoder.Commit()?;
+        }
+        Ok(())
+    }
+}
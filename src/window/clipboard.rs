@@ -0,0 +1,298 @@
+//!
+//! System clipboard. The clipboard is shared with other applications which may hold it open
+//! for a moment, so the functions are async and wait for it instead of failing at once.
+//!
+use std::{ptr::copy_nonoverlapping, time::Duration};
+
+use async_std::task::sleep;
+use windows::{
+    core::{self, PCWSTR},
+    Win32::{
+        Foundation::{E_INVALIDARG, E_OUTOFMEMORY, HANDLE, HINSTANCE, HWND},
+        System::{
+            DataExchange::{
+                CloseClipboard, EmptyClipboard, GetClipboardData, IsClipboardFormatAvailable,
+                OpenClipboard, SetClipboardData,
+            },
+            Memory::{
+                GlobalAlloc, GlobalFree, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE,
+            },
+            SystemServices::{CF_DIB, CF_UNICODETEXT},
+        },
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, HMENU, HWND_MESSAGE, WINDOW_EX_STYLE, WINDOW_STYLE,
+        },
+    },
+};
+
+use super::ToWide;
+
+const OPEN_ATTEMPTS: usize = 10;
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+// BITMAPINFOHEADER of the device independent bitmap
+const DIB_HEADER_SIZE: usize = 40;
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+thread_local! {
+    // The clipboard needs the owner window to accept the data, the thread's windows
+    // may come and go, so the clipboard has its own invisible one
+    static OWNER: core::Result<HWND> = {
+        let owner = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                "STATIC".to_wide().as_pcwstr(),
+                PCWSTR::null(),
+                WINDOW_STYLE::default(),
+                0,
+                0,
+                0,
+                0,
+                HWND_MESSAGE,
+                HMENU::default(),
+                HINSTANCE::default(),
+                None,
+            )
+        };
+        if owner.0 == 0 {
+            Err(core::Error::from_win32())
+        } else {
+            Ok(owner)
+        }
+    };
+}
+
+// Clipboard opened by this thread, closed on drop
+struct OpenedClipboard;
+
+impl OpenedClipboard {
+    fn open() -> crate::Result<Self> {
+        let owner = OWNER.with(|owner| owner.clone())?;
+        if unsafe { OpenClipboard(owner) }.as_bool() {
+            Ok(OpenedClipboard)
+        } else {
+            Err(core::Error::from_win32().into())
+        }
+    }
+}
+
+impl Drop for OpenedClipboard {
+    fn drop(&mut self) {
+        unsafe { CloseClipboard() };
+    }
+}
+
+// Runs `f` with the clipboard open, retrying while another application holds it
+async fn with_clipboard<R>(f: impl FnOnce() -> crate::Result<R>) -> crate::Result<R> {
+    let mut attempt = 1;
+    loop {
+        match OpenedClipboard::open() {
+            Ok(clipboard) => {
+                let result = f();
+                drop(clipboard);
+                return result;
+            }
+            Err(error) if attempt >= OPEN_ATTEMPTS => return Err(error),
+            Err(_) => {
+                attempt += 1;
+                sleep(OPEN_RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+// Content of the clipboard in the format, the clipboard must be open
+fn read(format: u32) -> crate::Result<Option<Vec<u8>>> {
+    if !unsafe { IsClipboardFormatAvailable(format) }.as_bool() {
+        return Ok(None);
+    }
+    let handle = unsafe { GetClipboardData(format) }?;
+    unsafe {
+        let ptr = GlobalLock(handle.0) as *const u8;
+        if ptr.is_null() {
+            return Err(core::Error::from_win32().into());
+        }
+        let bytes = std::slice::from_raw_parts(ptr, GlobalSize(handle.0)).to_vec();
+        GlobalUnlock(handle.0);
+        Ok(Some(bytes))
+    }
+}
+
+// Replaces the content of the clipboard, the clipboard must be open
+fn write(format: u32, bytes: &[u8]) -> crate::Result<()> {
+    unsafe {
+        if !EmptyClipboard().as_bool() {
+            return Err(core::Error::from_win32().into());
+        }
+        let global = GlobalAlloc(GMEM_MOVEABLE, bytes.len());
+        if global == 0 {
+            return Err(core::Error::from(E_OUTOFMEMORY).into());
+        }
+        let ptr = GlobalLock(global) as *mut u8;
+        if ptr.is_null() {
+            let error = core::Error::from_win32();
+            GlobalFree(global);
+            return Err(error.into());
+        }
+        copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+        GlobalUnlock(global);
+        // The system owns the memory only if the call succeeds
+        if let Err(error) = SetClipboardData(format, HANDLE(global)) {
+            GlobalFree(global);
+            return Err(error.into());
+        }
+    }
+    Ok(())
+}
+
+/// Text on the clipboard, `None` if the clipboard holds no text
+pub async fn get_text() -> crate::Result<Option<String>> {
+    let bytes = match with_clipboard(|| read(CF_UNICODETEXT.0)).await? {
+        Some(bytes) => bytes,
+        None => return Ok(None),
+    };
+    let wide: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+        .collect();
+    // The text is null terminated, the block may be larger than the text
+    let len = wide.iter().position(|v| *v == 0).unwrap_or(wide.len());
+    Ok(Some(String::from_utf16_lossy(&wide[..len])))
+}
+
+/// Puts the text on the clipboard, replacing its content
+pub async fn set_text(text: &str) -> crate::Result<()> {
+    let bytes: Vec<u8> = text
+        .encode_utf16()
+        .chain(Some(0))
+        .flat_map(u16::to_ne_bytes)
+        .collect();
+    with_clipboard(|| write(CF_UNICODETEXT.0, &bytes)).await
+}
+
+/// Image with 8-bit blue, green, red and alpha channels, rows from top to bottom
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ClipboardImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl ClipboardImage {
+    // Parses the 24 or 32 bit uncompressed DIB, the others are not supported
+    fn from_dib(dib: &[u8]) -> Option<Self> {
+        let u32_at = |pos: usize| {
+            let bytes = dib.get(pos..pos + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().ok()?))
+        };
+        let header_size = u32_at(0)? as usize;
+        let width = u32_at(4)? as i32;
+        let height = u32_at(8)? as i32;
+        let bit_count = u16::from_le_bytes(dib.get(14..16)?.try_into().ok()?);
+        let compression = u32_at(16)?;
+        let colors_used = u32_at(32)? as usize;
+        if width <= 0 || height == 0 || !(bit_count == 24 || bit_count == 32) {
+            return None;
+        }
+        let masks = match compression {
+            BI_RGB => 0,
+            // The masks follow the header unless it's the larger one including them
+            BI_BITFIELDS if header_size == DIB_HEADER_SIZE => 12,
+            BI_BITFIELDS => 0,
+            _ => return None,
+        };
+        let offset = header_size + masks + colors_used * 4;
+        let (width, rows) = (width as usize, height.unsigned_abs() as usize);
+        let bytes_per_pixel = bit_count as usize / 8;
+        let stride = (width * bytes_per_pixel + 3) / 4 * 4;
+        let data = dib.get(offset..offset + stride * rows)?;
+        let mut pixels = Vec::with_capacity(width * rows * 4);
+        for row in 0..rows {
+            // Positive height means the rows are stored from bottom to top
+            let row = if height > 0 { rows - 1 - row } else { row };
+            let line = &data[row * stride..row * stride + width * bytes_per_pixel];
+            for pixel in line.chunks_exact(bytes_per_pixel) {
+                let alpha = if bytes_per_pixel == 4 { pixel[3] } else { 255 };
+                pixels.extend_from_slice(&[pixel[0], pixel[1], pixel[2], alpha]);
+            }
+        }
+        // Most applications leave the alpha of 32-bit bitmaps zero, such images are opaque
+        if bytes_per_pixel == 4 && pixels.chunks_exact(4).all(|v| v[3] == 0) {
+            pixels.chunks_exact_mut(4).for_each(|v| v[3] = 255);
+        }
+        Some(ClipboardImage {
+            width: width as u32,
+            height: rows as u32,
+            pixels,
+        })
+    }
+
+    // The empty images and the pixels not matching the size are rejected
+    fn to_dib(&self) -> crate::Result<Vec<u8>> {
+        let size_matches = self.pixels.len() == self.width as usize * self.height as usize * 4;
+        let fits = i32::try_from(self.width).is_ok() && i32::try_from(self.height).is_ok();
+        if self.width == 0 || self.height == 0 || !size_matches || !fits {
+            return Err(core::Error::from(E_INVALIDARG).into());
+        }
+        let mut dib = Vec::with_capacity(DIB_HEADER_SIZE + self.pixels.len());
+        dib.extend_from_slice(&(DIB_HEADER_SIZE as u32).to_le_bytes());
+        dib.extend_from_slice(&(self.width as i32).to_le_bytes());
+        // Bottom-up rows, the top-down ones are misread by some applications
+        dib.extend_from_slice(&(self.height as i32).to_le_bytes());
+        dib.extend_from_slice(&1u16.to_le_bytes());
+        dib.extend_from_slice(&32u16.to_le_bytes());
+        dib.extend_from_slice(&BI_RGB.to_le_bytes());
+        dib.extend_from_slice(&(self.pixels.len() as u32).to_le_bytes());
+        dib.resize(DIB_HEADER_SIZE, 0);
+        let stride = self.width as usize * 4;
+        for row in self.pixels.chunks_exact(stride).rev() {
+            dib.extend_from_slice(row);
+        }
+        Ok(dib)
+    }
+}
+
+///
+/// Image on the clipboard, `None` if the clipboard holds no image or the image is not an
+/// uncompressed 24 or 32 bit bitmap
+///
+pub async fn get_image() -> crate::Result<Option<ClipboardImage>> {
+    let dib = with_clipboard(|| read(CF_DIB.0)).await?;
+    Ok(dib.and_then(|v| ClipboardImage::from_dib(&v)))
+}
+
+/// Puts the image on the clipboard, replacing its content
+pub async fn set_image(image: &ClipboardImage) -> crate::Result<()> {
+    let dib = image.to_dib()?;
+    with_clipboard(|| write(CF_DIB.0, &dib)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dib_round_trip() {
+        let image = ClipboardImage {
+            width: 2,
+            height: 2,
+            pixels: (0..16).collect(),
+        };
+        let dib = image.to_dib().unwrap();
+        assert_eq!(ClipboardImage::from_dib(&dib), Some(image));
+    }
+
+    #[test]
+    fn empty_images_are_rejected() {
+        let image = |width, height| ClipboardImage {
+            width,
+            height,
+            pixels: Vec::new(),
+        };
+        assert!(image(0, 2).to_dib().is_err());
+        assert!(image(2, 0).to_dib().is_err());
+        assert!(image(0, 0).to_dib().is_err());
+        assert!(image(1, 1).to_dib().is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use windows::{
+    core::Interface,
+    Win32::{
+        Foundation::{HANDLE, POINT},
+        Graphics::{
+            Direct3D11::{
+                ID3D11Device1, ID3D11DeviceContext, ID3D11Resource, ID3D11Texture2D, D3D11_BOX,
+                D3D11_TEXTURE2D_DESC,
+            },
+            Dxgi::IDXGIKeyedMutex,
+        },
+        System::WinRT::Composition::ICompositionDrawingSurfaceInterop,
+    },
+    UI::Composition::CompositionDrawingSurface,
+};
+
+use super::graphics::{check_for_device_removed, d3d11_device};
+
+///
+/// Copies the texture into the drawing surface. The texture must be created on the device
+/// returned by `d3d11_device` on the same thread, use `SharedTexture` for the textures of other
+/// devices. The part of the texture exceeding the surface is clipped. Call it each frame
+/// after the renderer finished the texture, the copy is queued on the device's immediate
+/// context before the surface is handed back to the compositor.
+///
+pub fn copy_texture_to_surface(
+    surface: &CompositionDrawingSurface,
+    texture: &ID3D11Texture2D,
+) -> crate::Result<()> {
+    let mut desc = D3D11_TEXTURE2D_DESC::default();
+    unsafe { texture.GetDesc(&mut desc) };
+    let size = surface.SizeInt32()?;
+    let source_box = D3D11_BOX {
+        left: 0,
+        top: 0,
+        front: 0,
+        right: desc.Width.min(size.Width.max(0) as u32),
+        bottom: desc.Height.min(size.Height.max(0) as u32),
+        back: 1,
+    };
+    if source_box.right == 0 || source_box.bottom == 0 {
+        return Ok(());
+    }
+    let mut context: Option<ID3D11DeviceContext> = None;
+    unsafe { d3d11_device()?.GetImmediateContext(&mut context) };
+    let context = match context {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let source: ID3D11Resource = texture.cast()?;
+    let mut offset = POINT { x: 0, y: 0 };
+    let surface_interop: ICompositionDrawingSurfaceInterop = surface.cast()?;
+    // The surface may be a part of the atlas, so the copy goes to the offset returned by BeginDraw
+    let target: Option<ID3D11Resource> =
+        check_for_device_removed(unsafe { surface_interop.BeginDraw(None, &mut offset) })?;
+    if let Some(target) = target {
+        unsafe {
+            context.CopySubresourceRegion(
+                &target,
+                0,
+                offset.x as u32,
+                offset.y as u32,
+                0,
+                &source,
+                0,
+                Some(&source_box),
+            )
+        };
+        unsafe { surface_interop.EndDraw() }?;
+    }
+    Ok(())
+}
+
+///
+/// Texture shared by another device, e.g. by the video decoder or the renderer running on its
+/// own thread, opened on wag's device. If the texture has the keyed mutex, the copy takes it to
+/// not read the frame while the producer writes it.
+///
+pub struct SharedTexture {
+    texture: ID3D11Texture2D,
+    keyed_mutex: Option<IDXGIKeyedMutex>,
+}
+
+impl SharedTexture {
+    ///
+    /// Opens the shared handle, either the NT handle from `CreateSharedHandle` or the legacy
+    /// one from `GetSharedHandle`. Open it once and copy each frame.
+    ///
+    pub fn open(handle: HANDLE) -> crate::Result<Self> {
+        let device = d3d11_device()?;
+        let texture: ID3D11Texture2D = match device.cast::<ID3D11Device1>() {
+            Ok(device1) => match unsafe { device1.OpenSharedResource1(handle) } {
+                Ok(texture) => texture,
+                Err(_) => unsafe { device.OpenSharedResource(handle) }?,
+            },
+            Err(_) => unsafe { device.OpenSharedResource(handle) }?,
+        };
+        let keyed_mutex = texture.cast::<IDXGIKeyedMutex>().ok();
+        Ok(SharedTexture {
+            texture,
+            keyed_mutex,
+        })
+    }
+
+    pub fn texture(&self) -> &ID3D11Texture2D {
+        &self.texture
+    }
+
+    ///
+    /// Copies the texture into the surface. With the keyed mutex, waits up to `timeout` for
+    /// the producer to release it with `acquire_key` and releases it with `release_key` after
+    /// the copy, giving the texture back to the producer.
+    ///
+    pub fn copy_to_surface(
+        &self,
+        surface: &CompositionDrawingSurface,
+        acquire_key: u64,
+        release_key: u64,
+        timeout: Duration,
+    ) -> crate::Result<()> {
+        match &self.keyed_mutex {
+            Some(keyed_mutex) => {
+                let timeout = timeout.as_millis().min(u32::MAX as u128) as u32;
+                unsafe { keyed_mutex.AcquireSync(acquire_key, timeout) }?;
+                let result = copy_texture_to_surface(surface, &self.texture);
+                let release_result = unsafe { keyed_mutex.ReleaseSync(release_key) };
+                result?;
+                release_result?;
+                Ok(())
+            }
+            None => copy_texture_to_surface(surface, &self.texture),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::{cell::RefCell, ffi::c_void, iter::once, os::windows::ffi::OsStrExt, path::Path};
+
+use windows::{
+    core::{InParam, Interface, PCWSTR},
+    Win32::{
+        Foundation::BOOL,
+        Graphics::DirectWrite::{
+            IDWriteFactory5, IDWriteFontCollection, IDWriteFontFile, IDWriteFontFileLoader,
+            IDWriteInMemoryFontFileLoader,
+        },
+    },
+};
+
+use super::{dwrite_factory, ToWide};
+
+#[derive(Default)]
+struct Fonts {
+    loader: Option<IDWriteInMemoryFontFileLoader>,
+    files: Vec<IDWriteFontFile>,
+    collection: Option<IDWriteFontCollection>,
+}
+
+thread_local! {
+    static FONTS: RefCell<Fonts> = RefCell::new(Fonts::default());
+}
+
+fn factory5() -> crate::Result<IDWriteFactory5> {
+    Ok(dwrite_factory()?.cast()?)
+}
+
+impl Fonts {
+    // Rebuilds the collection with the new file, the file is kept only if it's a valid font
+    fn add(&mut self, factory: &IDWriteFactory5, file: IDWriteFontFile) -> crate::Result<()> {
+        let builder = unsafe { factory.CreateFontSetBuilder2() }?;
+        for file in self.files.iter().chain(once(&file)) {
+            unsafe { builder.AddFontFile(file) }?;
+        }
+        let font_set = unsafe { builder.CreateFontSet() }?;
+        let collection = unsafe { factory.CreateFontCollectionFromFontSet(&font_set) }?;
+        self.collection = Some(collection.cast()?);
+        self.files.push(file);
+        Ok(())
+    }
+}
+
+///
+/// Registers the font file (TTF, OTF or TTC) for the text panels of this thread. The fonts
+/// are found by their family names, the system fonts are used for other families. Register
+/// the fonts on the window thread before creating the panels using them.
+///
+pub fn register_font_file(path: &Path) -> crate::Result<()> {
+    let factory = factory5()?;
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain(once(0)).collect();
+    let file = unsafe { factory.CreateFontFileReference(PCWSTR(path.as_ptr()), None) }?;
+    FONTS.with(|fonts| fonts.borrow_mut().add(&factory, file))
+}
+
+/// Registers the font from memory, e.g. embedded with `include_bytes!`. The data is copied.
+pub fn register_font_data(data: &[u8]) -> crate::Result<()> {
+    let factory = factory5()?;
+    FONTS.with(|fonts| {
+        let mut fonts = fonts.borrow_mut();
+        let loader = match &fonts.loader {
+            Some(loader) => loader.clone(),
+            None => {
+                let loader = unsafe { factory.CreateInMemoryFontFileLoader() }?;
+                let base: IDWriteFontFileLoader = loader.cast()?;
+                unsafe { factory.RegisterFontFileLoader(&base) }?;
+                fonts.loader = Some(loader.clone());
+                loader
+            }
+        };
+        // Without the owner object the loader keeps its own copy of the data
+        let file = unsafe {
+            loader.CreateInMemoryFontFileReference(
+                &dwrite_factory()?,
+                data.as_ptr() as *const c_void,
+                data.len() as u32,
+                InParam::null(),
+            )
+        }?;
+        fonts.add(&factory, file)
+    })
+}
+
+///
+/// Collection of the registered fonts if it has the family, to be passed to DirectWrite
+/// instead of the system collection
+///
+pub fn font_collection(family: &str) -> crate::Result<Option<IDWriteFontCollection>> {
+    let collection = match FONTS.with(|fonts| fonts.borrow().collection.clone()) {
+        Some(collection) => collection,
+        None => return Ok(None),
+    };
+    let mut index = 0;
+    let mut exists = BOOL::default();
+    unsafe { collection.FindFamilyName(family.to_wide().as_pcwstr(), &mut index, &mut exists) }?;
+    Ok(exists.as_bool().then_some(collection))
+}
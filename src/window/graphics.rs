@@ -2,7 +2,7 @@ use windows::{
     core::{InParam, Interface},
     Win32::Graphics::Dxgi::{DXGI_ERROR_DEVICE_REMOVED, DXGI_ERROR_DEVICE_RESET},
     Win32::{
-        Foundation::{HINSTANCE, POINT},
+        Foundation::{HINSTANCE, POINT, RECT},
         Graphics::{
             Direct2D::{
                 D2D1CreateFactory, ID2D1Device, ID2D1DeviceContext, ID2D1Factory1,
@@ -13,7 +13,6 @@ use windows::{
                 D3D11CreateDevice, ID3D11Device, D3D11_CREATE_DEVICE_BGRA_SUPPORT,
                 D3D11_SDK_VERSION,
             },
-            DirectWrite::{DWriteCreateFactory, IDWriteFactory, DWRITE_FACTORY_TYPE_SHARED},
             Dxgi::IDXGIDevice,
         },
         System::WinRT::Composition::{ICompositionDrawingSurfaceInterop, ICompositorInterop},
@@ -21,18 +20,29 @@ use windows::{
     UI::Composition::{CompositionDrawingSurface, CompositionGraphicsDevice, Compositor},
 };
 
+#[cfg(feature = "text")]
+use windows::Win32::Graphics::DirectWrite::{
+    DWriteCreateFactory, IDWriteFactory, DWRITE_FACTORY_TYPE_SHARED,
+};
+
+#[cfg(feature = "text")]
 thread_local! {
     static DWRITE_FACTORY: windows::core::Result<IDWriteFactory> = create_dwrite_factory();
+}
+
+thread_local! {
     static D3D11_DEVICE: windows::core::Result<ID3D11Device> = create_d3d11_device();
     static D2D1_DEVICE: windows::core::Result<ID2D1Device> = create_d2d1_device();
 }
 
+#[cfg(feature = "text")]
 fn create_dwrite_factory() -> windows::core::Result<IDWriteFactory> {
     let dwrite_factory =
         unsafe { DWriteCreateFactory::<IDWriteFactory>(DWRITE_FACTORY_TYPE_SHARED) }?;
     Ok(dwrite_factory.cast()?)
 }
 
+#[cfg(feature = "text")]
 pub fn dwrite_factory() -> windows::core::Result<IDWriteFactory> {
     DWRITE_FACTORY.with(|v| v.clone())
 }
@@ -107,18 +117,36 @@ pub fn check_for_device_removed<T>(
     }
 }
 
-pub fn draw<F: Fn(ID2D1DeviceContext, POINT) -> crate::Result<()>>(
+//
+// Calls `f` between BeginDraw and EndDraw of the surface. EndDraw is called even if `f` fails.
+//
+pub fn draw<F: FnOnce(ID2D1DeviceContext, POINT) -> crate::Result<()>>(
+    surface: &CompositionDrawingSurface,
+    f: F,
+) -> crate::Result<()> {
+    draw_rect(surface, None, f)
+}
+
+//
+// Same as `draw` but updates only the `rect` area of the surface. The `POINT` passed to `f`
+// is the position in the context corresponding to the top left corner of `rect`.
+//
+pub fn draw_rect<F: FnOnce(ID2D1DeviceContext, POINT) -> crate::Result<()>>(
     surface: &CompositionDrawingSurface,
+    rect: Option<RECT>,
     f: F,
 ) -> crate::Result<()> {
     let mut updateoffset = POINT { x: 0, y: 0 };
     let surface_interop: ICompositionDrawingSurfaceInterop = surface.cast()?;
+    let updaterect = rect.as_ref().map(|v| v as *const RECT);
     let context: Option<ID2D1DeviceContext> = check_for_device_removed(unsafe {
-        surface_interop.BeginDraw(None, &mut updateoffset)
+        surface_interop.BeginDraw(updaterect, &mut updateoffset)
     })?;
     if let Some(context) = context {
-        f(context, updateoffset)?;
-        unsafe { surface_interop.EndDraw() }?;
+        let result = f(context, updateoffset);
+        let end_result = unsafe { surface_interop.EndDraw() };
+        result?;
+        end_result?;
     }
     Ok(())
 }
@@ -0,0 +1,199 @@
+use windows::Win32::{
+    Foundation::LPARAM,
+    UI::Input::KeyboardAndMouse::{
+        GetKeyState, VIRTUAL_KEY, VK_0, VK_1, VK_2, VK_3, VK_4, VK_5, VK_6, VK_7, VK_8, VK_9, VK_A,
+        VK_ADD, VK_APPS, VK_B, VK_BACK, VK_BROWSER_BACK, VK_BROWSER_FAVORITES, VK_BROWSER_FORWARD,
+        VK_BROWSER_HOME, VK_BROWSER_REFRESH, VK_BROWSER_SEARCH, VK_BROWSER_STOP, VK_C, VK_CAPITAL,
+        VK_CONTROL, VK_CONVERT, VK_D, VK_DECIMAL, VK_DELETE, VK_DIVIDE, VK_DOWN, VK_E, VK_END,
+        VK_ESCAPE, VK_F, VK_F1, VK_F10, VK_F11, VK_F12, VK_F13, VK_F14, VK_F15, VK_F16, VK_F17,
+        VK_F18, VK_F19, VK_F2, VK_F20, VK_F21, VK_F22, VK_F23, VK_F24, VK_F3, VK_F4, VK_F5, VK_F6,
+        VK_F7, VK_F8, VK_F9, VK_G, VK_H, VK_HOME, VK_I, VK_INSERT, VK_J, VK_K, VK_KANA, VK_KANJI,
+        VK_L, VK_LAUNCH_MAIL, VK_LAUNCH_MEDIA_SELECT, VK_LCONTROL, VK_LEFT, VK_LMENU, VK_LSHIFT,
+        VK_LWIN, VK_M, VK_MEDIA_NEXT_TRACK, VK_MEDIA_PLAY_PAUSE, VK_MEDIA_PREV_TRACK,
+        VK_MEDIA_STOP, VK_MENU, VK_MULTIPLY, VK_N, VK_NEXT, VK_NONCONVERT, VK_NUMLOCK, VK_NUMPAD0,
+        VK_NUMPAD1, VK_NUMPAD2, VK_NUMPAD3, VK_NUMPAD4, VK_NUMPAD5, VK_NUMPAD6, VK_NUMPAD7,
+        VK_NUMPAD8, VK_NUMPAD9, VK_O, VK_OEM_1, VK_OEM_102, VK_OEM_2, VK_OEM_3, VK_OEM_4, VK_OEM_5,
+        VK_OEM_6, VK_OEM_7, VK_OEM_COMMA, VK_OEM_MINUS, VK_OEM_PERIOD, VK_OEM_PLUS, VK_P, VK_PAUSE,
+        VK_PRIOR, VK_Q, VK_R, VK_RCONTROL, VK_RETURN, VK_RIGHT, VK_RMENU, VK_RSHIFT, VK_RWIN, VK_S,
+        VK_SCROLL, VK_SEPARATOR, VK_SHIFT, VK_SLEEP, VK_SNAPSHOT, VK_SPACE, VK_SUBTRACT, VK_T,
+        VK_TAB, VK_U, VK_UP, VK_V, VK_VOLUME_DOWN, VK_VOLUME_MUTE, VK_VOLUME_UP, VK_W, VK_X, VK_Y,
+        VK_Z,
+    },
+};
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+// Scan code of the right shift, the shifts are not told apart by the extended key flag
+const RIGHT_SHIFT_SCANCODE: isize = 0x36;
+
+///
+/// Key of the WM_KEYDOWN / WM_KEYUP message. The `lparam` of the message tells the right
+/// modifiers and the numpad Enter from the main ones. The punctuation keys are named by
+/// their US layout positions, as winit does.
+///
+pub(crate) fn virtual_key_code(vk: VIRTUAL_KEY, lparam: LPARAM) -> Option<VirtualKeyCode> {
+    let extended = lparam.0 & (1 << 24) != 0;
+    let scancode = (lparam.0 >> 16) & 0xff;
+    let key = match vk {
+        VK_0 => VirtualKeyCode::Key0,
+        VK_1 => VirtualKeyCode::Key1,
+        VK_2 => VirtualKeyCode::Key2,
+        VK_3 => VirtualKeyCode::Key3,
+        VK_4 => VirtualKeyCode::Key4,
+        VK_5 => VirtualKeyCode::Key5,
+        VK_6 => VirtualKeyCode::Key6,
+        VK_7 => VirtualKeyCode::Key7,
+        VK_8 => VirtualKeyCode::Key8,
+        VK_9 => VirtualKeyCode::Key9,
+        VK_A => VirtualKeyCode::A,
+        VK_B => VirtualKeyCode::B,
+        VK_C => VirtualKeyCode::C,
+        VK_D => VirtualKeyCode::D,
+        VK_E => VirtualKeyCode::E,
+        VK_F => VirtualKeyCode::F,
+        VK_G => VirtualKeyCode::G,
+        VK_H => VirtualKeyCode::H,
+        VK_I => VirtualKeyCode::I,
+        VK_J => VirtualKeyCode::J,
+        VK_K => VirtualKeyCode::K,
+        VK_L => VirtualKeyCode::L,
+        VK_M => VirtualKeyCode::M,
+        VK_N => VirtualKeyCode::N,
+        VK_O => VirtualKeyCode::O,
+        VK_P => VirtualKeyCode::P,
+        VK_Q => VirtualKeyCode::Q,
+        VK_R => VirtualKeyCode::R,
+        VK_S => VirtualKeyCode::S,
+        VK_T => VirtualKeyCode::T,
+        VK_U => VirtualKeyCode::U,
+        VK_V => VirtualKeyCode::V,
+        VK_W => VirtualKeyCode::W,
+        VK_X => VirtualKeyCode::X,
+        VK_Y => VirtualKeyCode::Y,
+        VK_Z => VirtualKeyCode::Z,
+        VK_F1 => VirtualKeyCode::F1,
+        VK_F2 => VirtualKeyCode::F2,
+        VK_F3 => VirtualKeyCode::F3,
+        VK_F4 => VirtualKeyCode::F4,
+        VK_F5 => VirtualKeyCode::F5,
+        VK_F6 => VirtualKeyCode::F6,
+        VK_F7 => VirtualKeyCode::F7,
+        VK_F8 => VirtualKeyCode::F8,
+        VK_F9 => VirtualKeyCode::F9,
+        VK_F10 => VirtualKeyCode::F10,
+        VK_F11 => VirtualKeyCode::F11,
+        VK_F12 => VirtualKeyCode::F12,
+        VK_F13 => VirtualKeyCode::F13,
+        VK_F14 => VirtualKeyCode::F14,
+        VK_F15 => VirtualKeyCode::F15,
+        VK_F16 => VirtualKeyCode::F16,
+        VK_F17 => VirtualKeyCode::F17,
+        VK_F18 => VirtualKeyCode::F18,
+        VK_F19 => VirtualKeyCode::F19,
+        VK_F20 => VirtualKeyCode::F20,
+        VK_F21 => VirtualKeyCode::F21,
+        VK_F22 => VirtualKeyCode::F22,
+        VK_F23 => VirtualKeyCode::F23,
+        VK_F24 => VirtualKeyCode::F24,
+        VK_NUMPAD0 => VirtualKeyCode::Numpad0,
+        VK_NUMPAD1 => VirtualKeyCode::Numpad1,
+        VK_NUMPAD2 => VirtualKeyCode::Numpad2,
+        VK_NUMPAD3 => VirtualKeyCode::Numpad3,
+        VK_NUMPAD4 => VirtualKeyCode::Numpad4,
+        VK_NUMPAD5 => VirtualKeyCode::Numpad5,
+        VK_NUMPAD6 => VirtualKeyCode::Numpad6,
+        VK_NUMPAD7 => VirtualKeyCode::Numpad7,
+        VK_NUMPAD8 => VirtualKeyCode::Numpad8,
+        VK_NUMPAD9 => VirtualKeyCode::Numpad9,
+        VK_ADD => VirtualKeyCode::NumpadAdd,
+        VK_SUBTRACT => VirtualKeyCode::NumpadSubtract,
+        VK_MULTIPLY => VirtualKeyCode::NumpadMultiply,
+        VK_DIVIDE => VirtualKeyCode::NumpadDivide,
+        VK_DECIMAL => VirtualKeyCode::NumpadDecimal,
+        VK_SEPARATOR => VirtualKeyCode::NumpadComma,
+        VK_NUMLOCK => VirtualKeyCode::Numlock,
+        VK_OEM_1 => VirtualKeyCode::Semicolon,
+        VK_OEM_2 => VirtualKeyCode::Slash,
+        VK_OEM_3 => VirtualKeyCode::Grave,
+        VK_OEM_4 => VirtualKeyCode::LBracket,
+        VK_OEM_5 => VirtualKeyCode::Backslash,
+        VK_OEM_6 => VirtualKeyCode::RBracket,
+        VK_OEM_7 => VirtualKeyCode::Apostrophe,
+        VK_OEM_102 => VirtualKeyCode::OEM102,
+        VK_OEM_PLUS => VirtualKeyCode::Equals,
+        VK_OEM_MINUS => VirtualKeyCode::Minus,
+        VK_OEM_COMMA => VirtualKeyCode::Comma,
+        VK_OEM_PERIOD => VirtualKeyCode::Period,
+        VK_ESCAPE => VirtualKeyCode::Escape,
+        VK_RETURN if extended => VirtualKeyCode::NumpadEnter,
+        VK_RETURN => VirtualKeyCode::Return,
+        VK_SPACE => VirtualKeyCode::Space,
+        VK_TAB => VirtualKeyCode::Tab,
+        VK_BACK => VirtualKeyCode::Back,
+        VK_DELETE => VirtualKeyCode::Delete,
+        VK_INSERT => VirtualKeyCode::Insert,
+        VK_HOME => VirtualKeyCode::Home,
+        VK_END => VirtualKeyCode::End,
+        VK_PRIOR => VirtualKeyCode::PageUp,
+        VK_NEXT => VirtualKeyCode::PageDown,
+        VK_LEFT => VirtualKeyCode::Left,
+        VK_RIGHT => VirtualKeyCode::Right,
+        VK_UP => VirtualKeyCode::Up,
+        VK_DOWN => VirtualKeyCode::Down,
+        VK_SNAPSHOT => VirtualKeyCode::Snapshot,
+        VK_SCROLL => VirtualKeyCode::Scroll,
+        VK_PAUSE => VirtualKeyCode::Pause,
+        VK_CAPITAL => VirtualKeyCode::Capital,
+        VK_APPS => VirtualKeyCode::Apps,
+        VK_SLEEP => VirtualKeyCode::Sleep,
+        VK_KANA => VirtualKeyCode::Kana,
+        VK_KANJI => VirtualKeyCode::Kanji,
+        VK_CONVERT => VirtualKeyCode::Convert,
+        VK_NONCONVERT => VirtualKeyCode::NoConvert,
+        VK_SHIFT if scancode == RIGHT_SHIFT_SCANCODE => VirtualKeyCode::RShift,
+        VK_SHIFT | VK_LSHIFT => VirtualKeyCode::LShift,
+        VK_RSHIFT => VirtualKeyCode::RShift,
+        VK_CONTROL if extended => VirtualKeyCode::RControl,
+        VK_CONTROL | VK_LCONTROL => VirtualKeyCode::LControl,
+        VK_RCONTROL => VirtualKeyCode::RControl,
+        VK_MENU if extended => VirtualKeyCode::RAlt,
+        VK_MENU | VK_LMENU => VirtualKeyCode::LAlt,
+        VK_RMENU => VirtualKeyCode::RAlt,
+        VK_LWIN => VirtualKeyCode::LWin,
+        VK_RWIN => VirtualKeyCode::RWin,
+        VK_BROWSER_BACK => VirtualKeyCode::NavigateBackward,
+        VK_BROWSER_FORWARD => VirtualKeyCode::NavigateForward,
+        VK_BROWSER_REFRESH => VirtualKeyCode::WebRefresh,
+        VK_BROWSER_STOP => VirtualKeyCode::WebStop,
+        VK_BROWSER_SEARCH => VirtualKeyCode::WebSearch,
+        VK_BROWSER_FAVORITES => VirtualKeyCode::WebFavorites,
+        VK_BROWSER_HOME => VirtualKeyCode::WebHome,
+        VK_VOLUME_MUTE => VirtualKeyCode::Mute,
+        VK_VOLUME_DOWN => VirtualKeyCode::VolumeDown,
+        VK_VOLUME_UP => VirtualKeyCode::VolumeUp,
+        VK_MEDIA_NEXT_TRACK => VirtualKeyCode::NextTrack,
+        VK_MEDIA_PREV_TRACK => VirtualKeyCode::PrevTrack,
+        VK_MEDIA_STOP => VirtualKeyCode::MediaStop,
+        VK_MEDIA_PLAY_PAUSE => VirtualKeyCode::PlayPause,
+        VK_LAUNCH_MAIL => VirtualKeyCode::Mail,
+        VK_LAUNCH_MEDIA_SELECT => VirtualKeyCode::MediaSelect,
+        _ => return None,
+    };
+    Some(key)
+}
+
+fn is_key_down(vk: VIRTUAL_KEY) -> bool {
+    unsafe { GetKeyState(vk.0 as i32) < 0 }
+}
+
+/// Current state of modifier keys, valid while processing the keyboard message
+pub(crate) fn modifiers_state() -> ModifiersState {
+    let mut state = ModifiersState::empty();
+    state.set(ModifiersState::SHIFT, is_key_down(VK_SHIFT));
+    state.set(ModifiersState::CTRL, is_key_down(VK_CONTROL));
+    state.set(ModifiersState::ALT, is_key_down(VK_MENU));
+    state.set(
+        ModifiersState::LOGO,
+        is_key_down(VK_LWIN) || is_key_down(VK_RWIN),
+    );
+    state
+}
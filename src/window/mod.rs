@@ -1,24 +1,60 @@
+mod bitmap;
+#[cfg(feature = "text")]
+pub mod clipboard;
+mod d3d_interop;
+#[cfg(feature = "text")]
+mod fonts;
 mod graphics;
 mod interop;
+mod keyboard;
 mod native_window;
+mod popup_window;
+mod present_statistics;
+#[cfg(feature = "dialogs")]
+mod shell_drag_drop;
+#[cfg(feature = "text")]
+mod spell_checker;
+mod system_events;
+#[cfg(feature = "capture")]
+mod visual_capture;
 mod wide_string;
 
 pub mod native {
     pub use super::native_window::run_message_loop;
-    pub use super::native_window::Window;
+    pub use super::native_window::{Window, WindowModeEvent, COMPACT_OVERLAY_SIZE};
+    pub use super::popup_window::{PopupWindowHandle, PopupWindowHost};
+    #[cfg(feature = "dialogs")]
+    pub use super::shell_drag_drop::{DragOutData, ShellDragSource};
+    pub use super::system_events::{ColorScheme, PowerSource, SystemEvent, SystemEvents};
 }
 
+pub use bitmap::Bitmap;
+pub use d3d_interop::{copy_texture_to_surface, SharedTexture};
+#[cfg(feature = "text")]
+pub use fonts::{font_collection, register_font_data, register_font_file};
+#[cfg(feature = "text")]
+pub use graphics::dwrite_factory;
 pub use graphics::{
-    check_for_device_removed, create_composition_graphics_device, d2d1_device, d3d11_device,
-    dwrite_factory, draw
+    check_for_device_removed, create_composition_graphics_device, d2d1_device, d3d11_device, draw,
+    draw_rect,
 };
 pub use interop::create_dispatcher_queue_controller;
 pub use interop::create_dispatcher_queue_controller_for_current_thread;
+pub use native_window::{
+    caret_blink_time, caret_width, double_click_limits, open_url, system_idle_time,
+};
+pub use present_statistics::{present_statistics, PresentStatistics};
+#[cfg(feature = "text")]
+pub use spell_checker::{spell_checker_languages, Misspelling, SpellChecker, SpellingAction};
+#[cfg(feature = "capture")]
+pub use visual_capture::{capture_visual, capture_visual_to_surface};
 pub use wide_string::{ToWide, WideString};
 use windows::System::DispatcherQueueController;
+use windows::Win32::System::Ole::OleInitialize;
+use windows::Win32::System::Ole::OleUninitialize;
 use windows::Win32::System::WinRT::RoInitialize;
 use windows::Win32::System::WinRT::RoUninitialize;
-use windows::Win32::System::WinRT::RO_INIT_MULTITHREADED;
+use windows::Win32::System::WinRT::RO_INIT_SINGLETHREADED;
 
 pub struct WindowThread {
     pub controller: DispatcherQueueController,
@@ -26,13 +62,33 @@ pub struct WindowThread {
 
 impl Drop for WindowThread {
     fn drop(&mut self) {
-        unsafe { RoUninitialize() }
+        unsafe {
+            OleUninitialize();
+            RoUninitialize()
+        }
     }
 }
 
+///
+/// Initializes the thread running windows' message loop. OLE drag-and-drop with other
+/// applications requires single-threaded apartment on this thread.
+///
 pub fn initialize_window_thread() -> crate::Result<WindowThread> {
-    unsafe { RoInitialize(RO_INIT_MULTITHREADED)? }
-    Ok(WindowThread {
-        controller: create_dispatcher_queue_controller_for_current_thread()?,
-    })
+    unsafe {
+        RoInitialize(RO_INIT_SINGLETHREADED)?;
+        if let Err(e) = OleInitialize(std::ptr::null_mut()) {
+            RoUninitialize();
+            return Err(e.into());
+        }
+    }
+    match create_dispatcher_queue_controller_for_current_thread() {
+        Ok(controller) => Ok(WindowThread { controller }),
+        Err(e) => {
+            unsafe {
+                OleUninitialize();
+                RoUninitialize()
+            }
+            Err(e.into())
+        }
+    }
 }
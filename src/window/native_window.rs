@@ -1,32 +1,114 @@
-use std::sync::Once;
+use std::{
+    ffi::c_void,
+    mem::size_of,
+    sync::{Arc, Once},
+    time::Duration,
+};
 
-use futures::channel::mpsc::Sender;
+use async_event_streams::{EventSource, EventStream, EventStreams};
+use futures::task::Spawn;
+#[cfg(feature = "dialogs")]
+use windows::Win32::System::Ole::{IDropTarget, RegisterDragDrop, RevokeDragDrop};
 use windows::{
     core::{self, Interface, PCWSTR},
+    Foundation::Numerics::Vector2,
     Graphics::SizeInt32,
+    System::DispatcherQueue,
     Win32::{
-        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM},
-        System::{LibraryLoader::GetModuleHandleW, WinRT::Composition::ICompositorDesktopInterop},
+        Foundation::{
+            E_ILLEGAL_METHOD_CALL, HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM,
+        },
+        Graphics::Gdi::{
+            ClientToScreen, EnumDisplaySettingsW, GetMonitorInfoW, MonitorFromWindow,
+            ScreenToClient, DEVMODEW, ENUM_CURRENT_SETTINGS, MONITORINFO, MONITORINFOEXW,
+            MONITOR_DEFAULTTONEAREST,
+        },
+        System::{
+            LibraryLoader::GetModuleHandleW, SystemInformation::GetTickCount,
+            WinRT::Composition::ICompositorDesktopInterop,
+        },
+        UI::Input::{
+            GetRawInputData,
+            KeyboardAndMouse::{
+                EnableWindow, GetDoubleClickTime, GetLastInputInfo, ReleaseCapture, SetCapture,
+                LASTINPUTINFO, VIRTUAL_KEY,
+            },
+            Pointer::GetPointerType,
+            RegisterRawInputDevices, HRAWINPUT, RAWINPUT, RAWINPUTDEVICE, RAWINPUTDEVICE_FLAGS,
+            RAWINPUTHEADER, RIDEV_REMOVE, RID_INPUT, RIM_TYPEMOUSE,
+        },
+        UI::Shell::ShellExecuteW,
         UI::WindowsAndMessaging::{
-            AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, DispatchMessageW, GetClientRect,
-            GetMessageW, LoadCursorW, PostQuitMessage, RegisterClassW, ShowWindow,
-            TranslateMessage, CREATESTRUCTW, CW_USEDEFAULT, GWLP_USERDATA, HMENU, IDC_ARROW, MSG,
-            SW_SHOW, WINDOW_LONG_PTR_INDEX, WM_DESTROY, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE,
-            WM_NCCREATE, WM_RBUTTONDOWN, WM_SIZE, WM_SIZING, WM_TIMER, WNDCLASSW,
-            WS_EX_NOREDIRECTIONBITMAP, WS_OVERLAPPEDWINDOW,
+            AdjustWindowRectEx, ClipCursor, CreateWindowExW, DefWindowProcW, DispatchMessageW,
+            GetCaretBlinkTime, GetClientRect, GetForegroundWindow, GetMessageW, GetSystemMetrics,
+            GetWindowRect, LoadCursorW, PostMessageW, PostQuitMessage, RegisterClassW, SetCursor,
+            SetForegroundWindow, SetWindowPos, ShowWindow, SystemParametersInfoW, TranslateMessage,
+            CREATESTRUCTW, CW_USEDEFAULT, GWLP_USERDATA, GWL_STYLE, HCURSOR, HMENU, HTCLIENT,
+            HWND_NOTOPMOST, HWND_TOPMOST, IDC_APPSTARTING, IDC_ARROW, IDC_CROSS, IDC_HAND,
+            IDC_HELP, IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE,
+            IDC_SIZEWE, IDC_WAIT, MSG, POINTER_INPUT_TYPE, PT_TOUCH, SM_CXDOUBLECLK,
+            SM_CYDOUBLECLK, SPI_GETCARETWIDTH, SWP_FRAMECHANGED, SWP_NOACTIVATE, SW_SHOW,
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, WA_INACTIVE, WHEEL_DELTA, WINDOW_LONG_PTR_INDEX,
+            WINDOW_STYLE, WM_ACTIVATE, WM_APP, WM_CHAR, WM_CLOSE, WM_DESTROY, WM_DISPLAYCHANGE,
+            WM_DWMCOLORIZATIONCOLORCHANGED, WM_INPUT, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN,
+            WM_LBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_MOVE, WM_NCCREATE,
+            WM_POINTERCAPTURECHANGED, WM_POINTERDOWN, WM_POINTERUP, WM_POINTERUPDATE,
+            WM_POWERBROADCAST, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETCURSOR, WM_SETTINGCHANGE,
+            WM_SIZE, WM_SIZING, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_TIMER, WNDCLASSW,
+            WS_EX_NOREDIRECTIONBITMAP, WS_OVERLAPPEDWINDOW, WS_POPUP, WS_THICKFRAME, WS_VISIBLE,
         },
     },
     UI::Composition::{Compositor, ContainerVisual, Desktop::DesktopWindowTarget},
 };
 use winit::{
     dpi::PhysicalPosition,
-    event::{DeviceId, ElementState, ModifiersState, MouseButton, WindowEvent},
+    event::{
+        DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+        Touch, TouchPhase, WindowEvent,
+    },
+    window::CursorIcon,
+};
+
+use crate::{
+    gui::{
+        set_refresh_rate, CursorSelector, DebugFrames, DragDrop, EventFilters, EventSequencer,
+        IdleMonitor, PanelEvent, PointerCapture, Theme, WindowEventSender, WindowServices,
+        DEFAULT_REFRESH_RATE,
+    },
+    window::{
+        keyboard::{modifiers_state, virtual_key_code},
+        popup_window::PopupWindowHost,
+        system_events::SystemEvents,
+        wide_string::ToWide,
+    },
 };
 
-use crate::window::wide_string::ToWide;
+#[cfg(feature = "dialogs")]
+use crate::window::shell_drag_drop::{FileDropTarget, ShellDragSource};
 
 static REGISTER_WINDOW_CLASS: Once = Once::new();
+// Posted when the panels select another cursor
+const WM_UPDATE_CURSOR: u32 = WM_APP + 1;
 static WINDOW_CLASS_NAME: &str = "wag.Window";
+// Raw input device of the mouse
+const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
+const HID_USAGE_GENERIC_MOUSE: u16 = 0x02;
+// Raw mouse reports absolute coordinates, e.g. from the remote desktop or the pen tablet
+const MOUSE_MOVE_ABSOLUTE: u16 = 0x01;
+
+/// Default size of the window in compact overlay mode
+pub const COMPACT_OVERLAY_SIZE: SizeInt32 = SizeInt32 {
+    Width: 320,
+    Height: 180,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum WindowModeEvent {
+    /// Window entered (true) or left (false) the compact overlay mode
+    CompactOverlayChanged(bool),
+    /// Window moved to the display with the different refresh rate, in Hz
+    RefreshRateChanged(f32),
+}
 
 pub struct Window {
     handle: HWND,
@@ -34,7 +116,18 @@ pub struct Window {
     target: Option<DesktopWindowTarget>,
     compositor: Compositor,
     root_visual: ContainerVisual,
-    event_channel: Sender<WindowEvent<'static>>,
+    event_channel: WindowEventSender,
+    // Style and position of the window before entering compact overlay mode
+    normal_placement: Option<(WINDOW_STYLE, RECT)>,
+    // Owner disabled while this modal window is open
+    owner: Option<HWND>,
+    window_mode_events: EventStreams<WindowModeEvent>,
+    system_events: Option<SystemEvents>,
+    cursor_locked: bool,
+    // First half of the surrogate pair received by WM_CHAR
+    high_surrogate: Option<u16>,
+    // Refresh rate of the display showing the window
+    refresh_rate: Option<f32>,
 }
 
 impl Window {
@@ -42,7 +135,7 @@ impl Window {
         compositor: Compositor,
         title: &'static str,
         root_visual: ContainerVisual,
-        event_channel: Sender<WindowEvent<'static>>,
+        event_channel: WindowEventSender,
     ) -> Self {
         Self {
             handle: HWND::default(),
@@ -51,6 +144,13 @@ impl Window {
             compositor,
             root_visual,
             event_channel,
+            normal_placement: None,
+            owner: None,
+            window_mode_events: EventStreams::new(),
+            system_events: None,
+            cursor_locked: false,
+            high_surrogate: None,
+            refresh_rate: None,
         }
     }
 
@@ -88,19 +188,40 @@ impl Window {
             (rect.right - rect.left, rect.bottom - rect.top)
         };
 
+        // Modal window is centered over the owner
+        let (x, y, parent) = match self.owner {
+            Some(owner) => {
+                let mut owner_rect = RECT::default();
+                unsafe { GetWindowRect(owner, &mut owner_rect).ok()? };
+                let x = owner_rect.left + (owner_rect.right - owner_rect.left - adjusted_width) / 2;
+                let y = owner_rect.top + (owner_rect.bottom - owner_rect.top - adjusted_height) / 2;
+                // Kept on the owner's monitor, which may be at negative coordinates
+                match monitor_work_area(owner) {
+                    Some(area) => (
+                        x.min(area.right - adjusted_width).max(area.left),
+                        y.min(area.bottom - adjusted_height).max(area.top),
+                        owner,
+                    ),
+                    None => (x, y, owner),
+                }
+            }
+            None => (CW_USEDEFAULT, CW_USEDEFAULT, HWND::default()),
+        };
+
         let title = self.title.to_wide();
         let mut result = Box::new(self); // TODO: use pin?
+        result.system_events = Some(SystemEvents::new()?);
         let window = unsafe {
             CreateWindowExW(
                 window_ex_style,
                 class_name.as_pcwstr(),
                 title.as_pcwstr(),
                 window_style,
-                CW_USEDEFAULT,
-                CW_USEDEFAULT,
+                x,
+                y,
                 adjusted_width,
                 adjusted_height,
-                HWND::default(),
+                parent,
                 HMENU::default(),
                 h_instance,
                 Some(result.as_mut() as *mut _ as _),
@@ -113,22 +234,330 @@ impl Window {
         target.SetRoot(&result.root_visual)?;
         result.target = Some(target);
 
+        result
+            .event_channel
+            .services()
+            .cursors()
+            .set_notify(Arc::new(move || unsafe {
+                PostMessageW(
+                    window,
+                    WM_UPDATE_CURSOR,
+                    WPARAM::default(),
+                    LPARAM::default(),
+                );
+            }));
+
+        // Files dragged from the shell are posted to the panel tree
+        #[cfg(feature = "dialogs")]
+        {
+            let drop_target: IDropTarget =
+                FileDropTarget::new(window, result.event_channel.clone()).into();
+            unsafe { RegisterDragDrop(window, &drop_target)? };
+        }
+
         unsafe { ShowWindow(window, SW_SHOW) };
+        result.update_refresh_rate();
+        if let Some(owner) = result.owner {
+            unsafe { EnableWindow(owner, false) };
+        }
         Ok(result)
     }
 
+    ///
+    /// Opens the window as modal dialog: the window is centered over the `owner`, the owner
+    /// doesn't receive input until the dialog is closed, then it's enabled and activated again.
+    /// Closing the modal window doesn't stop the message loop.
+    ///
+    pub fn open_modal(mut self, owner: HWND) -> crate::Result<Box<Self>> {
+        self.owner = Some(owner);
+        self.open()
+    }
+
+    /// Closes the window the same way as the close button does
+    pub fn close(&self) -> crate::Result<()> {
+        unsafe { PostMessageW(self.handle, WM_CLOSE, WPARAM::default(), LPARAM::default()).ok()? };
+        Ok(())
+    }
+
     pub fn size(&self) -> crate::Result<SizeInt32> {
         Ok(get_window_size(self.handle)?)
     }
 
+    /// Refresh rate in Hz of the display showing the window
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate.unwrap_or(DEFAULT_REFRESH_RATE)
+    }
+
+    // Follows the refresh rate of the window's display, the frame timers use the rate of the
+    // active window
+    fn update_refresh_rate(&mut self) {
+        let rate = monitor_refresh_rate(self.handle);
+        if rate.is_some() && rate != self.refresh_rate {
+            self.refresh_rate = rate;
+            self.window_mode_events.post_event(
+                WindowModeEvent::RefreshRateChanged(self.refresh_rate()),
+                None,
+            );
+        }
+        if unsafe { GetForegroundWindow() } == self.handle {
+            set_refresh_rate(self.refresh_rate());
+        }
+    }
+
     pub fn handle(&self) -> HWND {
         self.handle
     }
 
+    pub fn is_compact_overlay(&self) -> bool {
+        self.normal_placement.is_some()
+    }
+
+    ///
+    /// Switches the window to small borderless always-on-top mode (picture-in-picture) or back.
+    /// The panel tree is kept as is, the root panel just receives `Resized` event. The
+    /// `WindowModeEvent::CompactOverlayChanged` allows the application to adjust the content.
+    ///
+    pub fn set_compact_overlay(
+        &mut self,
+        enabled: bool,
+        size: Option<SizeInt32>,
+    ) -> crate::Result<()> {
+        if enabled == self.is_compact_overlay() {
+            return Ok(());
+        }
+        if enabled {
+            let style = WINDOW_STYLE(unsafe { GetWindowLong(self.handle, GWL_STYLE) } as u32);
+            let mut rect = RECT::default();
+            unsafe { GetWindowRect(self.handle, &mut rect).ok()? };
+            self.normal_placement = Some((style, rect));
+            let size = size.unwrap_or(COMPACT_OVERLAY_SIZE);
+            unsafe {
+                SetWindowLong(
+                    self.handle,
+                    GWL_STYLE,
+                    (WS_POPUP | WS_THICKFRAME | WS_VISIBLE).0 as isize,
+                );
+                SetWindowPos(
+                    self.handle,
+                    HWND_TOPMOST,
+                    rect.left,
+                    rect.top,
+                    size.Width,
+                    size.Height,
+                    SWP_FRAMECHANGED | SWP_NOACTIVATE,
+                )
+                .ok()?;
+            }
+        } else if let Some((style, rect)) = self.normal_placement.take() {
+            unsafe {
+                SetWindowLong(self.handle, GWL_STYLE, style.0 as isize);
+                SetWindowPos(
+                    self.handle,
+                    HWND_NOTOPMOST,
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_FRAMECHANGED | SWP_NOACTIVATE,
+                )
+                .ok()?;
+            }
+        }
+        self.window_mode_events
+            .post_event(WindowModeEvent::CompactOverlayChanged(enabled), None);
+        Ok(())
+    }
+
+    ///
+    /// Factory of popup windows owned by this window. Must be called on the window's thread
+    /// after the window is opened.
+    ///
+    pub fn popup_host(&self) -> crate::Result<PopupWindowHost> {
+        Ok(PopupWindowHost::new(
+            self.compositor.clone(),
+            self.handle,
+            self.event_channel.clone(),
+            DispatcherQueue::GetForCurrentThread()?,
+        ))
+    }
+
+    /// Sequencer of events posted by this window to the panel tree
+    pub fn sequencer(&self) -> &EventSequencer {
+        self.event_channel.sequencer()
+    }
+
+    /// Pointer capture routing the mouse events of this window to one panel
+    pub fn pointer_capture(&self) -> &PointerCapture {
+        self.event_channel.services().pointer_capture()
+    }
+
+    /// Drag-and-drop between the panels of this window
+    pub fn drag_drop(&self) -> &DragDrop {
+        self.event_channel.services().drag_drop()
+    }
+
+    /// Cursors declared by the panels of this window
+    pub fn cursors(&self) -> &CursorSelector {
+        self.event_channel.services().cursors()
+    }
+
+    /// Filters receiving the events of this window before the panel tree
+    pub fn event_filters(&self) -> &EventFilters {
+        self.event_channel.services().filters()
+    }
+
+    /// Developer mode framing the visuals of this window
+    pub fn debug_frames(&self) -> &DebugFrames {
+        self.event_channel.services().debug_frames()
+    }
+
+    /// Idle detection by the input in this window
+    pub fn idle_monitor(&self) -> &IdleMonitor {
+        self.event_channel.services().idle_monitor()
+    }
+
+    /// Singletons of this window found by type, released when the window is destroyed
+    pub fn services(&self) -> &WindowServices {
+        self.event_channel.services()
+    }
+
+    ///
+    /// Sends `PanelEvent::ThemeChanged` through the panel tree now and on each change of
+    /// the theme, so the skins following the theme restyle themselves. The theme is
+    /// registered in the window's services as well.
+    ///
+    pub fn broadcast_theme<S: Spawn + ?Sized>(
+        &self,
+        theme: &Theme,
+        spawner: &S,
+    ) -> crate::Result<()> {
+        self.services().register(theme.clone());
+        theme.broadcast(self.event_channel.clone(), spawner)
+    }
+
+    ///
+    /// Theme with the user's accent color and dark or light apps' mode, kept up to date with
+    /// the system settings and broadcast through the panel tree, so the skins following
+    /// the theme switch with Windows. Must be called after the window is opened.
+    ///
+    pub fn follow_system_theme<S: Spawn + ?Sized>(&self, spawner: &S) -> crate::Result<Theme> {
+        let system_events = match &self.system_events {
+            Some(system_events) => system_events,
+            None => return Err(core::Error::from(E_ILLEGAL_METHOD_CALL).into()),
+        };
+        let theme = Theme::from_system(system_events);
+        theme.follow(system_events, spawner)?;
+        self.broadcast_theme(&theme, spawner)?;
+        Ok(theme)
+    }
+
+    pub fn is_cursor_locked(&self) -> bool {
+        self.cursor_locked
+    }
+
+    ///
+    /// Hides the cursor over the window and keeps it inside the window, e.g. for the first-person
+    /// camera controls with `set_raw_mouse_input`. The system releases the cursor when the
+    /// window is deactivated, it's locked again on activation. Must be called on the window's
+    /// thread after the window is opened.
+    ///
+    pub fn set_cursor_locked(&mut self, locked: bool) -> crate::Result<()> {
+        self.cursor_locked = locked;
+        self.clip_cursor()?;
+        self.set_cursor();
+        Ok(())
+    }
+
+    ///
+    /// Enables `PanelEvent::MouseMotion` with the relative mouse movement from the raw input.
+    /// Must be called on the window's thread after the window is opened.
+    ///
+    pub fn set_raw_mouse_input(&mut self, enabled: bool) -> crate::Result<()> {
+        let (flags, target) = if enabled {
+            (RAWINPUTDEVICE_FLAGS::default(), self.handle)
+        } else {
+            (RIDEV_REMOVE, HWND::default())
+        };
+        let device = RAWINPUTDEVICE {
+            usUsagePage: HID_USAGE_PAGE_GENERIC,
+            usUsage: HID_USAGE_GENERIC_MOUSE,
+            dwFlags: flags,
+            hwndTarget: target,
+        };
+        unsafe { RegisterRawInputDevices(&[device], size_of::<RAWINPUTDEVICE>() as u32).ok()? };
+        Ok(())
+    }
+
+    // Limits the cursor to the client area of the window when it's locked
+    fn clip_cursor(&self) -> crate::Result<()> {
+        if !self.cursor_locked {
+            unsafe { ClipCursor(None).ok()? };
+            return Ok(());
+        }
+        let mut rect = RECT::default();
+        unsafe { GetClientRect(self.handle, &mut rect).ok()? };
+        let mut top_left = POINT {
+            x: rect.left,
+            y: rect.top,
+        };
+        let mut bottom_right = POINT {
+            x: rect.right,
+            y: rect.bottom,
+        };
+        unsafe {
+            ClientToScreen(self.handle, &mut top_left).ok()?;
+            ClientToScreen(self.handle, &mut bottom_right).ok()?;
+        }
+        let rect = RECT {
+            left: top_left.x,
+            top: top_left.y,
+            right: bottom_right.x,
+            bottom: bottom_right.y,
+        };
+        unsafe { ClipCursor(Some(&rect as *const _)).ok()? };
+        Ok(())
+    }
+
+    /// Changes of the system settings, available after the window is opened
+    pub fn system_events(&self) -> Option<&SystemEvents> {
+        self.system_events.as_ref()
+    }
+
+    ///
+    /// Source of drags of text and files from this window to other applications. Must be
+    /// called on the window's thread after the window is opened.
+    ///
+    #[cfg(feature = "dialogs")]
+    pub fn shell_drag_source(&self) -> crate::Result<ShellDragSource> {
+        Ok(ShellDragSource::new(
+            DispatcherQueue::GetForCurrentThread()?,
+            self.event_channel.clone(),
+        ))
+    }
+
     fn message_handler(&mut self, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
         match message {
+            WM_CLOSE => {
+                // Owner have to be enabled before the modal window is destroyed, otherwise
+                // the system activates some other window
+                if let Some(owner) = self.owner {
+                    unsafe { EnableWindow(owner, true) };
+                }
+            }
             WM_DESTROY => {
-                unsafe { PostQuitMessage(0) };
+                #[cfg(feature = "dialogs")]
+                unsafe {
+                    let _ = RevokeDragDrop(self.handle);
+                }
+                self.event_channel.services().clear();
+                match self.owner.take() {
+                    // The window may be destroyed without WM_CLOSE, the owner stays disabled then
+                    Some(owner) => unsafe {
+                        EnableWindow(owner, true);
+                        SetForegroundWindow(owner);
+                    },
+                    None => unsafe { PostQuitMessage(0) },
+                }
                 return LRESULT::default();
             }
             WM_MOUSEMOVE => {
@@ -143,12 +572,15 @@ impl Window {
                 });
             }
             WM_SIZE | WM_SIZING => {
+                let _ = self.clip_cursor();
                 let size = self.size().unwrap();
                 let _ = self
                     .event_channel
                     .try_send(WindowEvent::Resized((size.Width, size.Height).into()));
             }
             WM_LBUTTONDOWN => {
+                // Capture the mouse to receive the release and moves outside of the window
+                unsafe { SetCapture(self.handle) };
                 let _ = self.event_channel.try_send(WindowEvent::MouseInput {
                     device_id: unsafe { DeviceId::dummy() },
                     state: ElementState::Pressed,
@@ -157,6 +589,7 @@ impl Window {
                 });
             }
             WM_LBUTTONUP => {
+                unsafe { ReleaseCapture() };
                 let _ = self.event_channel.try_send(WindowEvent::MouseInput {
                     device_id: unsafe { DeviceId::dummy() },
                     state: ElementState::Released,
@@ -164,8 +597,131 @@ impl Window {
                     modifiers: ModifiersState::default(),
                 });
             }
-            WM_RBUTTONDOWN => {
-                // self.game.on_pointer_pressed(true, false).unwrap();
+            WM_KEYDOWN | WM_KEYUP | WM_SYSKEYDOWN | WM_SYSKEYUP => {
+                let state = if message == WM_KEYDOWN || message == WM_SYSKEYDOWN {
+                    ElementState::Pressed
+                } else {
+                    ElementState::Released
+                };
+                #[allow(deprecated)]
+                let input = KeyboardInput {
+                    scancode: ((lparam.0 >> 16) & 0xff) as u32,
+                    state,
+                    virtual_keycode: virtual_key_code(VIRTUAL_KEY(wparam.0 as u16), lparam),
+                    modifiers: modifiers_state(),
+                };
+                let _ = self.event_channel.try_send(WindowEvent::KeyboardInput {
+                    device_id: unsafe { DeviceId::dummy() },
+                    input,
+                    is_synthetic: false,
+                });
+            }
+            WM_CHAR => {
+                let unit = wparam.0 as u16;
+                let units = match self.high_surrogate.take() {
+                    Some(high) => vec![high, unit],
+                    None if (0xD800..0xDC00).contains(&unit) => {
+                        self.high_surrogate = Some(unit);
+                        vec![]
+                    }
+                    None => vec![unit],
+                };
+                for c in char::decode_utf16(units).filter_map(|v| v.ok()) {
+                    let _ = self
+                        .event_channel
+                        .try_send(WindowEvent::ReceivedCharacter(c));
+                }
+            }
+            WM_RBUTTONDOWN | WM_RBUTTONUP => {
+                let state = if message == WM_RBUTTONDOWN {
+                    ElementState::Pressed
+                } else {
+                    ElementState::Released
+                };
+                let _ = self.event_channel.try_send(WindowEvent::MouseInput {
+                    device_id: unsafe { DeviceId::dummy() },
+                    state,
+                    button: MouseButton::Right,
+                    modifiers: ModifiersState::default(),
+                });
+            }
+            WM_SETCURSOR if (lparam.0 & 0xffff) as u32 == HTCLIENT => {
+                self.set_cursor();
+                return LRESULT(1);
+            }
+            WM_MOVE => {
+                let _ = self.clip_cursor();
+                self.update_refresh_rate();
+            }
+            WM_ACTIVATE if (wparam.0 & 0xffff) as u32 != WA_INACTIVE => {
+                let _ = self.clip_cursor();
+                set_refresh_rate(self.refresh_rate());
+            }
+            WM_INPUT => {
+                if let Some(delta) = raw_mouse_delta(lparam) {
+                    let _ = self
+                        .event_channel
+                        .try_send_panel_event(PanelEvent::MouseMotion(delta));
+                }
+            }
+            WM_UPDATE_CURSOR => {
+                self.set_cursor();
+                return LRESULT::default();
+            }
+            WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+                let _ = self
+                    .event_channel
+                    .try_send(mouse_wheel_event(message, wparam));
+            }
+            WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP | WM_POINTERCAPTURECHANGED => {
+                // Mouse and pen pointers come as mouse messages, the touches are reported
+                // in addition to the mouse messages promoted from them by the system
+                let pointer_id = (wparam.0 & 0xffff) as u32;
+                let mut pointer_type = POINTER_INPUT_TYPE::default();
+                let is_touch = unsafe { GetPointerType(pointer_id, &mut pointer_type) }.as_bool()
+                    && pointer_type == PT_TOUCH;
+                if is_touch {
+                    let phase = match message {
+                        WM_POINTERDOWN => TouchPhase::Started,
+                        WM_POINTERUPDATE => TouchPhase::Moved,
+                        WM_POINTERUP => TouchPhase::Ended,
+                        _ => TouchPhase::Cancelled,
+                    };
+                    // Pointer messages carry the screen coordinates
+                    let (x, y) = get_mouse_position(lparam);
+                    let mut point = POINT {
+                        x: x as i32,
+                        y: y as i32,
+                    };
+                    unsafe { ScreenToClient(self.handle, &mut point) };
+                    let location = PhysicalPosition {
+                        x: point.x as f64,
+                        y: point.y as f64,
+                    };
+                    let _ = self.event_channel.try_send(WindowEvent::CursorMoved {
+                        device_id: unsafe { DeviceId::dummy() },
+                        position: location,
+                        modifiers: ModifiersState::default(),
+                    });
+                    let _ = self.event_channel.try_send(WindowEvent::Touch(Touch {
+                        device_id: unsafe { DeviceId::dummy() },
+                        phase,
+                        location,
+                        force: None,
+                        id: pointer_id as u64,
+                    }));
+                }
+            }
+            WM_SETTINGCHANGE
+            | WM_DWMCOLORIZATIONCOLORCHANGED
+            | WM_POWERBROADCAST
+            | WM_DISPLAYCHANGE => {
+                if let Some(system_events) = &self.system_events {
+                    let _ = system_events.refresh(message == WM_DISPLAYCHANGE);
+                }
+                if message == WM_DISPLAYCHANGE {
+                    self.update_refresh_rate();
+                }
             }
             WM_TIMER => {
                 // dbg!("timer");
@@ -176,6 +732,17 @@ impl Window {
         unsafe { DefWindowProcW(self.handle, message, wparam, lparam) }
     }
 
+    fn set_cursor(&self) {
+        if self.cursor_locked {
+            unsafe { SetCursor(HCURSOR::default()) };
+            return;
+        }
+        let icon = self.event_channel.services().cursors().current();
+        if let Ok(cursor) = unsafe { LoadCursorW(HINSTANCE::default(), cursor_resource(icon)) } {
+            unsafe { SetCursor(cursor) };
+        }
+    }
+
     unsafe extern "system" fn wnd_proc(
         window: HWND,
         message: u32,
@@ -204,6 +771,12 @@ impl Window {
     }
 }
 
+impl EventSource<WindowModeEvent> for Window {
+    fn event_stream(&self) -> EventStream<WindowModeEvent> {
+        self.window_mode_events.create_event_stream()
+    }
+}
+
 pub fn run_message_loop() {
     let mut message = MSG::default();
     unsafe {
@@ -216,6 +789,58 @@ pub fn run_message_loop() {
     }
 }
 
+// Event of the WM_MOUSEWHEEL or WM_MOUSEHWHEEL message
+pub(super) fn mouse_wheel_event(message: u32, wparam: WPARAM) -> WindowEvent<'static> {
+    let lines = ((wparam.0 >> 16) & 0xffff) as i16 as f32 / WHEEL_DELTA as f32;
+    let delta = if message == WM_MOUSEWHEEL {
+        MouseScrollDelta::LineDelta(0., lines)
+    } else {
+        MouseScrollDelta::LineDelta(lines, 0.)
+    };
+    #[allow(deprecated)]
+    WindowEvent::MouseWheel {
+        device_id: unsafe { DeviceId::dummy() },
+        delta,
+        phase: TouchPhase::Moved,
+        modifiers: modifiers_state(),
+    }
+}
+
+// Area of the window's monitor not covered by the taskbar, in screen coordinates
+fn monitor_work_area(window_handle: HWND) -> Option<RECT> {
+    let monitor = unsafe { MonitorFromWindow(window_handle, MONITOR_DEFAULTTONEAREST) };
+    let mut info = MONITORINFO {
+        cbSize: size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe { GetMonitorInfoW(monitor, &mut info) }
+        .as_bool()
+        .then_some(info.rcWork)
+}
+
+// Refresh rate of the display mode of the window's monitor, `None` if it's unknown
+fn monitor_refresh_rate(window_handle: HWND) -> Option<f32> {
+    let monitor = unsafe { MonitorFromWindow(window_handle, MONITOR_DEFAULTTONEAREST) };
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
+    if !unsafe { GetMonitorInfoW(monitor, &mut info as *mut _ as *mut MONITORINFO) }.as_bool() {
+        return None;
+    }
+    let mut mode = DEVMODEW {
+        dmSize: size_of::<DEVMODEW>() as u16,
+        ..Default::default()
+    };
+    let found = unsafe {
+        EnumDisplaySettingsW(
+            PCWSTR(info.szDevice.as_ptr()),
+            ENUM_CURRENT_SETTINGS,
+            &mut mode,
+        )
+    };
+    // 0 and 1 stand for the hardware's default rate
+    (found.as_bool() && mode.dmDisplayFrequency > 1).then_some(mode.dmDisplayFrequency as f32)
+}
+
 fn get_window_size(window_handle: HWND) -> core::Result<SizeInt32> {
     unsafe {
         let mut rect = RECT::default();
@@ -229,15 +854,152 @@ fn get_window_size(window_handle: HWND) -> core::Result<SizeInt32> {
     }
 }
 
-fn get_mouse_position(lparam: LPARAM) -> (isize, isize) {
-    let x = lparam.0 & 0xffff;
-    let y = (lparam.0 >> 16) & 0xffff;
+///
+/// Maximal time between the presses and size of the rectangle around the first press
+/// in which the presses are counted as double click, as configured in the system
+///
+pub fn double_click_limits() -> (Duration, Vector2) {
+    unsafe {
+        (
+            Duration::from_millis(GetDoubleClickTime() as u64),
+            Vector2 {
+                X: GetSystemMetrics(SM_CXDOUBLECLK) as f32,
+                Y: GetSystemMetrics(SM_CYDOUBLECLK) as f32,
+            },
+        )
+    }
+}
+
+///
+/// Time the text caret stays visible or hidden while blinking, as configured in the system.
+/// `None` if the user turned blinking off.
+///
+pub fn caret_blink_time() -> Option<Duration> {
+    match unsafe { GetCaretBlinkTime() } {
+        // INFINITE means no blinking, 0 is the error
+        0 | u32::MAX => None,
+        ms => Some(Duration::from_millis(ms as u64)),
+    }
+}
+
+/// Width of the text caret in pixels, the accessibility setting of the system
+pub fn caret_width() -> u32 {
+    let mut width: u32 = 1;
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCARETWIDTH,
+            0,
+            Some(&mut width as *mut _ as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS::default(),
+        )
+    };
+    if ok.as_bool() {
+        width.max(1)
+    } else {
+        1
+    }
+}
+
+/// Time since the last input in the system: mouse, keyboard or touch in any application
+pub fn system_idle_time() -> Duration {
+    let mut info = LASTINPUTINFO {
+        cbSize: size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    unsafe {
+        if !GetLastInputInfo(&mut info).as_bool() {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(GetTickCount().wrapping_sub(info.dwTime) as u64)
+    }
+}
+
+/// Opens the URL or the file with the application registered for it, e.g. the browser
+pub fn open_url(url: &str) -> crate::Result<()> {
+    let result = unsafe {
+        ShellExecuteW(
+            HWND(0),
+            "open".to_wide().as_pcwstr(),
+            url.to_wide().as_pcwstr(),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOW,
+        )
+    };
+    // Values up to 32 are error codes
+    if result.0 <= 32 {
+        return Err(core::Error::from_win32().into());
+    }
+    Ok(())
+}
+
+// System cursor closest to the winit cursor icon
+fn cursor_resource(icon: CursorIcon) -> PCWSTR {
+    match icon {
+        CursorIcon::Hand => IDC_HAND,
+        CursorIcon::Text | CursorIcon::VerticalText => IDC_IBEAM,
+        CursorIcon::Crosshair | CursorIcon::Cell => IDC_CROSS,
+        CursorIcon::Move | CursorIcon::AllScroll | CursorIcon::Grab | CursorIcon::Grabbing => {
+            IDC_SIZEALL
+        }
+        CursorIcon::Wait => IDC_WAIT,
+        CursorIcon::Progress => IDC_APPSTARTING,
+        CursorIcon::Help => IDC_HELP,
+        CursorIcon::NotAllowed | CursorIcon::NoDrop => IDC_NO,
+        CursorIcon::EResize
+        | CursorIcon::WResize
+        | CursorIcon::EwResize
+        | CursorIcon::ColResize => IDC_SIZEWE,
+        CursorIcon::NResize
+        | CursorIcon::SResize
+        | CursorIcon::NsResize
+        | CursorIcon::RowResize => IDC_SIZENS,
+        CursorIcon::NeResize | CursorIcon::SwResize | CursorIcon::NeswResize => IDC_SIZENESW,
+        CursorIcon::NwResize | CursorIcon::SeResize | CursorIcon::NwseResize => IDC_SIZENWSE,
+        _ => IDC_ARROW,
+    }
+}
+
+// Relative movement of the mouse from the WM_INPUT message
+fn raw_mouse_delta(lparam: LPARAM) -> Option<Vector2> {
+    let mut input = RAWINPUT::default();
+    let mut size = size_of::<RAWINPUT>() as u32;
+    let read = unsafe {
+        GetRawInputData(
+            HRAWINPUT(lparam.0),
+            RID_INPUT,
+            Some(&mut input as *mut _ as *mut c_void),
+            &mut size,
+            size_of::<RAWINPUTHEADER>() as u32,
+        )
+    };
+    if read == u32::MAX || input.header.dwType != RIM_TYPEMOUSE.0 {
+        return None;
+    }
+    let mouse = unsafe { input.data.mouse };
+    if mouse.usFlags & MOUSE_MOVE_ABSOLUTE != 0 {
+        return None;
+    }
+    Some(Vector2 {
+        X: mouse.lLastX as f32,
+        Y: mouse.lLastY as f32,
+    })
+}
+
+pub(super) fn get_mouse_position(lparam: LPARAM) -> (isize, isize) {
+    // Coordinates are signed: they are negative when the captured mouse is left or above the window
+    let x = (lparam.0 & 0xffff) as i16 as isize;
+    let y = ((lparam.0 >> 16) & 0xffff) as i16 as isize;
     (x, y)
 }
 
 #[allow(non_snake_case)]
 #[cfg(target_pointer_width = "32")]
-unsafe fn SetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX, value: isize) -> isize {
+pub(super) unsafe fn SetWindowLong(
+    window: HWND,
+    index: WINDOW_LONG_PTR_INDEX,
+    value: isize,
+) -> isize {
     use windows::Win32::UI::WindowsAndMessaging::SetWindowLongW;
 
     SetWindowLongW(window, index, value as _) as _
@@ -245,7 +1007,11 @@ unsafe fn SetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX, value: isize
 
 #[allow(non_snake_case)]
 #[cfg(target_pointer_width = "64")]
-unsafe fn SetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX, value: isize) -> isize {
+pub(super) unsafe fn SetWindowLong(
+    window: HWND,
+    index: WINDOW_LONG_PTR_INDEX,
+    value: isize,
+) -> isize {
     use windows::Win32::UI::WindowsAndMessaging::SetWindowLongPtrW;
 
     SetWindowLongPtrW(window, index, value)
@@ -253,7 +1019,7 @@ unsafe fn SetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX, value: isize
 
 #[allow(non_snake_case)]
 #[cfg(target_pointer_width = "32")]
-unsafe fn GetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX) -> isize {
+pub(super) unsafe fn GetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX) -> isize {
     use windows::Win32::UI::WindowsAndMessaging::SetWindowLongW;
 
     GetWindowLongW(window, index) as _
@@ -261,7 +1027,7 @@ unsafe fn GetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX) -> isize {
 
 #[allow(non_snake_case)]
 #[cfg(target_pointer_width = "64")]
-unsafe fn GetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX) -> isize {
+pub(super) unsafe fn GetWindowLong(window: HWND, index: WINDOW_LONG_PTR_INDEX) -> isize {
     use windows::Win32::UI::WindowsAndMessaging::GetWindowLongPtrW;
 
     GetWindowLongPtrW(window, index)
@@ -0,0 +1,381 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Once,
+};
+
+use futures::channel::oneshot;
+use windows::{
+    core::{self, Interface, PCWSTR},
+    Foundation::Numerics::Vector2,
+    System::{DispatcherQueue, DispatcherQueueHandler},
+    Win32::{
+        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, POINT, WPARAM},
+        Graphics::Gdi::{
+            ClientToScreen, GetMonitorInfoW, MonitorFromWindow, ScreenToClient, MONITORINFO,
+            MONITOR_DEFAULTTONEAREST,
+        },
+        System::{LibraryLoader::GetModuleHandleW, WinRT::Composition::ICompositorDesktopInterop},
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DefWindowProcW, DestroyWindow, LoadCursorW, RegisterClassW,
+            SetWindowPos, ShowWindow, CREATESTRUCTW, GWLP_USERDATA, HMENU, IDC_ARROW,
+            MA_NOACTIVATE, SWP_NOACTIVATE, SWP_NOZORDER, SW_SHOWNOACTIVATE, WM_LBUTTONDOWN,
+            WM_LBUTTONUP, WM_MOUSEACTIVATE, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL,
+            WM_NCCREATE, WM_NCDESTROY, WM_RBUTTONDOWN, WM_RBUTTONUP, WNDCLASSW, WS_EX_NOACTIVATE,
+            WS_EX_NOREDIRECTIONBITMAP, WS_EX_TOOLWINDOW, WS_POPUP,
+        },
+    },
+    UI::Composition::{Compositor, ContainerVisual, Desktop::DesktopWindowTarget},
+};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{DeviceId, ElementState, ModifiersState, MouseButton, WindowEvent},
+};
+
+use crate::{
+    geometry::{Point, Rect, Size},
+    gui::WindowEventSender,
+    window::wide_string::ToWide,
+};
+
+use super::native_window::{get_mouse_position, mouse_wheel_event, GetWindowLong, SetWindowLong};
+
+static REGISTER_POPUP_WINDOW_CLASS: Once = Once::new();
+static POPUP_WINDOW_CLASS_NAME: &str = "wag.PopupWindow";
+
+///
+/// Runs the function on the thread owning the dispatcher queue and returns its result.
+/// Windows can be created and destroyed only on the thread running their message loop.
+///
+pub(super) async fn run_on_window_thread<T, F>(queue: &DispatcherQueue, f: F) -> crate::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> crate::Result<T> + Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let mut task = Some((f, tx));
+    let handler = DispatcherQueueHandler::new(move || {
+        if let Some((f, tx)) = task.take() {
+            let _ = tx.send(f());
+        }
+        Ok(())
+    });
+    if !queue.TryEnqueue(&handler)? {
+        return Err(crate::Error::WindowThreadStopped);
+    }
+    rx.await.map_err(|_| crate::Error::WindowThreadStopped)?
+}
+
+// Translates origin of the rectangle in owner's client coordinates to screen coordinates
+fn client_to_screen(owner: HWND, rect: Rect) -> POINT {
+    let mut point = POINT {
+        x: rect.origin.x as i32,
+        y: rect.origin.y as i32,
+    };
+    unsafe { ClientToScreen(owner, &mut point) };
+    point
+}
+
+///
+/// Borderless window without activation used to show popups which don't fit into the owner
+/// window. The popup is owned by the owner window, so it stays above it without being topmost
+/// over the other applications and is hidden with it when it's minimized. Mouse input is forwarded to the owner's event channel in the owner's client
+/// coordinates, so for the panel tree the popup looks like a part of the owner window.
+///
+struct PopupWindow {
+    handle: HWND,
+    owner: HWND,
+    event_channel: WindowEventSender,
+    target: Option<DesktopWindowTarget>,
+}
+
+impl PopupWindow {
+    fn create(
+        compositor: &Compositor,
+        owner: HWND,
+        event_channel: WindowEventSender,
+        rect: Rect,
+    ) -> crate::Result<(HWND, ContainerVisual)> {
+        let class_name = POPUP_WINDOW_CLASS_NAME.to_wide();
+        let h_instance = unsafe { GetModuleHandleW(PCWSTR::null())? };
+        let h_cursor = unsafe { LoadCursorW(HINSTANCE::default(), IDC_ARROW)? };
+        REGISTER_POPUP_WINDOW_CLASS.call_once(|| {
+            let class = WNDCLASSW {
+                hCursor: h_cursor,
+                hInstance: h_instance,
+                lpszClassName: class_name.as_pcwstr(),
+                lpfnWndProc: Some(Self::wnd_proc),
+                ..Default::default()
+            };
+            assert_ne!(unsafe { RegisterClassW(&class) }, 0);
+        });
+
+        let origin = client_to_screen(owner, rect);
+        let this = Box::into_raw(Box::new(PopupWindow {
+            handle: HWND::default(),
+            owner,
+            event_channel,
+            target: None,
+        }));
+        let handle = unsafe {
+            CreateWindowExW(
+                WS_EX_NOREDIRECTIONBITMAP | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                class_name.as_pcwstr(),
+                PCWSTR::null(),
+                WS_POPUP,
+                origin.x,
+                origin.y,
+                rect.size.width as i32,
+                rect.size.height as i32,
+                owner,
+                HMENU::default(),
+                h_instance,
+                Some(this as _),
+            )
+        };
+        if handle.0 == 0 {
+            drop(unsafe { Box::from_raw(this) });
+            return Err(core::Error::from_win32().into());
+        }
+
+        // The structure is owned by the window now and freed on WM_NCDESTROY
+        let root_visual = match Self::attach_visual(compositor, handle, rect) {
+            Ok((target, root_visual)) => {
+                unsafe { (*this).target = Some(target) };
+                root_visual
+            }
+            Err(e) => {
+                unsafe { DestroyWindow(handle) };
+                return Err(e);
+            }
+        };
+        unsafe { ShowWindow(handle, SW_SHOWNOACTIVATE) };
+        Ok((handle, root_visual))
+    }
+
+    // Composition target of the window with the root visual of the popup's size
+    fn attach_visual(
+        compositor: &Compositor,
+        handle: HWND,
+        rect: Rect,
+    ) -> crate::Result<(DesktopWindowTarget, ContainerVisual)> {
+        let root_visual = compositor.CreateContainerVisual()?;
+        root_visual.SetSize(rect.size.into())?;
+        let compositor_desktop: ICompositorDesktopInterop = compositor.cast()?;
+        let target = unsafe { compositor_desktop.CreateDesktopWindowTarget(handle, true)? };
+        target.SetRoot(&root_visual)?;
+        Ok((target, root_visual))
+    }
+
+    fn to_owner(&self, lparam: LPARAM) -> PhysicalPosition<f64> {
+        let (x, y) = get_mouse_position(lparam);
+        let mut point = POINT {
+            x: x as i32,
+            y: y as i32,
+        };
+        unsafe {
+            ClientToScreen(self.handle, &mut point);
+            ScreenToClient(self.owner, &mut point);
+        }
+        PhysicalPosition {
+            x: point.x as f64,
+            y: point.y as f64,
+        }
+    }
+
+    fn send_mouse_input(&mut self, state: ElementState, button: MouseButton) {
+        let _ = self.event_channel.try_send(WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button,
+            modifiers: ModifiersState::default(),
+        });
+    }
+
+    fn message_handler(&mut self, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        match message {
+            WM_MOUSEACTIVATE => return LRESULT(MA_NOACTIVATE as isize),
+            WM_MOUSEMOVE => {
+                let position = self.to_owner(lparam);
+                let _ = self.event_channel.try_send(WindowEvent::CursorMoved {
+                    device_id: unsafe { DeviceId::dummy() },
+                    position,
+                    modifiers: ModifiersState::default(),
+                });
+            }
+            WM_LBUTTONDOWN => self.send_mouse_input(ElementState::Pressed, MouseButton::Left),
+            WM_LBUTTONUP => self.send_mouse_input(ElementState::Released, MouseButton::Left),
+            WM_RBUTTONDOWN => self.send_mouse_input(ElementState::Pressed, MouseButton::Right),
+            WM_RBUTTONUP => self.send_mouse_input(ElementState::Released, MouseButton::Right),
+            // The wheel scrolls the window under the cursor, so the owner doesn't get it
+            WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+                let _ = self
+                    .event_channel
+                    .try_send(mouse_wheel_event(message, wparam));
+                return LRESULT(0);
+            }
+            _ => {}
+        }
+        unsafe { DefWindowProcW(self.handle, message, wparam, lparam) }
+    }
+
+    unsafe extern "system" fn wnd_proc(
+        window: HWND,
+        message: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if message == WM_NCCREATE {
+            let cs = lparam.0 as *const CREATESTRUCTW;
+            let this = (*cs).lpCreateParams as *mut Self;
+            (*this).handle = window;
+            SetWindowLong(window, GWLP_USERDATA, this as _);
+        } else if message == WM_NCDESTROY {
+            let this = GetWindowLong(window, GWLP_USERDATA) as *mut Self;
+            if !this.is_null() {
+                SetWindowLong(window, GWLP_USERDATA, 0);
+                drop(Box::from_raw(this));
+            }
+        } else {
+            let this = GetWindowLong(window, GWLP_USERDATA) as *mut Self;
+            if let Some(this) = this.as_mut() {
+                return this.message_handler(message, wparam, lparam);
+            }
+        }
+        DefWindowProcW(window, message, wparam, lparam)
+    }
+}
+
+///
+/// Factory of popup windows owned by the application window. Can be used from any thread,
+/// the windows are created on the thread of the owner window.
+///
+#[derive(Clone)]
+pub struct PopupWindowHost {
+    compositor: Compositor,
+    owner: HWND,
+    event_channel: WindowEventSender,
+    queue: DispatcherQueue,
+}
+
+impl PopupWindowHost {
+    pub(super) fn new(
+        compositor: Compositor,
+        owner: HWND,
+        event_channel: WindowEventSender,
+        queue: DispatcherQueue,
+    ) -> Self {
+        Self {
+            compositor,
+            owner,
+            event_channel,
+            queue,
+        }
+    }
+
+    /// Work area of the monitor with the owner window in the owner's client coordinates
+    pub fn screen_bounds(&self) -> crate::Result<Rect> {
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        let mut origin = POINT::default();
+        unsafe {
+            let monitor = MonitorFromWindow(self.owner, MONITOR_DEFAULTTONEAREST);
+            GetMonitorInfoW(monitor, &mut info).ok()?;
+            ClientToScreen(self.owner, &mut origin);
+        }
+        let work = info.rcWork;
+        Ok(Rect::new(
+            Point::new((work.left - origin.x) as f32, (work.top - origin.y) as f32),
+            Size::new(
+                (work.right - work.left) as f32,
+                (work.bottom - work.top) as f32,
+            ),
+        ))
+    }
+
+    /// Opens popup window at the rectangle in the owner's client coordinates
+    pub async fn open(&self, rect: Rect) -> crate::Result<PopupWindowHandle> {
+        let compositor = self.compositor.clone();
+        let owner = self.owner;
+        let event_channel = self.event_channel.clone();
+        let (handle, root_visual) = run_on_window_thread(&self.queue, move || {
+            PopupWindow::create(&compositor, owner, event_channel, rect)
+        })
+        .await?;
+        Ok(PopupWindowHandle {
+            handle,
+            owner,
+            root_visual,
+            queue: self.queue.clone(),
+            closed: AtomicBool::new(false),
+        })
+    }
+}
+
+/// Popup window, destroyed by `close` or when the handle is dropped
+pub struct PopupWindowHandle {
+    handle: HWND,
+    owner: HWND,
+    root_visual: ContainerVisual,
+    queue: DispatcherQueue,
+    closed: AtomicBool,
+}
+
+impl PopupWindowHandle {
+    /// Root of the popup window's visual tree
+    pub fn root_visual(&self) -> &ContainerVisual {
+        &self.root_visual
+    }
+
+    /// Moves the window to the rectangle in the owner's client coordinates
+    pub async fn set_rect(&self, rect: Rect) -> crate::Result<()> {
+        self.root_visual.SetSize(Vector2::from(rect.size))?;
+        let handle = self.handle;
+        let owner = self.owner;
+        run_on_window_thread(&self.queue, move || {
+            let origin = client_to_screen(owner, rect);
+            unsafe {
+                SetWindowPos(
+                    handle,
+                    HWND::default(),
+                    origin.x,
+                    origin.y,
+                    rect.size.width as i32,
+                    rect.size.height as i32,
+                    SWP_NOACTIVATE | SWP_NOZORDER,
+                )
+                .ok()?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn close(&self) -> crate::Result<()> {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let handle = self.handle;
+        run_on_window_thread(&self.queue, move || {
+            unsafe { DestroyWindow(handle).ok()? };
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl Drop for PopupWindowHandle {
+    fn drop(&mut self) {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Nobody waits for the result, the window is destroyed if its thread is still running
+        let handle = self.handle;
+        let handler = DispatcherQueueHandler::new(move || {
+            unsafe { DestroyWindow(handle) };
+            Ok(())
+        });
+        let _ = self.queue.TryEnqueue(&handler);
+    }
+}
@@ -0,0 +1,59 @@
+use windows::Win32::Graphics::Dxgi::{
+    IDXGISwapChain, DXGI_ERROR_FRAME_STATISTICS_DISJOINT, DXGI_FRAME_STATISTICS,
+};
+
+///
+/// Snapshot of the present statistics of the swap chain presented through the composition
+/// tree. Two snapshots taken some frames apart show the stutter: the frames dropped between
+/// them and the number of frames queued but not yet displayed. DXGI doesn't report the GPU
+/// time, measure it with the renderer's timestamp queries if needed.
+///
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PresentStatistics {
+    /// Number of the last Present call which reached the screen
+    pub present_count: u32,
+    /// Number of vblanks at the moment the frame `present_count` was displayed
+    pub present_refresh_count: u32,
+    /// Number of the last Present call made by the application
+    pub last_present_count: u32,
+    /// QueryPerformanceCounter value of the vblank `present_refresh_count`
+    pub sync_qpc_time: i64,
+}
+
+impl PresentStatistics {
+    /// Frames presented by the application but not displayed yet
+    pub fn queued_frames(&self) -> u32 {
+        self.last_present_count.wrapping_sub(self.present_count)
+    }
+
+    ///
+    /// Vblanks passed since the `previous` snapshot which didn't show a new frame. For the
+    /// renderer presenting each vblank this is the number of dropped frames.
+    ///
+    pub fn dropped_frames_since(&self, previous: &PresentStatistics) -> u32 {
+        let refreshes = self
+            .present_refresh_count
+            .wrapping_sub(previous.present_refresh_count);
+        let presents = self.present_count.wrapping_sub(previous.present_count);
+        refreshes.saturating_sub(presents)
+    }
+}
+
+///
+/// Reads the present statistics of the swap chain. Returns `None` while the statistics are
+/// unavailable, e.g. right after the first Present or after the display mode change.
+///
+pub fn present_statistics(swap_chain: &IDXGISwapChain) -> crate::Result<Option<PresentStatistics>> {
+    let mut stats = DXGI_FRAME_STATISTICS::default();
+    match unsafe { swap_chain.GetFrameStatistics(&mut stats) } {
+        Err(e) if e.code() == DXGI_ERROR_FRAME_STATISTICS_DISJOINT => return Ok(None),
+        result => result?,
+    }
+    let last_present_count = unsafe { swap_chain.GetLastPresentCount() }?;
+    Ok(Some(PresentStatistics {
+        present_count: stats.PresentCount,
+        present_refresh_count: stats.PresentRefreshCount,
+        last_present_count,
+        sync_qpc_time: stats.SyncQPCTime,
+    }))
+}
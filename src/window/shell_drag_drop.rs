@@ -0,0 +1,381 @@
+use std::{
+    ffi::OsString,
+    mem::size_of,
+    os::windows::ffi::{OsStrExt, OsStringExt},
+    path::PathBuf,
+    ptr::{copy_nonoverlapping, null_mut},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use windows::{
+    core::{self, implement, HRESULT},
+    Foundation::Numerics::Vector2,
+    System::DispatcherQueue,
+    Win32::{
+        Foundation::{
+            BOOL, DRAGDROP_S_CANCEL, DRAGDROP_S_DROP, DRAGDROP_S_USEDEFAULTCURSORS, DV_E_FORMATETC,
+            E_NOTIMPL, E_OUTOFMEMORY, HWND, OLE_E_ADVISENOTSUPPORTED, POINT, POINTL, S_OK,
+        },
+        Graphics::Gdi::ScreenToClient,
+        System::{
+            Com::{
+                IAdviseSink, IDataObject, IDataObject_Impl, IEnumFORMATETC, IEnumSTATDATA,
+                DATADIR_GET, DVASPECT_CONTENT, FORMATETC, STGMEDIUM, STGMEDIUM_0, TYMED_HGLOBAL,
+            },
+            Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+            Ole::{
+                DoDragDrop, IDropSource, IDropSource_Impl, IDropTarget, IDropTarget_Impl,
+                ReleaseStgMedium, DROPEFFECT, DROPEFFECT_COPY, DROPEFFECT_NONE,
+            },
+            SystemServices::{CF_HDROP, CF_UNICODETEXT, MK_LBUTTON, MODIFIERKEYS_FLAGS},
+        },
+        UI::Shell::{DragQueryFileW, SHCreateStdEnumFmtEtc, DROPFILES, HDROP},
+    },
+};
+use winit::event::{ElementState, MouseButton};
+
+use crate::gui::{PanelEvent, WindowEventSender};
+
+use super::popup_window::run_on_window_thread;
+
+fn format_etc(format: u32) -> FORMATETC {
+    FORMATETC {
+        cfFormat: format as u16,
+        ptd: null_mut(),
+        dwAspect: DVASPECT_CONTENT.0 as u32,
+        lindex: -1,
+        tymed: TYMED_HGLOBAL.0 as u32,
+    }
+}
+
+// Paths of the files in the data object dragged from the shell
+fn dropped_files(data: &IDataObject) -> core::Result<Vec<PathBuf>> {
+    unsafe {
+        let mut medium = data.GetData(&format_etc(CF_HDROP.0))?;
+        let hdrop = HDROP(medium.Anonymous.hGlobal);
+        let count = DragQueryFileW(hdrop, u32::MAX, None);
+        let mut paths = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            let len = DragQueryFileW(hdrop, i, None) as usize;
+            let mut buffer = vec![0u16; len + 1];
+            DragQueryFileW(hdrop, i, Some(&mut buffer));
+            paths.push(PathBuf::from(OsString::from_wide(&buffer[..len])));
+        }
+        ReleaseStgMedium(&mut medium);
+        Ok(paths)
+    }
+}
+
+///
+/// Drop target registered for the window. Files dragged over the window are reported as
+/// cursor moves in the client coordinates, the drop sends `PanelEvent::FileDrop` after the
+/// cursor move to the drop point.
+///
+#[implement(IDropTarget)]
+pub(super) struct FileDropTarget {
+    window: HWND,
+    event_channel: Mutex<WindowEventSender>,
+    // The dragged data object contains files
+    accepted: AtomicBool,
+}
+
+impl FileDropTarget {
+    pub(super) fn new(window: HWND, event_channel: WindowEventSender) -> Self {
+        Self {
+            window,
+            event_channel: Mutex::new(event_channel),
+            accepted: AtomicBool::new(false),
+        }
+    }
+
+    fn send(&self, event: PanelEvent) {
+        let _ = self
+            .event_channel
+            .lock()
+            .unwrap()
+            .try_send_panel_event(event);
+    }
+
+    fn drag_over(&self, pt: &POINTL, pdweffect: *mut DROPEFFECT) {
+        let mut point = POINT { x: pt.x, y: pt.y };
+        unsafe { ScreenToClient(self.window, &mut point) };
+        self.send(PanelEvent::CursorMoved(Vector2 {
+            X: point.x as f32,
+            Y: point.y as f32,
+        }));
+        let effect = if self.accepted.load(Ordering::SeqCst) {
+            DROPEFFECT_COPY
+        } else {
+            DROPEFFECT_NONE
+        };
+        if let Some(pdweffect) = unsafe { pdweffect.as_mut() } {
+            *pdweffect = effect;
+        }
+    }
+}
+
+impl IDropTarget_Impl for FileDropTarget {
+    fn DragEnter(
+        &self,
+        pdataobj: &Option<IDataObject>,
+        _grfkeystate: MODIFIERKEYS_FLAGS,
+        pt: &POINTL,
+        pdweffect: *mut DROPEFFECT,
+    ) -> core::Result<()> {
+        let accepted = pdataobj.as_ref().map_or(false, |data| unsafe {
+            data.QueryGetData(&format_etc(CF_HDROP.0)) == S_OK
+        });
+        self.accepted.store(accepted, Ordering::SeqCst);
+        self.drag_over(pt, pdweffect);
+        Ok(())
+    }
+
+    fn DragOver(
+        &self,
+        _grfkeystate: MODIFIERKEYS_FLAGS,
+        pt: &POINTL,
+        pdweffect: *mut DROPEFFECT,
+    ) -> core::Result<()> {
+        self.drag_over(pt, pdweffect);
+        Ok(())
+    }
+
+    fn DragLeave(&self) -> core::Result<()> {
+        self.accepted.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn Drop(
+        &self,
+        pdataobj: &Option<IDataObject>,
+        _grfkeystate: MODIFIERKEYS_FLAGS,
+        pt: &POINTL,
+        pdweffect: *mut DROPEFFECT,
+    ) -> core::Result<()> {
+        self.drag_over(pt, pdweffect);
+        if let Some(data) = pdataobj {
+            if self.accepted.swap(false, Ordering::SeqCst) {
+                let paths = dropped_files(data)?;
+                if !paths.is_empty() {
+                    self.send(PanelEvent::FileDrop(paths));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Data dragged from the window to other applications
+#[derive(Clone, Debug)]
+pub enum DragOutData {
+    Text(String),
+    Files(Vec<PathBuf>),
+}
+
+impl DragOutData {
+    // Clipboard format and content of the global memory block in this format
+    fn to_clipboard(&self) -> (u32, Vec<u8>) {
+        fn push_wide(bytes: &mut Vec<u8>, wide: impl Iterator<Item = u16>) {
+            for v in wide.chain(Some(0)) {
+                bytes.extend_from_slice(&v.to_ne_bytes());
+            }
+        }
+        let mut bytes = Vec::new();
+        match self {
+            DragOutData::Text(text) => {
+                push_wide(&mut bytes, text.encode_utf16());
+                (CF_UNICODETEXT.0, bytes)
+            }
+            DragOutData::Files(paths) => {
+                // DROPFILES header followed by the double null terminated list of wide paths
+                let header = DROPFILES {
+                    pFiles: size_of::<DROPFILES>() as u32,
+                    fWide: true.into(),
+                    ..Default::default()
+                };
+                let header = unsafe {
+                    std::slice::from_raw_parts(
+                        &header as *const DROPFILES as *const u8,
+                        size_of::<DROPFILES>(),
+                    )
+                };
+                bytes.extend_from_slice(header);
+                for path in paths {
+                    push_wide(&mut bytes, path.as_os_str().encode_wide());
+                }
+                bytes.extend_from_slice(&0u16.to_ne_bytes());
+                (CF_HDROP.0, bytes)
+            }
+        }
+    }
+}
+
+// Data object providing single format in the global memory
+#[implement(IDataObject)]
+struct DataObject {
+    format: u32,
+    bytes: Vec<u8>,
+}
+
+impl DataObject {
+    fn supports(&self, format: *const FORMATETC) -> bool {
+        match unsafe { format.as_ref() } {
+            Some(format) => {
+                format.cfFormat as u32 == self.format
+                    && format.tymed & TYMED_HGLOBAL.0 as u32 != 0
+                    && format.dwAspect == DVASPECT_CONTENT.0 as u32
+            }
+            None => false,
+        }
+    }
+}
+
+impl IDataObject_Impl for DataObject {
+    fn GetData(&self, pformatetcin: *const FORMATETC) -> core::Result<STGMEDIUM> {
+        if !self.supports(pformatetcin) {
+            return Err(DV_E_FORMATETC.into());
+        }
+        unsafe {
+            let global = GlobalAlloc(GMEM_MOVEABLE, self.bytes.len());
+            if global == 0 {
+                return Err(E_OUTOFMEMORY.into());
+            }
+            let ptr = GlobalLock(global) as *mut u8;
+            if ptr.is_null() {
+                GlobalFree(global);
+                return Err(E_OUTOFMEMORY.into());
+            }
+            copy_nonoverlapping(self.bytes.as_ptr(), ptr, self.bytes.len());
+            GlobalUnlock(global);
+            Ok(STGMEDIUM {
+                tymed: TYMED_HGLOBAL.0 as u32,
+                Anonymous: STGMEDIUM_0 { hGlobal: global },
+                pUnkForRelease: None,
+            })
+        }
+    }
+
+    fn GetDataHere(
+        &self,
+        _pformatetc: *const FORMATETC,
+        _pmedium: *mut STGMEDIUM,
+    ) -> core::Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn QueryGetData(&self, pformatetc: *const FORMATETC) -> HRESULT {
+        if self.supports(pformatetc) {
+            S_OK
+        } else {
+            DV_E_FORMATETC
+        }
+    }
+
+    fn GetCanonicalFormatEtc(
+        &self,
+        _pformatectin: *const FORMATETC,
+        _pformatetcout: *mut FORMATETC,
+    ) -> HRESULT {
+        E_NOTIMPL
+    }
+
+    fn SetData(
+        &self,
+        _pformatetc: *const FORMATETC,
+        _pmedium: *const STGMEDIUM,
+        _frelease: BOOL,
+    ) -> core::Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn EnumFormatEtc(&self, dwdirection: u32) -> core::Result<IEnumFORMATETC> {
+        if dwdirection == DATADIR_GET.0 as u32 {
+            unsafe { SHCreateStdEnumFmtEtc(&[format_etc(self.format)]) }
+        } else {
+            Err(E_NOTIMPL.into())
+        }
+    }
+
+    fn DAdvise(
+        &self,
+        _pformatetc: *const FORMATETC,
+        _advf: u32,
+        _padvsink: &Option<IAdviseSink>,
+    ) -> core::Result<u32> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+
+    fn DUnadvise(&self, _dwconnection: u32) -> core::Result<()> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+
+    fn EnumDAdvise(&self) -> core::Result<IEnumSTATDATA> {
+        Err(OLE_E_ADVISENOTSUPPORTED.into())
+    }
+}
+
+// Drop source following the left mouse button, Escape cancels the drag
+#[implement(IDropSource)]
+struct DropSource;
+
+impl IDropSource_Impl for DropSource {
+    fn QueryContinueDrag(&self, fescapepressed: BOOL, grfkeystate: MODIFIERKEYS_FLAGS) -> HRESULT {
+        if fescapepressed.as_bool() {
+            DRAGDROP_S_CANCEL
+        } else if grfkeystate.0 & MK_LBUTTON.0 == 0 {
+            DRAGDROP_S_DROP
+        } else {
+            S_OK
+        }
+    }
+
+    fn GiveFeedback(&self, _dweffect: DROPEFFECT) -> HRESULT {
+        DRAGDROP_S_USEDEFAULTCURSORS
+    }
+}
+
+///
+/// Starts drags of text or files from the window to other applications. The drag is run by
+/// the system on the window's thread until the left button is released, so the panel starts
+/// it on pressed left button, usually after the cursor moves far enough.
+///
+#[derive(Clone)]
+pub struct ShellDragSource {
+    queue: DispatcherQueue,
+    event_channel: WindowEventSender,
+}
+
+impl ShellDragSource {
+    pub(super) fn new(queue: DispatcherQueue, event_channel: WindowEventSender) -> Self {
+        Self {
+            queue,
+            event_channel,
+        }
+    }
+
+    /// Drags the data out of the window, returns true if it was dropped somewhere
+    pub async fn drag(&self, data: DragOutData) -> crate::Result<bool> {
+        let mut event_channel = self.event_channel.clone();
+        run_on_window_thread(&self.queue, move || {
+            let (format, bytes) = data.to_clipboard();
+            let data_object: IDataObject = DataObject { format, bytes }.into();
+            let drop_source: IDropSource = DropSource.into();
+            let mut effect = DROPEFFECT_NONE;
+            let result =
+                unsafe { DoDragDrop(&data_object, &drop_source, DROPEFFECT_COPY, &mut effect) };
+            // The system loop consumed the button release, the panel tree still waits for it
+            let _ = event_channel.try_send_panel_event(PanelEvent::MouseInput {
+                in_slot: true,
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                click_count: 1,
+                seq: 0,
+            });
+            Ok(result == DRAGDROP_S_DROP && effect != DROPEFFECT_NONE)
+        })
+        .await
+    }
+}
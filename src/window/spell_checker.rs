@@ -0,0 +1,136 @@
+use std::ops::Range;
+
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Globalization::{
+            IEnumSpellingError, ISpellChecker, ISpellCheckerFactory, ISpellingError,
+            SpellCheckerFactory, CORRECTIVE_ACTION_DELETE, CORRECTIVE_ACTION_REPLACE,
+        },
+        System::Com::{CoCreateInstance, CoTaskMemFree, IEnumString, CLSCTX_INPROC_SERVER},
+    },
+};
+
+use super::ToWide;
+
+/// What the spell checker suggests to do with the misspelled fragment
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SpellingAction {
+    /// Offer the user the words returned by [`SpellChecker::suggest`]
+    Suggest,
+    /// Replace the fragment with the given text, e.g. by autocorrection
+    Replace(String),
+    /// Delete the fragment, e.g. the repeated word
+    Delete,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Misspelling {
+    /// Position of the fragment in UTF-16 code units, as the text widgets keep it
+    pub range: Range<usize>,
+    pub action: SpellingAction,
+}
+
+// Takes the string allocated by the spell checker, freeing it
+fn take_string(value: PWSTR) -> String {
+    if value.is_null() {
+        return String::new();
+    }
+    let result = unsafe { value.to_string() };
+    unsafe { CoTaskMemFree(Some(value.0 as *const _)) };
+    result.unwrap_or_default()
+}
+
+fn collect_strings(strings: IEnumString) -> crate::Result<Vec<String>> {
+    let mut result = Vec::new();
+    loop {
+        let mut value = [PWSTR::null()];
+        let mut fetched = 0;
+        unsafe { strings.Next(&mut value, Some(&mut fetched as *mut _)) }.ok()?;
+        if fetched == 0 {
+            return Ok(result);
+        }
+        result.push(take_string(value[0]));
+    }
+}
+
+fn factory() -> crate::Result<ISpellCheckerFactory> {
+    Ok(unsafe { CoCreateInstance(&SpellCheckerFactory, None, CLSCTX_INPROC_SERVER) }?)
+}
+
+/// BCP47 tags of the languages which have the spell checker installed, e.g. "en-US"
+pub fn spell_checker_languages() -> crate::Result<Vec<String>> {
+    collect_strings(unsafe { factory()?.SupportedLanguages() }?)
+}
+
+///
+/// Windows spell checker for one language. The user's dictionary is shared with other
+/// applications, so the words added here are also known to them. Create it on the window
+/// thread, it relies on COM initialized by `WindowThread`.
+///
+pub struct SpellChecker {
+    checker: ISpellChecker,
+    language: String,
+}
+
+impl SpellChecker {
+    pub fn new(language: &str) -> crate::Result<Self> {
+        let checker = unsafe { factory()?.CreateSpellChecker(language.to_wide().as_pcwstr()) }?;
+        Ok(SpellChecker {
+            checker,
+            language: language.to_string(),
+        })
+    }
+
+    pub fn is_supported(language: &str) -> crate::Result<bool> {
+        Ok(unsafe { factory()?.IsSupported(language.to_wide().as_pcwstr()) }?.as_bool())
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Misspelled fragments of the text to underline
+    pub fn check(&self, text: &str) -> crate::Result<Vec<Misspelling>> {
+        let errors: IEnumSpellingError = unsafe { self.checker.Check(text.to_wide().as_pcwstr()) }?;
+        let mut result = Vec::new();
+        loop {
+            // The end of the enumeration is reported by S_FALSE without the error object
+            let error: ISpellingError = match unsafe { errors.Next() } {
+                Ok(error) => error,
+                Err(e) if e.code().is_ok() => return Ok(result),
+                Err(e) => return Err(e.into()),
+            };
+            let start = unsafe { error.StartIndex() }? as usize;
+            let length = unsafe { error.Length() }? as usize;
+            let action = match unsafe { error.CorrectiveAction() }? {
+                CORRECTIVE_ACTION_REPLACE => {
+                    SpellingAction::Replace(take_string(unsafe { error.Replacement() }?))
+                }
+                CORRECTIVE_ACTION_DELETE => SpellingAction::Delete,
+                _ => SpellingAction::Suggest,
+            };
+            result.push(Misspelling {
+                range: start..start + length,
+                action,
+            });
+        }
+    }
+
+    /// Replacements for the misspelled word, for the context menu
+    pub fn suggest(&self, word: &str) -> crate::Result<Vec<String>> {
+        collect_strings(unsafe { self.checker.Suggest(word.to_wide().as_pcwstr()) }?)
+    }
+
+    /// Adds the word to the user's dictionary
+    pub fn add(&self, word: &str) -> crate::Result<()> {
+        unsafe { self.checker.Add(word.to_wide().as_pcwstr()) }?;
+        Ok(())
+    }
+
+    /// Ignores the word until the application exits
+    pub fn ignore(&self, word: &str) -> crate::Result<()> {
+        unsafe { self.checker.Ignore(word.to_wide().as_pcwstr()) }?;
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    ffi::c_void,
+    mem::size_of,
+    sync::{Arc, Mutex},
+};
+
+use async_event_streams::{EventSource, EventStream, EventStreams};
+use windows::{
+    core::IInspectable,
+    w,
+    Foundation::TypedEventHandler,
+    Win32::{
+        Foundation::ERROR_SUCCESS,
+        Globalization::GetUserDefaultLocaleName,
+        System::{
+            Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS},
+            Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD},
+        },
+        UI::WindowsAndMessaging::{GetSystemMetrics, SM_CMONITORS},
+    },
+    UI::{
+        Color,
+        ViewManagement::{UIColorType, UISettings},
+    },
+};
+
+// Maximal length of the locale name including the terminating null
+const LOCALE_NAME_MAX_LENGTH: usize = 85;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    Unknown,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum SystemEvent {
+    ColorSchemeChanged(ColorScheme),
+    AccentColorChanged(Color),
+    /// The user's locale name, e.g. "en-US"
+    LocaleChanged(String),
+    PowerSourceChanged(PowerSource),
+    DisplayAdded,
+    DisplayRemoved,
+    /// Resolution or arrangement of the displays changed
+    DisplaySettingsChanged,
+}
+
+#[derive(PartialEq, Clone)]
+struct State {
+    color_scheme: ColorScheme,
+    accent_color: Color,
+    locale: String,
+    power_source: PowerSource,
+    display_count: i32,
+}
+
+impl State {
+    fn query(settings: &UISettings) -> crate::Result<Self> {
+        Ok(Self {
+            color_scheme: color_scheme(settings)?,
+            accent_color: settings.GetColorValue(UIColorType::Accent)?,
+            locale: locale(),
+            power_source: power_source(),
+            display_count: unsafe { GetSystemMetrics(SM_CMONITORS) },
+        })
+    }
+}
+
+// The apps' mode chosen in the personalization settings, not set on the older systems
+fn registry_color_scheme() -> Option<ColorScheme> {
+    let mut value = 0u32;
+    let mut size = size_of::<u32>() as u32;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            w!("AppsUseLightTheme"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut c_void),
+            Some(&mut size),
+        )
+    };
+    (result == ERROR_SUCCESS).then_some(if value == 0 {
+        ColorScheme::Dark
+    } else {
+        ColorScheme::Light
+    })
+}
+
+fn color_scheme(settings: &UISettings) -> crate::Result<ColorScheme> {
+    if let Some(color_scheme) = registry_color_scheme() {
+        return Ok(color_scheme);
+    }
+    // The system text color is light on the dark background
+    let foreground = settings.GetColorValue(UIColorType::Foreground)?;
+    let luminance =
+        0.299 * foreground.R as f32 + 0.587 * foreground.G as f32 + 0.114 * foreground.B as f32;
+    Ok(if luminance > 128. {
+        ColorScheme::Dark
+    } else {
+        ColorScheme::Light
+    })
+}
+
+fn locale() -> String {
+    let mut buffer = [0u16; LOCALE_NAME_MAX_LENGTH];
+    let len = unsafe { GetUserDefaultLocaleName(&mut buffer) };
+    // The length includes the terminating null, zero means failure
+    String::from_utf16_lossy(&buffer[..(len.max(1) - 1) as usize])
+}
+
+fn power_source() -> PowerSource {
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if !unsafe { GetSystemPowerStatus(&mut status) }.as_bool() {
+        return PowerSource::Unknown;
+    }
+    match status.ACLineStatus {
+        0 => PowerSource::Battery,
+        1 => PowerSource::Ac,
+        _ => PowerSource::Unknown,
+    }
+}
+
+// Queries the settings again and sends events for the changed ones
+fn refresh(
+    settings: &UISettings,
+    state: &Mutex<State>,
+    system_events: &EventStreams<SystemEvent>,
+    display_changed: bool,
+) -> crate::Result<()> {
+    let new = State::query(settings)?;
+    let old = std::mem::replace(&mut *state.lock().unwrap(), new.clone());
+    let mut events = Vec::new();
+    if old.color_scheme != new.color_scheme {
+        events.push(SystemEvent::ColorSchemeChanged(new.color_scheme));
+    }
+    if old.accent_color != new.accent_color {
+        events.push(SystemEvent::AccentColorChanged(new.accent_color));
+    }
+    if old.locale != new.locale {
+        events.push(SystemEvent::LocaleChanged(new.locale));
+    }
+    if old.power_source != new.power_source {
+        events.push(SystemEvent::PowerSourceChanged(new.power_source));
+    }
+    if new.display_count > old.display_count {
+        events.push(SystemEvent::DisplayAdded);
+    } else if new.display_count < old.display_count {
+        events.push(SystemEvent::DisplayRemoved);
+    } else if display_changed {
+        events.push(SystemEvent::DisplaySettingsChanged);
+    }
+    for event in events {
+        system_events.post_event(event, None);
+    }
+    Ok(())
+}
+
+///
+/// Changes of the system settings broadcast to the top-level windows: color scheme, accent
+/// color, locale, power source and displays. The window refreshes the state on the related
+/// messages and sends the event for each value which actually changed. The color scheme is
+/// the apps' mode from the personalization settings, its and the accent color's changes are
+/// also reported by the system's `UISettings`, so they are detected without a window.
+///
+#[derive(Clone)]
+pub struct SystemEvents {
+    settings: UISettings,
+    state: Arc<Mutex<State>>,
+    system_events: Arc<EventStreams<SystemEvent>>,
+}
+
+impl SystemEvents {
+    pub fn new() -> crate::Result<Self> {
+        let settings = UISettings::new()?;
+        let state = Arc::new(Mutex::new(State::query(&settings)?));
+        let system_events = Arc::new(EventStreams::new());
+        // The handler is called on a background thread; it's owned by the settings, so it
+        // holds the rest weakly
+        let weak_state = Arc::downgrade(&state);
+        let weak_events = Arc::downgrade(&system_events);
+        settings.ColorValuesChanged(&TypedEventHandler::new(
+            move |settings: &Option<UISettings>, _: &Option<IInspectable>| {
+                if let (Some(settings), Some(state), Some(system_events)) =
+                    (settings, weak_state.upgrade(), weak_events.upgrade())
+                {
+                    let _ = refresh(settings, &state, &system_events, false);
+                }
+                Ok(())
+            },
+        ))?;
+        Ok(Self {
+            settings,
+            state,
+            system_events,
+        })
+    }
+
+    pub fn color_scheme(&self) -> ColorScheme {
+        self.state.lock().unwrap().color_scheme
+    }
+
+    pub fn accent_color(&self) -> Color {
+        self.state.lock().unwrap().accent_color
+    }
+
+    pub fn locale(&self) -> String {
+        self.state.lock().unwrap().locale.clone()
+    }
+
+    pub fn power_source(&self) -> PowerSource {
+        self.state.lock().unwrap().power_source
+    }
+
+    pub fn display_count(&self) -> usize {
+        self.state.lock().unwrap().display_count.max(0) as usize
+    }
+
+    /// Queries the settings again and sends events for the changed ones
+    pub(super) fn refresh(&self, display_changed: bool) -> crate::Result<()> {
+        refresh(
+            &self.settings,
+            &self.state,
+            &self.system_events,
+            display_changed,
+        )
+    }
+}
+
+impl EventSource<SystemEvent> for SystemEvents {
+    fn event_stream(&self) -> EventStream<SystemEvent> {
+        self.system_events.create_event_stream()
+    }
+}
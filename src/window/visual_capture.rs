@@ -0,0 +1,153 @@
+use std::sync::Mutex;
+
+use futures::channel::oneshot;
+use windows::{
+    core::{self, Interface},
+    Foundation::TypedEventHandler,
+    Graphics::{
+        Capture::{Direct3D11CaptureFramePool, GraphicsCaptureItem},
+        DirectX::{Direct3D11::IDirect3DDevice, DirectXPixelFormat},
+        SizeInt32,
+    },
+    Win32::{
+        Foundation::{E_ABORT, E_INVALIDARG},
+        Graphics::{
+            Direct3D11::{
+                ID3D11DeviceContext, ID3D11Texture2D, D3D11_BIND_FLAG, D3D11_CPU_ACCESS_READ,
+                D3D11_MAP_READ, D3D11_RESOURCE_MISC_FLAG, D3D11_TEXTURE2D_DESC,
+                D3D11_USAGE_STAGING,
+            },
+            Dxgi::IDXGIDevice,
+        },
+        System::WinRT::Direct3D11::{
+            CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess,
+        },
+    },
+    UI::Composition::{CompositionDrawingSurface, Visual},
+};
+
+use super::{copy_texture_to_surface, graphics::d3d11_device, Bitmap};
+
+// Reads the texture back to the memory, cropped to the size
+fn read_texture(texture: &ID3D11Texture2D, width: u32, height: u32) -> crate::Result<Bitmap> {
+    let device = d3d11_device()?;
+    let mut desc = D3D11_TEXTURE2D_DESC::default();
+    unsafe { texture.GetDesc(&mut desc) };
+    desc.Usage = D3D11_USAGE_STAGING;
+    desc.BindFlags = D3D11_BIND_FLAG(0);
+    desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+    desc.MiscFlags = D3D11_RESOURCE_MISC_FLAG(0);
+    let mut staging: Option<ID3D11Texture2D> = None;
+    unsafe { device.CreateTexture2D(&desc, None, Some(&mut staging)) }?;
+    let staging = staging.ok_or_else(|| core::Error::from(E_ABORT))?;
+    let mut context: Option<ID3D11DeviceContext> = None;
+    unsafe { device.GetImmediateContext(&mut context) };
+    let context = context.ok_or_else(|| core::Error::from(E_ABORT))?;
+    unsafe { context.CopyResource(&staging, texture) };
+    let mapped = unsafe { context.Map(&staging, 0, D3D11_MAP_READ, 0) }?;
+    let (width, height) = (width.min(desc.Width), height.min(desc.Height));
+    let mut bitmap = Bitmap::empty(width, height);
+    let row_len = width as usize * 4;
+    for row in 0..height as usize {
+        let source = unsafe {
+            std::slice::from_raw_parts(
+                (mapped.pData as *const u8).add(row * mapped.RowPitch as usize),
+                row_len,
+            )
+        };
+        bitmap.pixels[row * row_len..(row + 1) * row_len].copy_from_slice(source);
+    }
+    unsafe { context.Unmap(&staging, 0) };
+    // The compositor renders the premultiplied colors, the images keep the straight ones
+    for pixel in bitmap.pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha != 0 && alpha != 255 {
+            for v in &mut pixel[..3] {
+                *v = (*v as u32 * 255 / alpha).min(255) as u8;
+            }
+        }
+    }
+    Ok(bitmap)
+}
+
+// Renders the visual into the captured frame and passes the frame's texture to `f`
+async fn capture_frame<T>(
+    visual: &Visual,
+    width: u32,
+    height: u32,
+    f: impl FnOnce(&ID3D11Texture2D) -> crate::Result<T>,
+) -> crate::Result<T> {
+    if width == 0 || height == 0 {
+        return Err(core::Error::from(E_INVALIDARG).into());
+    }
+    let dxgi_device: IDXGIDevice = d3d11_device()?.cast()?;
+    let device: IDirect3DDevice =
+        unsafe { CreateDirect3D11DeviceFromDXGIDevice(&dxgi_device) }?.cast()?;
+    let item = GraphicsCaptureItem::CreateFromVisual(visual)?;
+    let size = SizeInt32 {
+        Width: width as i32,
+        Height: height as i32,
+    };
+    let pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
+        &device,
+        DirectXPixelFormat::B8G8R8A8UIntNormalized,
+        1,
+        size,
+    )?;
+    let session = pool.CreateCaptureSession(&item)?;
+    let (sender, receiver) = oneshot::channel();
+    let sender = Mutex::new(Some(sender));
+    let token = pool.FrameArrived(&TypedEventHandler::new(
+        move |pool: &Option<Direct3D11CaptureFramePool>, _| {
+            if let Some(pool) = pool {
+                if let Some(sender) = sender.lock().unwrap().take() {
+                    let _ = sender.send(pool.TryGetNextFrame());
+                }
+            }
+            Ok(())
+        },
+    ))?;
+    session.StartCapture()?;
+    let frame = receiver.await;
+    pool.RemoveFrameArrived(token)?;
+    session.Close()?;
+    let frame = frame.map_err(|_| core::Error::from(E_ABORT))??;
+    let access: IDirect3DDxgiInterfaceAccess = frame.Surface()?.cast()?;
+    let texture: ID3D11Texture2D = unsafe { access.GetInterface() }?;
+    let result = f(&texture);
+    frame.Close()?;
+    pool.Close()?;
+    result
+}
+
+///
+/// Renders the visual with its children into the bitmap of `width` x `height` pixels. The
+/// visual doesn't need to be shown in a window, so the panels may be rendered without one,
+/// e.g. in the tests. Waits for the compositor to commit the pending changes of the tree
+/// and render the frame.
+///
+pub async fn capture_visual(visual: &Visual, width: u32, height: u32) -> crate::Result<Bitmap> {
+    capture_frame(visual, width, height, |texture| {
+        read_texture(texture, width, height)
+    })
+    .await
+}
+
+///
+/// Renders the visual with its children into the drawing surface of its size, staying on the
+/// GPU. The surface keeps the image until the next capture, so the static content may be
+/// rendered once and shown by the surface's brush.
+///
+pub async fn capture_visual_to_surface(
+    visual: &Visual,
+    surface: &CompositionDrawingSurface,
+) -> crate::Result<()> {
+    let size = surface.SizeInt32()?;
+    capture_frame(
+        visual,
+        size.Width.max(0) as u32,
+        size.Height.max(0) as u32,
+        |texture| copy_texture_to_surface(surface, texture),
+    )
+    .await
+}